@@ -0,0 +1,69 @@
+//! Requires at least two signed certificate timestamps from known
+//! Certificate Transparency logs, as browsers do.
+use std::collections::HashSet;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rquest::tls::{Impersonate, TlsInfo};
+
+/// An excerpt of the logs of https://www.gstatic.com/ct/log_list/v3/log_list.json,
+/// by name and base64 id. Logs are retired over time, refresh it from there.
+const KNOWN_LOGS: &[(&str, &str)] = &[
+    (
+        "Google 'Argon2025h1'",
+        "TnWjJ1yaEMM4W2zU3z9S6x3w4I4bjWnAsfpksWKaOd8=",
+    ),
+    (
+        "Google 'Argon2025h2'",
+        "EvFONL1TckyEBhnDjz96E/jntWKHiJxtMAWE6+WGJjo=",
+    ),
+    (
+        "Google 'Xenon2025h1'",
+        "zxFW7tUufK/zh1vZaS6b6RpxZ0qwF+ysAdJbd87MOwg=",
+    ),
+    (
+        "Google 'Xenon2025h2'",
+        "3dzKNJXX4RYF55Uy+sef+D0cUN/bADoUEnYKLKy7yCo=",
+    ),
+    (
+        "Cloudflare 'Nimbus2025'",
+        "zPsPaoVxCWX+lZtTzumyfCLphVwNl422qX5UwP5MDbA=",
+    ),
+];
+
+/// The names of the known logs `tls` has a timestamp of.
+fn known_logs(tls: &TlsInfo) -> HashSet<&'static str> {
+    tls.signed_certificate_timestamps()
+        .iter()
+        .filter_map(|sct| {
+            KNOWN_LOGS
+                .iter()
+                .find(|(_, id)| STANDARD.decode(id).ok().as_deref() == Some(&sct.log_id()[..]))
+                .map(|(name, _)| *name)
+        })
+        .collect()
+}
+
+#[tokio::main]
+async fn main() -> Result<(), rquest::Error> {
+    // Chrome asks for the timestamps in the TLS handshake too.
+    let client = rquest::Client::builder()
+        .impersonate(Impersonate::Chrome130)
+        .tls_info(true)
+        .build()?;
+
+    let resp = client.get("https://www.google.com/").send().await?;
+    let logs = resp
+        .extensions()
+        .get::<TlsInfo>()
+        .map(known_logs)
+        .unwrap_or_default();
+    if logs.len() < 2 {
+        // Checked once the response is in, so drop it unread.
+        eprintln!("rejected: timestamps from {} known logs", logs.len());
+        return Ok(());
+    }
+
+    println!("accepted, logged in {:?}", logs);
+    println!("{} bytes", resp.text().await?.len());
+    Ok(())
+}
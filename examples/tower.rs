@@ -0,0 +1,55 @@
+// Wrap a client in tower middleware: a retry layer for server errors and a
+// concurrency limit.
+//
+// This is using the `tokio` runtime and `tower`. You'll need the following
+// dependencies:
+//
+// `tokio = { version = "1", features = ["full"] }`
+// `tower = { version = "0.5", features = ["limit", "retry"] }`
+use std::future;
+
+use rquest::{Client, Request, Response};
+use tower::limit::ConcurrencyLimitLayer;
+use tower::retry::{Policy, RetryLayer};
+
+/// Retries server errors and failed requests a fixed number of times.
+#[derive(Clone)]
+struct Attempts(usize);
+
+impl Policy<Request, Response, rquest::Error> for Attempts {
+    type Future = future::Ready<()>;
+
+    fn retry(
+        &mut self,
+        _req: &mut Request,
+        result: &mut Result<Response, rquest::Error>,
+    ) -> Option<Self::Future> {
+        match result {
+            Ok(res) if !res.status().is_server_error() => None,
+            _ if self.0 > 0 => {
+                self.0 -= 1;
+                Some(future::ready(()))
+            }
+            _ => None,
+        }
+    }
+
+    fn clone_request(&mut self, req: &Request) -> Option<Request> {
+        // Streaming bodies can't be replayed, so those are not retried.
+        req.try_clone()
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), rquest::Error> {
+    // The first layer is the outermost: every retry waits for a permit.
+    let client = Client::builder()
+        .layer(RetryLayer::new(Attempts(2)))
+        .layer(ConcurrencyLimitLayer::new(8))
+        .build()?;
+
+    let res = client.get("https://httpbin.org/status/503").send().await?;
+    println!("Response status {}", res.status());
+
+    Ok(())
+}
@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rquest::tls::{BoringTlsConnector, TlsSettings};
+
+fuzz_target!(|data: &[u8]| {
+    let settings = TlsSettings::builder()
+        .cipher_list(String::from_utf8_lossy(data).into_owned())
+        .build();
+    let _ = BoringTlsConnector::new(settings);
+});
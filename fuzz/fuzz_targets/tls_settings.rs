@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rquest::tls::TlsSettings;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = TlsSettings::from_bytes(data);
+});
@@ -0,0 +1,419 @@
+//! Alternative services advertised by origins.
+//!
+//! An origin can tell with an `Alt-Svc` response header (RFC 7838) that it
+//! is also served at another host and port. The advertisements of each
+//! origin are remembered until they expire, and new connections to the
+//! origin are opened to an alternative instead, as long as connecting to it
+//! works. TLS is still negotiated for the origin's host.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use http::header::ALT_SVC;
+use http::{HeaderMap, Uri};
+use percent_encoding::percent_decode_str;
+use url::Url;
+
+/// How long an alternative is kept when its advertisement has no `ma`.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The longest an alternative is kept, whatever its `ma`.
+const MAX_MAX_AGE: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// How long an alternative that could not be connected to is not used.
+const BROKEN_FOR: Duration = Duration::from_secs(5 * 60);
+
+/// Alternatives kept per origin, the ones advertised past it are ignored.
+const MAX_PER_ORIGIN: usize = 8;
+
+/// Origins past which the expired alternatives are dropped.
+const MAX_ORIGINS: usize = 1024;
+
+/// An alternative service of an origin, see [`AltSvcCache`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AltService {
+    protocol: String,
+    host: String,
+    port: u16,
+    expires: Instant,
+    persist: bool,
+    broken_until: Option<Instant>,
+}
+
+impl AltService {
+    /// An alternative served at `host` and `port` with the ALPN `protocol`,
+    /// such as `h2`, kept for 24 hours.
+    pub fn new(protocol: impl Into<String>, host: impl Into<String>, port: u16) -> AltService {
+        AltService {
+            protocol: protocol.into(),
+            host: host.into(),
+            port,
+            expires: Instant::now() + DEFAULT_MAX_AGE,
+            persist: false,
+            broken_until: None,
+        }
+    }
+
+    /// Sets how long from now the alternative is kept, the `ma` parameter
+    /// of the advertisement.
+    pub fn max_age(mut self, max_age: Duration) -> AltService {
+        self.expires = Instant::now() + max_age.min(MAX_MAX_AGE);
+        self
+    }
+
+    /// Sets the `persist` parameter of the advertisement.
+    pub fn persist(mut self, persist: bool) -> AltService {
+        self.persist = persist;
+        self
+    }
+
+    /// Returns the ALPN protocol of the alternative, `h2` or `h3` for
+    /// instance.
+    pub fn protocol(&self) -> &str {
+        &self.protocol
+    }
+
+    /// Returns the host of the alternative, the origin's when the
+    /// advertisement only gave a port.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Returns the port of the alternative.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Returns when the alternative expires.
+    pub fn expires(&self) -> Instant {
+        self.expires
+    }
+
+    /// Returns whether the advertisement asked for the alternative to be
+    /// kept across network changes. This cache is kept in memory only, and
+    /// is not cleared on network changes either way.
+    pub fn is_persistent(&self) -> bool {
+        self.persist
+    }
+
+    /// Returns whether connecting to the alternative failed lately, in
+    /// which case it is not used for a few minutes.
+    pub fn is_broken(&self) -> bool {
+        self.broken_until
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires <= now
+    }
+
+    fn is_same(&self, other: &AltService) -> bool {
+        self.protocol == other.protocol && self.host == other.host && self.port == other.port
+    }
+}
+
+/// The alternative services advertised by origins, by origin, enabled with
+/// [`ClientBuilder::alt_svc`](crate::ClientBuilder::alt_svc).
+///
+/// New connections to an origin are opened to the first of its
+/// alternatives that speaks a protocol the client allows, `h2` or
+/// `http/1.1`, has not expired and is not broken. When connecting to it
+/// fails, it is marked broken for a few minutes and the origin itself is
+/// connected to instead. Alternatives are only followed for `https`
+/// origins, not through proxies and not for WebSocket connections.
+///
+/// Cloning the cache is cheap, clones share their alternatives, so that
+/// the cache of a client can be looked at and seeded with
+/// [`Client::alt_svc_cache`](crate::Client::alt_svc_cache).
+#[derive(Clone, Default)]
+pub struct AltSvcCache {
+    origins: Arc<Mutex<HashMap<String, Vec<AltService>>>>,
+}
+
+impl AltSvcCache {
+    /// Returns the alternatives of the origin of `url` that have not
+    /// expired, the preferred one first.
+    pub fn get(&self, url: &Url) -> Vec<AltService> {
+        let now = Instant::now();
+        self.lock()
+            .get(&url.origin().ascii_serialization())
+            .map(|alternatives| {
+                alternatives
+                    .iter()
+                    .filter(|alt| !alt.is_expired(now))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Replaces the alternatives of the origin of `url`, as an `Alt-Svc`
+    /// header received from it would. No alternatives clears them.
+    pub fn insert(&self, url: &Url, alternatives: Vec<AltService>) {
+        let origin = url.origin().ascii_serialization();
+        self.replace(origin, Some(alternatives));
+    }
+
+    /// Forgets the alternatives of the origin of `url`.
+    pub fn remove(&self, url: &Url) {
+        self.lock().remove(&url.origin().ascii_serialization());
+    }
+
+    /// Forgets the alternatives of all origins.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Remembers the alternatives advertised by a response from `url`.
+    pub(crate) fn update(&self, url: &Url, headers: &HeaderMap) {
+        if url.scheme() != "https" || !headers.contains_key(ALT_SVC) {
+            return;
+        }
+        let Some(host) = url.host_str() else {
+            return;
+        };
+
+        let value = headers
+            .get_all(ALT_SVC)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(",");
+        let origin = url.origin().ascii_serialization();
+        self.replace(origin, parse(&value, host, Instant::now()));
+    }
+
+    /// The alternative to connect to instead of `dst`, if one speaking one
+    /// of `protocols` is usable.
+    pub(crate) fn alternative(&self, dst: &Uri, protocols: &[&str]) -> Option<AltService> {
+        let origin = crate::conn_info::origin(dst)?;
+        let (host, port) = (dst.host()?, dst.port_u16().unwrap_or(443));
+        let now = Instant::now();
+        self.lock()
+            .get(&origin)?
+            .iter()
+            .find(|alt| {
+                !alt.is_expired(now)
+                    && !alt.is_broken()
+                    && protocols.contains(&alt.protocol.as_str())
+                    && !(alt.host.eq_ignore_ascii_case(host) && alt.port == port)
+            })
+            .cloned()
+    }
+
+    /// Stops using `alt` for `dst` for a while, connecting to it failed.
+    pub(crate) fn mark_broken(&self, dst: &Uri, alt: &AltService) {
+        let Some(origin) = crate::conn_info::origin(dst) else {
+            return;
+        };
+        log::debug!(
+            "alternative service {}:{} of {} is broken",
+            alt.host,
+            alt.port,
+            origin
+        );
+        if let Some(alternatives) = self.lock().get_mut(&origin) {
+            for cached in alternatives.iter_mut().filter(|cached| cached.is_same(alt)) {
+                cached.broken_until = Some(Instant::now() + BROKEN_FOR);
+            }
+        }
+    }
+
+    /// Replaces the alternatives of `origin`, `None` clearing them. An
+    /// alternative advertised again stays broken.
+    fn replace(&self, origin: String, alternatives: Option<Vec<AltService>>) {
+        let mut origins = self.lock();
+        let Some(mut alternatives) = alternatives.filter(|alts| !alts.is_empty()) else {
+            origins.remove(&origin);
+            return;
+        };
+
+        alternatives.truncate(MAX_PER_ORIGIN);
+        if let Some(previous) = origins.get(&origin) {
+            for alt in alternatives.iter_mut() {
+                alt.broken_until = previous
+                    .iter()
+                    .find(|previous| previous.is_same(alt))
+                    .and_then(|previous| previous.broken_until);
+            }
+        }
+        if origins.len() >= MAX_ORIGINS && !origins.contains_key(&origin) {
+            let now = Instant::now();
+            origins.retain(|_, alternatives| {
+                alternatives.retain(|alt| !alt.is_expired(now));
+                !alternatives.is_empty()
+            });
+        }
+        origins.insert(origin, alternatives);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Vec<AltService>>> {
+        self.origins.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl fmt::Debug for AltSvcCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AltSvcCache")
+            .field("origins", &self.lock().len())
+            .finish()
+    }
+}
+
+/// Parses the `Alt-Svc` value of an origin served at `host`, `None` for
+/// `clear`. Alternatives that don't parse are skipped.
+fn parse(value: &str, host: &str, now: Instant) -> Option<Vec<AltService>> {
+    if value.trim() == "clear" {
+        return None;
+    }
+    Some(
+        split_unquoted(value, ',')
+            .filter_map(|alternative| parse_alternative(alternative, host, now))
+            .collect(),
+    )
+}
+
+/// Parses `protocol-id="[host]:port"; param=value...`.
+fn parse_alternative(value: &str, origin_host: &str, now: Instant) -> Option<AltService> {
+    let mut parts = split_unquoted(value, ';');
+    let (protocol, authority) = parts.next()?.split_once('=')?;
+    let protocol = percent_decode_str(protocol.trim()).decode_utf8().ok()?;
+    let authority = unquote(authority.trim());
+    let (host, port) = authority.rsplit_once(':')?;
+
+    let mut max_age = DEFAULT_MAX_AGE;
+    let mut persist = false;
+    for param in parts {
+        let Some((name, value)) = param.split_once('=') else {
+            continue;
+        };
+        let value = unquote(value.trim());
+        match name.trim() {
+            "ma" => {
+                if let Ok(secs) = value.parse() {
+                    max_age = Duration::from_secs(secs).min(MAX_MAX_AGE);
+                }
+            }
+            "persist" => persist = value == "1",
+            _ => {}
+        }
+    }
+
+    Some(AltService {
+        protocol: protocol.into_owned(),
+        host: match host {
+            "" => origin_host.to_owned(),
+            host => host.to_owned(),
+        },
+        port: port.parse().ok()?,
+        expires: now + max_age,
+        persist,
+        broken_until: None,
+    })
+}
+
+/// Splits `value` on `sep`, except within quoted strings, skipping the
+/// empty parts.
+fn split_unquoted(value: &str, sep: char) -> impl Iterator<Item = &str> {
+    let (mut quoted, mut escaped) = (false, false);
+    value
+        .split(move |c: char| {
+            if escaped {
+                escaped = false;
+                return false;
+            }
+            match c {
+                '\\' if quoted => escaped = true,
+                '"' => quoted = !quoted,
+                c => return c == sep && !quoted,
+            }
+            false
+        })
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+}
+
+fn unquote(value: &str) -> String {
+    let Some(quoted) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+        return value.to_owned();
+    };
+    let mut unquoted = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            c => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_alternatives() {
+        let now = Instant::now();
+        let value =
+            r#"h3=":443"; ma=3600, h2="alt.example.com:8443"; persist=1, http%2F1.1="[::1]:80""#;
+        let alternatives = parse(value, "example.com", now).unwrap();
+        assert_eq!(alternatives.len(), 3);
+
+        assert_eq!(alternatives[0].protocol(), "h3");
+        assert_eq!(alternatives[0].host(), "example.com");
+        assert_eq!(alternatives[0].port(), 443);
+        assert_eq!(alternatives[0].expires(), now + Duration::from_secs(3600));
+
+        assert_eq!(alternatives[1].protocol(), "h2");
+        assert_eq!(alternatives[1].host(), "alt.example.com");
+        assert_eq!(alternatives[1].port(), 8443);
+        assert_eq!(alternatives[1].expires(), now + DEFAULT_MAX_AGE);
+        assert!(alternatives[1].is_persistent());
+
+        assert_eq!(alternatives[2].protocol(), "http/1.1");
+        assert_eq!(alternatives[2].host(), "[::1]");
+
+        assert_eq!(parse("clear", "example.com", now), None);
+        assert_eq!(
+            parse(r#"h2="no-port", h2"#, "example.com", now),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn steers_to_usable_alternatives() {
+        let cache = AltSvcCache::default();
+        let url = Url::parse("https://example.com/").unwrap();
+        let dst = Uri::from_static("https://example.com/");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            ALT_SVC,
+            r#"h3=":443", h2=":443", h2="alt.example.com:443""#.parse().unwrap(),
+        );
+        cache.update(&url, &headers);
+        assert_eq!(cache.get(&url).len(), 3);
+
+        // h3 isn't spoken and the first h2 alternative is the origin itself
+        let alt = cache.alternative(&dst, &["h2"]).unwrap();
+        assert_eq!(alt.host(), "alt.example.com");
+        assert!(cache.alternative(&dst, &["http/1.1"]).is_none());
+
+        cache.mark_broken(&dst, &alt);
+        assert!(cache.alternative(&dst, &["h2"]).is_none());
+        // advertised again, it stays broken
+        cache.update(&url, &headers);
+        assert!(cache.get(&url)[2].is_broken());
+
+        headers.insert(ALT_SVC, "clear".parse().unwrap());
+        cache.update(&url, &headers);
+        assert!(cache.get(&url).is_empty());
+
+        let seeded = AltService::new("h2", "seed.example.com", 443).max_age(Duration::ZERO);
+        cache.insert(&url, vec![seeded]);
+        assert!(cache.get(&url).is_empty());
+    }
+}
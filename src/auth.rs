@@ -0,0 +1,101 @@
+//! Authentication
+//!
+//! A [`TokenProvider`] set with
+//! [`ClientBuilder::bearer_auth_provider`](crate::ClientBuilder::bearer_auth_provider)
+//! supplies the bearer tokens of the requests of a client, and refreshes
+//! them when the server rejects them.
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+/// The future of a [`TokenProvider`].
+pub type TokenFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Token, Box<dyn StdError + Send + Sync>>> + Send + 'a>>;
+
+/// A source of bearer tokens.
+///
+/// The client keeps the token it got until it expires, so `token` is only
+/// called when there is no valid token. Requests sent while a token is
+/// fetched wait for it, instead of fetching their own.
+///
+/// # Example
+///
+/// ```rust
+/// use rquest::auth::{Token, TokenFuture, TokenProvider};
+/// use std::time::Duration;
+///
+/// struct Static;
+///
+/// impl TokenProvider for Static {
+///     fn token(&self) -> TokenFuture<'_> {
+///         Box::pin(async { Ok(Token::new("secret").expires_in(Duration::from_secs(3600))) })
+///     }
+/// }
+/// ```
+pub trait TokenProvider: Send + Sync + 'static {
+    /// Get a token.
+    fn token(&self) -> TokenFuture<'_>;
+
+    /// Get a new token, after the server answered `401 Unauthorized` to the
+    /// current one.
+    ///
+    /// The default implementation calls [`token`](TokenProvider::token).
+    fn refresh(&self) -> TokenFuture<'_> {
+        self.token()
+    }
+}
+
+/// A bearer token, and when it expires.
+#[derive(Clone)]
+pub struct Token {
+    value: String,
+    expires_at: Option<Instant>,
+}
+
+impl Token {
+    /// Create a token that does not expire.
+    pub fn new(value: impl Into<String>) -> Token {
+        Token {
+            value: value.into(),
+            expires_at: None,
+        }
+    }
+
+    /// Set the token to expire after `ttl`, as the `expires_in` of an OAuth
+    /// token response.
+    pub fn expires_in(mut self, ttl: Duration) -> Token {
+        self.expires_at = Instant::now().checked_add(ttl);
+        self
+    }
+
+    /// Set the token to expire at `at`.
+    pub fn expires_at(mut self, at: Instant) -> Token {
+        self.expires_at = Some(at);
+        self
+    }
+
+    /// Get the value of the token.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Get when the token expires, if it does.
+    pub fn expiry(&self) -> Option<Instant> {
+        self.expires_at
+    }
+
+    pub(crate) fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Instant::now())
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Token")
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
@@ -0,0 +1,266 @@
+use std::fmt;
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, Read};
+
+use bytes::Bytes;
+
+use crate::client::body as async_body;
+
+/// The body of a `Request`.
+///
+/// In most cases, this is not needed directly, as the
+/// [`RequestBuilder.body`][builder] method uses `Into<Body>`, which allows
+/// passing many things (like a string or vector of bytes).
+///
+/// [builder]: ./struct.RequestBuilder.html#method.body
+#[derive(Debug)]
+pub struct Body {
+    kind: Kind,
+}
+
+impl Body {
+    /// Instantiate a `Body` from a reader.
+    ///
+    /// # Note
+    ///
+    /// While allowing for many types to be used, these bodies do not have
+    /// a way to reset to the beginning and be reused. This means that when
+    /// encountering a 307 or 308 status code, instead of repeating the
+    /// request at the new location, the `Response` will be returned with
+    /// the redirect status code set.
+    ///
+    /// ```rust
+    /// # use std::fs::File;
+    /// # use rquest::blocking::Body;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let file = File::open("national_secrets.txt")?;
+    /// let body = Body::new(file);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// If you have a set of bytes, like `String` or `Vec<u8>`, using the
+    /// `From` implementations for `Body` will store the data in a manner
+    /// it can be reused.
+    pub fn new<R: Read + Send + 'static>(reader: R) -> Body {
+        Body {
+            kind: Kind::Reader(Box::from(reader), None),
+        }
+    }
+
+    /// Create a `Body` from a `Read` where the size is known in advance
+    /// but the data should not be fully loaded into memory. This will
+    /// set the `Content-Length` header and stream from the `Read`.
+    ///
+    /// ```rust
+    /// # use std::fs::File;
+    /// # use rquest::blocking::Body;
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let file = File::open("a_large_file.txt")?;
+    /// let file_size = file.metadata()?.len();
+    /// let body = Body::sized(file, file_size);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn sized<R: Read + Send + 'static>(reader: R, len: u64) -> Body {
+        Body {
+            kind: Kind::Reader(Box::from(reader), Some(len)),
+        }
+    }
+
+    /// Returns the body as a byte slice if the body is already buffered in
+    /// memory. For streamed requests this method returns `None`.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self.kind {
+            Kind::Reader(_, _) => None,
+            Kind::Bytes(ref bytes) => Some(bytes.as_ref()),
+        }
+    }
+
+    /// Converts streamed requests to their buffered equivalent and
+    /// returns a reference to the buffer. If the request is already
+    /// buffered, this has no effect.
+    ///
+    /// Be aware that for large requests this method is expensive
+    /// and may cause your program to run out of memory.
+    pub fn buffer(&mut self) -> Result<&[u8], crate::Error> {
+        if let Kind::Reader(ref mut reader, maybe_len) = self.kind {
+            let mut bytes = Vec::with_capacity(maybe_len.unwrap_or(0) as usize);
+            reader
+                .read_to_end(&mut bytes)
+                .map_err(crate::error::builder)?;
+            self.kind = Kind::Bytes(bytes.into());
+        }
+
+        match self.kind {
+            Kind::Bytes(ref bytes) => Ok(bytes.as_ref()),
+            Kind::Reader(..) => unreachable!("body was just buffered"),
+        }
+    }
+
+    pub(crate) fn try_clone(&self) -> Option<Body> {
+        match self.kind {
+            Kind::Reader(..) => None,
+            Kind::Bytes(ref bytes) => Some(Body::from(bytes.clone())),
+        }
+    }
+
+    /// Convert into the async body, and the sender streaming a reader into it.
+    pub(crate) fn into_async(self) -> (Option<Sender>, async_body::Body, Option<u64>) {
+        match self.kind {
+            Kind::Reader(reader, len) => {
+                let (tx, rx) = hyper::Body::channel();
+                let tx = Sender { reader, len, tx };
+                (Some(tx), async_body::Body::from(rx), len)
+            }
+            Kind::Bytes(bytes) => {
+                let len = bytes.len() as u64;
+                (None, async_body::Body::from(bytes), Some(len))
+            }
+        }
+    }
+}
+
+enum Kind {
+    Reader(Box<dyn Read + Send>, Option<u64>),
+    Bytes(Bytes),
+}
+
+impl From<Vec<u8>> for Body {
+    #[inline]
+    fn from(v: Vec<u8>) -> Body {
+        Body::from(Bytes::from(v))
+    }
+}
+
+impl From<String> for Body {
+    #[inline]
+    fn from(s: String) -> Body {
+        Body::from(Bytes::from(s))
+    }
+}
+
+impl From<&'static [u8]> for Body {
+    #[inline]
+    fn from(s: &'static [u8]) -> Body {
+        Body::from(Bytes::from_static(s))
+    }
+}
+
+impl From<&'static str> for Body {
+    #[inline]
+    fn from(s: &'static str) -> Body {
+        s.as_bytes().into()
+    }
+}
+
+impl From<File> for Body {
+    #[inline]
+    fn from(f: File) -> Body {
+        let len = f.metadata().map(|m| m.len()).ok();
+        Body {
+            kind: Kind::Reader(Box::new(f), len),
+        }
+    }
+}
+
+impl From<Bytes> for Body {
+    #[inline]
+    fn from(b: Bytes) -> Body {
+        Body {
+            kind: Kind::Bytes(b),
+        }
+    }
+}
+
+impl fmt::Debug for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Kind::Reader(_, ref v) => f
+                .debug_struct("Reader")
+                .field("length", &DebugLength(v))
+                .finish(),
+            Kind::Bytes(ref v) => fmt::Debug::fmt(v, f),
+        }
+    }
+}
+
+struct DebugLength<'a>(&'a Option<u64>);
+
+impl fmt::Debug for DebugLength<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self.0 {
+            Some(ref len) => fmt::Debug::fmt(len, f),
+            None => f.write_str("Unknown"),
+        }
+    }
+}
+
+/// Streams a reader into the body of a request in flight.
+///
+/// It runs on the calling thread, where blocking on the reader is fine,
+/// while the request itself is driven by the runtime thread.
+pub(crate) struct Sender {
+    reader: Box<dyn Read + Send>,
+    len: Option<u64>,
+    tx: hyper::body::Sender,
+}
+
+impl Sender {
+    pub(crate) fn send(self) -> impl Future<Output = Result<(), crate::Error>> {
+        send_future(self)
+    }
+}
+
+async fn send_future(sender: Sender) -> Result<(), crate::Error> {
+    let Sender {
+        mut reader,
+        len,
+        mut tx,
+    } = sender;
+
+    let cap = len.unwrap_or(8192).min(8192) as usize;
+    let mut buf = vec![0; cap.max(1)];
+    let mut written = 0;
+
+    loop {
+        if Some(written) == len {
+            // Written up to content-length, so stop.
+            return Ok(());
+        }
+
+        // Read before waiting on the channel: a server may stop reading as
+        // soon as it has the whole body, and there might be nothing left.
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                tx.abort();
+                return Err(crate::error::body(e));
+            }
+        };
+
+        tx.send_data(Bytes::copy_from_slice(&buf[..n]))
+            .await
+            .map_err(crate::error::body)?;
+        written += n as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Body;
+
+    #[test]
+    fn buffer_reader() {
+        let mut body = Body::new(&b"hello world"[..]);
+        assert!(body.as_bytes().is_none());
+        assert!(body.try_clone().is_none());
+
+        assert_eq!(body.buffer().unwrap(), b"hello world");
+        assert_eq!(body.as_bytes(), Some(&b"hello world"[..]));
+        assert!(body.try_clone().is_some());
+    }
+}
@@ -0,0 +1,1049 @@
+use std::convert::TryInto;
+use std::fmt;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::Poll;
+use std::thread;
+use std::time::Duration;
+
+use http::header::HeaderValue;
+use log::{error, trace};
+use serde::Serialize;
+use tokio::sync::{mpsc, oneshot};
+
+use super::request::{Request, RequestBuilder};
+use super::response::Response;
+use super::wait;
+#[cfg(feature = "cookies")]
+use crate::cookie;
+use crate::dns::Resolve;
+use crate::error::TimedOut;
+use crate::header::{HeaderMap, HeaderName};
+use crate::into_url;
+use crate::metrics::MetricsObserver;
+#[cfg(feature = "boring-tls")]
+use crate::tls::{self, Impersonate, ImpersonateSettings};
+use crate::{
+    redirect, retry, AcceptEncoding, IdnaPolicy, IntoUrl, Method, Proxy, TimeoutPhase, Url,
+};
+
+/// A `Client` to make Requests with.
+///
+/// The Client has various configuration values to tweak, but the defaults
+/// are set to what is usually the most commonly desired value. To configure a
+/// `Client`, use `Client::builder()`.
+///
+/// The `Client` holds a connection pool internally, so it is advised that
+/// you create one and **reuse** it.
+///
+/// The requests are driven by a runtime on a dedicated thread, so the client
+/// can be created and used from any thread, including one that is already
+/// running a tokio runtime. Blocking such a thread still stalls the tasks
+/// scheduled on it.
+///
+/// # Examples
+///
+/// ```rust
+/// use rquest::blocking::Client;
+/// #
+/// # fn run() -> Result<(), rquest::Error> {
+/// let client = Client::new();
+/// let resp = client.get("http://httpbin.org/").send()?;
+/// #   drop(resp);
+/// #   Ok(())
+/// # }
+///
+/// ```
+#[derive(Clone)]
+pub struct Client {
+    inner: ClientHandle,
+}
+
+/// A `ClientBuilder` can be used to create a `Client` with  custom configuration.
+///
+/// # Example
+///
+/// ```
+/// # fn run() -> Result<(), rquest::Error> {
+/// use std::time::Duration;
+///
+/// let client = rquest::blocking::Client::builder()
+///     .timeout(Duration::from_secs(10))
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[must_use]
+pub struct ClientBuilder {
+    inner: crate::ClientBuilder,
+    timeout: Timeout,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClientBuilder {
+    /// Constructs a new `ClientBuilder`.
+    ///
+    /// This is the same as `Client::builder()`.
+    pub fn new() -> ClientBuilder {
+        ClientBuilder {
+            inner: crate::ClientBuilder::new(),
+            timeout: Timeout::default(),
+        }
+    }
+
+    /// Checks the configuration for settings that conflict or have no
+    /// effect.
+    ///
+    /// See [`crate::ClientBuilder::validate`].
+    pub fn validate(&self) -> Result<Vec<crate::ValidationWarning>, Vec<crate::ValidationError>> {
+        self.inner.validate()
+    }
+
+    /// Returns a `Client` that uses this `ClientBuilder` configuration.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if TLS backend cannot be initialized, or the resolver
+    /// cannot load the system configuration.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the runtime thread panics while starting up.
+    pub fn build(self) -> crate::Result<Client> {
+        ClientHandle::new(self).map(|handle| Client { inner: handle })
+    }
+
+    // Higher-level options
+
+    /// Sets the necessary values to mimic the specified impersonate version.
+    /// This will set the necessary headers and TLS settings.
+    #[cfg(feature = "boring-tls")]
+    pub fn impersonate(self, impersonate: Impersonate) -> ClientBuilder {
+        self.with_inner(move |inner| inner.impersonate(impersonate))
+    }
+
+    /// Sets the necessary values to mimic the specified impersonate version (without headers).
+    /// This will set the necessary headers and TLS settings.
+    #[cfg(feature = "boring-tls")]
+    pub fn impersonate_without_headers(self, impersonate: Impersonate) -> ClientBuilder {
+        self.with_inner(move |inner| inner.impersonate_without_headers(impersonate))
+    }
+
+    /// Use the preconfigured TLS settings.
+    #[cfg(feature = "boring-tls")]
+    pub fn use_preconfigured_tls(self, settings: ImpersonateSettings) -> ClientBuilder {
+        self.with_inner(move |inner| inner.use_preconfigured_tls(settings))
+    }
+
+    /// Use these TLS settings, in place of the current ones.
+    #[cfg(feature = "boring-tls")]
+    pub fn tls_settings(self, settings: crate::tls::TlsSettings) -> ClientBuilder {
+        self.with_inner(move |inner| inner.tls_settings(settings))
+    }
+
+    /// Use these HTTP/2 settings.
+    #[cfg(feature = "boring-tls")]
+    pub fn http2_settings(self, settings: crate::tls::Http2Settings) -> ClientBuilder {
+        self.with_inner(move |inner| inner.http2_settings(settings))
+    }
+
+    /// Sets the `User-Agent` header to be used by this client.
+    pub fn user_agent<V>(self, value: V) -> ClientBuilder
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: Into<http::Error>,
+    {
+        self.with_inner(move |inner| inner.user_agent(value))
+    }
+
+    /// Sets the default headers for every request.
+    pub fn default_headers(self, headers: HeaderMap) -> ClientBuilder {
+        self.with_inner(move |inner| inner.default_headers(headers))
+    }
+
+    /// Sets a base URL that relative URLs given to the request methods are
+    /// resolved against.
+    pub fn base_url(self, url: Url) -> ClientBuilder {
+        self.with_inner(move |inner| inner.base_url(url))
+    }
+
+    /// Sets how non-ASCII host names are converted to ASCII.
+    pub fn idna(self, policy: IdnaPolicy) -> ClientBuilder {
+        self.with_inner(move |inner| inner.idna(policy))
+    }
+
+    /// Adds query parameters to the URL of every request, unless it already
+    /// has a parameter with the same key.
+    pub fn default_query<T: Serialize + ?Sized>(self, query: &T) -> ClientBuilder {
+        self.with_inner(move |inner| inner.default_query(query))
+    }
+
+    /// Change the order in which headers will be sent.
+    pub fn headers_order(self, order: &'static [HeaderName]) -> ClientBuilder {
+        self.with_inner(move |inner| inner.headers_order(order))
+    }
+
+    /// Enable a persistent cookie store for the client.
+    ///
+    /// Cookies received in responses will be preserved and included in
+    /// additional requests.
+    ///
+    /// By default, no cookie store is used.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `cookies` feature to be enabled.
+    #[cfg(feature = "cookies")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cookies")))]
+    pub fn cookie_store(self, enable: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.cookie_store(enable))
+    }
+
+    /// Set the persistent cookie store for the client.
+    ///
+    /// Cookies received in responses will be passed to this store, and
+    /// additional requests will query this store for cookies.
+    ///
+    /// By default, no cookie store is used.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `cookies` feature to be enabled.
+    #[cfg(feature = "cookies")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cookies")))]
+    pub fn cookie_provider<C: cookie::CookieStore + 'static>(
+        self,
+        cookie_store: Arc<C>,
+    ) -> ClientBuilder {
+        self.with_inner(|inner| inner.cookie_provider(cookie_store))
+    }
+
+    /// Enable auto gzip decompression by checking the `Content-Encoding` response header.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `gzip` feature to be enabled
+    #[cfg(feature = "gzip")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "gzip")))]
+    pub fn gzip(self, enable: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.gzip(enable))
+    }
+
+    /// Enable auto brotli decompression by checking the `Content-Encoding` response header.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `brotli` feature to be enabled
+    #[cfg(feature = "brotli")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "brotli")))]
+    pub fn brotli(self, enable: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.brotli(enable))
+    }
+
+    /// Sets the size of the chunks brotli response bodies are decoded into.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `brotli` feature to be enabled
+    #[cfg(feature = "brotli")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "brotli")))]
+    pub fn brotli_buffer_size(self, size: usize) -> ClientBuilder {
+        self.with_inner(move |inner| inner.brotli_buffer_size(size))
+    }
+
+    /// Whether brotli response bodies may use the large-window extension.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `brotli` feature to be enabled
+    #[cfg(feature = "brotli")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "brotli")))]
+    pub fn brotli_large_window(self, enable: bool) -> ClientBuilder {
+        self.with_inner(move |inner| inner.brotli_large_window(enable))
+    }
+
+    /// Enable auto zstd decompression by checking the `Content-Encoding` response header.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `zstd` feature to be enabled
+    #[cfg(feature = "zstd")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "zstd")))]
+    pub fn zstd(self, enable: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.zstd(enable))
+    }
+
+    /// Enable auto deflate decompression by checking the `Content-Encoding` response header.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `deflate` feature to be enabled
+    #[cfg(feature = "deflate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "deflate")))]
+    pub fn deflate(self, enable: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.deflate(enable))
+    }
+
+    /// Set the content codings to accept and decompress, advertised by order
+    /// of preference in the `Accept-Encoding` header.
+    pub fn accept_encoding(self, encoding: AcceptEncoding) -> ClientBuilder {
+        self.with_inner(move |inner| inner.accept_encoding(encoding))
+    }
+
+    // Redirect options
+
+    /// Set a `redirect::Policy` for this client.
+    ///
+    /// Default will follow redirects up to a maximum of 10.
+    pub fn redirect(self, policy: redirect::Policy) -> ClientBuilder {
+        self.with_inner(move |inner| inner.redirect(policy))
+    }
+
+    /// Set a `retry::Policy` for this client.
+    ///
+    /// The client's [`timeout`](ClientBuilder::timeout) covers every
+    /// attempt, including the waits between them.
+    pub fn retry(self, policy: retry::Policy) -> ClientBuilder {
+        self.with_inner(move |inner| inner.retry(policy))
+    }
+
+    /// Retry `429` and `503` responses once, after the delay of their
+    /// `Retry-After` header, unless it is longer than `max_wait`.
+    ///
+    /// See [`crate::ClientBuilder::respect_retry_after`].
+    pub fn respect_retry_after<D>(self, max_wait: D) -> ClientBuilder
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.with_inner(move |inner| inner.respect_retry_after(max_wait))
+    }
+
+    /// Limit the retries of the [`retry`](ClientBuilder::retry) policy to a
+    /// share of the requests in flight.
+    ///
+    /// See [`crate::ClientBuilder::retry_budget`].
+    pub fn retry_budget(self, total_ratio: f64, min_retries: u32) -> ClientBuilder {
+        self.with_inner(move |inner| inner.retry_budget(total_ratio, min_retries))
+    }
+
+    /// Send a backup of each request still without a response after
+    /// `threshold`.
+    ///
+    /// See [`crate::ClientBuilder::hedge_requests`].
+    pub fn hedge_requests(self, threshold: Duration) -> ClientBuilder {
+        self.with_inner(move |inner| inner.hedge_requests(threshold))
+    }
+
+    /// Set a `MetricsObserver` told about the timing of every request.
+    pub fn metrics_observer(self, observer: Arc<dyn MetricsObserver>) -> ClientBuilder {
+        self.with_inner(move |inner| inner.metrics_observer(observer))
+    }
+
+    /// Send every request with an id, to correlate it with the logs of the
+    /// server.
+    ///
+    /// See [`crate::ClientBuilder::request_id`].
+    pub fn request_id(self, policy: crate::RequestIdPolicy) -> ClientBuilder {
+        self.with_inner(move |inner| inner.request_id(policy))
+    }
+
+    /// Calls `callback` as a response body is read, with the bytes read so
+    /// far and the total length of the body, if known.
+    pub fn on_download_progress<F>(self, callback: F) -> ClientBuilder
+    where
+        F: Fn(u64, Option<u64>) + Send + Sync + 'static,
+    {
+        self.with_inner(move |inner| inner.on_download_progress(callback))
+    }
+
+    /// Limits the size of response bodies, in bytes after decompression.
+    ///
+    /// There is no limit by default.
+    pub fn max_response_body_size(self, max: u64) -> ClientBuilder {
+        self.with_inner(move |inner| inner.max_response_body_size(max))
+    }
+
+    /// Fail reading response bodies that end before their `Content-Length`,
+    /// or before their last chunk.
+    ///
+    /// Default is `false`.
+    pub fn strict_content_length(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(move |inner| inner.strict_content_length(enabled))
+    }
+
+    /// Limit the rate requests are sent at.
+    ///
+    /// Default is no limit.
+    pub fn rate_limit(self, limit: crate::RateLimit) -> ClientBuilder {
+        self.with_inner(move |inner| inner.rate_limit(limit))
+    }
+
+    /// Limit the rate requests are sent at to the hosts matching `pattern`.
+    pub fn rate_limit_for_host(self, pattern: &str, limit: crate::RateLimit) -> ClientBuilder {
+        self.with_inner(move |inner| inner.rate_limit_for_host(pattern, limit))
+    }
+
+    /// Send bodies of at least `threshold` bytes with `Expect: 100-continue`.
+    ///
+    /// See [`crate::ClientBuilder::expect_continue_threshold`].
+    pub fn expect_continue_threshold(self, threshold: u64) -> ClientBuilder {
+        self.with_inner(move |inner| inner.expect_continue_threshold(threshold))
+    }
+
+    /// Sets how long a body sent with `Expect: 100-continue` is held back
+    /// for a final response.
+    ///
+    /// Default is 1 second.
+    pub fn expect_continue_timeout(self, timeout: Duration) -> ClientBuilder {
+        self.with_inner(move |inner| inner.expect_continue_timeout(timeout))
+    }
+
+    /// Collapse concurrent identical `GET` and `HEAD` requests into one.
+    ///
+    /// Default is `false`.
+    pub fn deduplicate_requests(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.deduplicate_requests(enabled))
+    }
+
+    /// Authenticate requests with bearer tokens from `provider`.
+    ///
+    /// See [`crate::ClientBuilder::bearer_auth_provider`].
+    pub fn bearer_auth_provider(
+        self,
+        provider: std::sync::Arc<dyn crate::auth::TokenProvider>,
+    ) -> ClientBuilder {
+        self.with_inner(move |inner| inner.bearer_auth_provider(provider))
+    }
+
+    /// Cache the responses to `GET` and `HEAD` requests, as configured by
+    /// `config`.
+    ///
+    /// See [`crate::ClientBuilder::http_cache`].
+    #[cfg(feature = "cache")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cache")))]
+    pub fn http_cache(self, config: crate::cache::CacheConfig) -> ClientBuilder {
+        self.with_inner(move |inner| inner.http_cache(config))
+    }
+
+    /// Enable or disable automatic setting of the `Referer` header.
+    ///
+    /// Default is `true`.
+    pub fn referer(self, enable: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.referer(enable))
+    }
+
+    // Proxy options
+
+    /// Add a `Proxy` to the list of proxies the `Client` will use.
+    ///
+    /// # Note
+    ///
+    /// Adding a proxy will disable the automatic usage of the "system" proxy.
+    pub fn proxy(self, proxy: Proxy) -> ClientBuilder {
+        self.with_inner(move |inner| inner.proxy(proxy))
+    }
+
+    /// Whether connections tunneled through an HTTP proxy with `CONNECT` are
+    /// kept in the pool for later requests.
+    ///
+    /// Default is `true`.
+    pub fn reuse_proxy_tunnels(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(move |inner| inner.reuse_proxy_tunnels(enabled))
+    }
+
+    /// Picks the proxy of each new connection with `selector`.
+    ///
+    /// See [`crate::ClientBuilder::proxy_selector`].
+    pub fn proxy_selector<F>(self, selector: F) -> ClientBuilder
+    where
+        F: Fn(&Url) -> Option<Proxy> + Send + Sync + 'static,
+    {
+        self.with_inner(move |inner| inner.proxy_selector(selector))
+    }
+
+    /// Clear all `Proxies`, so `Client` will use no proxy anymore.
+    ///
+    /// This also disables the automatic usage of the "system" proxy.
+    pub fn no_proxy(self) -> ClientBuilder {
+        self.with_inner(move |inner| inner.no_proxy())
+    }
+
+    // Timeout options
+
+    /// Set a timeout for connect, read and write operations of a `Client`.
+    ///
+    /// Default is 30 seconds.
+    ///
+    /// Pass `None` to disable timeout.
+    pub fn timeout<T>(mut self, timeout: T) -> ClientBuilder
+    where
+        T: Into<Option<Duration>>,
+    {
+        self.timeout = Timeout(timeout.into());
+        self
+    }
+
+    /// Set a timeout for only the connect phase of a `Client`.
+    ///
+    /// Default is `None`.
+    pub fn connect_timeout<T>(self, timeout: T) -> ClientBuilder
+    where
+        T: Into<Option<Duration>>,
+    {
+        match timeout.into() {
+            Some(timeout) => self.with_inner(|inner| inner.connect_timeout(timeout)),
+            None => self,
+        }
+    }
+
+    /// Set whether connections should emit verbose logs.
+    pub fn connection_verbose(self, verbose: bool) -> ClientBuilder {
+        self.with_inner(move |inner| inner.connection_verbose(verbose))
+    }
+
+    // HTTP options
+
+    /// Set an optional timeout for idle sockets being kept-alive.
+    ///
+    /// Pass `None` to disable timeout.
+    ///
+    /// Default is 90 seconds.
+    pub fn pool_idle_timeout<D>(self, val: D) -> ClientBuilder
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.with_inner(|inner| inner.pool_idle_timeout(val))
+    }
+
+    /// Sets the maximum number of connections open to each host, idle or
+    /// not.
+    ///
+    /// See [`crate::ClientBuilder::max_connections_per_host`].
+    pub fn max_connections_per_host(self, max: usize) -> ClientBuilder {
+        self.with_inner(move |inner| inner.max_connections_per_host(max))
+    }
+
+    /// Sets the maximum number of connections open to all hosts, idle or
+    /// not.
+    pub fn max_total_connections(self, max: usize) -> ClientBuilder {
+        self.with_inner(move |inner| inner.max_total_connections(max))
+    }
+
+    /// Sets how long a request waits for a connection under the connection
+    /// limits, before failing.
+    pub fn pool_checkout_timeout<D>(self, timeout: D) -> ClientBuilder
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.with_inner(move |inner| inner.pool_checkout_timeout(timeout))
+    }
+
+    /// Sets the maximum idle connection per host allowed in the pool.
+    pub fn pool_max_idle_per_host(self, max: usize) -> ClientBuilder {
+        self.with_inner(move |inner| inner.pool_max_idle_per_host(max))
+    }
+
+    /// Sets the maximum number of headers a response may have. HTTP/1
+    /// responses can't have more than 100.
+    ///
+    /// Default is 100.
+    pub fn max_response_headers(self, max: usize) -> ClientBuilder {
+        self.with_inner(move |inner| inner.max_response_headers(max))
+    }
+
+    /// Sets the maximum total size of the headers of a response.
+    ///
+    /// Default is 256 KiB.
+    pub fn max_response_header_size(self, max: usize) -> ClientBuilder {
+        self.with_inner(move |inner| inner.max_response_header_size(max))
+    }
+
+    /// Only use HTTP/1.
+    pub fn http1_only(self) -> ClientBuilder {
+        self.with_inner(|inner| inner.http1_only())
+    }
+
+    /// Only use HTTP/2.
+    pub fn http2_only(self) -> ClientBuilder {
+        self.with_inner(|inner| inner.http2_only())
+    }
+
+    /// Sets how long a new HTTP/2 connection waits for the server to
+    /// acknowledge its initial `SETTINGS` frame.
+    ///
+    /// See [`crate::ClientBuilder::http2_settings_ack_timeout`].
+    pub fn http2_settings_ack_timeout<D>(self, timeout: D) -> ClientBuilder
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.with_inner(move |inner| inner.http2_settings_ack_timeout(timeout))
+    }
+
+    // TCP options
+
+    /// Set whether sockets have `TCP_NODELAY` enabled.
+    pub fn tcp_nodelay(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(move |inner| inner.tcp_nodelay(enabled))
+    }
+
+    /// Bind to a local IP Address.
+    pub fn local_address<T>(self, addr: T) -> ClientBuilder
+    where
+        T: Into<Option<IpAddr>>,
+    {
+        self.with_inner(move |inner| inner.local_address(addr))
+    }
+
+    /// Bind to an interface by `SO_BINDTODEVICE`.
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    pub fn interface(self, interface: &str) -> ClientBuilder {
+        self.with_inner(move |inner| inner.interface(interface))
+    }
+
+    /// Set that all sockets have `SO_KEEPALIVE` set with the supplied duration.
+    ///
+    /// If `None`, the option will not be set.
+    pub fn tcp_keepalive<D>(self, val: D) -> ClientBuilder
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.with_inner(move |inner| inner.tcp_keepalive(val))
+    }
+
+    // TLS options
+
+    /// Controls the use of certificate validation.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// # Warning
+    ///
+    /// You should think very carefully before using this method. If
+    /// invalid certificates are trusted, *any* certificate for *any* site
+    /// will be trusted for use.
+    #[cfg(feature = "boring-tls")]
+    pub fn danger_accept_invalid_certs(self, accept_invalid_certs: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.danger_accept_invalid_certs(accept_invalid_certs))
+    }
+
+    /// Set the minimum required TLS version for connections.
+    #[cfg(feature = "boring-tls")]
+    pub fn min_tls_version(self, version: tls::Version) -> ClientBuilder {
+        self.with_inner(|inner| inner.min_tls_version(version))
+    }
+
+    /// Set the maximum allowed TLS version for connections.
+    #[cfg(feature = "boring-tls")]
+    pub fn max_tls_version(self, version: tls::Version) -> ClientBuilder {
+        self.with_inner(|inner| inner.max_tls_version(version))
+    }
+
+    /// Add TLS information as `TlsInfo` extension to responses.
+    #[cfg(feature = "boring-tls")]
+    pub fn tls_info(self, tls_info: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.tls_info(tls_info))
+    }
+
+    /// Add the protocols negotiated through ALPN as `ProtocolInfo`
+    /// extension to responses received over TLS.
+    #[cfg(feature = "boring-tls")]
+    pub fn protocol_info(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.protocol_info(enabled))
+    }
+
+    /// Restrict the Client to be used with HTTPS only requests.
+    ///
+    /// Defaults to false.
+    pub fn https_only(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.https_only(enabled))
+    }
+
+    // DNS options
+
+    /// Override DNS resolution for specific domains to a particular IP address.
+    ///
+    /// Warning
+    ///
+    /// Since the DNS protocol has no notion of ports, if you wish to send
+    /// traffic to a particular port you must include this port in the URL
+    /// itself, any port in the overridden addr will be ignored and traffic sent
+    /// to the conventional port for the given scheme (e.g. 80 for http).
+    pub fn resolve(self, domain: &str, addr: SocketAddr) -> ClientBuilder {
+        self.resolve_to_addrs(domain, &[addr])
+    }
+
+    /// Override DNS resolution for specific domains to particular IP addresses.
+    pub fn resolve_to_addrs(self, domain: &str, addrs: &[SocketAddr]) -> ClientBuilder {
+        self.with_inner(|inner| inner.resolve_to_addrs(domain, addrs))
+    }
+
+    /// Override the DNS resolver implementation.
+    pub fn dns_resolver<R: Resolve + 'static>(self, resolver: Arc<R>) -> ClientBuilder {
+        self.with_inner(|inner| inner.dns_resolver(resolver))
+    }
+
+    // private
+
+    fn with_inner<F>(mut self, func: F) -> ClientBuilder
+    where
+        F: FnOnce(crate::ClientBuilder) -> crate::ClientBuilder,
+    {
+        self.inner = func(self.inner);
+        self
+    }
+}
+
+/// Uses an async `ClientBuilder` for the options the blocking builder
+/// doesn't mirror.
+///
+/// The blocking timeout still defaults to 30 seconds.
+impl From<crate::ClientBuilder> for ClientBuilder {
+    fn from(builder: crate::ClientBuilder) -> Self {
+        Self {
+            inner: builder,
+            timeout: Timeout::default(),
+        }
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Client {
+    /// Constructs a new `Client`.
+    ///
+    /// # Panic
+    ///
+    /// This method panics if TLS backend cannot be initialized, or the resolver
+    /// cannot load the system configuration.
+    ///
+    /// Use `Client::builder()` if you wish to handle the failure as an `Error`
+    /// instead of panicking.
+    pub fn new() -> Client {
+        ClientBuilder::new().build().expect("Client::new()")
+    }
+
+    /// Creates a `ClientBuilder` to configure a `Client`.
+    ///
+    /// This is the same as `ClientBuilder::new()`.
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    /// Convenience method to make a `GET` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    /// Convenience method to make a `POST` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    /// Convenience method to make a `PUT` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn put<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    /// Convenience method to make a `PATCH` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn patch<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::PATCH, url)
+    }
+
+    /// Convenience method to make a `DELETE` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn delete<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+
+    /// Convenience method to make a `HEAD` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn head<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::HEAD, url)
+    }
+
+    /// Start building a `Request` with the `Method` and `Url`.
+    ///
+    /// Returns a `RequestBuilder`, which will allow setting headers and
+    /// request body before sending.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn request<U: IntoUrl>(&self, method: Method, url: U) -> RequestBuilder {
+        let req = into_url::resolve(self.inner.base_url.as_ref(), url, self.inner.idna)
+            .map(move |url| Request::new(method, url));
+        RequestBuilder::new(self.clone(), req)
+    }
+
+    /// Executes a `Request`.
+    ///
+    /// A `Request` can be built manually with `Request::new()` or obtained
+    /// from a RequestBuilder with `RequestBuilder::build()`.
+    ///
+    /// You should prefer to use the `RequestBuilder` and
+    /// `RequestBuilder::send()`.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if there was an error while sending request,
+    /// or redirect limit was exhausted.
+    pub fn execute(&self, request: Request) -> crate::Result<Response> {
+        self.inner.execute_request(request)
+    }
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("timeout", &self.inner.timeout.0)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for ClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+#[derive(Clone)]
+struct ClientHandle {
+    timeout: Timeout,
+    base_url: Option<Url>,
+    idna: IdnaPolicy,
+    inner: Arc<InnerClientHandle>,
+}
+
+type OneshotResponse = oneshot::Sender<crate::Result<crate::Response>>;
+type ThreadSender = mpsc::UnboundedSender<(crate::Request, OneshotResponse)>;
+
+struct InnerClientHandle {
+    tx: Option<ThreadSender>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for InnerClientHandle {
+    fn drop(&mut self) {
+        let id = self
+            .thread
+            .as_ref()
+            .map(|h| h.thread().id())
+            .expect("thread not dropped yet");
+
+        trace!("closing runtime thread ({:?})", id);
+        self.tx.take();
+        trace!("signaled close for runtime thread ({:?})", id);
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+        trace!("closed runtime thread ({:?})", id);
+    }
+}
+
+impl ClientHandle {
+    fn new(builder: ClientBuilder) -> crate::Result<ClientHandle> {
+        let timeout = builder.timeout;
+        let base_url = builder.inner.base_url_ref().cloned();
+        let idna = builder.inner.idna_policy();
+        let builder = builder.inner;
+        let (tx, rx) = mpsc::unbounded_channel::<(crate::Request, OneshotResponse)>();
+        let (spawn_tx, spawn_rx) = oneshot::channel::<crate::Result<()>>();
+
+        // The runtime gets a thread of its own, so that it is never nested
+        // in a runtime the caller may be running.
+        let handle = thread::Builder::new()
+            .name("rquest-internal-sync-runtime".into())
+            .spawn(move || {
+                let rt = match tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .map_err(crate::error::builder)
+                {
+                    Err(e) => {
+                        if let Err(e) = spawn_tx.send(Err(e)) {
+                            error!("Failed to communicate runtime creation failure: {:?}", e);
+                        }
+                        return;
+                    }
+                    Ok(v) => v,
+                };
+
+                let f = async move {
+                    let client = match builder.build() {
+                        Err(e) => {
+                            if let Err(e) = spawn_tx.send(Err(e)) {
+                                error!("Failed to communicate client creation failure: {:?}", e);
+                            }
+                            return;
+                        }
+                        Ok(v) => v,
+                    };
+                    if let Err(e) = spawn_tx.send(Ok(())) {
+                        error!("Failed to communicate successful startup: {:?}", e);
+                        return;
+                    }
+
+                    let mut rx = rx;
+
+                    while let Some((req, req_tx)) = rx.recv().await {
+                        let req_fut = client.execute(req);
+                        tokio::spawn(forward(req_fut, req_tx));
+                    }
+
+                    trace!("({:?}) Receiver is shutdown", thread::current().id());
+                };
+
+                trace!("({:?}) start runtime::block_on", thread::current().id());
+                rt.block_on(f);
+                trace!("({:?}) end runtime::block_on", thread::current().id());
+                drop(rt);
+                trace!("({:?}) finished", thread::current().id());
+            })
+            .map_err(crate::error::builder)?;
+
+        // Wait for the runtime thread to start up...
+        match wait::timeout(spawn_rx, None) {
+            Ok(Ok(())) => (),
+            Ok(Err(err)) => return Err(err),
+            Err(_canceled) => event_loop_panicked(),
+        }
+
+        let inner_handle = Arc::new(InnerClientHandle {
+            tx: Some(tx),
+            thread: Some(handle),
+        });
+
+        Ok(ClientHandle {
+            timeout,
+            base_url,
+            idna,
+            inner: inner_handle,
+        })
+    }
+
+    fn execute_request(&self, req: Request) -> crate::Result<Response> {
+        let (tx, rx) = oneshot::channel();
+        let (req, body) = req.into_async();
+        let url = req.url().clone();
+        let timeout = req.timeout().copied().or(self.timeout.0);
+
+        self.inner
+            .tx
+            .as_ref()
+            .expect("core thread exited early")
+            .send((req, tx))
+            .expect("core thread panicked");
+
+        let result: Result<crate::Result<crate::Response>, wait::Waited<crate::Error>> =
+            if let Some(body) = body {
+                let f = async move {
+                    body.send().await?;
+                    rx.await.map_err(|_canceled| event_loop_panicked())
+                };
+                wait::timeout(f, timeout)
+            } else {
+                let f = async move { rx.await.map_err(|_canceled| event_loop_panicked()) };
+                wait::timeout(f, timeout)
+            };
+
+        match result {
+            Ok(Err(err)) => Err(err.with_url(url)),
+            Ok(Ok(res)) => Ok(Response::new(
+                res,
+                timeout,
+                KeepCoreThreadAlive(Some(self.inner.clone())),
+            )),
+            Err(wait::Waited::TimedOut(elapsed)) => {
+                let timed_out = TimedOut::new(TimeoutPhase::Total, elapsed);
+                Err(crate::error::request(timed_out).with_url(url))
+            }
+            Err(wait::Waited::Inner(err)) => Err(err.with_url(url)),
+        }
+    }
+}
+
+async fn forward<F>(fut: F, mut tx: OneshotResponse)
+where
+    F: Future<Output = crate::Result<crate::Response>>,
+{
+    let mut fut = pin!(fut);
+
+    // "select" on the sender being canceled, and the future completing
+    let res = std::future::poll_fn(|cx| match fut.as_mut().poll(cx) {
+        Poll::Ready(val) => Poll::Ready(Some(val)),
+        Poll::Pending => {
+            // check if the callback is canceled
+            futures_core::ready!(tx.poll_closed(cx));
+            Poll::Ready(None)
+        }
+    })
+    .await;
+
+    if let Some(res) = res {
+        let _ = tx.send(res);
+    }
+    // else request is canceled
+}
+
+#[derive(Clone, Copy)]
+struct Timeout(Option<Duration>);
+
+impl Default for Timeout {
+    fn default() -> Timeout {
+        // default mentioned in ClientBuilder::timeout() doc comment
+        Timeout(Some(Duration::from_secs(30)))
+    }
+}
+
+/// Keeps the runtime thread running while a `Response` body is read.
+pub(crate) struct KeepCoreThreadAlive(Option<Arc<InnerClientHandle>>);
+
+impl KeepCoreThreadAlive {
+    pub(crate) fn empty() -> KeepCoreThreadAlive {
+        KeepCoreThreadAlive(None)
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn event_loop_panicked() -> ! {
+    // The only possible reason there would be a Canceled error
+    // is if the thread running the event loop panicked. We could return
+    // an Err here, like a BrokenPipe, but the Client is not
+    // recoverable. Additionally, the panic in the other thread
+    // is not normal, and should likely be propagated.
+    panic!("event loop thread panicked");
+}
@@ -0,0 +1,119 @@
+//! A blocking Client API.
+//!
+//! The blocking `Client` will block the current thread to execute, instead
+//! of returning futures that need to be executed on a runtime.
+//!
+//! Conversely, the functionality in `rquest::blocking` needs no runtime from
+//! the caller: requests are driven by a runtime on an internal thread, so a
+//! blocking `Client` can also be created and dropped from within an async
+//! runtime without panicking. Keep in mind that blocking a runtime worker
+//! still stalls the other tasks scheduled on it.
+//!
+//! # Optional
+//!
+//! This requires the optional `blocking` feature to be enabled.
+//!
+//! # Making a GET request
+//!
+//! For a single request, you can use the [`get`] shortcut method.
+//!
+//! ```rust,no_run
+//! # use rquest::{Error, Response};
+//!
+//! # fn run() -> Result<(), Error> {
+//! let body = rquest::blocking::get("https://www.rust-lang.org")?
+//!     .text()?;
+//!
+//! println!("body = {:?}", body);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Additionally, the blocking [`Response`] struct implements Rust's
+//! `Read` trait, so many useful standard library and third party crates will
+//! have convenience methods that take a `Response` anywhere `T: Read` is
+//! acceptable.
+//!
+//! **NOTE**: If you plan to perform multiple requests, it is best to create a
+//! [`Client`] and reuse it, taking advantage of keep-alive connection pooling.
+//!
+//! # Impersonation
+//!
+//! The blocking [`ClientBuilder`] mirrors the async one, including
+//! impersonation, cookies, proxies and timeouts.
+//!
+//! ```rust,no_run
+//! use rquest::tls::Impersonate;
+//!
+//! # fn run() -> Result<(), rquest::Error> {
+//! let client = rquest::blocking::Client::builder()
+//!     .impersonate(Impersonate::Chrome130)
+//!     .build()?;
+//!
+//! let resp = client.get("https://tls.peet.ws/api/all").send()?;
+//! println!("{}", resp.text()?);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Making POST requests (or setting request bodies)
+//!
+//! There are several ways you can set the body of a request. The basic one is
+//! by using the `body()` method of a [`RequestBuilder`]. This lets you set the
+//! exact raw bytes of what the body should be. It accepts various types,
+//! including `String`, `Vec<u8>`, and `File`. If you wish to pass a custom
+//! Reader, you can use the `rquest::blocking::Body::new()` constructor.
+//!
+//! ```rust,no_run
+//! # use rquest::Error;
+//! #
+//! # fn run() -> Result<(), Error> {
+//! let client = rquest::blocking::Client::new();
+//! let res = client.post("http://httpbin.org/post")
+//!     .body("the exact body that is sent")
+//!     .send()?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod body;
+mod client;
+mod request;
+mod response;
+mod wait;
+
+pub use self::body::Body;
+pub use self::client::{Client, ClientBuilder};
+pub use self::request::{Request, RequestBuilder};
+pub use self::response::Response;
+
+/// Shortcut method to quickly make a *blocking* `GET` request.
+///
+/// **NOTE**: This function creates a new internal `Client` on each call,
+/// and so should not be used if making many requests. Create a
+/// [`Client`](./struct.Client.html) instead.
+///
+/// # Examples
+///
+/// ```rust
+/// # fn run() -> Result<(), rquest::Error> {
+/// let body = rquest::blocking::get("https://www.rust-lang.org")?
+///     .text()?;
+/// # Ok(())
+/// # }
+/// # fn main() { }
+/// ```
+///
+/// # Errors
+///
+/// This function fails if:
+///
+/// - the native TLS backend cannot be initialized,
+/// - the supplied `Url` cannot be parsed,
+/// - there was an error while sending request,
+/// - a redirect loop was detected,
+/// - the redirect limit was exhausted, or
+/// - the total download time exceeds the timeout.
+pub fn get<T: crate::IntoUrl>(url: T) -> crate::Result<Response> {
+    Client::builder().build()?.get(url).send()
+}
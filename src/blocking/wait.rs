@@ -0,0 +1,72 @@
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+/// Drive `fut` on the current thread, parking it between polls.
+///
+/// The futures driven here only wait on channels to the runtime thread, so
+/// this never needs a runtime of its own, and can be called from a thread
+/// that is already inside one.
+pub(crate) fn timeout<F, I, E>(fut: F, timeout: Option<Duration>) -> Result<I, Waited<E>>
+where
+    F: Future<Output = Result<I, E>>,
+{
+    let started = Instant::now();
+    let deadline = timeout.map(|d| {
+        log::trace!("wait at most {:?}", d);
+        Instant::now() + d
+    });
+
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    let mut fut = pin!(fut);
+
+    loop {
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(Ok(val)) => return Ok(val),
+            Poll::Ready(Err(err)) => return Err(Waited::Inner(err)),
+            Poll::Pending => (), // fallthrough
+        }
+
+        if let Some(deadline) = deadline {
+            let now = Instant::now();
+            if now >= deadline {
+                log::trace!("wait timeout exceeded");
+                return Err(Waited::TimedOut(now - started));
+            }
+
+            log::trace!(
+                "({:?}) park timeout {:?}",
+                thread::current().id(),
+                deadline - now
+            );
+            thread::park_timeout(deadline - now);
+        } else {
+            log::trace!("({:?}) park without timeout", thread::current().id());
+            thread::park();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum Waited<E> {
+    /// Timed out after waiting this long.
+    TimedOut(Duration),
+    Inner(E),
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
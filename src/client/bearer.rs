@@ -0,0 +1,87 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use http::header::AUTHORIZATION;
+use http::HeaderValue;
+use log::trace;
+use tokio::sync::Mutex;
+
+use super::request::Request;
+use super::response::Response;
+use crate::auth::{Token, TokenProvider};
+use crate::{error, StatusCode};
+
+/// The bearer tokens of a client, from its `TokenProvider`.
+pub(crate) struct BearerAuth {
+    provider: Arc<dyn TokenProvider>,
+    /// The current token and its header. The lock is held while fetching a
+    /// token, so concurrent requests wait for a single call.
+    token: Mutex<Option<(Token, HeaderValue)>>,
+}
+
+impl BearerAuth {
+    pub(crate) fn new(provider: Arc<dyn TokenProvider>) -> BearerAuth {
+        BearerAuth {
+            provider,
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Send `req` with `send` and the current token.
+    ///
+    /// A `401 Unauthorized` response is answered by refreshing the token and
+    /// sending the request again, if its body can be cloned, otherwise the
+    /// response is returned as is.
+    pub(crate) async fn send<F, Fut>(&self, mut req: Request, send: F) -> crate::Result<Response>
+    where
+        F: Fn(Request) -> Fut,
+        Fut: Future<Output = crate::Result<Response>>,
+    {
+        let url = req.url().clone();
+        let auth = self
+            .current(None)
+            .await
+            .map_err(|e| e.with_url(url.clone()))?;
+
+        let retry = req.try_clone();
+        req.headers_mut().insert(AUTHORIZATION, auth.clone());
+        let res = send(req).await?;
+        if res.status() != StatusCode::UNAUTHORIZED {
+            return Ok(res);
+        }
+
+        let Some(mut retry) = retry else {
+            return Ok(res);
+        };
+        trace!("refreshing bearer token for {}", url);
+        let auth = self
+            .current(Some(&auth))
+            .await
+            .map_err(|e| e.with_url(url))?;
+        retry.headers_mut().insert(AUTHORIZATION, auth);
+        send(retry).await
+    }
+
+    /// The header of the current token, fetching one if there is none or it
+    /// expired, or refreshing it if it is still the `rejected` one.
+    async fn current(&self, rejected: Option<&HeaderValue>) -> crate::Result<HeaderValue> {
+        let mut current = self.token.lock().await;
+        if let Some((ref token, ref value)) = *current {
+            if !token.is_expired() && Some(value) != rejected {
+                return Ok(value.clone());
+            }
+        }
+
+        let token = match rejected {
+            Some(_) => self.provider.refresh().await,
+            None => self.provider.token().await,
+        }
+        .map_err(error::request_boxed)?;
+
+        let mut value =
+            HeaderValue::from_str(&format!("Bearer {}", token.value())).map_err(error::request)?;
+        value.set_sensitive(true);
+        *current = Some((token, value.clone()));
+        Ok(value)
+    }
+}
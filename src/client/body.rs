@@ -1,7 +1,9 @@
 use std::fmt;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
 use bytes::Bytes;
 use futures_core::Stream;
@@ -9,10 +11,15 @@ use http_body::Body as HttpBody;
 use pin_project_lite::pin_project;
 #[cfg(feature = "stream")]
 use tokio::fs::File;
+use tokio::sync::oneshot;
 use tokio::time::Sleep;
 #[cfg(feature = "stream")]
 use tokio_util::io::ReaderStream;
 
+use crate::error::{IncompleteBody, TimedOut};
+use crate::metrics::Written;
+use crate::TimeoutPhase;
+
 /// An asynchronous request body.
 pub struct Body {
     inner: Inner,
@@ -31,7 +38,8 @@ enum Inner {
                     + Sync,
             >,
         >,
-        timeout: Option<Pin<Box<Sleep>>>,
+        /// The request timeout, and when the request started.
+        timeout: Option<(Pin<Box<Sleep>>, Instant)>,
     },
 }
 
@@ -44,6 +52,53 @@ pin_project! {
 
 struct WrapHyper(hyper::Body);
 
+pin_project! {
+    /// Fails a response body that ends before its length, or whose
+    /// connection closed before the last chunk.
+    struct LengthCheckBody<B> {
+        #[pin]
+        inner: B,
+        expected: Option<u64>,
+        received: u64,
+    }
+}
+
+pin_project! {
+    /// Holds back a body sent with `Expect: 100-continue`, until its delay
+    /// elapses. Once the final response came, the body is never sent.
+    struct ContinueBody<B> {
+        #[pin]
+        inner: B,
+        delay: Option<Pin<Box<Sleep>>>,
+        responded: oneshot::Receiver<()>,
+    }
+}
+
+pin_project! {
+    /// Tells `written` once the connection polled a request body to its end.
+    struct SentBody<B> {
+        #[pin]
+        inner: B,
+        written: Written,
+    }
+}
+
+/// A callback told the bytes of a body transferred so far, and its total
+/// length if known.
+pub(crate) type Progress = Arc<dyn Fn(u64, Option<u64>) + Send + Sync>;
+
+pin_project! {
+    /// Reports the progress of a body as it is polled, by the connection
+    /// for a request body or by the user for a response body.
+    struct ProgressBody<B> {
+        #[pin]
+        inner: B,
+        transferred: u64,
+        total: Option<u64>,
+        progress: Progress,
+    }
+}
+
 impl Body {
     /// Returns a reference to the internal data of the `Body`.
     ///
@@ -108,7 +163,7 @@ impl Body {
         }
     }
 
-    pub(crate) fn response(body: hyper::Body, timeout: Option<Pin<Box<Sleep>>>) -> Body {
+    pub(crate) fn response(body: hyper::Body, timeout: Option<(Pin<Box<Sleep>>, Instant)>) -> Body {
         Body {
             inner: Inner::Streaming {
                 body: Box::pin(WrapHyper(body)),
@@ -143,11 +198,120 @@ impl Body {
         }
     }
 
+    /// Wraps the body to call `progress` as its chunks are sent.
+    pub(crate) fn with_progress(self, progress: Progress) -> Body {
+        let total = match self.inner {
+            Inner::Reusable(ref bytes) => Some(bytes.len() as u64),
+            Inner::Streaming { ref body, .. } => body.size_hint().exact(),
+        };
+        self.with_progress_total(progress, total)
+    }
+
+    /// Wraps the body to call `progress` as its chunks are polled, reporting
+    /// `total` as its length.
+    pub(crate) fn with_progress_total(self, progress: Progress, total: Option<u64>) -> Body {
+        match self.inner {
+            Inner::Reusable(bytes) => Body {
+                inner: Inner::Streaming {
+                    body: Box::pin(ProgressBody::new(
+                        Body::reusable(bytes).into_stream(),
+                        total,
+                        progress,
+                    )),
+                    timeout: None,
+                },
+            },
+            Inner::Streaming { body, timeout } => Body {
+                inner: Inner::Streaming {
+                    body: Box::pin(ProgressBody::new(body, total, progress)),
+                    timeout,
+                },
+            },
+        }
+    }
+
+    /// Wraps a request body to tell `written` once it was sent whole.
+    pub(crate) fn with_written(self, written: &Written) -> Body {
+        if self.content_length() == Some(0) {
+            written.body_sent();
+            return self;
+        }
+        let (inner, timeout) = match self.inner {
+            Inner::Reusable(bytes) => (Body::reusable(bytes).into_stream(), None),
+            Inner::Streaming { body, timeout } => (
+                Body {
+                    inner: Inner::Streaming {
+                        body,
+                        timeout: None,
+                    },
+                }
+                .into_stream(),
+                timeout,
+            ),
+        };
+        Body {
+            inner: Inner::Streaming {
+                body: Box::pin(SentBody {
+                    inner,
+                    written: written.clone(),
+                }),
+                timeout,
+            },
+        }
+    }
+
+    /// Wraps a response body to fail once it turns out incomplete, for
+    /// `ClientBuilder::strict_content_length`. The length expected is the
+    /// one framing the body on the connection, if any.
+    pub(crate) fn with_length_check(self) -> Body {
+        match self.inner {
+            Inner::Reusable(_) => self,
+            Inner::Streaming { body, timeout } => Body {
+                inner: Inner::Streaming {
+                    body: Box::pin(LengthCheckBody {
+                        expected: body.size_hint().exact(),
+                        inner: body,
+                        received: 0,
+                    }),
+                    timeout,
+                },
+            },
+        }
+    }
+
+    /// Wraps the body to hold it back until `delay` elapses, or for good if
+    /// `responded` fires first, for a request sent with
+    /// `Expect: 100-continue`.
+    pub(crate) fn with_continue(self, delay: Duration, responded: oneshot::Receiver<()>) -> Body {
+        let (inner, timeout) = match self.inner {
+            Inner::Reusable(bytes) => (Body::reusable(bytes).into_stream(), None),
+            Inner::Streaming { body, timeout } => (
+                Body {
+                    inner: Inner::Streaming {
+                        body,
+                        timeout: None,
+                    },
+                }
+                .into_stream(),
+                timeout,
+            ),
+        };
+        Body {
+            inner: Inner::Streaming {
+                body: Box::pin(ContinueBody {
+                    inner,
+                    delay: Some(Box::pin(tokio::time::sleep(delay))),
+                    responded,
+                }),
+                timeout,
+            },
+        }
+    }
+
     pub(crate) fn into_stream(self) -> ImplStream {
         ImplStream(self)
     }
 
-    #[cfg(feature = "multipart")]
     pub(crate) fn content_length(&self) -> Option<u64> {
         match self.inner {
             Inner::Reusable(ref bytes) => Some(bytes.len() as u64),
@@ -233,9 +397,11 @@ impl HttpBody for ImplStream {
                 ref mut body,
                 ref mut timeout,
             } => {
-                if let Some(ref mut timeout) = timeout {
+                if let Some((timeout, started)) = timeout {
                     if let Poll::Ready(()) = timeout.as_mut().poll(cx) {
-                        return Poll::Ready(Some(Err(crate::error::body(crate::error::TimedOut))));
+                        let timed_out =
+                            TimedOut::new(TimeoutPhase::ResponseBody, started.elapsed());
+                        return Poll::Ready(Some(Err(crate::error::body(timed_out))));
                     }
                 }
                 futures_core::ready!(Pin::new(body).poll_data(cx))
@@ -315,6 +481,210 @@ where
     }
 }
 
+// ===== impl ProgressBody =====
+
+impl<B> ProgressBody<B> {
+    fn new(inner: B, total: Option<u64>, progress: Progress) -> Self {
+        ProgressBody {
+            inner,
+            transferred: 0,
+            total,
+            progress,
+        }
+    }
+}
+
+impl<B> HttpBody for ProgressBody<B>
+where
+    B: HttpBody<Data = Bytes>,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Data = Bytes;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        let chunk = futures_core::ready!(this.inner.poll_data(cx));
+        if let Some(Ok(ref chunk)) = chunk {
+            *this.transferred += chunk.len() as u64;
+            (this.progress)(*this.transferred, *this.total);
+        }
+        Poll::Ready(chunk.map(|chunk| chunk.map_err(Into::into)))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx).map_err(Into::into)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+// ===== impl LengthCheckBody =====
+
+impl<B> HttpBody for LengthCheckBody<B>
+where
+    B: HttpBody<Data = Bytes>,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Data = Bytes;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        let incomplete = |received| {
+            let incomplete = IncompleteBody {
+                expected: *this.expected,
+                received,
+            };
+            Poll::Ready(Some(Err(incomplete.into())))
+        };
+        match futures_core::ready!(this.inner.poll_data(cx)) {
+            Some(Ok(chunk)) => {
+                *this.received += chunk.len() as u64;
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            // hyper reports a connection closed in the middle of a body as
+            // an unexpected EOF.
+            Some(Err(err)) => {
+                let err = err.into();
+                if is_unexpected_eof(&*err) {
+                    return incomplete(*this.received);
+                }
+                Poll::Ready(Some(Err(err)))
+            }
+            None => match *this.expected {
+                Some(expected) if expected != *this.received => incomplete(*this.received),
+                _ => Poll::Ready(None),
+            },
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx).map_err(Into::into)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+fn is_unexpected_eof(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            if io.kind() == std::io::ErrorKind::UnexpectedEof {
+                return true;
+            }
+        }
+        source = err.source();
+    }
+    false
+}
+
+// ===== impl ContinueBody =====
+
+impl<B> HttpBody for ContinueBody<B>
+where
+    B: HttpBody<Data = Bytes>,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Data = Bytes;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        if let Some(delay) = this.delay {
+            // The request is over, either answered or dropped.
+            if Pin::new(&mut *this.responded).poll(cx).is_ready() {
+                return Poll::Pending;
+            }
+            futures_core::ready!(delay.as_mut().poll(cx));
+            *this.delay = None;
+        }
+        this.inner
+            .poll_data(cx)
+            .map(|opt| opt.map(|res| res.map_err(Into::into)))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx).map_err(Into::into)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+// ===== impl SentBody =====
+
+impl<B> HttpBody for SentBody<B>
+where
+    B: HttpBody<Data = Bytes>,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Data = Bytes;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let mut this = self.project();
+        let opt = futures_core::ready!(this.inner.as_mut().poll_data(cx));
+        if opt.is_none() || this.inner.is_end_stream() {
+            this.written.body_sent();
+        }
+        Poll::Ready(opt.map(|res| res.map_err(Into::into)))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx).map_err(Into::into)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
 // ===== impl WrapHyper =====
 
 impl HttpBody for WrapHyper {
@@ -0,0 +1,172 @@
+//! Streaming brotli decompression.
+//!
+//! Response bodies are decoded as they are read, into chunks of a bounded
+//! size: the decoder only holds the brotli window, up to 16 MiB (or 1 GiB
+//! with the large-window extension), and the chunk being filled.
+
+use std::io;
+
+use brotli_crate::enc::StandardAlloc;
+use brotli_crate::{BrotliDecompressStream, BrotliResult, BrotliState};
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::Decoder;
+
+/// How brotli bodies are decoded, see
+/// [`ClientBuilder::brotli_buffer_size`](crate::ClientBuilder::brotli_buffer_size)
+/// and [`ClientBuilder::brotli_large_window`](crate::ClientBuilder::brotli_large_window).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct BrotliParams {
+    pub(super) buffer_size: usize,
+    pub(super) large_window: bool,
+}
+
+impl Default for BrotliParams {
+    fn default() -> BrotliParams {
+        BrotliParams {
+            buffer_size: 8 * 1024,
+            large_window: false,
+        }
+    }
+}
+
+/// Decodes a brotli stream into chunks of at most `buffer_size` bytes,
+/// consuming its input as it goes.
+pub(super) struct BrotliCodec {
+    state: BrotliState<StandardAlloc, StandardAlloc, StandardAlloc>,
+    buffer_size: usize,
+    // The last chunk filled up, the decoder may have more output without
+    // more input.
+    more_output: bool,
+    done: bool,
+}
+
+impl BrotliCodec {
+    pub(super) fn new(params: BrotliParams) -> BrotliCodec {
+        let mut state = BrotliState::new(
+            StandardAlloc::default(),
+            StandardAlloc::default(),
+            StandardAlloc::default(),
+        );
+        state.large_window = params.large_window;
+        BrotliCodec {
+            state,
+            buffer_size: params.buffer_size.max(1),
+            more_output: false,
+            done: false,
+        }
+    }
+}
+
+impl Decoder for BrotliCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        if self.done {
+            // Whatever follows the end of the stream is ignored.
+            src.clear();
+            return Ok(None);
+        }
+        if src.is_empty() && !self.more_output {
+            return Ok(None);
+        }
+
+        let mut out = BytesMut::zeroed(self.buffer_size);
+        let mut available_in = src.len();
+        let mut input_offset = 0;
+        let mut available_out = out.len();
+        let mut output_offset = 0;
+        let mut total_out = 0;
+        let result = BrotliDecompressStream(
+            &mut available_in,
+            &mut input_offset,
+            &src[..],
+            &mut available_out,
+            &mut output_offset,
+            &mut out[..],
+            &mut total_out,
+            &mut self.state,
+        );
+        src.advance(input_offset);
+        out.truncate(output_offset);
+
+        match result {
+            BrotliResult::ResultSuccess => {
+                self.done = true;
+                self.more_output = false;
+            }
+            BrotliResult::NeedsMoreOutput => self.more_output = true,
+            BrotliResult::NeedsMoreInput => self.more_output = false,
+            BrotliResult::ResultFailure => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid brotli stream",
+                ));
+            }
+        }
+
+        if out.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(out))
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        match self.decode(src)? {
+            Some(out) => Ok(Some(out)),
+            None if self.done => Ok(None),
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "brotli stream ended early",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut compressed = Vec::new();
+        brotli_crate::CompressorReader::new(data, 4096, 5, 22)
+            .read_to_end(&mut compressed)
+            .unwrap();
+        compressed
+    }
+
+    #[test]
+    fn decodes_in_bounded_chunks() {
+        let data = b"abcdefgh".repeat(10_000);
+        let mut src = BytesMut::from(&compress(&data)[..]);
+        let mut codec = BrotliCodec::new(BrotliParams {
+            buffer_size: 1000,
+            large_window: false,
+        });
+
+        let mut decoded = Vec::new();
+        while let Some(chunk) = codec.decode_eof(&mut src).unwrap() {
+            assert!(chunk.len() <= 1000);
+            decoded.extend_from_slice(&chunk);
+        }
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn truncated_stream_fails() {
+        let compressed = compress(&b"abcdefgh".repeat(10_000));
+        let mut src = BytesMut::from(&compressed[..compressed.len() / 2]);
+        let mut codec = BrotliCodec::new(BrotliParams::default());
+
+        let err = loop {
+            match codec.decode_eof(&mut src) {
+                Ok(Some(_)) => continue,
+                Ok(None) => panic!("truncated stream decoded"),
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
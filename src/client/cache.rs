@@ -0,0 +1,556 @@
+//! HTTP caching
+//!
+//! A client built with
+//! [`ClientBuilder::http_cache`](crate::ClientBuilder::http_cache) keeps
+//! the responses to its `GET` and `HEAD` requests that may be reused, as
+//! described by [RFC 9111](https://www.rfc-editor.org/rfc/rfc9111), which
+//! obsoletes RFC 7234. A fresh stored response is returned without
+//! contacting the server, a stale one is revalidated with
+//! `If-None-Match` or `If-Modified-Since`. Stale responses are never
+//! served.
+//!
+//! Each response of such a client has a [`CacheStatus`] in its
+//! extensions.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), rquest::Error> {
+//! use rquest::cache::{CacheConfig, CacheStatus};
+//!
+//! let client = rquest::Client::builder()
+//!     .http_cache(CacheConfig::default())
+//!     .build()?;
+//! let res = client.get("https://hyper.rs").send().await?;
+//! let status = res.extensions().get::<CacheStatus>();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use bytes::Bytes;
+use http::header::{
+    AGE, AUTHORIZATION, CACHE_CONTROL, CONTENT_LENGTH, DATE, ETAG, EXPIRES, IF_MODIFIED_SINCE,
+    IF_NONE_MATCH, LAST_MODIFIED, PRAGMA, VARY,
+};
+use http::{HeaderMap, HeaderName, HeaderValue};
+use log::trace;
+
+use super::request::Request;
+use super::response::{Replay, Response};
+use crate::{Method, StatusCode, Url};
+
+/// How a response was served, in the extensions of the responses of a
+/// client with a cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// The stored response, served without contacting the server.
+    Hit,
+    /// The stored response, after the server confirmed it is still valid.
+    Revalidated,
+    /// A response from the server.
+    Miss,
+}
+
+/// The configuration of the cache of a client.
+#[derive(Clone)]
+pub struct CacheConfig {
+    /// Where the responses are stored.
+    pub storage: Arc<dyn CacheStorage>,
+    /// The size of the largest body stored, in bytes.
+    pub max_object_size: u64,
+    /// Whether the cache is shared between users, as in a proxy.
+    ///
+    /// A shared cache honors `s-maxage`, and doesn't store `private`
+    /// responses, nor those to requests with an `Authorization` header
+    /// unless they allow it.
+    pub shared: bool,
+}
+
+impl Default for CacheConfig {
+    /// A private cache of 1024 responses of up to 1 MiB, in memory.
+    fn default() -> CacheConfig {
+        CacheConfig {
+            storage: Arc::new(MemoryCache::new(1024)),
+            max_object_size: 1024 * 1024,
+            shared: false,
+        }
+    }
+}
+
+impl fmt::Debug for CacheConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CacheConfig")
+            .field("max_object_size", &self.max_object_size)
+            .field("shared", &self.shared)
+            .finish()
+    }
+}
+
+/// A store of cached responses.
+///
+/// Responses are stored under a key made of the method and URL of their
+/// request.
+pub trait CacheStorage: Send + Sync + 'static {
+    /// Get the response stored under `key`.
+    fn get(&self, key: &str) -> Option<CachedResponse>;
+
+    /// Store `response` under `key`, replacing the one stored before.
+    fn put(&self, key: &str, response: CachedResponse);
+
+    /// Remove the response stored under `key`.
+    fn remove(&self, key: &str);
+}
+
+/// A stored response.
+#[derive(Clone)]
+pub struct CachedResponse {
+    replay: Arc<Replay>,
+    /// The request headers named by `Vary`, and their values.
+    vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    request_time: SystemTime,
+    response_time: SystemTime,
+}
+
+impl CachedResponse {
+    /// Get the status of the response.
+    pub fn status(&self) -> StatusCode {
+        self.replay.status()
+    }
+
+    /// Get the headers of the response.
+    pub fn headers(&self) -> &HeaderMap {
+        self.replay.headers()
+    }
+
+    /// Get the body of the response, decoded.
+    pub fn body(&self) -> &Bytes {
+        self.replay.body()
+    }
+
+    /// Whether the response can be used for a request with `headers`.
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| headers.get(name) == value.as_ref())
+    }
+
+    /// How old the response is, from RFC 9111 section 4.2.3.
+    fn age(&self, now: SystemTime) -> Duration {
+        let date = self.date().unwrap_or(self.response_time);
+        let apparent_age = since(date, self.response_time);
+        let response_delay = since(self.request_time, self.response_time);
+        let age_value = self
+            .headers()
+            .get(AGE)
+            .and_then(|age| age.to_str().ok()?.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_default();
+        let initial_age = apparent_age.max(age_value + response_delay);
+        initial_age + since(self.response_time, now)
+    }
+
+    /// How long the response is fresh, from RFC 9111 section 4.2.1.
+    fn freshness_lifetime(&self, shared: bool) -> Duration {
+        let directives = Directives::of(self.headers());
+        if shared {
+            if let Some(lifetime) = directives.seconds("s-maxage") {
+                return lifetime;
+            }
+        }
+        if let Some(lifetime) = directives.seconds("max-age") {
+            return lifetime;
+        }
+
+        let date = self.date().unwrap_or(self.response_time);
+        if let Some(expires) = self.headers().get(EXPIRES) {
+            // An invalid date means the response is already expired.
+            return http_date(expires).map_or(Duration::ZERO, |expires| since(date, expires));
+        }
+
+        // The heuristic of 10% of the time since the last modification.
+        match self.headers().get(LAST_MODIFIED).and_then(http_date) {
+            Some(modified) if is_cacheable_by_default(self.status()) => since(modified, date) / 10,
+            _ => Duration::ZERO,
+        }
+    }
+
+    fn is_fresh(&self, now: SystemTime, shared: bool) -> bool {
+        !Directives::of(self.headers()).has("no-cache")
+            && self.freshness_lifetime(shared) > self.age(now)
+    }
+
+    fn date(&self) -> Option<SystemTime> {
+        self.headers().get(DATE).and_then(http_date)
+    }
+
+    /// The response updated with the headers of a `304 Not Modified`.
+    fn refresh(
+        &self,
+        headers: &HeaderMap,
+        request_time: SystemTime,
+        response_time: SystemTime,
+    ) -> CachedResponse {
+        let mut stored = self.headers().clone();
+        for name in headers.keys() {
+            if name != CONTENT_LENGTH {
+                stored.remove(name);
+                for value in headers.get_all(name) {
+                    stored.append(name, value.clone());
+                }
+            }
+        }
+        CachedResponse {
+            replay: Arc::new(self.replay.with_headers(stored)),
+            vary: self.vary.clone(),
+            request_time,
+            response_time,
+        }
+    }
+
+    fn to_response(&self, status: CacheStatus, now: SystemTime) -> Response {
+        let mut res = self.replay.to_response();
+        res.headers_mut()
+            .insert(AGE, HeaderValue::from(self.age(now).as_secs()));
+        res.extensions_mut().insert(status);
+        res
+    }
+}
+
+impl fmt::Debug for CachedResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CachedResponse")
+            .field("status", &self.status())
+            .field("headers", self.headers())
+            .finish()
+    }
+}
+
+/// A [`CacheStorage`] in memory, evicting the least recently used
+/// responses.
+pub struct MemoryCache {
+    max_entries: usize,
+    entries: Mutex<MemoryEntries>,
+}
+
+#[derive(Default)]
+struct MemoryEntries {
+    clock: u64,
+    /// The responses, with when they were last used.
+    map: HashMap<String, (u64, CachedResponse)>,
+}
+
+impl MemoryCache {
+    /// Create a cache of up to `max_entries` responses.
+    pub fn new(max_entries: usize) -> MemoryCache {
+        MemoryCache {
+            max_entries,
+            entries: Mutex::default(),
+        }
+    }
+}
+
+impl CacheStorage for MemoryCache {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        let mut entries = self.entries.lock().expect("cache lock poisoned");
+        entries.clock += 1;
+        let clock = entries.clock;
+        let (used, response) = entries.map.get_mut(key)?;
+        *used = clock;
+        Some(response.clone())
+    }
+
+    fn put(&self, key: &str, response: CachedResponse) {
+        let mut entries = self.entries.lock().expect("cache lock poisoned");
+        if !entries.map.contains_key(key) && entries.map.len() >= self.max_entries {
+            let oldest = entries
+                .map
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => {
+                    entries.map.remove(&oldest);
+                }
+                None => return,
+            }
+        }
+        entries.clock += 1;
+        let clock = entries.clock;
+        entries.map.insert(key.to_owned(), (clock, response));
+    }
+
+    fn remove(&self, key: &str) {
+        self.entries
+            .lock()
+            .expect("cache lock poisoned")
+            .map
+            .remove(key);
+    }
+}
+
+impl fmt::Debug for MemoryCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemoryCache")
+            .field("max_entries", &self.max_entries)
+            .finish()
+    }
+}
+
+/// The cache of a client.
+pub(crate) struct HttpCache {
+    config: CacheConfig,
+}
+
+impl HttpCache {
+    pub(crate) fn new(config: CacheConfig) -> HttpCache {
+        HttpCache { config }
+    }
+
+    pub(crate) async fn send<F, Fut>(&self, mut req: Request, send: F) -> crate::Result<Response>
+    where
+        F: FnOnce(Request) -> Fut,
+        Fut: Future<Output = crate::Result<Response>>,
+    {
+        let method = req.method().clone();
+        let url = req.url().clone();
+        if method != Method::GET && method != Method::HEAD {
+            let res = send(req).await?;
+            // A change to the resource makes its stored responses stale.
+            if !matches!(method, Method::OPTIONS | Method::TRACE)
+                && (res.status().is_success() || res.status().is_redirection())
+            {
+                self.config.storage.remove(&key(&Method::GET, &url));
+                self.config.storage.remove(&key(&Method::HEAD, &url));
+            }
+            return Ok(res);
+        }
+
+        let request = Directives::of(req.headers());
+        // Requests that validate their own response get it as is.
+        let bypass = request.has("no-store")
+            || req.headers().contains_key(IF_NONE_MATCH)
+            || req.headers().contains_key(IF_MODIFIED_SINCE);
+        if bypass {
+            return send(req)
+                .await
+                .map(|res| with_status(res, CacheStatus::Miss));
+        }
+        let no_cache = request.has("no-cache")
+            || request.seconds("max-age") == Some(Duration::ZERO)
+            || (!req.headers().contains_key(CACHE_CONTROL)
+                && req
+                    .headers()
+                    .get(PRAGMA)
+                    .is_some_and(|pragma| pragma == "no-cache"));
+
+        let key = key(&method, &url);
+        let stored = self
+            .config
+            .storage
+            .get(&key)
+            .filter(|stored| stored.matches(req.headers()));
+        if let Some(ref stored) = stored {
+            let now = SystemTime::now();
+            if !no_cache && stored.is_fresh(now, self.config.shared) {
+                trace!("cache hit for {}", url);
+                return Ok(stored.to_response(CacheStatus::Hit, now));
+            }
+
+            trace!("revalidating cached response for {}", url);
+            if let Some(etag) = stored.headers().get(ETAG) {
+                req.headers_mut().insert(IF_NONE_MATCH, etag.clone());
+            }
+            if let Some(modified) = stored.headers().get(LAST_MODIFIED) {
+                req.headers_mut()
+                    .insert(IF_MODIFIED_SINCE, modified.clone());
+            }
+        }
+
+        let headers = req.headers().clone();
+        let request_time = SystemTime::now();
+        let res = send(req).await?;
+        let response_time = SystemTime::now();
+
+        if let Some(stored) = stored {
+            if res.status() == StatusCode::NOT_MODIFIED {
+                let stored = stored.refresh(res.headers(), request_time, response_time);
+                self.config.storage.put(&key, stored.clone());
+                return Ok(stored.to_response(CacheStatus::Revalidated, response_time));
+            }
+        }
+
+        if res.url() != &url || !self.is_storable(&headers, &res) {
+            return Ok(with_status(res, CacheStatus::Miss));
+        }
+        let (res, replay) = res.replay().await?;
+        if replay.body().len() as u64 <= self.config.max_object_size {
+            trace!("storing response for {}", url);
+            let vary = vary(replay.headers())
+                .map(|name| {
+                    let value = headers.get(&name).cloned();
+                    (name, value)
+                })
+                .collect();
+            let stored = CachedResponse {
+                replay: Arc::new(replay),
+                vary,
+                request_time,
+                response_time,
+            };
+            self.config.storage.put(&key, stored);
+        }
+        Ok(with_status(res, CacheStatus::Miss))
+    }
+
+    /// Whether `res`, to a request with `headers`, can be stored, from RFC
+    /// 9111 section 3.
+    fn is_storable(&self, headers: &HeaderMap, res: &Response) -> bool {
+        let status = res.status();
+        if status.is_informational()
+            || status == StatusCode::PARTIAL_CONTENT
+            || status == StatusCode::NOT_MODIFIED
+        {
+            return false;
+        }
+
+        let response = Directives::of(res.headers());
+        let shared = self.config.shared;
+        if response.has("no-store") || (shared && response.has("private")) {
+            return false;
+        }
+        if res.headers().get_all(VARY).iter().any(|vary| {
+            vary.as_bytes()
+                .split(|&b| b == b',')
+                .any(|f| f.trim_ascii() == b"*")
+        }) {
+            return false;
+        }
+        if shared
+            && headers.contains_key(AUTHORIZATION)
+            && !(response.has("public")
+                || response.has("s-maxage")
+                || response.has("must-revalidate"))
+        {
+            return false;
+        }
+        if res
+            .content_length()
+            .is_some_and(|len| len > self.config.max_object_size)
+        {
+            return false;
+        }
+
+        response.has("public")
+            || (!shared && response.has("private"))
+            || response.has("max-age")
+            || (shared && response.has("s-maxage"))
+            || res.headers().contains_key(EXPIRES)
+            || is_cacheable_by_default(status)
+    }
+}
+
+/// The key a response to `method` and `url` is stored under.
+fn key(method: &Method, url: &Url) -> String {
+    format!("{} {}", method, url)
+}
+
+fn with_status(mut res: Response, status: CacheStatus) -> Response {
+    res.extensions_mut().insert(status);
+    res
+}
+
+/// The request header names in the `Vary` header of a response.
+fn vary(headers: &HeaderMap) -> impl Iterator<Item = HeaderName> + '_ {
+    headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|vary| vary.to_str().ok())
+        .flat_map(|vary| vary.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+}
+
+/// The statuses whose responses can be stored without explicit freshness.
+fn is_cacheable_by_default(status: StatusCode) -> bool {
+    matches!(
+        status.as_u16(),
+        200 | 203 | 204 | 300 | 301 | 308 | 404 | 405 | 410 | 414 | 501
+    )
+}
+
+fn http_date(value: &HeaderValue) -> Option<SystemTime> {
+    httpdate::parse_http_date(value.to_str().ok()?).ok()
+}
+
+/// The time from `earlier` to `later`, or zero if it is not earlier.
+fn since(earlier: SystemTime, later: SystemTime) -> Duration {
+    later.duration_since(earlier).unwrap_or_default()
+}
+
+/// The directives of the `Cache-Control` headers.
+struct Directives(Vec<(String, Option<String>)>);
+
+impl Directives {
+    fn of(headers: &HeaderMap) -> Directives {
+        let directives = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|directive| !directive.is_empty())
+            .map(|directive| match directive.split_once('=') {
+                Some((name, value)) => (
+                    name.trim().to_ascii_lowercase(),
+                    Some(value.trim().trim_matches('"').to_owned()),
+                ),
+                None => (directive.to_ascii_lowercase(), None),
+            })
+            .collect();
+        Directives(directives)
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.0.iter().any(|(directive, _)| directive == name)
+    }
+
+    fn seconds(&self, name: &str) -> Option<Duration> {
+        self.0
+            .iter()
+            .find(|(directive, _)| directive == name)
+            .and_then(|(_, value)| value.as_ref()?.parse().ok())
+            .map(Duration::from_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directives_are_case_insensitive_and_unquoted() {
+        let mut headers = HeaderMap::new();
+        headers.append(
+            CACHE_CONTROL,
+            HeaderValue::from_static("Public, MAX-AGE=\"60\""),
+        );
+        headers.append(CACHE_CONTROL, HeaderValue::from_static("no-transform"));
+
+        let directives = Directives::of(&headers);
+        assert!(directives.has("public"));
+        assert!(directives.has("no-transform"));
+        assert_eq!(directives.seconds("max-age"), Some(Duration::from_secs(60)));
+        assert_eq!(directives.seconds("s-maxage"), None);
+    }
+
+    #[test]
+    fn cacheable_statuses() {
+        assert!(is_cacheable_by_default(StatusCode::OK));
+        assert!(is_cacheable_by_default(StatusCode::NOT_FOUND));
+        assert!(!is_cacheable_by_default(StatusCode::CREATED));
+        assert!(!is_cacheable_by_default(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+}
@@ -0,0 +1,100 @@
+use std::io;
+use std::pin::Pin;
+
+#[cfg(feature = "gzip")]
+use async_compression::tokio::bufread::GzipEncoder;
+#[cfg(feature = "zstd")]
+use async_compression::tokio::bufread::ZstdEncoder;
+use async_compression::Level;
+use bytes::{Bytes, BytesMut};
+use futures_core::Stream;
+use futures_util::{FutureExt, StreamExt, TryStreamExt};
+use http::HeaderValue;
+use tokio::io::AsyncBufRead;
+use tokio_util::codec::{BytesCodec, FramedRead};
+use tokio_util::io::StreamReader;
+
+use super::Body;
+
+/// A compression of request bodies, see
+/// [`RequestBuilder::compress_body`](crate::RequestBuilder::compress_body).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// gzip, levels 0 to 9.
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// zstd, levels 1 to 22.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+type Encoded = Pin<Box<dyn Stream<Item = io::Result<BytesMut>> + Send + Sync>>;
+
+impl Compression {
+    /// The `Content-Encoding` of a body compressed this way.
+    pub(crate) fn content_encoding(&self) -> HeaderValue {
+        match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => HeaderValue::from_static("gzip"),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => HeaderValue::from_static("zstd"),
+        }
+    }
+
+    /// Compresses `body` at `level`.
+    ///
+    /// A buffered body is compressed right away, so it keeps a length and
+    /// can still be sent again. A streaming body is compressed as it is sent.
+    pub(crate) fn compress(&self, body: Body, level: i32) -> io::Result<Body> {
+        match body.as_bytes() {
+            Some(bytes) => self.compress_bytes(Bytes::copy_from_slice(bytes), level),
+            None => {
+                let stream = body
+                    .into_stream()
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e));
+                Ok(Body::stream(self.encode(StreamReader::new(stream), level)))
+            }
+        }
+    }
+
+    fn compress_bytes(&self, bytes: Bytes, level: i32) -> io::Result<Body> {
+        let mut encoded = self.encode(
+            StreamReader::new(futures_util::stream::iter([Ok::<_, io::Error>(bytes)])),
+            level,
+        );
+        // Reading from memory never waits, so neither does the encoder.
+        let mut compressed = BytesMut::new();
+        loop {
+            match encoded.next().now_or_never() {
+                Some(Some(chunk)) => compressed.extend_from_slice(&chunk?),
+                Some(None) => return Ok(Body::reusable(compressed.freeze())),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WouldBlock,
+                        "compressing a buffered body",
+                    ))
+                }
+            }
+        }
+    }
+
+    fn encode<R>(&self, reader: R, level: i32) -> Encoded
+    where
+        R: AsyncBufRead + Send + Sync + 'static,
+    {
+        let level = Level::Precise(level);
+        match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Box::pin(FramedRead::new(
+                GzipEncoder::with_quality(reader, level),
+                BytesCodec::new(),
+            )),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Box::pin(FramedRead::new(
+                ZstdEncoder::with_quality(reader, level),
+                BytesCodec::new(),
+            )),
+        }
+    }
+}
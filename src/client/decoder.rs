@@ -7,7 +7,7 @@ use std::task::{Context, Poll};
 use async_compression::tokio::bufread::GzipDecoder;
 
 #[cfg(feature = "brotli")]
-use async_compression::tokio::bufread::BrotliDecoder;
+use super::brotli::{BrotliCodec, BrotliParams};
 
 #[cfg(feature = "zstd")]
 use async_compression::tokio::bufread::ZstdDecoder;
@@ -21,13 +21,15 @@ use futures_util::stream::Peekable;
 use http::HeaderMap;
 use hyper::body::HttpBody;
 
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "deflate"))]
+use tokio_util::codec::BytesCodec;
 #[cfg(any(
     feature = "gzip",
     feature = "brotli",
     feature = "zstd",
     feature = "deflate"
 ))]
-use tokio_util::codec::{BytesCodec, FramedRead};
+use tokio_util::codec::FramedRead;
 #[cfg(any(
     feature = "gzip",
     feature = "brotli",
@@ -38,6 +40,10 @@ use tokio_util::io::StreamReader;
 
 use super::super::Body;
 use crate::error;
+use crate::metrics::Recorder;
+
+#[cfg(feature = "tracing")]
+use super::trace::BodyTrace;
 
 #[derive(Clone, Copy, Debug)]
 pub(super) struct Accepts {
@@ -45,10 +51,29 @@ pub(super) struct Accepts {
     pub(super) gzip: bool,
     #[cfg(feature = "brotli")]
     pub(super) brotli: bool,
+    #[cfg(feature = "brotli")]
+    pub(super) brotli_params: BrotliParams,
     #[cfg(feature = "zstd")]
     pub(super) zstd: bool,
     #[cfg(feature = "deflate")]
     pub(super) deflate: bool,
+    /// Whether `Accept-Encoding` has quality values, set by
+    /// `ClientBuilder::accept_encoding`.
+    pub(super) weighted: bool,
+}
+
+/// The content codings a `Client` accepts and decompresses.
+///
+/// Set with [`ClientBuilder::accept_encoding`](crate::ClientBuilder::accept_encoding),
+/// which advertises them by order of preference in the `Accept-Encoding`
+/// header: `zstd`, then `br`, `gzip` and `deflate`. A coding whose crate
+/// feature is disabled is never accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AcceptEncoding {
+    gzip: bool,
+    brotli: bool,
+    zstd: bool,
+    deflate: bool,
 }
 
 /// A response decompressor over a non-blocking stream of chunks.
@@ -56,6 +81,13 @@ pub(super) struct Accepts {
 /// The inner decoder may be constructed asynchronously.
 pub(crate) struct Decoder {
     inner: Inner,
+    /// Reports the request metrics once the body ends.
+    recorder: Option<Recorder>,
+    /// Records the body size in the request span once the body ends.
+    #[cfg(feature = "tracing")]
+    trace: Option<BodyTrace>,
+    /// The most decoded bytes to read, and how many were read so far.
+    limit: Option<(u64, u64)>,
 }
 
 type PeekableIoStream = Peekable<IoStream>;
@@ -73,7 +105,7 @@ enum Inner {
 
     /// A `Brotli` decoder will uncompress the brotlied response content before returning it.
     #[cfg(feature = "brotli")]
-    Brotli(Pin<Box<FramedRead<BrotliDecoder<PeekableIoStreamReader>, BytesCodec>>>),
+    Brotli(Pin<Box<FramedRead<PeekableIoStreamReader, BrotliCodec>>>),
 
     /// A `Zstd` decoder will uncompress the zstd compressed response content before returning it.
     #[cfg(feature = "zstd")]
@@ -102,7 +134,7 @@ enum DecoderType {
     #[cfg(feature = "gzip")]
     Gzip,
     #[cfg(feature = "brotli")]
-    Brotli,
+    Brotli(BrotliParams),
     #[cfg(feature = "zstd")]
     Zstd,
     #[cfg(feature = "deflate")]
@@ -122,6 +154,10 @@ impl Decoder {
     fn plain_text(body: Body) -> Decoder {
         Decoder {
             inner: Inner::PlainText(body.into_stream()),
+            recorder: None,
+            #[cfg(feature = "tracing")]
+            trace: None,
+            limit: None,
         }
     }
 
@@ -137,6 +173,10 @@ impl Decoder {
                 IoStream(body.into_stream()).peekable(),
                 DecoderType::Gzip,
             ))),
+            recorder: None,
+            #[cfg(feature = "tracing")]
+            trace: None,
+            limit: None,
         }
     }
 
@@ -144,14 +184,18 @@ impl Decoder {
     ///
     /// This decoder will buffer and decompress chunks that are brotlied.
     #[cfg(feature = "brotli")]
-    fn brotli(body: Body) -> Decoder {
+    fn brotli(body: Body, params: BrotliParams) -> Decoder {
         use futures_util::StreamExt;
 
         Decoder {
             inner: Inner::Pending(Box::pin(Pending(
                 IoStream(body.into_stream()).peekable(),
-                DecoderType::Brotli,
+                DecoderType::Brotli(params),
             ))),
+            recorder: None,
+            #[cfg(feature = "tracing")]
+            trace: None,
+            limit: None,
         }
     }
 
@@ -167,6 +211,10 @@ impl Decoder {
                 IoStream(body.into_stream()).peekable(),
                 DecoderType::Zstd,
             ))),
+            recorder: None,
+            #[cfg(feature = "tracing")]
+            trace: None,
+            limit: None,
         }
     }
 
@@ -182,6 +230,10 @@ impl Decoder {
                 IoStream(body.into_stream()).peekable(),
                 DecoderType::Deflate,
             ))),
+            recorder: None,
+            #[cfg(feature = "tracing")]
+            trace: None,
+            limit: None,
         }
     }
 
@@ -237,7 +289,7 @@ impl Decoder {
         #[cfg(feature = "brotli")]
         {
             if _accepts.brotli && Decoder::detect_encoding(_headers, "br") {
-                return Decoder::brotli(body);
+                return Decoder::brotli(body, _accepts.brotli_params);
             }
         }
 
@@ -257,12 +309,63 @@ impl Decoder {
 
         Decoder::plain_text(body)
     }
-}
 
-impl Stream for Decoder {
-    type Item = Result<Bytes, error::Error>;
+    pub(super) fn set_recorder(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
 
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+    #[cfg(feature = "tracing")]
+    pub(super) fn set_trace(&mut self, trace: BodyTrace) {
+        self.trace = Some(trace);
+    }
+
+    pub(super) fn set_limit(&mut self, limit: Option<u64>) {
+        self.limit = limit.map(|max| (max, 0));
+    }
+
+    /// Polls the decoded body, failing once it is longer than the limit.
+    ///
+    /// A body whose length is known to be over the limit fails before any of
+    /// it is read.
+    fn poll_limited(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Bytes, error::Error>>> {
+        let (max, read) = match self.limit {
+            Some(limit) => limit,
+            None => return self.poll_decoded(cx),
+        };
+
+        let known_over = read == 0
+            && HttpBody::size_hint(&*self)
+                .exact()
+                .is_some_and(|len| len > max);
+        let over = if known_over {
+            0
+        } else {
+            match futures_core::ready!(self.as_mut().poll_decoded(cx)) {
+                Some(Ok(bytes)) => {
+                    let read = read + bytes.len() as u64;
+                    if read <= max {
+                        self.limit = Some((max, read));
+                        return Poll::Ready(Some(Ok(bytes)));
+                    }
+                    read
+                }
+                other => return Poll::Ready(other),
+            }
+        };
+
+        // Drop the rest of the body, so it is not read any further.
+        self.inner = Inner::PlainText(Body::empty().into_stream());
+        self.limit = None;
+        Poll::Ready(Some(Err(error::body_too_large(max, over))))
+    }
+
+    fn poll_decoded(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Bytes, error::Error>>> {
         // Do a read or poll for a pending decoder value.
         match self.inner {
             #[cfg(any(
@@ -274,7 +377,7 @@ impl Stream for Decoder {
             Inner::Pending(ref mut future) => match Pin::new(future).poll(cx) {
                 Poll::Ready(Ok(inner)) => {
                     self.inner = inner;
-                    self.poll_next(cx)
+                    self.poll_decoded(cx)
                 }
                 Poll::Ready(Err(e)) => Poll::Ready(Some(Err(crate::error::decode_io(e)))),
                 Poll::Pending => Poll::Pending,
@@ -316,6 +419,35 @@ impl Stream for Decoder {
     }
 }
 
+impl Stream for Decoder {
+    type Item = Result<Bytes, error::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let poll = self.as_mut().poll_limited(cx);
+        let end = match poll {
+            Poll::Ready(Some(Err(ref err))) => Some(Some(err)),
+            Poll::Ready(None) => Some(None),
+            _ => None,
+        };
+        #[cfg(feature = "tracing")]
+        if let Some(ref mut trace) = self.trace {
+            if let Poll::Ready(Some(Ok(ref bytes))) = poll {
+                trace.read(bytes.len());
+            }
+        }
+        if let Some(err) = end {
+            if let Some(mut recorder) = self.recorder.take() {
+                recorder.finish(err);
+            }
+            #[cfg(feature = "tracing")]
+            if let Some(mut trace) = self.trace.take() {
+                trace.finish(err);
+            }
+        }
+        poll
+    }
+}
+
 impl HttpBody for Decoder {
     type Data = Bytes;
     type Error = crate::Error;
@@ -377,10 +509,13 @@ impl Future for Pending {
 
         match self.1 {
             #[cfg(feature = "brotli")]
-            DecoderType::Brotli => Poll::Ready(Ok(Inner::Brotli(Box::pin(FramedRead::new(
-                BrotliDecoder::new(StreamReader::new(_body)),
-                BytesCodec::new(),
-            ))))),
+            DecoderType::Brotli(params) => {
+                Poll::Ready(Ok(Inner::Brotli(Box::pin(FramedRead::with_capacity(
+                    StreamReader::new(_body),
+                    BrotliCodec::new(params),
+                    params.buffer_size,
+                )))))
+            }
             #[cfg(feature = "zstd")]
             DecoderType::Zstd => Poll::Ready(Ok(Inner::Zstd(Box::pin(FramedRead::new(
                 ZstdDecoder::new(StreamReader::new(_body)),
@@ -421,14 +556,82 @@ impl Accepts {
             gzip: false,
             #[cfg(feature = "brotli")]
             brotli: false,
+            #[cfg(feature = "brotli")]
+            brotli_params: BrotliParams::default(),
             #[cfg(feature = "zstd")]
             zstd: false,
             #[cfg(feature = "deflate")]
             deflate: false,
+            weighted: false,
         }
     }
 
+    /// The codings of an `Accept-Encoding` header value that are also in
+    /// `self`, leaving out those with a zero quality value.
+    ///
+    /// `identity` and unknown codings are ignored, `*` stands for all of
+    /// `self`.
+    pub(super) fn restrict_to(&self, accept_encoding: &str) -> Accepts {
+        let mut accepts = Accepts::none();
+        accepts.weighted = self.weighted;
+        #[cfg(feature = "brotli")]
+        {
+            accepts.brotli_params = self.brotli_params;
+        }
+        for coding in accept_encoding.split(',') {
+            let mut params = coding.split(';');
+            let name = params.next().unwrap_or("").trim();
+            let refused = params.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.trim().parse::<f32>().ok())
+                    .is_some_and(|q| q == 0.0)
+            });
+            if refused {
+                continue;
+            }
+            match name.to_ascii_lowercase().as_str() {
+                "*" => return *self,
+                #[cfg(feature = "gzip")]
+                "gzip" => accepts.gzip = self.gzip,
+                #[cfg(feature = "brotli")]
+                "br" => accepts.brotli = self.brotli,
+                #[cfg(feature = "zstd")]
+                "zstd" => accepts.zstd = self.zstd,
+                #[cfg(feature = "deflate")]
+                "deflate" => accepts.deflate = self.deflate,
+                _ => (),
+            }
+        }
+        accepts
+    }
+
+    pub(super) fn set(&mut self, _encoding: AcceptEncoding) {
+        #[cfg(feature = "gzip")]
+        {
+            self.gzip = _encoding.gzip;
+        }
+        #[cfg(feature = "brotli")]
+        {
+            self.brotli = _encoding.brotli;
+        }
+        #[cfg(feature = "zstd")]
+        {
+            self.zstd = _encoding.zstd;
+        }
+        #[cfg(feature = "deflate")]
+        {
+            self.deflate = _encoding.deflate;
+        }
+        self.weighted = true;
+    }
+
     pub(super) fn as_str(&self) -> Option<&'static str> {
+        if self.weighted {
+            return self.as_weighted_str();
+        }
+
         match (
             self.is_gzip(),
             self.is_brotli(),
@@ -454,6 +657,33 @@ impl Accepts {
         }
     }
 
+    /// The codings by order of preference, with quality values.
+    fn as_weighted_str(&self) -> Option<&'static str> {
+        match (
+            self.is_zstd(),
+            self.is_brotli(),
+            self.is_gzip(),
+            self.is_deflate(),
+        ) {
+            (true, true, true, true) => Some("zstd, br;q=0.9, gzip;q=0.8, deflate;q=0.7"),
+            (true, true, true, false) => Some("zstd, br;q=0.9, gzip;q=0.8"),
+            (true, true, false, true) => Some("zstd, br;q=0.9, deflate;q=0.7"),
+            (true, true, false, false) => Some("zstd, br;q=0.9"),
+            (true, false, true, true) => Some("zstd, gzip;q=0.8, deflate;q=0.7"),
+            (true, false, true, false) => Some("zstd, gzip;q=0.8"),
+            (true, false, false, true) => Some("zstd, deflate;q=0.7"),
+            (true, false, false, false) => Some("zstd"),
+            (false, true, true, true) => Some("br;q=0.9, gzip;q=0.8, deflate;q=0.7"),
+            (false, true, true, false) => Some("br;q=0.9, gzip;q=0.8"),
+            (false, true, false, true) => Some("br;q=0.9, deflate;q=0.7"),
+            (false, true, false, false) => Some("br;q=0.9"),
+            (false, false, true, true) => Some("gzip;q=0.8, deflate;q=0.7"),
+            (false, false, true, false) => Some("gzip;q=0.8"),
+            (false, false, false, true) => Some("deflate;q=0.7"),
+            (false, false, false, false) => None,
+        }
+    }
+
     fn is_gzip(&self) -> bool {
         #[cfg(feature = "gzip")]
         {
@@ -510,12 +740,69 @@ impl Default for Accepts {
             gzip: true,
             #[cfg(feature = "brotli")]
             brotli: true,
+            #[cfg(feature = "brotli")]
+            brotli_params: BrotliParams::default(),
             #[cfg(feature = "zstd")]
             zstd: true,
             #[cfg(feature = "deflate")]
             deflate: true,
+            weighted: false,
+        }
+    }
+}
+
+// ===== impl AcceptEncoding =====
+
+impl AcceptEncoding {
+    /// Accepts every coding whose crate feature is enabled.
+    pub fn new() -> AcceptEncoding {
+        AcceptEncoding {
+            gzip: true,
+            brotli: true,
+            zstd: true,
+            deflate: true,
         }
     }
+
+    /// Accepts no coding, so responses are not decompressed.
+    pub fn none() -> AcceptEncoding {
+        AcceptEncoding {
+            gzip: false,
+            brotli: false,
+            zstd: false,
+            deflate: false,
+        }
+    }
+
+    /// Accepts `gzip`.
+    pub fn gzip(mut self, enable: bool) -> AcceptEncoding {
+        self.gzip = enable;
+        self
+    }
+
+    /// Accepts `br`.
+    pub fn brotli(mut self, enable: bool) -> AcceptEncoding {
+        self.brotli = enable;
+        self
+    }
+
+    /// Accepts `zstd`.
+    pub fn zstd(mut self, enable: bool) -> AcceptEncoding {
+        self.zstd = enable;
+        self
+    }
+
+    /// Accepts `deflate`.
+    pub fn deflate(mut self, enable: bool) -> AcceptEncoding {
+        self.deflate = enable;
+        self
+    }
+}
+
+impl Default for AcceptEncoding {
+    fn default() -> AcceptEncoding {
+        AcceptEncoding::new()
+    }
 }
 
 #[cfg(test)]
@@ -554,10 +841,13 @@ mod tests {
                             gzip,
                             #[cfg(feature = "brotli")]
                             brotli,
+                            #[cfg(feature = "brotli")]
+                            brotli_params: BrotliParams::default(),
                             #[cfg(feature = "zstd")]
                             zstd,
                             #[cfg(feature = "deflate")]
                             deflate,
+                            weighted: false,
                         });
                     }
                 }
@@ -570,4 +860,61 @@ mod tests {
             assert_eq!(got, expected.as_str());
         }
     }
+
+    #[test]
+    fn weighted_accepts_are_ordered_by_preference() {
+        let mut accepts = Accepts::none();
+        accepts.set(AcceptEncoding::new());
+        let expected = [
+            (cfg!(feature = "zstd"), "zstd"),
+            (cfg!(feature = "brotli"), "br;q=0.9"),
+            (cfg!(feature = "gzip"), "gzip;q=0.8"),
+            (cfg!(feature = "deflate"), "deflate;q=0.7"),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, coding)| *coding)
+        .collect::<Vec<_>>()
+        .join(", ");
+        assert_eq!(accepts.as_str().unwrap_or(""), expected);
+
+        accepts.set(AcceptEncoding::none());
+        assert_eq!(accepts.as_str(), None);
+    }
+
+    #[test]
+    fn accepts_restricted_to_header() {
+        let codings = |accepts: Accepts| {
+            [
+                accepts.is_gzip(),
+                accepts.is_brotli(),
+                accepts.is_zstd(),
+                accepts.is_deflate(),
+            ]
+        };
+        let gzip = cfg!(feature = "gzip");
+        let br = cfg!(feature = "brotli");
+        let zstd = cfg!(feature = "zstd");
+        let deflate = cfg!(feature = "deflate");
+
+        let all = Accepts::default();
+        for (header, expected) in [
+            ("identity", [false; 4]),
+            ("gzip", [gzip, false, false, false]),
+            ("br;q=1.0, gzip;q=0", [false, br, false, false]),
+            ("ZSTD, deflate", [false, false, zstd, deflate]),
+            ("*", [gzip, br, zstd, deflate]),
+            ("compress, identity;q=0.5", [false; 4]),
+        ] {
+            assert_eq!(codings(all.restrict_to(header)), expected, "{header}");
+        }
+
+        // Codings the client doesn't decompress stay out.
+        let mut accepts = Accepts::none();
+        accepts.set(AcceptEncoding::new().gzip(false));
+        assert_eq!(
+            codings(accepts.restrict_to("gzip, br")),
+            [false, br, false, false]
+        );
+    }
 }
@@ -0,0 +1,203 @@
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+use log::trace;
+use tokio::sync::oneshot;
+
+use super::request::Request;
+use super::response::{Replay, Response};
+use crate::Method;
+
+/// The key function of `ClientBuilder::dedup_key`.
+pub(crate) type DedupKey = Arc<dyn Fn(&Request) -> Option<u64> + Send + Sync>;
+
+/// Collapses concurrent identical requests into one.
+///
+/// The first request with a given key is sent, the ones arriving while it
+/// is in flight wait for its response. If it has waiters, the response body
+/// is read in full and each waiter gets a copy.
+///
+/// When the first request fails, or is dropped, the waiters send their own
+/// request instead: errors are not shared.
+#[derive(Default)]
+pub(crate) struct Dedup {
+    in_flight: Mutex<HashMap<u64, Vec<oneshot::Sender<Arc<Replay>>>>>,
+    key_fn: Option<DedupKey>,
+}
+
+impl Dedup {
+    pub(crate) fn new(key_fn: Option<DedupKey>) -> Dedup {
+        Dedup {
+            in_flight: Mutex::default(),
+            key_fn,
+        }
+    }
+
+    pub(crate) fn key_fn(&self) -> Option<DedupKey> {
+        self.key_fn.clone()
+    }
+
+    /// The key `req` is deduplicated with, if it can be.
+    ///
+    /// With a key function, it decides. Otherwise, only `GET` and `HEAD`
+    /// requests without a body are, keyed on their method, URL and headers,
+    /// regardless of the headers order.
+    pub(crate) fn key(&self, req: &Request) -> Option<u64> {
+        if let Some(ref key_fn) = self.key_fn {
+            return key_fn(req);
+        }
+        if !matches!(*req.method(), Method::GET | Method::HEAD) || req.body().is_some() {
+            return None;
+        }
+
+        let mut headers = req
+            .headers()
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_bytes()))
+            .collect::<Vec<_>>();
+        headers.sort_unstable();
+
+        let mut hasher = DefaultHasher::new();
+        req.method().hash(&mut hasher);
+        req.url().as_str().hash(&mut hasher);
+        headers.hash(&mut hasher);
+        Some(hasher.finish())
+    }
+
+    pub(crate) async fn send<F, Fut>(
+        &self,
+        key: u64,
+        req: Request,
+        send: F,
+    ) -> crate::Result<Response>
+    where
+        F: FnOnce(Request) -> Fut,
+        Fut: Future<Output = crate::Result<Response>>,
+    {
+        let waiting = {
+            let mut in_flight = self.in_flight.lock().expect("dedup lock poisoned");
+            match in_flight.entry(key) {
+                Entry::Occupied(mut entry) => {
+                    let (tx, rx) = oneshot::channel();
+                    entry.get_mut().push(tx);
+                    Some(rx)
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(Vec::new());
+                    None
+                }
+            }
+        };
+
+        if let Some(rx) = waiting {
+            trace!("waiting for identical request to {}", req.url());
+            return match rx.await {
+                Ok(replay) => Ok(replay.to_response()),
+                Err(_) => send(req).await,
+            };
+        }
+
+        let mut leader = Leader {
+            dedup: self,
+            key,
+            done: false,
+        };
+        let res = send(req).await;
+        let waiters = leader.finish();
+        if waiters.is_empty() {
+            return res;
+        }
+
+        trace!("sharing response with {} identical requests", waiters.len());
+        let (res, replay) = res?.replay().await?;
+        let replay = Arc::new(replay);
+        for tx in waiters {
+            let _ = tx.send(replay.clone());
+        }
+        Ok(res)
+    }
+}
+
+/// Removes the in-flight entry of a request, even if it is dropped.
+struct Leader<'a> {
+    dedup: &'a Dedup,
+    key: u64,
+    done: bool,
+}
+
+impl Leader<'_> {
+    fn finish(&mut self) -> Vec<oneshot::Sender<Arc<Replay>>> {
+        self.done = true;
+        self.dedup
+            .in_flight
+            .lock()
+            .expect("dedup lock poisoned")
+            .remove(&self.key)
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            // dropping the senders wakes the waiters up
+            self.finish();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method, url: &str, headers: &[(&'static str, &'static str)]) -> Request {
+        let mut req = Request::new(method, url.parse().unwrap());
+        for &(name, value) in headers {
+            req.headers_mut()
+                .append(name, http::HeaderValue::from_static(value));
+        }
+        req
+    }
+
+    #[test]
+    fn key_ignores_header_order() {
+        let dedup = Dedup::default();
+        let a = request(Method::GET, "http://a.test/", &[("x-a", "1"), ("x-b", "2")]);
+        let b = request(Method::GET, "http://a.test/", &[("x-b", "2"), ("x-a", "1")]);
+        let c = request(Method::GET, "http://a.test/", &[("x-a", "1"), ("x-b", "3")]);
+
+        assert_eq!(dedup.key(&a), dedup.key(&b));
+        assert_ne!(dedup.key(&a), dedup.key(&c));
+    }
+
+    #[test]
+    fn mutating_requests_have_no_key() {
+        let dedup = Dedup::default();
+
+        for method in [Method::POST, Method::PUT, Method::PATCH, Method::DELETE] {
+            assert_eq!(dedup.key(&request(method, "http://a.test/", &[])), None);
+        }
+    }
+
+    #[test]
+    fn key_fn_overrides_default_key() {
+        let dedup = Dedup::new(Some(Arc::new(|req: &Request| {
+            req.url()
+                .query_pairs()
+                .find(|(name, _)| name == "id")
+                .and_then(|(_, id)| id.parse().ok())
+        })));
+
+        let a = request(Method::GET, "http://a.test/?id=1&t=1", &[]);
+        let b = request(Method::POST, "http://b.test/?t=2&id=1", &[("x-a", "1")]);
+        assert_eq!(dedup.key(&a), Some(1));
+        assert_eq!(dedup.key(&b), Some(1));
+        assert_eq!(
+            dedup.key(&request(Method::GET, "http://a.test/", &[])),
+            None
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::future::Future;
+use std::sync::Mutex;
+
+use boring::hash::{hash, MessageDigest};
+use http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use http::{HeaderMap, HeaderValue};
+use log::trace;
+use url::{Position, Url};
+
+use super::request::Request;
+use super::response::Response;
+use crate::{Method, StatusCode};
+
+/// The username and password of a request using Digest authentication.
+#[derive(Clone)]
+pub(crate) struct DigestCredentials {
+    pub(crate) username: String,
+    pub(crate) password: String,
+}
+
+/// The Digest challenges received by a client, keyed on the host and realm
+/// they are for, so requests to the same host can authenticate up front.
+#[derive(Default)]
+pub(crate) struct DigestCache {
+    challenges: Mutex<HashMap<(String, String), Challenge>>,
+}
+
+struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: Algorithm,
+    qop_auth: bool,
+    /// The requests sent with this nonce.
+    nc: u32,
+    /// When the challenge was received, the latest one is used up front.
+    seq: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Algorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess,
+}
+
+impl DigestCache {
+    /// Send `req` with `send`, answering a Digest challenge once.
+    ///
+    /// If a challenge was received for the host before, the request is sent
+    /// with its answer. A `401 Unauthorized` response with a new challenge is
+    /// answered by sending the request again, if its body can be cloned,
+    /// otherwise the response is returned as is.
+    pub(crate) async fn send<F, Fut>(
+        &self,
+        credentials: &DigestCredentials,
+        mut req: Request,
+        send: F,
+    ) -> crate::Result<Response>
+    where
+        F: Fn(Request) -> Fut,
+        Fut: Future<Output = crate::Result<Response>>,
+    {
+        if let Some(auth) = self.authorization(credentials, req.method(), req.url()) {
+            req.headers_mut().insert(AUTHORIZATION, auth);
+        }
+
+        let retry = req.try_clone();
+        let res = send(req).await?;
+        if res.status() != StatusCode::UNAUTHORIZED {
+            return Ok(res);
+        }
+
+        let mut retry = match retry {
+            Some(retry) if self.challenge(retry.url(), res.headers()) => retry,
+            _ => return Ok(res),
+        };
+        match self.authorization(credentials, retry.method(), retry.url()) {
+            Some(auth) => {
+                trace!("answering digest challenge for {}", retry.url());
+                retry.headers_mut().insert(AUTHORIZATION, auth);
+                send(retry).await
+            }
+            None => Ok(res),
+        }
+    }
+
+    /// Keep the best supported Digest challenge of `headers`, returning
+    /// whether there was one.
+    fn challenge(&self, url: &Url, headers: &HeaderMap) -> bool {
+        let challenge = headers
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(parse_challenges)
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("digest"))
+            .filter_map(|(_, params)| Challenge::from_params(params))
+            .max_by_key(|challenge| challenge.algorithm);
+
+        let Some(mut challenge) = challenge else {
+            return false;
+        };
+
+        let mut challenges = self.challenges.lock().expect("digest lock poisoned");
+        challenge.seq = challenges.values().map(|c| c.seq + 1).max().unwrap_or(0);
+        challenges.insert((authority(url), challenge.realm.clone()), challenge);
+        true
+    }
+
+    /// The `Authorization` header answering the latest challenge for the
+    /// host of `url`.
+    fn authorization(
+        &self,
+        credentials: &DigestCredentials,
+        method: &Method,
+        url: &Url,
+    ) -> Option<HeaderValue> {
+        let authority = authority(url);
+        let mut challenges = self.challenges.lock().expect("digest lock poisoned");
+        let challenge = challenges
+            .iter_mut()
+            .filter(|((host, _), _)| *host == authority)
+            .map(|(_, challenge)| challenge)
+            .max_by_key(|challenge| challenge.seq)?;
+
+        challenge.nc += 1;
+        let cnonce = format!("{:016x}", crate::util::fast_random());
+        let mut value = challenge.answer(credentials, method, url, &cnonce)?;
+        value.set_sensitive(true);
+        Some(value)
+    }
+}
+
+impl Challenge {
+    fn from_params(params: Vec<(String, String)>) -> Option<Challenge> {
+        let mut realm = None;
+        let mut nonce = None;
+        let mut opaque = None;
+        let mut algorithm = Algorithm::Md5;
+        let mut qop = None;
+
+        for (name, value) in params {
+            match name.to_ascii_lowercase().as_str() {
+                "realm" => realm = Some(value),
+                "nonce" => nonce = Some(value),
+                "opaque" => opaque = Some(value),
+                "qop" => qop = Some(value),
+                "algorithm" => {
+                    algorithm = match value.to_ascii_uppercase().as_str() {
+                        "MD5" => Algorithm::Md5,
+                        "MD5-SESS" => Algorithm::Md5Sess,
+                        "SHA-256" => Algorithm::Sha256,
+                        "SHA-256-SESS" => Algorithm::Sha256Sess,
+                        _ => return None,
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Only `auth` is supported, not `auth-int`.
+        let qop_auth = match qop {
+            Some(qop) if qop.split(',').any(|qop| qop.trim() == "auth") => true,
+            Some(_) => return None,
+            None => false,
+        };
+
+        Some(Challenge {
+            realm: realm?,
+            nonce: nonce?,
+            opaque,
+            algorithm,
+            qop_auth,
+            nc: 0,
+            seq: 0,
+        })
+    }
+
+    fn answer(
+        &self,
+        credentials: &DigestCredentials,
+        method: &Method,
+        url: &Url,
+        cnonce: &str,
+    ) -> Option<HeaderValue> {
+        let digest = |data: String| self.algorithm.hex(data.as_bytes());
+        let uri = &url[Position::BeforePath..Position::AfterQuery];
+        let nc = format!("{:08x}", self.nc);
+
+        let mut ha1 = digest(format!(
+            "{}:{}:{}",
+            credentials.username, self.realm, credentials.password
+        ))?;
+        if matches!(self.algorithm, Algorithm::Md5Sess | Algorithm::Sha256Sess) {
+            ha1 = digest(format!("{}:{}:{}", ha1, self.nonce, cnonce))?;
+        }
+        let ha2 = digest(format!("{}:{}", method, uri))?;
+        let response = if self.qop_auth {
+            digest(format!(
+                "{}:{}:{}:{}:auth:{}",
+                ha1, self.nonce, nc, cnonce, ha2
+            ))?
+        } else {
+            digest(format!("{}:{}:{}", ha1, self.nonce, ha2))?
+        };
+
+        let mut header = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"",
+            quote(&credentials.username),
+            quote(&self.realm),
+            quote(&self.nonce),
+            quote(uri),
+            self.algorithm.name(),
+            response
+        );
+        if let Some(ref opaque) = self.opaque {
+            let _ = write!(header, ", opaque=\"{}\"", quote(opaque));
+        }
+        if self.qop_auth {
+            let _ = write!(header, ", qop=auth, nc={}, cnonce=\"{}\"", nc, cnonce);
+        }
+        HeaderValue::from_str(&header).ok()
+    }
+}
+
+impl Algorithm {
+    fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5",
+            Algorithm::Md5Sess => "MD5-sess",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha256Sess => "SHA-256-sess",
+        }
+    }
+
+    fn hex(&self, data: &[u8]) -> Option<String> {
+        let md = match self {
+            Algorithm::Md5 | Algorithm::Md5Sess => MessageDigest::md5(),
+            Algorithm::Sha256 | Algorithm::Sha256Sess => MessageDigest::sha256(),
+        };
+        let digest = hash(md, data).ok()?;
+        Some(digest.iter().fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        }))
+    }
+}
+
+fn authority(url: &Url) -> String {
+    format!(
+        "{}:{}",
+        url.host_str().unwrap_or_default(),
+        url.port_or_known_default().unwrap_or_default()
+    )
+}
+
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Split a `WWW-Authenticate` value into its challenges, each a scheme and
+/// its parameters.
+fn parse_challenges(value: &str) -> Vec<(String, Vec<(String, String)>)> {
+    let mut challenges: Vec<(String, Vec<(String, String)>)> = Vec::new();
+
+    for item in split_quoted(value, ',') {
+        let item = item.trim();
+        if item.is_empty() {
+            continue;
+        }
+
+        // A new challenge starts with a scheme, followed by a space before
+        // its first parameter.
+        let param = match item.find(' ') {
+            Some(space) if !item[..space].contains('=') => {
+                challenges.push((item[..space].to_owned(), Vec::new()));
+                item[space..].trim()
+            }
+            None if !item.contains('=') => {
+                challenges.push((item.to_owned(), Vec::new()));
+                continue;
+            }
+            _ => item,
+        };
+
+        if let (Some((_, params)), Some((name, value))) =
+            (challenges.last_mut(), param.split_once('='))
+        {
+            params.push((name.trim().to_owned(), unquote(value.trim())));
+        }
+    }
+
+    challenges
+}
+
+/// Split `value` on `sep`, except between double quotes.
+fn split_quoted(value: &str, sep: char) -> Vec<&str> {
+    let mut items = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == sep && !quoted => {
+                items.push(&value[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    items.push(&value[start..]);
+    items
+}
+
+fn unquote(value: &str) -> String {
+    match value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        Some(value) => {
+            let mut unquoted = String::with_capacity(value.len());
+            let mut chars = value.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => unquoted.extend(chars.next()),
+                    c => unquoted.push(c),
+                }
+            }
+            unquoted
+        }
+        None => value.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_multiple_challenges() {
+        let challenges = parse_challenges(
+            r#"Basic realm="a, b", Digest realm="test@example.com", qop="auth,auth-int", nonce="abc", opaque="x\"y""#,
+        );
+        assert_eq!(challenges.len(), 2);
+        assert_eq!(challenges[0].0, "Basic");
+        assert_eq!(challenges[0].1, [("realm".to_owned(), "a, b".to_owned())]);
+        assert_eq!(challenges[1].0, "Digest");
+        assert_eq!(
+            challenges[1].1[1],
+            ("qop".to_owned(), "auth,auth-int".to_owned())
+        );
+        assert_eq!(challenges[1].1[3], ("opaque".to_owned(), "x\"y".to_owned()));
+    }
+
+    // The example of RFC 7616, section 3.9.1.
+    #[test]
+    fn answer_rfc_7616_example() {
+        let credentials = DigestCredentials {
+            username: "Mufasa".into(),
+            password: "Circle of Life".into(),
+        };
+        let url = Url::parse("http://www.example.org/dir/index.html").unwrap();
+        let cnonce = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+        let nonce = "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v";
+
+        let mut challenge = Challenge {
+            realm: "http-auth@example.org".into(),
+            nonce: nonce.into(),
+            opaque: Some("FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS".into()),
+            algorithm: Algorithm::Md5,
+            qop_auth: true,
+            nc: 1,
+            seq: 0,
+        };
+        let md5 = challenge
+            .answer(&credentials, &Method::GET, &url, cnonce)
+            .unwrap();
+        assert!(md5
+            .to_str()
+            .unwrap()
+            .contains("response=\"8ca523f5e9506fed4657c9700eebdbec\""));
+
+        challenge.algorithm = Algorithm::Sha256;
+        let sha256 = challenge
+            .answer(&credentials, &Method::GET, &url, cnonce)
+            .unwrap();
+        let sha256 = sha256.to_str().unwrap();
+        assert!(sha256.contains(
+            "response=\"753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1\""
+        ));
+        assert!(sha256.contains("algorithm=SHA-256"));
+        assert!(sha256.contains("qop=auth, nc=00000001"));
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+use super::http::ClientBuilder;
+#[cfg(feature = "boring-tls")]
+use crate::tls::Impersonate;
+use crate::{NoProxy, Proxy};
+
+/// The settings read by `ClientBuilder::from_env`.
+pub(super) struct EnvSettings {
+    #[cfg(feature = "boring-tls")]
+    impersonate: Option<Impersonate>,
+    user_agent: Option<String>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    http_version: Option<HttpVersion>,
+    proxy: Option<Proxy>,
+    #[cfg(feature = "boring-tls")]
+    accept_invalid_certs: Option<bool>,
+}
+
+enum HttpVersion {
+    Http1,
+    Http2,
+}
+
+impl EnvSettings {
+    pub(super) fn read(prefix: &str) -> crate::Result<EnvSettings> {
+        let env = Env { prefix };
+        let mut settings = EnvSettings {
+            #[cfg(feature = "boring-tls")]
+            impersonate: env.parse("IMPERSONATE")?,
+            user_agent: env.var("USER_AGENT").map(|(_, value)| value),
+            timeout: env.parse("TIMEOUT_MS")?.map(Duration::from_millis),
+            connect_timeout: env.parse("CONNECT_TIMEOUT_MS")?.map(Duration::from_millis),
+            pool_idle_timeout: env
+                .parse("POOL_IDLE_TIMEOUT_MS")?
+                .map(Duration::from_millis),
+            pool_max_idle_per_host: env.parse("POOL_MAX_IDLE_PER_HOST")?,
+            http_version: None,
+            proxy: None,
+            #[cfg(feature = "boring-tls")]
+            accept_invalid_certs: None,
+        };
+
+        if let Some((name, value)) = env.var("HTTP_VERSION") {
+            settings.http_version = Some(match value.to_ascii_lowercase().as_str() {
+                "1" | "1.1" | "http1" | "http/1.1" => HttpVersion::Http1,
+                "2" | "http2" | "h2" => HttpVersion::Http2,
+                _ => return Err(invalid(&name, &value, "expected 1 or 2")),
+            });
+        }
+
+        if let Some((name, value)) = env.var("PROXY") {
+            let proxy = Proxy::all(value.as_str()).map_err(|e| invalid(&name, &value, e))?;
+            let no_proxy = env
+                .var("NO_PROXY")
+                .and_then(|(_, value)| NoProxy::from_string(&value));
+            settings.proxy = Some(proxy.no_proxy(no_proxy));
+        }
+
+        #[cfg(feature = "boring-tls")]
+        if let Some((name, value)) = env.var("ACCEPT_INVALID_CERTS") {
+            settings.accept_invalid_certs = Some(match value.to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" => true,
+                "0" | "false" | "no" => false,
+                _ => return Err(invalid(&name, &value, "expected true or false")),
+            });
+        }
+
+        Ok(settings)
+    }
+
+    pub(super) fn apply(self, mut builder: ClientBuilder) -> ClientBuilder {
+        // Impersonating replaces the headers and TLS settings, so it goes
+        // first for the others to apply over it.
+        #[cfg(feature = "boring-tls")]
+        if let Some(impersonate) = self.impersonate {
+            builder = builder.impersonate(impersonate);
+        }
+        if let Some(user_agent) = self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        if let Some(max) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        match self.http_version {
+            Some(HttpVersion::Http1) => builder = builder.http1_only(),
+            Some(HttpVersion::Http2) => builder = builder.http2_only(),
+            None => {}
+        }
+        if let Some(proxy) = self.proxy {
+            builder = builder.proxy(proxy);
+        }
+        #[cfg(feature = "boring-tls")]
+        if let Some(accept_invalid_certs) = self.accept_invalid_certs {
+            builder = builder.danger_accept_invalid_certs(accept_invalid_certs);
+        }
+        builder
+    }
+}
+
+struct Env<'a> {
+    prefix: &'a str,
+}
+
+impl Env<'_> {
+    /// The name and trimmed value of the variable, unless unset or empty.
+    fn var(&self, name: &str) -> Option<(String, String)> {
+        let name = format!("{}_{}", self.prefix, name);
+        let value = std::env::var(&name).ok()?;
+        let value = value.trim();
+        (!value.is_empty()).then(|| (name, value.to_owned()))
+    }
+
+    fn parse<T>(&self, name: &str) -> crate::Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.var(name)
+            .map(|(name, value)| value.parse().map_err(|e| invalid(&name, &value, e)))
+            .transpose()
+    }
+}
+
+fn invalid(name: &str, value: &str, reason: impl Display) -> crate::Error {
+    crate::error::builder(format!(
+        "invalid value {:?} for environment variable {}: {}",
+        value, name, reason
+    ))
+}
@@ -0,0 +1,78 @@
+use std::future::Future;
+use std::pin::pin;
+use std::time::Duration;
+
+use futures_util::future::{select, Either};
+use log::trace;
+
+use super::request::Request;
+use super::response::Response;
+use crate::Method;
+
+/// Which of the requests of a hedged request got the response.
+///
+/// Added to the extensions of the responses to requests that were hedged,
+/// see [`ClientBuilder::hedge_requests`](crate::ClientBuilder::hedge_requests).
+///
+/// # Example
+///
+/// ```rust
+/// # async fn run(client: rquest::Client) -> Result<(), rquest::Error> {
+/// let res = client.get("https://example.com").send().await?;
+/// if let Some(rquest::HedgedResponse::Backup) = res.extensions().get() {
+///     println!("the backup request was faster");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HedgedResponse {
+    /// The response to the request sent first.
+    Original,
+    /// The response to the backup request, sent after the threshold.
+    Backup,
+}
+
+/// Whether `req` may be sent twice: it opted in or out with
+/// `RequestBuilder::hedge`, or its method is idempotent and safe.
+pub(super) fn is_hedged(req: &Request) -> bool {
+    req.hedge()
+        .unwrap_or_else(|| matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS))
+}
+
+/// Sends `req` with `send`, and a copy of it if no response came within
+/// `threshold`. The first response wins, the other request is dropped.
+pub(super) async fn send<F, Fut>(
+    threshold: Duration,
+    req: Request,
+    send: F,
+) -> crate::Result<Response>
+where
+    F: Fn(Request) -> Fut,
+    Fut: Future<Output = crate::Result<Response>>,
+{
+    let backup = req.try_clone();
+    let mut original = pin!(send(req));
+    let backup = match backup {
+        Some(backup) => backup,
+        None => return original.await,
+    };
+
+    if let Ok(res) = tokio::time::timeout(threshold, original.as_mut()).await {
+        return res;
+    }
+
+    trace!("no response after {:?}, sending backup request", threshold);
+    let backup = pin!(send(backup));
+    let (res, winner) = match select(original, backup).await {
+        // An error doesn't win: the other request may still succeed.
+        Either::Left((Err(_), backup)) => (backup.await, HedgedResponse::Backup),
+        Either::Left((res, _)) => (res, HedgedResponse::Original),
+        Either::Right((Err(_), original)) => (original.await, HedgedResponse::Original),
+        Either::Right((res, _)) => (res, HedgedResponse::Backup),
+    };
+    res.map(|mut res| {
+        res.extensions_mut().insert(winner);
+        res
+    })
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use super::request::Request;
+use super::response::Response;
+use crate::error::BoxError;
+
+type BoxFuture = Pin<Box<dyn Future<Output = crate::Result<Request>> + Send>>;
+
+#[derive(Clone)]
+enum RequestHook {
+    Sync(Arc<dyn Fn(&mut Request) -> crate::Result<()> + Send + Sync>),
+    Async(Arc<dyn Fn(Request) -> BoxFuture + Send + Sync>),
+}
+
+type ResponseHook = Arc<dyn Fn(&Request, &Response) + Send + Sync>;
+
+/// The hooks added with `ClientBuilder::with_request_hook` and friends.
+#[derive(Clone, Default)]
+pub(crate) struct Hooks {
+    request: Vec<RequestHook>,
+    response: Vec<ResponseHook>,
+}
+
+impl Hooks {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.request.is_empty() && self.response.is_empty()
+    }
+
+    pub(crate) fn push_request<F, E>(&mut self, hook: F)
+    where
+        F: Fn(&mut Request) -> Result<(), E> + Send + Sync + 'static,
+        E: Into<BoxError>,
+    {
+        self.request.push(RequestHook::Sync(Arc::new(move |req| {
+            hook(req).map_err(|e| crate::error::request_boxed(e.into()))
+        })));
+    }
+
+    pub(crate) fn push_async_request<F, Fut, E>(&mut self, hook: F)
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Request, E>> + Send + 'static,
+        E: Into<BoxError>,
+    {
+        self.request.push(RequestHook::Async(Arc::new(move |req| {
+            let fut = hook(req);
+            Box::pin(async move { fut.await.map_err(|e| crate::error::request_boxed(e.into())) })
+        })));
+    }
+
+    pub(crate) fn push_response<F>(&mut self, hook: F)
+    where
+        F: Fn(&Request, &Response) + Send + Sync + 'static,
+    {
+        self.response.push(Arc::new(hook));
+    }
+
+    /// Runs the request hooks in registration order.
+    pub(crate) async fn on_request(&self, mut req: Request) -> crate::Result<Request> {
+        for hook in &self.request {
+            match hook {
+                RequestHook::Sync(hook) => hook(&mut req)?,
+                RequestHook::Async(hook) => req = hook(req).await?,
+            }
+        }
+        Ok(req)
+    }
+
+    /// Runs the response hooks in registration order.
+    pub(crate) fn on_response(&self, req: &Request, res: &Response) {
+        for hook in &self.response {
+            hook(req, res);
+        }
+    }
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("request", &self.request.len())
+            .field("response", &self.response.len())
+            .finish()
+    }
+}
+
+/// A copy of `req` for the response hooks, without its body if that is a
+/// stream.
+pub(crate) fn snapshot(req: &Request) -> Request {
+    req.try_clone().unwrap_or_else(|| {
+        let mut copy = Request::new(req.method().clone(), req.url().clone());
+        *copy.headers_mut() = req.headers().clone();
+        *copy.version_mut() = req.version();
+        copy
+    })
+}
@@ -1,6 +1,6 @@
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::{collections::HashMap, convert::TryInto, net::SocketAddr};
 use std::{fmt, str};
 
@@ -8,39 +8,70 @@ use std::{fmt, str};
 use boring::{error::ErrorStack, x509::store::X509Store};
 use bytes::Bytes;
 use http::header::{
-    Entry, HeaderMap, HeaderValue, ACCEPT, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH,
-    CONTENT_TYPE, LOCATION, PROXY_AUTHORIZATION, RANGE, REFERER, TRANSFER_ENCODING, USER_AGENT,
+    Entry, HeaderMap, HeaderValue, ACCEPT, ACCEPT_ENCODING, CONNECTION, CONTENT_ENCODING,
+    CONTENT_LENGTH, CONTENT_TYPE, EXPECT, HOST, LOCATION, PROXY_AUTHORIZATION, RANGE, REFERER,
+    TRANSFER_ENCODING, USER_AGENT,
 };
-use http::uri::Scheme;
+use http::uri::{PathAndQuery, Scheme};
 use http::{HeaderName, Uri};
 use hyper::client::{HttpConnector, ResponseFuture as HyperResponseFuture};
 #[cfg(feature = "boring-tls")]
 use hyper::{PseudoOrder, SettingsOrder, StreamDependency, StreamId};
 use pin_project_lite::pin_project;
+use serde::Serialize;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use tokio::sync::oneshot;
 use tokio::time::Sleep;
+use tower_layer::Layer;
+use tower_service::Service as _;
 
-use super::decoder::Accepts;
-use super::request::{Request, RequestBuilder};
+use super::bearer::BearerAuth;
+use super::body::Progress;
+use super::decoder::{AcceptEncoding, Accepts};
+use super::dedup::{Dedup, DedupKey};
+#[cfg(feature = "boring-tls")]
+use super::digest::DigestCache;
+use super::env::EnvSettings;
+use super::hedge;
+use super::hooks::{self, Hooks};
+use super::rate_limit::{RateLimit, RateLimiter, RateLimits};
+use super::request::{Request, RequestBuilder, RequestTarget};
+use super::request_id::{self, RequestIdPolicy};
 use super::response::Response;
+use super::shutdown::{Shutdown, ShutdownReport};
+#[cfg(feature = "tracing")]
+use super::trace::{self, TracePath};
+use super::validation::{Validation, ValidationError, ValidationErrors, ValidationWarning};
 use super::Body;
-use crate::connect::Connector;
+use crate::auth::TokenProvider;
+use crate::conn_limit::ConnLimits;
+use crate::connect::{ConnectOverrides, Connector};
 #[cfg(feature = "cookies")]
 use crate::cookie;
 #[cfg(feature = "hickory-dns")]
 use crate::dns::hickory::HickoryDnsResolver;
 use crate::dns::{gai::GaiResolver, DnsResolverWithOverrides, DynResolver, Resolve};
 use crate::error;
-use crate::into_url::{expect_uri, try_uri};
+use crate::h2_stats::H2Handle;
+use crate::into_url::{self, expect_uri, try_uri, with_path_and_query, IdnaPolicy};
+use crate::metrics::{MetricsObserver, Recorder, Written};
+use crate::middleware::ClientService;
 use crate::redirect::{self, remove_sensitive_headers};
+use crate::retry::{self, RetryBudget};
+use crate::sign::{self, Payload};
 #[cfg(feature = "boring-tls")]
-use crate::tls::{self, BoringTlsConnector, Impersonate, ImpersonateSettings, TlsSettings};
-use crate::{IntoUrl, Method, Proxy, StatusCode, Url};
+use crate::tls::{
+    self, Http2Settings, Impersonate, ImpersonateSettings, MaybeLazyTlsConnector, TlsSettings,
+};
+use crate::{IntoUrl, Method, Proxy, StatusCode, Url, Version};
 #[cfg(feature = "hickory-dns")]
 use hickory_resolver::config::LookupIpStrategy;
-use log::{debug, trace};
+use log::{debug, trace, warn};
+
+/// The default of [`ClientBuilder::max_response_header_size`].
+const DEFAULT_MAX_RESPONSE_HEADER_SIZE: usize = 256 * 1024;
 
 /// An asynchronous `Client` to make Requests with.
 ///
@@ -54,10 +85,16 @@ use log::{debug, trace};
 /// You do **not** have to wrap the `Client` in an [`Rc`] or [`Arc`] to **reuse** it,
 /// because it already uses an [`Arc`] internally.
 ///
+/// Cloning a `Client` is cheap: the clones share its connection pool, cookie
+/// store and configuration. To share the pool with a different timeout or
+/// redirect policy, use [`to_builder`](Client::to_builder) instead.
+///
 /// [`Rc`]: std::rc::Rc
 #[derive(Clone)]
 pub struct Client {
     inner: Arc<ClientRef>,
+    /// The layers added with `ClientBuilder::layer`, around this client.
+    service: Option<Arc<Mutex<ClientService>>>,
 }
 
 /// A `ClientBuilder` can be used to create a `Client` with custom configuration.
@@ -78,23 +115,63 @@ pub enum HttpVersionPref {
     All,
 }
 
+type BoxLayer = Arc<dyn Fn(ClientService) -> ClientService + Send + Sync>;
+
 struct Config {
     // NOTE: When adding a new field, update `fmt::Debug for ClientBuilder`
     accepts: Accepts,
     headers: HeaderMap,
     headers_order: Option<&'static [HeaderName]>,
+    base_url: Option<Url>,
+    idna: IdnaPolicy,
+    default_query: Vec<(String, String)>,
     connect_timeout: Option<Duration>,
     connection_verbose: bool,
     pool_idle_timeout: Option<Duration>,
+    max_connections_per_host: Option<usize>,
+    max_total_connections: Option<usize>,
+    pool_checkout_timeout: Option<Duration>,
+    http2_settings_ack_timeout: Option<Duration>,
     pool_max_idle_per_host: usize,
+    max_response_headers: usize,
+    max_response_header_size: Option<usize>,
     tcp_keepalive: Option<Duration>,
     proxies: Vec<Proxy>,
     auto_sys_proxy: bool,
-    redirect_policy: redirect::Policy,
+    redirect_policy: Arc<redirect::Policy>,
+    retry_policy: Option<retry::Policy>,
+    retry_budget: Option<(f64, u32)>,
+    respect_retry_after: Option<Duration>,
+    hedge_threshold: Option<Duration>,
+    rate_limits: RateLimits,
+    deduplicate_requests: bool,
+    dedup_key: Option<DedupKey>,
+    bearer_auth_provider: Option<Arc<dyn TokenProvider>>,
+    #[cfg(feature = "cache")]
+    http_cache: Option<crate::cache::CacheConfig>,
     referer: bool,
     timeout: Option<Duration>,
     local_address_ipv6: Option<Ipv6Addr>,
     local_address_ipv4: Option<Ipv4Addr>,
+    proxy_protocol: Option<(SocketAddr, SocketAddr)>,
+    reuse_proxy_tunnels: bool,
+    #[cfg(feature = "chaos")]
+    chaos: Option<crate::testing::chaos::ChaosLayer>,
+    #[cfg(feature = "mock")]
+    transport: Option<Arc<dyn crate::testing::mock::MockTransport>>,
+    #[cfg(feature = "tracing")]
+    logging: Option<crate::middleware::LoggingMiddleware>,
+    #[cfg(feature = "tracing")]
+    trace_path: Option<TracePath>,
+    layers: Vec<BoxLayer>,
+    hooks: Hooks,
+    metrics_observer: Option<Arc<dyn MetricsObserver>>,
+    request_id: Option<RequestIdPolicy>,
+    download_progress: Option<Progress>,
+    max_response_body_size: Option<u64>,
+    strict_content_length: bool,
+    expect_continue_threshold: Option<u64>,
+    expect_continue_timeout: Duration,
     http1_title_case_headers: bool,
     #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
     interface: Option<String>,
@@ -112,7 +189,25 @@ struct Config {
     #[cfg(feature = "boring-tls")]
     tls_info: bool,
     #[cfg(feature = "boring-tls")]
+    protocol_info: bool,
+    #[cfg(feature = "boring-tls")]
+    lazy_tls_init: bool,
+    #[cfg(feature = "boring-tls")]
+    adaptive_http_version: bool,
+    #[cfg(feature = "boring-tls")]
+    alt_svc: bool,
+    #[cfg(feature = "boring-tls")]
     tls: TlsSettings,
+    #[cfg(feature = "websocket")]
+    ws_behavior: crate::WsBehaviorSettings,
+    #[cfg(feature = "websocket")]
+    max_ws_connections: usize,
+    /// The client this builder was made from by `Client::to_builder`.
+    base: Option<Arc<ClientRef>>,
+    /// Whether the connections of `base` are used, until a setting of the
+    /// connections is changed.
+    share_connections: bool,
+    proxies_changed: bool,
 }
 
 impl Default for ClientBuilder {
@@ -132,20 +227,58 @@ impl ClientBuilder {
                 accepts: Accepts::default(),
                 headers: HeaderMap::with_capacity(1),
                 headers_order: None,
+                base_url: None,
+                idna: IdnaPolicy::default(),
+                default_query: Vec::new(),
                 connect_timeout: None,
                 connection_verbose: false,
                 pool_idle_timeout: Some(Duration::from_secs(90)),
+                max_connections_per_host: None,
+                max_total_connections: None,
+                pool_checkout_timeout: None,
+                http2_settings_ack_timeout: Some(Duration::from_secs(10)),
                 pool_max_idle_per_host: usize::MAX,
+                max_response_headers: 100,
+                max_response_header_size: None,
                 // TODO: Re-enable default duration once hyper's HttpConnector is fixed
                 // to no longer error when an option fails.
                 tcp_keepalive: None,
                 proxies: Vec::new(),
                 auto_sys_proxy: true,
-                redirect_policy: redirect::Policy::none(),
+                redirect_policy: Arc::new(redirect::Policy::none()),
+                retry_policy: None,
+                retry_budget: None,
+                respect_retry_after: None,
+                hedge_threshold: None,
+                rate_limits: RateLimits::default(),
+                deduplicate_requests: false,
+                dedup_key: None,
+                bearer_auth_provider: None,
+                #[cfg(feature = "cache")]
+                http_cache: None,
                 referer: true,
                 timeout: None,
                 local_address_ipv6: None,
                 local_address_ipv4: None,
+                proxy_protocol: None,
+                reuse_proxy_tunnels: true,
+                #[cfg(feature = "chaos")]
+                chaos: None,
+                #[cfg(feature = "mock")]
+                transport: None,
+                #[cfg(feature = "tracing")]
+                logging: None,
+                #[cfg(feature = "tracing")]
+                trace_path: None,
+                layers: Vec::new(),
+                hooks: Hooks::default(),
+                metrics_observer: None,
+                request_id: None,
+                download_progress: None,
+                max_response_body_size: None,
+                strict_content_length: false,
+                expect_continue_threshold: None,
+                expect_continue_timeout: Duration::from_secs(1),
                 #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
                 interface: None,
                 nodelay: true,
@@ -161,111 +294,364 @@ impl ClientBuilder {
                 #[cfg(feature = "boring-tls")]
                 tls_info: false,
                 #[cfg(feature = "boring-tls")]
+                protocol_info: false,
+                #[cfg(feature = "boring-tls")]
+                lazy_tls_init: false,
+                #[cfg(feature = "boring-tls")]
+                adaptive_http_version: false,
+                #[cfg(feature = "boring-tls")]
+                alt_svc: false,
+                #[cfg(feature = "boring-tls")]
                 tls: Default::default(),
+                #[cfg(feature = "websocket")]
+                ws_behavior: Default::default(),
+                #[cfg(feature = "websocket")]
+                max_ws_connections: 4,
                 http1_title_case_headers: true,
+                base: None,
+                share_connections: false,
+                proxies_changed: false,
             },
         }
     }
 
+    /// Applies the settings of the environment variables named after
+    /// `prefix` over the current ones.
+    ///
+    /// With a prefix of `RQUEST`, these are:
+    ///
+    /// - `RQUEST_IMPERSONATE`: the browser to [`impersonate`](ClientBuilder::impersonate),
+    ///   by the name of its [`Impersonate`](crate::tls::Impersonate) variant, such as `Chrome130`, or
+    ///   its version string, such as `chrome_130`, in any case,
+    /// - `RQUEST_USER_AGENT`: the [`user_agent`](ClientBuilder::user_agent),
+    /// - `RQUEST_TIMEOUT_MS`: the [`timeout`](ClientBuilder::timeout), in
+    ///   milliseconds,
+    /// - `RQUEST_CONNECT_TIMEOUT_MS`: the
+    ///   [`connect_timeout`](ClientBuilder::connect_timeout), in milliseconds,
+    /// - `RQUEST_POOL_IDLE_TIMEOUT_MS`: the
+    ///   [`pool_idle_timeout`](ClientBuilder::pool_idle_timeout), in
+    ///   milliseconds,
+    /// - `RQUEST_POOL_MAX_IDLE_PER_HOST`: the
+    ///   [`pool_max_idle_per_host`](ClientBuilder::pool_max_idle_per_host),
+    /// - `RQUEST_HTTP_VERSION`: `1` for [`http1_only`](ClientBuilder::http1_only),
+    ///   or `2` for [`http2_only`](ClientBuilder::http2_only),
+    /// - `RQUEST_PROXY`: the URL of a proxy for all requests, added as with
+    ///   [`Proxy::all`], which `RQUEST_NO_PROXY` lists the exceptions of,
+    ///   as parsed by [`NoProxy::from_string`](crate::NoProxy::from_string),
+    /// - `RQUEST_ACCEPT_INVALID_CERTS`: `true` or `false`, for
+    ///   [`danger_accept_invalid_certs`](ClientBuilder::danger_accept_invalid_certs).
+    ///
+    /// `RQUEST_IMPERSONATE` applies first, so that the others apply over
+    /// the headers and TLS settings it sets. Unset and empty variables are
+    /// ignored. A value that cannot be parsed makes [`build`](ClientBuilder::build)
+    /// fail with an error naming its variable.
+    ///
+    /// ```rust
+    /// # fn doc() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::builder()
+    ///     .timeout(std::time::Duration::from_secs(30))
+    ///     .from_env("RQUEST")
+    ///     .build()?;
+    /// # drop(client);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_env(mut self, prefix: &str) -> ClientBuilder {
+        match EnvSettings::read(prefix) {
+            Ok(settings) => settings.apply(self),
+            Err(err) => {
+                self.config.error = Some(err);
+                self
+            }
+        }
+    }
+
+    /// Checks the configuration for settings that conflict or have no
+    /// effect.
+    ///
+    /// Returns the warnings if there are no errors. [`build`](Self::build)
+    /// runs the same checks, failing on the errors and logging the
+    /// warnings.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// let warnings = rquest::Client::builder()
+    ///     .connect_timeout(Duration::from_secs(10))
+    ///     .timeout(Duration::from_secs(5))
+    ///     .validate()
+    ///     .unwrap();
+    /// assert_eq!(warnings.len(), 1);
+    /// ```
+    pub fn validate(&self) -> Result<Vec<ValidationWarning>, Vec<ValidationError>> {
+        let mut validation = Validation::default();
+        validation.check_timeouts(self.config.connect_timeout, self.config.timeout);
+        #[cfg(feature = "boring-tls")]
+        validation.check_tls(&self.config.tls);
+
+        if validation.errors.is_empty() {
+            Ok(validation.warnings)
+        } else {
+            Err(validation.errors)
+        }
+    }
+
     /// Returns a `Client` that uses this `ClientBuilder` configuration.
     ///
     /// # Errors
     ///
-    /// This method fails if a TLS backend cannot be initialized, or the resolver
-    /// cannot load the system configuration.
+    /// This method fails if a TLS backend cannot be initialized, the resolver
+    /// cannot load the system configuration, or [`validate`](Self::validate)
+    /// finds errors, which
+    /// [`Error::validation_errors`](crate::Error::validation_errors) returns.
     pub fn build(self) -> crate::Result<Client> {
+        if self.config.error.is_none() {
+            match self.validate() {
+                Ok(warnings) => {
+                    for warning in warnings {
+                        warn!("client configuration: {}", warning);
+                    }
+                }
+                Err(errors) => return Err(crate::error::builder(ValidationErrors(errors))),
+            }
+        }
+
         let mut config = self.config;
 
         if let Some(err) = config.error {
             return Err(err);
         }
 
-        let mut proxies = config.proxies;
-        if config.auto_sys_proxy {
-            proxies.push(Proxy::system());
-        }
-        let proxies = Arc::new(proxies);
+        let base = config.base.take();
+        let shared = base.as_ref().filter(|_| config.share_connections);
+        #[cfg(feature = "boring-tls")]
+        let alt_svc = match shared {
+            Some(base) => base.alt_svc.clone(),
+            None => config.alt_svc.then(crate::AltSvcCache::default),
+        };
+        let (hyper, connect_to, proxies_maybe_http_auth) = if let Some(base) = shared {
+            let mut hyper = base.hyper.clone();
+            let mut proxies_maybe_http_auth = base.proxies_maybe_http_auth;
+            if config.proxies_changed {
+                // The pooled connections were opened through the proxies of
+                // `base`, so they are not reused by these.
+                let mut connector = Connector::clone(&base.hyper);
+                connector.set_proxies(&config.proxies);
+                proxies_maybe_http_auth = config.proxies.iter().any(|p| p.maybe_has_http_auth());
+                hyper = base.connect_to.builder.build(connector);
+            }
+            (hyper, base.connect_to.clone(), proxies_maybe_http_auth)
+        } else {
+            let mut proxies = config.proxies;
+            if config.auto_sys_proxy {
+                proxies.push(Proxy::system());
+            }
+            let proxies = Arc::new(proxies);
 
-        let proxies_maybe_http_auth = proxies.iter().any(|p| p.maybe_has_http_auth());
+            let proxies_maybe_http_auth = proxies.iter().any(|p| p.maybe_has_http_auth());
 
-        let mut connector = {
-            #[cfg(feature = "boring-tls")]
-            fn user_agent(headers: &HeaderMap) -> Option<HeaderValue> {
-                headers.get(USER_AGENT).cloned()
-            }
+            let mut connector = {
+                #[cfg(feature = "boring-tls")]
+                fn user_agent(headers: &HeaderMap) -> Option<HeaderValue> {
+                    headers.get(USER_AGENT).cloned()
+                }
 
-            let mut resolver: Arc<dyn Resolve> = match config.hickory_dns {
-                false => Arc::new(GaiResolver::new()),
-                #[cfg(feature = "hickory-dns")]
-                true => Arc::new(HickoryDnsResolver::new(config.dns_strategy)?),
-                #[cfg(not(feature = "hickory-dns"))]
-                true => unreachable!("hickory-dns shouldn't be enabled unless the feature is"),
+                let mut resolver: Arc<dyn Resolve> = match config.hickory_dns {
+                    false => Arc::new(GaiResolver::new()),
+                    #[cfg(feature = "hickory-dns")]
+                    true => Arc::new(HickoryDnsResolver::new(config.dns_strategy)?),
+                    #[cfg(not(feature = "hickory-dns"))]
+                    true => unreachable!("hickory-dns shouldn't be enabled unless the feature is"),
+                };
+                if let Some(dns_resolver) = config.dns_resolver {
+                    resolver = dns_resolver;
+                }
+                if !config.dns_overrides.is_empty() {
+                    resolver = Arc::new(DnsResolverWithOverrides::new(
+                        resolver,
+                        config.dns_overrides,
+                    ));
+                }
+                let mut http = HttpConnector::new_with_resolver(DynResolver::new(resolver));
+                http.set_connect_timeout(config.connect_timeout);
+
+                #[cfg(feature = "boring-tls")]
+                {
+                    let version_cache = (config.adaptive_http_version
+                        && matches!(config.tls.http_version_pref, HttpVersionPref::All))
+                    .then(|| Arc::new(tls::AdaptiveVersionCache::default()));
+                    let alt_svc_protocols: &'static [&'static str] =
+                        match config.tls.http_version_pref {
+                            HttpVersionPref::Http1 => &["http/1.1"],
+                            HttpVersionPref::Http2 => &["h2"],
+                            HttpVersionPref::All => &["h2", "http/1.1"],
+                        };
+
+                    if let Some(extensions) = config.tls.custom_extensions.as_deref() {
+                        tls::custom_ext::validate(extensions)?;
+                    }
+
+                    let mut connector = Connector::new_boring_tls(
+                        http,
+                        MaybeLazyTlsConnector::new(config.tls, config.lazy_tls_init)?,
+                        proxies,
+                        user_agent(&config.headers),
+                        config.local_address_ipv4,
+                        config.local_address_ipv6,
+                        #[cfg(any(
+                            target_os = "android",
+                            target_os = "fuchsia",
+                            target_os = "linux"
+                        ))]
+                        config.interface.as_deref(),
+                        config.nodelay,
+                        config.tls_info,
+                    );
+                    connector.set_version_cache(version_cache);
+                    connector.set_alt_svc(alt_svc.clone(), alt_svc_protocols);
+                    connector.set_protocol_info(config.protocol_info);
+                    connector
+                }
+
+                #[cfg(not(feature = "boring-tls"))]
+                {
+                    Connector::new(
+                        http,
+                        proxies.clone(),
+                        config.local_address_ipv4,
+                        config.local_address_ipv6,
+                        config.nodelay,
+                    )
+                }
             };
-            if let Some(dns_resolver) = config.dns_resolver {
-                resolver = dns_resolver;
-            }
-            if !config.dns_overrides.is_empty() {
-                resolver = Arc::new(DnsResolverWithOverrides::new(
-                    resolver,
-                    config.dns_overrides,
-                ));
-            }
-            let mut http = HttpConnector::new_with_resolver(DynResolver::new(resolver));
-            http.set_connect_timeout(config.connect_timeout);
 
-            #[cfg(feature = "boring-tls")]
-            {
-                Connector::new_boring_tls(
-                    http,
-                    BoringTlsConnector::new(config.tls)?,
-                    proxies,
-                    user_agent(&config.headers),
-                    config.local_address_ipv4,
-                    config.local_address_ipv6,
-                    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
-                    config.interface.as_deref(),
-                    config.nodelay,
-                    config.tls_info,
-                )
+            connector.set_timeout(config.connect_timeout);
+            connector.set_verbose(config.connection_verbose);
+            connector.set_keepalive(config.tcp_keepalive);
+            connector.set_proxy_protocol(config.proxy_protocol);
+            connector.set_reuse_proxy_tunnels(config.reuse_proxy_tunnels);
+            connector.set_limits(ConnLimits::new(
+                config.max_connections_per_host,
+                config.max_total_connections,
+                config.pool_checkout_timeout,
+            ));
+            connector.set_settings_ack_timeout(config.http2_settings_ack_timeout);
+            #[cfg(feature = "chaos")]
+            connector.set_chaos(config.chaos);
+
+            let max_header_size = config
+                .max_response_header_size
+                .unwrap_or(DEFAULT_MAX_RESPONSE_HEADER_SIZE);
+            config
+                .builder
+                .pool_idle_timeout(config.pool_idle_timeout)
+                .pool_max_idle_per_host(config.pool_max_idle_per_host)
+                .http1_title_case_headers(config.http1_title_case_headers)
+                // hyper won't buffer less than 8 KiB.
+                .http1_max_buf_size(max_header_size.max(8 * 1024));
+            if let Some(max) = config.max_response_header_size {
+                config
+                    .builder
+                    .http2_max_header_list_size(u32::try_from(max).unwrap_or(u32::MAX));
             }
 
-            #[cfg(not(feature = "boring-tls"))]
-            {
-                Connector::new(
-                    http,
-                    proxies.clone(),
-                    config.local_address_ipv4,
-                    config.local_address_ipv6,
-                    config.nodelay,
-                )
-            }
+            (
+                config.builder.build(connector.clone()),
+                Arc::new(ConnectToClients {
+                    builder: config.builder,
+                    connector,
+                    clients: Mutex::default(),
+                }),
+                proxies_maybe_http_auth,
+            )
         };
 
-        connector.set_timeout(config.connect_timeout);
-        connector.set_verbose(config.connection_verbose);
-        connector.set_keepalive(config.tcp_keepalive);
-
-        config
-            .builder
-            .pool_idle_timeout(config.pool_idle_timeout)
-            .pool_max_idle_per_host(config.pool_max_idle_per_host)
-            .http1_title_case_headers(config.http1_title_case_headers);
-
-        Ok(Client {
+        let mut client = Client {
             inner: Arc::new(ClientRef {
                 accepts: config.accepts,
                 #[cfg(feature = "cookies")]
                 cookie_store: config.cookie_store,
-                hyper: config.builder.build(connector),
+                hyper,
+                connect_to,
                 headers: config.headers,
                 headers_order: config.headers_order,
-                redirect_policy: Arc::new(config.redirect_policy),
+                base_url: config.base_url,
+                idna: config.idna,
+                default_query: config.default_query,
+                redirect_policy: config.redirect_policy,
+                retry_policy: config.retry_policy.map(Arc::new),
+                retry_budget: config
+                    .retry_budget
+                    .map(|(ratio, min_retries)| Arc::new(RetryBudget::new(ratio, min_retries))),
+                respect_retry_after: config.respect_retry_after,
+                hedge_threshold: config.hedge_threshold,
+                rate_limiter: Arc::new(RateLimiter::new(config.rate_limits)),
+                dedup: config
+                    .deduplicate_requests
+                    .then(|| Arc::new(Dedup::new(config.dedup_key))),
+                bearer: config
+                    .bearer_auth_provider
+                    .map(|provider| Arc::new(BearerAuth::new(provider)))
+                    .or_else(|| base.as_ref().and_then(|base| base.bearer.clone())),
+                #[cfg(feature = "cache")]
+                cache: config
+                    .http_cache
+                    .map(|config| Arc::new(crate::cache::HttpCache::new(config)))
+                    .or_else(|| base.as_ref().and_then(|base| base.cache.clone())),
+                #[cfg(feature = "boring-tls")]
+                digest: base
+                    .as_ref()
+                    .map(|base| base.digest.clone())
+                    .unwrap_or_default(),
                 referer: config.referer,
                 request_timeout: config.timeout,
                 https_only: config.https_only,
+                #[cfg(feature = "boring-tls")]
+                alt_svc,
+                #[cfg(feature = "websocket")]
+                ws_behavior: config.ws_behavior,
+                #[cfg(feature = "websocket")]
+                ws_pool: Arc::new(crate::client::websocket::WsPoolState::new(
+                    config.max_ws_connections,
+                )),
+                #[cfg(feature = "tracing")]
+                logging: config.logging.map(Arc::new),
+                #[cfg(feature = "tracing")]
+                trace_path: config.trace_path,
+                hooks: (!config.hooks.is_empty()).then(|| Arc::new(config.hooks)),
+                metrics_observer: config.metrics_observer,
+                request_id: config.request_id,
+                download_progress: config.download_progress,
+                max_response_body_size: config.max_response_body_size,
+                strict_content_length: config.strict_content_length,
+                expect_continue_threshold: config.expect_continue_threshold,
+                expect_continue_timeout: config.expect_continue_timeout,
+                max_response_headers: config.max_response_headers,
+                max_response_header_size: config.max_response_header_size,
+                #[cfg(feature = "mock")]
+                transport: config.transport,
+                layers: config.layers,
+                shutdown: Arc::default(),
                 proxies_maybe_http_auth,
             }),
-        })
+            service: None,
+        };
+
+        if !client.inner.layers.is_empty() {
+            let service = client
+                .inner
+                .layers
+                .iter()
+                .rev()
+                .fold(ClientService::sender(client.clone()), |service, layer| {
+                    layer(service)
+                });
+            client.service = Some(Arc::new(Mutex::new(service)));
+        }
+
+        Ok(client)
     }
 
     /// Sets the necessary values to mimic the specified impersonate version.
@@ -283,14 +669,71 @@ impl ClientBuilder {
     }
 
     /// Use the preconfigured TLS settings.
+    ///
+    /// This is the same as calling [`tls_settings`](ClientBuilder::tls_settings)
+    /// and [`http2_settings`](ClientBuilder::http2_settings) with the parts
+    /// of `settings`, and applying its headers.
     #[cfg(feature = "boring-tls")]
     pub fn use_preconfigured_tls(self, settings: ImpersonateSettings) -> ClientBuilder {
         self.apply_tls_settings(settings, true)
     }
 
+    /// Use these TLS settings, in place of the current ones.
+    ///
+    /// # Errors
+    ///
+    /// [`build`](ClientBuilder::build) fails if
+    /// [`TlsSettings::validate`] does.
+    #[cfg(feature = "boring-tls")]
+    pub fn tls_settings(mut self, settings: TlsSettings) -> ClientBuilder {
+        self.config.share_connections = false;
+        if let Err(err) = settings.validate() {
+            self.config.error = Some(crate::error::builder(err));
+            return self;
+        }
+        self.config.tls = settings;
+        self
+    }
+
+    /// Use these HTTP/2 settings.
+    ///
+    /// Each setting left unset in `settings` is reset to its default.
+    #[cfg(feature = "boring-tls")]
+    pub fn http2_settings(self, settings: Http2Settings) -> ClientBuilder {
+        let http2_headers_priority = settings
+            .headers_priority
+            .map(|(a, b, c)| StreamDependency::new(StreamId::from(a), b, c));
+
+        self.http2_initial_stream_window_size(settings.initial_stream_window_size)
+            .http2_initial_connection_window_size(settings.initial_connection_window_size)
+            .http2_max_concurrent_streams(settings.max_concurrent_streams)
+            .http2_max_header_list_size(settings.max_header_list_size)
+            .http2_header_table_size(settings.header_table_size)
+            .http2_enable_push(settings.enable_push)
+            .http2_max_frame_size(settings.max_frame_size)
+            .http2_headers_priority(http2_headers_priority)
+            .http2_headers_pseudo_order(settings.headers_pseudo_order)
+            .http2_settings_order(settings.settings_order)
+            .http2_unknown_setting8(settings.unknown_setting8)
+            .http2_unknown_setting9(settings.unknown_setting9)
+            .http2_settings_ack_timeout(
+                settings
+                    .settings_ack_timeout
+                    .or(Some(Duration::from_secs(10))),
+            )
+    }
+
     /// Private helper to configure impersonation.
     #[cfg(feature = "boring-tls")]
-    fn configure_impersonate(self, impersonate: Impersonate, with_headers: bool) -> ClientBuilder {
+    fn configure_impersonate(
+        mut self,
+        impersonate: Impersonate,
+        with_headers: bool,
+    ) -> ClientBuilder {
+        #[cfg(feature = "websocket")]
+        {
+            self.config.ws_behavior = impersonate.into();
+        }
         if let Ok(settings) = tls::tls_settings(impersonate) {
             return self.apply_tls_settings(settings, with_headers);
         }
@@ -299,40 +742,23 @@ impl ClientBuilder {
 
     /// Apply the given TLS settings and header function.
     #[cfg(feature = "boring-tls")]
-    fn apply_tls_settings(
-        mut self,
-        settings: ImpersonateSettings,
-        set_headers: bool,
-    ) -> ClientBuilder {
+    fn apply_tls_settings(self, settings: ImpersonateSettings, set_headers: bool) -> ClientBuilder {
+        let mut builder = self.tls_settings(settings.tls);
+        if builder.config.error.is_some() {
+            return builder;
+        }
         if set_headers {
             if let Some(headers) = settings.headers {
-                (headers)(&mut self.config.headers);
+                (headers)(&mut builder.config.headers);
             }
         }
-        self.config.tls = settings.tls;
-        let http2_headers_priority = settings
-            .http2
-            .headers_priority
-            .map(|(a, b, c)| StreamDependency::new(StreamId::from(a), b, c));
-
-        // Set the http2 version preference
-        self.http2_initial_stream_window_size(settings.http2.initial_stream_window_size)
-            .http2_initial_connection_window_size(settings.http2.initial_connection_window_size)
-            .http2_max_concurrent_streams(settings.http2.max_concurrent_streams)
-            .http2_max_header_list_size(settings.http2.max_header_list_size)
-            .http2_header_table_size(settings.http2.header_table_size)
-            .http2_enable_push(settings.http2.enable_push)
-            .http2_max_frame_size(settings.http2.max_frame_size)
-            .http2_headers_priority(http2_headers_priority)
-            .http2_headers_pseudo_order(settings.http2.headers_pseudo_order)
-            .http2_settings_order(settings.http2.settings_order)
-            .http2_unknown_setting8(settings.http2.unknown_setting8)
-            .http2_unknown_setting9(settings.http2.unknown_setting9)
+        builder.http2_settings(settings.http2)
     }
 
     /// Enable Encrypted Client Hello (Secure SNI)
     #[cfg(feature = "boring-tls")]
     pub fn enable_ech_grease(mut self, enabled: bool) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.tls.enable_ech_grease = enabled;
         self
     }
@@ -340,6 +766,7 @@ impl ClientBuilder {
     /// Enable TLS permute_extensions
     #[cfg(feature = "boring-tls")]
     pub fn permute_extensions(mut self, enabled: bool) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.tls.permute_extensions = Some(enabled);
         self
     }
@@ -347,6 +774,7 @@ impl ClientBuilder {
     /// Enable TLS pre_shared_key
     #[cfg(feature = "boring-tls")]
     pub fn pre_shared_key(mut self, enabled: bool) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.tls.pre_shared_key = enabled;
         self
     }
@@ -451,6 +879,79 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets a base URL that relative URLs given to the request methods are
+    /// resolved against.
+    ///
+    /// The path of a relative URL is appended to the path of the base URL,
+    /// whether or not either has a slash between them. Absolute URLs are
+    /// used as is.
+    ///
+    /// ```rust
+    /// # async fn doc() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = rquest::Client::builder()
+    ///     .base_url("https://api.example.com/v2".parse()?)
+    ///     .build()?;
+    /// // Sends a request to `https://api.example.com/v2/users`.
+    /// let res = client.get("/users").send().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn base_url(mut self, url: Url) -> ClientBuilder {
+        self.config.base_url = Some(url);
+        self
+    }
+
+    /// Adds query parameters to the URL of every request, unless it already
+    /// has a parameter with the same key.
+    ///
+    /// The parameters are serialized the same way as by
+    /// [`RequestBuilder::query`](crate::RequestBuilder::query).
+    ///
+    /// # Errors
+    ///
+    /// Building the client fails if `query` cannot be serialized into a query
+    /// string.
+    pub fn default_query<T: Serialize + ?Sized>(mut self, query: &T) -> ClientBuilder {
+        match serde_urlencoded::to_string(query) {
+            Ok(query) => self
+                .config
+                .default_query
+                .extend(url::form_urlencoded::parse(query.as_bytes()).into_owned()),
+            Err(err) => self.config.error = Some(crate::error::builder(err)),
+        }
+        self
+    }
+
+    /// Returns the base URL set with `base_url`.
+    pub(crate) fn base_url_ref(&self) -> Option<&Url> {
+        self.config.base_url.as_ref()
+    }
+
+    /// Sets how non-ASCII host names are converted to ASCII.
+    ///
+    /// Defaults to [`IdnaPolicy::NonTransitional`]. The converted host name
+    /// is used for DNS, TLS SNI and the `Host` header alike.
+    ///
+    /// ```rust
+    /// # fn doc() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::builder()
+    ///     .idna(rquest::IdnaPolicy::Reject)
+    ///     .build()?;
+    /// let err = client.get("https://bücher.example").build().unwrap_err();
+    /// assert!(err.is_idna());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn idna(mut self, policy: IdnaPolicy) -> ClientBuilder {
+        self.config.idna = policy;
+        self
+    }
+
+    /// Returns the policy set with `idna`.
+    pub(crate) fn idna_policy(&self) -> IdnaPolicy {
+        self.config.idna
+    }
+
     /// Default accpet
     pub fn default_accpet(mut self) -> ClientBuilder {
         self.config
@@ -546,6 +1047,43 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets the size of the chunks brotli response bodies are decoded into.
+    ///
+    /// Bodies are decoded as they are read, so a decoder holds one chunk
+    /// and the brotli window at most, however large the body. Larger
+    /// chunks decode faster.
+    ///
+    /// Default is 8 KiB.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `brotli` feature to be enabled
+    #[cfg(feature = "brotli")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "brotli")))]
+    pub fn brotli_buffer_size(mut self, size: usize) -> ClientBuilder {
+        self.config.accepts.brotli_params.buffer_size = size;
+        self
+    }
+
+    /// Whether brotli response bodies may use the large-window extension.
+    ///
+    /// Standard brotli windows go up to 16 MiB (`window_bits` of 24), which
+    /// are always decoded. Large windows go up to 1 GiB, and are only
+    /// understood by a decoder opted into them; it then holds as much
+    /// memory for a body sent with one.
+    ///
+    /// Default is `false`.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `brotli` feature to be enabled
+    #[cfg(feature = "brotli")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "brotli")))]
+    pub fn brotli_large_window(mut self, enable: bool) -> ClientBuilder {
+        self.config.accepts.brotli_params.large_window = enable;
+        self
+    }
+
     /// Enable auto zstd decompression by checking the `Content-Encoding` response header.
     ///
     /// If auto zstd decompression is turned on:
@@ -660,13 +1198,321 @@ impl ClientBuilder {
         }
     }
 
+    /// Set the content codings to accept and decompress.
+    ///
+    /// This replaces the [`gzip`](ClientBuilder::gzip),
+    /// [`brotli`](ClientBuilder::brotli), [`zstd`](ClientBuilder::zstd) and
+    /// [`deflate`](ClientBuilder::deflate) options. The `Accept-Encoding`
+    /// header then lists the codings with quality values by order of
+    /// preference, e.g. `zstd, br;q=0.9, gzip;q=0.8, deflate;q=0.7`, unless
+    /// the request already has one.
+    ///
+    /// ```rust
+    /// use rquest::AcceptEncoding;
+    ///
+    /// # fn run() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::builder()
+    ///     .accept_encoding(AcceptEncoding::new().deflate(false))
+    ///     .build()?;
+    /// # drop(client);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn accept_encoding(mut self, encoding: AcceptEncoding) -> ClientBuilder {
+        self.config.accepts.set(encoding);
+        self
+    }
+
     // Redirect options
 
     /// Set a `RedirectPolicy` for this client.
     ///
     /// Default will follow redirects up to a maximum of 10.
     pub fn redirect(mut self, policy: redirect::Policy) -> ClientBuilder {
-        self.config.redirect_policy = policy;
+        self.config.redirect_policy = Arc::new(policy);
+        self
+    }
+
+    /// Set a `retry::Policy` for this client.
+    ///
+    /// Default does not retry, apart from HTTP/2 requests refused by a
+    /// graceful shutdown.
+    pub fn retry(mut self, policy: retry::Policy) -> ClientBuilder {
+        self.config.retry_policy = Some(policy);
+        self
+    }
+
+    /// Retry `429 Too Many Requests` and `503 Service Unavailable` responses
+    /// once, after the delay of their `Retry-After` header.
+    ///
+    /// A response whose delay is longer than `max_wait`, or whose request
+    /// body can't be replayed, is returned as is. Responses carry a
+    /// [`retry::RetryAfterWaits`] extension telling whether the request was
+    /// retried. The time spent waiting doesn't count in the request
+    /// timeout.
+    ///
+    /// With a [`retry`](ClientBuilder::retry) policy also set, the policy
+    /// takes precedence: the statuses it retries are left to it, and this
+    /// applies to each of its attempts otherwise.
+    ///
+    /// Default is `None`.
+    pub fn respect_retry_after<D>(mut self, max_wait: D) -> ClientBuilder
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.config.respect_retry_after = max_wait.into();
+        self
+    }
+
+    /// Send a backup of each request still without a response after
+    /// `threshold`, to cut the latency of the slowest requests.
+    ///
+    /// The first response of the two is used, and the other request is
+    /// cancelled. A request that fails doesn't win while the other one may
+    /// still succeed. Responses to requests that were hedged carry a
+    /// [`HedgedResponse`](crate::HedgedResponse) extension telling which
+    /// request won.
+    ///
+    /// As the server may process both requests, only `GET`, `HEAD` and
+    /// `OPTIONS` requests are hedged, unless opted in or out with
+    /// [`RequestBuilder::hedge`](crate::RequestBuilder::hedge). Requests
+    /// with a streaming body are never hedged.
+    ///
+    /// Default is no hedging.
+    pub fn hedge_requests(mut self, threshold: Duration) -> ClientBuilder {
+        self.config.hedge_threshold = Some(threshold);
+        self
+    }
+
+    /// Limit the rate requests are sent at.
+    ///
+    /// Requests over the limit wait their turn before being sent, or fail
+    /// with an error for which
+    /// [`Error::is_rate_limited`](crate::Error::is_rate_limited) is true
+    /// past the `max_queue` or `max_wait` of the limit. A
+    /// [`RateLimit::global`] limit and a [`RateLimit::per_host`] one can be
+    /// set together, a request then keeps to both. The limits are shared
+    /// by the clones of the client, and apply to each attempt of a retried
+    /// request. The time spent waiting doesn't count in the request
+    /// timeout.
+    ///
+    /// Default is no limit.
+    pub fn rate_limit(mut self, limit: RateLimit) -> ClientBuilder {
+        self.config.rate_limits.set(limit);
+        self
+    }
+
+    /// Limit the rate requests are sent at to the hosts matching `pattern`,
+    /// in place of the [`RateLimit::per_host`] limit set with
+    /// [`rate_limit`](ClientBuilder::rate_limit).
+    ///
+    /// `pattern` is a host name, or `*.example.com` to match the subdomains
+    /// of `example.com`. The first pattern set that matches a host applies.
+    /// With a [`RateLimit::global`] limit, the hosts matching the pattern
+    /// share it.
+    pub fn rate_limit_for_host(mut self, pattern: &str, limit: RateLimit) -> ClientBuilder {
+        self.config.rate_limits.set_for_host(pattern, limit);
+        self
+    }
+
+    /// Limit the retries of the [`retry`](ClientBuilder::retry) policy, so
+    /// that they don't add to the load of a failing server.
+    ///
+    /// A request is only retried while the retries in flight are fewer than
+    /// `total_ratio` times the requests in flight, or than `min_retries`,
+    /// counted across the client and its clones. A request that is not
+    /// retried because of it fails right away with an error for which
+    /// [`Error::is_retry_budget_exhausted`](crate::Error::is_retry_budget_exhausted)
+    /// is true.
+    ///
+    /// Default is no budget.
+    pub fn retry_budget(mut self, total_ratio: f64, min_retries: u32) -> ClientBuilder {
+        self.config.retry_budget = Some((total_ratio, min_retries));
+        self
+    }
+
+    /// Set a `MetricsObserver` told about the timing of every request.
+    ///
+    /// The observer is called exactly once per request, including when it
+    /// fails or is dropped, once the response body has been read. The
+    /// `RequestMetrics` as of when the response headers arrived are also
+    /// added to the response extensions.
+    ///
+    /// See the [`metrics`](crate::metrics) module for an example.
+    pub fn metrics_observer(mut self, observer: Arc<dyn MetricsObserver>) -> ClientBuilder {
+        self.config.metrics_observer = Some(observer);
+        self
+    }
+
+    /// Send every request with an id, to correlate it with the logs of the
+    /// server.
+    ///
+    /// The id is sent in the header of the policy, unless the request
+    /// already has one, whose value is then its id. Redirects and retries
+    /// of a request are sent with its id. The id is added to the extensions
+    /// of the response as a [`RequestId`](crate::RequestId), and is in
+    /// [`Error::request_id`](crate::Error::request_id) when the request
+    /// fails.
+    ///
+    /// Default is no id.
+    pub fn request_id(mut self, policy: RequestIdPolicy) -> ClientBuilder {
+        self.config.request_id = Some(policy);
+        self
+    }
+
+    /// Calls `callback` as a response body is read, with the bytes read so
+    /// far and the total length of the body.
+    ///
+    /// The total comes from the `Content-Length` header of the response, if
+    /// any. The bytes are counted as received, before any decompression.
+    /// A request can set its own callback with
+    /// [`RequestBuilder::on_download_progress`](crate::RequestBuilder::on_download_progress).
+    pub fn on_download_progress<F>(mut self, callback: F) -> ClientBuilder
+    where
+        F: Fn(u64, Option<u64>) + Send + Sync + 'static,
+    {
+        self.config.download_progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Limits the size of response bodies, in bytes after decompression.
+    ///
+    /// Reading a body longer than `max` fails with an error for which
+    /// [`Error::is_body_too_large`](crate::Error::is_body_too_large) is true.
+    /// A body whose `Content-Length` is over the limit fails before any of it
+    /// is read, otherwise it fails as soon as the limit is crossed. This also
+    /// applies to `Response::chunk` and `Response::bytes_stream`.
+    ///
+    /// A request can set its own limit with
+    /// [`RequestBuilder::max_response_body_size`](crate::RequestBuilder::max_response_body_size).
+    /// There is no limit by default.
+    pub fn max_response_body_size(mut self, max: u64) -> ClientBuilder {
+        self.config.max_response_body_size = Some(max);
+        self
+    }
+
+    /// Fail reading response bodies that end early.
+    ///
+    /// A body that ends before its `Content-Length`, or a chunked body whose
+    /// connection closes before the last chunk, fails with an error for
+    /// which [`Error::is_incomplete_body`](crate::Error::is_incomplete_body)
+    /// is true, and [`Error::content_length_mismatch`](crate::Error::content_length_mismatch)
+    /// tells the bytes expected and received. This applies to
+    /// `Response::bytes`, `Response::text` and the streaming methods alike.
+    /// The lengths are those on the wire, before decompression.
+    ///
+    /// Default is `false`.
+    pub fn strict_content_length(mut self, enabled: bool) -> ClientBuilder {
+        self.config.strict_content_length = enabled;
+        self
+    }
+
+    /// Send bodies of at least `threshold` bytes with `Expect: 100-continue`.
+    ///
+    /// The headers of such a request are sent first, and its body is held
+    /// back until the server had time to answer them, so a server rejecting
+    /// the request, for instance with `401 Unauthorized`, does so before the
+    /// body is uploaded. The final response is then returned as is, and the
+    /// body is never sent.
+    ///
+    /// Only bodies of known length are considered. A request can opt in or
+    /// out with
+    /// [`RequestBuilder::expect_continue`](crate::RequestBuilder::expect_continue).
+    /// Requests made for HTTP/2 with
+    /// [`RequestBuilder::version`](crate::RequestBuilder::version) are not
+    /// affected.
+    ///
+    /// Default is `None`, no request uses `Expect: 100-continue`.
+    pub fn expect_continue_threshold(mut self, threshold: u64) -> ClientBuilder {
+        self.config.expect_continue_threshold = Some(threshold);
+        self
+    }
+
+    /// Sets how long a body sent with `Expect: 100-continue` is held back
+    /// for a final response.
+    ///
+    /// The interim `100 Continue` response is not reported by the
+    /// connection, so the body is sent once `timeout` elapses without a
+    /// final response.
+    ///
+    /// Default is 1 second.
+    pub fn expect_continue_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.config.expect_continue_timeout = timeout;
+        self
+    }
+
+    /// Collapse concurrent identical `GET` and `HEAD` requests into one.
+    ///
+    /// A request with the same method, URL and headers as one in flight,
+    /// and no body, waits for the response of the first one instead of
+    /// being sent. That response's body is then read in full, and each
+    /// waiter gets a copy of it. Errors are not shared: if the first
+    /// request fails, the others are sent on their own.
+    ///
+    /// Default is `false`.
+    pub fn deduplicate_requests(mut self, enabled: bool) -> ClientBuilder {
+        self.config.deduplicate_requests = enabled;
+        self
+    }
+
+    /// Collapse concurrent requests with the same key into one, computing
+    /// the key of each request with `key`.
+    ///
+    /// This enables [`deduplicate_requests`](ClientBuilder::deduplicate_requests)
+    /// and replaces its notion of identical requests: a request for which
+    /// `key` returns `Some` waits for the response of the one in flight
+    /// with the same key, if any, whatever its method, URL, headers or
+    /// body. A request for which it returns `None` is always sent.
+    ///
+    /// ```rust
+    /// # fn doc() -> Result<(), rquest::Error> {
+    /// // Product lookups are identical whatever their tracking parameters.
+    /// let client = rquest::Client::builder()
+    ///     .dedup_key(|req| {
+    ///         let id = req.url().query_pairs().find(|(name, _)| name == "id")?.1;
+    ///         id.parse().ok()
+    ///     })
+    ///     .build()?;
+    /// # drop(client);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dedup_key<F>(mut self, key: F) -> ClientBuilder
+    where
+        F: Fn(&Request) -> Option<u64> + Send + Sync + 'static,
+    {
+        self.config.deduplicate_requests = true;
+        self.config.dedup_key = Some(Arc::new(key));
+        self
+    }
+
+    /// Authenticate requests with bearer tokens from `provider`.
+    ///
+    /// The token is kept until it expires, and concurrent requests wait for
+    /// a single call to the provider when a new one is needed. A
+    /// `401 Unauthorized` response makes the client ask the provider for a
+    /// new token with [`TokenProvider::refresh`], and send the request again
+    /// once with it, if its body can be cloned.
+    ///
+    /// Requests that set their own `Authorization` header, or use Digest or
+    /// NTLM authentication, are sent as they are.
+    pub fn bearer_auth_provider(mut self, provider: Arc<dyn TokenProvider>) -> ClientBuilder {
+        self.config.bearer_auth_provider = Some(provider);
+        self
+    }
+
+    /// Cache the responses to `GET` and `HEAD` requests, as configured by
+    /// `config`.
+    ///
+    /// See the [`cache`](crate::cache) module.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `cache` feature to be enabled.
+    #[cfg(feature = "cache")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cache")))]
+    pub fn http_cache(mut self, config: crate::cache::CacheConfig) -> ClientBuilder {
+        self.config.http_cache = Some(config);
         self
     }
 
@@ -688,6 +1534,67 @@ impl ClientBuilder {
     pub fn proxy(mut self, proxy: Proxy) -> ClientBuilder {
         self.config.proxies.push(proxy);
         self.config.auto_sys_proxy = false;
+        self.config.proxies_changed = true;
+        self
+    }
+
+    /// Picks the proxy of each new connection with `selector`.
+    ///
+    /// `selector` is called with the target URL, reduced to its scheme, host
+    /// and port, and returns the `Proxy` to connect through, or `None` to
+    /// connect directly. The returned `Proxy` applies as if it was added
+    /// with `proxy`, along with its authentication and `no_proxy` list, so a
+    /// `Proxy::https` is not used for an `http` target.
+    ///
+    /// Connections are reused by the requests to the same host, through the
+    /// proxy they were opened with. To pick a proxy for every request, such
+    /// as when rotating through a pool, disable pooling with
+    /// `pool_max_idle_per_host(0)`.
+    ///
+    /// ```rust
+    /// # fn doc() -> Result<(), rquest::Error> {
+    /// let pool = ["http://proxy-a:3128", "http://proxy-b:3128"];
+    /// let next = std::sync::atomic::AtomicUsize::new(0);
+    /// let client = rquest::Client::builder()
+    ///     .proxy_selector(move |url| {
+    ///         if url.host_str() == Some("localhost") {
+    ///             return None;
+    ///         }
+    ///         let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    ///         rquest::Proxy::all(pool[i % pool.len()]).ok()
+    ///     })
+    ///     .pool_max_idle_per_host(0)
+    ///     .build()?;
+    /// # drop(client);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// This is added to the list of proxies like `proxy`, which the
+    /// selector falls through to when it returns `None`, and disables the
+    /// automatic usage of the "system" proxy.
+    pub fn proxy_selector<F>(self, selector: F) -> ClientBuilder
+    where
+        F: Fn(&Url) -> Option<Proxy> + Send + Sync + 'static,
+    {
+        self.proxy(Proxy::selector(selector))
+    }
+
+    /// Whether connections tunneled through an HTTP proxy with `CONNECT` are
+    /// kept in the pool for later requests.
+    ///
+    /// Default is `true`: like browsers, requests to the same HTTPS host
+    /// reuse the tunnel and the TLS connection through it. When disabled,
+    /// each tunnel is closed once its request is done, and the next request
+    /// opens a new one, which the proxy may route differently.
+    ///
+    /// Plain HTTP requests are not tunneled, they are sent to the proxy on
+    /// connections pooled like direct ones.
+    pub fn reuse_proxy_tunnels(mut self, enabled: bool) -> ClientBuilder {
+        self.config.share_connections = false;
+        self.config.reuse_proxy_tunnels = enabled;
         self
     }
 
@@ -701,6 +1608,7 @@ impl ClientBuilder {
     pub fn no_proxy(mut self) -> ClientBuilder {
         self.config.proxies.clear();
         self.config.auto_sys_proxy = false;
+        self.config.proxies_changed = true;
         self
     }
 
@@ -726,6 +1634,7 @@ impl ClientBuilder {
     /// This **requires** the futures be executed in a tokio runtime with
     /// a tokio timer enabled.
     pub fn connect_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.connect_timeout = Some(timeout);
         self
     }
@@ -737,6 +1646,7 @@ impl ClientBuilder {
     ///
     /// [log]: https://crates.io/crates/log
     pub fn connection_verbose(mut self, verbose: bool) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.connection_verbose = verbose;
         self
     }
@@ -752,18 +1662,128 @@ impl ClientBuilder {
     where
         D: Into<Option<Duration>>,
     {
+        self.config.share_connections = false;
         self.config.pool_idle_timeout = val.into();
         self
     }
 
+    /// Sets the maximum number of connections open to each host, idle or
+    /// not.
+    ///
+    /// Requests needing a new connection past the limit wait for one to be
+    /// returned to the pool or closed, up to the
+    /// [`pool_checkout_timeout`](ClientBuilder::pool_checkout_timeout).
+    /// HTTP/2 connections count once however many streams they carry.
+    ///
+    /// Default is no limit.
+    pub fn max_connections_per_host(mut self, max: usize) -> ClientBuilder {
+        self.config.share_connections = false;
+        self.config.max_connections_per_host = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of connections open to all hosts, idle or
+    /// not.
+    ///
+    /// Requests needing a new connection past the limit wait as with
+    /// [`max_connections_per_host`](ClientBuilder::max_connections_per_host).
+    /// An idle connection to another host is not closed to make room, set a
+    /// [`pool_idle_timeout`](ClientBuilder::pool_idle_timeout) to have them
+    /// closed.
+    ///
+    /// Default is no limit.
+    pub fn max_total_connections(mut self, max: usize) -> ClientBuilder {
+        self.config.share_connections = false;
+        self.config.max_total_connections = Some(max);
+        self
+    }
+
+    /// Sets how long a request waits for a connection under the
+    /// [`max_connections_per_host`](ClientBuilder::max_connections_per_host)
+    /// and [`max_total_connections`](ClientBuilder::max_total_connections)
+    /// limits, before failing with an error for which
+    /// [`Error::is_pool_exhausted`](crate::Error::is_pool_exhausted) is
+    /// true.
+    ///
+    /// The wait is not part of the
+    /// [`connect_timeout`](ClientBuilder::connect_timeout).
+    ///
+    /// Default is `None`, waiting as long as the request
+    /// [`timeout`](ClientBuilder::timeout) allows.
+    pub fn pool_checkout_timeout<D>(mut self, timeout: D) -> ClientBuilder
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.config.share_connections = false;
+        self.config.pool_checkout_timeout = timeout.into();
+        self
+    }
+
+    /// Sets the maximum number of WebSocket connections to a host kept by
+    /// the [`websocket_pool`](Client::websocket_pool), checked out or idle.
+    ///
+    /// Checking out a connection past the limit waits for one to be
+    /// returned to the pool. The connections opened by
+    /// [`Client::websocket`] are not counted.
+    ///
+    /// Default is 4. A limit of 0 is taken as 1.
+    #[cfg(feature = "websocket")]
+    pub fn max_ws_connections(mut self, max: usize) -> ClientBuilder {
+        self.config.max_ws_connections = max;
+        self
+    }
+
     /// Sets the maximum idle connection per host allowed in the pool.
     pub fn pool_max_idle_per_host(mut self, max: usize) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.pool_max_idle_per_host = max;
         self
     }
 
+    /// Sets the maximum number of headers a response may have.
+    ///
+    /// A response with more headers fails with an error for which
+    /// [`Error::is_headers_too_large`](crate::Error::is_headers_too_large) is
+    /// true. The headers are counted once the response head is received.
+    ///
+    /// HTTP/1 responses can't have more than 100 headers whatever the limit:
+    /// hyper fails to parse the ones with more, with the same error. A
+    /// larger limit only applies to HTTP/2 responses.
+    ///
+    /// Default is 100.
+    pub fn max_response_headers(mut self, max: usize) -> ClientBuilder {
+        self.config.share_connections = false;
+        self.config.max_response_headers = max;
+        self
+    }
+
+    /// Sets the maximum total size of the headers of a response, counting
+    /// each header as its name and value plus 32 bytes, as HTTP/2 does.
+    ///
+    /// A response with larger headers fails with an error for which
+    /// [`Error::is_headers_too_large`](crate::Error::is_headers_too_large) is
+    /// true. HTTP/1 connections stop reading a response head once it
+    /// reaches this size, or 8 KiB if that is more. Setting it also sends it
+    /// to HTTP/2 servers as `SETTINGS_MAX_HEADER_LIST_SIZE`, in place of
+    /// [`http2_max_header_list_size`](ClientBuilder::http2_max_header_list_size),
+    /// so that the headers are dropped as they are decoded. h2 resets the
+    /// stream of a response over the limit with `PROTOCOL_ERROR`, which
+    /// `is_headers_too_large` does not tell apart from other protocol errors.
+    ///
+    /// Left unset, the `SETTINGS` of the connection, and so its fingerprint,
+    /// stay the same, and the headers of HTTP/2 responses are only measured
+    /// once received.
+    ///
+    /// Default is 256 KiB.
+    pub fn max_response_header_size(mut self, max: usize) -> ClientBuilder {
+        self.config.share_connections = false;
+        self.config.max_response_header_size = Some(max);
+        self
+    }
+
     /// Send headers as title case instead of lowercase.
     pub fn http1_title_case_headers(mut self, enabled: bool) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.http1_title_case_headers = enabled;
         self
     }
@@ -777,6 +1797,7 @@ impl ClientBuilder {
         mut self,
         value: bool,
     ) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config
             .builder
             .http1_allow_obsolete_multiline_headers_in_responses(value);
@@ -785,6 +1806,7 @@ impl ClientBuilder {
 
     /// Sets whether invalid header lines should be silently ignored in HTTP/1 responses.
     pub fn http1_ignore_invalid_headers_in_responses(mut self, value: bool) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config
             .builder
             .http1_ignore_invalid_headers_in_responses(value);
@@ -800,6 +1822,7 @@ impl ClientBuilder {
         mut self,
         value: bool,
     ) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config
             .builder
             .http1_allow_spaces_after_header_name_in_responses(value);
@@ -809,6 +1832,7 @@ impl ClientBuilder {
     /// Only use HTTP/1.
     /// Default is Http/1.
     pub fn http1_only(mut self) -> ClientBuilder {
+        self.config.share_connections = false;
         #[cfg(feature = "boring-tls")]
         {
             self.config.tls.http_version_pref = HttpVersionPref::Http1;
@@ -820,12 +1844,14 @@ impl ClientBuilder {
 
     /// Allow HTTP/0.9 responses
     pub fn http09_responses(mut self) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.builder.http09_responses(true);
         self
     }
 
     /// Only use HTTP/2.
     pub fn http2_only(mut self) -> ClientBuilder {
+        self.config.share_connections = false;
         #[cfg(feature = "boring-tls")]
         {
             self.config.tls.http_version_pref = HttpVersionPref::Http2;
@@ -839,6 +1865,7 @@ impl ClientBuilder {
     ///
     /// Default is currently 65,535 but may change internally to optimize for common uses.
     pub fn http2_initial_stream_window_size(mut self, sz: impl Into<Option<u32>>) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config
             .builder
             .http2_initial_stream_window_size(sz.into());
@@ -852,6 +1879,7 @@ impl ClientBuilder {
         mut self,
         sz: impl Into<Option<u32>>,
     ) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config
             .builder
             .http2_initial_connection_window_size(sz.into());
@@ -863,6 +1891,7 @@ impl ClientBuilder {
     /// Enabling this will override the limits set in `http2_initial_stream_window_size` and
     /// `http2_initial_connection_window_size`.
     pub fn http2_adaptive_window(mut self, enabled: bool) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.builder.http2_adaptive_window(enabled);
         self
     }
@@ -871,6 +1900,7 @@ impl ClientBuilder {
     ///
     /// Default is currently 16,384 but may change internally to optimize for common uses.
     pub fn http2_max_frame_size(mut self, sz: impl Into<Option<u32>>) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.builder.http2_max_frame_size(sz.into());
         self
     }
@@ -879,6 +1909,7 @@ impl ClientBuilder {
     ///
     /// Passing `None` will do nothing.
     pub fn http2_max_concurrent_streams(mut self, sz: impl Into<Option<u32>>) -> ClientBuilder {
+        self.config.share_connections = false;
         if let Some(max) = sz.into() {
             self.config.builder.http2_max_concurrent_streams(max);
         }
@@ -889,6 +1920,7 @@ impl ClientBuilder {
     ///
     /// Passing `None` will do nothing.
     pub fn http2_max_header_list_size(mut self, sz: impl Into<Option<u32>>) -> ClientBuilder {
+        self.config.share_connections = false;
         if let Some(sz) = sz.into() {
             self.config.builder.http2_max_header_list_size(sz);
         }
@@ -899,6 +1931,7 @@ impl ClientBuilder {
     ///
     /// Passing `None` will do nothing.
     pub fn http2_enable_push(mut self, sz: impl Into<Option<bool>>) -> ClientBuilder {
+        self.config.share_connections = false;
         if let Some(sz) = sz.into() {
             self.config.builder.http2_enable_push(sz);
         }
@@ -907,6 +1940,7 @@ impl ClientBuilder {
 
     /// Http2 unknown_setting8
     pub fn http2_unknown_setting8(mut self, sz: impl Into<Option<bool>>) -> ClientBuilder {
+        self.config.share_connections = false;
         if let Some(sz) = sz.into() {
             self.config.builder.http2_unknown_setting8(sz);
         }
@@ -915,6 +1949,7 @@ impl ClientBuilder {
 
     /// Http2 unknown_setting9
     pub fn http2_unknown_setting9(mut self, sz: impl Into<Option<bool>>) -> ClientBuilder {
+        self.config.share_connections = false;
         if let Some(sz) = sz.into() {
             self.config.builder.http2_unknown_setting9(sz);
         }
@@ -925,6 +1960,7 @@ impl ClientBuilder {
     ///
     /// Passing `None` will do nothing.
     pub fn http2_header_table_size(mut self, sz: impl Into<Option<u32>>) -> ClientBuilder {
+        self.config.share_connections = false;
         if let Some(sz) = sz.into() {
             self.config.builder.http2_header_table_size(sz);
         }
@@ -934,11 +1970,21 @@ impl ClientBuilder {
     /// Sets the pseudo header order for HTTP2.
     /// This is an array of 4 elements, each element is a `PseudoOrder` enum.
     /// Default is `None`.
+    ///
+    /// Each pseudo header must appear once, otherwise building the client
+    /// fails.
     pub fn http2_headers_pseudo_order(
         mut self,
         order: impl Into<Option<[PseudoOrder; 4]>>,
     ) -> ClientBuilder {
-        self.config.builder.http2_headers_pseudo_order(order.into());
+        self.config.share_connections = false;
+        let order = order.into();
+        #[cfg(feature = "boring-tls")]
+        if let Some(Err(err)) = order.map(|order| tls::check_pseudo_order(&order)) {
+            self.config.error = Some(crate::error::builder(err));
+            return self;
+        }
+        self.config.builder.http2_headers_pseudo_order(order);
         self
     }
 
@@ -948,6 +1994,7 @@ impl ClientBuilder {
         mut self,
         priority: impl Into<Option<StreamDependency>>,
     ) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.builder.http2_headers_priority(priority.into());
         self
     }
@@ -959,6 +2006,7 @@ impl ClientBuilder {
         mut self,
         order: impl Into<Option<[SettingsOrder; 8]>>,
     ) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.builder.http2_settings_order(order.into());
         self
     }
@@ -971,6 +2019,7 @@ impl ClientBuilder {
         mut self,
         interval: impl Into<Option<Duration>>,
     ) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config
             .builder
             .http2_keep_alive_interval(interval.into());
@@ -983,6 +2032,7 @@ impl ClientBuilder {
     /// Does nothing if `http2_keep_alive_interval` is disabled.
     /// Default is currently disabled.
     pub fn http2_keep_alive_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.builder.http2_keep_alive_timeout(timeout);
         self
     }
@@ -994,16 +2044,36 @@ impl ClientBuilder {
     /// Does nothing if `http2_keep_alive_interval` is disabled.
     /// Default is `false`.
     pub fn http2_keep_alive_while_idle(mut self, enabled: bool) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.builder.http2_keep_alive_while_idle(enabled);
         self
     }
 
+    /// Sets how long a new HTTP/2 connection waits for the server to
+    /// acknowledge its initial `SETTINGS` frame.
+    ///
+    /// A connection whose settings are not acknowledged in time is closed,
+    /// failing its requests with an error for which
+    /// [`Error::is_settings_ack_timeout`](crate::Error::is_settings_ack_timeout)
+    /// is true.
+    ///
+    /// Pass `None` to wait forever. Default is 10 seconds.
+    pub fn http2_settings_ack_timeout<D>(mut self, timeout: D) -> ClientBuilder
+    where
+        D: Into<Option<Duration>>,
+    {
+        self.config.share_connections = false;
+        self.config.http2_settings_ack_timeout = timeout.into();
+        self
+    }
+
     // TCP options
 
     /// Set whether sockets have `TCP_NODELAY` enabled.
     ///
     /// Default is `true`.
     pub fn tcp_nodelay(mut self, enabled: bool) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.nodelay = enabled;
         self
     }
@@ -1023,6 +2093,7 @@ impl ClientBuilder {
     where
         T: Into<Option<IpAddr>>,
     {
+        self.config.share_connections = false;
         match addr.into() {
             Some(IpAddr::V4(v4)) => {
                 self.config.local_address_ipv4 = Some(v4);
@@ -1038,6 +2109,7 @@ impl ClientBuilder {
     /// Set that all sockets are bound to the configured IPv4 or IPv6 address (depending on host's
     /// preferences) before connection.
     pub fn local_addresses(mut self, addr_ipv4: Ipv4Addr, addr_ipv6: Ipv6Addr) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.local_address_ipv4 = Some(addr_ipv4);
         self.config.local_address_ipv6 = Some(addr_ipv6);
         self
@@ -1055,6 +2127,7 @@ impl ClientBuilder {
     /// ```
     #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
     pub fn interface(mut self, interface: &str) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.interface = Some(interface.to_string());
         self
     }
@@ -1066,10 +2139,226 @@ impl ClientBuilder {
     where
         D: Into<Option<Duration>>,
     {
+        self.config.share_connections = false;
         self.config.tcp_keepalive = val.into();
         self
     }
 
+    /// Send a Proxy Protocol v2 header at the start of every connection.
+    ///
+    /// The header tells the server that the connection was accepted from
+    /// `source_addr` on `dest_addr`, for backends behind a gateway that need
+    /// the original client address. It is written right after the TCP connect,
+    /// before the TLS handshake or any HTTP bytes, and only on connections that
+    /// don't go through a proxy.
+    ///
+    /// Default is disabled.
+    pub fn proxy_protocol_v2(
+        mut self,
+        source_addr: SocketAddr,
+        dest_addr: SocketAddr,
+    ) -> ClientBuilder {
+        self.config.share_connections = false;
+        self.config.proxy_protocol = Some((source_addr, dest_addr));
+        self
+    }
+
+    /// Inject latency, packet loss and I/O errors into every connection.
+    ///
+    /// See [`ChaosLayer`](crate::testing::chaos::ChaosLayer) for the knobs.
+    /// Faults apply to the established connection, after any proxy tunnel
+    /// and TLS handshake, so they hit HTTP bytes only.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `chaos` feature to be enabled.
+    #[cfg(feature = "chaos")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "chaos")))]
+    pub fn chaos(mut self, chaos: crate::testing::chaos::ChaosLayer) -> ClientBuilder {
+        self.config.share_connections = false;
+        self.config.chaos = Some(chaos);
+        self
+    }
+
+    /// Send every request to `transport` instead of the network.
+    ///
+    /// The transport gets each request as it would be sent, once per
+    /// redirect hop and retry, after the default headers, cookies and
+    /// request hooks were applied, and answers it. See
+    /// [`MockServer`](crate::testing::mock::MockServer) for a transport
+    /// matching requests against expectations.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `mock` feature to be enabled.
+    #[cfg(feature = "mock")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "mock")))]
+    pub fn with_transport<T>(mut self, transport: T) -> ClientBuilder
+    where
+        T: crate::testing::mock::MockTransport,
+    {
+        self.config.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Record the requests of the client and their responses to the
+    /// cassette at `path`, or replay them from it, depending on `mode`.
+    ///
+    /// This adds a [`Vcr`](crate::testing::vcr::Vcr) layer with its default
+    /// settings; add one with [`layer`](ClientBuilder::layer) to match
+    /// requests on headers or their body, or to redact more headers.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `vcr` feature to be enabled.
+    #[cfg(feature = "vcr")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "vcr")))]
+    pub fn vcr(
+        self,
+        mode: crate::testing::vcr::VcrMode,
+        path: impl Into<std::path::PathBuf>,
+    ) -> ClientBuilder {
+        self.layer(crate::testing::vcr::Vcr::new(mode, path))
+    }
+
+    /// Wrap every request sent by the client in a tower [`Layer`].
+    ///
+    /// Layers see each `Request` once, as built by the `RequestBuilder`,
+    /// and the final `Response`: redirects, cookies and retries, including
+    /// those of the [`retry`](ClientBuilder::retry) policy, all happen
+    /// inside of them. They
+    /// compose in registration order, so the first layer added is the
+    /// outermost.
+    ///
+    /// ```rust,ignore
+    /// let client = rquest::Client::builder()
+    ///     .layer(tower::retry::RetryLayer::new(policy))
+    ///     .layer(tower::limit::ConcurrencyLimitLayer::new(8))
+    ///     .build()?;
+    /// ```
+    ///
+    /// See `examples/tower.rs` for a complete example.
+    pub fn layer<L>(mut self, layer: L) -> ClientBuilder
+    where
+        L: Layer<ClientService> + Send + Sync + 'static,
+        L::Service: tower_service::Service<Request, Response = Response> + Clone + Send + 'static,
+        <L::Service as tower_service::Service<Request>>::Error: Into<crate::error::BoxError>,
+        <L::Service as tower_service::Service<Request>>::Future: Send + 'static,
+    {
+        self.config.layers.push(Arc::new(move |service| {
+            ClientService::new(layer.layer(service))
+        }));
+        self
+    }
+
+    /// Run `hook` on every request before it is sent, including each
+    /// redirect hop and retry.
+    ///
+    /// Request hooks run in registration order, along with those added by
+    /// [`with_async_request_hook`](ClientBuilder::with_async_request_hook),
+    /// and see the request as it will be sent: with its final URL, and its
+    /// headers in their final order. An error fails the request.
+    ///
+    /// ```rust
+    /// # fn run() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::builder()
+    ///     .with_request_hook(|req| {
+    ///         let path = req.url().path().parse()?;
+    ///         req.headers_mut().insert("x-request-path", path);
+    ///         Ok::<_, rquest::header::InvalidHeaderValue>(())
+    ///     })
+    ///     .build()?;
+    /// # drop(client);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_request_hook<F, E>(mut self, hook: F) -> ClientBuilder
+    where
+        F: Fn(&mut Request) -> Result<(), E> + Send + Sync + 'static,
+        E: Into<crate::error::BoxError>,
+    {
+        self.config.hooks.push_request(hook);
+        self
+    }
+
+    /// Run `hook` on every request before it is sent, including each
+    /// redirect hop and retry, and send the request it resolves to.
+    ///
+    /// This is the asynchronous version of
+    /// [`with_request_hook`](ClientBuilder::with_request_hook), for hooks
+    /// that need to wait on something, like a remote signing service.
+    pub fn with_async_request_hook<F, Fut, E>(mut self, hook: F) -> ClientBuilder
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Request, E>> + Send + 'static,
+        E: Into<crate::error::BoxError>,
+    {
+        self.config.hooks.push_async_request(hook);
+        self
+    }
+
+    /// Run `hook` on every response, including those of redirect hops,
+    /// along with the request it answers.
+    ///
+    /// Response hooks run in registration order. The request is passed
+    /// without its body if that was a stream.
+    pub fn with_response_hook<F>(mut self, hook: F) -> ClientBuilder
+    where
+        F: Fn(&Request, &Response) + Send + Sync + 'static,
+    {
+        self.config.hooks.push_response(hook);
+        self
+    }
+
+    /// Log every request and its outcome through `tracing`.
+    ///
+    /// See [`LoggingMiddleware`](crate::middleware::LoggingMiddleware) for
+    /// what is logged and how it is redacted.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `tracing` feature to be enabled.
+    #[cfg(feature = "tracing")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tracing")))]
+    pub fn logging(mut self, logging: crate::middleware::LoggingMiddleware) -> ClientBuilder {
+        self.config.logging = Some(logging);
+        self
+    }
+
+    /// Set how the path of a request is recorded in its `rquest.request`
+    /// span.
+    ///
+    /// Every request is sent in a `tracing` span recording its method, host
+    /// and path. By default the path is the one of the URL, which has too
+    /// many distinct values for paths holding IDs: `trace_path` can turn it
+    /// into a template instead. It is also used for the path of redirects.
+    ///
+    /// ```rust
+    /// # fn doc() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::builder()
+    ///     .trace_path(|url| match url.path().strip_prefix("/users/") {
+    ///         Some(_) => "/users/{id}".to_owned(),
+    ///         None => url.path().to_owned(),
+    ///     })
+    ///     .build()?;
+    /// # drop(client);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `tracing` feature to be enabled.
+    #[cfg(feature = "tracing")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tracing")))]
+    pub fn trace_path<F>(mut self, trace_path: F) -> ClientBuilder
+    where
+        F: Fn(&Url) -> String + Send + Sync + 'static,
+    {
+        self.config.trace_path = Some(Arc::new(trace_path));
+        self
+    }
+
     /// Controls the use of certificate validation.
     ///
     /// Defaults to `false`.
@@ -1087,6 +2376,7 @@ impl ClientBuilder {
     /// feature to be enabled.
     #[cfg(feature = "boring-tls")]
     pub fn danger_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.tls.certs_verification = !accept_invalid_certs;
         self
     }
@@ -1100,6 +2390,7 @@ impl ClientBuilder {
     /// feature to be enabled.
     #[cfg(feature = "boring-tls")]
     pub fn tls_sni(mut self, tls_sni: bool) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.tls.tls_sni = tls_sni;
         self
     }
@@ -1120,6 +2411,7 @@ impl ClientBuilder {
     /// feature to be enabled.
     #[cfg(feature = "boring-tls")]
     pub fn min_tls_version(mut self, version: tls::Version) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.tls.min_tls_version = Some(version);
         self
     }
@@ -1140,6 +2432,7 @@ impl ClientBuilder {
     /// feature to be enabled.
     #[cfg(feature = "boring-tls")]
     pub fn max_tls_version(mut self, version: tls::Version) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.tls.max_tls_version = Some(version);
         self
     }
@@ -1151,10 +2444,80 @@ impl ClientBuilder {
     /// feature to be enabled.
     #[cfg(feature = "boring-tls")]
     pub fn tls_info(mut self, tls_info: bool) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.tls_info = tls_info;
         self
     }
 
+    /// Add the protocols negotiated through ALPN as `ProtocolInfo`
+    /// extension to responses received over TLS.
+    ///
+    /// This is also done when `tls_info` is set.
+    ///
+    /// # Optional
+    ///
+    /// feature to be enabled.
+    #[cfg(feature = "boring-tls")]
+    pub fn protocol_info(mut self, enabled: bool) -> ClientBuilder {
+        self.config.share_connections = false;
+        self.config.protocol_info = enabled;
+        self
+    }
+
+    /// Learn which origins never negotiate HTTP/2, and stop offering `h2`
+    /// to them.
+    ///
+    /// After the last few connections to an origin all negotiated HTTP/1.1
+    /// through ALPN, new connections to it only offer `http/1.1`. `h2` is
+    /// offered again every few minutes, in case the origin started
+    /// supporting it. This only applies when both HTTP/1.1 and HTTP/2 are
+    /// allowed, and changes the ClientHello sent to those origins.
+    ///
+    /// See [`Client::http_version_cache_stats`] for what was learned.
+    ///
+    /// Default is `false`.
+    #[cfg(feature = "boring-tls")]
+    pub fn adaptive_http_version(mut self, enabled: bool) -> ClientBuilder {
+        self.config.share_connections = false;
+        self.config.adaptive_http_version = enabled;
+        self
+    }
+
+    /// Remember the alternative services origins advertise with `Alt-Svc`
+    /// response headers, and connect to them instead of the origins.
+    ///
+    /// Only `h2` and `http/1.1` alternatives allowed by the HTTP version
+    /// preference are connected to, with TLS still negotiated for the
+    /// origin. An alternative that can't be connected to is not used for a
+    /// few minutes, the origin being connected to instead. See
+    /// [`AltSvcCache`](crate::AltSvcCache) for the details, and
+    /// [`Client::alt_svc_cache`] to look at or seed the cache.
+    ///
+    /// Default is `false`.
+    #[cfg(feature = "boring-tls")]
+    pub fn alt_svc(mut self, enabled: bool) -> ClientBuilder {
+        self.config.share_connections = false;
+        self.config.alt_svc = enabled;
+        self
+    }
+
+    /// Defer building the TLS contexts until the first request is sent.
+    ///
+    /// This makes `build()` cheap for short-lived clients. Any error building
+    /// the TLS contexts is then returned by the first request instead.
+    ///
+    /// Default is `false`.
+    ///
+    /// # Optional
+    ///
+    /// feature to be enabled.
+    #[cfg(feature = "boring-tls")]
+    pub fn lazy_tls_init(mut self, enabled: bool) -> ClientBuilder {
+        self.config.share_connections = false;
+        self.config.lazy_tls_init = enabled;
+        self
+    }
+
     /// Restrict the Client to be used with HTTPS only requests.
     ///
     /// Defaults to false.
@@ -1169,6 +2532,7 @@ impl ClientBuilder {
     where
         F: Fn() -> Result<X509Store, ErrorStack> + Send + Sync + 'static,
     {
+        self.config.share_connections = false;
         self.config.tls.ca_cert_store = Some(Box::new(store));
         self
     }
@@ -1183,6 +2547,7 @@ impl ClientBuilder {
     #[cfg(feature = "hickory-dns")]
     #[cfg_attr(docsrs, doc(cfg(feature = "hickory-dns")))]
     pub fn hickory_dns_strategy(mut self, strategy: LookupIpStrategy) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.dns_strategy = Some(strategy);
         self
     }
@@ -1195,6 +2560,7 @@ impl ClientBuilder {
     #[cfg(feature = "hickory-dns")]
     #[cfg_attr(docsrs, doc(cfg(feature = "hickory-dns")))]
     pub fn no_hickory_dns(mut self) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.hickory_dns = false;
         self
     }
@@ -1220,6 +2586,7 @@ impl ClientBuilder {
     /// itself, any port in the overridden addresses will be ignored and traffic sent
     /// to the conventional port for the given scheme (e.g. 80 for http).
     pub fn resolve_to_addrs(mut self, domain: &str, addrs: &[SocketAddr]) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config
             .dns_overrides
             .insert(domain.to_string(), addrs.to_vec());
@@ -1232,6 +2599,7 @@ impl ClientBuilder {
     /// Overrides for specific names passed to `resolve` and `resolve_to_addrs` will
     /// still be applied on top of this resolver.
     pub fn dns_resolver<R: Resolve + 'static>(mut self, resolver: Arc<R>) -> ClientBuilder {
+        self.config.share_connections = false;
         self.config.dns_resolver = Some(resolver as _);
         self
     }
@@ -1266,6 +2634,94 @@ impl Client {
         ClientBuilder::new()
     }
 
+    /// Returns a `ClientBuilder` starting from the configuration of this
+    /// client.
+    ///
+    /// The client it builds shares the connection pool, TLS configuration
+    /// and DNS resolver of this one, along with its cookie store, HTTP cache
+    /// and authentication state. Its default headers, timeouts, redirect and
+    /// retry policies, hooks and layers can be changed without affecting
+    /// this client.
+    ///
+    /// Changing the proxies keeps all of this shared but the pool, as the
+    /// pooled connections go through the proxies of this client. Changing
+    /// a setting of the connections themselves, such as the impersonated
+    /// browser, TLS, HTTP/2, DNS or pool options, builds new ones instead:
+    /// these settings cannot be read back from this client, so they start
+    /// from their defaults, along with the changes made to the builder.
+    ///
+    /// ```rust
+    /// # fn doc() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::builder().cookie_store(true).build()?;
+    /// let impatient = client
+    ///     .to_builder()
+    ///     .timeout(std::time::Duration::from_secs(5))
+    ///     .build()?;
+    /// # drop(impatient);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_builder(&self) -> ClientBuilder {
+        let inner = &self.inner;
+        let mut builder = ClientBuilder::new();
+        let config = &mut builder.config;
+        config.accepts = inner.accepts;
+        #[cfg(feature = "cookies")]
+        {
+            config.cookie_store = inner.cookie_store.clone();
+        }
+        config.headers = inner.headers.clone();
+        config.headers_order = inner.headers_order;
+        config.base_url = inner.base_url.clone();
+        config.idna = inner.idna;
+        config.default_query = inner.default_query.clone();
+        config.proxies = inner.hyper.get_proxies().to_vec();
+        config.auto_sys_proxy = false;
+        config.redirect_policy = inner.redirect_policy.clone();
+        config.retry_policy = inner.retry_policy.as_deref().cloned();
+        config.retry_budget = inner.retry_budget.as_ref().map(|budget| budget.settings());
+        config.respect_retry_after = inner.respect_retry_after;
+        config.hedge_threshold = inner.hedge_threshold;
+        config.rate_limits = inner.rate_limiter.limits();
+        config.deduplicate_requests = inner.dedup.is_some();
+        config.dedup_key = inner.dedup.as_ref().and_then(|dedup| dedup.key_fn());
+        config.referer = inner.referer;
+        config.timeout = inner.request_timeout;
+        config.https_only = inner.https_only;
+        #[cfg(feature = "boring-tls")]
+        {
+            config.alt_svc = inner.alt_svc.is_some();
+        }
+        #[cfg(feature = "websocket")]
+        {
+            config.ws_behavior = inner.ws_behavior;
+            config.max_ws_connections = inner.ws_pool.max_per_host();
+        }
+        #[cfg(feature = "tracing")]
+        {
+            config.logging = inner.logging.as_deref().cloned();
+            config.trace_path = inner.trace_path.clone();
+        }
+        config.hooks = inner.hooks.as_deref().cloned().unwrap_or_default();
+        config.metrics_observer = inner.metrics_observer.clone();
+        config.request_id = inner.request_id.clone();
+        config.download_progress = inner.download_progress.clone();
+        config.max_response_body_size = inner.max_response_body_size;
+        config.strict_content_length = inner.strict_content_length;
+        config.expect_continue_threshold = inner.expect_continue_threshold;
+        config.expect_continue_timeout = inner.expect_continue_timeout;
+        config.max_response_headers = inner.max_response_headers;
+        config.max_response_header_size = inner.max_response_header_size;
+        #[cfg(feature = "mock")]
+        {
+            config.transport = inner.transport.clone();
+        }
+        config.layers = inner.layers.clone();
+        config.base = Some(inner.clone());
+        config.share_connections = true;
+        builder
+    }
+
     /// Convenience method to make a `GET` request to a URL.
     ///
     /// # Errors
@@ -1281,7 +2737,19 @@ impl Client {
     /// request.
     #[cfg(feature = "websocket")]
     pub fn websocket<U: IntoUrl>(&self, url: U) -> crate::WebSocketRequestBuilder {
-        crate::WebSocketRequestBuilder::new(self.request(Method::GET, url))
+        crate::WebSocketRequestBuilder::new(self.request(Method::GET, url), self.inner.ws_behavior)
+    }
+
+    /// Returns the pool of persistent WebSocket connections of the client,
+    /// shared by its clones.
+    ///
+    /// Connections checked out from the pool are returned to it once
+    /// dropped, instead of being closed, so that frequent exchanges don't
+    /// open a connection each. See
+    /// [`max_ws_connections`](ClientBuilder::max_ws_connections).
+    #[cfg(feature = "websocket")]
+    pub fn websocket_pool(&self) -> crate::WebSocketPool {
+        crate::WebSocketPool::new(self.clone(), self.inner.ws_pool.clone())
     }
 
     /// Convenience method to make a `POST` request to a URL.
@@ -1338,7 +2806,8 @@ impl Client {
     ///
     /// This method fails whenever the supplied `Url` cannot be parsed.
     pub fn request<U: IntoUrl>(&self, method: Method, url: U) -> RequestBuilder {
-        let req = url.into_url().map(move |url| Request::new(method, url));
+        let req = into_url::resolve(self.inner.base_url.as_ref(), url, self.inner.idna)
+            .map(move |url| Request::new(method, url));
         RequestBuilder::new(self.clone(), req)
     }
 
@@ -1361,7 +2830,281 @@ impl Client {
         self.execute_request(request)
     }
 
-    pub(super) fn execute_request(&self, req: Request) -> Pending {
+    pub(super) fn execute_request(&self, mut req: Request) -> Pending {
+        let in_flight = match self.inner.shutdown.enter() {
+            Some(in_flight) => in_flight,
+            None => return Pending::new_err(error::client_closed().with_url(req.url().clone())),
+        };
+
+        self.inner.add_default_query(req.url_mut());
+
+        let request_id = match self.inner.request_id {
+            Some(ref policy) => match policy.apply(&mut req) {
+                Ok(id) => Some(id),
+                Err(err) => return Pending::new_err(err),
+            },
+            None => None,
+        };
+
+        let recorder = self
+            .inner
+            .metrics_observer
+            .as_ref()
+            .map(|observer| Recorder::new(observer.clone(), &req));
+
+        #[cfg(feature = "tracing")]
+        let span = trace::request_span(req.method(), req.url(), self.inner.trace_path.as_ref());
+
+        let pending = match self.service {
+            Some(ref service) => {
+                let mut service = service
+                    .lock()
+                    .expect("client service lock poisoned")
+                    .clone();
+                Pending {
+                    inner: PendingInner::Boxed(Box::pin(async move {
+                        std::future::poll_fn(|cx| service.poll_ready(cx)).await?;
+                        service.call(req).await
+                    })),
+                }
+            }
+            None => self.send_request(req),
+        };
+
+        let pending = match recorder {
+            Some(recorder) => Pending {
+                inner: PendingInner::Boxed(Box::pin(recorder.observe(pending))),
+            },
+            None => pending,
+        };
+
+        #[cfg(feature = "tracing")]
+        let pending = Pending {
+            inner: PendingInner::Boxed(Box::pin(trace::instrument(span, pending))),
+        };
+
+        let pending = Pending {
+            inner: PendingInner::Boxed(Box::pin(in_flight.run(pending))),
+        };
+
+        match request_id {
+            Some(id) => Pending {
+                inner: PendingInner::Boxed(Box::pin(request_id::tag(id, pending))),
+            },
+            None => pending,
+        }
+    }
+
+    /// Shuts this client down, along with its clones.
+    ///
+    /// Requests sent from now on fail with an error for which
+    /// [`Error::is_client_closed`](crate::Error::is_client_closed) is true.
+    /// The requests in flight are given `timeout` to end, after which they
+    /// fail with that error too, then the idle pooled connections are
+    /// closed. A request ends once its response headers are received:
+    /// response bodies being read are not waited for, and keep their
+    /// connection until they are done.
+    ///
+    /// Dropping the last handle of a client closes its connections as
+    /// usual, without this. Clients made with
+    /// [`to_builder`](Client::to_builder) are shut down on their own.
+    ///
+    /// ```rust
+    /// # async fn doc(client: rquest::Client) {
+    /// let report = client.shutdown(std::time::Duration::from_secs(10)).await;
+    /// println!("{} requests completed, {} aborted", report.completed, report.aborted);
+    /// # }
+    /// ```
+    pub fn shutdown(&self, timeout: Duration) -> impl Future<Output = ShutdownReport> {
+        let inner = self.inner.clone();
+        async move {
+            let report = inner.shutdown.close(timeout).await;
+            inner.hyper.reset_pool_idle();
+            inner
+                .connect_to
+                .clients
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clear();
+            report
+        }
+    }
+
+    /// Send `req`, skipping the layers.
+    pub(crate) fn send_request(&self, mut req: Request) -> Pending {
+        if let Some(validators) = req.take_validators() {
+            let client = self.clone();
+            return Pending {
+                inner: PendingInner::Boxed(Box::pin(async move {
+                    validators.send(req, |req| client.send_request(req)).await
+                })),
+            };
+        }
+
+        if let Some(ref bearer) = self.inner.bearer {
+            if !req.has_auth() {
+                let bearer = bearer.clone();
+                let client = self.clone();
+                return Pending {
+                    inner: PendingInner::Boxed(Box::pin(async move {
+                        bearer.send(req, |req| client.send_cached(req)).await
+                    })),
+                };
+            }
+        }
+
+        #[cfg(feature = "boring-tls")]
+        if let Some(credentials) = req.digest_auth().cloned() {
+            let digest = self.inner.digest.clone();
+            let client = self.clone();
+            return Pending {
+                inner: PendingInner::Boxed(Box::pin(async move {
+                    digest
+                        .send(&credentials, req, |req| client.send_cached(req))
+                        .await
+                })),
+            };
+        }
+
+        #[cfg(feature = "ntlm")]
+        if let Some(credentials) = req.ntlm_auth().cloned() {
+            let client = self.clone();
+            return Pending {
+                inner: PendingInner::Boxed(Box::pin(async move {
+                    crate::ntlm::send(&credentials, req, |req| client.send_cached(req)).await
+                })),
+            };
+        }
+
+        self.send_cached(req)
+    }
+
+    fn send_cached(&self, req: Request) -> Pending {
+        #[cfg(feature = "cache")]
+        if let Some(ref cache) = self.inner.cache {
+            let cache = cache.clone();
+            let client = self.clone();
+            return Pending {
+                inner: PendingInner::Boxed(Box::pin(async move {
+                    cache.send(req, |req| client.send_deduplicated(req)).await
+                })),
+            };
+        }
+
+        self.send_deduplicated(req)
+    }
+
+    fn send_deduplicated(&self, req: Request) -> Pending {
+        if let Some(ref dedup) = self.inner.dedup {
+            if let Some(key) = dedup.key(&req) {
+                let dedup = dedup.clone();
+                let client = self.clone();
+                return Pending {
+                    inner: PendingInner::Boxed(Box::pin(async move {
+                        dedup.send(key, req, |req| client.send_hedged(req)).await
+                    })),
+                };
+            }
+        }
+
+        self.send_hedged(req)
+    }
+
+    fn send_hedged(&self, req: Request) -> Pending {
+        if let Some(threshold) = self.inner.hedge_threshold {
+            if hedge::is_hedged(&req) {
+                let client = self.clone();
+                return Pending {
+                    inner: PendingInner::Boxed(Box::pin(async move {
+                        hedge::send(threshold, req, |req| client.send_retrying(req)).await
+                    })),
+                };
+            }
+        }
+
+        self.send_retrying(req)
+    }
+
+    fn send_retrying(&self, req: Request) -> Pending {
+        match self.inner.retry_policy {
+            Some(ref policy) => {
+                let policy = policy.clone();
+                let client = self.clone();
+                Pending {
+                    inner: PendingInner::Boxed(Box::pin(async move {
+                        let budget = client.inner.retry_budget.as_deref();
+                        policy
+                            .send(req, budget, |req| client.send_retry_after(req))
+                            .await
+                    })),
+                }
+            }
+            None => self.send_retry_after(req),
+        }
+    }
+
+    fn send_retry_after(&self, req: Request) -> Pending {
+        match self.inner.respect_retry_after {
+            Some(max_wait) => {
+                let client = self.clone();
+                Pending {
+                    inner: PendingInner::Boxed(Box::pin(async move {
+                        let policy = client.inner.retry_policy.as_deref();
+                        let skip = |status| policy.is_some_and(|p| p.retries_status(status));
+                        retry::send_after_retry_after(max_wait, req, skip, |req| {
+                            client.send_rate_limited(req)
+                        })
+                        .await
+                    })),
+                }
+            }
+            None => self.send_rate_limited(req),
+        }
+    }
+
+    fn send_rate_limited(&self, req: Request) -> Pending {
+        match self.inner.rate_limiter.reserve(req.url()) {
+            Ok(None) => self.send_once(req),
+            Ok(Some(reservation)) => {
+                let client = self.clone();
+                Pending {
+                    inner: PendingInner::Boxed(Box::pin(async move {
+                        reservation.wait().await;
+                        client.send_once(req).await
+                    })),
+                }
+            }
+            Err(limited) => Pending::new_err(error::rate_limited(limited, req.url().clone())),
+        }
+    }
+
+    fn send_once(&self, req: Request) -> Pending {
+        let upload_progress = req.upload_progress().cloned();
+        let download_progress = req
+            .download_progress()
+            .or(self.inner.download_progress.as_ref())
+            .cloned();
+        let max_response_body_size = req
+            .max_response_body_size()
+            .or(self.inner.max_response_body_size);
+        let signer = req.signer().cloned();
+        let expect_continue = req.expect_continue();
+        let raw_path_and_query = match req.request_target() {
+            Some(RequestTarget::Asterisk) => Some(PathAndQuery::from_static("*")),
+            Some(RequestTarget::Authority) if req.method() != Method::CONNECT => {
+                return Pending::new_err(error::builder(
+                    "authority-form request target without the CONNECT method",
+                ));
+            }
+            _ => req.raw_path_and_query().cloned(),
+        };
+        let overrides = req.connect_overrides();
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        let compression = req.compression();
+        let accepts = match req.accept_encoding() {
+            Some(accept_encoding) => self.inner.accepts.restrict_to(accept_encoding),
+            None => self.inner.accepts,
+        };
         let (method, url, mut headers, body, timeout, version) = req.pieces();
         if url.scheme() != "http"
             && url.scheme() != "https"
@@ -1394,6 +3137,10 @@ impl Client {
             }
         }
 
+        if version == Version::HTTP_10 && !headers.contains_key(CONNECTION) {
+            headers.insert(CONNECTION, HeaderValue::from_static("keep-alive"));
+        }
+
         let accept_encoding = self.inner.accepts.as_str();
 
         if let Some(accept_encoding) = accept_encoding {
@@ -1402,7 +3149,25 @@ impl Client {
             }
         }
 
-        let uri = expect_uri(&url);
+        let uri = match raw_path_and_query {
+            Some(ref raw) => with_path_and_query(expect_uri(&url), raw.clone()),
+            None => expect_uri(&url),
+        };
+
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        let body = match (body, compression) {
+            (Some(body), Some((compression, level))) if !headers.contains_key(CONTENT_ENCODING) => {
+                match compression.compress(body, level) {
+                    Ok(body) => {
+                        headers.insert(CONTENT_ENCODING, compression.content_encoding());
+                        headers.remove(CONTENT_LENGTH);
+                        Some(body)
+                    }
+                    Err(e) => return Pending::new_err(error::request(e).with_url(url)),
+                }
+            }
+            (body, _) => body,
+        };
 
         let (reusable, body) = match body {
             Some(body) => {
@@ -1411,8 +3176,44 @@ impl Client {
             }
             None => (None, Body::empty()),
         };
+        let body = match upload_progress {
+            Some(ref progress) => body.with_progress(progress.clone()),
+            None => body,
+        };
+
+        let expect_continue = reusable.is_some()
+            && version != Version::HTTP_2
+            && expect_continue.unwrap_or_else(|| {
+                self.inner
+                    .expect_continue_threshold
+                    .zip(body.content_length())
+                    .is_some_and(|(threshold, len)| len >= threshold)
+            });
+        let (body, responded) = if expect_continue {
+            let (tx, rx) = oneshot::channel();
+            headers.insert(EXPECT, HeaderValue::from_static("100-continue"));
+            (
+                body.with_continue(self.inner.expect_continue_timeout, rx),
+                Some(tx),
+            )
+        } else {
+            (body, None)
+        };
+
+        if overrides.addr.is_none() {
+            self.proxy_auth(&uri, &mut headers);
+        }
 
-        self.proxy_auth(&uri, &mut headers);
+        // A Host header set on the request goes first, where browsers send
+        // it over HTTP/1, unless the headers order places it.
+        if headers.contains_key(HOST)
+            && !self
+                .inner
+                .headers_order
+                .is_some_and(|order| order.contains(&HOST))
+        {
+            headers = host_first(headers);
+        }
 
         // Insert headers in order if enabled
         if let Some(headers_order) = self.inner.headers_order {
@@ -1435,37 +3236,76 @@ impl Client {
             headers = sorted_headers;
         }
 
-        let builder = hyper::Request::builder()
-            .method(method.clone())
-            .uri(uri)
-            .version(version);
+        if let Some(ref signer) = signer {
+            let payload = match reusable {
+                Some(Some(ref bytes)) => Payload::Bytes(bytes),
+                Some(None) => Payload::Streaming,
+                None => Payload::Bytes(&[]),
+            };
+            if let Err(err) = sign::sign(signer, &method, &url, &mut headers, payload) {
+                return Pending::new_err(err);
+            }
+        }
 
-        let in_flight = {
-            let mut req = builder
-                .body(body.into_stream())
-                .expect("valid request parts");
-            *req.headers_mut() = headers.clone();
-            ResponseFuture::Default(self.inner.hyper.request(req))
-        };
+        #[cfg(feature = "tracing")]
+        let logging = self.inner.logging.clone().map(|logging| {
+            let body = reusable.as_ref().map(Option::as_ref);
+            logging.log_request(&method, &url, &headers, body);
+            (logging, method.clone(), url.clone(), Instant::now())
+        });
+
+        let written = Written::new();
+        let in_flight = self.inner.request(
+            method.clone(),
+            &url,
+            uri,
+            Some(version),
+            headers.clone(),
+            body,
+            overrides,
+            &written,
+        );
 
         let timeout = timeout
             .or(self.inner.request_timeout)
             .map(tokio::time::sleep)
             .map(Box::pin);
 
-        Pending {
+        let pending = Pending {
             inner: PendingInner::Request(PendingRequest {
                 method,
                 url,
+                raw_path_and_query,
+                overrides,
                 headers,
                 body: reusable,
+                upload_progress,
+                download_progress,
+                max_response_body_size,
+                accepts,
                 urls: Vec::new(),
                 retry_count: 0,
+                sent: None,
+                responded,
                 client: self.inner.clone(),
                 in_flight,
                 timeout,
+                started: Instant::now(),
+                written,
             }),
+        };
+
+        #[cfg(feature = "tracing")]
+        if let Some((logging, method, url, started)) = logging {
+            return Pending {
+                inner: PendingInner::Boxed(Box::pin(async move {
+                    let res = pending.await;
+                    logging.log_response(method, url, started, res).await
+                })),
+            };
         }
+
+        pending
     }
 
     fn proxy_auth(&self, dst: &Uri, headers: &mut HeaderMap) {
@@ -1544,6 +3384,91 @@ impl Client {
         Ok(())
     }
 
+    /// Returns the TLS session cache counters of this client.
+    ///
+    /// Sessions are only cached when the TLS settings enable `pre_shared_key`.
+    #[cfg(feature = "boring-tls")]
+    pub fn session_cache_stats(&self) -> tls::SessionCacheStats {
+        self.inner.hyper.session_cache_stats()
+    }
+
+    /// Returns the counters of the adaptive HTTP version cache, all zero
+    /// unless [`ClientBuilder::adaptive_http_version`] is enabled.
+    #[cfg(feature = "boring-tls")]
+    pub fn http_version_cache_stats(&self) -> tls::HttpVersionCacheStats {
+        self.inner.hyper.http_version_cache_stats()
+    }
+
+    /// Returns the cache of alternative services of this client and its
+    /// clones, `None` unless [`ClientBuilder::alt_svc`] is enabled.
+    ///
+    /// Alternatives inserted into it are connected to by the next new
+    /// connections to their origin.
+    #[cfg(feature = "boring-tls")]
+    pub fn alt_svc_cache(&self) -> Option<&crate::AltSvcCache> {
+        self.inner.alt_svc.as_ref()
+    }
+
+    /// Changes a rate limit set with
+    /// [`ClientBuilder::rate_limit`], for this client and its clones.
+    ///
+    /// The requests already waiting keep their turn, the new limit applies
+    /// to the next ones.
+    pub fn set_rate_limit(&self, limit: RateLimit) {
+        self.inner.rate_limiter.update(|limits| limits.set(limit));
+    }
+
+    /// Changes or adds a rate limit set with
+    /// [`ClientBuilder::rate_limit_for_host`], for this client and its
+    /// clones.
+    pub fn set_rate_limit_for_host(&self, pattern: &str, limit: RateLimit) {
+        self.inner
+            .rate_limiter
+            .update(|limits| limits.set_for_host(pattern, limit));
+    }
+
+    /// Returns the statistics of the HTTP/2 connection open to `origin`,
+    /// such as `https://example.com`.
+    ///
+    /// This is the last HTTP/2 connection opened to the origin, `None` once
+    /// it is closed or if it never was opened.
+    pub fn h2_connection_stats(&self, origin: &str) -> Option<crate::H2ConnectionStats> {
+        self.inner.hyper.h2_connection_stats(origin)
+    }
+
+    /// Returns the origins the server of the HTTP/2 connection open to
+    /// `origin` declared it is authoritative for, with `ORIGIN` frames
+    /// (RFC 8336).
+    ///
+    /// `None` if no such connection is open, or its server sent no `ORIGIN`
+    /// frame.
+    ///
+    /// Requests to the other origins still open connections of their own.
+    /// Once the set leaves `origin` out, the connection is no longer reused
+    /// for it.
+    pub fn h2_origin_set(&self, origin: &str) -> Option<Vec<String>> {
+        self.inner.hyper.h2_origin_set(origin)
+    }
+
+    /// Returns what the connection open to the origin of `url` is: its
+    /// address, TLS session, HTTP version, age and streams.
+    ///
+    /// With several connections open to the origin, this is the one used
+    /// last. `None` if none is open. The connection pool is only looked
+    /// at: no connection is opened, checked out or kept alive by this.
+    pub fn connection_info(&self, url: &str) -> Option<crate::ConnectionInfo> {
+        self.inner.hyper.connection_info(url)
+    }
+
+    /// Returns the number of connections open, and of new connections
+    /// waiting for the
+    /// [`max_connections_per_host`](ClientBuilder::max_connections_per_host)
+    /// or [`max_total_connections`](ClientBuilder::max_total_connections)
+    /// limit.
+    pub fn pool_stats(&self) -> crate::PoolStats {
+        self.inner.hyper.pool_stats()
+    }
+
     /// Set the proxies for this client.
     #[inline]
     pub fn set_proxies(&mut self, proxies: &[Proxy]) {
@@ -1592,6 +3517,9 @@ impl fmt::Debug for Client {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut builder = f.debug_struct("Client");
         self.inner.fmt_fields(&mut builder);
+        if self.service.is_some() {
+            builder.field("layers", &true);
+        }
         builder.finish()
     }
 }
@@ -1650,20 +3578,135 @@ impl Config {
             f.field("proxies", &self.proxies);
         }
 
+        if !self.reuse_proxy_tunnels {
+            f.field("reuse_proxy_tunnels", &false);
+        }
+
+        if self.base.is_some() {
+            f.field("share_connections", &self.share_connections);
+        }
+
         if !self.redirect_policy.is_default() {
             f.field("redirect_policy", &self.redirect_policy);
         }
 
+        if let Some(ref retry_policy) = self.retry_policy {
+            f.field("retry_policy", retry_policy);
+        }
+
+        if let Some(ref retry_budget) = self.retry_budget {
+            f.field("retry_budget", retry_budget);
+        }
+
+        if let Some(ref respect_retry_after) = self.respect_retry_after {
+            f.field("respect_retry_after", respect_retry_after);
+        }
+
+        if let Some(ref hedge_threshold) = self.hedge_threshold {
+            f.field("hedge_threshold", hedge_threshold);
+        }
+
+        if !self.rate_limits.is_empty() {
+            f.field("rate_limits", &self.rate_limits);
+        }
+
+        if self.deduplicate_requests {
+            f.field("deduplicate_requests", &true);
+        }
+
+        if self.dedup_key.is_some() {
+            f.field("dedup_key", &true);
+        }
+
+        if self.bearer_auth_provider.is_some() {
+            f.field("bearer_auth_provider", &true);
+        }
+
+        #[cfg(feature = "cache")]
+        if let Some(ref cache) = self.http_cache {
+            f.field("http_cache", cache);
+        }
+
+        if !self.hooks.is_empty() {
+            f.field("hooks", &self.hooks);
+        }
+
+        if self.metrics_observer.is_some() {
+            f.field("metrics_observer", &true);
+        }
+
+        if let Some(ref request_id) = self.request_id {
+            f.field("request_id", request_id);
+        }
+
+        if self.download_progress.is_some() {
+            f.field("download_progress", &true);
+        }
+
+        if let Some(max) = self.max_response_body_size {
+            f.field("max_response_body_size", &max);
+        }
+
+        if self.strict_content_length {
+            f.field("strict_content_length", &true);
+        }
+
+        if let Some(threshold) = self.expect_continue_threshold {
+            f.field("expect_continue_threshold", &threshold);
+        }
+
+        #[cfg(feature = "mock")]
+        if self.transport.is_some() {
+            f.field("transport", &true);
+        }
+
         if self.referer {
             f.field("referer", &true);
         }
 
         f.field("default_headers", &self.headers);
 
+        if let Some(ref base_url) = self.base_url {
+            f.field("base_url", base_url);
+        }
+
+        if self.idna != IdnaPolicy::default() {
+            f.field("idna", &self.idna);
+        }
+
+        if !self.default_query.is_empty() {
+            // Only the keys, the values may be secrets.
+            let keys = self.default_query.iter().map(|(key, _)| key);
+            f.field("default_query", &keys.collect::<Vec<_>>());
+        }
+
         if let Some(ref d) = self.connect_timeout {
             f.field("connect_timeout", d);
         }
 
+        if let Some(ref addrs) = self.proxy_protocol {
+            f.field("proxy_protocol_v2", addrs);
+        }
+
+        #[cfg(feature = "chaos")]
+        if let Some(ref chaos) = self.chaos {
+            f.field("chaos", chaos);
+        }
+
+        #[cfg(feature = "tracing")]
+        if let Some(ref logging) = self.logging {
+            f.field("logging", logging);
+        }
+
+        #[cfg(feature = "tracing")]
+        if self.trace_path.is_some() {
+            f.field("trace_path", &true);
+        }
+
+        if !self.layers.is_empty() {
+            f.field("layers", &self.layers.len());
+        }
+
         if let Some(ref d) = self.timeout {
             f.field("timeout", d);
         }
@@ -1687,6 +3730,22 @@ impl Config {
             }
 
             f.field("tls_info", &self.tls_info);
+
+            if self.protocol_info {
+                f.field("protocol_info", &true);
+            }
+
+            if self.lazy_tls_init {
+                f.field("lazy_tls_init", &true);
+            }
+
+            if self.adaptive_http_version {
+                f.field("adaptive_http_version", &true);
+            }
+
+            if self.alt_svc {
+                f.field("alt_svc", &true);
+            }
         }
 
         if self.https_only {
@@ -1708,15 +3767,174 @@ struct ClientRef {
     cookie_store: Option<Arc<dyn cookie::CookieStore>>,
     headers: HeaderMap,
     headers_order: Option<&'static [HeaderName]>,
+    base_url: Option<Url>,
+    idna: IdnaPolicy,
+    default_query: Vec<(String, String)>,
     hyper: HyperClient,
+    connect_to: Arc<ConnectToClients>,
     redirect_policy: Arc<redirect::Policy>,
+    retry_policy: Option<Arc<retry::Policy>>,
+    retry_budget: Option<Arc<RetryBudget>>,
+    respect_retry_after: Option<Duration>,
+    hedge_threshold: Option<Duration>,
+    rate_limiter: Arc<RateLimiter>,
+    dedup: Option<Arc<Dedup>>,
+    bearer: Option<Arc<BearerAuth>>,
+    #[cfg(feature = "cache")]
+    cache: Option<Arc<crate::cache::HttpCache>>,
+    #[cfg(feature = "boring-tls")]
+    digest: Arc<DigestCache>,
     referer: bool,
     request_timeout: Option<Duration>,
     proxies_maybe_http_auth: bool,
     https_only: bool,
+    #[cfg(feature = "boring-tls")]
+    alt_svc: Option<crate::AltSvcCache>,
+    #[cfg(feature = "websocket")]
+    ws_behavior: crate::WsBehaviorSettings,
+    #[cfg(feature = "websocket")]
+    ws_pool: Arc<crate::client::websocket::WsPoolState>,
+    #[cfg(feature = "tracing")]
+    logging: Option<Arc<crate::middleware::LoggingMiddleware>>,
+    #[cfg(feature = "tracing")]
+    trace_path: Option<TracePath>,
+    hooks: Option<Arc<Hooks>>,
+    metrics_observer: Option<Arc<dyn MetricsObserver>>,
+    request_id: Option<RequestIdPolicy>,
+    download_progress: Option<Progress>,
+    max_response_body_size: Option<u64>,
+    strict_content_length: bool,
+    expect_continue_threshold: Option<u64>,
+    expect_continue_timeout: Duration,
+    max_response_headers: usize,
+    max_response_header_size: Option<usize>,
+    #[cfg(feature = "mock")]
+    transport: Option<Arc<dyn crate::testing::mock::MockTransport>>,
+    layers: Vec<BoxLayer>,
+    shutdown: Arc<Shutdown>,
+}
+
+/// The clients of the requests sent with `RequestBuilder::connect_to` or
+/// `RequestBuilder::min_tls_version`, one per set of overrides, so that
+/// their connections are pooled apart.
+struct ConnectToClients {
+    builder: hyper::client::Builder,
+    connector: Connector,
+    // Few enough to be searched in order.
+    clients: Mutex<Vec<(ConnectOverrides, HyperClient)>>,
+}
+
+impl ConnectToClients {
+    fn get(&self, overrides: ConnectOverrides) -> HyperClient {
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, client)) = clients.iter().find(|(o, _)| *o == overrides) {
+            return client.clone();
+        }
+        let client = self.builder.build(self.connector.with_overrides(overrides));
+        clients.push((overrides, client.clone()));
+        client
+    }
 }
 
 impl ClientRef {
+    fn add_default_query(&self, url: &mut Url) {
+        if self.default_query.is_empty() {
+            return;
+        }
+
+        let missing = self
+            .default_query
+            .iter()
+            .filter(|(key, _)| !url.query_pairs().any(|(k, _)| k == key.as_str()))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            url.query_pairs_mut().extend_pairs(missing);
+        }
+    }
+
+    /// Sends one request, after running the request hooks on it.
+    fn request(
+        self: &Arc<Self>,
+        method: Method,
+        url: &Url,
+        uri: Uri,
+        version: Option<Version>,
+        headers: HeaderMap,
+        body: Body,
+        overrides: ConnectOverrides,
+        written: &Written,
+    ) -> ResponseFuture {
+        #[cfg(feature = "mock")]
+        let direct = self.hooks.is_none() && self.transport.is_none();
+        #[cfg(not(feature = "mock"))]
+        let direct = self.hooks.is_none();
+        let connect_to = (!overrides.is_empty()).then(|| self.connect_to.get(overrides));
+
+        if direct {
+            let mut req = hyper::Request::builder().method(method).uri(uri);
+            if let Some(version) = version {
+                req = req.version(version);
+            }
+            let mut req = req
+                .body(body.with_written(written).into_stream())
+                .expect("valid request parts");
+            *req.headers_mut() = headers;
+            written.capture(&mut req);
+            let hyper = connect_to.as_ref().unwrap_or(&self.hyper);
+            return ResponseFuture::Default(hyper.request(req));
+        }
+
+        let client = self.clone();
+        let mut req = Request::new(method, url.clone());
+        *req.headers_mut() = headers;
+        *req.body_mut() = Some(body);
+        if let Some(version) = version {
+            *req.version_mut() = version;
+        }
+
+        let url = url.clone();
+        let written = written.clone();
+        ResponseFuture::Hooked(Box::pin(async move {
+            let req = match client.hooks {
+                Some(ref hooks) => hooks.on_request(req).await?,
+                None => req,
+            };
+            let sent = hooks::snapshot(&req);
+
+            #[cfg(feature = "mock")]
+            if let Some(ref transport) = client.transport {
+                let url = req.url().clone();
+                let res = transport
+                    .handle(req)
+                    .map_err(|e| error::request_boxed(e).with_url(url))?;
+                return Ok((sent, Ok(res.map(hyper::Body::from))));
+            }
+
+            let (method, hooked_url, headers, body, _, version) = req.pieces();
+            // Keep a raw path and query unless a hook changed the URL.
+            let uri = if hooked_url == url {
+                uri
+            } else {
+                try_uri(&hooked_url).ok_or_else(|| error::url_bad_scheme(hooked_url))?
+            };
+            let mut req = hyper::Request::builder()
+                .method(method)
+                .uri(uri)
+                .version(version)
+                .body(
+                    body.unwrap_or_else(Body::empty)
+                        .with_written(&written)
+                        .into_stream(),
+                )
+                .expect("valid request parts");
+            *req.headers_mut() = headers;
+            written.capture(&mut req);
+
+            let hyper = connect_to.as_ref().unwrap_or(&client.hyper);
+            Ok((sent, hyper.request(req).await))
+        }))
+    }
+
     fn fmt_fields(&self, f: &mut fmt::DebugStruct<'_, '_>) {
         // Instead of deriving Debug, only print fields when their output
         // would provide relevant or interesting data.
@@ -1739,12 +3957,93 @@ impl ClientRef {
             f.field("redirect_policy", &self.redirect_policy);
         }
 
+        if let Some(ref retry_policy) = self.retry_policy {
+            f.field("retry_policy", retry_policy);
+        }
+
+        if let Some(ref retry_budget) = self.retry_budget {
+            f.field("retry_budget", &retry_budget.settings());
+        }
+
+        if let Some(ref respect_retry_after) = self.respect_retry_after {
+            f.field("respect_retry_after", respect_retry_after);
+        }
+
+        if let Some(ref hedge_threshold) = self.hedge_threshold {
+            f.field("hedge_threshold", hedge_threshold);
+        }
+
+        let rate_limits = self.rate_limiter.limits();
+        if !rate_limits.is_empty() {
+            f.field("rate_limits", &rate_limits);
+        }
+
+        if self.dedup.is_some() {
+            f.field("deduplicate_requests", &true);
+        }
+
+        if self.bearer.is_some() {
+            f.field("bearer_auth_provider", &true);
+        }
+
+        #[cfg(feature = "cache")]
+        if self.cache.is_some() {
+            f.field("http_cache", &true);
+        }
+
+        if let Some(ref hooks) = self.hooks {
+            f.field("hooks", hooks);
+        }
+
+        if self.metrics_observer.is_some() {
+            f.field("metrics_observer", &true);
+        }
+
+        if let Some(ref request_id) = self.request_id {
+            f.field("request_id", request_id);
+        }
+
+        if self.download_progress.is_some() {
+            f.field("download_progress", &true);
+        }
+
+        if let Some(max) = self.max_response_body_size {
+            f.field("max_response_body_size", &max);
+        }
+
+        if self.strict_content_length {
+            f.field("strict_content_length", &true);
+        }
+
+        if let Some(threshold) = self.expect_continue_threshold {
+            f.field("expect_continue_threshold", &threshold);
+        }
+
+        #[cfg(feature = "mock")]
+        if self.transport.is_some() {
+            f.field("transport", &true);
+        }
+
         if self.referer {
             f.field("referer", &true);
         }
 
         f.field("default_headers", &self.headers);
 
+        if let Some(ref base_url) = self.base_url {
+            f.field("base_url", base_url);
+        }
+
+        if self.idna != IdnaPolicy::default() {
+            f.field("idna", &self.idna);
+        }
+
+        if !self.default_query.is_empty() {
+            // Only the keys, the values may be secrets.
+            let keys = self.default_query.iter().map(|(key, _)| key);
+            f.field("default_query", &keys.collect::<Vec<_>>());
+        }
+
         if let Some(ref d) = self.request_timeout {
             f.field("timeout", d);
         }
@@ -1760,6 +4059,7 @@ pin_project! {
 
 enum PendingInner {
     Request(PendingRequest),
+    Boxed(Pin<Box<dyn Future<Output = Result<Response, crate::Error>> + Send>>),
     Error(Option<crate::Error>),
 }
 
@@ -1767,24 +4067,51 @@ pin_project! {
     struct PendingRequest {
         method: Method,
         url: Url,
+        // Sent instead of the path and query of `url`, until a redirect.
+        raw_path_and_query: Option<PathAndQuery>,
+        // How to connect instead of the client's way. The address to connect
+        // to instead of the host of `url` is kept until a redirect to another
+        // origin.
+        overrides: ConnectOverrides,
         headers: HeaderMap,
         body: Option<Option<Bytes>>,
+        upload_progress: Option<Progress>,
+        download_progress: Option<Progress>,
+        max_response_body_size: Option<u64>,
+        // The codings the response is decompressed from.
+        accepts: Accepts,
 
         urls: Vec<Url>,
 
         retry_count: usize,
 
+        // The request sent last, for the response hooks.
+        sent: Option<Request>,
+
+        // Tells a body held back by `Expect: 100-continue` the final
+        // response came.
+        responded: Option<oneshot::Sender<()>>,
+
         client: Arc<ClientRef>,
 
         #[pin]
         in_flight: ResponseFuture,
         #[pin]
         timeout: Option<Pin<Box<Sleep>>>,
+        // When the request started, for the elapsed time of a timeout.
+        started: Instant,
+        // How far the request sent last got, for the phase of a timeout.
+        written: Written,
     }
 }
 
+type HookedResponse = (Request, Result<hyper::Response<hyper::Body>, hyper::Error>);
+
 enum ResponseFuture {
     Default(HyperResponseFuture),
+    /// Runs the request hooks first, then yields the request they produced
+    /// along with the response, possibly from a mock transport.
+    Hooked(Pin<Box<dyn Future<Output = crate::Result<HookedResponse>> + Send>>),
 }
 
 impl PendingRequest {
@@ -1804,8 +4131,26 @@ impl PendingRequest {
         self.project().headers
     }
 
+    /// The body to send again, after a redirect or a retry.
+    fn resend_body(&self, body: Body) -> Body {
+        match self.upload_progress {
+            Some(ref progress) => body.with_progress(progress.clone()),
+            None => body,
+        }
+    }
+
+    fn on_response(&self, res: &Response) {
+        if let (Some(hooks), Some(sent)) = (&self.client.hooks, &self.sent) {
+            hooks.on_response(sent, res);
+        }
+    }
+
     fn retry_error(mut self: Pin<&mut Self>, err: &(dyn std::error::Error + 'static)) -> bool {
-        if !is_retryable_error(err) {
+        let h2 = self
+            .written
+            .connection_extras()
+            .and_then(|mut extensions| extensions.remove::<H2Handle>());
+        if !is_retryable_error(err, h2.as_ref()) {
             return false;
         }
 
@@ -1819,39 +4164,71 @@ impl PendingRequest {
             }
             None => Body::empty(),
         };
+        let body = self.resend_body(body);
 
         if self.retry_count >= 2 {
             trace!("retry count too high");
             return false;
         }
         self.retry_count += 1;
-
-        let uri = expect_uri(&self.url);
-
-        *self.as_mut().in_flight().get_mut() = match *self.as_mut().in_flight().as_ref() {
-            _ => {
-                let mut req = hyper::Request::builder()
-                    .method(self.method.clone())
-                    .uri(uri)
-                    .body(body.into_stream())
-                    .expect("valid request parts");
-                *req.headers_mut() = self.headers.clone();
-                ResponseFuture::Default(self.client.hyper.request(req))
-            }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            target: "rquest",
+            attempt = self.retry_count + 1,
+            error = %err,
+            "retrying request"
+        );
+
+        let uri = match self.raw_path_and_query {
+            Some(ref raw) => with_path_and_query(expect_uri(&self.url), raw.clone()),
+            None => expect_uri(&self.url),
         };
 
+        let written = Written::new();
+        *self.as_mut().in_flight().get_mut() = self.client.request(
+            self.method.clone(),
+            &self.url,
+            uri,
+            None,
+            self.headers.clone(),
+            body,
+            self.overrides,
+            &written,
+        );
+        self.written = written;
+
         true
     }
 }
 
-fn is_retryable_error(err: &(dyn std::error::Error + 'static)) -> bool {
-    if let Some(cause) = err.source() {
+/// Whether the request was refused by an HTTP/2 server shutting the
+/// connection down gracefully.
+///
+/// After a `GOAWAY`, the streams up to its last stream ID complete on the
+/// connection, which takes no new ones and leaves the pool once they are
+/// done. The later ones were not processed and fail with this error, so they
+/// can be sent again on a new connection.
+///
+/// h2 only reports the reason of the `GOAWAY`, its last stream ID is read
+/// from the frames of `h2`, the connection the request was sent on.
+fn is_retryable_error(err: &(dyn std::error::Error + 'static), h2: Option<&H2Handle>) -> bool {
+    let mut source = err.source();
+    while let Some(cause) = source {
         if let Some(err) = cause.downcast_ref::<hyper::h2::Error>() {
+            if !err.is_go_away() || !err.is_remote() {
+                return false;
+            }
             // They sent us a graceful shutdown, try with a new connection!
-            return err.is_go_away()
-                && err.is_remote()
-                && err.reason() == Some(hyper::h2::Reason::NO_ERROR);
+            let graceful = err.reason() == Some(hyper::h2::Reason::NO_ERROR);
+            debug!(
+                "HTTP/2 connection going away with {:?}, last stream ID {:?}, retry: {}",
+                err.reason(),
+                h2.and_then(H2Handle::go_away_stream_id),
+                graceful
+            );
+            return graceful;
         }
+        source = cause.source();
     }
     false
 }
@@ -1875,6 +4252,7 @@ impl Future for Pending {
         let inner = self.inner();
         match inner.get_mut() {
             PendingInner::Request(ref mut req) => Pin::new(req).poll(cx),
+            PendingInner::Boxed(ref mut fut) => fut.as_mut().poll(cx),
             PendingInner::Error(ref mut err) => Poll::Ready(Err(err
                 .take()
                 .expect("Pending error polled more than once"))),
@@ -1888,8 +4266,10 @@ impl Future for PendingRequest {
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         if let Some(delay) = self.as_mut().timeout().as_mut().as_pin_mut() {
             if let Poll::Ready(()) = delay.poll(cx) {
+                let phase = self.written.timeout_phase();
+                let timed_out = error::TimedOut::new(phase, self.started.elapsed());
                 return Poll::Ready(Err(
-                    crate::error::request(crate::error::TimedOut).with_url(self.url.clone())
+                    crate::error::request(timed_out).with_url(self.url.clone())
                 ));
             }
         }
@@ -1897,19 +4277,57 @@ impl Future for PendingRequest {
         loop {
             let res = match self.as_mut().in_flight().get_mut() {
                 ResponseFuture::Default(r) => match Pin::new(r).poll(cx) {
-                    Poll::Ready(Err(e)) => {
-                        if self.as_mut().retry_error(&e) {
-                            continue;
-                        }
-                        return Poll::Ready(Err(
-                            crate::error::request(e).with_url(self.url.clone())
-                        ));
+                    Poll::Ready(res) => res,
+                    Poll::Pending => return Poll::Pending,
+                },
+                ResponseFuture::Hooked(r) => match r.as_mut().poll(cx) {
+                    Poll::Ready(Ok((sent, res))) => {
+                        self.sent = Some(sent);
+                        res
                     }
-                    Poll::Ready(Ok(res)) => res,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
                     Poll::Pending => return Poll::Pending,
                 },
             };
 
+            if let Some(responded) = self.responded.take() {
+                let _ = responded.send(());
+            }
+
+            let res = match res {
+                Ok(res) => res,
+                Err(e) => {
+                    if self.as_mut().retry_error(&e) {
+                        continue;
+                    }
+                    return Poll::Ready(Err(crate::error::request(e).with_url(self.url.clone())));
+                }
+            };
+
+            if let Err(e) = check_header_limits(
+                res.headers(),
+                self.client.max_response_headers,
+                self.client
+                    .max_response_header_size
+                    .unwrap_or(DEFAULT_MAX_RESPONSE_HEADER_SIZE),
+            ) {
+                return Poll::Ready(Err(crate::error::request(e).with_url(self.url.clone())));
+            }
+
+            // The server of an HTTP/2 connection may leave its origin out of
+            // the `ORIGIN` frames it sends, it is not sent other requests then.
+            if res
+                .extensions()
+                .get::<H2Handle>()
+                .is_some_and(|h2| h2.excludes(&self.url))
+            {
+                debug!(
+                    "connection is not authoritative for {}",
+                    self.url.origin().ascii_serialization()
+                );
+                self.written.poison_connection();
+            }
+
             #[cfg(feature = "cookies")]
             {
                 if let Some(ref cookie_store) = self.client.cookie_store {
@@ -1920,6 +4338,10 @@ impl Future for PendingRequest {
                     }
                 }
             }
+            #[cfg(feature = "boring-tls")]
+            if let Some(ref alt_svc) = self.client.alt_svc {
+                alt_svc.update(&self.url, res.headers());
+            }
             let should_redirect = match res.status() {
                 StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND | StatusCode::SEE_OTHER => {
                     self.body = None;
@@ -1989,6 +4411,8 @@ impl Future for PendingRequest {
                     match action {
                         redirect::ActionKind::Follow => {
                             debug!("redirecting '{}' to '{}'", self.url, loc);
+                            #[cfg(feature = "tracing")]
+                            trace::redirect(res.status(), &loc, self.client.trace_path.as_ref());
 
                             if loc.scheme() != "http" && loc.scheme() != "https" {
                                 return Poll::Ready(Err(error::url_bad_scheme(loc)));
@@ -2001,7 +4425,24 @@ impl Future for PendingRequest {
                                 )));
                             }
 
+                            if self.client.hooks.is_some() {
+                                let hop = Response::new(
+                                    res,
+                                    self.url.clone(),
+                                    Accepts::none(),
+                                    None,
+                                    None,
+                                    false,
+                                );
+                                self.on_response(&hop);
+                            }
+
+                            // An address to connect to only stands for its origin.
+                            if loc.origin() != self.url.origin() {
+                                self.overrides.addr = None;
+                            }
                             self.url = loc;
+                            self.raw_path_and_query = None;
                             let mut headers =
                                 std::mem::replace(self.as_mut().headers(), HeaderMap::new());
 
@@ -2011,6 +4452,7 @@ impl Future for PendingRequest {
                                 Some(Some(ref body)) => Body::reusable(body.clone()),
                                 _ => Body::empty(),
                             };
+                            let body = self.resend_body(body);
 
                             // Add cookies from the cookie store.
                             #[cfg(feature = "cookies")]
@@ -2020,19 +4462,20 @@ impl Future for PendingRequest {
                                 }
                             }
 
-                            *self.as_mut().in_flight().get_mut() =
-                                match *self.as_mut().in_flight().as_ref() {
-                                    _ => {
-                                        let mut req = hyper::Request::builder()
-                                            .method(self.method.clone())
-                                            .uri(uri.clone())
-                                            .body(body.into_stream())
-                                            .expect("valid request parts");
-                                        *req.headers_mut() = headers.clone();
-                                        std::mem::swap(self.as_mut().headers(), &mut headers);
-                                        ResponseFuture::Default(self.client.hyper.request(req))
-                                    }
-                                };
+                            let written = Written::new();
+                            let in_flight = self.client.request(
+                                self.method.clone(),
+                                &self.url,
+                                uri,
+                                None,
+                                headers.clone(),
+                                body,
+                                self.overrides,
+                                &written,
+                            );
+                            self.written = written;
+                            std::mem::swap(self.as_mut().headers(), &mut headers);
+                            *self.as_mut().in_flight().get_mut() = in_flight;
 
                             continue;
                         }
@@ -2046,12 +4489,16 @@ impl Future for PendingRequest {
                 }
             }
 
-            let res = Response::new(
+            let mut res = Response::new(
                 res,
                 self.url.clone(),
-                self.client.accepts,
-                self.timeout.take(),
+                self.accepts,
+                self.timeout.take().map(|timeout| (timeout, self.started)),
+                self.download_progress.clone(),
+                self.client.strict_content_length,
             );
+            res.limit_body(self.max_response_body_size);
+            self.on_response(&res);
             return Poll::Ready(Ok(res));
         }
     }
@@ -2065,11 +4512,38 @@ impl fmt::Debug for Pending {
                 .field("method", &req.method)
                 .field("url", &req.url)
                 .finish(),
+            PendingInner::Boxed(_) => f.debug_struct("Pending").finish_non_exhaustive(),
             PendingInner::Error(ref err) => f.debug_struct("Pending").field("error", err).finish(),
         }
     }
 }
 
+fn check_header_limits(
+    headers: &HeaderMap,
+    max_count: usize,
+    max_size: usize,
+) -> Result<(), error::HeadersTooLarge> {
+    if headers.len() > max_count {
+        return Err(error::HeadersTooLarge::Count {
+            count: headers.len(),
+            max: max_count,
+        });
+    }
+
+    let size = headers
+        .iter()
+        .map(|(name, value)| name.as_str().len() + value.len() + 32)
+        .sum::<usize>();
+    if size > max_size {
+        return Err(error::HeadersTooLarge::Size {
+            size,
+            max: max_size,
+        });
+    }
+
+    Ok(())
+}
+
 fn make_referer(next: &Url, previous: &Url) -> Option<HeaderValue> {
     if next.scheme() == "http" && previous.scheme() == "https" {
         return None;
@@ -2082,6 +4556,15 @@ fn make_referer(next: &Url, previous: &Url) -> Option<HeaderValue> {
     referer.as_str().parse().ok()
 }
 
+fn host_first(mut headers: HeaderMap) -> HeaderMap {
+    let mut sorted = HeaderMap::with_capacity(headers.keys_len());
+    if let Some(host) = headers.remove(HOST) {
+        sorted.insert(HOST, host);
+    }
+    sorted.extend(headers);
+    sorted
+}
+
 #[cfg(feature = "cookies")]
 fn add_cookie_header(headers: &mut HeaderMap, cookie_store: &dyn cookie::CookieStore, url: &Url) {
     if let Some(header) = cookie_store.cookies(url) {
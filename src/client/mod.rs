@@ -1,16 +1,45 @@
 pub use self::body::Body;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub use self::compress::Compression;
+pub use self::decoder::AcceptEncoding;
+pub use self::hedge::HedgedResponse;
 pub use self::http::{Client, ClientBuilder, HttpVersionPref};
-pub use self::request::{Request, RequestBuilder};
+pub use self::rate_limit::RateLimit;
+pub use self::request::{Request, RequestBuilder, RequestTarget};
+pub use self::request_id::{RequestId, RequestIdPolicy};
 pub use self::response::Response;
+pub use self::shutdown::ShutdownReport;
 pub use self::upgrade::Upgraded;
+pub use self::validation::{ValidationError, ValidationWarning};
+pub use self::validators::ValidatorStore;
 
+mod bearer;
 pub mod body;
+#[cfg(feature = "brotli")]
+mod brotli;
+#[cfg(feature = "cache")]
+pub mod cache;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+mod compress;
 pub mod decoder;
+mod dedup;
+#[cfg(feature = "boring-tls")]
+mod digest;
+mod env;
+mod hedge;
+mod hooks;
 pub mod http;
 #[cfg(feature = "multipart")]
 pub mod multipart;
+mod rate_limit;
 pub(crate) mod request;
+mod request_id;
 mod response;
+mod shutdown;
+#[cfg(feature = "tracing")]
+mod trace;
 mod upgrade;
+pub(crate) mod validation;
+mod validators;
 #[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
 pub mod websocket;
@@ -252,21 +252,53 @@ impl Part {
         Part::new(value.into(), Some(length))
     }
 
+    /// Makes a new parameter from an arbitrary stream, sent as a file named
+    /// `filename`.
+    ///
+    /// The mime of the part is guessed from the extension of `filename`, and
+    /// left unset if unknown.
+    pub fn stream_with_file_name<T, U>(value: T, filename: U) -> Part
+    where
+        T: Into<Body>,
+        U: Into<Cow<'static, str>>,
+    {
+        let filename = filename.into();
+        let part = match mime_guess::from_path(&*filename).first() {
+            Some(mime) => Part::stream(value).mime(mime),
+            None => Part::stream(value),
+        };
+        part.file_name(filename)
+    }
+
     /// Makes a file parameter.
     ///
+    /// The file name of the part is the one of `path`. Its mime is guessed
+    /// from the extension of `path`, or else from the first bytes of the
+    /// file, defaulting to `application/octet-stream`.
+    ///
     /// # Errors
     ///
     /// Errors when the file cannot be opened.
     #[cfg(feature = "stream")]
     #[cfg_attr(docsrs, doc(cfg(feature = "stream")))]
     pub async fn file<T: AsRef<Path>>(path: T) -> io::Result<Part> {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
         let path = path.as_ref();
         let file_name = path
             .file_name()
             .map(|filename| filename.to_string_lossy().into_owned());
         let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
-        let mime = mime_guess::from_ext(ext).first_or_octet_stream();
-        let file = File::open(path).await?;
+        let mut file = File::open(path).await?;
+        let mime = match mime_guess::from_ext(ext).first() {
+            Some(mime) => mime,
+            None => {
+                let mut head = [0; 8];
+                let read = file.read(&mut head).await?;
+                file.rewind().await?;
+                sniff_mime(&head[..read])
+            }
+        };
         let field = Part::stream(file).mime(mime);
         Ok(if let Some(file_name) = file_name {
             field.file_name(file_name)
@@ -433,6 +465,22 @@ impl<P: fmt::Debug> FormParts<P> {
     }
 }
 
+/// Guesses the mime of a file from its first bytes.
+#[cfg(feature = "stream")]
+fn sniff_mime(head: &[u8]) -> Mime {
+    if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        mime::IMAGE_PNG
+    } else if head.starts_with(b"\xff\xd8\xff") {
+        mime::IMAGE_JPEG
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        mime::IMAGE_GIF
+    } else if head.starts_with(b"%PDF-") {
+        mime::APPLICATION_PDF
+    } else {
+        mime::APPLICATION_OCTET_STREAM
+    }
+}
+
 // ===== impl PartMetadata =====
 
 impl PartMetadata {
@@ -713,6 +761,49 @@ mod tests {
         assert_eq!(body_part.value_len().unwrap(), bytes_len as u64);
     }
 
+    #[test]
+    fn stream_with_file_name_guesses_mime() {
+        let part = Part::stream_with_file_name("{}", "data.json");
+        assert_eq!(part.meta.mime, Some(mime::APPLICATION_JSON));
+        assert_eq!(part.meta.file_name.as_deref(), Some("data.json"));
+
+        let part = Part::stream_with_file_name("", "data");
+        assert_eq!(part.meta.mime, None);
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn file_detects_mime() {
+        let dir = std::env::temp_dir().join(format!("rquest-multipart-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let rt = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("new rt");
+        let mime_of = |name: &str, contents: &[u8]| {
+            let path = dir.join(name);
+            std::fs::write(&path, contents).unwrap();
+            let part = rt.block_on(Part::file(&path)).unwrap();
+            assert_eq!(part.meta.file_name.as_deref(), Some(name));
+            part.meta.mime.unwrap()
+        };
+
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let pdf = b"%PDF-1.7\n%\xe2\xe3\xcf\xd3\n";
+        assert_eq!(mime_of("image.png", png), mime::IMAGE_PNG);
+        assert_eq!(mime_of("image", png), mime::IMAGE_PNG);
+        assert_eq!(mime_of("doc.pdf", pdf), mime::APPLICATION_PDF);
+        assert_eq!(mime_of("doc", pdf), mime::APPLICATION_PDF);
+        assert_eq!(mime_of("data.json", b"{}"), mime::APPLICATION_JSON);
+        assert_eq!(mime_of("data", b"{}"), mime::APPLICATION_OCTET_STREAM);
+
+        let part = rt.block_on(Part::file(dir.join("image"))).unwrap();
+        let body = part.value.into_stream().map_ok(|c| c.to_vec()).try_concat();
+        assert_eq!(rt.block_on(body).unwrap(), png);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn header_percent_encoding() {
         let name = "start%'\"\r\nßend";
@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+use crate::error::RateLimited;
+
+/// A limit on the rate requests are sent at, see
+/// [`ClientBuilder::rate_limit`](crate::ClientBuilder::rate_limit).
+///
+/// Requests go through as with a token bucket: up to `requests` of them at
+/// once, then one every `per / requests`. The others wait their turn, in
+/// the order they were sent.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use rquest::RateLimit;
+///
+/// let client = rquest::Client::builder()
+///     .rate_limit(RateLimit::per_host(10, Duration::from_secs(1)))
+///     .rate_limit(RateLimit::global(100, Duration::from_secs(1)).max_queue(1000))
+///     .rate_limit_for_host(
+///         "*.slow.example.com",
+///         RateLimit::per_host(1, Duration::from_secs(1)).max_wait(Duration::from_secs(5)),
+///     )
+///     .build()?;
+/// # Ok::<_, rquest::Error>(())
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    per_host: bool,
+    requests: u32,
+    per: Duration,
+    max_queue: Option<usize>,
+    max_wait: Option<Duration>,
+}
+
+impl RateLimit {
+    /// `requests` every `per`, to each host.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests` or `per` is zero.
+    pub fn per_host(requests: u32, per: Duration) -> RateLimit {
+        RateLimit::new(true, requests, per)
+    }
+
+    /// `requests` every `per`, across all hosts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests` or `per` is zero.
+    pub fn global(requests: u32, per: Duration) -> RateLimit {
+        RateLimit::new(false, requests, per)
+    }
+
+    fn new(per_host: bool, requests: u32, per: Duration) -> RateLimit {
+        assert!(requests > 0, "rate limit of zero requests");
+        assert!(!per.is_zero(), "rate limit over a zero duration");
+        RateLimit {
+            per_host,
+            requests,
+            per,
+            max_queue: None,
+            max_wait: None,
+        }
+    }
+
+    /// Fail the requests that would wait behind `max` others, instead of
+    /// queueing them.
+    ///
+    /// Default is no maximum.
+    pub fn max_queue(mut self, max: usize) -> RateLimit {
+        self.max_queue = Some(max);
+        self
+    }
+
+    /// Fail the requests that would wait longer than `max`, instead of
+    /// queueing them.
+    ///
+    /// Default is no maximum.
+    pub fn max_wait(mut self, max: Duration) -> RateLimit {
+        self.max_wait = Some(max);
+        self
+    }
+
+    /// The tokens refilled per second.
+    fn rate(&self) -> f64 {
+        self.requests as f64 / self.per.as_secs_f64()
+    }
+}
+
+/// The limits of a client.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RateLimits {
+    global: Option<RateLimit>,
+    per_host: Option<RateLimit>,
+    /// Host patterns and their limits, replacing `per_host` for the hosts
+    /// they match. The first match wins.
+    hosts: Vec<(String, RateLimit)>,
+}
+
+impl RateLimits {
+    /// Sets the global or per host limit, after the scope of `limit`.
+    pub(crate) fn set(&mut self, limit: RateLimit) {
+        if limit.per_host {
+            self.per_host = Some(limit);
+        } else {
+            self.global = Some(limit);
+        }
+    }
+
+    pub(crate) fn set_for_host(&mut self, pattern: &str, limit: RateLimit) {
+        let pattern = pattern.to_ascii_lowercase();
+        match self.hosts.iter_mut().find(|(p, _)| *p == pattern) {
+            Some((_, existing)) => *existing = limit,
+            None => self.hosts.push((pattern, limit)),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.global.is_none() && self.per_host.is_none() && self.hosts.is_empty()
+    }
+
+    /// The bucket keys and limits that apply to `host`.
+    fn keys(&self, host: &str) -> impl Iterator<Item = (Key, RateLimit)> {
+        let global = self.global.map(|limit| (Key::Global, limit));
+        let host = match self
+            .hosts
+            .iter()
+            .find(|(pattern, _)| matches(pattern, host))
+        {
+            // A global limit for a pattern is shared by the hosts it matches.
+            Some((pattern, limit)) if !limit.per_host => {
+                Some((Key::Pattern(pattern.clone()), *limit))
+            }
+            Some((_, limit)) => Some((Key::Host(host.to_owned()), *limit)),
+            None => self
+                .per_host
+                .map(|limit| (Key::Host(host.to_owned()), limit)),
+        };
+        global.into_iter().chain(host)
+    }
+}
+
+/// Whether `host` matches `pattern`, a host name or a `*.` wildcard
+/// matching the subdomains of a domain.
+fn matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix('*') {
+        Some(suffix) if suffix.starts_with('.') => host.ends_with(suffix),
+        _ => pattern == host,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Global,
+    Pattern(String),
+    Host(String),
+}
+
+/// Past this many buckets, the idle ones are dropped.
+const MAX_IDLE_BUCKETS: usize = 1024;
+
+/// Delays requests to keep to the limits of a client and its clones.
+#[derive(Debug, Default)]
+pub(crate) struct RateLimiter {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    limits: RateLimits,
+    buckets: HashMap<Key, Arc<Mutex<Bucket>>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    limit: RateLimit,
+    /// Negative once requests are waiting for tokens.
+    tokens: f64,
+    updated: Instant,
+    /// The requests waiting for a token.
+    queued: usize,
+}
+
+impl Bucket {
+    fn new(limit: RateLimit, now: Instant) -> Bucket {
+        Bucket {
+            limit,
+            tokens: limit.requests as f64,
+            updated: now,
+            queued: 0,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.rate()).min(self.capacity());
+        self.updated = now;
+    }
+
+    fn capacity(&self) -> f64 {
+        self.limit.requests as f64
+    }
+
+    /// How long a request sent now waits for its token.
+    fn wait(&self) -> Duration {
+        let missing = 1.0 - self.tokens;
+        if missing <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(missing / self.limit.rate())
+        }
+    }
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limits: RateLimits) -> RateLimiter {
+        RateLimiter {
+            state: Mutex::new(State {
+                limits,
+                buckets: HashMap::new(),
+            }),
+        }
+    }
+
+    pub(crate) fn limits(&self) -> RateLimits {
+        self.lock().limits.clone()
+    }
+
+    /// Changes the limits, the requests already waiting keep their turn.
+    pub(crate) fn update(&self, f: impl FnOnce(&mut RateLimits)) {
+        f(&mut self.lock().limits);
+    }
+
+    /// Takes a token for a request to `url`, from each of the buckets that
+    /// apply to it.
+    ///
+    /// `None` if the request can be sent right away, otherwise the
+    /// reservation to wait on, unless the wait goes over the `max_queue` or
+    /// `max_wait` of a bucket.
+    pub(crate) fn reserve(&self, url: &Url) -> Result<Option<Reservation>, RateLimited> {
+        let mut state = self.lock();
+        if state.limits.is_empty() {
+            return Ok(None);
+        }
+
+        let now = Instant::now();
+        let host = url.host_str().unwrap_or_default();
+        let keys: Vec<_> = state.limits.keys(host).collect();
+        let mut buckets = Vec::with_capacity(keys.len());
+        let mut wait = Duration::ZERO;
+        for (key, limit) in keys {
+            let bucket = state.bucket(key, limit, now);
+            {
+                let mut b = lock(&bucket);
+                if b.limit != limit {
+                    b.limit = limit;
+                    b.tokens = b.tokens.min(b.capacity());
+                }
+                b.refill(now);
+                let bucket_wait = b.wait();
+                if !bucket_wait.is_zero() {
+                    if let Some(max_queue) = limit.max_queue.filter(|max| b.queued >= *max) {
+                        return Err(RateLimited::QueueFull { max_queue });
+                    }
+                    if let Some(max_wait) = limit.max_wait.filter(|max| bucket_wait > *max) {
+                        return Err(RateLimited::WaitTooLong {
+                            wait: bucket_wait,
+                            max_wait,
+                        });
+                    }
+                }
+                wait = wait.max(bucket_wait);
+            }
+            buckets.push(bucket);
+        }
+
+        for bucket in &buckets {
+            lock(bucket).tokens -= 1.0;
+        }
+        if wait.is_zero() {
+            return Ok(None);
+        }
+        for bucket in &buckets {
+            lock(bucket).queued += 1;
+        }
+        Ok(Some(Reservation {
+            buckets,
+            until: now + wait,
+            done: false,
+        }))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl State {
+    fn bucket(&mut self, key: Key, limit: RateLimit, now: Instant) -> Arc<Mutex<Bucket>> {
+        if let Some(bucket) = self.buckets.get(&key) {
+            return bucket.clone();
+        }
+        if self.buckets.len() >= MAX_IDLE_BUCKETS {
+            self.buckets.retain(|_, bucket| {
+                let mut b = lock(bucket);
+                b.refill(now);
+                Arc::strong_count(bucket) > 1 || b.tokens < b.capacity()
+            });
+        }
+        self.buckets
+            .entry(key)
+            .or_insert_with(|| Arc::new(Mutex::new(Bucket::new(limit, now))))
+            .clone()
+    }
+}
+
+fn lock(bucket: &Mutex<Bucket>) -> MutexGuard<'_, Bucket> {
+    bucket.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The turn of a request held back by the rate limits. Dropped before its
+/// turn, it gives its tokens back.
+pub(crate) struct Reservation {
+    buckets: Vec<Arc<Mutex<Bucket>>>,
+    until: Instant,
+    done: bool,
+}
+
+impl Reservation {
+    pub(crate) async fn wait(mut self) {
+        tokio::time::sleep_until(self.until.into()).await;
+        self.done = true;
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        for bucket in &self.buckets {
+            let mut b = lock(bucket);
+            b.queued -= 1;
+            if !self.done {
+                b.tokens = (b.tokens + 1.0).min(b.capacity());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(f: impl FnOnce(&mut RateLimits)) -> RateLimiter {
+        let mut limits = RateLimits::default();
+        f(&mut limits);
+        RateLimiter::new(limits)
+    }
+
+    fn url(host: &str) -> Url {
+        Url::parse(&format!("https://{host}/")).unwrap()
+    }
+
+    #[test]
+    fn bursts_then_queues_per_host() {
+        let limiter = limiter(|limits| limits.set(RateLimit::per_host(2, Duration::from_secs(1))));
+
+        assert!(limiter.reserve(&url("a.test")).unwrap().is_none());
+        assert!(limiter.reserve(&url("a.test")).unwrap().is_none());
+        let third = limiter.reserve(&url("a.test")).unwrap().unwrap();
+        assert!(third.until > Instant::now() + Duration::from_millis(400));
+        // Another host has a bucket of its own.
+        assert!(limiter.reserve(&url("b.test")).unwrap().is_none());
+    }
+
+    #[test]
+    fn fails_fast_past_max_queue_and_max_wait() {
+        let queue = limiter(|limits| {
+            limits.set(RateLimit::global(1, Duration::from_secs(1)).max_queue(1));
+        });
+
+        assert!(queue.reserve(&url("a.test")).unwrap().is_none());
+        let queued = queue.reserve(&url("a.test")).unwrap().unwrap();
+        assert!(matches!(
+            queue.reserve(&url("b.test")),
+            Err(RateLimited::QueueFull { max_queue: 1 })
+        ));
+
+        // Dropped before its turn, the token goes back.
+        drop(queued);
+        assert!(queue.reserve(&url("b.test")).unwrap().is_some());
+
+        let slow = limiter(|limits| {
+            limits.set_for_host(
+                "*.slow.test",
+                RateLimit::per_host(1, Duration::from_secs(10)).max_wait(Duration::from_secs(1)),
+            );
+        });
+        assert!(slow.reserve(&url("api.slow.test")).unwrap().is_none());
+        assert!(matches!(
+            slow.reserve(&url("api.slow.test")),
+            Err(RateLimited::WaitTooLong { .. })
+        ));
+        // Not matched by the pattern, so not limited.
+        assert!(slow.reserve(&url("slow.test")).unwrap().is_none());
+        assert!(slow.reserve(&url("slow.test")).unwrap().is_none());
+    }
+
+    #[test]
+    fn update_changes_limits_at_runtime() {
+        let limiter = limiter(|limits| limits.set(RateLimit::per_host(1, Duration::from_secs(60))));
+        assert!(limiter.reserve(&url("a.test")).unwrap().is_none());
+        assert!(limiter.reserve(&url("a.test")).unwrap().is_some());
+
+        limiter.update(|limits| {
+            limits.set_for_host("a.test", RateLimit::per_host(1, Duration::from_millis(1)))
+        });
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(limiter.reserve(&url("a.test")).unwrap().is_none());
+    }
+}
@@ -1,21 +1,35 @@
 use std::convert::TryFrom;
 use std::fmt;
 use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
 
 use serde::Serialize;
 #[cfg(feature = "json")]
 use serde_json;
 
-use super::body::Body;
+use super::body::{Body, Progress};
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use super::compress::Compression;
+#[cfg(feature = "boring-tls")]
+use super::digest::DigestCredentials;
 use super::http::{Client, Pending};
 #[cfg(feature = "multipart")]
 use super::multipart;
 use super::response::Response;
+use super::validators::ValidatorStore;
+use crate::connect::ConnectOverrides;
 #[cfg(feature = "multipart")]
 use crate::header::CONTENT_LENGTH;
-use crate::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use crate::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT_ENCODING, CONTENT_TYPE, HOST};
+#[cfg(feature = "ntlm")]
+use crate::ntlm::NtlmCredentials;
+use crate::sign::RequestSigner;
+#[cfg(feature = "boring-tls")]
+use crate::tls;
 use crate::{Method, Url};
+use http::uri::PathAndQuery;
 use http::{request::Parts, Request as HttpRequest, Version};
 
 /// A request which can be executed with `Client::execute()`.
@@ -26,6 +40,40 @@ pub struct Request {
     body: Option<Body>,
     timeout: Option<Duration>,
     version: Version,
+    upload_progress: Option<Progress>,
+    download_progress: Option<Progress>,
+    max_response_body_size: Option<u64>,
+    expect_continue: Option<bool>,
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    compression: Option<(Compression, i32)>,
+    #[cfg(feature = "boring-tls")]
+    digest_auth: Option<DigestCredentials>,
+    #[cfg(feature = "ntlm")]
+    ntlm_auth: Option<NtlmCredentials>,
+    signer: Option<Arc<dyn RequestSigner>>,
+    validators: Option<ValidatorStore>,
+    raw_path_and_query: Option<PathAndQuery>,
+    connect_to: Option<SocketAddr>,
+    #[cfg(feature = "boring-tls")]
+    min_tls_version: Option<tls::Version>,
+    hedge: Option<bool>,
+    accept_encoding: Option<String>,
+    request_target: Option<RequestTarget>,
+}
+
+/// The form of the target of an HTTP/1 request line, see
+/// [`RequestBuilder::request_target`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestTarget {
+    /// The path and query, `GET /path?query HTTP/1.1`.
+    Origin,
+    /// The whole URL, `GET http://example.com/path HTTP/1.1`.
+    Absolute,
+    /// The host and port, `CONNECT example.com:443 HTTP/1.1`. Only sent by
+    /// `CONNECT` requests.
+    Authority,
+    /// An asterisk, `OPTIONS * HTTP/1.1`.
+    Asterisk,
 }
 
 /// A builder to construct the properties of a `Request`.
@@ -48,6 +96,25 @@ impl Request {
             body: None,
             timeout: None,
             version: Version::default(),
+            upload_progress: None,
+            download_progress: None,
+            max_response_body_size: None,
+            expect_continue: None,
+            #[cfg(any(feature = "gzip", feature = "zstd"))]
+            compression: None,
+            #[cfg(feature = "boring-tls")]
+            digest_auth: None,
+            #[cfg(feature = "ntlm")]
+            ntlm_auth: None,
+            signer: None,
+            validators: None,
+            raw_path_and_query: None,
+            connect_to: None,
+            #[cfg(feature = "boring-tls")]
+            min_tls_version: None,
+            hedge: None,
+            accept_encoding: None,
+            request_target: None,
         }
     }
 
@@ -136,9 +203,116 @@ impl Request {
         *req.headers_mut() = self.headers().clone();
         *req.version_mut() = self.version();
         req.body = body;
+        req.upload_progress = self.upload_progress.clone();
+        req.download_progress = self.download_progress.clone();
+        req.max_response_body_size = self.max_response_body_size;
+        req.expect_continue = self.expect_continue;
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        {
+            req.compression = self.compression;
+        }
+        req.signer = self.signer.clone();
+        req.validators = self.validators.clone();
+        req.raw_path_and_query = self.raw_path_and_query.clone();
+        req.connect_to = self.connect_to;
+        req.hedge = self.hedge;
+        req.accept_encoding = self.accept_encoding.clone();
+        req.request_target = self.request_target;
+        #[cfg(feature = "boring-tls")]
+        {
+            req.digest_auth = self.digest_auth.clone();
+            req.min_tls_version = self.min_tls_version;
+        }
+        #[cfg(feature = "ntlm")]
+        {
+            req.ntlm_auth = self.ntlm_auth.clone();
+        }
         Some(req)
     }
 
+    pub(super) fn upload_progress(&self) -> Option<&Progress> {
+        self.upload_progress.as_ref()
+    }
+
+    pub(super) fn download_progress(&self) -> Option<&Progress> {
+        self.download_progress.as_ref()
+    }
+
+    pub(super) fn max_response_body_size(&self) -> Option<u64> {
+        self.max_response_body_size
+    }
+
+    pub(super) fn expect_continue(&self) -> Option<bool> {
+        self.expect_continue
+    }
+
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    pub(super) fn compression(&self) -> Option<(Compression, i32)> {
+        self.compression
+    }
+
+    pub(super) fn raw_path_and_query(&self) -> Option<&PathAndQuery> {
+        self.raw_path_and_query.as_ref()
+    }
+
+    pub(super) fn connect_overrides(&self) -> ConnectOverrides {
+        ConnectOverrides {
+            addr: self.connect_to,
+            #[cfg(feature = "boring-tls")]
+            min_tls_version: self.min_tls_version,
+            absolute_form: match self.request_target {
+                Some(RequestTarget::Origin | RequestTarget::Asterisk) => Some(false),
+                Some(RequestTarget::Absolute) => Some(true),
+                Some(RequestTarget::Authority) | None => None,
+            },
+        }
+    }
+
+    pub(super) fn request_target(&self) -> Option<RequestTarget> {
+        self.request_target
+    }
+
+    pub(super) fn hedge(&self) -> Option<bool> {
+        self.hedge
+    }
+
+    pub(super) fn accept_encoding(&self) -> Option<&str> {
+        self.accept_encoding.as_deref()
+    }
+
+    pub(super) fn take_validators(&mut self) -> Option<ValidatorStore> {
+        self.validators.take()
+    }
+
+    pub(super) fn signer(&self) -> Option<&Arc<dyn RequestSigner>> {
+        self.signer.as_ref()
+    }
+
+    #[cfg(feature = "boring-tls")]
+    pub(super) fn digest_auth(&self) -> Option<&DigestCredentials> {
+        self.digest_auth.as_ref()
+    }
+
+    #[cfg(feature = "ntlm")]
+    pub(super) fn ntlm_auth(&self) -> Option<&NtlmCredentials> {
+        self.ntlm_auth.as_ref()
+    }
+
+    /// Whether the request brings its own authentication.
+    pub(super) fn has_auth(&self) -> bool {
+        #[allow(unused_mut)]
+        let mut has_auth = self.headers.contains_key(crate::header::AUTHORIZATION);
+        #[cfg(feature = "boring-tls")]
+        {
+            has_auth |= self.digest_auth.is_some();
+        }
+        #[cfg(feature = "ntlm")]
+        {
+            has_auth |= self.ntlm_auth.is_some();
+        }
+        has_auth
+    }
+
     pub(super) fn pieces(
         self,
     ) -> (
@@ -168,7 +342,7 @@ impl RequestBuilder {
             .request
             .as_mut()
             .ok()
-            .and_then(|req| extract_authority(&mut req.url));
+            .and_then(|req| crate::util::extract_authority(&mut req.url));
 
         if let Some((username, password)) = auth {
             builder.basic_auth(username, password)
@@ -261,6 +435,93 @@ impl RequestBuilder {
         self.header_sensitive(crate::header::AUTHORIZATION, header_value, true)
     }
 
+    /// Enable HTTP Digest access authentication, as of RFC 7616.
+    ///
+    /// The request is sent, and if the server answers `401 Unauthorized` with
+    /// a Digest challenge, sent again once with the answer to it. Otherwise,
+    /// the `401` response is returned as is. The `MD5` and `SHA-256`
+    /// algorithms, and their `-sess` variants, are supported with the `auth`
+    /// quality of protection.
+    ///
+    /// The client keeps the challenges it received, so following requests to
+    /// the same host are sent with the answer up front, without the extra
+    /// round trip.
+    ///
+    /// # Note
+    ///
+    /// The request can only be sent again if its body can be cloned, a
+    /// streaming body is sent once and its `401` response returned.
+    #[cfg(feature = "boring-tls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "boring-tls")))]
+    pub fn digest_auth<U, P>(mut self, username: U, password: P) -> RequestBuilder
+    where
+        U: fmt::Display,
+        P: fmt::Display,
+    {
+        if let Ok(ref mut req) = self.request {
+            req.digest_auth = Some(DigestCredentials {
+                username: username.to_string(),
+                password: password.to_string(),
+            });
+        }
+        self
+    }
+
+    /// Enable NTLM authentication with the server.
+    ///
+    /// The request is sent with an NTLM negotiate message, and if the server
+    /// answers `401 Unauthorized` with an NTLM challenge, sent again once
+    /// with the NTLMv2 answer to it. Otherwise, the response is returned as
+    /// is.
+    ///
+    /// # Note
+    ///
+    /// NTLM authenticates the connection, the answer must be sent on the
+    /// connection the challenge came on. The client reads the `401` response
+    /// to the end so its connection goes back to the pool, where it is picked
+    /// up again first, unless other requests to the host take it in between.
+    /// NTLM does not work over HTTP/2, use [`RequestBuilder::version`] to
+    /// send the request over HTTP/1.1. A streaming body is sent once, with
+    /// no authentication.
+    #[cfg(feature = "ntlm")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ntlm")))]
+    pub fn ntlm_auth<U, P, D>(mut self, username: U, password: P, domain: D) -> RequestBuilder
+    where
+        U: fmt::Display,
+        P: fmt::Display,
+        D: fmt::Display,
+    {
+        if let Ok(ref mut req) = self.request {
+            req.ntlm_auth = Some(NtlmCredentials::new(
+                username.to_string(),
+                password.to_string(),
+                domain.to_string(),
+            ));
+        }
+        self
+    }
+
+    /// Sign the request with `signer` right before it is sent.
+    ///
+    /// The signer sees the final method, url and headers of the request,
+    /// including the default headers of the client and the `Host` and
+    /// `Content-Length` headers, and the body if it is buffered. A failing
+    /// signer fails the request. A retried request is signed again.
+    ///
+    /// # Note
+    ///
+    /// Redirects are followed with the headers of the signed request, they
+    /// are not signed again.
+    ///
+    /// See [`sign::sigv4::SigV4`](crate::sign) for signing with AWS
+    /// Signature Version 4.
+    pub fn sign_with<S: RequestSigner>(mut self, signer: S) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.signer = Some(Arc::new(signer));
+        }
+        self
+    }
+
     /// Enable HTTP bearer authentication.
     pub fn bearer_auth<T>(self, token: T) -> RequestBuilder
     where
@@ -290,6 +551,170 @@ impl RequestBuilder {
         self
     }
 
+    /// Calls `callback` as the request body is sent, with the bytes sent so
+    /// far and the total length of the body.
+    ///
+    /// The total is `None` for a streaming body of unknown length. The
+    /// bytes are counted as the connection takes them, so they may still be
+    /// buffered when the callback is called. A body sent again, after a
+    /// redirect or a retry, is counted from 0 again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # async fn run() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::new();
+    /// let res = client
+    ///     .post("http://httpbin.org/post")
+    ///     .body(vec![0; 1 << 20])
+    ///     .on_upload_progress(|sent, total| {
+    ///         println!("sent {} of {:?} bytes", sent, total);
+    ///     })
+    ///     .send()
+    ///     .await?;
+    /// # drop(res);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_upload_progress<F>(mut self, callback: F) -> RequestBuilder
+    where
+        F: Fn(u64, Option<u64>) + Send + Sync + 'static,
+    {
+        if let Ok(ref mut req) = self.request {
+            req.upload_progress = Some(Arc::new(callback));
+        }
+        self
+    }
+
+    /// Calls `callback` as the response body is read, with the bytes read so
+    /// far and the total length of the body.
+    ///
+    /// This overrides the callback set with
+    /// [`ClientBuilder::on_download_progress`](crate::ClientBuilder::on_download_progress).
+    /// The total comes from the `Content-Length` header of the response, if
+    /// any. The bytes are counted as received, before any decompression.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # async fn run() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::new();
+    /// let bytes = client
+    ///     .get("http://httpbin.org/bytes/1048576")
+    ///     .on_download_progress(|read, total| {
+    ///         println!("read {} of {:?} bytes", read, total);
+    ///     })
+    ///     .send()
+    ///     .await?
+    ///     .bytes()
+    ///     .await?;
+    /// # drop(bytes);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_download_progress<F>(mut self, callback: F) -> RequestBuilder
+    where
+        F: Fn(u64, Option<u64>) + Send + Sync + 'static,
+    {
+        if let Ok(ref mut req) = self.request {
+            req.download_progress = Some(Arc::new(callback));
+        }
+        self
+    }
+
+    /// Limits the size of the response body, in bytes after decompression.
+    ///
+    /// This overrides the limit set with
+    /// [`ClientBuilder::max_response_body_size`](crate::ClientBuilder::max_response_body_size).
+    pub fn max_response_body_size(mut self, max: u64) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.max_response_body_size = Some(max);
+        }
+        self
+    }
+
+    /// Send the body with `Expect: 100-continue`.
+    ///
+    /// The headers are sent first, and the body is held back until the
+    /// server had time to answer them, see
+    /// [`ClientBuilder::expect_continue_timeout`](crate::ClientBuilder::expect_continue_timeout).
+    /// A final response coming first is returned without sending the body.
+    ///
+    /// This overrides the threshold set with
+    /// [`ClientBuilder::expect_continue_threshold`](crate::ClientBuilder::expect_continue_threshold).
+    /// It has no effect on requests without a body, or made for HTTP/2 with
+    /// [`version`](RequestBuilder::version).
+    pub fn expect_continue(mut self, enabled: bool) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.expect_continue = Some(enabled);
+        }
+        self
+    }
+
+    /// Makes the request conditional on the validators stored in `store`.
+    ///
+    /// The `ETag` and `Last-Modified` last seen for the URL are sent as
+    /// `If-None-Match` and `If-Modified-Since`, unless either header is
+    /// already set, and the store is updated from the response. On a
+    /// `304 Not Modified`, [`Response::not_modified`] is `true` and the
+    /// response carries the headers stored for the URL.
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let store = rquest::ValidatorStore::new();
+    /// let client = rquest::Client::new();
+    /// let res = client
+    ///     .get("http://httpbin.org/etag/abc")
+    ///     .if_none_match_from(&store)
+    ///     .send()
+    ///     .await?;
+    /// if res.not_modified() {
+    ///     // Keep using the cached body.
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn if_none_match_from(mut self, store: &ValidatorStore) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.validators = Some(store.clone());
+        }
+        self
+    }
+
+    /// Compresses the body with `compression` at `level`.
+    ///
+    /// `Content-Encoding` is set accordingly. A buffered body is compressed
+    /// before it is sent, and keeps a `Content-Length`, a streaming one is
+    /// compressed as it is sent, with chunked framing. A request sent again,
+    /// for a retry, is compressed again from its original body.
+    ///
+    /// A body whose `Content-Encoding` is already set is sent as is.
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// # #[cfg(feature = "gzip")]
+    /// let res = rquest::Client::new()
+    ///     .post("http://httpbin.org/post")
+    ///     .body("telemetry")
+    ///     .compress_body(rquest::Compression::Gzip, 6)
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the `gzip` or `zstd` feature to be enabled.
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "gzip", feature = "zstd"))))]
+    pub fn compress_body(mut self, compression: Compression, level: i32) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.compression = Some((compression, level));
+        }
+        self
+    }
+
     /// Sends a multipart/form-data body.
     ///
     /// ```
@@ -368,7 +793,261 @@ impl RequestBuilder {
         self
     }
 
+    /// Sends `path_and_query` exactly as given, instead of the path and
+    /// query of the URL.
+    ///
+    /// The URL still picks the host to connect to, and is the one used for
+    /// redirects, cookies, signing and `Response::url`. Percent-encoded
+    /// sequences such as `%2F`, and dot segments, reach the server
+    /// untouched. A redirect to another URL sends the path of that URL.
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = rquest::Client::new()
+    ///     .get("https://api.example.com")
+    ///     .raw_path_and_query("/objects/a%2Fb?sig=x+y%3D")
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The request fails to build if `path_and_query` does not start with
+    /// `/` or is not a valid path and query.
+    pub fn raw_path_and_query(mut self, path_and_query: &str) -> RequestBuilder {
+        let mut error = None;
+        if let Ok(ref mut req) = self.request {
+            match PathAndQuery::try_from(path_and_query) {
+                Ok(raw) if raw.as_str() == path_and_query && path_and_query.starts_with('/') => {
+                    req.raw_path_and_query = Some(raw);
+                }
+                Ok(_) => {
+                    error = Some(crate::error::builder(
+                        "raw path and query must start with '/' and have no fragment",
+                    ))
+                }
+                Err(e) => error = Some(crate::error::builder(e)),
+            }
+        }
+        if let Some(err) = error {
+            self.request = Err(err);
+        }
+        self
+    }
+
+    /// Connects to `addr` instead of the address the host of the URL
+    /// resolves to, like curl's `--connect-to`.
+    ///
+    /// The host of the URL is still the one used for TLS SNI, certificate
+    /// verification and the `Host` header. No proxy is used. Connections to
+    /// `addr` are pooled apart from the others, so they are only reused by
+    /// requests connecting to the same address. Redirects to another origin
+    /// connect as usual.
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = rquest::Client::new()
+    ///     .get("https://www.example.com/health")
+    ///     .connect_to("10.0.0.7:8443".parse()?)
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn connect_to(mut self, addr: SocketAddr) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.connect_to = Some(addr);
+        }
+        self
+    }
+
+    /// Sets the minimum TLS version of the connection used for this
+    /// request, overriding the one of the client, e.g. to reach a legacy
+    /// server without building a second client.
+    ///
+    /// Connections opened with an overridden minimum version are pooled
+    /// apart from the others, so they are only reused by requests
+    /// overriding it the same way. The override is kept across redirects.
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = rquest::Client::new()
+    ///     .get("https://legacy.example.com/")
+    ///     .min_tls_version(rquest::tls::Version::TLS_1_2)
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `boring-tls` feature to be enabled.
+    #[cfg(feature = "boring-tls")]
+    pub fn min_tls_version(mut self, version: tls::Version) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.min_tls_version = Some(version);
+        }
+        self
+    }
+
+    /// Whether a backup of this request may be sent if it is slow, see
+    /// [`ClientBuilder::hedge_requests`](crate::ClientBuilder::hedge_requests).
+    ///
+    /// Only `GET`, `HEAD` and `OPTIONS` requests are hedged by default, as
+    /// the server may process both requests. Opt other requests in when
+    /// they are safe to repeat, or opt a request out. This has no effect
+    /// unless the client hedges requests.
+    pub fn hedge(mut self, enabled: bool) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.hedge = Some(enabled);
+        }
+        self
+    }
+
+    /// Sets the `Accept-Encoding` header of this request, and decompresses
+    /// the response only with the codings it lists.
+    ///
+    /// The value is sent verbatim. Codings the client doesn't decompress,
+    /// and ones with a `q=0` weight, are left compressed, so
+    /// `accept_encoding("identity")` gets the body as the server sent it.
+    ///
+    /// An `Accept-Encoding` header set with [`header`](RequestBuilder::header)
+    /// instead is never replaced, but doesn't change which codings are
+    /// decompressed.
+    pub fn accept_encoding(mut self, value: &str) -> RequestBuilder {
+        let mut error = None;
+        if let Ok(ref mut req) = self.request {
+            match HeaderValue::try_from(value) {
+                Ok(header) => {
+                    req.headers_mut().insert(ACCEPT_ENCODING, header);
+                    req.accept_encoding = Some(value.to_owned());
+                }
+                Err(e) => error = Some(crate::error::builder(e)),
+            }
+        }
+        if let Some(err) = error {
+            self.request = Err(err);
+        }
+        self
+    }
+
+    /// Sets the `Host` header of this request, while still connecting to,
+    /// and sending the TLS server name of, the host of the URL.
+    ///
+    /// This replaces a `Host` header set with
+    /// [`header`](RequestBuilder::header) before. A `Host` header set on
+    /// the request either way is sent verbatim in place of the one derived from the URL,
+    /// first in the headers unless
+    /// [`ClientBuilder::headers_order`](crate::ClientBuilder::headers_order)
+    /// places it. It is dropped when redirected to another host.
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = rquest::Client::new()
+    ///     .get("http://203.0.113.7/")
+    ///     .host("staging.example.com")
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # HTTP/2
+    ///
+    /// The HTTP/2 client takes the `:authority` pseudo-header from the
+    /// URL, the one the connection is opened and pooled for, so it can't
+    /// be overridden: over HTTP/2 the `Host` header is sent as a regular
+    /// field next to it. Use [`version`](RequestBuilder::version) with
+    /// `HTTP_11` when the server must only see the overridden host.
+    pub fn host(mut self, host: &str) -> RequestBuilder {
+        let mut error = None;
+        if let Ok(ref mut req) = self.request {
+            match HeaderValue::try_from(host) {
+                Ok(host) => {
+                    req.headers_mut().insert(HOST, host);
+                }
+                Err(e) => error = Some(crate::error::builder(e)),
+            }
+        }
+        if let Some(err) = error {
+            self.request = Err(err);
+        }
+        self
+    }
+
+    /// Sets the `Priority` header (RFC 9218) of this request, replacing the
+    /// one of the client, such as the `u=0, i` Chrome sends on navigations.
+    ///
+    /// `urgency` goes from 0, the most urgent, to 7. Browsers send
+    /// subresources with a lower urgency, e.g. `priority(1, false)` for a
+    /// stylesheet or `priority(4, true)` for an image.
+    ///
+    /// The header carries the priority on both HTTP/1 and HTTP/2. No HTTP/2
+    /// `PRIORITY_UPDATE` frame is sent: these only reprioritize a request
+    /// once sent, which the client never does.
+    ///
+    /// # Errors
+    ///
+    /// The request fails to build if `urgency` is above 7.
+    pub fn priority(mut self, urgency: u8, incremental: bool) -> RequestBuilder {
+        if urgency > 7 {
+            self.request = Err(crate::error::builder("priority urgency above 7"));
+        }
+        if let Ok(ref mut req) = self.request {
+            let value = if incremental {
+                format!("u={urgency}, i")
+            } else {
+                format!("u={urgency}")
+            };
+            let value = HeaderValue::try_from(value).expect("valid priority");
+            req.headers_mut().insert("priority", value);
+        }
+        self
+    }
+
+    /// Sets the form of the target of the request line, whether or not
+    /// the request goes through a proxy.
+    ///
+    /// By default the target is the path and query, or the whole URL
+    /// through an HTTP proxy. Requests with a request target set are sent
+    /// on connections of their own, opened as usual to the host of the URL
+    /// or to the proxy, and pooled apart from the others.
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// use rquest::RequestTarget;
+    ///
+    /// // OPTIONS * HTTP/1.1
+    /// let res = rquest::Client::new()
+    ///     .request(rquest::Method::OPTIONS, "http://example.com/")
+    ///     .request_target(RequestTarget::Asterisk)
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Over HTTP/2, where there is no request line, this has no effect.
+    ///
+    /// # Errors
+    ///
+    /// Sending fails with a builder error if the target is
+    /// [`RequestTarget::Authority`] and the method is not `CONNECT`.
+    pub fn request_target(mut self, target: RequestTarget) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            req.request_target = Some(target);
+        }
+        self
+    }
+
     /// Set HTTP version
+    ///
+    /// `HTTP_10` requests are sent with a `Connection: keep-alive` header
+    /// unless they have a `Connection` header already, so that their
+    /// connection can be reused if the server agrees.
     pub fn version(mut self, version: Version) -> RequestBuilder {
         if let Ok(ref mut req) = self.request {
             req.version = version;
@@ -569,32 +1248,6 @@ fn fmt_request_fields<'a, 'b>(
 
 /// Check the request URL for a "username:password" type authority, and if
 /// found, remove it from the URL and return it.
-pub(crate) fn extract_authority(url: &mut Url) -> Option<(String, Option<String>)> {
-    use percent_encoding::percent_decode;
-
-    if url.has_authority() {
-        let username: String = percent_decode(url.username().as_bytes())
-            .decode_utf8()
-            .ok()?
-            .into();
-        let password = url.password().and_then(|pass| {
-            percent_decode(pass.as_bytes())
-                .decode_utf8()
-                .ok()
-                .map(String::from)
-        });
-        if !username.is_empty() || password.is_some() {
-            url.set_username("")
-                .expect("has_authority means set_username shouldn't fail");
-            url.set_password(None)
-                .expect("has_authority means set_password shouldn't fail");
-            return Some((username, password));
-        }
-    }
-
-    None
-}
-
 impl<T> TryFrom<HttpRequest<T>> for Request
 where
     T: Into<Body>,
@@ -618,6 +1271,25 @@ where
             body: Some(body.into()),
             timeout: None,
             version,
+            upload_progress: None,
+            download_progress: None,
+            max_response_body_size: None,
+            expect_continue: None,
+            #[cfg(any(feature = "gzip", feature = "zstd"))]
+            compression: None,
+            #[cfg(feature = "boring-tls")]
+            digest_auth: None,
+            #[cfg(feature = "ntlm")]
+            ntlm_auth: None,
+            signer: None,
+            validators: None,
+            raw_path_and_query: None,
+            connect_to: None,
+            #[cfg(feature = "boring-tls")]
+            min_tls_version: None,
+            hedge: None,
+            accept_encoding: None,
+            request_target: None,
         })
     }
 }
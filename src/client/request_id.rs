@@ -0,0 +1,156 @@
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use http::header::{HeaderName, HeaderValue};
+
+use super::request::Request;
+use super::response::Response;
+use crate::error;
+use crate::util::fast_random;
+
+/// How requests are given an id, see
+/// [`ClientBuilder::request_id`](crate::ClientBuilder::request_id).
+///
+/// # Example
+///
+/// ```rust
+/// use rquest::RequestIdPolicy;
+///
+/// let client = rquest::Client::builder()
+///     .request_id(RequestIdPolicy::new().header_name("x-correlation-id".parse().unwrap()))
+///     .build()?;
+/// # Ok::<_, rquest::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct RequestIdPolicy {
+    header_name: HeaderName,
+    generator: Arc<dyn Fn() -> String + Send + Sync>,
+}
+
+impl RequestIdPolicy {
+    /// Sends the id in an `x-request-id` header, generating UUIDv7 ones.
+    pub fn new() -> RequestIdPolicy {
+        RequestIdPolicy {
+            header_name: HeaderName::from_static("x-request-id"),
+            generator: Arc::new(uuid_v7),
+        }
+    }
+
+    /// Sets the header the id is sent in.
+    ///
+    /// Default is `x-request-id`.
+    pub fn header_name(mut self, header_name: HeaderName) -> RequestIdPolicy {
+        self.header_name = header_name;
+        self
+    }
+
+    /// Sets how ids are generated. The ids must be valid header values.
+    ///
+    /// Default is a random UUIDv7, ordered by time.
+    pub fn generator<F>(mut self, generator: F) -> RequestIdPolicy
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        self.generator = Arc::new(generator);
+        self
+    }
+
+    /// Sets the id header of `req`, unless it already has one, and returns
+    /// the id it is sent with.
+    pub(crate) fn apply(&self, req: &mut Request) -> crate::Result<RequestId> {
+        if let Some(value) = req.headers().get(&self.header_name) {
+            return Ok(RequestId(
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            ));
+        }
+
+        let id = (self.generator)();
+        let value = HeaderValue::try_from(id.as_str())
+            .map_err(|err| error::builder(err).with_url(req.url().clone()))?;
+        req.headers_mut().insert(self.header_name.clone(), value);
+        Ok(RequestId(id))
+    }
+}
+
+impl Default for RequestIdPolicy {
+    fn default() -> RequestIdPolicy {
+        RequestIdPolicy::new()
+    }
+}
+
+impl fmt::Debug for RequestIdPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RequestIdPolicy")
+            .field("header_name", &self.header_name)
+            .finish()
+    }
+}
+
+/// The id a request was sent with, added to the extensions of its
+/// response.
+///
+/// The id of a failed request is in
+/// [`Error::request_id`](crate::Error::request_id).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// The id, as sent in the header.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Tags the response of `send`, or its error, with `id`.
+pub(crate) async fn tag<F>(id: RequestId, send: F) -> crate::Result<Response>
+where
+    F: Future<Output = crate::Result<Response>>,
+{
+    match send.await {
+        Ok(mut res) => {
+            res.extensions_mut().insert(id);
+            Ok(res)
+        }
+        Err(err) => Err(err.with_request_id(id.0)),
+    }
+}
+
+/// A version 7 UUID: a millisecond Unix timestamp followed by random bits.
+fn uuid_v7() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let high = (millis & 0xffff_ffff_ffff) << 16 | 0x7000 | (fast_random() >> 52);
+    let low = fast_random() >> 2 | 0x8000_0000_0000_0000;
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uuid_v7_format() {
+        let id = uuid_v7();
+        assert_eq!(id.len(), 36, "{id}");
+        assert_eq!(id.as_bytes()[14], b'7', "{id}");
+        assert!(b"89ab".contains(&id.as_bytes()[19]), "{id}");
+        assert_ne!(id, uuid_v7());
+    }
+}
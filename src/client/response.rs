@@ -1,11 +1,13 @@
 use std::fmt;
 use std::net::SocketAddr;
 use std::pin::Pin;
+use std::time::{Instant, SystemTime};
 
 use bytes::Bytes;
 use encoding_rs::{Encoding, UTF_8};
 use futures_util::stream::StreamExt;
 use hyper::client::connect::HttpInfo;
+use hyper::header::{CONTENT_LENGTH, ETAG, LAST_MODIFIED};
 use hyper::{HeaderMap, StatusCode, Version};
 use mime::Mime;
 #[cfg(feature = "json")]
@@ -15,10 +17,11 @@ use serde_json;
 use tokio::time::Sleep;
 use url::Url;
 
-use super::body::Body;
+use super::body::{Body, Progress};
 use super::decoder::{Accepts, Decoder};
 #[cfg(feature = "cookies")]
 use crate::cookie;
+use crate::metrics::Recorder;
 use crate::response::ResponseUrl;
 
 /// A Response to a submitted `Request`.
@@ -34,10 +37,28 @@ impl Response {
         res: hyper::Response<hyper::Body>,
         url: Url,
         accepts: Accepts,
-        timeout: Option<Pin<Box<Sleep>>>,
+        timeout: Option<(Pin<Box<Sleep>>, Instant)>,
+        download_progress: Option<Progress>,
+        strict_content_length: bool,
     ) -> Response {
         let (mut parts, body) = res.into_parts();
-        let decoder = Decoder::detect(&mut parts.headers, Body::response(body, timeout), accepts);
+        let mut body = Body::response(body, timeout);
+        if strict_content_length {
+            body = body.with_length_check();
+        }
+        // Counted before decoding, against the length of the encoded body.
+        let body = match download_progress {
+            Some(progress) => {
+                let total = parts
+                    .headers
+                    .get(CONTENT_LENGTH)
+                    .and_then(|len| len.to_str().ok())
+                    .and_then(|len| len.parse().ok());
+                body.with_progress_total(progress, total)
+            }
+            None => body,
+        };
+        let decoder = Decoder::detect(&mut parts.headers, body, accepts);
         let res = hyper::Response::from_parts(parts, decoder);
 
         Response {
@@ -83,6 +104,30 @@ impl Response {
         HttpBody::size_hint(self.res.body()).exact()
     }
 
+    /// Get the `ETag` of this response, if any.
+    ///
+    /// The entity tag is returned as sent, with its quotes and any `W/`
+    /// prefix, ready to be sent back in `If-None-Match`.
+    pub fn etag(&self) -> Option<&str> {
+        self.res.headers().get(ETAG)?.to_str().ok()
+    }
+
+    /// Get the `Last-Modified` date of this response, if any and valid.
+    pub fn last_modified(&self) -> Option<SystemTime> {
+        let value = self.res.headers().get(LAST_MODIFIED)?.to_str().ok()?;
+        httpdate::parse_http_date(value).ok()
+    }
+
+    /// Whether the server answered `304 Not Modified`.
+    ///
+    /// For a request sent with
+    /// [`RequestBuilder::if_none_match_from`](crate::RequestBuilder::if_none_match_from),
+    /// the headers are the ones stored with the validators, so a body kept
+    /// from the earlier response is still described by them.
+    pub fn not_modified(&self) -> bool {
+        self.res.status() == StatusCode::NOT_MODIFIED
+    }
+
     /// Retrieve the cookies contained in the response.
     ///
     /// Note that invalid 'Set-Cookie' headers will be ignored.
@@ -259,6 +304,56 @@ impl Response {
         hyper::body::to_bytes(self.res.into_body()).await
     }
 
+    /// Hand the request metrics over to the body, to be reported once it
+    /// ends or is dropped.
+    pub(crate) fn record_body(&mut self, recorder: Recorder) {
+        self.res.body_mut().set_recorder(recorder);
+    }
+
+    /// Keep the request span open until the body ends.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn trace_body(&mut self, trace: super::trace::BodyTrace) {
+        self.res.body_mut().set_trace(trace);
+    }
+
+    /// Fail reading the body once it is longer than `limit`.
+    pub(crate) fn limit_body(&mut self, limit: Option<u64>) {
+        self.res.body_mut().set_limit(limit);
+    }
+
+    /// Read the whole body, returning it along with a response that replays
+    /// it.
+    pub(crate) async fn buffered(self) -> crate::Result<(Response, Bytes)> {
+        let (mut parts, body) = self.res.into_parts();
+        let bytes = hyper::body::to_bytes(body).await?;
+        // Already decoded, so the replay is passed through as is.
+        let body = Decoder::detect(
+            &mut parts.headers,
+            Body::reusable(bytes.clone()),
+            Accepts::none(),
+        );
+        let res = Response {
+            res: hyper::Response::from_parts(parts, body),
+            url: self.url,
+        };
+        Ok((res, bytes))
+    }
+
+    /// Read the whole body, returning the response along with a `Replay`
+    /// of it.
+    pub(crate) async fn replay(self) -> crate::Result<(Response, Replay)> {
+        let (res, body) = self.buffered().await?;
+        let replay = Replay {
+            status: res.status(),
+            version: res.version(),
+            headers: res.headers().clone(),
+            url: res.url().clone(),
+            http_info: res.extensions().get::<HttpInfo>().cloned(),
+            body,
+        };
+        Ok((res, replay))
+    }
+
     /// Stream a chunk of the response body.
     ///
     /// When the response body has been exhausted, this will return `None`.
@@ -311,6 +406,21 @@ impl Response {
         self.res.into_body()
     }
 
+    /// Convert the response into a `Stream` of `Bytes` from the body, without
+    /// the limit set with `max_response_body_size`.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `stream` feature to be enabled.
+    #[cfg(feature = "stream")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "stream")))]
+    pub fn bytes_stream_unlimited(
+        mut self,
+    ) -> impl futures_core::Stream<Item = crate::Result<Bytes>> {
+        self.limit_body(None);
+        self.res.into_body()
+    }
+
     // util methods
 
     /// Turn a response into an error if the server returned an error.
@@ -374,6 +484,66 @@ impl Response {
     }
 }
 
+/// A response read in full, which can be turned into as many `Response`s
+/// as needed.
+#[derive(Clone)]
+pub(crate) struct Replay {
+    status: StatusCode,
+    version: Version,
+    headers: HeaderMap,
+    url: Url,
+    http_info: Option<HttpInfo>,
+    body: Bytes,
+}
+
+impl Replay {
+    #[cfg(feature = "cache")]
+    pub(crate) fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    #[cfg(feature = "cache")]
+    pub(crate) fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    #[cfg(feature = "cache")]
+    pub(crate) fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// The same response with other headers.
+    #[cfg(feature = "cache")]
+    pub(crate) fn with_headers(&self, headers: HeaderMap) -> Replay {
+        Replay {
+            headers,
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn to_response(&self) -> Response {
+        let mut headers = self.headers.clone();
+        let body = Decoder::detect(
+            &mut headers,
+            Body::reusable(self.body.clone()),
+            Accepts::none(),
+        );
+
+        let mut res = hyper::Response::new(body);
+        *res.status_mut() = self.status;
+        *res.version_mut() = self.version;
+        *res.headers_mut() = headers;
+        if let Some(ref info) = self.http_info {
+            res.extensions_mut().insert(info.clone());
+        }
+
+        Response {
+            res,
+            url: Box::new(self.url.clone()),
+        }
+    }
+}
+
 impl fmt::Debug for Response {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Response")
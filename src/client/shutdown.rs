@@ -0,0 +1,172 @@
+use std::future::Future;
+use std::pin::pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use futures_util::future::{select, Either};
+use tokio::sync::Notify;
+
+use super::response::Response;
+
+/// What became of the requests in flight when a client was shut down, see
+/// [`Client::shutdown`](crate::Client::shutdown).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Requests that ended before the deadline, whatever their outcome.
+    pub completed: usize,
+    /// Requests still in flight at the deadline, which failed with an error
+    /// for which [`Error::is_client_closed`](crate::Error::is_client_closed)
+    /// is true.
+    pub aborted: usize,
+}
+
+#[derive(Default)]
+struct State {
+    closed: bool,
+    aborted: bool,
+    in_flight: usize,
+    completed: usize,
+}
+
+/// Tracks the requests in flight of a client and its clones.
+#[derive(Default)]
+pub(crate) struct Shutdown {
+    state: Mutex<State>,
+    /// Notified when the last request in flight ends.
+    idle: Notify,
+    /// Notified when the requests in flight are aborted.
+    abort: Notify,
+}
+
+impl Shutdown {
+    /// Registers a request, unless the client is closed.
+    pub(crate) fn enter(self: &Arc<Self>) -> Option<InFlight> {
+        let mut state = self.lock();
+        if state.closed {
+            return None;
+        }
+        state.in_flight += 1;
+        Some(InFlight {
+            shutdown: self.clone(),
+        })
+    }
+
+    /// Closes the client, then waits up to `timeout` for the requests in
+    /// flight to end, aborting the others.
+    pub(crate) async fn close(&self, timeout: Duration) -> ShutdownReport {
+        self.lock().closed = true;
+
+        let drained = tokio::time::timeout(timeout, async {
+            loop {
+                // Created before checking the count, so that the last
+                // request ending in between is not missed.
+                let idle = self.idle.notified();
+                if self.lock().in_flight == 0 {
+                    return;
+                }
+                idle.await;
+            }
+        })
+        .await;
+
+        let mut state = self.lock();
+        let report = ShutdownReport {
+            completed: state.completed,
+            aborted: if drained.is_ok() { 0 } else { state.in_flight },
+        };
+        if drained.is_err() {
+            state.aborted = true;
+            drop(state);
+            self.abort.notify_waiters();
+        }
+        report
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A request in flight, until dropped.
+pub(crate) struct InFlight {
+    shutdown: Arc<Shutdown>,
+}
+
+impl InFlight {
+    /// Runs `fut`, unless the requests in flight are aborted first.
+    pub(crate) async fn run<F>(self, fut: F) -> crate::Result<Response>
+    where
+        F: Future<Output = crate::Result<Response>>,
+    {
+        let aborted = self.shutdown.abort.notified();
+        if self.shutdown.lock().aborted {
+            return Err(crate::error::client_closed());
+        }
+        match select(pin!(fut), pin!(aborted)).await {
+            Either::Left((res, _)) => res,
+            Either::Right(_) => Err(crate::error::client_closed()),
+        }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut state = self.shutdown.lock();
+        state.in_flight -= 1;
+        if state.closed && !state.aborted {
+            state.completed += 1;
+        }
+        if state.in_flight == 0 {
+            drop(state);
+            self.shutdown.idle.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn close_waits_for_requests_in_flight() {
+        let shutdown = Arc::new(Shutdown::default());
+        let request = shutdown.enter().unwrap();
+        let done = shutdown.enter().unwrap();
+        drop(done);
+
+        let report = {
+            let close = shutdown.close(Duration::from_secs(5));
+            let end = async {
+                tokio::task::yield_now().await;
+                drop(request);
+            };
+            futures_util::future::join(close, end).await.0
+        };
+        assert_eq!(
+            report,
+            ShutdownReport {
+                completed: 1,
+                aborted: 0
+            }
+        );
+        assert!(shutdown.enter().is_none());
+    }
+
+    #[tokio::test]
+    async fn close_aborts_requests_at_deadline() {
+        let shutdown = Arc::new(Shutdown::default());
+        let request = shutdown.enter().unwrap();
+        let pending = request.run(std::future::pending());
+
+        let (report, res) =
+            futures_util::future::join(shutdown.close(Duration::from_millis(10)), pending).await;
+        assert_eq!(
+            report,
+            ShutdownReport {
+                completed: 0,
+                aborted: 1
+            }
+        );
+        assert!(res.unwrap_err().is_client_closed());
+    }
+}
@@ -0,0 +1,117 @@
+//! `tracing` spans for requests.
+//!
+//! Every request runs in an `rquest.request` span, current while it is
+//! polled so request hooks can propagate it, and kept open until its
+//! response body is read to the end. Connections are opened in a child
+//! `rquest.connect` span, and redirects and retries are events of the
+//! request span.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use http::StatusCode;
+use tracing::field::{self, Empty};
+use tracing::{Instrument, Span};
+use url::Url;
+
+use crate::metrics::ErrorKind;
+use crate::{Method, Response};
+
+/// Turns the URL of a request into the `http.path` of its span, see
+/// [`ClientBuilder::trace_path`](crate::ClientBuilder::trace_path).
+pub(crate) type TracePath = Arc<dyn Fn(&Url) -> String + Send + Sync>;
+
+fn path(url: &Url, trace_path: Option<&TracePath>) -> String {
+    match trace_path {
+        Some(trace_path) => trace_path(url),
+        None => url.path().to_owned(),
+    }
+}
+
+/// The span of a request to `url`.
+pub(crate) fn request_span(method: &Method, url: &Url, trace_path: Option<&TracePath>) -> Span {
+    tracing::info_span!(
+        target: "rquest",
+        "rquest.request",
+        http.method = %method,
+        http.host = url.host_str().unwrap_or(""),
+        http.path = %path(url, trace_path),
+        http.status = Empty,
+        http.version = Empty,
+        http.response_bytes = Empty,
+        error.kind = Empty,
+    )
+}
+
+/// Polls `send` in `span`, then hands the span over to the body of the
+/// response, to be closed once it is read.
+pub(crate) async fn instrument<F>(span: Span, send: F) -> crate::Result<Response>
+where
+    F: Future<Output = crate::Result<Response>>,
+{
+    match send.instrument(span.clone()).await {
+        Ok(mut res) => {
+            span.record("http.status", res.status().as_u16());
+            span.record("http.version", field::debug(res.version()));
+            res.trace_body(BodyTrace {
+                span,
+                bytes: 0,
+                done: false,
+            });
+            Ok(res)
+        }
+        Err(err) => {
+            record_error(&span, &err);
+            Err(err)
+        }
+    }
+}
+
+fn record_error(span: &Span, err: &crate::Error) {
+    span.record("error.kind", field::debug(ErrorKind::of(err)));
+    tracing::debug!(target: "rquest", parent: span, error = %err, "request failed");
+}
+
+/// Emits the event of a redirect to `to`, in the current request span.
+pub(crate) fn redirect(status: StatusCode, to: &Url, trace_path: Option<&TracePath>) {
+    tracing::event!(
+        target: "rquest",
+        tracing::Level::DEBUG,
+        http.status = status.as_u16(),
+        http.host = to.host_str().unwrap_or(""),
+        http.path = %path(to, trace_path),
+        "following redirect"
+    );
+}
+
+/// Counts the bytes of a response body, recording them in the request span
+/// once the body ends or is dropped.
+pub(crate) struct BodyTrace {
+    span: Span,
+    bytes: u64,
+    done: bool,
+}
+
+impl BodyTrace {
+    pub(crate) fn read(&mut self, len: usize) {
+        self.bytes += len as u64;
+    }
+
+    pub(crate) fn finish(&mut self, err: Option<&crate::Error>) {
+        self.done = true;
+        self.span.record("http.response_bytes", self.bytes);
+        if let Some(err) = err {
+            record_error(&self.span, err);
+        }
+    }
+}
+
+impl Drop for BodyTrace {
+    fn drop(&mut self) {
+        if !self.done {
+            self.span.record("http.response_bytes", self.bytes);
+            self.span
+                .record("error.kind", field::debug(ErrorKind::Canceled));
+        }
+    }
+}
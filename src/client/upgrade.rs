@@ -70,4 +70,13 @@ impl super::response::Response {
             .map_err(crate::error::upgrade)
             .await
     }
+
+    /// Returns a future for a possible HTTP upgrade, keeping the response head.
+    #[cfg(feature = "websocket")]
+    pub(crate) async fn upgrade_in_place(&mut self) -> crate::Result<Upgraded> {
+        hyper::upgrade::on(&mut self.res)
+            .map_ok(Upgraded::from)
+            .map_err(crate::error::upgrade)
+            .await
+    }
 }
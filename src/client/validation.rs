@@ -0,0 +1,246 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::time::Duration;
+
+#[cfg(feature = "boring-tls")]
+use crate::tls::{self, TlsSettings, TlsSettingsError, Version};
+#[cfg(feature = "boring-tls")]
+use crate::HttpVersionPref;
+
+/// A mistake in the configuration of a `ClientBuilder`, which makes
+/// [`build`](crate::ClientBuilder::build) fail.
+///
+/// Returned by [`ClientBuilder::validate`](crate::ClientBuilder::validate),
+/// and by [`Error::validation_errors`](crate::Error::validation_errors) when
+/// building failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationError {
+    /// The TLS settings have an unknown cipher or signature algorithm name.
+    #[cfg(feature = "boring-tls")]
+    TlsSettings(TlsSettingsError),
+    /// The minimum TLS version is above the maximum one.
+    #[cfg(feature = "boring-tls")]
+    TlsVersionRange {
+        /// The minimum TLS version.
+        min: Version,
+        /// The maximum TLS version.
+        max: Version,
+    },
+    /// None of the ciphers of the cipher list can be used up to the maximum
+    /// TLS version.
+    #[cfg(feature = "boring-tls")]
+    NoCipherForTlsVersion {
+        /// The maximum TLS version.
+        max: Version,
+    },
+    /// The ciphers of the cipher list all authenticate the server with
+    /// a kind of key none of the signature algorithms verifies.
+    #[cfg(feature = "boring-tls")]
+    SigalgsMismatchCiphers {
+        /// The authentication of the ciphers, such as `ECDSA`.
+        auth: String,
+    },
+}
+
+/// A setting of a `ClientBuilder` that has no effect, or not the one that
+/// was likely meant.
+///
+/// Returned by [`ClientBuilder::validate`](crate::ClientBuilder::validate),
+/// and logged by [`build`](crate::ClientBuilder::build).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationWarning {
+    /// The connect timeout is not shorter than the total timeout, so it
+    /// never fires.
+    ConnectTimeoutNotShorter {
+        /// The connect timeout.
+        connect_timeout: Duration,
+        /// The total timeout.
+        timeout: Duration,
+    },
+    /// Application settings (ALPS) are enabled, but only HTTP/1 is offered,
+    /// which has none.
+    #[cfg(feature = "boring-tls")]
+    ApplicationSettingsWithHttp1,
+}
+
+impl fmt::Display for ValidationError {
+    #[cfg(not(feature = "boring-tls"))]
+    fn fmt(&self, _f: &mut fmt::Formatter) -> fmt::Result {
+        // without TLS settings there is nothing to validate
+        match *self {}
+    }
+
+    #[cfg(feature = "boring-tls")]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::TlsSettings(ref err) => fmt::Display::fmt(err, f),
+            ValidationError::TlsVersionRange { min, max } => write!(
+                f,
+                "minimum TLS version {:?} is above the maximum {:?}",
+                min, max
+            ),
+            ValidationError::NoCipherForTlsVersion { max } => write!(
+                f,
+                "no cipher of the cipher list can be used up to TLS version {:?}",
+                max
+            ),
+            ValidationError::SigalgsMismatchCiphers { ref auth } => write!(
+                f,
+                "the ciphers need {} certificates, which no signature algorithm verifies",
+                auth
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationWarning::ConnectTimeoutNotShorter {
+                connect_timeout,
+                timeout,
+            } => write!(
+                f,
+                "connect timeout {:?} is not shorter than the timeout {:?}",
+                connect_timeout, timeout
+            ),
+            #[cfg(feature = "boring-tls")]
+            ValidationWarning::ApplicationSettingsWithHttp1 => {
+                f.write_str("application settings have no effect when only HTTP/1 is offered")
+            }
+        }
+    }
+}
+
+/// The source of the error `build` fails with.
+#[derive(Debug)]
+pub(crate) struct ValidationErrors(pub(crate) Vec<ValidationError>);
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid client configuration")?;
+        for (i, err) in self.0.iter().enumerate() {
+            f.write_str(if i == 0 { ": " } else { "; " })?;
+            fmt::Display::fmt(err, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl StdError for ValidationErrors {}
+
+/// Collects the errors and warnings of a configuration.
+#[derive(Default)]
+pub(crate) struct Validation {
+    pub(crate) errors: Vec<ValidationError>,
+    pub(crate) warnings: Vec<ValidationWarning>,
+}
+
+impl Validation {
+    pub(crate) fn check_timeouts(
+        &mut self,
+        connect_timeout: Option<Duration>,
+        timeout: Option<Duration>,
+    ) {
+        if let (Some(connect_timeout), Some(timeout)) = (connect_timeout, timeout) {
+            if connect_timeout >= timeout {
+                self.warnings
+                    .push(ValidationWarning::ConnectTimeoutNotShorter {
+                        connect_timeout,
+                        timeout,
+                    });
+            }
+        }
+    }
+
+    #[cfg(feature = "boring-tls")]
+    pub(crate) fn check_tls(&mut self, settings: &TlsSettings) {
+        if let Err(err) = settings.validate() {
+            // The lists can't be compared with unknown names in them.
+            self.errors.push(ValidationError::TlsSettings(err));
+            return;
+        }
+
+        if let (Some(min), Some(max)) = (settings.min_tls_version, settings.max_tls_version) {
+            if version_rank(min) > version_rank(max) {
+                self.errors
+                    .push(ValidationError::TlsVersionRange { min, max });
+            }
+        }
+
+        if settings.application_settings
+            && matches!(settings.http_version_pref, HttpVersionPref::Http1)
+        {
+            self.warnings
+                .push(ValidationWarning::ApplicationSettingsWithHttp1);
+        }
+
+        self.check_ciphers(settings);
+    }
+
+    /// Checks the ciphers of the cipher list can be used with the TLS
+    /// versions and signature algorithms.
+    ///
+    /// Cipher lists with rule keywords are not checked, as which ciphers
+    /// they select depends on BoringSSL.
+    #[cfg(feature = "boring-tls")]
+    fn check_ciphers(&mut self, settings: &TlsSettings) {
+        let Some(ref cipher_list) = settings.cipher_list else {
+            return;
+        };
+        let Some(ciphers) = cipher_list
+            .split(':')
+            .map(tls::find_cipher)
+            .collect::<Option<Vec<_>>>()
+        else {
+            return;
+        };
+
+        // TLS 1.3 ciphers can't be disabled, and work with any certificate.
+        let max = match settings.max_tls_version {
+            Some(max) if max != Version::TLS_1_3 => max,
+            _ => return,
+        };
+        let usable = ciphers
+            .iter()
+            .filter(|cipher| version_rank(cipher.protocol) <= version_rank(max))
+            .collect::<Vec<_>>();
+        if usable.is_empty() {
+            self.errors
+                .push(ValidationError::NoCipherForTlsVersion { max });
+            return;
+        }
+
+        let Some(ref sigalgs_list) = settings.sigalgs_list else {
+            return;
+        };
+        let verifies = |auth: &str| {
+            let prefix = match auth {
+                "RSA" => "rsa_",
+                "ECDSA" => "ecdsa_",
+                _ => return true,
+            };
+            sigalgs_list.split(':').any(|name| name.starts_with(prefix))
+        };
+        if !usable.iter().any(|cipher| verifies(&cipher.auth)) {
+            self.errors.push(ValidationError::SigalgsMismatchCiphers {
+                auth: usable[0].auth.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(feature = "boring-tls")]
+fn version_rank(version: Version) -> usize {
+    [
+        Version::TLS_1_0,
+        Version::TLS_1_1,
+        Version::TLS_1_2,
+        Version::TLS_1_3,
+    ]
+    .iter()
+    .position(|v| *v == version)
+    .unwrap_or(0)
+}
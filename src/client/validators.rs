@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use http::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use http::{HeaderMap, StatusCode};
+
+use super::request::Request;
+use super::response::Response;
+
+/// A store of the validators last seen for each URL.
+///
+/// A request sent with
+/// [`RequestBuilder::if_none_match_from`](crate::RequestBuilder::if_none_match_from)
+/// is made conditional on the `ETag` and `Last-Modified` stored for its URL.
+/// Successful responses update the store. On a `304 Not Modified`, the
+/// response carries the headers stored with the validators, updated by the
+/// ones of the `304`, and [`Response::not_modified`] is `true`, so that a
+/// body kept by the caller can be used as is.
+///
+/// Clones share the same store.
+#[derive(Clone, Default)]
+pub struct ValidatorStore {
+    entries: Arc<Mutex<HashMap<String, HeaderMap>>>,
+}
+
+impl ValidatorStore {
+    /// Creates an empty store.
+    pub fn new() -> ValidatorStore {
+        ValidatorStore::default()
+    }
+
+    /// Forgets the validators stored for `url`.
+    pub fn remove(&self, url: &str) {
+        self.lock().remove(url);
+    }
+
+    /// Forgets all the stored validators.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// The number of URLs with stored validators.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no validators are stored.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, HeaderMap>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(super) async fn send<F, Fut>(&self, mut req: Request, send: F) -> crate::Result<Response>
+    where
+        F: FnOnce(Request) -> Fut,
+        Fut: Future<Output = crate::Result<Response>>,
+    {
+        let key = req.url().as_str().to_owned();
+        let stored = self.lock().get(&key).cloned();
+
+        // Headers set by the caller win over the stored validators.
+        if let Some(ref stored) = stored {
+            let headers = req.headers_mut();
+            if !headers.contains_key(IF_NONE_MATCH) && !headers.contains_key(IF_MODIFIED_SINCE) {
+                if let Some(etag) = stored.get(ETAG) {
+                    headers.insert(IF_NONE_MATCH, etag.clone());
+                }
+                if let Some(last_modified) = stored.get(LAST_MODIFIED) {
+                    headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+                }
+            }
+        }
+
+        let mut res = send(req).await?;
+        match res.status() {
+            StatusCode::NOT_MODIFIED => {
+                if let Some(mut stored) = stored {
+                    for name in res.headers().keys() {
+                        stored.remove(name);
+                    }
+                    for (name, value) in res.headers() {
+                        stored.append(name, value.clone());
+                    }
+                    self.lock().insert(key, stored.clone());
+                    *res.headers_mut() = stored;
+                }
+            }
+            status if status.is_success() => {
+                let headers = res.headers();
+                if headers.contains_key(ETAG) || headers.contains_key(LAST_MODIFIED) {
+                    self.lock().insert(key, headers.clone());
+                } else {
+                    self.lock().remove(&key);
+                }
+            }
+            _ => {}
+        }
+        Ok(res)
+    }
+}
+
+impl fmt::Debug for ValidatorStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ValidatorStore")
+            .field("len", &self.len())
+            .finish()
+    }
+}
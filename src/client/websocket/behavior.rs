@@ -0,0 +1,117 @@
+//! Client behavior on the wire: masking, fragmentation and text validation.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+#[cfg(feature = "boring-tls")]
+use crate::tls::Impersonate;
+
+/// The random source used to generate the masking key of outgoing frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WsMasking {
+    /// A fresh key from the thread-local CSPRNG for every frame, as browsers do.
+    #[default]
+    ThreadRng,
+
+    /// Keys from an RNG seeded with the given value.
+    ///
+    /// Every connection produces the same sequence of keys, which makes the
+    /// frames sent reproducible in tests. Don't use this against real servers.
+    Seeded(u64),
+}
+
+/// How the client frames the messages it sends.
+///
+/// Anti-bot systems can inspect the frames of a connection as well as its
+/// handshake. The default is taken from the [`Impersonate`] profile of the
+/// client, and can be changed per connection with
+/// [`WebSocketRequestBuilder::behavior`](super::WebSocketRequestBuilder::behavior).
+///
+/// [`Impersonate`]: crate::tls::Impersonate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WsBehaviorSettings {
+    /// The random source of the masking keys.
+    pub masking: WsMasking,
+
+    /// Split outgoing messages into frames with at most this many payload bytes.
+    ///
+    /// `None` sends every message as a single frame.
+    pub fragment_size: Option<usize>,
+
+    /// Check text messages are valid UTF-8 before they are sent.
+    pub validate_utf8: bool,
+}
+
+impl Default for WsBehaviorSettings {
+    fn default() -> Self {
+        WsBehaviorSettings {
+            masking: WsMasking::ThreadRng,
+            fragment_size: None,
+            validate_utf8: true,
+        }
+    }
+}
+
+#[cfg(feature = "boring-tls")]
+impl From<Impersonate> for WsBehaviorSettings {
+    fn from(impersonate: Impersonate) -> Self {
+        match impersonate {
+            // OkHttp encodes text without a separate validation pass.
+            Impersonate::OkHttp3_9
+            | Impersonate::OkHttp3_11
+            | Impersonate::OkHttp3_13
+            | Impersonate::OkHttp3_14
+            | Impersonate::OkHttp4_9
+            | Impersonate::OkHttp4_10
+            | Impersonate::OkHttp5 => WsBehaviorSettings {
+                validate_utf8: false,
+                ..Default::default()
+            },
+            _ => WsBehaviorSettings::default(),
+        }
+    }
+}
+
+impl WsBehaviorSettings {
+    /// Whether outgoing frames must be rewritten to apply these settings.
+    pub(super) fn rewrites_frames(&self) -> bool {
+        self.masking != WsMasking::ThreadRng || self.fragment_size.is_some() || self.validate_utf8
+    }
+}
+
+/// Generator of masking keys for one connection.
+#[derive(Debug)]
+pub(super) enum MaskGenerator {
+    Thread,
+    Seeded(StdRng),
+}
+
+impl MaskGenerator {
+    pub(super) fn new(masking: WsMasking) -> MaskGenerator {
+        match masking {
+            WsMasking::ThreadRng => MaskGenerator::Thread,
+            WsMasking::Seeded(seed) => MaskGenerator::Seeded(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    pub(super) fn next_key(&mut self) -> [u8; 4] {
+        match self {
+            MaskGenerator::Thread => rand::thread_rng().gen(),
+            MaskGenerator::Seeded(rng) => rng.gen(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MaskGenerator, WsMasking};
+
+    #[test]
+    fn seeded_masking_is_reproducible() {
+        let mut a = MaskGenerator::new(WsMasking::Seeded(42));
+        let mut b = MaskGenerator::new(WsMasking::Seeded(42));
+        for _ in 0..8 {
+            assert_eq!(a.next_key(), b.next_key());
+        }
+    }
+}
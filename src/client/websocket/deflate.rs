@@ -0,0 +1,396 @@
+//! The `permessage-deflate` extension ([RFC 7692]).
+//!
+//! [RFC 7692]: https://datatracker.ietf.org/doc/html/rfc7692
+
+use std::fmt::Write;
+use std::io;
+
+use super::WsError;
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use http::header::GetAll;
+use http::HeaderValue;
+
+const EXTENSION_NAME: &str = "permessage-deflate";
+
+/// Trailing bytes of a sync flush, stripped from every compressed message.
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+const MIN_WINDOW_BITS: u8 = 8;
+const MAX_WINDOW_BITS: u8 = 15;
+
+/// Configuration of the `permessage-deflate` WebSocket extension.
+///
+/// The default offer matches browsers: `permessage-deflate; client_max_window_bits`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WsCompression {
+    /// The LZ77 window size (8-15) the client uses to compress messages.
+    ///
+    /// `None` offers the parameter without a value, leaving the choice to the server.
+    pub client_max_window_bits: Option<u8>,
+
+    /// The LZ77 window size (8-15) the server may use to compress messages.
+    pub server_max_window_bits: Option<u8>,
+
+    /// Reset the client compression context after every message.
+    pub client_no_context_takeover: bool,
+
+    /// Ask the server to reset its compression context after every message.
+    pub server_no_context_takeover: bool,
+}
+
+impl WsCompression {
+    /// Check the window sizes are in the range allowed by RFC 7692.
+    pub(super) fn validate(&self) -> Result<(), String> {
+        for (name, bits) in [
+            ("client_max_window_bits", self.client_max_window_bits),
+            ("server_max_window_bits", self.server_max_window_bits),
+        ] {
+            if let Some(bits) = bits {
+                if !(MIN_WINDOW_BITS..=MAX_WINDOW_BITS).contains(&bits) {
+                    return Err(format!("invalid {name}: {bits}"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The `Sec-WebSocket-Extensions` value offered in the upgrade request.
+    pub(super) fn offer(&self) -> String {
+        let mut offer = String::from(EXTENSION_NAME);
+
+        if self.client_no_context_takeover {
+            offer.push_str("; client_no_context_takeover");
+        }
+
+        if self.server_no_context_takeover {
+            offer.push_str("; server_no_context_takeover");
+        }
+
+        match self.client_max_window_bits {
+            Some(bits) => {
+                let _ = write!(offer, "; client_max_window_bits={bits}");
+            }
+            None => offer.push_str("; client_max_window_bits"),
+        }
+
+        if let Some(bits) = self.server_max_window_bits {
+            let _ = write!(offer, "; server_max_window_bits={bits}");
+        }
+
+        offer
+    }
+
+    /// Parse the server's `Sec-WebSocket-Extensions` response against this offer.
+    ///
+    /// Returns `None` if the server declined compression.
+    pub(super) fn negotiate(
+        &self,
+        headers: GetAll<'_, HeaderValue>,
+    ) -> Result<Option<WsCompression>, String> {
+        let mut negotiated = None;
+
+        for value in headers {
+            let value = value
+                .to_str()
+                .map_err(|_| "invalid extensions header".to_owned())?;
+
+            for extension in value.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                let mut params = extension.split(';').map(str::trim);
+                let name = params.next().unwrap_or_default();
+                if !name.eq_ignore_ascii_case(EXTENSION_NAME) {
+                    return Err(format!("unexpected extension: {name}"));
+                }
+                if negotiated.is_some() {
+                    return Err(format!("duplicate extension: {name}"));
+                }
+                negotiated = Some(self.accept(params)?);
+            }
+        }
+
+        Ok(negotiated)
+    }
+
+    fn accept<'a>(&self, params: impl Iterator<Item = &'a str>) -> Result<WsCompression, String> {
+        // A window size we asked for binds us even if the server omits it.
+        let mut accepted = WsCompression {
+            client_max_window_bits: Some(self.client_max_window_bits.unwrap_or(MAX_WINDOW_BITS)),
+            server_max_window_bits: Some(MAX_WINDOW_BITS),
+            client_no_context_takeover: false,
+            server_no_context_takeover: false,
+        };
+        let mut seen = Vec::with_capacity(4);
+
+        for param in params.filter(|s| !s.is_empty()) {
+            let (key, value) = match param.split_once('=') {
+                Some((key, value)) => (key.trim(), Some(value.trim().trim_matches('"'))),
+                None => (param, None),
+            };
+
+            if seen.contains(&key) {
+                return Err(format!("duplicate extension parameter: {key}"));
+            }
+            seen.push(key);
+
+            match (key, value) {
+                ("client_no_context_takeover", None) => accepted.client_no_context_takeover = true,
+                ("server_no_context_takeover", None) => accepted.server_no_context_takeover = true,
+                ("client_max_window_bits", Some(value)) => {
+                    let bits = parse_window_bits(key, value, self.client_max_window_bits)?;
+                    accepted.client_max_window_bits = Some(bits);
+                }
+                ("server_max_window_bits", Some(value)) => {
+                    let bits = parse_window_bits(key, value, self.server_max_window_bits)?;
+                    accepted.server_max_window_bits = Some(bits);
+                }
+                _ => return Err(format!("invalid extension parameter: {param}")),
+            }
+        }
+
+        // The server must honor a context takeover restriction we asked for.
+        if self.server_no_context_takeover && !accepted.server_no_context_takeover {
+            return Err("server ignored server_no_context_takeover".to_owned());
+        }
+        accepted.client_no_context_takeover |= self.client_no_context_takeover;
+
+        Ok(accepted)
+    }
+}
+
+fn parse_window_bits(key: &str, value: &str, offered: Option<u8>) -> Result<u8, String> {
+    let bits = value
+        .parse::<u8>()
+        .ok()
+        .filter(|bits| (MIN_WINDOW_BITS..=MAX_WINDOW_BITS).contains(bits))
+        .ok_or_else(|| format!("invalid {key}: {value}"))?;
+
+    match offered {
+        Some(max) if bits > max => Err(format!("{key} exceeds offer: {bits}")),
+        _ => Ok(bits),
+    }
+}
+
+/// Compression state of an established connection.
+pub(super) struct DeflateContext {
+    compress: Compress,
+    decompress: Decompress,
+    client_no_context_takeover: bool,
+    server_no_context_takeover: bool,
+}
+
+impl DeflateContext {
+    pub(super) fn new(params: &WsCompression) -> DeflateContext {
+        // miniz always compresses with a 32KiB window. If the server restricts
+        // ours, emit stored blocks only: they never reference earlier data.
+        let level = match params.client_max_window_bits {
+            Some(bits) if bits < MAX_WINDOW_BITS => Compression::none(),
+            _ => Compression::default(),
+        };
+
+        DeflateContext {
+            compress: Compress::new(level, false),
+            decompress: Decompress::new(false),
+            client_no_context_takeover: params.client_no_context_takeover,
+            server_no_context_takeover: params.server_no_context_takeover,
+        }
+    }
+
+    /// Compress one frame of an outgoing message.
+    pub(super) fn compress(&mut self, payload: &[u8], fin: bool) -> io::Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(payload.len() / 2 + 64);
+        let start = self.compress.total_in();
+
+        loop {
+            let (before_in, before_out) = (self.compress.total_in(), self.compress.total_out());
+            let consumed = (before_in - start) as usize;
+
+            self.compress
+                .compress_vec(&payload[consumed..], &mut out, FlushCompress::Sync)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            let consumed = (self.compress.total_in() - start) as usize;
+            if consumed == payload.len() && out.len() < out.capacity() {
+                break;
+            }
+            if self.compress.total_in() == before_in
+                && self.compress.total_out() == before_out
+                && out.len() < out.capacity()
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "deflate stream stalled",
+                ));
+            }
+            out.reserve(out.capacity().max(64));
+        }
+
+        if fin {
+            if out.ends_with(&TRAILER) {
+                out.truncate(out.len() - TRAILER.len());
+            }
+            if self.client_no_context_takeover {
+                self.compress.reset();
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Decompress one frame of an incoming message.
+    ///
+    /// `limit` bounds the size of the inflated payload.
+    pub(super) fn decompress(
+        &mut self,
+        payload: &[u8],
+        fin: bool,
+        limit: Option<usize>,
+    ) -> io::Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(payload.len().saturating_mul(2).max(64));
+        self.inflate(payload, &mut out, limit)?;
+
+        if fin {
+            self.inflate(&TRAILER, &mut out, limit)?;
+            if self.server_no_context_takeover {
+                self.decompress.reset(false);
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn inflate(&mut self, input: &[u8], out: &mut Vec<u8>, limit: Option<usize>) -> io::Result<()> {
+        let start = self.decompress.total_in();
+
+        loop {
+            let (before_in, before_out) = (self.decompress.total_in(), self.decompress.total_out());
+            let consumed = (before_in - start) as usize;
+
+            let status = self
+                .decompress
+                .decompress_vec(&input[consumed..], out, FlushDecompress::Sync)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            if let Some(limit) = limit.filter(|limit| out.len() > *limit) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    WsError::MessageTooLarge {
+                        size: out.len(),
+                        max_size: limit,
+                    },
+                ));
+            }
+
+            let consumed = (self.decompress.total_in() - start) as usize;
+            if matches!(status, Status::StreamEnd)
+                || (consumed == input.len() && out.len() < out.capacity())
+            {
+                return Ok(());
+            }
+            if self.decompress.total_in() == before_in
+                && self.decompress.total_out() == before_out
+                && out.len() < out.capacity()
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "deflate stream stalled",
+                ));
+            }
+            out.reserve(out.capacity().max(64));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeflateContext, WsCompression};
+    use http::{header::SEC_WEBSOCKET_EXTENSIONS, HeaderMap, HeaderValue};
+
+    fn negotiate(
+        offer: WsCompression,
+        response: &'static str,
+    ) -> Result<Option<WsCompression>, String> {
+        let mut headers = HeaderMap::new();
+        headers.insert(SEC_WEBSOCKET_EXTENSIONS, HeaderValue::from_static(response));
+        offer.negotiate(headers.get_all(SEC_WEBSOCKET_EXTENSIONS))
+    }
+
+    #[test]
+    fn default_offer_matches_browsers() {
+        assert_eq!(
+            WsCompression::default().offer(),
+            "permessage-deflate; client_max_window_bits"
+        );
+    }
+
+    #[test]
+    fn negotiate_server_parameters() {
+        let negotiated = negotiate(
+            WsCompression::default(),
+            "permessage-deflate; server_no_context_takeover; client_max_window_bits=12",
+        )
+        .unwrap()
+        .unwrap();
+
+        assert!(negotiated.server_no_context_takeover);
+        assert!(!negotiated.client_no_context_takeover);
+        assert_eq!(negotiated.client_max_window_bits, Some(12));
+        assert_eq!(negotiated.server_max_window_bits, Some(15));
+    }
+
+    #[test]
+    fn negotiate_rejects_invalid_responses() {
+        let offer = WsCompression {
+            server_max_window_bits: Some(10),
+            ..Default::default()
+        };
+
+        assert!(negotiate(offer, "x-webkit-deflate-frame").is_err());
+        assert!(negotiate(offer, "permessage-deflate; server_max_window_bits=12").is_err());
+        assert!(negotiate(offer, "permessage-deflate; client_max_window_bits=7").is_err());
+        assert!(negotiate(offer, "permessage-deflate; foo").is_err());
+        assert!(negotiate(offer, "permessage-deflate, permessage-deflate").is_err());
+    }
+
+    #[test]
+    fn roundtrip_with_context_takeover() {
+        let params = WsCompression::default();
+        let mut client = DeflateContext::new(&params);
+        let mut server = DeflateContext::new(&params);
+
+        for _ in 0..3 {
+            let message = b"Hello, World! Hello, World! Hello, World!";
+            let compressed = client.compress(message, true).unwrap();
+            assert!(!compressed.ends_with(&super::TRAILER));
+
+            let decompressed = server.decompress(&compressed, true, None).unwrap();
+            assert_eq!(decompressed, message);
+        }
+    }
+
+    #[test]
+    fn roundtrip_fragmented_message() {
+        let params = WsCompression {
+            client_no_context_takeover: true,
+            server_no_context_takeover: true,
+            ..Default::default()
+        };
+        let mut client = DeflateContext::new(&params);
+        let mut server = DeflateContext::new(&params);
+
+        let first = client.compress(b"fragmented ", false).unwrap();
+        let last = client.compress(b"message", true).unwrap();
+
+        let mut decompressed = server.decompress(&first, false, None).unwrap();
+        decompressed.extend(server.decompress(&last, true, None).unwrap());
+        assert_eq!(decompressed, b"fragmented message");
+    }
+
+    #[test]
+    fn decompress_respects_limit() {
+        let params = WsCompression::default();
+        let mut client = DeflateContext::new(&params);
+        let mut server = DeflateContext::new(&params);
+
+        let compressed = client.compress(&[0u8; 4096], true).unwrap();
+        assert!(server.decompress(&compressed, true, Some(1024)).is_err());
+    }
+}
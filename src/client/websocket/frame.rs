@@ -0,0 +1,680 @@
+//! Frame level rewriting of the websocket byte stream.
+//!
+//! tungstenite does not implement extensions and rejects frames with a
+//! reserved bit set, so compressed frames are rewritten beneath it: inbound
+//! frames are inflated and have `RSV1` cleared, outbound data frames are
+//! deflated and have `RSV1` set on the first frame of each message.
+//!
+//! Outbound frames are also where the [`WsBehaviorSettings`] are applied:
+//! they are re-masked, fragmented and checked here.
+
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{Buf, BufMut, BytesMut};
+use futures_core::ready;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_util::io::poll_read_buf;
+
+use super::behavior::{MaskGenerator, WsBehaviorSettings};
+use super::deflate::DeflateContext;
+use super::WsError;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+
+/// Encoded bytes kept before `poll_write` waits for the transport.
+const WRITE_HIGH_WATER: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FrameHeader {
+    fin: bool,
+    rsv1: bool,
+    rsv2: bool,
+    rsv3: bool,
+    opcode: u8,
+    mask: Option<[u8; 4]>,
+    payload_len: u64,
+}
+
+impl FrameHeader {
+    /// Parse a frame header, returning it with its encoded length.
+    fn parse(buf: &[u8]) -> Option<(FrameHeader, usize)> {
+        let (&first, &second) = (buf.first()?, buf.get(1)?);
+
+        let (payload_len, mut len) = match second & 0x7f {
+            126 => (
+                u16::from_be_bytes(buf.get(2..4)?.try_into().ok()?) as u64,
+                4,
+            ),
+            127 => (u64::from_be_bytes(buf.get(2..10)?.try_into().ok()?), 10),
+            n => (n as u64, 2),
+        };
+
+        let mask = if second & 0x80 != 0 {
+            let mask = buf.get(len..len + 4)?.try_into().ok()?;
+            len += 4;
+            Some(mask)
+        } else {
+            None
+        };
+
+        let header = FrameHeader {
+            fin: first & 0x80 != 0,
+            rsv1: first & 0x40 != 0,
+            rsv2: first & 0x20 != 0,
+            rsv3: first & 0x10 != 0,
+            opcode: first & 0x0f,
+            mask,
+            payload_len,
+        };
+
+        Some((header, len))
+    }
+
+    fn encode(&self, dst: &mut BytesMut) {
+        let first = (self.fin as u8) << 7
+            | (self.rsv1 as u8) << 6
+            | (self.rsv2 as u8) << 5
+            | (self.rsv3 as u8) << 4
+            | self.opcode;
+        let masked = if self.mask.is_some() { 0x80 } else { 0 };
+
+        dst.put_u8(first);
+        match self.payload_len {
+            len if len < 126 => dst.put_u8(masked | len as u8),
+            len if len <= u16::MAX as u64 => {
+                dst.put_u8(masked | 126);
+                dst.put_u16(len as u16);
+            }
+            len => {
+                dst.put_u8(masked | 127);
+                dst.put_u64(len);
+            }
+        }
+
+        if let Some(mask) = self.mask {
+            dst.put_slice(&mask);
+        }
+    }
+
+    fn is_data(&self) -> bool {
+        matches!(self.opcode, OP_CONTINUATION | OP_TEXT | OP_BINARY)
+    }
+}
+
+fn apply_mask(buf: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in buf.iter_mut().enumerate() {
+        *byte ^= mask[i & 3];
+    }
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn too_large(size: usize, max_size: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        WsError::MessageTooLarge { size, max_size },
+    )
+}
+
+/// Transport adapter applying the negotiated extensions.
+///
+/// Without an extension or behavior settings to apply, every call is
+/// forwarded to the transport as is.
+pub struct FrameStream<T> {
+    io: T,
+    deflate: Option<DeflateContext>,
+    behavior: WsBehaviorSettings,
+    masks: MaskGenerator,
+    max_frame_size: Option<usize>,
+    max_message_size: Option<usize>,
+    read_buffer_size: Option<usize>,
+
+    read_raw: BytesMut,
+    read_ready: BytesMut,
+    /// Payload bytes of the current inbound frame to forward unmodified.
+    read_passthrough: usize,
+    /// Whether the inbound message in progress is compressed.
+    read_compressed: bool,
+    read_message_len: usize,
+
+    write_raw: BytesMut,
+    write_ready: BytesMut,
+    /// Payload bytes of the current outbound frame to forward unmodified.
+    write_passthrough: usize,
+}
+
+impl<T> FrameStream<T> {
+    pub(super) fn new(
+        io: T,
+        deflate: Option<DeflateContext>,
+        behavior: WsBehaviorSettings,
+        max_frame_size: Option<usize>,
+        max_message_size: Option<usize>,
+        read_buffer_size: Option<usize>,
+    ) -> FrameStream<T> {
+        FrameStream {
+            io,
+            deflate,
+            behavior,
+            masks: MaskGenerator::new(behavior.masking),
+            max_frame_size,
+            max_message_size,
+            read_buffer_size,
+            read_raw: BytesMut::new(),
+            read_ready: BytesMut::new(),
+            read_passthrough: 0,
+            read_compressed: false,
+            read_message_len: 0,
+            write_raw: BytesMut::new(),
+            write_ready: BytesMut::new(),
+            write_passthrough: 0,
+        }
+    }
+
+    /// Move the next inbound frame, or the part of it already received, to
+    /// `read_ready`. Returns `false` if more bytes are needed.
+    fn decode(&mut self) -> io::Result<bool> {
+        if self.read_passthrough > 0 {
+            let len = self.read_passthrough.min(self.read_raw.len());
+            self.read_ready
+                .extend_from_slice(&self.read_raw.split_to(len));
+            self.read_passthrough -= len;
+            return Ok(len > 0);
+        }
+
+        let Some((mut header, header_len)) = FrameHeader::parse(&self.read_raw) else {
+            return Ok(false);
+        };
+
+        // Servers must not mask frames; leave those for tungstenite to reject.
+        let inflate = header.is_data() && header.mask.is_none() && {
+            if header.opcode != OP_CONTINUATION {
+                self.read_compressed = header.rsv1;
+                self.read_message_len = 0;
+            } else if header.rsv1 {
+                return Err(invalid_data("RSV1 set on continuation frame"));
+            }
+            self.read_compressed
+        };
+
+        let payload_len =
+            usize::try_from(header.payload_len).map_err(|_| invalid_data("frame too large"))?;
+
+        if !inflate {
+            self.read_ready
+                .extend_from_slice(&self.read_raw.split_to(header_len));
+            self.read_passthrough = payload_len;
+            return Ok(true);
+        }
+
+        if let Some(max) = self.max_frame_size.filter(|max| payload_len > *max) {
+            return Err(too_large(payload_len, max));
+        }
+
+        let frame_len = header_len + payload_len;
+        if self.read_raw.len() < frame_len {
+            self.read_raw.reserve(frame_len - self.read_raw.len());
+            return Ok(false);
+        }
+
+        self.read_raw.advance(header_len);
+        let payload = self.read_raw.split_to(payload_len);
+
+        let (received, max_size) = (self.read_message_len, self.max_message_size);
+        let payload = self
+            .deflate
+            .as_mut()
+            .ok_or_else(|| invalid_data("RSV1 set without negotiated compression"))?
+            .decompress(
+                &payload,
+                header.fin,
+                max_size.map(|max| max.saturating_sub(received)),
+            )
+            .map_err(|e| match WsError::from_io(&e) {
+                // report the size of the whole message
+                Some(WsError::MessageTooLarge { size, .. }) => {
+                    too_large(received + size, max_size.unwrap_or_default())
+                }
+                _ => e,
+            })?;
+        self.read_message_len += payload.len();
+
+        header.rsv1 = false;
+        header.payload_len = payload.len() as u64;
+        header.encode(&mut self.read_ready);
+        self.read_ready.extend_from_slice(&payload);
+
+        Ok(true)
+    }
+
+    /// Move the next outbound frame, or the part of it already written, to
+    /// `write_ready`. Returns `false` if more bytes are needed.
+    fn encode(&mut self) -> io::Result<bool> {
+        if self.write_passthrough > 0 {
+            let len = self.write_passthrough.min(self.write_raw.len());
+            self.write_ready
+                .extend_from_slice(&self.write_raw.split_to(len));
+            self.write_passthrough -= len;
+            return Ok(len > 0);
+        }
+
+        let Some((mut header, header_len)) = FrameHeader::parse(&self.write_raw) else {
+            return Ok(false);
+        };
+
+        let payload_len =
+            usize::try_from(header.payload_len).map_err(|_| invalid_data("frame too large"))?;
+
+        let compress = header.is_data() && self.deflate.is_some();
+        if !compress && !self.behavior.rewrites_frames() {
+            self.write_ready
+                .extend_from_slice(&self.write_raw.split_to(header_len));
+            self.write_passthrough = payload_len;
+            return Ok(true);
+        }
+
+        let frame_len = header_len + payload_len;
+        if self.write_raw.len() < frame_len {
+            return Ok(false);
+        }
+
+        self.write_raw.advance(header_len);
+        let mut payload = self.write_raw.split_to(payload_len);
+
+        if let Some(mask) = header.mask {
+            apply_mask(&mut payload, mask);
+        }
+
+        // tungstenite writes every message as a single frame.
+        if self.behavior.validate_utf8
+            && header.opcode == OP_TEXT
+            && header.fin
+            && std::str::from_utf8(&payload).is_err()
+        {
+            return Err(invalid_data("text message is not valid UTF-8"));
+        }
+
+        if let Some(deflate) = self.deflate.as_mut().filter(|_| compress) {
+            payload = BytesMut::from(&deflate.compress(&payload, header.fin)?[..]);
+            // Only the first frame of a message carries the compression bit.
+            header.rsv1 = header.opcode != OP_CONTINUATION;
+        }
+
+        // Control frames must not be fragmented.
+        let fragment_size = match self.behavior.fragment_size {
+            Some(size) if header.is_data() => size.max(1),
+            _ => payload.len().max(1),
+        };
+
+        let mut first = true;
+        loop {
+            let chunk = payload.split_to(fragment_size.min(payload.len()));
+            let last = payload.is_empty();
+
+            let frame = FrameHeader {
+                fin: header.fin && last,
+                rsv1: header.rsv1 && first,
+                opcode: if first {
+                    header.opcode
+                } else {
+                    OP_CONTINUATION
+                },
+                mask: header.mask.map(|_| self.masks.next_key()),
+                payload_len: chunk.len() as u64,
+                ..header
+            };
+            frame.encode(&mut self.write_ready);
+
+            let start = self.write_ready.len();
+            self.write_ready.extend_from_slice(&chunk);
+            if let Some(mask) = frame.mask {
+                apply_mask(&mut self.write_ready[start..], mask);
+            }
+
+            if last {
+                return Ok(true);
+            }
+            first = false;
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for FrameStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameStream")
+            .field("io", &self.io)
+            .field("deflate", &self.deflate.is_some())
+            .field("behavior", &self.behavior)
+            .finish()
+    }
+}
+
+impl<T: AsyncWrite + Unpin> FrameStream<T> {
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.write_ready.is_empty() {
+            let n = ready!(Pin::new(&mut self.io).poll_write(cx, &self.write_ready))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.write_ready.advance(n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for FrameStream<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if this.deflate.is_none() {
+            let Some(read_buffer_size) = this.read_buffer_size else {
+                return Pin::new(&mut this.io).poll_read(cx, buf);
+            };
+
+            if this.read_raw.is_empty() {
+                this.read_raw.reserve(read_buffer_size);
+                ready!(poll_read_buf(
+                    Pin::new(&mut this.io),
+                    cx,
+                    &mut this.read_raw
+                ))?;
+            }
+
+            let len = this.read_raw.len().min(buf.remaining());
+            buf.put_slice(&this.read_raw.split_to(len));
+            return Poll::Ready(Ok(()));
+        }
+
+        loop {
+            if !this.read_ready.is_empty() {
+                let len = this.read_ready.len().min(buf.remaining());
+                buf.put_slice(&this.read_ready.split_to(len));
+                return Poll::Ready(Ok(()));
+            }
+
+            if this.decode()? {
+                continue;
+            }
+
+            if let Some(read_buffer_size) = this.read_buffer_size {
+                this.read_raw.reserve(read_buffer_size);
+            }
+
+            if ready!(poll_read_buf(
+                Pin::new(&mut this.io),
+                cx,
+                &mut this.read_raw
+            ))? == 0
+            {
+                // Hand a truncated frame to tungstenite, which reports the error.
+                let len = this.read_raw.len();
+                let len = len.min(buf.remaining());
+                buf.put_slice(&this.read_raw.split_to(len));
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for FrameStream<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if this.deflate.is_none() && !this.behavior.rewrites_frames() {
+            return Pin::new(&mut this.io).poll_write(cx, buf);
+        }
+
+        if this.write_ready.len() >= WRITE_HIGH_WATER {
+            ready!(this.poll_drain(cx))?;
+        }
+
+        this.write_raw.extend_from_slice(buf);
+        while this.encode()? {}
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.io).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        apply_mask, FrameHeader, FrameStream, WsError, OP_BINARY, OP_CONTINUATION, OP_TEXT,
+    };
+    use crate::client::websocket::behavior::{MaskGenerator, WsBehaviorSettings, WsMasking};
+    use crate::client::websocket::deflate::{DeflateContext, WsCompression};
+    use bytes::BytesMut;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn header_roundtrip() {
+        for payload_len in [0, 125, 126, 65535, 65536] {
+            let header = FrameHeader {
+                fin: true,
+                rsv1: true,
+                rsv2: false,
+                rsv3: false,
+                opcode: OP_BINARY,
+                mask: Some([1, 2, 3, 4]),
+                payload_len,
+            };
+
+            let mut buf = BytesMut::new();
+            header.encode(&mut buf);
+            assert_eq!(FrameHeader::parse(&buf), Some((header, buf.len())));
+            assert_eq!(FrameHeader::parse(&buf[..buf.len() - 1]), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn compressed_roundtrip() {
+        let params = WsCompression::default();
+        let (client, mut server) = tokio::io::duplex(1024);
+        let mut client = FrameStream::new(
+            client,
+            Some(DeflateContext::new(&params)),
+            WsBehaviorSettings::default(),
+            None,
+            None,
+            None,
+        );
+        let mut server_deflate = DeflateContext::new(&params);
+
+        // Outbound: a masked text frame is compressed and flagged.
+        let message = b"Hello, World! Hello, World!";
+        let mask = [0x37, 0xfa, 0x21, 0x3d];
+        let mut payload = message.to_vec();
+        apply_mask(&mut payload, mask);
+        let mut frame = BytesMut::new();
+        FrameHeader {
+            fin: true,
+            rsv1: false,
+            rsv2: false,
+            rsv3: false,
+            opcode: OP_TEXT,
+            mask: Some(mask),
+            payload_len: payload.len() as u64,
+        }
+        .encode(&mut frame);
+        frame.extend_from_slice(&payload);
+
+        client.write_all(&frame).await.unwrap();
+        client.flush().await.unwrap();
+
+        let mut buf = vec![0; 1024];
+        let n = server.read(&mut buf).await.unwrap();
+        let (header, header_len) = FrameHeader::parse(&buf[..n]).unwrap();
+        assert!(header.rsv1);
+        let mut payload = buf[header_len..n].to_vec();
+        apply_mask(&mut payload, header.mask.unwrap());
+        let inflated = server_deflate.decompress(&payload, true, None).unwrap();
+        assert_eq!(inflated, message);
+
+        // Inbound: a compressed frame is inflated and the flag cleared.
+        let mut server_ctx = DeflateContext::new(&params);
+        let compressed = server_ctx.compress(message, true).unwrap();
+        let mut frame = BytesMut::new();
+        FrameHeader {
+            fin: true,
+            rsv1: true,
+            rsv2: false,
+            rsv3: false,
+            opcode: OP_TEXT,
+            mask: None,
+            payload_len: compressed.len() as u64,
+        }
+        .encode(&mut frame);
+        frame.extend_from_slice(&compressed);
+        server.write_all(&frame).await.unwrap();
+
+        let n = client.read(&mut buf).await.unwrap();
+        let (header, header_len) = FrameHeader::parse(&buf[..n]).unwrap();
+        assert!(!header.rsv1);
+        assert_eq!(&buf[header_len..n], message);
+    }
+
+    #[tokio::test]
+    async fn compressed_message_too_large() {
+        let params = WsCompression::default();
+        let (client, mut server) = tokio::io::duplex(4096);
+        let mut client = FrameStream::new(
+            client,
+            Some(DeflateContext::new(&params)),
+            WsBehaviorSettings::default(),
+            None,
+            Some(1024),
+            None,
+        );
+
+        let compressed = DeflateContext::new(&params)
+            .compress(&[0u8; 2048], true)
+            .unwrap();
+        let mut frame = BytesMut::new();
+        FrameHeader {
+            fin: true,
+            rsv1: true,
+            rsv2: false,
+            rsv3: false,
+            opcode: OP_BINARY,
+            mask: None,
+            payload_len: compressed.len() as u64,
+        }
+        .encode(&mut frame);
+        frame.extend_from_slice(&compressed);
+        server.write_all(&frame).await.unwrap();
+
+        let err = client.read(&mut [0; 4096]).await.unwrap_err();
+        assert!(matches!(
+            WsError::from_io(&err),
+            Some(WsError::MessageTooLarge { max_size: 1024, .. })
+        ));
+    }
+
+    fn masked_frame(opcode: u8, message: &[u8]) -> BytesMut {
+        let mask = [0x37, 0xfa, 0x21, 0x3d];
+        let mut payload = message.to_vec();
+        apply_mask(&mut payload, mask);
+        let mut frame = BytesMut::new();
+        FrameHeader {
+            fin: true,
+            rsv1: false,
+            rsv2: false,
+            rsv3: false,
+            opcode,
+            mask: Some(mask),
+            payload_len: payload.len() as u64,
+        }
+        .encode(&mut frame);
+        frame.extend_from_slice(&payload);
+        frame
+    }
+
+    #[tokio::test]
+    async fn fragmented_with_seeded_masks() {
+        let behavior = WsBehaviorSettings {
+            masking: WsMasking::Seeded(7),
+            fragment_size: Some(4),
+            validate_utf8: true,
+        };
+        let (client, mut server) = tokio::io::duplex(1024);
+        let mut client = FrameStream::new(client, None, behavior, None, None, None);
+
+        let message = b"0123456789";
+        client
+            .write_all(&masked_frame(OP_BINARY, message))
+            .await
+            .unwrap();
+        client.flush().await.unwrap();
+
+        let mut buf = vec![0; 1024];
+        let n = server.read(&mut buf).await.unwrap();
+        let mut buf = &buf[..n];
+
+        let mut masks = MaskGenerator::new(WsMasking::Seeded(7));
+        let mut received = Vec::new();
+        for (opcode, fin, len) in [
+            (OP_BINARY, false, 4),
+            (OP_CONTINUATION, false, 4),
+            (OP_CONTINUATION, true, 2),
+        ] {
+            let (header, header_len) = FrameHeader::parse(buf).unwrap();
+            assert_eq!(
+                (header.opcode, header.fin, header.payload_len),
+                (opcode, fin, len)
+            );
+            assert_eq!(header.mask, Some(masks.next_key()));
+
+            let mut payload = buf[header_len..header_len + len as usize].to_vec();
+            apply_mask(&mut payload, header.mask.unwrap());
+            received.extend_from_slice(&payload);
+            buf = &buf[header_len + len as usize..];
+        }
+        assert!(buf.is_empty());
+        assert_eq!(received, message);
+    }
+
+    #[tokio::test]
+    async fn invalid_utf8_text() {
+        let (client, _server) = tokio::io::duplex(1024);
+        let mut client = FrameStream::new(
+            client,
+            None,
+            WsBehaviorSettings::default(),
+            None,
+            None,
+            None,
+        );
+
+        let err = client
+            .write_all(&masked_frame(OP_TEXT, &[0xff, 0xfe]))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -61,6 +61,31 @@ impl From<&[u8]> for Message {
     }
 }
 
+/// A close frame, with the code and reason the endpoint gave for closing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CloseFrame {
+    /// The close code.
+    pub code: CloseCode,
+    /// The reason for closing the connection, possibly empty.
+    pub reason: String,
+}
+
+impl From<Option<tungstenite::protocol::CloseFrame<'_>>> for CloseFrame {
+    fn from(value: Option<tungstenite::protocol::CloseFrame<'_>>) -> Self {
+        match value {
+            Some(frame) => Self {
+                code: frame.code.into(),
+                reason: frame.reason.into_owned(),
+            },
+            // the server didn't include a status code
+            None => Self {
+                code: CloseCode::Status,
+                reason: String::new(),
+            },
+        }
+    }
+}
+
 /// Status code used to indicate why an endpoint is closing the `WebSocket`
 /// connection.[1]
 ///
@@ -1,24 +1,39 @@
+mod behavior;
+mod deflate;
+mod frame;
 #[cfg(feature = "json")]
 mod json;
 mod message;
+mod pool;
 
 use std::{
+    error::Error as StdError,
+    fmt,
+    future::Future,
     ops::{Deref, DerefMut},
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use crate::{error::Kind, RequestBuilder};
 use crate::{Error, Response};
 use async_tungstenite::tungstenite;
+pub use behavior::{WsBehaviorSettings, WsMasking};
+pub use deflate::WsCompression;
+use frame::FrameStream;
+use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{Sink, SinkExt, Stream, StreamExt};
 use http::{header, HeaderName, HeaderValue, StatusCode, Version};
-pub use message::{CloseCode, Message};
+pub use message::{CloseCode, CloseFrame, Message};
+pub(crate) use pool::WsPoolState;
+pub use pool::{WebSocketConnection, WebSocketPool};
+use tokio::time::{Instant, Sleep};
 use tokio_util::compat::TokioAsyncReadCompatExt;
 use tungstenite::protocol::WebSocketConfig;
 
 pub type WebSocketStream =
-    async_tungstenite::WebSocketStream<tokio_util::compat::Compat<crate::Upgraded>>;
+    async_tungstenite::WebSocketStream<tokio_util::compat::Compat<FrameStream<crate::Upgraded>>>;
 
 /// Wrapper for [`RequestBuilder`] that performs the
 /// websocket handshake when sent.
@@ -27,15 +42,29 @@ pub struct WebSocketRequestBuilder {
     inner: RequestBuilder,
     nonce: Option<String>,
     protocols: Option<Vec<String>>,
+    compression: Option<WsCompression>,
+    ping_interval: Option<Duration>,
+    pong_timeout: Option<Duration>,
+    close_timeout: Duration,
+    close_on_drop: bool,
+    read_buffer_size: Option<usize>,
+    behavior: WsBehaviorSettings,
     config: WebSocketConfig,
 }
 
 impl WebSocketRequestBuilder {
-    pub(crate) fn new(inner: RequestBuilder) -> Self {
+    pub(crate) fn new(inner: RequestBuilder, behavior: WsBehaviorSettings) -> Self {
         Self {
             inner,
             nonce: None,
             protocols: None,
+            compression: None,
+            ping_interval: None,
+            pong_timeout: None,
+            close_timeout: DEFAULT_CLOSE_TIMEOUT,
+            close_on_drop: true,
+            read_buffer_size: None,
+            behavior,
             config: WebSocketConfig::default(),
         }
     }
@@ -74,7 +103,58 @@ impl WebSocketRequestBuilder {
         self
     }
 
-    /// Sets the websocket max_frame_size configuration.
+    /// Offers the `permessage-deflate` extension to the server.
+    ///
+    /// Messages are compressed only if the server accepts the offer, the
+    /// negotiated parameters are available from [`WebSocket::compression`].
+    pub fn compression(mut self, compression: WsCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Sends a Ping frame whenever nothing was sent for `interval`.
+    ///
+    /// Keepalive pings are driven while the [`WebSocket`] is polled for
+    /// messages, and their Pongs are not yielded from the stream.
+    pub fn ping_interval(mut self, interval: Duration) -> Self {
+        self.ping_interval = Some(interval);
+        self
+    }
+
+    /// Sets how long to wait for the Pong answering a keepalive ping.
+    ///
+    /// If the Pong doesn't arrive in time the connection is closed and the
+    /// stream yields a [`WsError::PongTimeout`] error. Defaults to the
+    /// [`ping_interval`](Self::ping_interval), and has no effect without it.
+    pub fn pong_timeout(mut self, timeout: Duration) -> Self {
+        self.pong_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how long [`WebSocket::close`] waits for the server's close frame.
+    ///
+    /// Default is 5 seconds.
+    pub fn close_timeout(mut self, timeout: Duration) -> Self {
+        self.close_timeout = timeout;
+        self
+    }
+
+    /// Sets whether dropping an open [`WebSocket`] sends a close frame
+    /// with [`CloseCode::Away`].
+    ///
+    /// Default is `true`.
+    pub fn close_on_drop(mut self, enabled: bool) -> Self {
+        self.close_on_drop = enabled;
+        self
+    }
+
+    /// Sets the maximum size of a single frame.
+    ///
+    /// A larger frame fails the read with [`WsError::MessageTooLarge`] and
+    /// closes the connection with [`CloseCode::Size`]. For compressed frames
+    /// the limit applies to the compressed payload.
+    ///
+    /// Default is 16 MiB.
     pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
         self.config.max_frame_size = Some(max_frame_size);
         self
@@ -92,12 +172,34 @@ impl WebSocketRequestBuilder {
         self
     }
 
-    /// Sets the websocket max_message_size configuration.
+    /// Sets the maximum size of a message, including all its fragments.
+    ///
+    /// A larger message fails the read with [`WsError::MessageTooLarge`] and
+    /// closes the connection with [`CloseCode::Size`]. For compressed messages
+    /// the limit applies to the decompressed payload.
+    ///
+    /// Default is 64 MiB.
     pub fn max_message_size(mut self, max_message_size: usize) -> Self {
         self.config.max_message_size = Some(max_message_size);
         self
     }
 
+    /// Sets how many bytes are read from the connection at once.
+    ///
+    /// A larger buffer reduces the number of reads for high-throughput feeds.
+    pub fn read_buffer_size(mut self, read_buffer_size: usize) -> Self {
+        self.read_buffer_size = Some(read_buffer_size);
+        self
+    }
+
+    /// Sets how outgoing messages are masked and framed.
+    ///
+    /// Defaults to the settings of the client's impersonation profile.
+    pub fn behavior(mut self, behavior: WsBehaviorSettings) -> Self {
+        self.behavior = behavior;
+        self
+    }
+
     /// Sets the websocket accept_unmasked_frames configuration.
     pub fn accept_unmasked_frames(mut self, accept_unmasked_frames: bool) -> Self {
         self.config.accept_unmasked_frames = accept_unmasked_frames;
@@ -150,6 +252,19 @@ impl WebSocketRequestBuilder {
                         );
                     }
                 }
+
+                if let Some(ref compression) = self.compression {
+                    compression
+                        .validate()
+                        .map_err(|e| Error::new(Kind::Builder, Some(e)))?;
+
+                    request.headers_mut().insert(
+                        header::SEC_WEBSOCKET_EXTENSIONS,
+                        compression.offer().parse().map_err(|_| {
+                            Error::new(Kind::Builder, Some("invalid extension offer"))
+                        })?,
+                    );
+                }
             }
 
             Version::HTTP_2 => {
@@ -165,6 +280,14 @@ impl WebSocketRequestBuilder {
             inner: client.execute(request).await?,
             nonce,
             protocols: self.protocols,
+            compression: self.compression,
+            keepalive: self
+                .ping_interval
+                .map(|interval| (interval, self.pong_timeout.unwrap_or(interval))),
+            close_timeout: self.close_timeout,
+            close_on_drop: self.close_on_drop,
+            read_buffer_size: self.read_buffer_size,
+            behavior: self.behavior,
             version,
             config: self.config,
         })
@@ -180,6 +303,12 @@ pub struct WebSocketResponse {
     inner: Response,
     nonce: String,
     protocols: Option<Vec<String>>,
+    compression: Option<WsCompression>,
+    keepalive: Option<(Duration, Duration)>,
+    close_timeout: Duration,
+    close_on_drop: bool,
+    read_buffer_size: Option<usize>,
+    behavior: WsBehaviorSettings,
     version: Version,
     config: WebSocketConfig,
 }
@@ -201,10 +330,11 @@ impl DerefMut for WebSocketResponse {
 impl WebSocketResponse {
     /// Turns the response into a websocket. This checks if the websocket
     /// handshake was successful.
-    pub async fn into_websocket(self) -> Result<WebSocket, Error> {
-        let (inner, protocol) = {
-            let headers = self.inner.headers();
-
+    ///
+    /// If the server didn't switch protocols, the response is kept in the
+    /// error and can be taken back with [`Error::into_response`].
+    pub async fn into_websocket(mut self) -> Result<WebSocket, Error> {
+        let (inner, protocol, compression) = {
             // Check the version
             if self.inner.version() != self.version {
                 return Err(Error::new(
@@ -215,12 +345,11 @@ impl WebSocketResponse {
 
             // Check the status code
             if self.inner.status() != StatusCode::SWITCHING_PROTOCOLS {
-                return Err(Error::new(
-                    Kind::Upgrade,
-                    Some(format!("unexpected status code: {}", self.inner.status())),
-                ));
+                return Err(crate::error::unexpected_response(self.inner));
             }
 
+            let headers = self.inner.headers();
+
             // Check the connection header
             if let Some(header) = headers.get(header::CONNECTION) {
                 if !header
@@ -294,7 +423,7 @@ impl WebSocketResponse {
                 (false, None) => {
                     // server didn't reply with a protocol
                     return Err(Error::new(
-                        Kind::Status(self.res.status()),
+                        Kind::Status(self.inner.status()),
                         Some("missing protocol"),
                     ));
                 }
@@ -303,14 +432,14 @@ impl WebSocketResponse {
                         if !protocols.contains(protocol) {
                             // the responded protocol is none which we requested
                             return Err(Error::new(
-                                Kind::Status(self.res.status()),
+                                Kind::Status(self.inner.status()),
                                 Some(format!("invalid protocol: {}", protocol)),
                             ));
                         }
                     } else {
                         // we didn't request any protocols but got one anyway
                         return Err(Error::new(
-                            Kind::Status(self.res.status()),
+                            Kind::Status(self.inner.status()),
                             Some("invalid protocol"),
                         ));
                     }
@@ -318,23 +447,59 @@ impl WebSocketResponse {
                 (true, Some(_)) => {
                     // we didn't request any protocols but got one anyway
                     return Err(Error::new(
-                        Kind::Status(self.res.status()),
+                        Kind::Status(self.inner.status()),
                         Some("invalid protocol"),
                     ));
                 }
             }
 
+            // Ensure the server accepted the extensions we offered, if any
+            let extensions = headers.get_all(header::SEC_WEBSOCKET_EXTENSIONS);
+            let compression = match self.compression {
+                Some(ref offer) => offer.negotiate(extensions).map_err(|e| {
+                    log::debug!("server responded with invalid extensions: {e}");
+                    Error::new(Kind::Upgrade, Some(e))
+                })?,
+                None if extensions.iter().next().is_some() => {
+                    // we didn't offer any extensions but got one anyway
+                    return Err(Error::new(Kind::Upgrade, Some("unexpected extension")));
+                }
+                None => None,
+            };
+
+            let io = FrameStream::new(
+                self.inner.upgrade_in_place().await?,
+                compression.as_ref().map(deflate::DeflateContext::new),
+                self.behavior,
+                self.config.max_frame_size,
+                self.config.max_message_size,
+                self.read_buffer_size,
+            );
+
             let inner = async_tungstenite::WebSocketStream::from_raw_socket(
-                self.inner.upgrade().await?.compat(),
+                io.compat(),
                 async_tungstenite::tungstenite::protocol::Role::Client,
                 Some(self.config),
             )
             .await;
 
-            (inner, protocol)
+            (inner, protocol, compression)
         };
 
-        Ok(WebSocket { inner, protocol })
+        Ok(WebSocket {
+            inner,
+            response: self.inner,
+            protocol,
+            compression,
+            keepalive: self
+                .keepalive
+                .map(|(interval, timeout)| Keepalive::new(interval, timeout)),
+            close_timeout: self.close_timeout,
+            close_on_drop: self.close_on_drop,
+            close_frame: None,
+            closed: false,
+            failed: false,
+        })
     }
 }
 
@@ -342,35 +507,231 @@ impl WebSocketResponse {
 #[derive(Debug)]
 pub struct WebSocket {
     inner: WebSocketStream,
+    response: Response,
     protocol: Option<String>,
+    compression: Option<WsCompression>,
+    keepalive: Option<Keepalive>,
+    close_timeout: Duration,
+    close_on_drop: bool,
+    /// The close frame received from the server.
+    close_frame: Option<CloseFrame>,
+    /// Whether a close frame was sent or received.
+    closed: bool,
+    /// Whether the connection failed with an error.
+    failed: bool,
+}
+
+const DEFAULT_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Keepalive ping state of a [`WebSocket`].
+#[derive(Debug)]
+struct Keepalive {
+    interval: Duration,
+    timeout: Duration,
+    /// Fires at the next ping, or at the pong deadline while one is in flight.
+    timer: Pin<Box<Sleep>>,
+    /// Payload of a ping waiting for room in the sink.
+    queued: Option<[u8; 8]>,
+    /// Payload of a ping waiting for its pong.
+    in_flight: Option<[u8; 8]>,
+    flushing: bool,
+    sent_traffic: bool,
+    expired: bool,
+}
+
+impl Keepalive {
+    fn new(interval: Duration, timeout: Duration) -> Keepalive {
+        Keepalive {
+            interval,
+            timeout,
+            timer: Box::pin(tokio::time::sleep(interval)),
+            queued: None,
+            in_flight: None,
+            flushing: false,
+            sent_traffic: false,
+            expired: false,
+        }
+    }
+
+    fn reset(&mut self, after: Duration) {
+        self.timer.as_mut().reset(Instant::now() + after);
+    }
 }
 
 impl WebSocket {
+    /// Returns the server's response to the upgrade request.
+    ///
+    /// This gives access to the handshake headers, the HTTP version and the
+    /// response extensions, such as [`TlsInfo`](crate::tls::TlsInfo).
+    pub fn response(&self) -> &Response {
+        &self.response
+    }
+
     /// Returns the protocol negotiated during the handshake.
     pub fn protocol(&self) -> Option<&str> {
         self.protocol.as_deref()
     }
 
-    /// Closes the connection with a given code and (optional) reason.
+    /// Returns the `permessage-deflate` parameters negotiated during the handshake.
+    pub fn compression(&self) -> Option<&WsCompression> {
+        self.compression.as_ref()
+    }
+
+    /// Returns the close frame sent by the server, once it was received.
+    pub fn close_frame(&self) -> Option<&CloseFrame> {
+        self.close_frame.as_ref()
+    }
+
+    /// Performs the closing handshake with a given code and reason.
     ///
-    /// # WASM
+    /// This sends a close frame and waits for the server's close frame, up to
+    /// the [`close_timeout`](WebSocketRequestBuilder::close_timeout). Messages
+    /// received in the meantime are discarded.
     ///
-    /// On wasm `code` must be [`CloseCode::Normal`], [`CloseCode::Iana(_)`],
-    /// or [`CloseCode::Library(_)`]. Furthermore `reason` must be at most 123
-    /// bytes long. Otherwise the call to [`close`][Self::close] will fail.
-    pub async fn close(self, code: CloseCode, reason: Option<&str>) -> Result<(), Error> {
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            let mut inner = self.inner;
-            inner
-                .close(Some(tungstenite::protocol::CloseFrame {
-                    code: code.into(),
-                    reason: reason.unwrap_or_default().into(),
-                }))
+    /// Returns the server's close frame, or `None` if the connection ended
+    /// or the timeout elapsed without one. Fails if sending the close frame
+    /// or reading the server's reply fails.
+    pub async fn close(
+        mut self,
+        code: impl Into<CloseCode>,
+        reason: &str,
+    ) -> Result<Option<CloseFrame>, Error> {
+        if !self.closed {
+            let code: CloseCode = code.into();
+            self.closed = true;
+            self.inner
+                .send(tungstenite::Message::Close(Some(
+                    tungstenite::protocol::CloseFrame {
+                        code: code.into(),
+                        reason: reason.to_owned().into(),
+                    },
+                )))
                 .await?;
         }
 
-        Ok(())
+        let (inner, close_frame) = (&mut self.inner, &mut self.close_frame);
+        let echo = async {
+            while close_frame.is_none() {
+                match inner.next().await {
+                    Some(Ok(tungstenite::Message::Close(frame))) => {
+                        *close_frame = Some(frame.into());
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(tungstenite::Error::ConnectionClosed)) | None => break,
+                    Some(Err(error)) => return Err(Error::from(error)),
+                }
+            }
+            Ok::<(), Error>(())
+        };
+
+        match tokio::time::timeout(self.close_timeout, echo).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => return Err(e),
+            Err(_elapsed) => {
+                log::debug!(
+                    "websocket close frame not received within {:?}",
+                    self.close_timeout
+                );
+            }
+        }
+
+        Ok(self.close_frame.take())
+    }
+}
+
+impl Drop for WebSocket {
+    fn drop(&mut self) {
+        if !self.close_on_drop || self.closed {
+            return;
+        }
+
+        let mut cx = Context::from_waker(futures_util::task::noop_waker_ref());
+        self.try_send_close(&mut cx, CloseCode::Away);
+    }
+}
+
+impl WebSocket {
+    /// Whether the connection can still be used: it was neither closed nor
+    /// failed, as far as it was read from and written to.
+    pub(crate) fn is_open(&self) -> bool {
+        !self.closed && !self.failed
+    }
+
+    /// Sends a close frame without waiting: it is only sent if the sink has room now.
+    fn try_send_close(&mut self, cx: &mut Context<'_>, code: CloseCode) {
+        self.closed = true;
+
+        if let Poll::Ready(Ok(())) = self.inner.poll_ready_unpin(cx) {
+            let frame = tungstenite::protocol::CloseFrame {
+                code: code.into(),
+                reason: "".into(),
+            };
+            if self
+                .inner
+                .start_send_unpin(tungstenite::Message::Close(Some(frame)))
+                .is_ok()
+            {
+                let _ = self.inner.poll_flush_unpin(cx);
+            }
+        }
+    }
+
+    /// Sends keepalive pings and checks their pongs arrive in time.
+    fn poll_keepalive(&mut self, cx: &mut Context<'_>) -> Result<(), Error> {
+        let Some(keepalive) = self.keepalive.as_mut() else {
+            return Ok(());
+        };
+
+        loop {
+            if let Some(payload) = keepalive.queued {
+                if self.inner.poll_ready_unpin(cx)?.is_pending() {
+                    return Ok(());
+                }
+                self.inner
+                    .start_send_unpin(tungstenite::Message::Ping(payload.to_vec()))?;
+                keepalive.queued = None;
+                keepalive.in_flight = Some(payload);
+                keepalive.flushing = true;
+                keepalive.reset(keepalive.timeout);
+            }
+
+            if keepalive.flushing && self.inner.poll_flush_unpin(cx)?.is_ready() {
+                keepalive.flushing = false;
+            }
+
+            if keepalive.timer.as_mut().poll(cx).is_pending() {
+                return Ok(());
+            }
+
+            if keepalive.in_flight.is_some() {
+                log::debug!("websocket pong not received within {:?}", keepalive.timeout);
+                keepalive.expired = true;
+                // Best effort: the close frame is flushed if the sink has room.
+                self.closed = true;
+                let _ = self.inner.poll_close_unpin(cx);
+                return Err(crate::error::upgrade(WsError::PongTimeout));
+            }
+
+            if std::mem::take(&mut keepalive.sent_traffic) {
+                keepalive.reset(keepalive.interval);
+            } else {
+                keepalive.queued = Some(crate::util::fast_random().to_be_bytes());
+            }
+        }
+    }
+
+    /// Whether `message` answers the keepalive ping in flight.
+    fn is_keepalive_pong(&mut self, message: &tungstenite::Message) -> bool {
+        match (self.keepalive.as_mut(), message) {
+            (Some(keepalive), tungstenite::Message::Pong(data))
+                if keepalive.in_flight.is_some_and(|p| p[..] == data[..]) =>
+            {
+                keepalive.in_flight = None;
+                keepalive.reset(keepalive.interval);
+                true
+            }
+            _ => false,
+        }
     }
 }
 
@@ -378,21 +739,60 @@ impl Stream for WebSocket {
     type Item = Result<Message, Error>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        match self.inner.poll_next_unpin(cx) {
-            Poll::Pending => Poll::Pending,
-            Poll::Ready(None) => Poll::Ready(None),
-            Poll::Ready(Some(Err(error))) => Poll::Ready(Some(Err(error.into()))),
-            Poll::Ready(Some(Ok(message))) => match message.try_into() {
-                Ok(message) => Poll::Ready(Some(Ok(message))),
-                Err(e) => {
-                    // this fails only for raw frames (which are not received)
-                    log::debug!("received invalid frame: {:?}", e);
-                    Poll::Ready(Some(Err(Error::new(
-                        Kind::Body,
-                        Some("unsupported websocket frame"),
-                    ))))
+        if self.keepalive.as_ref().is_some_and(|k| k.expired) {
+            return Poll::Ready(None);
+        }
+
+        if let Err(error) = self.poll_keepalive(cx) {
+            return Poll::Ready(Some(Err(error)));
+        }
+
+        loop {
+            return match self.inner.poll_next_unpin(cx) {
+                Poll::Pending => Poll::Pending,
+                Poll::Ready(None) => {
+                    self.closed = true;
+                    Poll::Ready(None)
+                }
+                Poll::Ready(Some(Err(
+                    tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed,
+                ))) if self.close_frame.is_some() => {
+                    // the server's close frame was already yielded
+                    Poll::Ready(None)
+                }
+                Poll::Ready(Some(Err(error))) => match WsError::from_tungstenite(&error) {
+                    Some(error @ WsError::MessageTooLarge { .. }) => {
+                        log::debug!("{error}");
+                        self.try_send_close(cx, CloseCode::Size);
+                        Poll::Ready(Some(Err(crate::error::upgrade(error))))
+                    }
+                    _ => {
+                        self.failed = true;
+                        Poll::Ready(Some(Err(error.into())))
+                    }
+                },
+                Poll::Ready(Some(Ok(message))) if self.is_keepalive_pong(&message) => continue,
+                Poll::Ready(Some(Ok(tungstenite::Message::Close(frame)))) => {
+                    let frame = CloseFrame::from(frame);
+                    self.closed = true;
+                    self.close_frame = Some(frame.clone());
+                    Poll::Ready(Some(Ok(Message::Close {
+                        code: frame.code,
+                        reason: Some(frame.reason),
+                    })))
                 }
-            },
+                Poll::Ready(Some(Ok(message))) => match message.try_into() {
+                    Ok(message) => Poll::Ready(Some(Ok(message))),
+                    Err(e) => {
+                        // this fails only for raw frames (which are not received)
+                        log::debug!("received invalid frame: {:?}", e);
+                        Poll::Ready(Some(Err(Error::new(
+                            Kind::Body,
+                            Some("unsupported websocket frame"),
+                        ))))
+                    }
+                },
+            };
         }
     }
 }
@@ -401,22 +801,147 @@ impl Sink<Message> for WebSocket {
     type Error = Error;
 
     fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        self.inner.poll_ready_unpin(cx).map_err(Into::into)
+        let ready = self.inner.poll_ready_unpin(cx);
+        self.failed |= matches!(ready, Poll::Ready(Err(_)));
+        ready.map_err(Into::into)
     }
 
     fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
-        self.inner.start_send_unpin(item.into()).map_err(Into::into)
+        if let Some(ref mut keepalive) = self.keepalive {
+            keepalive.sent_traffic = true;
+        }
+        if matches!(item, Message::Close { .. }) {
+            self.closed = true;
+        }
+        let sent = self.inner.start_send_unpin(item.into());
+        self.failed |= sent.is_err();
+        sent.map_err(Into::into)
     }
 
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        self.inner.poll_flush_unpin(cx).map_err(Into::into)
+        let flushed = self.inner.poll_flush_unpin(cx);
+        self.failed |= matches!(flushed, Poll::Ready(Err(_)));
+        flushed.map_err(Into::into)
     }
 
     fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.closed = true;
         self.inner.poll_close_unpin(cx).map_err(Into::into)
     }
 }
 
+/// The sending half of a [`WebSocket`], created by [`WebSocket::split`].
+#[derive(Debug)]
+pub struct WsSink {
+    inner: SplitSink<WebSocket, Message>,
+}
+
+/// The receiving half of a [`WebSocket`], created by [`WebSocket::split`].
+///
+/// After the sink sent a close frame, this yields the peer's close frame and
+/// then ends.
+#[derive(Debug)]
+pub struct WsStream {
+    inner: SplitStream<WebSocket>,
+}
+
+impl WebSocket {
+    /// Splits the connection into halves that can be used from separate tasks.
+    ///
+    /// Neither half waits for the other: a pending read doesn't hold up sends.
+    pub fn split(self) -> (WsSink, WsStream) {
+        let (sink, stream) = StreamExt::split(self);
+        (WsSink { inner: sink }, WsStream { inner: stream })
+    }
+
+    /// Puts the halves created by [`split`](Self::split) back together.
+    ///
+    /// Fails, returning the halves, if they come from different connections.
+    pub fn reunite(sink: WsSink, stream: WsStream) -> Result<WebSocket, (WsSink, WsStream)> {
+        stream
+            .inner
+            .reunite(sink.inner)
+            .map_err(|e| (WsSink { inner: e.0 }, WsStream { inner: e.1 }))
+    }
+}
+
+impl Stream for WsStream {
+    type Item = Result<Message, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl Sink<Message> for WsSink {
+    type Error = Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready_unpin(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        self.inner.start_send_unpin(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_flush_unpin(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_close_unpin(cx)
+    }
+}
+
+/// WebSocket specific errors, found in the source chain of an [`Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WsError {
+    /// The Pong answering a keepalive ping didn't arrive in time.
+    PongTimeout,
+    /// A frame or message exceeded the configured size limit.
+    MessageTooLarge {
+        /// The size received, which may be only part of the frame or message.
+        size: usize,
+        /// The configured limit.
+        max_size: usize,
+    },
+}
+
+impl WsError {
+    fn from_io(error: &std::io::Error) -> Option<WsError> {
+        error.get_ref()?.downcast_ref::<WsError>().copied()
+    }
+
+    fn from_tungstenite(error: &tungstenite::Error) -> Option<WsError> {
+        match error {
+            tungstenite::Error::Capacity(tungstenite::error::CapacityError::MessageTooLong {
+                size,
+                max_size,
+            }) => Some(WsError::MessageTooLarge {
+                size: *size,
+                max_size: *max_size,
+            }),
+            tungstenite::Error::Io(error) => WsError::from_io(error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for WsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WsError::PongTimeout => f.write_str("websocket pong timed out"),
+            WsError::MessageTooLarge { size, max_size } => write!(
+                f,
+                "websocket message too large: {size} bytes exceeds {max_size}"
+            ),
+        }
+    }
+}
+
+impl StdError for WsError {}
+
 impl TryFrom<tungstenite::Message> for Message {
     type Error = tungstenite::Message;
 
@@ -0,0 +1,302 @@
+//! Persistent WebSocket connections shared by the tasks of a client.
+//!
+//! Each host is allowed a number of connections, whether checked out, idle
+//! or being opened. A connection is handed back to the pool once its
+//! checkout is dropped, and forgotten if it was closed or failed by then,
+//! freeing its place for a new one.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+
+use futures_util::{Sink, Stream};
+use tokio::sync::Notify;
+use url::Url;
+
+use super::{Message, WebSocket};
+use crate::{Client, Error, IntoUrl};
+
+/// The idle connections to a host, and how many it has in all.
+#[derive(Default)]
+struct Host {
+    /// Connections checked out, idle or being opened.
+    open: usize,
+    /// Idle connections, the one returned last at the end.
+    idle: Vec<(Url, WebSocket)>,
+}
+
+/// The WebSocket connections of a client, by origin.
+pub(crate) struct WsPoolState {
+    max_per_host: usize,
+    hosts: Mutex<HashMap<String, Host>>,
+    /// Notified when a connection is returned or closed.
+    released: Notify,
+}
+
+impl WsPoolState {
+    pub(crate) fn new(max_per_host: usize) -> WsPoolState {
+        WsPoolState {
+            max_per_host: max_per_host.max(1),
+            hosts: Mutex::default(),
+            released: Notify::new(),
+        }
+    }
+
+    pub(crate) fn max_per_host(&self) -> usize {
+        self.max_per_host
+    }
+
+    /// Waits for a place for a connection to `url`, along with an idle
+    /// connection to it if there is one.
+    async fn checkout(self: &Arc<Self>, url: &Url) -> (Slot, Option<WebSocket>) {
+        let origin = url.origin().ascii_serialization();
+        loop {
+            // Created before looking at the host, so that a connection
+            // released in between is not missed.
+            let released = self.released.notified();
+            {
+                let mut hosts = self.lock();
+                let host = hosts.entry(origin.clone()).or_default();
+                let idle = host.idle.len();
+                host.idle.retain(|(_, socket)| socket.is_open());
+                host.open -= idle - host.idle.len();
+
+                if let Some(i) = host.idle.iter().rposition(|(idle, _)| idle == url) {
+                    let (_, socket) = host.idle.remove(i);
+                    return (self.slot(origin), Some(socket));
+                }
+                if host.open >= self.max_per_host && !host.idle.is_empty() {
+                    // Makes room with the connection idle the longest,
+                    // which is bound to another URL.
+                    host.idle.remove(0);
+                    host.open -= 1;
+                }
+                if host.open < self.max_per_host {
+                    host.open += 1;
+                    return (self.slot(origin), None);
+                }
+            }
+            released.await;
+        }
+    }
+
+    fn slot(self: &Arc<Self>, origin: String) -> Slot {
+        Slot {
+            pool: self.clone(),
+            origin,
+            idle: None,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Host>> {
+        self.hosts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The place of a connection in the pool, released on drop: the connection
+/// it holds by then is returned idle.
+struct Slot {
+    pool: Arc<WsPoolState>,
+    origin: String,
+    idle: Option<(Url, WebSocket)>,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let mut hosts = self.pool.lock();
+        if let Some(host) = hosts.get_mut(&self.origin) {
+            match self.idle.take() {
+                Some(idle) => host.idle.push(idle),
+                None => host.open -= 1,
+            }
+            if host.open == 0 {
+                hosts.remove(&self.origin);
+            }
+        }
+        drop(hosts);
+        self.pool.released.notify_waiters();
+    }
+}
+
+/// A pool of persistent WebSocket connections, created by
+/// [`Client::websocket_pool`].
+///
+/// Up to [`max_ws_connections`](crate::ClientBuilder::max_ws_connections)
+/// connections are kept open to each host. A connection is bound to the URL
+/// it was opened to, and is only checked out again for that URL. Checking
+/// out a connection while all of them are in use waits for one to be
+/// returned; an idle connection to another URL of the host is closed to
+/// make room for a new one instead.
+///
+/// A connection closed or failed by the time it is returned is dropped, and
+/// the next checkout opens a new one in its place. A connection closed by
+/// the server while idle is only noticed once it is checked out and used.
+///
+/// Cloning the pool is cheap, clones share their connections, as do the
+/// clones of the client.
+#[derive(Clone)]
+pub struct WebSocketPool {
+    client: Client,
+    state: Arc<WsPoolState>,
+}
+
+impl WebSocketPool {
+    pub(crate) fn new(client: Client, state: Arc<WsPoolState>) -> WebSocketPool {
+        WebSocketPool { client, state }
+    }
+
+    /// Checks out a connection to `url`, opening one with the client's
+    /// settings if none is idle.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if `url` cannot be parsed, or if opening a new
+    /// connection fails, in which case its place in the pool is released.
+    pub async fn websocket<U: IntoUrl>(&self, url: U) -> Result<WebSocketConnection, Error> {
+        let url = url.into_url()?;
+        let (slot, idle) = self.state.checkout(&url).await;
+        let socket = match idle {
+            Some(socket) => socket,
+            None => {
+                self.client
+                    .websocket(url.clone())
+                    .send()
+                    .await?
+                    .into_websocket()
+                    .await?
+            }
+        };
+        Ok(WebSocketConnection {
+            socket: Some(socket),
+            url,
+            slot,
+        })
+    }
+}
+
+impl fmt::Debug for WebSocketPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebSocketPool")
+            .field("max_per_host", &self.state.max_per_host)
+            .finish()
+    }
+}
+
+/// A WebSocket connection checked out from a [`WebSocketPool`].
+///
+/// It derefs to the [`WebSocket`], and is a [`Stream`] and a [`Sink`] of
+/// messages like it. The connection is returned to the pool on drop,
+/// unless it was closed or failed. Messages still unread by then are
+/// received by whoever checks it out next.
+pub struct WebSocketConnection {
+    socket: Option<WebSocket>,
+    url: Url,
+    slot: Slot,
+}
+
+impl WebSocketConnection {
+    /// Returns the URL the connection was opened to.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Takes the connection out of the pool, freeing its place there.
+    pub fn into_inner(mut self) -> WebSocket {
+        self.socket.take().expect("socket taken on drop")
+    }
+
+    fn socket(&mut self) -> Pin<&mut WebSocket> {
+        Pin::new(self.socket.as_mut().expect("socket taken on drop"))
+    }
+}
+
+impl Deref for WebSocketConnection {
+    type Target = WebSocket;
+
+    fn deref(&self) -> &WebSocket {
+        self.socket.as_ref().expect("socket taken on drop")
+    }
+}
+
+impl DerefMut for WebSocketConnection {
+    fn deref_mut(&mut self) -> &mut WebSocket {
+        self.socket.as_mut().expect("socket taken on drop")
+    }
+}
+
+impl Drop for WebSocketConnection {
+    fn drop(&mut self) {
+        if let Some(socket) = self.socket.take() {
+            if socket.is_open() {
+                self.slot.idle = Some((self.url.clone(), socket));
+            }
+        }
+    }
+}
+
+impl fmt::Debug for WebSocketConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebSocketConnection")
+            .field("url", &self.url)
+            .field("socket", &self.socket)
+            .finish()
+    }
+}
+
+impl Stream for WebSocketConnection {
+    type Item = Result<Message, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.socket().poll_next(cx)
+    }
+}
+
+impl Sink<Message> for WebSocketConnection {
+    type Error = Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.socket().poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Error> {
+        self.socket().start_send(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.socket().poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.socket().poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::pin;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn checkout_waits_for_a_place() {
+        let pool = Arc::new(WsPoolState::new(2));
+        let url = Url::parse("wss://example.com/feed").unwrap();
+
+        let (first, idle) = pool.checkout(&url).await;
+        assert!(idle.is_none());
+        let (second, _) = pool.checkout(&url).await;
+
+        let mut third = pin!(pool.checkout(&url));
+        let waited = tokio::time::timeout(Duration::from_millis(50), third.as_mut()).await;
+        assert!(waited.is_err());
+
+        drop(first);
+        let (third, idle) = third.await;
+        assert!(idle.is_none());
+        drop((second, third));
+        assert!(pool.lock().is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+//! What the open connections of a client are.
+//!
+//! hyper's pool can't be looked into, so each connection registers itself
+//! under its origin once connected instead, and is forgotten once it is
+//! closed. Looking a connection up doesn't touch the pool.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::{Duration, Instant};
+
+use http::{Uri, Version};
+use url::Url;
+
+use crate::h2_stats::H2Handle;
+
+/// An open connection of a client, see
+/// [`Client::connection_info`](crate::Client::connection_info).
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ConnectionInfo {
+    /// The address connected to, the one of the proxy for connections
+    /// through one. `None` if the socket couldn't tell.
+    pub remote_addr: Option<SocketAddr>,
+    /// The TLS version negotiated, `None` without TLS.
+    #[cfg(feature = "boring-tls")]
+    pub tls_version: Option<crate::tls::Version>,
+    /// The name of the cipher suite negotiated, `None` without TLS.
+    #[cfg(feature = "boring-tls")]
+    pub cipher_suite: Option<String>,
+    /// `HTTP/2` once the connection sent the HTTP/2 preface, `HTTP/1.1`
+    /// otherwise.
+    pub http_version: Version,
+    /// How long ago the connection was opened.
+    pub connection_age: Duration,
+    /// How long ago anything was last sent or received on the connection.
+    pub idle_for: Duration,
+    /// The streams open on an HTTP/2 connection. Not counted on HTTP/1
+    /// connections, where it is always 0.
+    pub stream_count: usize,
+}
+
+/// The origin of `dst`, under which its connections are registered.
+pub(crate) fn origin(dst: &Uri) -> Option<String> {
+    let (scheme, authority) = (dst.scheme_str()?, dst.authority()?);
+    Url::parse(&format!("{scheme}://{authority}"))
+        .ok()
+        .map(|url| url.origin().ascii_serialization())
+}
+
+/// What a connection is, as it was connected.
+pub(crate) struct ConnDetails {
+    pub(crate) remote_addr: Option<SocketAddr>,
+    #[cfg(feature = "boring-tls")]
+    pub(crate) tls_version: Option<crate::tls::Version>,
+    #[cfg(feature = "boring-tls")]
+    pub(crate) cipher_suite: Option<String>,
+}
+
+struct ConnState {
+    details: ConnDetails,
+    h2: H2Handle,
+    opened: Instant,
+    /// When the connection was last active, in nanoseconds since it was
+    /// opened.
+    active: AtomicU64,
+}
+
+impl ConnState {
+    fn idle_for(&self) -> Duration {
+        let active = Duration::from_nanos(self.active.load(Ordering::Relaxed));
+        self.opened.elapsed().saturating_sub(active)
+    }
+
+    fn info(&self) -> ConnectionInfo {
+        let streams = self.h2.open_streams();
+        ConnectionInfo {
+            remote_addr: self.details.remote_addr,
+            #[cfg(feature = "boring-tls")]
+            tls_version: self.details.tls_version,
+            #[cfg(feature = "boring-tls")]
+            cipher_suite: self.details.cipher_suite.clone(),
+            http_version: match streams {
+                Some(_) => Version::HTTP_2,
+                None => Version::HTTP_11,
+            },
+            connection_age: self.opened.elapsed(),
+            idle_for: self.idle_for(),
+            stream_count: streams.unwrap_or(0),
+        }
+    }
+}
+
+/// The open connections of a connector, by origin.
+#[derive(Default)]
+pub(crate) struct ConnRegistry {
+    conns: Mutex<HashMap<String, Vec<Weak<ConnState>>>>,
+}
+
+impl ConnRegistry {
+    /// The connection to the origin of `url` used last, if one is open.
+    pub(crate) fn info(&self, url: &str) -> Option<ConnectionInfo> {
+        let origin = Url::parse(url).ok()?.origin().ascii_serialization();
+        let conns = self.lock();
+        conns
+            .get(&origin)?
+            .iter()
+            .filter_map(Weak::upgrade)
+            .min_by_key(|conn| conn.idle_for())
+            .map(|conn| conn.info())
+    }
+
+    /// Registers a connection to `dst`, until the returned tracker is
+    /// dropped.
+    pub(crate) fn register(&self, dst: &Uri, details: ConnDetails, h2: H2Handle) -> ConnTracker {
+        let state = Arc::new(ConnState {
+            details,
+            h2,
+            opened: Instant::now(),
+            active: AtomicU64::new(0),
+        });
+        if let Some(origin) = origin(dst) {
+            let mut conns = self.lock();
+            conns.retain(|_, conns| {
+                conns.retain(|conn| conn.strong_count() > 0);
+                !conns.is_empty()
+            });
+            conns
+                .entry(origin)
+                .or_default()
+                .push(Arc::downgrade(&state));
+        }
+        ConnTracker(state)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Vec<Weak<ConnState>>>> {
+        self.conns.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Keeps a connection registered, held by the connection.
+pub(crate) struct ConnTracker(Arc<ConnState>);
+
+impl ConnTracker {
+    /// Called when something is sent or received on the connection.
+    pub(crate) fn on_active(&self) {
+        let active = self.0.opened.elapsed().as_nanos() as u64;
+        self.0.active.store(active, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::h2_stats::{H2Registry, H2Tracker};
+
+    fn details() -> ConnDetails {
+        ConnDetails {
+            remote_addr: Some(([127, 0, 0, 1], 443).into()),
+            #[cfg(feature = "boring-tls")]
+            tls_version: None,
+            #[cfg(feature = "boring-tls")]
+            cipher_suite: None,
+        }
+    }
+
+    #[test]
+    fn registers_open_connections() {
+        let registry = ConnRegistry::default();
+        let dst = Uri::from_static("https://example.com/");
+        let h2 = H2Tracker::new(&Arc::new(H2Registry::default()), &dst, None);
+        assert_eq!(registry.info("https://example.com/"), None);
+
+        let tracker = registry.register(&dst, details(), h2.handle());
+        tracker.on_active();
+        let info = registry.info("https://example.com/path").unwrap();
+        assert_eq!(info.remote_addr, Some(([127, 0, 0, 1], 443).into()));
+        assert_eq!(info.http_version, Version::HTTP_11);
+        assert_eq!(info.stream_count, 0);
+        assert!(info.idle_for <= info.connection_age);
+        assert_eq!(registry.info("https://example.org/"), None);
+
+        drop(tracker);
+        assert_eq!(registry.info("https://example.com/"), None);
+    }
+}
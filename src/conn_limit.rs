@@ -0,0 +1,233 @@
+//! Limits on the connections a client opens.
+//!
+//! hyper's pool opens a new connection whenever no idle one is available,
+//! so the limits gate the connector instead: a new connection first waits
+//! for a permit, held until the connection is closed. Requests whose
+//! connection waits are handed an idle connection as soon as one is
+//! returned to the pool.
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use http::Uri;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::error::BoxError;
+
+/// Idle host semaphores are dropped past this many hosts.
+const MAX_IDLE_HOSTS: usize = 1024;
+
+/// The connections of a client, see
+/// [`Client::pool_stats`](crate::Client::pool_stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PoolStats {
+    /// Connections open, idle or not.
+    pub connections: usize,
+    /// New connections waiting for the
+    /// [`max_connections_per_host`](crate::ClientBuilder::max_connections_per_host)
+    /// or [`max_total_connections`](crate::ClientBuilder::max_total_connections)
+    /// limit.
+    pub waiting: usize,
+}
+
+/// The limits and counters of the connections of a connector.
+#[derive(Default)]
+pub(crate) struct ConnLimits {
+    per_host: Option<usize>,
+    total: Option<Arc<Semaphore>>,
+    checkout_timeout: Option<Duration>,
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+    connections: AtomicUsize,
+    waiting: AtomicUsize,
+}
+
+impl ConnLimits {
+    pub(crate) fn new(
+        per_host: Option<usize>,
+        total: Option<usize>,
+        checkout_timeout: Option<Duration>,
+    ) -> ConnLimits {
+        ConnLimits {
+            per_host,
+            total: total.map(|total| Arc::new(Semaphore::new(total))),
+            checkout_timeout,
+            ..ConnLimits::default()
+        }
+    }
+
+    pub(crate) fn stats(&self) -> PoolStats {
+        PoolStats {
+            connections: self.connections.load(Ordering::Relaxed),
+            waiting: self.waiting.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Waits for a connection to `dst` to be allowed, failing past the
+    /// checkout timeout.
+    pub(crate) async fn acquire(self: &Arc<Self>, dst: &Uri) -> Result<ConnPermit, BoxError> {
+        let (host, total) = if self.per_host.is_none() && self.total.is_none() {
+            (None, None)
+        } else {
+            // Decremented on drop too, hyper drops a waiting connect once an
+            // idle connection is checked out instead.
+            let _waiting = Waiting::new(&self.waiting);
+            let acquire = async {
+                // The host permit first, so a connection waiting for its
+                // host doesn't keep one to another host from opening.
+                let host = match self.per_host {
+                    Some(max) => self.host_semaphore(dst, max).acquire_owned().await.ok(),
+                    None => None,
+                };
+                let total = match self.total {
+                    Some(ref total) => total.clone().acquire_owned().await.ok(),
+                    None => None,
+                };
+                (host, total)
+            };
+            let acquired = match self.checkout_timeout {
+                Some(timeout) => tokio::time::timeout(timeout, acquire).await,
+                None => Ok(acquire.await),
+            };
+
+            match acquired {
+                Ok(permits) => permits,
+                Err(_elapsed) => {
+                    log::debug!("no connection to {:?} available in time", dst);
+                    return Err(Box::new(PoolExhausted {
+                        timeout: self.checkout_timeout.unwrap_or_default(),
+                    }));
+                }
+            }
+        };
+
+        self.connections.fetch_add(1, Ordering::Relaxed);
+        Ok(ConnPermit {
+            limits: self.clone(),
+            _host: host,
+            _total: total,
+        })
+    }
+
+    fn host_semaphore(&self, dst: &Uri, max: usize) -> Arc<Semaphore> {
+        let key = match (dst.scheme_str(), dst.authority()) {
+            (Some(scheme), Some(authority)) => format!("{scheme}://{authority}"),
+            _ => dst.to_string(),
+        };
+
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        if hosts.len() >= MAX_IDLE_HOSTS && !hosts.contains_key(&key) {
+            // Permits, held or waited for, keep a reference to their host.
+            hosts.retain(|_, host| Arc::strong_count(host) > 1);
+        }
+        hosts
+            .entry(key)
+            .or_insert_with(|| Arc::new(Semaphore::new(max)))
+            .clone()
+    }
+}
+
+/// Allows a connection to stay open, counting it until it is dropped.
+pub(crate) struct ConnPermit {
+    limits: Arc<ConnLimits>,
+    _host: Option<OwnedSemaphorePermit>,
+    _total: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for ConnPermit {
+    fn drop(&mut self) {
+        self.limits.connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl<'a> Waiting<'a> {
+    fn new(waiting: &'a AtomicUsize) -> Waiting<'a> {
+        waiting.fetch_add(1, Ordering::Relaxed);
+        Waiting(waiting)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// No connection was available within the
+/// [`pool_checkout_timeout`](crate::ClientBuilder::pool_checkout_timeout).
+#[derive(Debug)]
+pub(crate) struct PoolExhausted {
+    timeout: Duration,
+}
+
+impl fmt::Display for PoolExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "pool exhausted: no connection available within {:?}",
+            self.timeout
+        )
+    }
+}
+
+impl StdError for PoolExhausted {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn waits_for_a_host_permit() {
+        let limits = Arc::new(ConnLimits::new(
+            Some(1),
+            None,
+            Some(Duration::from_millis(50)),
+        ));
+        let a: Uri = "http://a.example/".parse().unwrap();
+        let b: Uri = "http://b.example/".parse().unwrap();
+
+        let first = limits.acquire(&a).await.unwrap();
+        let other_host = limits.acquire(&b).await.unwrap();
+        assert_eq!(
+            limits.stats(),
+            PoolStats {
+                connections: 2,
+                waiting: 0
+            }
+        );
+
+        let err = limits.acquire(&a).await.err().unwrap();
+        assert!(err.is::<PoolExhausted>());
+        assert_eq!(limits.stats().connections, 2);
+
+        drop(first);
+        let _second = limits.acquire(&a).await.unwrap();
+        drop(other_host);
+        assert_eq!(limits.stats().connections, 1);
+    }
+
+    #[tokio::test]
+    async fn total_limit_spans_hosts() {
+        let limits = Arc::new(ConnLimits::new(None, Some(1), None));
+        let a: Uri = "http://a.example/".parse().unwrap();
+        let b: Uri = "http://b.example/".parse().unwrap();
+
+        let first = limits.acquire(&a).await.unwrap();
+        let waiting = tokio::spawn({
+            let limits = limits.clone();
+            async move { limits.acquire(&b).await.map(drop) }
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(limits.stats().waiting, 1);
+
+        drop(first);
+        waiting.await.unwrap().unwrap();
+        assert_eq!(limits.stats(), PoolStats::default());
+    }
+}
@@ -1,7 +1,12 @@
 #[cfg(feature = "boring-tls")]
 use self::boring_tls_conn::BoringTlsConn;
 #[cfg(feature = "boring-tls")]
-use crate::tls::{BoringTlsConnector, MaybeHttpsStream};
+use crate::alt_svc::{AltService, AltSvcCache};
+#[cfg(feature = "boring-tls")]
+use crate::tls::{
+    AdaptiveVersionCache, HttpVersionCacheStats, MaybeHttpsStream, MaybeLazyTlsConnector,
+    ProtocolInfo, SessionCacheStats,
+};
 #[cfg(feature = "boring-tls")]
 use http::header::HeaderValue;
 use http::uri::{Authority, Scheme};
@@ -13,30 +18,90 @@ use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use pin_project_lite::pin_project;
 use std::future::Future;
 use std::io::{self, IoSlice};
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 
+use crate::conn_info::{ConnDetails, ConnRegistry, ConnTracker, ConnectionInfo};
+use crate::conn_limit::{ConnLimits, ConnPermit, PoolStats};
 use crate::dns::DynResolver;
-use crate::error::BoxError;
+use crate::error::{BoxError, TimedOut};
+use crate::h2_stats::{H2ConnectionStats, H2Registry, H2Tracker};
+use crate::metrics::{self, ConnectMetrics, ConnectTimer, Phase};
 use crate::proxy::{Proxy, ProxyScheme};
 
 pub(crate) type HttpConnector = hyper::client::HttpConnector<DynResolver>;
 
+// Without TLS, no connection has any protocol negotiated.
+#[cfg(not(feature = "boring-tls"))]
+type ProtocolInfo = std::convert::Infallible;
+
+// Without TLS, no alternative service is connected to.
+#[cfg(not(feature = "boring-tls"))]
+type AltService = std::convert::Infallible;
+
 #[derive(Clone)]
 pub(crate) struct Connector {
     inner: Inner,
     proxies: Arc<Vec<Proxy>>,
     verbose: verbose::Wrapper,
     timeout: Option<Duration>,
+    /// Proxy Protocol header sent on direct connections.
+    proxy_protocol: Option<Arc<[u8]>>,
+    /// Whether connections tunneled through a proxy may be reused.
+    reuse_proxy_tunnels: bool,
+    /// Connect to this address instead of the destination, directly.
+    connect_to: Option<SocketAddr>,
+    /// Whether HTTP/1 requests are sent with an absolute-form target,
+    /// instead of only through HTTP proxies.
+    absolute_form: Option<bool>,
+    h2_stats: Arc<H2Registry>,
+    conn_info: Arc<ConnRegistry>,
+    /// How long HTTP/2 connections wait for their `SETTINGS` to be
+    /// acknowledged.
+    settings_ack_timeout: Option<Duration>,
+    limits: Arc<ConnLimits>,
+    #[cfg(feature = "chaos")]
+    chaos: Option<crate::testing::chaos::ChaosLayer>,
     #[cfg(feature = "boring-tls")]
     nodelay: bool,
     #[cfg(feature = "boring-tls")]
     tls_info: bool,
     #[cfg(feature = "boring-tls")]
+    protocol_info: bool,
+    /// Overrides the minimum TLS version of the TLS settings.
+    #[cfg(feature = "boring-tls")]
+    min_tls_version: Option<crate::tls::Version>,
+    #[cfg(feature = "boring-tls")]
     user_agent: Option<HeaderValue>,
+    #[cfg(feature = "boring-tls")]
+    version_cache: Option<Arc<AdaptiveVersionCache>>,
+    /// The alternative services connected to instead of their origins,
+    /// with the protocols of those that can be.
+    #[cfg(feature = "boring-tls")]
+    alt_svc: Option<(AltSvcCache, &'static [&'static str])>,
+}
+
+/// What a request overrides of the way the client connects, see
+/// `RequestBuilder::connect_to`, `RequestBuilder::min_tls_version` and
+/// `RequestBuilder::request_target`.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub(crate) struct ConnectOverrides {
+    /// Connect to this address instead of the destination, directly.
+    pub(crate) addr: Option<SocketAddr>,
+    #[cfg(feature = "boring-tls")]
+    pub(crate) min_tls_version: Option<crate::tls::Version>,
+    /// Whether HTTP/1 requests are sent with an absolute-form target,
+    /// instead of only through HTTP proxies.
+    pub(crate) absolute_form: Option<bool>,
+}
+
+impl ConnectOverrides {
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == ConnectOverrides::default()
+    }
 }
 
 #[derive(Clone)]
@@ -46,7 +111,7 @@ enum Inner {
     #[cfg(feature = "boring-tls")]
     BoringTls {
         http: HttpConnector,
-        tls: BoringTlsConnector,
+        tls: MaybeLazyTlsConnector,
     },
 }
 
@@ -78,13 +143,23 @@ impl Connector {
             proxies,
             verbose: verbose::OFF,
             timeout: None,
+            proxy_protocol: None,
+            h2_stats: Arc::default(),
+            conn_info: Arc::default(),
+            settings_ack_timeout: None,
+            limits: Arc::default(),
+            reuse_proxy_tunnels: true,
+            connect_to: None,
+            absolute_form: None,
+            #[cfg(feature = "chaos")]
+            chaos: None,
         }
     }
 
     #[cfg(feature = "boring-tls")]
     pub(crate) fn new_boring_tls(
         mut http: HttpConnector,
-        tls: BoringTlsConnector,
+        tls: MaybeLazyTlsConnector,
         proxies: Arc<Vec<Proxy>>,
         user_agent: Option<HeaderValue>,
         local_addr_v4: Option<Ipv4Addr>,
@@ -111,9 +186,23 @@ impl Connector {
             proxies,
             verbose: verbose::OFF,
             timeout: None,
+            proxy_protocol: None,
+            h2_stats: Arc::default(),
+            conn_info: Arc::default(),
+            settings_ack_timeout: None,
+            limits: Arc::default(),
+            reuse_proxy_tunnels: true,
+            connect_to: None,
+            absolute_form: None,
+            #[cfg(feature = "chaos")]
+            chaos: None,
             nodelay,
             user_agent,
             tls_info,
+            protocol_info: false,
+            min_tls_version: None,
+            version_cache: None,
+            alt_svc: None,
         }
     }
 
@@ -134,12 +223,136 @@ impl Connector {
         self.verbose.0 = enabled;
     }
 
+    pub(crate) fn set_proxy_protocol(&mut self, addrs: Option<(SocketAddr, SocketAddr)>) {
+        self.proxy_protocol = addrs
+            .map(|(source, destination)| proxy_protocol::encode_v2(source, destination).into());
+    }
+
+    pub(crate) fn set_reuse_proxy_tunnels(&mut self, enabled: bool) {
+        self.reuse_proxy_tunnels = enabled;
+    }
+
+    /// A connector opening its connections as `overrides` asks, for the
+    /// requests overriding how the client connects.
+    pub(crate) fn with_overrides(&self, overrides: ConnectOverrides) -> Connector {
+        Connector {
+            connect_to: overrides.addr,
+            #[cfg(feature = "boring-tls")]
+            min_tls_version: overrides.min_tls_version,
+            absolute_form: overrides.absolute_form,
+            ..self.clone()
+        }
+    }
+
+    #[cfg(feature = "chaos")]
+    pub(crate) fn set_chaos(&mut self, chaos: Option<crate::testing::chaos::ChaosLayer>) {
+        self.chaos = chaos;
+    }
+
+    #[cfg(feature = "boring-tls")]
+    pub(crate) fn session_cache_stats(&self) -> SessionCacheStats {
+        match &self.inner {
+            Inner::BoringTls { tls, .. } => tls.session_cache_stats(),
+        }
+    }
+
+    #[cfg(feature = "boring-tls")]
+    pub(crate) fn set_protocol_info(&mut self, enabled: bool) {
+        self.protocol_info = enabled;
+    }
+
+    #[cfg(feature = "boring-tls")]
+    pub(crate) fn set_version_cache(&mut self, cache: Option<Arc<AdaptiveVersionCache>>) {
+        self.version_cache = cache;
+    }
+
+    #[cfg(feature = "boring-tls")]
+    pub(crate) fn set_alt_svc(
+        &mut self,
+        cache: Option<AltSvcCache>,
+        protocols: &'static [&'static str],
+    ) {
+        self.alt_svc = cache.map(|cache| (cache, protocols));
+    }
+
+    #[cfg(feature = "boring-tls")]
+    pub(crate) fn http_version_cache_stats(&self) -> HttpVersionCacheStats {
+        self.version_cache
+            .as_ref()
+            .map(|cache| cache.stats())
+            .unwrap_or_default()
+    }
+
+    /// Whether to connect to `dst` offering only HTTP/1.1 through ALPN.
+    #[cfg(feature = "boring-tls")]
+    fn http1_alpn(&self, dst: &Uri, ws: bool) -> bool {
+        ws || (dst.scheme() == Some(&Scheme::HTTPS)
+            && self
+                .version_cache
+                .as_ref()
+                .map_or(false, |cache| cache.prefers_http1(dst)))
+    }
+
+    /// Learn from the protocol a connection offering `h2` negotiated.
+    #[cfg(feature = "boring-tls")]
+    fn record_alpn(&self, dst: &Uri, ssl: &boring::ssl::SslRef, http1_alpn: bool) {
+        if let Some(cache) = self.version_cache.as_ref().filter(|_| !http1_alpn) {
+            cache.record(dst, ssl);
+        }
+    }
+
+    /// What the TLS connection `ssl` negotiated through ALPN, when it is
+    /// added to the responses.
+    #[cfg(feature = "boring-tls")]
+    fn protocol_info(
+        &self,
+        ssl: &boring::ssl::SslRef,
+        http1_alpn: bool,
+        via_proxy_tunnel: bool,
+    ) -> Option<ProtocolInfo> {
+        if !self.protocol_info && !self.tls_info {
+            return None;
+        }
+        let Inner::BoringTls { tls, .. } = &self.inner;
+        Some(ProtocolInfo {
+            alpn_offered: tls.alpn_protos(http1_alpn),
+            alpn_selected: ssl
+                .selected_alpn_protocol()
+                .map(|proto| String::from_utf8_lossy(proto).into_owned()),
+            via_proxy_tunnel,
+        })
+    }
+
+    pub(crate) fn h2_connection_stats(&self, origin: &str) -> Option<H2ConnectionStats> {
+        self.h2_stats.stats(origin)
+    }
+
+    pub(crate) fn h2_origin_set(&self, origin: &str) -> Option<Vec<String>> {
+        self.h2_stats.origins(origin)
+    }
+
+    pub(crate) fn connection_info(&self, url: &str) -> Option<ConnectionInfo> {
+        self.conn_info.info(url)
+    }
+
+    pub(crate) fn set_limits(&mut self, limits: ConnLimits) {
+        self.limits = Arc::new(limits);
+    }
+
+    pub(crate) fn set_settings_ack_timeout(&mut self, timeout: Option<Duration>) {
+        self.settings_ack_timeout = timeout;
+    }
+
+    pub(crate) fn pool_stats(&self) -> PoolStats {
+        self.limits.stats()
+    }
+
     pub(crate) fn get_proxies(&self) -> Arc<Vec<Proxy>> {
         self.proxies.clone()
     }
 
     pub(crate) fn set_proxies(&mut self, proxies: &[Proxy]) {
-        Arc::make_mut(&mut self.proxies).clone_from_slice(proxies);
+        self.proxies = Arc::new(proxies.to_vec());
     }
 
     pub(crate) fn set_local_address(&mut self, addr: Option<IpAddr>) {
@@ -193,15 +406,28 @@ impl Connector {
                 if dst.scheme() == Some(&Scheme::HTTPS) {
                     let host = dst.host().ok_or("no host in url")?;
                     let conn = socks::connect(proxy, dst.clone(), dns).await?;
-                    let connector = tls.create_connector(http.clone(), ws).await;
+                    metrics::mark(Phase::Connected);
+                    let http1_alpn = self.http1_alpn(&dst, ws);
+                    let connector = tls
+                        .create_connector(http.clone(), http1_alpn, self.min_tls_version)
+                        .await?;
                     let setup_ssl = connector.setup_ssl(&dst, host)?;
                     let io = tokio_boring::SslStreamBuilder::new(setup_ssl, conn)
                         .connect()
                         .await?;
+                    metrics::mark(Phase::Handshaken);
+                    self.record_alpn(&dst, io.ssl(), http1_alpn);
+                    let protocol_info = self.protocol_info(io.ssl(), http1_alpn, true);
                     return Ok(Conn {
                         inner: self.verbose.wrap(BoringTlsConn { inner: io }),
                         is_proxy: false,
                         tls_info: self.tls_info,
+                        metrics: None,
+                        h2_stats: None,
+                        info: None,
+                        permit: None,
+                        poison: !self.reuse_proxy_tunnels,
+                        protocol_info,
                     });
                 }
             }
@@ -209,10 +435,18 @@ impl Connector {
             Inner::Http(_) => (),
         }
 
-        socks::connect(proxy, dst, dns).await.map(|tcp| Conn {
+        let tcp = socks::connect(proxy, dst, dns).await?;
+        metrics::mark(Phase::Connected);
+        Ok(Conn {
             inner: self.verbose.wrap(tcp),
             is_proxy: false,
             tls_info: false,
+            metrics: None,
+            h2_stats: None,
+            info: None,
+            permit: None,
+            poison: false,
+            protocol_info: None,
         })
     }
 
@@ -222,14 +456,26 @@ impl Connector {
         is_proxy: bool,
     ) -> Result<Conn, BoxError> {
         let ws = maybe_websocket_uri(&mut dst);
+
+        if let Some(header) = self.proxy_protocol.clone().filter(|_| !is_proxy) {
+            return self.connect_with_proxy_protocol(dst, ws, &header).await;
+        }
+
         match self.inner {
             #[cfg(not(feature = "boring-tls"))]
             Inner::Http(mut http) => {
                 let io = http.call(dst).await?;
+                metrics::mark(Phase::Connected);
                 Ok(Conn {
                     inner: self.verbose.wrap(io),
                     is_proxy,
                     tls_info: false,
+                    metrics: None,
+                    h2_stats: None,
+                    info: None,
+                    permit: None,
+                    poison: false,
+                    protocol_info: None,
                 })
             }
             #[cfg(feature = "boring-tls")]
@@ -243,10 +489,15 @@ impl Connector {
                     http.set_nodelay(true);
                 }
 
-                let mut http = tls.create_connector(http, ws).await;
-                let io = http.call(dst).await?;
+                let http1_alpn = self.http1_alpn(&dst, ws);
+                let mut http = tls
+                    .create_connector(http, http1_alpn, self.min_tls_version)
+                    .await?;
+                let io = http.call(dst.clone()).await?;
 
                 if let MaybeHttpsStream::Https(stream) = io {
+                    self.record_alpn(&dst, stream.ssl(), http1_alpn);
+                    let protocol_info = self.protocol_info(stream.ssl(), http1_alpn, false);
                     if !self.nodelay {
                         let stream_ref = stream.get_ref();
                         stream_ref.set_nodelay(false)?;
@@ -255,18 +506,270 @@ impl Connector {
                         inner: self.verbose.wrap(BoringTlsConn { inner: stream }),
                         is_proxy,
                         tls_info: self.tls_info,
+                        metrics: None,
+                        h2_stats: None,
+                        info: None,
+                        permit: None,
+                        poison: false,
+                        protocol_info,
                     })
                 } else {
                     Ok(Conn {
                         inner: self.verbose.wrap(io),
                         is_proxy,
                         tls_info: self.tls_info,
+                        metrics: None,
+                        h2_stats: None,
+                        info: None,
+                        permit: None,
+                        poison: false,
+                        protocol_info: None,
                     })
                 }
             }
         }
     }
 
+    /// Connect directly, sending the Proxy Protocol `header` before the
+    /// TLS handshake.
+    #[cfg_attr(not(feature = "boring-tls"), allow(unused_variables))]
+    async fn connect_with_proxy_protocol(
+        self,
+        dst: Uri,
+        ws: bool,
+        header: &[u8],
+    ) -> Result<Conn, BoxError> {
+        use tokio::io::AsyncWriteExt;
+
+        match &self.inner {
+            #[cfg(not(feature = "boring-tls"))]
+            Inner::Http(http) => {
+                let mut io = http.clone().call(dst).await?;
+                metrics::mark(Phase::Connected);
+                io.write_all(header).await?;
+                Ok(Conn {
+                    inner: self.verbose.wrap(io),
+                    is_proxy: false,
+                    tls_info: false,
+                    metrics: None,
+                    h2_stats: None,
+                    info: None,
+                    permit: None,
+                    poison: false,
+                    protocol_info: None,
+                })
+            }
+            #[cfg(feature = "boring-tls")]
+            Inner::BoringTls { http, tls } => {
+                let is_https = dst.scheme() == Some(&Scheme::HTTPS);
+                let mut http = http.clone();
+
+                // Disable Nagle's algorithm for TLS handshake
+                if !self.nodelay && is_https {
+                    http.set_nodelay(true);
+                }
+
+                let mut io = http.call(dst.clone()).await?;
+                metrics::mark(Phase::Connected);
+                io.write_all(header).await?;
+
+                if !is_https {
+                    return Ok(Conn {
+                        inner: self.verbose.wrap(io),
+                        is_proxy: false,
+                        tls_info: self.tls_info,
+                        metrics: None,
+                        h2_stats: None,
+                        info: None,
+                        permit: None,
+                        poison: false,
+                        protocol_info: None,
+                    });
+                }
+
+                let host = dst.host().ok_or("no host in url")?;
+                let http1_alpn = self.http1_alpn(&dst, ws);
+                let connector = tls
+                    .create_connector(http, http1_alpn, self.min_tls_version)
+                    .await?;
+                let ssl = connector.setup_ssl(&dst, host)?;
+                let io = tokio_boring::SslStreamBuilder::new(ssl, io)
+                    .connect()
+                    .await?;
+                metrics::mark(Phase::Handshaken);
+                self.record_alpn(&dst, io.ssl(), http1_alpn);
+                let protocol_info = self.protocol_info(io.ssl(), http1_alpn, false);
+
+                if !self.nodelay {
+                    io.get_ref().set_nodelay(false)?;
+                }
+
+                Ok(Conn {
+                    inner: self.verbose.wrap(BoringTlsConn { inner: io }),
+                    is_proxy: false,
+                    tls_info: self.tls_info,
+                    metrics: None,
+                    h2_stats: None,
+                    info: None,
+                    permit: None,
+                    poison: false,
+                    protocol_info,
+                })
+            }
+        }
+    }
+
+    /// Connects to `addr` directly, still using the host of `dst` for TLS.
+    async fn connect_to_addr(self, dst: Uri, addr: SocketAddr) -> Result<Conn, BoxError> {
+        let addr = addr.to_string().parse::<Authority>()?;
+        self.connect_to_authority(dst, addr, false).await
+    }
+
+    /// The alternative service to connect to instead of `dst`, if any.
+    #[cfg(feature = "boring-tls")]
+    fn alternative(&self, dst: &Uri) -> Option<AltService> {
+        let (cache, protocols) = self.alt_svc.as_ref()?;
+        if dst.scheme() != Some(&Scheme::HTTPS)
+            || self.proxies.iter().any(|p| p.intercept(dst).is_some())
+        {
+            return None;
+        }
+        cache.alternative(dst, protocols)
+    }
+
+    /// Connects to the alternative service `alt` of `dst`, or to `dst`
+    /// itself once connecting to `alt` failed.
+    #[cfg(feature = "boring-tls")]
+    async fn connect_to_alternative(self, dst: Uri, alt: AltService) -> Result<Conn, BoxError> {
+        // Marks `alt` broken unless it was connected to, also when the
+        // connect timeout ends the attempt.
+        struct Attempt<'a> {
+            connector: &'a Connector,
+            dst: &'a Uri,
+            alt: &'a AltService,
+            connected: bool,
+        }
+
+        impl Drop for Attempt<'_> {
+            fn drop(&mut self) {
+                if let Some((cache, _)) =
+                    self.connector.alt_svc.as_ref().filter(|_| !self.connected)
+                {
+                    cache.mark_broken(self.dst, self.alt);
+                }
+            }
+        }
+
+        let authority = format!("{}:{}", alt.host(), alt.port()).parse::<Authority>()?;
+        let http1_only = alt.protocol() == "http/1.1";
+        let mut attempt = Attempt {
+            connector: &self,
+            dst: &dst,
+            alt: &alt,
+            connected: false,
+        };
+        match self
+            .clone()
+            .connect_to_authority(dst.clone(), authority, http1_only)
+            .await
+        {
+            Ok(conn) => {
+                attempt.connected = true;
+                Ok(conn)
+            }
+            Err(e) => {
+                log::debug!("alternative service of {:?} failed: {}", dst, e);
+                drop(attempt);
+                self.connect_with_maybe_proxy(dst, false).await
+            }
+        }
+    }
+
+    #[cfg(not(feature = "boring-tls"))]
+    fn alternative(&self, _dst: &Uri) -> Option<AltService> {
+        None
+    }
+
+    #[cfg(not(feature = "boring-tls"))]
+    async fn connect_to_alternative(self, _dst: Uri, alt: AltService) -> Result<Conn, BoxError> {
+        match alt {}
+    }
+
+    /// Connects to `authority` directly, still using the host of `dst` for
+    /// TLS, offering only HTTP/1.1 through ALPN if `http1_only`.
+    async fn connect_to_authority(
+        self,
+        mut dst: Uri,
+        authority: Authority,
+        #[cfg_attr(not(feature = "boring-tls"), allow(unused_variables))] http1_only: bool,
+    ) -> Result<Conn, BoxError> {
+        let ws = maybe_websocket_uri(&mut dst);
+        let tcp_dst = into_uri(Scheme::HTTP, authority);
+
+        match &self.inner {
+            #[cfg(not(feature = "boring-tls"))]
+            Inner::Http(http) => {
+                let io = http.clone().call(tcp_dst).await?;
+                metrics::mark(Phase::Connected);
+                Ok(Conn {
+                    inner: self.verbose.wrap(io),
+                    is_proxy: false,
+                    tls_info: false,
+                    metrics: None,
+                    h2_stats: None,
+                    info: None,
+                    permit: None,
+                    poison: false,
+                    protocol_info: None,
+                })
+            }
+            #[cfg(feature = "boring-tls")]
+            Inner::BoringTls { http, tls } => {
+                let http1_alpn = http1_only || self.http1_alpn(&dst, ws);
+                let mut http = tls
+                    .create_connector(http.clone(), http1_alpn, self.min_tls_version)
+                    .await?;
+                let io = http.call(tcp_dst).await?;
+                metrics::mark(Phase::Connected);
+
+                if dst.scheme() != Some(&Scheme::HTTPS) {
+                    return Ok(Conn {
+                        inner: self.verbose.wrap(io),
+                        is_proxy: false,
+                        tls_info: self.tls_info,
+                        metrics: None,
+                        h2_stats: None,
+                        info: None,
+                        permit: None,
+                        poison: false,
+                        protocol_info: None,
+                    });
+                }
+
+                let host = dst.host().ok_or("no host in url")?;
+                let ssl = http.setup_ssl(&dst, host)?;
+                let io = tokio_boring::SslStreamBuilder::new(ssl, io)
+                    .connect()
+                    .await?;
+                metrics::mark(Phase::Handshaken);
+                self.record_alpn(&dst, io.ssl(), http1_alpn);
+                let protocol_info = self.protocol_info(io.ssl(), http1_alpn, false);
+
+                Ok(Conn {
+                    inner: self.verbose.wrap(BoringTlsConn { inner: io }),
+                    is_proxy: false,
+                    tls_info: self.tls_info,
+                    metrics: None,
+                    h2_stats: None,
+                    info: None,
+                    permit: None,
+                    poison: false,
+                    protocol_info,
+                })
+            }
+        }
+    }
+
     async fn connect_via_proxy(
         self,
         mut dst: Uri,
@@ -274,9 +777,18 @@ impl Connector {
     ) -> Result<Conn, BoxError> {
         log::debug!("proxy({:?}) intercepts '{:?}'", proxy_scheme, dst);
 
+        #[cfg(feature = "ntlm")]
+        let ntlm = match proxy_scheme {
+            ProxyScheme::Http { ref ntlm, .. } | ProxyScheme::Https { ref ntlm, .. } => {
+                ntlm.clone()
+            }
+            #[cfg(feature = "socks")]
+            _ => None,
+        };
+
         let (proxy_dst, _auth) = match proxy_scheme {
-            ProxyScheme::Http { host, auth } => (into_uri(Scheme::HTTP, host), auth),
-            ProxyScheme::Https { host, auth } => (into_uri(Scheme::HTTPS, host), auth),
+            ProxyScheme::Http { host, auth, .. } => (into_uri(Scheme::HTTP, host), auth),
+            ProxyScheme::Https { host, auth, .. } => (into_uri(Scheme::HTTPS, host), auth),
             #[cfg(feature = "socks")]
             ProxyScheme::Socks4 { .. } => return self.connect_socks(dst, proxy_scheme).await,
             #[cfg(feature = "socks")]
@@ -295,20 +807,42 @@ impl Connector {
                     let host = dst.host().ok_or("no host in url")?;
                     let port = dst.port().map(|p| p.as_u16()).unwrap_or(443);
 
-                    let mut http = tls.create_connector(http.clone(), ws).await;
-                    let conn = http.call(proxy_dst).await?;
+                    let http1_alpn = self.http1_alpn(&dst, ws);
+                    let mut http = tls
+                        .create_connector(http.clone(), http1_alpn, self.min_tls_version)
+                        .await?;
+                    let conn = http.call(proxy_dst).await.map_err(crate::error::proxy)?;
                     log::trace!("tunneling HTTPS over proxy");
-                    let tunneled = tunnel(conn, host, port, self.user_agent.as_ref(), auth).await?;
+                    #[cfg(feature = "ntlm")]
+                    let tunneled = match ntlm {
+                        Some(ref credentials) => {
+                            tunnel_ntlm(conn, host, port, self.user_agent.as_ref(), credentials)
+                                .await
+                        }
+                        None => tunnel(conn, host, port, self.user_agent.as_ref(), auth).await,
+                    };
+                    #[cfg(not(feature = "ntlm"))]
+                    let tunneled = tunnel(conn, host, port, self.user_agent.as_ref(), auth).await;
+                    let tunneled = tunneled.map_err(crate::error::proxy)?;
 
                     let ssl = http.setup_ssl(&dst, host)?;
                     let io = tokio_boring::SslStreamBuilder::new(ssl, tunneled)
                         .connect()
                         .await?;
+                    metrics::mark(Phase::Handshaken);
+                    self.record_alpn(&dst, io.ssl(), http1_alpn);
+                    let protocol_info = self.protocol_info(io.ssl(), http1_alpn, true);
 
                     return Ok(Conn {
                         inner: self.verbose.wrap(BoringTlsConn { inner: io }),
                         is_proxy: false,
                         tls_info: self.tls_info,
+                        metrics: None,
+                        h2_stats: None,
+                        info: None,
+                        permit: None,
+                        poison: false,
+                        protocol_info,
                     });
                 }
             }
@@ -316,7 +850,9 @@ impl Connector {
             Inner::Http(_) => (),
         }
 
-        self.connect_with_maybe_proxy(proxy_dst, true).await
+        self.connect_with_maybe_proxy(proxy_dst, true)
+            .await
+            .map_err(crate::error::proxy)
     }
 }
 
@@ -365,13 +901,30 @@ fn into_uri(scheme: Scheme, host: Authority) -> Uri {
         .expect("scheme and authority is valid Uri")
 }
 
-async fn with_timeout<T, F>(f: F, timeout: Option<Duration>) -> Result<T, BoxError>
+/// Runs `f` under the connect `timeout`, reporting the phase `timer` saw
+/// the connection to `dst` reach if it elapses. `resolving` is whether the
+/// host of `dst` is resolved to connect to it.
+async fn with_timeout<T, F>(
+    f: F,
+    timeout: Option<Duration>,
+    timer: Arc<ConnectTimer>,
+    dst: Uri,
+    resolving: bool,
+) -> Result<T, BoxError>
 where
     F: Future<Output = Result<T, BoxError>>,
 {
     if let Some(to) = timeout {
         match tokio::time::timeout(to, f).await {
-            Err(_elapsed) => Err(Box::new(crate::error::TimedOut) as BoxError),
+            Err(_elapsed) => {
+                let resolving = resolving
+                    && dst.host().map_or(false, |host| {
+                        host.trim_matches(['[', ']']).parse::<IpAddr>().is_err()
+                    });
+                let tls = dst.scheme() == Some(&Scheme::HTTPS);
+                let phase = timer.timeout_phase(resolving, tls);
+                Err(Box::new(TimedOut::new(phase, to)) as BoxError)
+            }
             Ok(Ok(try_res)) => Ok(try_res),
             Ok(Err(e)) => Err(e),
         }
@@ -390,49 +943,126 @@ impl Service<Uri> for Connector {
     }
 
     fn call(&mut self, dst: Uri) -> Self::Future {
+        // Waiting for the limits is neither part of the connect timeout nor
+        // of the connect metrics.
+        let mut connector = self.clone();
+        Box::pin(async move {
+            let permit = connector.limits.acquire(&dst).await?;
+            #[cfg(feature = "tracing")]
+            let span = tracing::debug_span!(
+                target: "rquest",
+                "rquest.connect",
+                net.host = dst.host().unwrap_or(""),
+                net.port = dst.port_u16(),
+            );
+            let connecting = connector.connect(dst);
+            #[cfg(feature = "tracing")]
+            let connecting = tracing::Instrument::instrument(connecting, span);
+            let mut conn = connecting.await?;
+            conn.permit = Some(permit);
+            Ok(conn)
+        })
+    }
+}
+
+impl Connector {
+    fn connect(&mut self, dst: Uri) -> Connecting {
         log::debug!("starting new connection: {:?}", dst);
+        let h2_stats = H2Tracker::new(&self.h2_stats, &dst, self.settings_ack_timeout);
+        let (conn_info, conn_dst) = (self.conn_info.clone(), dst.clone());
+        let absolute_form = self.absolute_form;
         let timeout = self.timeout;
-        for prox in self.proxies.iter() {
-            if let Some(proxy_scheme) = prox.intercept(&dst) {
-                return Box::pin(with_timeout(
-                    self.clone().connect_via_proxy(dst, proxy_scheme),
+        // Timed for the request metrics, and for the phase a connect or a
+        // request timeout elapsed in.
+        let timer = ConnectTimer::new();
+        let connecting: Connecting = if let Some(addr) = self.connect_to {
+            Box::pin(with_timeout(
+                self.clone().connect_to_addr(dst.clone(), addr),
+                timeout,
+                timer.clone(),
+                dst,
+                false,
+            ))
+        } else if let Some(alt) = self.alternative(&dst) {
+            Box::pin(with_timeout(
+                self.clone().connect_to_alternative(dst.clone(), alt),
+                timeout,
+                timer.clone(),
+                dst,
+                true,
+            ))
+        } else {
+            match self.proxies.iter().find_map(|p| p.intercept(&dst)) {
+                Some(proxy_scheme) => Box::pin(with_timeout(
+                    self.clone().connect_via_proxy(dst.clone(), proxy_scheme),
+                    timeout,
+                    timer.clone(),
+                    dst,
+                    true,
+                )),
+                None => Box::pin(with_timeout(
+                    self.clone().connect_with_maybe_proxy(dst.clone(), false),
                     timeout,
-                ));
+                    timer.clone(),
+                    dst,
+                    true,
+                )),
             }
-        }
+        };
+
+        #[cfg(feature = "chaos")]
+        let connecting: Connecting = match self.chaos.clone() {
+            Some(chaos) => Box::pin(async move {
+                let conn = chaos.connect(connecting).await?;
+                Ok(Conn {
+                    is_proxy: conn.get_ref().is_proxy,
+                    // the wrapped `Conn` already reports its TLS info
+                    tls_info: false,
+                    metrics: None,
+                    h2_stats: None,
+                    info: None,
+                    permit: None,
+                    poison: false,
+                    protocol_info: None,
+                    inner: Box::new(conn),
+                })
+            }),
+            None => connecting,
+        };
 
-        Box::pin(with_timeout(
-            self.clone().connect_with_maybe_proxy(dst, false),
-            timeout,
-        ))
+        let connecting = timer.time(connecting);
+        Box::pin(async move {
+            let mut conn = connecting.await?;
+            conn.metrics = Some(timer.finish());
+            conn.info = Some(conn_info.register(&conn_dst, conn.details(), h2_stats.handle()));
+            conn.h2_stats = Some(h2_stats);
+            conn.is_proxy = absolute_form.unwrap_or(conn.is_proxy);
+            Ok(conn)
+        })
     }
 }
 
 #[cfg(feature = "boring-tls")]
 trait TlsInfoFactory {
-    fn tls_info(&self) -> Option<crate::tls::TlsInfo>;
+    fn ssl(&self) -> Option<&boring::ssl::SslRef>;
+
+    fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
+        self.ssl().map(crate::tls::TlsInfo::new)
+    }
 }
 
 #[cfg(feature = "boring-tls")]
 impl TlsInfoFactory for BoringTlsConn<tokio::net::TcpStream> {
-    fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
-        let peer_certificate = self
-            .inner
-            .ssl()
-            .peer_certificate()
-            .and_then(|c| c.to_der().ok());
-        Some(crate::tls::TlsInfo { peer_certificate })
+    fn ssl(&self) -> Option<&boring::ssl::SslRef> {
+        Some(self.inner.ssl())
     }
 }
 
 #[cfg(feature = "boring-tls")]
 impl TlsInfoFactory for MaybeHttpsStream<tokio::net::TcpStream> {
-    fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
+    fn ssl(&self) -> Option<&boring::ssl::SslRef> {
         match self {
-            MaybeHttpsStream::Https(tls) => {
-                let peer_certificate = tls.ssl().peer_certificate().and_then(|c| c.to_der().ok());
-                Some(crate::tls::TlsInfo { peer_certificate })
-            }
+            MaybeHttpsStream::Https(tls) => Some(tls.ssl()),
             MaybeHttpsStream::Http(_) => None,
         }
     }
@@ -440,12 +1070,9 @@ impl TlsInfoFactory for MaybeHttpsStream<tokio::net::TcpStream> {
 
 #[cfg(feature = "boring-tls")]
 impl TlsInfoFactory for BoringTlsConn<MaybeHttpsStream<tokio::net::TcpStream>> {
-    fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
+    fn ssl(&self) -> Option<&boring::ssl::SslRef> {
         match self.inner.get_ref() {
-            MaybeHttpsStream::Https(ref tls) => {
-                let peer_certificate = tls.ssl().peer_certificate().and_then(|c| c.to_der().ok());
-                Some(crate::tls::TlsInfo { peer_certificate })
-            }
+            MaybeHttpsStream::Https(ref tls) => Some(tls.ssl()),
             MaybeHttpsStream::Http(_) => None,
         }
     }
@@ -453,11 +1080,54 @@ impl TlsInfoFactory for BoringTlsConn<MaybeHttpsStream<tokio::net::TcpStream>> {
 
 #[cfg(feature = "boring-tls")]
 impl TlsInfoFactory for tokio::net::TcpStream {
-    fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
+    fn ssl(&self) -> Option<&boring::ssl::SslRef> {
         None
     }
 }
 
+#[cfg(all(feature = "boring-tls", feature = "chaos"))]
+impl TlsInfoFactory for crate::testing::chaos::ChaosStream<Conn> {
+    fn ssl(&self) -> Option<&boring::ssl::SslRef> {
+        self.get_ref().inner.ssl()
+    }
+}
+
+/// The address a connection is connected to, for
+/// [`ConnectionInfo`](crate::ConnectionInfo).
+trait PeerAddr {
+    fn peer_addr(&self) -> Option<SocketAddr>;
+}
+
+impl PeerAddr for tokio::net::TcpStream {
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        tokio::net::TcpStream::peer_addr(self).ok()
+    }
+}
+
+#[cfg(feature = "boring-tls")]
+impl PeerAddr for MaybeHttpsStream<tokio::net::TcpStream> {
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        match self {
+            MaybeHttpsStream::Https(tls) => tls.get_ref().peer_addr().ok(),
+            MaybeHttpsStream::Http(tcp) => tcp.peer_addr().ok(),
+        }
+    }
+}
+
+#[cfg(feature = "boring-tls")]
+impl<T: PeerAddr> PeerAddr for BoringTlsConn<T> {
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.inner.get_ref().peer_addr()
+    }
+}
+
+#[cfg(feature = "chaos")]
+impl PeerAddr for crate::testing::chaos::ChaosStream<Conn> {
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.get_ref().inner.peer_addr()
+    }
+}
+
 pub(crate) trait AsyncConn:
     AsyncRead + AsyncWrite + Connection + Send + Sync + Unpin + 'static
 {
@@ -466,14 +1136,14 @@ pub(crate) trait AsyncConn:
 impl<T: AsyncRead + AsyncWrite + Connection + Send + Sync + Unpin + 'static> AsyncConn for T {}
 
 #[cfg(feature = "boring-tls")]
-trait AsyncConnWithInfo: AsyncConn + TlsInfoFactory {}
+trait AsyncConnWithInfo: AsyncConn + TlsInfoFactory + PeerAddr {}
 #[cfg(not(feature = "boring-tls"))]
-trait AsyncConnWithInfo: AsyncConn {}
+trait AsyncConnWithInfo: AsyncConn + PeerAddr {}
 
 #[cfg(feature = "boring-tls")]
-impl<T: AsyncConn + TlsInfoFactory> AsyncConnWithInfo for T {}
+impl<T: AsyncConn + TlsInfoFactory + PeerAddr> AsyncConnWithInfo for T {}
 #[cfg(not(feature = "boring-tls"))]
-impl<T: AsyncConn> AsyncConnWithInfo for T {}
+impl<T: AsyncConn + PeerAddr> AsyncConnWithInfo for T {}
 
 type BoxConn = Box<dyn AsyncConnWithInfo>;
 
@@ -482,29 +1152,63 @@ pin_project! {
     /// This tells hyper whether the URI should be written in
     /// * origin-form (`GET /just/a/path HTTP/1.1`), when `is_proxy == false`, or
     /// * absolute-form (`GET http://foo.bar/and/a/path HTTP/1.1`), otherwise.
+    /// `RequestBuilder::request_target` overrides it either way.
     pub(crate) struct Conn {
         #[pin]
         inner: BoxConn,
         is_proxy: bool,
         // Only needed for __boring, but #[cfg()] on fields breaks pin_project!
         tls_info: bool,
+        metrics: Option<ConnectMetrics>,
+        h2_stats: Option<H2Tracker>,
+        info: Option<ConnTracker>,
+        // Counts the connection against the limits until it is dropped.
+        permit: Option<ConnPermit>,
+        // Keeps hyper from reusing the connection.
+        poison: bool,
+        // Only ever set with __boring.
+        protocol_info: Option<ProtocolInfo>,
+    }
+}
+
+impl Conn {
+    fn details(&self) -> ConnDetails {
+        #[cfg(feature = "boring-tls")]
+        let ssl = self.inner.ssl();
+        ConnDetails {
+            remote_addr: self.inner.peer_addr(),
+            #[cfg(feature = "boring-tls")]
+            tls_version: ssl.and_then(crate::tls::Version::of),
+            #[cfg(feature = "boring-tls")]
+            cipher_suite: ssl
+                .and_then(|ssl| ssl.current_cipher())
+                .map(|cipher| cipher.name().to_owned()),
+        }
     }
 }
 
 impl Connection for Conn {
     fn connected(&self) -> Connected {
-        let connected = self.inner.connected().proxy(self.is_proxy);
+        let mut connected = self.inner.connected().proxy(self.is_proxy);
+        if self.poison {
+            connected.poison();
+        }
+        if let Some(ref metrics) = self.metrics {
+            connected = connected.extra(metrics.clone());
+        }
+        if let Some(ref h2_stats) = self.h2_stats {
+            connected = connected.extra(h2_stats.handle());
+        }
         #[cfg(feature = "boring-tls")]
         if self.tls_info {
             if let Some(tls_info) = self.inner.tls_info() {
-                connected.extra(tls_info)
-            } else {
-                connected
+                connected = connected.extra(tls_info);
             }
-        } else {
-            connected
         }
-        #[cfg(not(feature = "boring-tls"))]
+        #[cfg(feature = "boring-tls")]
+        if let Some(ref protocol_info) = self.protocol_info {
+            connected = connected.extra(protocol_info.clone());
+        }
         connected
     }
 }
@@ -516,7 +1220,27 @@ impl AsyncRead for Conn {
         buf: &mut ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
         let this = self.project();
-        AsyncRead::poll_read(this.inner, cx, buf)
+        let filled = buf.filled().len();
+        let res = AsyncRead::poll_read(this.inner, cx, buf);
+        if matches!(res, Poll::Ready(Ok(()))) && buf.filled().len() > filled {
+            if let Some(metrics) = this.metrics {
+                metrics.on_read();
+            }
+            if let Some(h2_stats) = this.h2_stats {
+                h2_stats.on_read(&buf.filled()[filled..]);
+            }
+            if let Some(info) = this.info {
+                info.on_active();
+            }
+        }
+        if res.is_pending() {
+            if let Some(h2_stats) = this.h2_stats {
+                if let Poll::Ready(err) = h2_stats.poll_settings_ack(cx) {
+                    return Poll::Ready(Err(err));
+                }
+            }
+        }
+        res
     }
 }
 
@@ -527,7 +1251,19 @@ impl AsyncWrite for Conn {
         buf: &[u8],
     ) -> Poll<Result<usize, io::Error>> {
         let this = self.project();
-        AsyncWrite::poll_write(this.inner, cx, buf)
+        let res = AsyncWrite::poll_write(this.inner, cx, buf);
+        if let Poll::Ready(Ok(n)) = res {
+            if let Some(metrics) = this.metrics.as_ref().filter(|_| n > 0) {
+                metrics.on_write();
+            }
+            if let Some(h2_stats) = this.h2_stats {
+                h2_stats.on_write(&buf[..n]);
+            }
+            if let Some(info) = this.info.as_ref().filter(|_| n > 0) {
+                info.on_active();
+            }
+        }
+        res
     }
 
     fn poll_write_vectored(
@@ -536,7 +1272,27 @@ impl AsyncWrite for Conn {
         bufs: &[IoSlice<'_>],
     ) -> Poll<Result<usize, io::Error>> {
         let this = self.project();
-        AsyncWrite::poll_write_vectored(this.inner, cx, bufs)
+        let res = AsyncWrite::poll_write_vectored(this.inner, cx, bufs);
+        if let Poll::Ready(Ok(n)) = res {
+            if let Some(metrics) = this.metrics.as_ref().filter(|_| n > 0) {
+                metrics.on_write();
+            }
+            if let Some(h2_stats) = this.h2_stats {
+                let mut left = n;
+                for buf in bufs {
+                    if left == 0 {
+                        break;
+                    }
+                    let len = buf.len().min(left);
+                    h2_stats.on_write(&buf[..len]);
+                    left -= len;
+                }
+            }
+            if let Some(info) = this.info.as_ref().filter(|_| n > 0) {
+                info.on_active();
+            }
+        }
+        res
     }
 
     fn is_write_vectored(&self) -> bool {
@@ -567,8 +1323,23 @@ async fn tunnel<T>(
 where
     T: AsyncRead + AsyncWrite + Unpin,
 {
-    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::io::AsyncWriteExt;
 
+    if auth.is_some() {
+        log::debug!("tunnel to {}:{} using basic auth", host, port);
+    }
+    conn.write_all(&connect_request(host, port, user_agent, auth.as_ref()))
+        .await?;
+    read_tunnel_response(conn).await
+}
+
+#[cfg(feature = "boring-tls")]
+fn connect_request(
+    host: &str,
+    port: u16,
+    user_agent: Option<&HeaderValue>,
+    auth: Option<&HeaderValue>,
+) -> Vec<u8> {
     let mut buf = format!(
         "\
          CONNECT {0}:{1} HTTP/1.1\r\n\
@@ -587,7 +1358,6 @@ where
 
     // proxy-authorization
     if let Some(value) = auth {
-        log::debug!("tunnel to {}:{} using basic auth", host, port);
         buf.extend_from_slice(b"Proxy-Authorization: ");
         buf.extend_from_slice(value.as_bytes());
         buf.extend_from_slice(b"\r\n");
@@ -595,8 +1365,15 @@ where
 
     // headers end
     buf.extend_from_slice(b"\r\n");
+    buf
+}
 
-    conn.write_all(&buf).await?;
+#[cfg(feature = "boring-tls")]
+async fn read_tunnel_response<T>(mut conn: T) -> Result<T, BoxError>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    use tokio::io::AsyncReadExt;
 
     let mut buf = [0; 8192];
     let mut pos = 0;
@@ -626,11 +1403,134 @@ where
     }
 }
 
+/// Open a tunnel authenticated with NTLM, answering the challenge of the
+/// proxy on the same connection.
+#[cfg(feature = "ntlm")]
+async fn tunnel_ntlm<T>(
+    mut conn: T,
+    host: &str,
+    port: u16,
+    user_agent: Option<&HeaderValue>,
+    credentials: &crate::ntlm::NtlmCredentials,
+) -> Result<T, BoxError>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    log::debug!("tunnel to {}:{} using ntlm auth", host, port);
+    let negotiate = credentials.negotiate();
+    conn.write_all(&connect_request(host, port, user_agent, Some(&negotiate)))
+        .await?;
+
+    // The head of the response, and the start of its body read with it.
+    let mut buf = Vec::new();
+    let mut chunk = [0; 4096];
+    let head_len = loop {
+        let n = conn.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(tunnel_eof());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+
+        if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break i + 4;
+        }
+        if buf.len() > 8192 {
+            return Err("proxy headers too long for tunnel".into());
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_len]);
+    let mut lines = head.split("\r\n");
+    let status = lines.next().unwrap_or_default();
+    if status.starts_with("HTTP/1.1 200") || status.starts_with("HTTP/1.0 200") {
+        return Ok(conn);
+    } else if !status.starts_with("HTTP/1.1 407") {
+        return Err("unsuccessful tunnel".into());
+    }
+
+    let mut challenges = Vec::new();
+    let mut content_length = 0;
+    for (name, value) in lines.filter_map(|line| line.split_once(':')) {
+        if name.eq_ignore_ascii_case("proxy-authenticate") {
+            challenges.extend(HeaderValue::from_str(value.trim()).ok());
+        } else if name.eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| "invalid proxy content-length")?;
+        }
+    }
+    let auth = credentials
+        .authenticate(&challenges)
+        .ok_or("proxy authentication required")?;
+
+    // Skip the body, the answer goes on the same connection.
+    let mut left = content_length.saturating_sub(buf.len() - head_len);
+    while left > 0 {
+        let n = conn.read(&mut chunk[..left.min(4096)]).await?;
+        if n == 0 {
+            return Err(tunnel_eof());
+        }
+        left -= n;
+    }
+
+    conn.write_all(&connect_request(host, port, user_agent, Some(&auth)))
+        .await?;
+    read_tunnel_response(conn).await
+}
+
 #[cfg(feature = "boring-tls")]
 fn tunnel_eof() -> BoxError {
     "unexpected eof while tunneling".into()
 }
 
+mod proxy_protocol {
+    use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+
+    const SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+    /// Protocol version 2, `PROXY` command.
+    const VERSION_COMMAND: u8 = 0x21;
+    const TCP_OVER_IPV4: u8 = 0x11;
+    const TCP_OVER_IPV6: u8 = 0x21;
+
+    /// Encode the Proxy Protocol v2 header of a TCP connection from `source`
+    /// to `destination`.
+    pub(super) fn encode_v2(source: SocketAddr, destination: SocketAddr) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(16 + 36);
+        buf.extend_from_slice(&SIGNATURE);
+        buf.push(VERSION_COMMAND);
+
+        match (source.ip(), destination.ip()) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                buf.push(TCP_OVER_IPV4);
+                buf.extend_from_slice(&12u16.to_be_bytes());
+                buf.extend_from_slice(&src.octets());
+                buf.extend_from_slice(&dst.octets());
+            }
+            // Mixed families are sent as IPv4-mapped IPv6 addresses.
+            (src, dst) => {
+                buf.push(TCP_OVER_IPV6);
+                buf.extend_from_slice(&36u16.to_be_bytes());
+                buf.extend_from_slice(&to_ipv6(src).octets());
+                buf.extend_from_slice(&to_ipv6(dst).octets());
+            }
+        }
+
+        buf.extend_from_slice(&source.port().to_be_bytes());
+        buf.extend_from_slice(&destination.port().to_be_bytes());
+        buf
+    }
+
+    fn to_ipv6(ip: IpAddr) -> Ipv6Addr {
+        match ip {
+            IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+            IpAddr::V6(ip) => ip,
+        }
+    }
+}
+
 #[cfg(feature = "boring-tls")]
 mod boring_tls_conn {
     use hyper::client::connect::{Connected, Connection};
@@ -721,6 +1621,7 @@ mod socks {
     use tokio_socks::tcp::{Socks4Stream, Socks5Stream};
 
     use super::{BoxError, Scheme};
+    use crate::error::DnsError;
     use crate::proxy::ProxyScheme;
 
     pub(super) enum DnsResolve {
@@ -745,7 +1646,10 @@ mod socks {
         };
 
         if let DnsResolve::Local = dns {
-            let maybe_new_target = (host.as_str(), port).to_socket_addrs()?.next();
+            let maybe_new_target = (host.as_str(), port)
+                .to_socket_addrs()
+                .map_err(|e| -> BoxError { Box::new(DnsError(e.into())) })?
+                .next();
             if let Some(new_target) = maybe_new_target {
                 host = new_target.ip().to_string();
             }
@@ -755,7 +1659,7 @@ mod socks {
             ProxyScheme::Socks4 { addr } => {
                 let stream = Socks4Stream::connect(addr, (host.as_str(), port))
                     .await
-                    .map_err(|e| format!("socks connect error: {e}"))?;
+                    .map_err(crate::error::proxy)?;
                 Ok(stream.into_inner())
             }
             ProxyScheme::Socks5 { addr, ref auth, .. } => {
@@ -767,11 +1671,11 @@ mod socks {
                         &password,
                     )
                     .await
-                    .map_err(|e| format!("socks connect error: {e}"))?
+                    .map_err(crate::error::proxy)?
                 } else {
                     Socks5Stream::connect(addr, (host.as_str(), port))
                         .await
-                        .map_err(|e| format!("socks connect error: {e}"))?
+                        .map_err(crate::error::proxy)?
                 };
 
                 Ok(stream.into_inner())
@@ -893,8 +1797,14 @@ mod verbose {
 
     #[cfg(feature = "boring-tls")]
     impl<T: super::TlsInfoFactory> super::TlsInfoFactory for Verbose<T> {
-        fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
-            self.inner.tls_info()
+        fn ssl(&self) -> Option<&boring::ssl::SslRef> {
+            self.inner.ssl()
+        }
+    }
+
+    impl<T: super::PeerAddr> super::PeerAddr for Verbose<T> {
+        fn peer_addr(&self) -> Option<std::net::SocketAddr> {
+            self.inner.peer_addr()
         }
     }
 
@@ -1002,6 +1912,33 @@ mod tests {
         }};
     }
 
+    #[test]
+    fn proxy_protocol_v2_header() {
+        let header = super::proxy_protocol::encode_v2(
+            "192.0.2.1:56324".parse().unwrap(),
+            "198.51.100.7:443".parse().unwrap(),
+        );
+        assert_eq!(
+            header,
+            b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0c\
+              \xc0\x00\x02\x01\xc6\x33\x64\x07\xdc\x04\x01\xbb"
+        );
+
+        let header = super::proxy_protocol::encode_v2(
+            "192.0.2.1:56324".parse().unwrap(),
+            "[2001:db8::1]:443".parse().unwrap(),
+        );
+        assert_eq!(&header[12..16], b"\x21\x21\x00\x24");
+        assert_eq!(header.len(), 16 + 36);
+        assert_eq!(
+            &header[16..32],
+            &"::ffff:192.0.2.1"
+                .parse::<std::net::Ipv6Addr>()
+                .unwrap()
+                .octets()
+        );
+    }
+
     fn ua() -> Option<http::header::HeaderValue> {
         Some(http::header::HeaderValue::from_static(TUNNEL_UA))
     }
@@ -1112,4 +2049,67 @@ mod tests {
 
         rt.block_on(f).unwrap();
     }
+
+    #[cfg(feature = "ntlm")]
+    #[test]
+    fn test_tunnel_ntlm_auth() {
+        use base64::prelude::{Engine as _, BASE64_STANDARD};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // A challenge without target information.
+        let mut challenge = b"NTLMSSP\0\x02\0\0\0\0\0\0\0\x20\0\0\0".to_vec();
+        challenge.extend_from_slice(&0x0008_8201u32.to_le_bytes());
+        challenge.extend_from_slice(&[7; 8]);
+
+        thread::spawn(move || {
+            let (mut sock, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            let n = sock.read(&mut buf).unwrap();
+            let connect = String::from_utf8_lossy(&buf[..n]).into_owned();
+            assert!(
+                connect.contains("Proxy-Authorization: NTLM TlRMTVNTUAABAAAA"),
+                "{}",
+                connect
+            );
+
+            let response = format!(
+                "\
+                 HTTP/1.1 407 Proxy Authentication Required\r\n\
+                 Proxy-Authenticate: Negotiate\r\n\
+                 Proxy-Authenticate: NTLM {}\r\n\
+                 Content-Length: 4\r\n\
+                 \r\n\
+                 deny\
+                 ",
+                BASE64_STANDARD.encode(&challenge)
+            );
+            sock.write_all(response.as_bytes()).unwrap();
+
+            let n = sock.read(&mut buf).unwrap();
+            let connect = String::from_utf8_lossy(&buf[..n]).into_owned();
+            assert!(
+                connect.contains("Proxy-Authorization: NTLM TlRMTVNTUAADAAAA"),
+                "{}",
+                connect
+            );
+            sock.write_all(TUNNEL_OK).unwrap();
+        });
+
+        let rt = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("new rt");
+        let f = async move {
+            let tcp = TcpStream::connect(&addr).await?;
+            let host = addr.ip().to_string();
+            let port = addr.port();
+            let credentials =
+                crate::ntlm::NtlmCredentials::new("user".into(), "pass".into(), "CORP".into());
+            super::tunnel_ntlm(tcp, &host, port, ua().as_ref(), &credentials).await
+        };
+
+        rt.block_on(f).unwrap();
+    }
 }
@@ -1,13 +1,14 @@
 //! DNS resolution via the [hickory-resolver](https://github.com/hickory-dns/hickory-dns) crate
 
-use crate::error::Kind;
-use crate::Error;
-
 use super::{Addrs, Name, Resolve, Resolving};
+use futures_util::future;
 pub use hickory_resolver::config::LookupIpStrategy;
-use hickory_resolver::{lookup_ip::LookupIpIntoIter, system_conf, TokioAsyncResolver};
-use std::io;
-use std::net::SocketAddr;
+use hickory_resolver::error::ResolveError;
+use hickory_resolver::proto::op::Query;
+use hickory_resolver::proto::rr::{RData, RecordType};
+use hickory_resolver::{lookup_ip::LookupIpIntoIter, system_conf, Hosts, TokioAsyncResolver};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
 use std::sync::Arc;
 
 /// Wrapper around an `AsyncResolver`, which implements the `Resolve` trait.
@@ -17,6 +18,10 @@ pub struct HickoryDnsResolver {
     /// Tokio Runtime in initialization, so we must delay the actual
     /// construction of the resolver.
     state: Arc<TokioAsyncResolver>,
+    /// The hosts file, consulted before the dual-stack queries as
+    /// `lookup_ip` does.
+    hosts: Option<Arc<Hosts>>,
+    strategy: LookupIpStrategy,
 }
 
 impl HickoryDnsResolver {
@@ -25,19 +30,87 @@ impl HickoryDnsResolver {
     /// overriden to look up for both IPv4 and IPv6 addresses
     /// to work with "happy eyeballs" algorithm.
     pub fn new<S: Into<Option<LookupIpStrategy>>>(strategy: S) -> crate::Result<Self> {
-        let (config, mut opts) = system_conf::read_system_conf()
-            .map_err(|e| {
-                io::Error::new(
-                    io::ErrorKind::Other,
-                    format!("error reading DNS system conf: {}", e),
-                )
-            })
-            .map_err(|err| Error::new(Kind::Builder, Some(err.to_string())))?;
-        opts.ip_strategy = strategy.into().unwrap_or(LookupIpStrategy::Ipv4AndIpv6);
+        let (config, mut opts) = system_conf::read_system_conf().map_err(crate::error::builder)?;
+        let strategy = strategy.into().unwrap_or(LookupIpStrategy::Ipv4AndIpv6);
+        opts.ip_strategy = strategy;
+        let hosts = opts.use_hosts_file.then(|| Arc::new(Hosts::new()));
         Ok(Self {
             state: Arc::new(TokioAsyncResolver::tokio(config, opts)),
+            hosts,
+            strategy,
         })
     }
+
+    /// Looks up the IPv4 and IPv6 addresses of `name` at once, sending the
+    /// `A` and `AAAA` queries in parallel.
+    ///
+    /// Addresses of a family listed in the hosts file are not queried. A
+    /// name with addresses of a single family resolves to an empty list
+    /// for the other. This fails only if both queries do.
+    pub async fn resolve_dual_stack(
+        &self,
+        name: &str,
+    ) -> Result<(Vec<Ipv4Addr>, Vec<Ipv6Addr>), ResolveError> {
+        let v4 = async {
+            match self.static_host(name, RecordType::A) {
+                Some(addrs) => Ok(addrs
+                    .into_iter()
+                    .filter_map(|ip| match ip {
+                        IpAddr::V4(ip) => Some(ip),
+                        IpAddr::V6(_) => None,
+                    })
+                    .collect()),
+                None => self
+                    .state
+                    .ipv4_lookup(name)
+                    .await
+                    .map(|lookup| lookup.iter().map(|a| a.0).collect()),
+            }
+        };
+        let v6 = async {
+            match self.static_host(name, RecordType::AAAA) {
+                Some(addrs) => Ok(addrs
+                    .into_iter()
+                    .filter_map(|ip| match ip {
+                        IpAddr::V6(ip) => Some(ip),
+                        IpAddr::V4(_) => None,
+                    })
+                    .collect()),
+                None => self
+                    .state
+                    .ipv6_lookup(name)
+                    .await
+                    .map(|lookup| lookup.iter().map(|aaaa| aaaa.0).collect()),
+            }
+        };
+        match future::join(v4, v6).await {
+            (Err(err), Err(_)) => Err(err),
+            (v4, v6) => Ok((v4.unwrap_or_default(), v6.unwrap_or_default())),
+        }
+    }
+
+    /// Returns the addresses listed for `name` in the hosts file, if any.
+    fn static_host(&self, name: &str, record_type: RecordType) -> Option<Vec<IpAddr>> {
+        // Names are lowercased when the hosts file is read.
+        let name = hickory_resolver::Name::from_str(&name.to_lowercase()).ok()?;
+        let query = Query::query(name, record_type);
+        let lookup = self.hosts.as_ref()?.lookup_static_host(&query)?;
+        Some(lookup.iter().filter_map(RData::ip_addr).collect())
+    }
+}
+
+/// Alternates between IPv6 and IPv4 addresses, starting with IPv6, as
+/// recommended by RFC 8305 for the connection attempts.
+fn interleave(v4: Vec<Ipv4Addr>, v6: Vec<Ipv6Addr>) -> Vec<IpAddr> {
+    let mut addrs = Vec::with_capacity(v4.len() + v6.len());
+    let mut v4 = v4.into_iter().map(IpAddr::V4);
+    let mut v6 = v6.into_iter().map(IpAddr::V6);
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => return addrs,
+            (v6, v4) => addrs.extend(v6.into_iter().chain(v4)),
+        }
+    }
 }
 
 struct SocketAddrs {
@@ -48,7 +121,25 @@ impl Resolve for HickoryDnsResolver {
     fn resolve(&self, name: Name) -> Resolving {
         let resolver = self.clone();
         Box::pin(async move {
+            // `lookup_ip` reads the hosts file and the search domains, and
+            // sends the `A` and `AAAA` queries in parallel for `Ipv4AndIpv6`.
             let lookup = resolver.state.lookup_ip(name.as_str()).await?;
+            if let LookupIpStrategy::Ipv4AndIpv6 = resolver.strategy {
+                let (mut v4, mut v6) = (Vec::new(), Vec::new());
+                for ip_addr in lookup.iter() {
+                    match ip_addr {
+                        IpAddr::V4(ip) => v4.push(ip),
+                        IpAddr::V6(ip) => v6.push(ip),
+                    }
+                }
+                let addrs: Addrs = Box::new(
+                    interleave(v4, v6)
+                        .into_iter()
+                        .map(|ip_addr| SocketAddr::new(ip_addr, 0)),
+                );
+                return Ok(addrs);
+            }
+
             let addrs: Addrs = Box::new(SocketAddrs {
                 iter: lookup.into_iter(),
             });
@@ -64,3 +155,93 @@ impl Iterator for SocketAddrs {
         self.iter.next().map(|ip_addr| SocketAddr::new(ip_addr, 0))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interleave_starts_with_ipv6() {
+        let v4 = vec![Ipv4Addr::new(192, 0, 2, 1), Ipv4Addr::new(192, 0, 2, 2)];
+        let v6 = vec![Ipv6Addr::LOCALHOST];
+        assert_eq!(
+            interleave(v4, v6),
+            [
+                IpAddr::V6(Ipv6Addr::LOCALHOST),
+                IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+                IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)),
+            ]
+        );
+    }
+
+    /// Answers every `A` query with 198.51.100.1, and `AAAA` queries with
+    /// no address.
+    async fn dns_server() -> std::net::SocketAddr {
+        use hickory_resolver::proto::op::{Message, MessageType};
+        use hickory_resolver::proto::rr::{rdata::A, Record};
+
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0; 512];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let request = Message::from_vec(&buf[..len]).unwrap();
+                let mut response = Message::new();
+                response
+                    .set_id(request.id())
+                    .set_message_type(MessageType::Response)
+                    .set_recursion_available(true)
+                    .add_queries(request.queries().to_vec());
+                for query in request.queries() {
+                    if query.query_type() == RecordType::A {
+                        let a = A(Ipv4Addr::new(198, 51, 100, 1));
+                        response.add_answer(Record::from_rdata(
+                            query.name().clone(),
+                            60,
+                            RData::A(a),
+                        ));
+                    }
+                }
+                let _ = socket.send_to(&response.to_vec().unwrap(), peer).await;
+            }
+        });
+        addr
+    }
+
+    async fn resolver_with_hosts(hosts: &str) -> HickoryDnsResolver {
+        use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+
+        let dns = dns_server().await;
+        let servers = NameServerConfigGroup::from_ips_clear(&[dns.ip()], dns.port(), true);
+        let config = ResolverConfig::from_parts(None, vec![], servers);
+        let read = || Hosts::default().read_hosts_conf(hosts.as_bytes()).unwrap();
+        let mut state = TokioAsyncResolver::tokio(config, ResolverOpts::default());
+        state.set_hosts(Some(read()));
+        HickoryDnsResolver {
+            state: Arc::new(state),
+            hosts: Some(Arc::new(read())),
+            strategy: LookupIpStrategy::Ipv4AndIpv6,
+        }
+    }
+
+    #[tokio::test]
+    async fn hosts_file_takes_precedence_over_dns() {
+        let resolver = resolver_with_hosts("192.0.2.7 Example.test\n").await;
+        let listed = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 7));
+
+        let addrs: Vec<_> = resolver
+            .resolve("example.test".parse().unwrap())
+            .await
+            .unwrap()
+            .map(|addr| addr.ip())
+            .collect();
+        assert_eq!(addrs, [listed]);
+
+        let (v4, v6) = resolver.resolve_dual_stack("EXAMPLE.test").await.unwrap();
+        assert_eq!(v4, [Ipv4Addr::new(192, 0, 2, 7)]);
+        assert!(v6.is_empty());
+
+        let (v4, _) = resolver.resolve_dual_stack("other.test").await.unwrap();
+        assert_eq!(v4, [Ipv4Addr::new(198, 51, 100, 1)]);
+    }
+}
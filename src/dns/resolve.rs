@@ -8,7 +8,8 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
-use crate::error::BoxError;
+use crate::error::{BoxError, DnsError};
+use crate::metrics::{self, Phase};
 
 /// Alias for an `Iterator` trait object over `SocketAddr`.
 pub type Addrs = Box<dyn Iterator<Item = SocketAddr> + Send>;
@@ -50,7 +51,21 @@ impl Service<Name> for DynResolver {
     }
 
     fn call(&mut self, name: Name) -> Self::Future {
-        self.resolver.resolve(name)
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(target: "rquest", "rquest.dns", dns.name = name.as_str());
+        let resolving = self.resolver.resolve(name);
+        let timing = metrics::is_timing();
+
+        let resolving = async move {
+            let addrs = resolving.await;
+            if timing {
+                metrics::mark(Phase::Resolved);
+            }
+            addrs.map_err(|err| -> BoxError { Box::new(DnsError(err)) })
+        };
+        #[cfg(feature = "tracing")]
+        let resolving = tracing::Instrument::instrument(resolving, span);
+        Box::pin(resolving)
     }
 }
 
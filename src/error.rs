@@ -2,6 +2,7 @@
 use std::error::Error as StdError;
 use std::fmt;
 use std::io;
+use std::time::Duration;
 
 use crate::{StatusCode, Url};
 
@@ -23,6 +24,8 @@ struct Inner {
     kind: Kind,
     source: Option<BoxError>,
     url: Option<Url>,
+    request_id: Option<String>,
+    retry_skipped: bool,
 }
 
 impl Error {
@@ -35,6 +38,8 @@ impl Error {
                 kind,
                 source: source.map(Into::into),
                 url: None,
+                request_id: None,
+                retry_skipped: false,
             }),
         }
     }
@@ -82,6 +87,17 @@ impl Error {
         self
     }
 
+    /// The id the failed request was sent with, for a client with a
+    /// [`request_id`](crate::ClientBuilder::request_id) policy.
+    pub fn request_id(&self) -> Option<&str> {
+        self.inner.request_id.as_deref()
+    }
+
+    pub(crate) fn with_request_id(mut self, id: String) -> Self {
+        self.inner.request_id = Some(id);
+        self
+    }
+
     /// Returns true if the error is from a type Builder.
     pub fn is_builder(&self) -> bool {
         matches!(self.inner.kind, Kind::Builder)
@@ -105,6 +121,10 @@ impl Error {
             if err.is::<TimedOut>() {
                 return true;
             }
+            #[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+            if let Some(crate::WsError::PongTimeout) = err.downcast_ref::<crate::WsError>() {
+                return true;
+            }
             if let Some(io) = err.downcast_ref::<io::Error>() {
                 if io.kind() == io::ErrorKind::TimedOut {
                     return true;
@@ -116,6 +136,19 @@ impl Error {
         false
     }
 
+    /// The phase the request was in when one of the client's timeouts
+    /// elapsed, if the error is from one.
+    pub fn timeout_phase(&self) -> Option<TimeoutPhase> {
+        self.find_source::<TimedOut>().map(|err| err.phase)
+    }
+
+    /// How long the request had been in flight when one of the client's
+    /// timeouts elapsed, or the connection being opened for the connect
+    /// timeout, if the error is from one.
+    pub fn timeout_elapsed(&self) -> Option<Duration> {
+        self.find_source::<TimedOut>().map(|err| err.elapsed)
+    }
+
     /// Returns true if the error is related to the request
     pub fn is_request(&self) -> bool {
         matches!(self.inner.kind, Kind::Request)
@@ -126,6 +159,7 @@ impl Error {
         let mut source = self.source();
 
         while let Some(err) = source {
+            #[cfg(not(target_arch = "wasm32"))]
             if let Some(hyper_err) = err.downcast_ref::<hyper::Error>() {
                 if hyper_err.is_connect() {
                     return true;
@@ -138,16 +172,223 @@ impl Error {
         false
     }
 
+    /// Returns true if the error is related to TLS, such as a failed
+    /// handshake or an invalid certificate.
+    pub fn is_tls(&self) -> bool {
+        #[cfg(all(feature = "boring-tls", not(target_arch = "wasm32")))]
+        {
+            self.find_source::<boring::ssl::Error>().is_some()
+                || self.find_source::<boring::error::ErrorStack>().is_some()
+        }
+
+        #[cfg(any(not(feature = "boring-tls"), target_arch = "wasm32"))]
+        {
+            false
+        }
+    }
+
+    /// Returns the code of the first error in the BoringSSL error stack of a
+    /// TLS error, if any.
+    ///
+    /// Its library and reason can be read with BoringSSL's
+    /// `ERR_GET_LIB` and `ERR_GET_REASON`.
+    #[cfg(all(feature = "boring-tls", not(target_arch = "wasm32")))]
+    pub fn tls_code(&self) -> Option<u64> {
+        let stack = match self.find_source::<boring::error::ErrorStack>() {
+            Some(stack) => stack,
+            None => self.find_source::<boring::ssl::Error>()?.ssl_error()?,
+        };
+        stack.errors().first().map(|err| err.code() as u64)
+    }
+
+    /// Returns true if the error is from resolving the host name.
+    pub fn is_dns(&self) -> bool {
+        self.find_source::<DnsError>().is_some()
+    }
+
+    /// Returns true if the error is from a host name that could not be
+    /// converted to ASCII, or that was rejected by [`IdnaPolicy::Reject`].
+    ///
+    /// [`IdnaPolicy::Reject`]: crate::IdnaPolicy::Reject
+    pub fn is_idna(&self) -> bool {
+        self.find_source::<IdnaRejected>().is_some()
+            || self.find_source::<url::ParseError>() == Some(&url::ParseError::IdnaError)
+    }
+
+    /// Returns true if the request was sent on a client that was shut down
+    /// with [`Client::shutdown`](crate::Client::shutdown), or was aborted
+    /// by it.
+    pub fn is_client_closed(&self) -> bool {
+        self.find_source::<ClientClosed>().is_some()
+    }
+
+    /// Returns true if a request was not retried because the budget set
+    /// with [`ClientBuilder::retry_budget`](crate::ClientBuilder::retry_budget)
+    /// was exhausted.
+    ///
+    /// The error of the last attempt, if it failed, is the source of this
+    /// one.
+    pub fn is_retry_budget_exhausted(&self) -> bool {
+        self.find_source::<RetryBudgetExhausted>().is_some()
+    }
+
+    /// Returns true if a request was not sent because the rate limit set
+    /// with `ClientBuilder::rate_limit` would have held it longer than its
+    /// `max_wait`, or behind more than `max_queue` requests.
+    pub fn is_rate_limited(&self) -> bool {
+        self.find_source::<RateLimited>().is_some()
+    }
+
+    /// Returns true if an HTTP/2 connection was closed because the server
+    /// did not acknowledge its `SETTINGS` within the
+    /// `ClientBuilder::http2_settings_ack_timeout`.
+    pub fn is_settings_ack_timeout(&self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut source = self.source();
+
+            while let Some(err) = source {
+                let io = match err.downcast_ref::<hyper::h2::Error>() {
+                    Some(h2) => h2.get_io(),
+                    None => err.downcast_ref::<io::Error>(),
+                };
+                if io.is_some_and(crate::h2_stats::SettingsAckTimeout::is) {
+                    return true;
+                }
+                source = err.source();
+            }
+
+            false
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        false
+    }
+
+    /// Returns true if no connection was available within the
+    /// `ClientBuilder::pool_checkout_timeout`, because of the
+    /// `max_connections_per_host` or `max_total_connections` limit.
+    pub fn is_pool_exhausted(&self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.find_source::<crate::conn_limit::PoolExhausted>()
+                .is_some()
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        false
+    }
+
+    /// Returns true if the error is from connecting through a proxy, or
+    /// was returned by the proxy.
+    pub fn is_proxy(&self) -> bool {
+        self.find_source::<ProxyError>().is_some()
+    }
+
+    /// Returns true if sending the request again may succeed.
+    ///
+    /// This is the case for:
+    ///
+    /// - errors connecting to the server, apart from DNS and TLS errors,
+    /// - timeouts,
+    /// - `429 Too Many Requests`, `502 Bad Gateway`, `503 Service Unavailable`
+    ///   and `504 Gateway Timeout` status errors,
+    /// - HTTP/2 requests refused by a server shutting the connection down
+    ///   gracefully.
+    ///
+    /// A request may have been processed by the server when it timed out,
+    /// so only retry idempotent requests on timeouts.
+    pub fn is_retryable(&self) -> bool {
+        if let Kind::Status(status) = self.inner.kind {
+            return matches!(
+                status,
+                StatusCode::TOO_MANY_REQUESTS
+                    | StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            );
+        }
+
+        if self.is_timeout() || self.is_go_away() {
+            return true;
+        }
+        self.is_connect() && !self.is_dns() && !self.is_tls()
+    }
+
     /// Returns true if the error is related to the request or response body
     pub fn is_body(&self) -> bool {
         matches!(self.inner.kind, Kind::Body)
     }
 
+    /// Returns true if the response body was longer than the limit set with
+    /// `max_response_body_size`.
+    pub fn is_body_too_large(&self) -> bool {
+        self.find_source::<BodyTooLarge>().is_some()
+    }
+
+    /// Returns true if the response body ended before its `Content-Length`,
+    /// or before the last chunk of a chunked body, with
+    /// `strict_content_length` enabled.
+    pub fn is_incomplete_body(&self) -> bool {
+        self.find_source::<IncompleteBody>().is_some()
+    }
+
+    /// The `Content-Length` of an incomplete response body, and the bytes
+    /// received of it, as `(expected, received)`.
+    ///
+    /// `None` if the error is not about an incomplete body, or if the body
+    /// had no `Content-Length`, see [`Error::is_incomplete_body`].
+    pub fn content_length_mismatch(&self) -> Option<(u64, u64)> {
+        let incomplete = self.find_source::<IncompleteBody>()?;
+        Some((incomplete.expected?, incomplete.received))
+    }
+
+    /// Returns the mistakes found in the configuration, if building a
+    /// `Client` failed because of them.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn validation_errors(&self) -> Option<&[crate::ValidationError]> {
+        self.find_source::<crate::client::validation::ValidationErrors>()
+            .map(|errors| &errors.0[..])
+    }
+
+    /// Returns true if the response had more headers, or larger ones, than
+    /// allowed by `max_response_headers` and `max_response_header_size`.
+    pub fn is_headers_too_large(&self) -> bool {
+        if self.find_source::<HeadersTooLarge>().is_some() {
+            return true;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.find_source::<hyper::Error>()
+                .is_some_and(hyper::Error::is_parse_too_large)
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            false
+        }
+    }
+
     /// Returns true if the error is related to decoding the response's body
     pub fn is_decode(&self) -> bool {
         matches!(self.inner.kind, Kind::Decode)
     }
 
+    /// Returns the response that caused this error, if it was kept.
+    ///
+    /// This is the case when the server refused a websocket upgrade, so the
+    /// body can still be read.
+    #[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+    pub fn into_response(self) -> Option<crate::Response> {
+        let unexpected = self.inner.source?.downcast::<UnexpectedResponse>().ok()?;
+        Some(
+            unexpected
+                .response
+                .into_inner()
+                .unwrap_or_else(|e| e.into_inner()),
+        )
+    }
+
     /// Returns the status code, if the error was generated from a response.
     pub fn status(&self) -> Option<StatusCode> {
         match self.inner.kind {
@@ -158,6 +399,47 @@ impl Error {
 
     // private
 
+    fn find_source<T: StdError + 'static>(&self) -> Option<&T> {
+        let mut source = self.source();
+
+        while let Some(err) = source {
+            if let Some(err) = err.downcast_ref::<T>() {
+                return Some(err);
+            }
+            // The source of an I/O error is that of the error it wraps,
+            // which would be skipped.
+            source = match err.downcast_ref::<io::Error>() {
+                Some(io) => io.get_ref().map(|e| e as _),
+                None => err.source(),
+            };
+        }
+
+        None
+    }
+
+    /// Whether an HTTP/2 server refused the request by shutting the
+    /// connection down gracefully.
+    pub(crate) fn is_go_away(&self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.find_source::<hyper::h2::Error>().is_some_and(|err| {
+                err.is_go_away()
+                    && err.is_remote()
+                    && err.reason() == Some(hyper::h2::Reason::NO_ERROR)
+            })
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            false
+        }
+    }
+
+    pub(crate) fn retry_skipped(mut self) -> Self {
+        self.inner.retry_skipped = true;
+        self
+    }
+
     #[allow(unused)]
     pub(crate) fn into_io(self) -> io::Error {
         io::Error::new(io::ErrorKind::Other, self)
@@ -173,9 +455,15 @@ impl fmt::Debug for Error {
         if let Some(ref url) = self.inner.url {
             builder.field("url", &url.as_str());
         }
+        if let Some(ref request_id) = self.inner.request_id {
+            builder.field("request_id", request_id);
+        }
         if let Some(ref source) = self.inner.source {
             builder.field("source", source);
         }
+        if self.inner.retry_skipped {
+            builder.field("retry_skipped", &true);
+        }
 
         builder.finish()
     }
@@ -209,6 +497,10 @@ impl fmt::Display for Error {
             write!(f, ": {}", e)?;
         }
 
+        if self.inner.retry_skipped {
+            f.write_str(" (retry skipped: request body is not replayable)")?;
+        }
+
         Ok(())
     }
 }
@@ -219,7 +511,7 @@ impl StdError for Error {
     }
 }
 
-#[cfg(feature = "websocket")]
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
 impl From<async_tungstenite::tungstenite::Error> for Error {
     fn from(err: async_tungstenite::tungstenite::Error) -> Error {
         Error::new(Kind::Upgrade, Some(err))
@@ -233,7 +525,7 @@ impl From<serde_json::Error> for Error {
     }
 }
 
-#[cfg(feature = "boring-tls")]
+#[cfg(all(feature = "boring-tls", not(target_arch = "wasm32")))]
 impl From<boring::error::ErrorStack> for Error {
     fn from(err: boring::error::ErrorStack) -> Error {
         Error::new(Kind::Builder, Some(err))
@@ -261,6 +553,10 @@ pub(crate) fn body<E: Into<BoxError>>(e: E) -> Error {
     Error::new(Kind::Body, Some(e))
 }
 
+pub(crate) fn body_too_large(limit: u64, read: u64) -> Error {
+    Error::new(Kind::Body, Some(BodyTooLarge { limit, read }))
+}
+
 pub(crate) fn decode<E: Into<BoxError>>(e: E) -> Error {
     Error::new(Kind::Decode, Some(e))
 }
@@ -269,6 +565,14 @@ pub(crate) fn request<E: Into<BoxError>>(e: E) -> Error {
     Error::new(Kind::Request, Some(e))
 }
 
+/// Passes an `Error` through as is, anything else becomes a request error.
+pub(crate) fn request_boxed(err: BoxError) -> Error {
+    match err.downcast::<Error>() {
+        Ok(err) => *err,
+        Err(err) => request(err),
+    }
+}
+
 pub(crate) fn redirect<E: Into<BoxError>>(e: E, url: Url) -> Error {
     Error::new(Kind::Redirect, Some(e)).with_url(url)
 }
@@ -277,10 +581,35 @@ pub(crate) fn status_code(url: Url, status: StatusCode) -> Error {
     Error::new(Kind::Status(status), None::<Error>).with_url(url)
 }
 
+pub(crate) fn client_closed() -> Error {
+    Error::new(Kind::Request, Some(ClientClosed))
+}
+
+pub(crate) fn retry_budget_exhausted(last: Option<Error>, url: Url) -> Error {
+    Error::new(Kind::Request, Some(RetryBudgetExhausted(last))).with_url(url)
+}
+
+pub(crate) fn rate_limited(limited: RateLimited, url: Url) -> Error {
+    Error::new(Kind::Request, Some(limited)).with_url(url)
+}
+
 pub(crate) fn url_bad_scheme(url: Url) -> Error {
     Error::new(Kind::Builder, Some(BadScheme)).with_url(url)
 }
 
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+pub(crate) fn unexpected_response(response: crate::Response) -> Error {
+    let url = response.url().clone();
+    Error::new(
+        Kind::Upgrade,
+        Some(UnexpectedResponse {
+            status: response.status(),
+            response: std::sync::Mutex::new(response),
+        }),
+    )
+    .with_url(url)
+}
+
 pub(crate) fn upgrade<E: Into<BoxError>>(e: E) -> Error {
     Error::new(Kind::Upgrade, Some(e))
 }
@@ -306,17 +635,136 @@ pub(crate) fn decode_io(e: io::Error) -> Error {
 
 // internal Error "sources"
 
+/// The phase a request was in when it timed out, see
+/// [`Error::timeout_phase`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TimeoutPhase {
+    /// Resolving the host of the connection, under the connect timeout.
+    DnsResolution,
+    /// Opening the TCP connection, or tunneling through a proxy, under the
+    /// connect timeout.
+    TcpConnect,
+    /// The TLS handshake, under the connect timeout.
+    TlsHandshake,
+    /// Writing the request headers or body, under the request timeout.
+    RequestWrite,
+    /// Waiting for the response headers once the request was sent, under
+    /// the request timeout.
+    ResponseHeader,
+    /// Reading the response body, under the request timeout.
+    ResponseBody,
+    /// Waiting for a connection, or anything else the request timeout
+    /// elapsed in.
+    Total,
+}
+
+impl fmt::Display for TimeoutPhase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            TimeoutPhase::DnsResolution => "dns resolution",
+            TimeoutPhase::TcpConnect => "tcp connect",
+            TimeoutPhase::TlsHandshake => "tls handshake",
+            TimeoutPhase::RequestWrite => "request write",
+            TimeoutPhase::ResponseHeader => "response header",
+            TimeoutPhase::ResponseBody => "response body",
+            TimeoutPhase::Total => "request",
+        })
+    }
+}
+
 #[derive(Debug)]
-pub(crate) struct TimedOut;
+pub(crate) struct TimedOut {
+    phase: TimeoutPhase,
+    elapsed: Duration,
+}
+
+impl TimedOut {
+    pub(crate) fn new(phase: TimeoutPhase, elapsed: Duration) -> TimedOut {
+        TimedOut { phase, elapsed }
+    }
+}
 
 impl fmt::Display for TimedOut {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str("operation timed out")
+        write!(
+            f,
+            "operation timed out ({} after {:?})",
+            self.phase, self.elapsed
+        )
     }
 }
 
 impl StdError for TimedOut {}
 
+#[derive(Debug)]
+pub(crate) struct BodyTooLarge {
+    limit: u64,
+    read: u64,
+}
+
+impl fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "response body exceeds the limit of {} bytes ({} bytes read)",
+            self.limit, self.read
+        )
+    }
+}
+
+impl StdError for BodyTooLarge {}
+
+#[derive(Debug)]
+pub(crate) struct IncompleteBody {
+    pub(crate) expected: Option<u64>,
+    pub(crate) received: u64,
+}
+
+impl fmt::Display for IncompleteBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.expected {
+            Some(expected) => write!(
+                f,
+                "response body ended after {} of its {} bytes",
+                self.received, expected
+            ),
+            None => write!(
+                f,
+                "chunked response body ended without its last chunk ({} bytes read)",
+                self.received
+            ),
+        }
+    }
+}
+
+impl StdError for IncompleteBody {}
+
+#[derive(Debug)]
+pub(crate) enum HeadersTooLarge {
+    Count { count: usize, max: usize },
+    Size { size: usize, max: usize },
+}
+
+impl fmt::Display for HeadersTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeadersTooLarge::Count { count, max } => write!(
+                f,
+                "response has {} headers, over the limit of {}",
+                count, max
+            ),
+            HeadersTooLarge::Size { size, max } => write!(
+                f,
+                "response headers are {} bytes, over the limit of {}",
+                size, max
+            ),
+        }
+    }
+}
+
+impl StdError for HeadersTooLarge {}
+
 #[derive(Debug)]
 pub(crate) struct BadScheme;
 
@@ -328,6 +776,137 @@ impl fmt::Display for BadScheme {
 
 impl StdError for BadScheme {}
 
+#[derive(Debug)]
+pub(crate) struct ClientClosed;
+
+impl fmt::Display for ClientClosed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("client closed")
+    }
+}
+
+impl StdError for ClientClosed {}
+
+/// Wraps the error of the last attempt, if any, for
+/// `Error::is_retry_budget_exhausted`.
+#[derive(Debug)]
+pub(crate) struct RetryBudgetExhausted(Option<Error>);
+
+impl fmt::Display for RetryBudgetExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("retry budget exhausted")
+    }
+}
+
+impl StdError for RetryBudgetExhausted {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.0.as_ref().map(|err| err as _)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum RateLimited {
+    QueueFull { max_queue: usize },
+    WaitTooLong { wait: Duration, max_wait: Duration },
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RateLimited::QueueFull { max_queue } => {
+                write!(f, "rate limited: {} requests already queued", max_queue)
+            }
+            RateLimited::WaitTooLong { wait, max_wait } => write!(
+                f,
+                "rate limited: would wait {:?}, more than {:?}",
+                wait, max_wait
+            ),
+        }
+    }
+}
+
+impl StdError for RateLimited {}
+
+/// A non-ASCII host name rejected by `IdnaPolicy::Reject`.
+#[derive(Debug)]
+pub(crate) struct IdnaRejected(pub(crate) String);
+
+impl fmt::Display for IdnaRejected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "non-ASCII host name {:?} is not allowed", self.0)
+    }
+}
+
+impl StdError for IdnaRejected {}
+
+/// Wraps the errors of DNS resolvers, for `Error::is_dns`.
+#[derive(Debug)]
+pub(crate) struct DnsError(pub(crate) BoxError);
+
+impl fmt::Display for DnsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "dns error: {}", self.0)
+    }
+}
+
+impl StdError for DnsError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&*self.0)
+    }
+}
+
+/// Wraps the errors of proxy connections, for `Error::is_proxy`.
+#[derive(Debug)]
+pub(crate) struct ProxyError(pub(crate) BoxError);
+
+impl fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "proxy error: {}", self.0)
+    }
+}
+
+impl StdError for ProxyError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&*self.0)
+    }
+}
+
+pub(crate) fn proxy<E: Into<BoxError>>(e: E) -> BoxError {
+    Box::new(ProxyError(e.into()))
+}
+
+/// Turns a JavaScript exception into an error source.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn wasm(js_val: wasm_bindgen::JsValue) -> BoxError {
+    format!("{:?}", js_val).into()
+}
+
+// `Response` isn't `Sync`, the mutex only exists to make the error `Sync`.
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+pub(crate) struct UnexpectedResponse {
+    status: StatusCode,
+    response: std::sync::Mutex<crate::Response>,
+}
+
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+impl fmt::Debug for UnexpectedResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UnexpectedResponse")
+            .field("status", &self.status)
+            .finish()
+    }
+}
+
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+impl fmt::Display for UnexpectedResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unexpected status code: {}", self.status)
+    }
+}
+
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+impl StdError for UnexpectedResponse {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -376,13 +955,68 @@ mod tests {
         }
     }
 
+    #[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+    #[test]
+    fn into_response_unexpected() {
+        let res = http::Response::builder()
+            .status(403)
+            .body("forbidden")
+            .unwrap();
+        let err = super::unexpected_response(crate::Response::from(res));
+        assert!(matches!(err.inner.kind, Kind::Upgrade));
+        assert!(err
+            .to_string()
+            .ends_with("unexpected status code: 403 Forbidden"));
+
+        let res = err.into_response().expect("response");
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        assert!(super::upgrade("other").into_response().is_none());
+    }
+
     #[test]
     fn is_timeout() {
-        let err = super::request(super::TimedOut);
+        let err = super::request(TimedOut::new(TimeoutPhase::Total, Duration::from_secs(1)));
         assert!(err.is_timeout());
+        assert_eq!(err.timeout_phase(), Some(TimeoutPhase::Total));
+        assert_eq!(err.timeout_elapsed(), Some(Duration::from_secs(1)));
 
         let io = io::Error::new(io::ErrorKind::Other, err);
         let nested = super::request(io);
         assert!(nested.is_timeout());
     }
+
+    #[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+    #[test]
+    fn is_timeout_pong() {
+        let err = super::upgrade(crate::WsError::PongTimeout);
+        assert!(err.is_timeout());
+    }
+
+    #[test]
+    fn is_dns_and_proxy_through_io_errors() {
+        let dns = io::Error::new(io::ErrorKind::Other, DnsError("no such host".into()));
+        let err = super::request(dns);
+        assert!(err.is_dns() && !err.is_proxy());
+        assert_eq!(
+            err.source().map(|e| e.to_string()),
+            Some("dns error: no such host".to_owned())
+        );
+
+        let timed_out = TimedOut::new(TimeoutPhase::TcpConnect, Duration::from_millis(100));
+        let err = super::request(super::proxy(timed_out));
+        assert!(err.is_proxy() && err.is_timeout());
+        assert_eq!(err.timeout_phase(), Some(TimeoutPhase::TcpConnect));
+    }
+
+    #[test]
+    fn is_retryable_statuses() {
+        let url = Url::parse("http://example.com").unwrap();
+        let err = super::status_code(url.clone(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(err.is_retryable());
+
+        let err = super::status_code(url, StatusCode::NOT_FOUND);
+        assert!(!err.is_retryable());
+        assert!(!super::builder("invalid").is_retryable());
+    }
 }
@@ -0,0 +1,749 @@
+//! Statistics of HTTP/2 connections, read from the frames they carry.
+//!
+//! hyper does not report what happens on its HTTP/2 connections, so each
+//! connection watches the bytes going through it instead. A connection
+//! whose first bytes written are the HTTP/2 client preface has its frame
+//! headers followed in both directions.
+//!
+//! The header blocks the client sends are decoded just enough to count the
+//! fields it added to its HPACK dynamic table.
+//!
+//! The frames also tell whether the server acknowledged the initial
+//! `SETTINGS` of the client, which hyper waits for without a timeout, and
+//! which origins the server declared with `ORIGIN` frames (RFC 8336). The
+//! last stream of a `GOAWAY` frame is kept too, as h2 leaves it out of the
+//! error it reports.
+//!
+//! A connection whose `ORIGIN` frames leave out the origin it was opened to
+//! is poisoned once a response arrives on it, so that hyper does not check
+//! it out of its pool again.
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use http::Uri;
+use tokio::time::Sleep;
+use url::Url;
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const RST_STREAM: u8 = 0x3;
+const SETTINGS: u8 = 0x4;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+const CONTINUATION: u8 = 0x9;
+const ORIGIN: u8 = 0xc;
+const ACK: u8 = 0x1;
+const END_STREAM: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const PADDED: u8 = 0x8;
+const PRIORITY_FLAG: u8 = 0x20;
+
+/// The sides of a stream that ended it.
+const LOCAL_END: u8 = 0x1;
+const REMOTE_END: u8 = 0x2;
+
+/// The payloads of frames larger than the default maximum frame size are
+/// not kept.
+const MAX_KEPT_PAYLOAD: usize = 16 * 1024;
+
+/// Statistics of an HTTP/2 connection, see
+/// [`Client::h2_connection_stats`](crate::Client::h2_connection_stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct H2ConnectionStats {
+    /// Streams opened by the client.
+    pub streams_opened: u64,
+    /// Streams reset, by either side.
+    pub streams_reset: u64,
+    /// The round trip time of the last acknowledged `PING`, in
+    /// milliseconds. Pings are only sent with
+    /// [`ClientBuilder::http2_keep_alive_interval`](crate::ClientBuilder::http2_keep_alive_interval)
+    /// or [`ClientBuilder::http2_adaptive_window`](crate::ClientBuilder::http2_adaptive_window).
+    pub ping_rtt_ms: Option<f64>,
+    /// Bytes of HTTP/2 frames sent, before any TLS encryption.
+    pub bytes_sent: u64,
+    /// Bytes of HTTP/2 frames received, after any TLS decryption.
+    pub bytes_received: u64,
+    /// Header fields the client added to its HPACK dynamic table, counted
+    /// from the header blocks it sent. Header blocks in frames larger than
+    /// 16 KiB are not counted.
+    pub hpack_table_entries: u64,
+}
+
+#[derive(Default)]
+struct H2Stats {
+    stats: H2ConnectionStats,
+    /// Whether the connection turned out to be an HTTP/2 one.
+    http2: bool,
+    last_stream_id: u32,
+    /// The streams open, with the sides that ended them so far.
+    open_streams: HashMap<u32, u8>,
+    ping_sent: Option<Instant>,
+    /// The origins declared by `ORIGIN` frames, once one is received.
+    origins: Option<Vec<String>>,
+    /// The last stream the server processed, from its last `GOAWAY`.
+    go_away_stream_id: Option<u32>,
+}
+
+/// The HTTP/2 connections of a connector, by origin.
+#[derive(Default)]
+pub(crate) struct H2Registry {
+    conns: Mutex<HashMap<String, Weak<Mutex<H2Stats>>>>,
+}
+
+impl H2Registry {
+    /// The statistics of the last HTTP/2 connection opened to `origin`, if
+    /// it is still open.
+    pub(crate) fn stats(&self, origin: &str) -> Option<H2ConnectionStats> {
+        let origin = Url::parse(origin).ok()?.origin().ascii_serialization();
+        let stats = self.lock().get(&origin)?.upgrade()?;
+        let stats = lock(&stats).stats;
+        Some(stats)
+    }
+
+    /// The origins the server of the HTTP/2 connection to `origin` declared
+    /// with `ORIGIN` frames, if it sent any.
+    pub(crate) fn origins(&self, origin: &str) -> Option<Vec<String>> {
+        let origin = Url::parse(origin).ok()?.origin().ascii_serialization();
+        let stats = self.lock().get(&origin)?.upgrade()?;
+        let origins = lock(&stats).origins.clone();
+        origins
+    }
+
+    fn register(&self, origin: String, stats: &Arc<Mutex<H2Stats>>) {
+        let mut conns = self.lock();
+        conns.retain(|_, stats| stats.strong_count() > 0);
+        conns.insert(origin, Arc::downgrade(stats));
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Weak<Mutex<H2Stats>>>> {
+        self.conns.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn lock(stats: &Mutex<H2Stats>) -> MutexGuard<'_, H2Stats> {
+    stats.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl H2Stats {
+    /// Marks `side` of a stream as ended, closing it once both are.
+    fn end_stream(&mut self, stream_id: u32, side: u8) {
+        if let Some(ended) = self.open_streams.get_mut(&stream_id) {
+            *ended |= side;
+            if *ended == LOCAL_END | REMOTE_END {
+                self.open_streams.remove(&stream_id);
+            }
+        }
+    }
+}
+
+/// The HTTP/2 state of a connection, for
+/// [`ConnectionInfo`](crate::ConnectionInfo).
+#[derive(Clone)]
+pub(crate) struct H2Handle(Arc<Mutex<H2Stats>>);
+
+impl H2Handle {
+    /// The streams open on the connection, `None` unless it is an HTTP/2
+    /// one.
+    pub(crate) fn open_streams(&self) -> Option<usize> {
+        let stats = lock(&self.0);
+        stats.http2.then(|| stats.open_streams.len())
+    }
+
+    /// The last stream the server said it processed in its last `GOAWAY`
+    /// frame, if it sent one.
+    pub(crate) fn go_away_stream_id(&self) -> Option<u32> {
+        lock(&self.0).go_away_stream_id
+    }
+
+    /// Whether the server declared an origin set that leaves out the origin
+    /// of `url`.
+    pub(crate) fn excludes(&self, url: &Url) -> bool {
+        let origin = url.origin().ascii_serialization();
+        lock(&self.0)
+            .origins
+            .as_ref()
+            .is_some_and(|origins| !origins.contains(&origin))
+    }
+}
+
+enum State {
+    /// This many bytes of the preface were written so far.
+    Preface(usize),
+    Frames,
+    /// Not an HTTP/2 connection.
+    Disabled,
+}
+
+/// Follows the frames of a connection, registering it once it turns out to
+/// be an HTTP/2 one.
+pub(crate) struct H2Tracker {
+    registry: Arc<H2Registry>,
+    origin: Option<String>,
+    stats: Arc<Mutex<H2Stats>>,
+    state: State,
+    sent: FrameReader,
+    received: FrameReader,
+    /// The header block being sent, until its last frame. `None` once a
+    /// frame of it was not kept.
+    header_block: Option<Vec<u8>>,
+    settings_ack: SettingsAck,
+}
+
+enum SettingsAck {
+    /// Waits this long for the acknowledgement, once the preface is sent.
+    Timeout(Duration),
+    Waiting(Duration, Pin<Box<Sleep>>),
+    /// Received, or not waited for.
+    Done,
+}
+
+impl H2Tracker {
+    pub(crate) fn new(
+        registry: &Arc<H2Registry>,
+        dst: &Uri,
+        settings_ack_timeout: Option<Duration>,
+    ) -> H2Tracker {
+        let origin = crate::conn_info::origin(dst);
+        H2Tracker {
+            registry: registry.clone(),
+            state: if origin.is_some() {
+                State::Preface(0)
+            } else {
+                State::Disabled
+            },
+            origin,
+            stats: Arc::default(),
+            sent: FrameReader::keeping(&[HEADERS, CONTINUATION]),
+            received: FrameReader::keeping(&[ORIGIN, GOAWAY]),
+            header_block: None,
+            settings_ack: match settings_ack_timeout {
+                Some(timeout) => SettingsAck::Timeout(timeout),
+                None => SettingsAck::Done,
+            },
+        }
+    }
+
+    pub(crate) fn handle(&self) -> H2Handle {
+        H2Handle(self.stats.clone())
+    }
+
+    /// Called with the bytes written to the connection.
+    pub(crate) fn on_write(&mut self, mut buf: &[u8]) {
+        if let State::Preface(written) = self.state {
+            let n = buf.len().min(PREFACE.len() - written);
+            if buf[..n] != PREFACE[written..written + n] {
+                self.state = State::Disabled;
+                return;
+            }
+            if written + n < PREFACE.len() {
+                self.state = State::Preface(written + n);
+                return;
+            }
+            self.state = State::Frames;
+            if let SettingsAck::Timeout(timeout) = self.settings_ack {
+                self.settings_ack =
+                    SettingsAck::Waiting(timeout, Box::pin(tokio::time::sleep(timeout)));
+            }
+            let mut stats = lock(&self.stats);
+            stats.http2 = true;
+            stats.stats.bytes_sent += PREFACE.len() as u64;
+            drop(stats);
+            if let Some(origin) = self.origin.take() {
+                self.registry.register(origin, &self.stats);
+            }
+            buf = &buf[n..];
+        }
+        if !matches!(self.state, State::Frames) || buf.is_empty() {
+            return;
+        }
+
+        let mut stats = lock(&self.stats);
+        stats.stats.bytes_sent += buf.len() as u64;
+        let header_block = &mut self.header_block;
+        self.sent.read(buf, |header, payload| match header.kind {
+            HEADERS | DATA => {
+                if header.kind == HEADERS {
+                    if header.stream_id > stats.last_stream_id {
+                        stats.last_stream_id = header.stream_id;
+                        stats.stats.streams_opened += 1;
+                        stats.open_streams.insert(header.stream_id, 0);
+                    }
+                    *header_block = fragment(&header, payload).map(<[u8]>::to_vec);
+                    if header.flags & END_HEADERS != 0 {
+                        stats.stats.hpack_table_entries += take_inserts(header_block);
+                    }
+                }
+                if header.flags & END_STREAM != 0 {
+                    stats.end_stream(header.stream_id, LOCAL_END);
+                }
+            }
+            CONTINUATION => {
+                match (header_block.as_mut(), fragment(&header, payload)) {
+                    (Some(block), Some(fragment)) => block.extend_from_slice(fragment),
+                    _ => *header_block = None,
+                }
+                if header.flags & END_HEADERS != 0 {
+                    stats.stats.hpack_table_entries += take_inserts(header_block);
+                }
+            }
+            RST_STREAM => {
+                stats.stats.streams_reset += 1;
+                stats.open_streams.remove(&header.stream_id);
+            }
+            PING if header.flags & ACK == 0 => stats.ping_sent = Some(Instant::now()),
+            _ => {}
+        });
+    }
+
+    /// Called with the bytes read from the connection.
+    pub(crate) fn on_read(&mut self, buf: &[u8]) {
+        if !matches!(self.state, State::Frames) || buf.is_empty() {
+            return;
+        }
+
+        let mut stats = lock(&self.stats);
+        stats.stats.bytes_received += buf.len() as u64;
+        let settings_ack = &mut self.settings_ack;
+        self.received
+            .read(buf, |header, payload| match header.kind {
+                HEADERS | DATA if header.flags & END_STREAM != 0 => {
+                    stats.end_stream(header.stream_id, REMOTE_END);
+                }
+                RST_STREAM => {
+                    stats.stats.streams_reset += 1;
+                    stats.open_streams.remove(&header.stream_id);
+                }
+                ORIGIN if header.stream_id == 0 && payload.len() == header.len => {
+                    stats
+                        .origins
+                        .get_or_insert_with(Vec::new)
+                        .extend(parse_origins(payload));
+                }
+                GOAWAY if payload.len() >= 8 => {
+                    let last = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
+                    stats.go_away_stream_id = Some(last & 0x7fff_ffff);
+                }
+                SETTINGS if header.flags & ACK != 0 => *settings_ack = SettingsAck::Done,
+                PING if header.flags & ACK != 0 => {
+                    if let Some(sent) = stats.ping_sent.take() {
+                        stats.stats.ping_rtt_ms = Some(sent.elapsed().as_secs_f64() * 1000.0);
+                    }
+                }
+                _ => {}
+            });
+    }
+
+    /// Fails once the server took too long to acknowledge the initial
+    /// `SETTINGS`, polled while the connection has nothing to read.
+    pub(crate) fn poll_settings_ack(&mut self, cx: &mut Context<'_>) -> Poll<io::Error> {
+        let SettingsAck::Waiting(timeout, ref mut sleep) = self.settings_ack else {
+            return Poll::Pending;
+        };
+        futures_core::ready!(sleep.as_mut().poll(cx));
+        self.settings_ack = SettingsAck::Done;
+        let err = SettingsAckTimeout { timeout };
+        log::debug!("{}", err);
+        Poll::Ready(io::Error::new(io::ErrorKind::TimedOut, err))
+    }
+}
+
+/// The server did not acknowledge the initial HTTP/2 `SETTINGS` in time.
+#[derive(Debug)]
+pub(crate) struct SettingsAckTimeout {
+    timeout: Duration,
+}
+
+impl SettingsAckTimeout {
+    const MESSAGE: &'static str = "HTTP/2 SETTINGS not acknowledged";
+
+    /// Whether `err` is, or was made from, a `SettingsAckTimeout`: h2 only
+    /// keeps the kind and message of the I/O errors of its connections.
+    pub(crate) fn is(err: &io::Error) -> bool {
+        err.kind() == io::ErrorKind::TimedOut
+            && err.get_ref().is_some_and(|inner| {
+                inner.is::<SettingsAckTimeout>()
+                    || inner.to_string().starts_with(SettingsAckTimeout::MESSAGE)
+            })
+    }
+}
+
+impl fmt::Display for SettingsAckTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} within {:?}",
+            SettingsAckTimeout::MESSAGE,
+            self.timeout
+        )
+    }
+}
+
+impl StdError for SettingsAckTimeout {}
+
+/// The header block fragment of a kept `HEADERS` or `CONTINUATION` frame,
+/// without its padding and priority. `None` if the frame was not kept or is
+/// malformed.
+fn fragment<'a>(header: &FrameHeader, payload: &'a [u8]) -> Option<&'a [u8]> {
+    if payload.len() != header.len {
+        return None;
+    }
+    if header.kind != HEADERS {
+        return Some(payload);
+    }
+    let (pad, payload) = if header.flags & PADDED != 0 {
+        let (&pad, payload) = payload.split_first()?;
+        (pad as usize, payload)
+    } else {
+        (0, payload)
+    };
+    let payload = if header.flags & PRIORITY_FLAG != 0 {
+        payload.get(5..)?
+    } else {
+        payload
+    };
+    payload.get(..payload.len().checked_sub(pad)?)
+}
+
+/// The dynamic table inserts of the complete header block, taken out of
+/// `block`.
+fn take_inserts(block: &mut Option<Vec<u8>>) -> u64 {
+    block.take().map_or(0, |block| hpack_inserts(&block))
+}
+
+/// Counts the literal fields with incremental indexing of an HPACK header
+/// block (RFC 7541, section 6), each added to the dynamic table. The count
+/// so far is returned if the block is malformed.
+fn hpack_inserts(mut block: &[u8]) -> u64 {
+    let mut inserts = 0;
+    while let Some(&first) = block.first() {
+        let (prefix, literal) = match first {
+            // Indexed field.
+            0x80..=0xff => (7, false),
+            // Literal with incremental indexing.
+            0x40..=0x7f => {
+                inserts += 1;
+                (6, true)
+            }
+            // Dynamic table size update.
+            0x20..=0x3f => (5, false),
+            // Literal without indexing, or never indexed.
+            _ => (4, true),
+        };
+        let Some((index, rest)) = hpack_int(block, prefix) else {
+            break;
+        };
+        block = rest;
+        if literal {
+            // A new name, then the value.
+            let strings = if index == 0 { 2 } else { 1 };
+            for _ in 0..strings {
+                let Some(rest) = hpack_int(block, 7)
+                    .and_then(|(len, rest)| rest.get(usize::try_from(len).ok()?..))
+                else {
+                    return inserts;
+                };
+                block = rest;
+            }
+        }
+    }
+    inserts
+}
+
+/// Decodes an HPACK integer with a `prefix` bits prefix, returning it and
+/// the bytes after it.
+fn hpack_int(buf: &[u8], prefix: u32) -> Option<(u64, &[u8])> {
+    let (&first, mut rest) = buf.split_first()?;
+    let max = (1u64 << prefix) - 1;
+    let mut value = u64::from(first) & max;
+    if value < max {
+        return Some((value, rest));
+    }
+    let mut shift = 0;
+    loop {
+        let (&byte, next) = rest.split_first()?;
+        rest = next;
+        value = value.checked_add(u64::from(byte & 0x7f).checked_shl(shift)?)?;
+        if byte & 0x80 == 0 {
+            return Some((value, rest));
+        }
+        shift += 7;
+        if shift > 56 {
+            return None;
+        }
+    }
+}
+
+/// The origins of the payload of an `ORIGIN` frame, each prefixed with
+/// its length. Malformed ones are left out.
+fn parse_origins(mut payload: &[u8]) -> Vec<String> {
+    let mut origins = Vec::new();
+    while payload.len() >= 2 {
+        let len = u16::from_be_bytes([payload[0], payload[1]]) as usize;
+        let Some(origin) = payload.get(2..2 + len) else {
+            break;
+        };
+        payload = &payload[2 + len..];
+        let origin = std::str::from_utf8(origin)
+            .ok()
+            .and_then(|origin| Url::parse(origin).ok())
+            .map(|url| url.origin())
+            .filter(|origin| origin.is_tuple());
+        if let Some(origin) = origin {
+            origins.push(origin.ascii_serialization());
+        }
+    }
+    origins
+}
+
+struct FrameHeader {
+    /// The length of the payload.
+    len: usize,
+    kind: u8,
+    flags: u8,
+    stream_id: u32,
+}
+
+/// Splits a stream of bytes into frames, keeping only their headers, and
+/// the payloads of some kinds of frames.
+#[derive(Default)]
+struct FrameReader {
+    header: [u8; 9],
+    header_len: usize,
+    /// The bytes of payload left to skip.
+    payload_left: usize,
+    /// The kinds of frames whose payloads are kept.
+    keep: &'static [u8],
+    /// The frame whose payload is being kept.
+    kept: Option<(FrameHeader, Vec<u8>)>,
+}
+
+impl FrameReader {
+    fn keeping(kinds: &'static [u8]) -> FrameReader {
+        FrameReader {
+            keep: kinds,
+            ..FrameReader::default()
+        }
+    }
+
+    /// Calls `on_frame` with the header of each frame, and its payload if
+    /// it is kept, once it is read. The payloads of kept frames larger than
+    /// `MAX_KEPT_PAYLOAD` are left empty.
+    fn read(&mut self, mut buf: &[u8], mut on_frame: impl FnMut(FrameHeader, &[u8])) {
+        while !buf.is_empty() {
+            if self.payload_left > 0 {
+                let n = buf.len().min(self.payload_left);
+                self.payload_left -= n;
+                if let Some((_, ref mut payload)) = self.kept {
+                    payload.extend_from_slice(&buf[..n]);
+                }
+                buf = &buf[n..];
+                if self.payload_left == 0 {
+                    if let Some((header, payload)) = self.kept.take() {
+                        on_frame(header, &payload);
+                    }
+                }
+                continue;
+            }
+
+            let n = buf.len().min(self.header.len() - self.header_len);
+            self.header[self.header_len..self.header_len + n].copy_from_slice(&buf[..n]);
+            self.header_len += n;
+            buf = &buf[n..];
+            if self.header_len < self.header.len() {
+                return;
+            }
+
+            let h = self.header;
+            self.header_len = 0;
+            self.payload_left = u32::from_be_bytes([0, h[0], h[1], h[2]]) as usize;
+            let header = FrameHeader {
+                len: self.payload_left,
+                kind: h[3],
+                flags: h[4],
+                stream_id: u32::from_be_bytes([h[5], h[6], h[7], h[8]]) & 0x7fff_ffff,
+            };
+            if !self.keep.contains(&header.kind)
+                || self.payload_left == 0
+                || self.payload_left > MAX_KEPT_PAYLOAD
+            {
+                on_frame(header, &[]);
+            } else {
+                self.kept = Some((header, Vec::with_capacity(self.payload_left)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(kind: u8, flags: u8, stream_id: u32, payload: &[u8]) -> Vec<u8> {
+        let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+        frame.extend_from_slice(&[kind, flags]);
+        frame.extend_from_slice(&stream_id.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn counts_frames_of_http2_connections() {
+        let registry = Arc::new(H2Registry::default());
+        let mut tracker = H2Tracker::new(
+            &registry,
+            &Uri::from_static("https://example.com:443/"),
+            None,
+        );
+
+        let mut sent = PREFACE.to_vec();
+        sent.extend(frame(HEADERS, 0x4, 1, b"hpack"));
+        sent.extend(frame(PING, 0, 0, &[0; 8]));
+        // Written in pieces that split the preface and the frame headers.
+        for chunk in sent.chunks(5) {
+            tracker.on_write(chunk);
+        }
+        tracker.on_write(&frame(HEADERS, 0x4, 3, b"hpack"));
+        // The trailers of stream 3.
+        tracker.on_write(&frame(HEADERS, 0x5, 3, b"hpack"));
+
+        let mut received = frame(PING, ACK, 0, &[0; 8]);
+        received.extend(frame(RST_STREAM, 0, 3, &[0, 0, 0, 8]));
+        tracker.on_read(&received);
+        assert_eq!(tracker.handle().go_away_stream_id(), None);
+        let go_away = frame(GOAWAY, 0, 0, &[0, 0, 0, 1, 0, 0, 0, 0]);
+        tracker.on_read(&go_away);
+        assert_eq!(tracker.handle().go_away_stream_id(), Some(1));
+
+        // Stream 1 is still open, stream 3 was reset.
+        assert_eq!(tracker.handle().open_streams(), Some(1));
+
+        let stats = registry.stats("https://example.com").unwrap();
+        assert_eq!(stats.streams_opened, 2);
+        assert_eq!(stats.streams_reset, 1);
+        assert!(stats.ping_rtt_ms.is_some());
+        assert_eq!(stats.bytes_sent, sent.len() as u64 + 2 * 14);
+        assert_eq!(
+            stats.bytes_received,
+            (received.len() + go_away.len()) as u64
+        );
+
+        drop(tracker);
+        assert_eq!(registry.stats("https://example.com"), None);
+    }
+
+    #[test]
+    fn counts_hpack_table_inserts() {
+        let registry = Arc::new(H2Registry::default());
+        let mut tracker =
+            H2Tracker::new(&registry, &Uri::from_static("https://example.com/"), None);
+        tracker.on_write(PREFACE);
+
+        let mut block = vec![0x82];
+        // `:authority`, indexed name, with incremental indexing.
+        block.extend_from_slice(b"\x41\x0bexample.com");
+        // A new name with incremental indexing.
+        block.extend_from_slice(b"\x40\x03foo\x03bar");
+        // `:path` without indexing, then the field added first.
+        block.extend_from_slice(b"\x04\x01/\xbf");
+
+        // Padded and with a priority, split over a `CONTINUATION`.
+        let (first, rest) = block.split_at(8);
+        let mut payload = vec![2];
+        payload.extend_from_slice(&[0, 0, 0, 0, 15]);
+        payload.extend_from_slice(first);
+        payload.extend_from_slice(&[0, 0]);
+        let mut sent = frame(HEADERS, PADDED | PRIORITY_FLAG, 1, &payload);
+        sent.extend(frame(CONTINUATION, END_HEADERS, 1, rest));
+        // A dynamic table size update, then one more insert.
+        sent.extend(frame(HEADERS, END_HEADERS, 3, b"\x3f\x01\x40\x01a\x01b"));
+        for chunk in sent.chunks(3) {
+            tracker.on_write(chunk);
+        }
+
+        let stats = registry.stats("https://example.com").unwrap();
+        assert_eq!(stats.streams_opened, 2);
+        assert_eq!(stats.hpack_table_entries, 3);
+    }
+
+    #[tokio::test]
+    async fn times_out_without_settings_ack() {
+        use std::future::poll_fn;
+
+        let registry = Arc::new(H2Registry::default());
+        let dst = Uri::from_static("https://example.com/");
+        let timeout = Some(Duration::from_millis(20));
+
+        let mut acked = H2Tracker::new(&registry, &dst, timeout);
+        acked.on_write(PREFACE);
+        acked.on_read(&frame(SETTINGS, ACK, 0, &[]));
+
+        let mut unacked = H2Tracker::new(&registry, &dst, timeout);
+        unacked.on_write(PREFACE);
+        unacked.on_read(&frame(SETTINGS, 0, 0, &[]));
+
+        let err = poll_fn(|cx| unacked.poll_settings_ack(cx)).await;
+        assert!(SettingsAckTimeout::is(&err));
+        // As h2 passes it on.
+        assert!(SettingsAckTimeout::is(&io::Error::new(
+            err.kind(),
+            err.to_string()
+        )));
+        assert!(poll_fn(|cx| Poll::Ready(acked.poll_settings_ack(cx).is_pending())).await);
+    }
+
+    #[test]
+    fn records_declared_origins() {
+        let registry = Arc::new(H2Registry::default());
+        let mut tracker =
+            H2Tracker::new(&registry, &Uri::from_static("https://example.com/"), None);
+        tracker.on_write(PREFACE);
+        assert_eq!(registry.origins("https://example.com"), None);
+
+        let mut payload = Vec::new();
+        for origin in [
+            "https://example.com",
+            "https://CDN.example.com:443",
+            "bogus",
+        ] {
+            payload.extend_from_slice(&(origin.len() as u16).to_be_bytes());
+            payload.extend_from_slice(origin.as_bytes());
+        }
+        let mut received = frame(ORIGIN, 0, 0, &payload);
+        // Only allowed on stream 0.
+        received.extend(frame(ORIGIN, 0, 1, &payload));
+        received.extend(frame(RST_STREAM, 0, 1, &[0, 0, 0, 8]));
+        for chunk in received.chunks(7) {
+            tracker.on_read(chunk);
+        }
+
+        assert_eq!(
+            registry.origins("https://example.com"),
+            Some(vec![
+                "https://example.com".to_owned(),
+                "https://cdn.example.com".to_owned(),
+            ])
+        );
+        let stats = registry.stats("https://example.com").unwrap();
+        assert_eq!(stats.streams_reset, 1);
+    }
+
+    #[test]
+    fn ignores_http1_connections() {
+        let registry = Arc::new(H2Registry::default());
+        let mut tracker = H2Tracker::new(&registry, &Uri::from_static("http://example.com/"), None);
+        tracker.on_write(b"GET / HTTP/1.1\r\n\r\n");
+        tracker.on_read(b"HTTP/1.1 200 OK\r\n\r\n");
+        assert_eq!(registry.stats("http://example.com"), None);
+        assert_eq!(tracker.handle().open_streams(), None);
+    }
+}
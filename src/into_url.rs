@@ -1,5 +1,25 @@
 use url::Url;
 
+/// How non-ASCII host names, such as `bücher.example`, are converted to the
+/// ASCII form used for DNS, TLS SNI and the `Host` header.
+///
+/// Set with [`ClientBuilder::idna`](crate::ClientBuilder::idna). Host names
+/// are converted as the request is built, URLs given as a parsed `Url` have
+/// already been converted with [`IdnaPolicy::NonTransitional`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IdnaPolicy {
+    /// UTS #46 transitional processing, which maps `ß` to `ss` and `ς` to
+    /// `σ`, and removes joiners, as IDNA 2003 did.
+    Transitional,
+    /// UTS #46 nontransitional processing, as done by browsers and the WHATWG
+    /// URL standard, which keeps `ß` and `ς`.
+    #[default]
+    NonTransitional,
+    /// Non-ASCII host names are rejected, with an error for which
+    /// [`Error::is_idna`](crate::Error::is_idna) is true.
+    Reject,
+}
+
 /// A trait to try to convert some type into a `Url`.
 ///
 /// This trait is "sealed", such that only types within rquest can
@@ -73,12 +93,91 @@ impl IntoUrlSealed for String {
     }
 }
 
+/// Parse `url`, resolving it against `base` if it is relative.
+///
+/// The path of `url` is appended to the one of `base`, so `/users` on
+/// `https://api.example.com/v2` gives `https://api.example.com/v2/users`.
+///
+/// A non-ASCII host name is converted following `idna`.
+pub(crate) fn resolve<U: IntoUrl>(
+    base: Option<&Url>,
+    url: U,
+    idna: IdnaPolicy,
+) -> crate::Result<Url> {
+    let host = raw_host(url.as_str())
+        .filter(|host| !host.is_ascii())
+        .map(str::to_owned);
+    let mut url = match base {
+        Some(base) if is_relative(url.as_str()) => join(base, url.as_str())?,
+        _ => url.into_url()?,
+    };
+
+    match (host, idna) {
+        (Some(host), IdnaPolicy::Reject) => {
+            Err(crate::error::builder(crate::error::IdnaRejected(host)).with_url(url))
+        }
+        (Some(host), IdnaPolicy::Transitional) if host.contains(DEVIATIONS) => {
+            let host = host
+                .replace(['ß', 'ẞ'], "ss")
+                .replace('ς', "σ")
+                .replace(['\u{200C}', '\u{200D}'], "");
+            url.set_host(Some(&host)).map_err(crate::error::builder)?;
+            Ok(url)
+        }
+        _ => Ok(url),
+    }
+}
+
+/// The characters that transitional and nontransitional processing map
+/// differently.
+const DEVIATIONS: [char; 5] = ['ß', 'ẞ', 'ς', '\u{200C}', '\u{200D}'];
+
+/// The host of `url` as written, if it is absolute.
+fn raw_host(url: &str) -> Option<&str> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '\\', '?', '#']).next()?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    // A port follows the last colon, unless it is within an IPv6 address.
+    Some(match host.rfind(':') {
+        Some(i) if !host[i..].contains(']') => &host[..i],
+        _ => host,
+    })
+}
+
+fn is_relative(url: &str) -> bool {
+    matches!(
+        Url::parse(url),
+        Err(url::ParseError::RelativeUrlWithoutBase)
+    )
+}
+
+fn join(base: &Url, relative: &str) -> crate::Result<Url> {
+    let path = format!(
+        "{}/{}",
+        base.path().trim_end_matches('/'),
+        relative.trim_start_matches('/')
+    );
+    base.join(&path).map_err(crate::error::builder)?.into_url()
+}
+
 pub(crate) fn expect_uri(url: &Url) -> http::Uri {
     url.as_str()
         .parse()
         .expect("a parsed Url should always be a valid Uri")
 }
 
+/// `uri` with its path and query replaced by `path_and_query`.
+pub(crate) fn with_path_and_query(
+    uri: http::Uri,
+    path_and_query: http::uri::PathAndQuery,
+) -> http::Uri {
+    let mut parts = uri.into_parts();
+    parts.path_and_query = Some(path_and_query);
+    http::Uri::from_parts(parts).expect("an absolute Uri with a new path should be valid")
+}
+
 pub(crate) fn try_uri(url: &Url) -> Option<http::Uri> {
     url.as_str().parse().ok()
 }
@@ -104,4 +203,85 @@ mod tests {
             "builder error for url (blob:https://example.com): URL scheme is not allowed"
         );
     }
+
+    #[test]
+    fn resolve_joins_base_path() {
+        let base = Url::parse("https://api.example.com/v2").unwrap();
+        let with_slash = Url::parse("https://api.example.com/v2/").unwrap();
+
+        for base in [&base, &with_slash] {
+            for path in ["users", "/users", "//users"] {
+                let url = resolve(Some(base), path, IdnaPolicy::default()).unwrap();
+                assert_eq!(url.as_str(), "https://api.example.com/v2/users");
+            }
+        }
+
+        let url = resolve(Some(&base), "/users?page=2#top", IdnaPolicy::default()).unwrap();
+        assert_eq!(url.as_str(), "https://api.example.com/v2/users?page=2#top");
+
+        let url = resolve(Some(&base), "", IdnaPolicy::default()).unwrap();
+        assert_eq!(url.as_str(), "https://api.example.com/v2/");
+
+        let root = Url::parse("https://api.example.com").unwrap();
+        let url = resolve(Some(&root), "users", IdnaPolicy::default()).unwrap();
+        assert_eq!(url.as_str(), "https://api.example.com/users");
+    }
+
+    #[test]
+    fn resolve_keeps_percent_encoded_segments() {
+        let base = Url::parse("https://api.example.com/files%2Fv1/").unwrap();
+
+        let url = resolve(Some(&base), "/a%2Fb/c d", IdnaPolicy::default()).unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.example.com/files%2Fv1/a%2Fb/c%20d"
+        );
+    }
+
+    #[test]
+    fn resolve_absolute_bypasses_base() {
+        let base = Url::parse("https://api.example.com/v2").unwrap();
+
+        let url = resolve(
+            Some(&base),
+            "http://other.example.com/users",
+            IdnaPolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(url.as_str(), "http://other.example.com/users");
+
+        let err = resolve(None, "/users", IdnaPolicy::default()).unwrap_err();
+        assert!(err.is_builder());
+    }
+
+    #[test]
+    fn resolve_applies_idna_policy() {
+        let url = resolve(None, "http://Bücher.example/", IdnaPolicy::default()).unwrap();
+        assert_eq!(url.host_str(), Some("xn--bcher-kva.example"));
+
+        let url = resolve(None, "http://💩.la:8080/", IdnaPolicy::default()).unwrap();
+        assert_eq!(url.as_str(), "http://xn--ls8h.la:8080/");
+
+        // Latin `p` with a Cyrillic `а`
+        let url = resolve(None, "https://аpple.com/", IdnaPolicy::default()).unwrap();
+        assert_eq!(url.host_str(), Some("xn--pple-43d.com"));
+
+        let url = resolve(None, "http://faß.de/", IdnaPolicy::NonTransitional).unwrap();
+        assert_eq!(url.host_str(), Some("xn--fa-hia.de"));
+        let url = resolve(None, "http://user@faß.de:81/ß", IdnaPolicy::Transitional).unwrap();
+        assert_eq!(url.as_str(), "http://user@fass.de:81/%C3%9F");
+
+        for url in ["http://💩.la/", "https://аpple.com/", "http://faß.de/"] {
+            let err = resolve(None, url, IdnaPolicy::Reject).unwrap_err();
+            assert!(err.is_idna(), "{url}");
+        }
+        // Only the host matters.
+        let url = resolve(None, "http://example.com/bücher", IdnaPolicy::Reject).unwrap();
+        assert_eq!(url.path(), "/b%C3%BCcher");
+        let url = resolve(None, "http://xn--bcher-kva.example/", IdnaPolicy::Reject).unwrap();
+        assert_eq!(url.host_str(), Some("xn--bcher-kva.example"));
+
+        let err = "http://a\u{FFFD}b.example/".into_url().unwrap_err();
+        assert!(err.is_idna());
+    }
 }
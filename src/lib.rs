@@ -288,6 +288,13 @@
 //! - Various parts of TLS can also be configured or even disabled on the
 //!   `ClientBuilder`.
 //!
+//! ## WASM
+//!
+//! On `wasm32` targets, requests go through the `fetch` API of the browser.
+//! Only the core of `Client`, `RequestBuilder` and `Response` is available
+//! there: the browser owns TLS, proxies and cookies, and impersonation is
+//! not possible.
+//!
 //! ## Optional Features
 //!
 //! The following are a list of [Cargo features][cargo-features] that can be
@@ -296,6 +303,12 @@
 //! - **boring-tls** *(enabled by default)*: Provides TLS support to connect
 //!   over HTTPS.
 //! - **websocket**: Provides websocket support.
+//! - **blocking**: Provides the [blocking][] client API.
+//! - **chaos**: Provides network fault injection in `testing::chaos`.
+//! - **mock**: Provides an in-memory transport for tests in `testing::mock`.
+//! - **vcr**: Provides recording and replaying of requests in `testing::vcr`.
+//! - **tracing**: Provides request spans, and request logging in `middleware`,
+//!   through `tracing`.
 //! - **cookies**: Provides cookie session support.
 //! - **gzip**: Provides response body gzip decompression.
 //! - **brotli**: Provides response body brotli decompression.
@@ -305,6 +318,11 @@
 //! - **multipart**: Provides functionality for multipart forms.
 //! - **stream**: Adds support for `futures::Stream`.
 //! - **socks**: Provides SOCKS5 proxy support.
+//! - **ntlm**: Provides NTLM authentication with proxies and servers.
+//! - **sigv4**: Provides AWS Signature Version 4 request signing in `sign::sigv4`.
+//! - **cache**: Provides an HTTP cache for clients in `cache`.
+//! - **impersonate-random**: Provides weighted random picking of an
+//!   [`Impersonate`](crate::tls::Impersonate) profile.
 //! - **hickory-dns**: Enables a hickory-dns async resolver instead of default
 //!   threadpool using `getaddrinfo`.
 //!
@@ -315,22 +333,39 @@
 //! [builder]: ./struct.RequestBuilder.html
 //! [serde]: http://serde.rs
 //! [redirect]: crate::redirect
+//! [blocking]: ./blocking/index.html
 //! [Proxy]: ./struct.Proxy.html
 //! [preconfigured]: ./struct.ClientBuilder.html#method.use_preconfigured_tls
 //! [cargo-features]: https://doc.rust-lang.org/stable/cargo/reference/manifest.html#the-features-section
 
-/// Re-export of boring to keep versions in check
-#[cfg(feature = "boring-tls")]
-pub use boring;
-#[cfg(feature = "boring-tls")]
-pub use boring_sys;
-#[cfg(feature = "hickory-dns")]
-pub use hickory_resolver;
+macro_rules! if_wasm {
+    ($($item:item)*) => {$(
+        #[cfg(target_arch = "wasm32")]
+        $item
+    )*}
+}
+
+macro_rules! if_hyper {
+    ($($item:item)*) => {$(
+        #[cfg(not(target_arch = "wasm32"))]
+        $item
+    )*}
+}
+
+if_hyper! {
+    /// Re-export of boring to keep versions in check
+    #[cfg(feature = "boring-tls")]
+    pub use boring;
+    #[cfg(feature = "boring-tls")]
+    pub use boring_sys;
+    #[cfg(feature = "hickory-dns")]
+    pub use hickory_resolver;
+    #[cfg(feature = "boring-tls")]
+    pub use tokio_boring;
+}
 pub use http::header;
 pub use http::Method;
 pub use http::{StatusCode, Version};
-#[cfg(feature = "boring-tls")]
-pub use tokio_boring;
 pub use url::Url;
 
 // universal mods
@@ -339,8 +374,8 @@ mod error;
 mod into_url;
 mod response;
 
-pub use self::error::{Error, Result};
-pub use self::into_url::IntoUrl;
+pub use self::error::{Error, Result, TimeoutPhase};
+pub use self::into_url::{IdnaPolicy, IntoUrl};
 pub use self::response::ResponseBuilderExt;
 
 /// Shortcut method to quickly make a `GET` request.
@@ -378,7 +413,7 @@ pub async fn get<T: IntoUrl>(url: T) -> crate::Result<Response> {
 ///
 /// This is a shorthand for creating a request, sending it, and turning the
 /// response into a websocket.
-#[cfg(feature = "websocket")]
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
 pub async fn websocket<T: IntoUrl>(url: T) -> crate::Result<WebSocket> {
     Client::builder()
         .build()?
@@ -408,32 +443,80 @@ fn _assert_impls() {
 
     assert_send::<Error>();
     assert_sync::<Error>();
+
+    #[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+    {
+        assert_send::<blocking::Client>();
+        assert_sync::<blocking::Client>();
+        assert_clone::<blocking::Client>();
+        assert_send::<blocking::Request>();
+        assert_send::<blocking::RequestBuilder>();
+        assert_send::<blocking::Response>();
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(target_arch = "wasm32")))]
 doc_comment::doctest!("../README.md");
 
-#[cfg(feature = "multipart")]
-pub use self::client::multipart;
-#[cfg(feature = "websocket")]
-pub use self::client::websocket::{
-    CloseCode, Message, WebSocket, WebSocketRequestBuilder, WebSocketResponse,
-};
-pub use self::client::{
-    Body, Client, ClientBuilder, HttpVersionPref, Request, RequestBuilder, Response, Upgraded,
-};
-pub use self::proxy::{NoProxy, Proxy};
+if_hyper! {
+    #[cfg(feature = "cache")]
+    pub use self::client::cache;
+    #[cfg(feature = "multipart")]
+    pub use self::client::multipart;
+    #[cfg(feature = "websocket")]
+    pub use self::client::websocket::{
+        CloseCode, CloseFrame, Message, WebSocket, WebSocketConnection, WebSocketPool,
+        WebSocketRequestBuilder, WebSocketResponse, WsBehaviorSettings, WsCompression, WsError,
+        WsMasking, WsSink, WsStream,
+    };
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    pub use self::client::Compression;
+    pub use self::client::{
+        AcceptEncoding, Body, Client, ClientBuilder, HedgedResponse, HttpVersionPref, RateLimit,
+        Request, RequestBuilder, RequestId, RequestIdPolicy, RequestTarget, Response,
+        ShutdownReport, Upgraded, ValidationError, ValidationWarning, ValidatorStore,
+    };
+    #[cfg(feature = "boring-tls")]
+    pub use self::alt_svc::{AltService, AltSvcCache};
+    pub use self::conn_info::ConnectionInfo;
+    pub use self::conn_limit::PoolStats;
+    pub use self::h2_stats::H2ConnectionStats;
+    pub use self::proxy::{NoProxy, Proxy};
 
-#[cfg(feature = "boring-tls")]
-pub use hyper::{PseudoOrder, SettingsOrder};
+    #[cfg(feature = "boring-tls")]
+    pub use hyper::{PseudoOrder, SettingsOrder};
+
+    pub mod auth;
+    #[cfg(feature = "blocking")]
+    pub mod blocking;
+    #[cfg(feature = "boring-tls")]
+    mod alt_svc;
+    mod client;
+    mod conn_info;
+    mod conn_limit;
+    mod connect;
+    #[cfg(feature = "cookies")]
+    pub mod cookie;
+    pub mod dns;
+    mod h2_stats;
+    pub mod metrics;
+    pub mod middleware;
+    #[cfg(feature = "ntlm")]
+    mod ntlm;
+    mod proxy;
+    pub mod redirect;
+    pub mod retry;
+    pub mod sign;
+    #[cfg(any(feature = "chaos", feature = "mock", feature = "vcr"))]
+    pub mod testing;
+    #[cfg(feature = "boring-tls")]
+    pub mod tls;
+}
+
+if_wasm! {
+    mod wasm;
+
+    pub use self::wasm::{Body, Client, ClientBuilder, Request, RequestBuilder, Response};
+}
 
-mod client;
-mod connect;
-#[cfg(feature = "cookies")]
-pub mod cookie;
-pub mod dns;
-mod proxy;
-pub mod redirect;
-#[cfg(feature = "boring-tls")]
-pub mod tls;
 mod util;
@@ -0,0 +1,595 @@
+//! Request Metrics
+//!
+//! A [`MetricsObserver`] set with
+//! [`ClientBuilder::metrics_observer`](crate::ClientBuilder::metrics_observer)
+//! is told how long each phase of every request took, once the request is
+//! complete. The same [`RequestMetrics`], as of when the response headers
+//! arrived, are added to the extensions of each response.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use rquest::metrics::{MetricsObserver, RequestMetrics};
+//!
+//! struct Log;
+//!
+//! impl MetricsObserver for Log {
+//!     fn on_request_complete(&self, metrics: RequestMetrics) {
+//!         println!("{} {}: {:?}", metrics.method(), metrics.url(), metrics.total());
+//!     }
+//! }
+//!
+//! # fn run() -> Result<(), rquest::Error> {
+//! let client = rquest::Client::builder()
+//!     .metrics_observer(Arc::new(Log))
+//!     .build()?;
+//! # drop(client);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use hyper::client::connect::CaptureConnection;
+use pin_project_lite::pin_project;
+use url::Url;
+
+use crate::{Method, Request, Response, TimeoutPhase, Version};
+
+/// Receives the metrics of the requests sent by a `Client`.
+pub trait MetricsObserver: Send + Sync {
+    /// Called exactly once per request: when its response body has been
+    /// read to the end, when it failed, or when the request or its response
+    /// was dropped before that.
+    ///
+    /// This is called from the task driving the request, it should not
+    /// block.
+    fn on_request_complete(&self, metrics: RequestMetrics);
+}
+
+/// The timing of the phases of a request.
+///
+/// A request includes the redirects it followed and the retries it made.
+/// The connection phases are those of the connection the final response
+/// was received on, and are only known if that connection was opened for
+/// this request.
+///
+/// On HTTP/2 connections, which are shared by concurrent requests, the
+/// queue and request write times are approximations.
+#[derive(Clone, Debug)]
+pub struct RequestMetrics {
+    method: Method,
+    url: Url,
+    queue: Option<Duration>,
+    dns: Option<Duration>,
+    connect: Option<Duration>,
+    tls: Option<Duration>,
+    request_write: Option<Duration>,
+    ttfb: Option<Duration>,
+    body_read: Option<Duration>,
+    total: Duration,
+    connection_reused: Option<bool>,
+    version: Option<Version>,
+    error: Option<ErrorKind>,
+}
+
+/// Why a request failed, in [`RequestMetrics::error`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The request could not be built.
+    Builder,
+    /// Connecting to the server failed.
+    Connect,
+    /// The request, or reading the response body, timed out.
+    Timeout,
+    /// Sending the request failed.
+    Request,
+    /// Following a redirect failed.
+    Redirect,
+    /// Reading the response body failed.
+    Body,
+    /// Decoding the response body failed.
+    Decode,
+    /// The request, or its response before the body was read to the end,
+    /// was dropped.
+    Canceled,
+    /// Any other error.
+    Other,
+}
+
+impl RequestMetrics {
+    /// The method of the request.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// The URL the request was sent to, before any redirect.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Time spent waiting for a connection, besides opening it.
+    pub fn queue(&self) -> Option<Duration> {
+        self.queue
+    }
+
+    /// Time spent resolving the host name of the new connection.
+    pub fn dns(&self) -> Option<Duration> {
+        self.dns
+    }
+
+    /// Time spent establishing the TCP connection, including through a
+    /// proxy.
+    pub fn connect(&self) -> Option<Duration> {
+        self.connect
+    }
+
+    /// Time spent in the TLS handshake of the new connection.
+    pub fn tls(&self) -> Option<Duration> {
+        self.tls
+    }
+
+    /// Time spent writing the request to the connection.
+    pub fn request_write(&self) -> Option<Duration> {
+        self.request_write
+    }
+
+    /// Time from the start of the request until the final response headers
+    /// arrived.
+    pub fn ttfb(&self) -> Option<Duration> {
+        self.ttfb
+    }
+
+    /// Time spent reading the response body, `None` until the request is
+    /// complete.
+    pub fn body_read(&self) -> Option<Duration> {
+        self.body_read
+    }
+
+    /// Time from the start of the request until it was complete, or until
+    /// the response headers arrived for the metrics in the response
+    /// extensions.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// Whether the response was received on a connection that had already
+    /// been used.
+    pub fn connection_reused(&self) -> Option<bool> {
+        self.connection_reused
+    }
+
+    /// The HTTP version of the response.
+    pub fn version(&self) -> Option<Version> {
+        self.version
+    }
+
+    /// Why the request failed, if it did.
+    pub fn error(&self) -> Option<ErrorKind> {
+        self.error
+    }
+}
+
+impl ErrorKind {
+    pub(crate) fn of(err: &crate::Error) -> ErrorKind {
+        if err.is_timeout() {
+            ErrorKind::Timeout
+        } else if err.is_connect() {
+            ErrorKind::Connect
+        } else if err.is_builder() {
+            ErrorKind::Builder
+        } else if err.is_redirect() {
+            ErrorKind::Redirect
+        } else if err.is_body() {
+            ErrorKind::Body
+        } else if err.is_decode() {
+            ErrorKind::Decode
+        } else if err.is_request() {
+            ErrorKind::Request
+        } else {
+            ErrorKind::Other
+        }
+    }
+}
+
+/// Follows a request from its start until it is complete, then reports its
+/// metrics, even if it is dropped.
+pub(crate) struct Recorder {
+    observer: Arc<dyn MetricsObserver>,
+    start: Instant,
+    headers: Option<Instant>,
+    metrics: Option<RequestMetrics>,
+}
+
+impl Recorder {
+    pub(crate) fn new(observer: Arc<dyn MetricsObserver>, req: &Request) -> Recorder {
+        Recorder {
+            observer,
+            start: Instant::now(),
+            headers: None,
+            metrics: Some(RequestMetrics {
+                method: req.method().clone(),
+                url: req.url().clone(),
+                queue: None,
+                dns: None,
+                connect: None,
+                tls: None,
+                request_write: None,
+                ttfb: None,
+                body_read: None,
+                total: Duration::ZERO,
+                connection_reused: None,
+                version: None,
+                error: None,
+            }),
+        }
+    }
+
+    /// Waits for the response of `send`, and hands the recorder over to its
+    /// body.
+    pub(crate) async fn observe<F>(mut self, send: F) -> crate::Result<Response>
+    where
+        F: Future<Output = crate::Result<Response>>,
+    {
+        let mut res = match send.await {
+            Ok(res) => res,
+            Err(err) => {
+                self.finish(Some(&err));
+                return Err(err);
+            }
+        };
+
+        let now = Instant::now();
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.ttfb = Some(now - self.start);
+            metrics.total = now - self.start;
+            metrics.version = Some(res.version());
+            if let Some(conn) = res.extensions().get::<ConnectMetrics>() {
+                conn.fill(metrics, self.start);
+            }
+            let snapshot = metrics.clone();
+            res.extensions_mut().insert(snapshot);
+        }
+        self.headers = Some(now);
+        res.record_body(self);
+        Ok(res)
+    }
+
+    pub(crate) fn finish(&mut self, err: Option<&crate::Error>) {
+        self.report(err.map(ErrorKind::of));
+    }
+
+    fn report(&mut self, error: Option<ErrorKind>) {
+        if let Some(mut metrics) = self.metrics.take() {
+            let now = Instant::now();
+            metrics.total = now - self.start;
+            metrics.body_read = self.headers.map(|headers| now - headers);
+            metrics.error = error;
+            self.observer.on_request_complete(metrics);
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.report(Some(ErrorKind::Canceled));
+    }
+}
+
+/// The phases a connection went through while it was opened.
+pub(crate) enum Phase {
+    Resolved,
+    Connected,
+    Handshaken,
+}
+
+thread_local! {
+    static CONNECTING: Cell<Option<Arc<ConnectTimer>>> = const { Cell::new(None) };
+}
+
+/// Records the instants a connection being opened reaches each `Phase`.
+///
+/// The connectors don't know about the request a connection is opened for,
+/// so the timer of the connection being polled is kept in a thread local,
+/// set by `Timed`, for them to `mark` the phases.
+pub(crate) struct ConnectTimer {
+    start: Instant,
+    phases: Mutex<[Option<Instant>; 3]>,
+}
+
+impl ConnectTimer {
+    pub(crate) fn new() -> Arc<ConnectTimer> {
+        Arc::new(ConnectTimer {
+            start: Instant::now(),
+            phases: Mutex::new([None; 3]),
+        })
+    }
+
+    /// Polls `connecting` with this timer set as the current one.
+    pub(crate) fn time<F: Future>(self: &Arc<Self>, connecting: F) -> Timed<F> {
+        Timed {
+            inner: connecting,
+            timer: self.clone(),
+        }
+    }
+
+    /// The phase the connection was in, from the last one it reached, for
+    /// when it timed out. `resolving` is whether its host is a name to
+    /// resolve rather than an address, `tls` whether it is a TLS one.
+    pub(crate) fn timeout_phase(&self, resolving: bool, tls: bool) -> TimeoutPhase {
+        match *self.phases.lock().expect("connect timer lock poisoned") {
+            [_, Some(_), _] if tls => TimeoutPhase::TlsHandshake,
+            [None, None, _] if resolving => TimeoutPhase::DnsResolution,
+            _ => TimeoutPhase::TcpConnect,
+        }
+    }
+
+    /// The metrics of the connection, once it is open.
+    pub(crate) fn finish(&self) -> ConnectMetrics {
+        let established = Instant::now();
+        let [resolved, connected, handshaken] =
+            *self.phases.lock().expect("connect timer lock poisoned");
+        let since = |from: Instant, to: Option<Instant>| to.map(|to| to - from);
+
+        ConnectMetrics(Arc::new(ConnState {
+            opening: established - self.start,
+            dns: since(self.start, resolved),
+            connect: since(resolved.unwrap_or(self.start), connected),
+            tls: connected.and_then(|connected| since(connected, handshaken)),
+            established,
+            reading: AtomicBool::new(true),
+            write_start: AtomicU64::new(0),
+            last_write: AtomicU64::new(0),
+            used: AtomicBool::new(false),
+        }))
+    }
+}
+
+/// Marks the connection currently being opened, if it is timed, as having
+/// reached `phase`, and emits the `tracing` event of the phase.
+pub(crate) fn mark(phase: Phase) {
+    #[cfg(feature = "tracing")]
+    match phase {
+        // Resolving has its own `rquest.dns` span.
+        Phase::Resolved => (),
+        Phase::Connected => tracing::debug!(target: "rquest", "tcp connected"),
+        Phase::Handshaken => tracing::debug!(target: "rquest", "tls handshake complete"),
+    }
+    CONNECTING.with(|current| {
+        let timer = current.take();
+        if let Some(ref timer) = timer {
+            let mut phases = timer.phases.lock().expect("connect timer lock poisoned");
+            phases[phase as usize] = Some(Instant::now());
+        }
+        current.set(timer);
+    });
+}
+
+/// Whether the connection currently being opened is timed.
+pub(crate) fn is_timing() -> bool {
+    CONNECTING.with(|current| {
+        let timer = current.take();
+        let timing = timer.is_some();
+        current.set(timer);
+        timing
+    })
+}
+
+pin_project! {
+    pub(crate) struct Timed<F> {
+        #[pin]
+        inner: F,
+        timer: Arc<ConnectTimer>,
+    }
+}
+
+impl<F: Future> Future for Timed<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        struct Restore(Option<Arc<ConnectTimer>>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CONNECTING.with(|current| current.set(self.0.take()));
+            }
+        }
+
+        let this = self.project();
+        let _restore =
+            Restore(CONNECTING.with(|current| current.replace(Some(this.timer.clone()))));
+        this.inner.poll(cx)
+    }
+}
+
+/// The metrics of an open connection, added to the extensions of the
+/// responses received on it.
+#[derive(Clone)]
+pub(crate) struct ConnectMetrics(Arc<ConnState>);
+
+struct ConnState {
+    opening: Duration,
+    dns: Option<Duration>,
+    connect: Option<Duration>,
+    tls: Option<Duration>,
+    established: Instant,
+    /// Whether the last I/O was a read, so the next write starts a request.
+    reading: AtomicBool,
+    /// Nanoseconds since `established`.
+    write_start: AtomicU64,
+    last_write: AtomicU64,
+    used: AtomicBool,
+}
+
+impl ConnectMetrics {
+    pub(crate) fn on_read(&self) {
+        self.0.reading.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn on_write(&self) {
+        let now = self.0.established.elapsed().as_nanos() as u64;
+        if self.0.reading.swap(false, Ordering::Relaxed) {
+            self.0.write_start.store(now, Ordering::Relaxed);
+        }
+        self.0.last_write.store(now, Ordering::Relaxed);
+    }
+
+    /// Whether a request started at `start` began being written.
+    fn writing_since(&self, start: Instant) -> bool {
+        let nanos = self.0.write_start.load(Ordering::Relaxed);
+        nanos > 0 && self.0.established + Duration::from_nanos(nanos) >= start
+    }
+
+    /// Fills in the connection phases of a request started at `start`,
+    /// whose response headers just arrived.
+    fn fill(&self, metrics: &mut RequestMetrics, start: Instant) {
+        let at = |nanos: &AtomicU64| {
+            self.0.established + Duration::from_nanos(nanos.load(Ordering::Relaxed))
+        };
+        let write_start = at(&self.0.write_start).max(start);
+        let waited = write_start - start;
+
+        let reused = self.0.used.swap(true, Ordering::Relaxed);
+        if reused {
+            metrics.queue = Some(waited);
+        } else {
+            metrics.queue = Some(waited.saturating_sub(self.0.opening));
+            metrics.dns = self.0.dns;
+            metrics.connect = self.0.connect;
+            metrics.tls = self.0.tls;
+        }
+        metrics.connection_reused = Some(reused);
+        metrics.request_write = Some(at(&self.0.last_write).saturating_duration_since(write_start));
+    }
+}
+
+/// How far the request sent last by a `PendingRequest` was written to its
+/// connection, to tell the phase it was in when its timeout elapsed.
+#[derive(Clone)]
+pub(crate) struct Written(Arc<WrittenState>);
+
+struct WrittenState {
+    start: Instant,
+    connection: Mutex<Option<CaptureConnection>>,
+    body_sent: AtomicBool,
+}
+
+impl Written {
+    pub(crate) fn new() -> Written {
+        Written(Arc::new(WrittenState {
+            start: Instant::now(),
+            connection: Mutex::new(None),
+            body_sent: AtomicBool::new(false),
+        }))
+    }
+
+    /// Captures the connection `req` is sent on, once hyper checks it out.
+    pub(crate) fn capture<B>(&self, req: &mut hyper::Request<B>) {
+        let connection = hyper::client::connect::capture_connection(req);
+        *self.0.connection.lock().expect("written lock poisoned") = Some(connection);
+    }
+
+    /// Records the whole request body was polled by the connection.
+    pub(crate) fn body_sent(&self) {
+        self.0.body_sent.store(true, Ordering::Relaxed);
+    }
+
+    /// The extra information of the connection the request was sent on, see
+    /// `Conn::connected`, once hyper checked it out.
+    pub(crate) fn connection_extras(&self) -> Option<http::Extensions> {
+        let connection = self.0.connection.lock().expect("written lock poisoned");
+        let connected = connection.as_ref()?.connection_metadata();
+        let mut extensions = http::Extensions::new();
+        connected.as_ref()?.get_extras(&mut extensions);
+        Some(extensions)
+    }
+
+    /// Keeps hyper from checking out the connection the request was sent on
+    /// from its pool again.
+    pub(crate) fn poison_connection(&self) {
+        let connection = self.0.connection.lock().expect("written lock poisoned");
+        if let Some(ref connection) = *connection {
+            if let Some(ref connected) = *connection.connection_metadata() {
+                connected.poison();
+            }
+        }
+    }
+
+    /// The phase the request was in: waiting for a connection, writing, or
+    /// waiting for the response headers once its body was sent.
+    pub(crate) fn timeout_phase(&self) -> TimeoutPhase {
+        let metrics = self
+            .connection_extras()
+            .and_then(|mut extensions| extensions.remove::<ConnectMetrics>());
+        match metrics {
+            None => TimeoutPhase::Total,
+            Some(metrics)
+                if metrics.writing_since(self.0.start)
+                    && self.0.body_sent.load(Ordering::Relaxed) =>
+            {
+                TimeoutPhase::ResponseHeader
+            }
+            Some(_) => TimeoutPhase::RequestWrite,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_are_split_by_marks() {
+        let timer = ConnectTimer::new();
+        let connecting = timer.time(async {
+            assert!(is_timing());
+            mark(Phase::Resolved);
+            mark(Phase::Connected);
+            mark(Phase::Handshaken);
+        });
+        futures_util::FutureExt::now_or_never(connecting).unwrap();
+        assert!(!is_timing());
+
+        let conn = timer.finish();
+        assert!(conn.0.dns.is_some());
+        assert!(conn.0.connect.is_some());
+        assert!(conn.0.tls.is_some());
+        assert!(conn.0.opening >= conn.0.dns.unwrap());
+    }
+
+    #[test]
+    fn marks_outside_a_timer_are_ignored() {
+        mark(Phase::Connected);
+
+        let conn = ConnectTimer::new().finish();
+        assert_eq!(conn.0.dns, None);
+        assert_eq!(conn.0.connect, None);
+        assert_eq!(conn.0.tls, None);
+    }
+
+    #[test]
+    fn second_response_reuses_the_connection() {
+        let conn = ConnectTimer::new().finish();
+        let req = Request::new(Method::GET, "http://a.test/".parse().unwrap());
+        let mut metrics = Recorder::new(Arc::new(Noop), &req).metrics.take().unwrap();
+
+        conn.fill(&mut metrics, Instant::now());
+        assert_eq!(metrics.connection_reused(), Some(false));
+        conn.fill(&mut metrics, Instant::now());
+        assert_eq!(metrics.connection_reused(), Some(true));
+    }
+
+    struct Noop;
+
+    impl MetricsObserver for Noop {
+        fn on_request_complete(&self, _: RequestMetrics) {}
+    }
+}
@@ -0,0 +1,360 @@
+use std::fmt::Write;
+use std::time::Instant;
+
+use bytes::Bytes;
+use http::header::{AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, SET_COOKIE};
+use http::{HeaderMap, HeaderName, HeaderValue};
+use tracing::Level;
+
+use crate::{Method, Response, Url};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// `tracing::event!` only takes a constant level.
+macro_rules! event {
+    ($level:expr, $($args:tt)+) => {
+        match $level {
+            Level::ERROR => tracing::event!(target: "rquest::middleware", Level::ERROR, $($args)+),
+            Level::WARN => tracing::event!(target: "rquest::middleware", Level::WARN, $($args)+),
+            Level::INFO => tracing::event!(target: "rquest::middleware", Level::INFO, $($args)+),
+            Level::DEBUG => tracing::event!(target: "rquest::middleware", Level::DEBUG, $($args)+),
+            _ => tracing::event!(target: "rquest::middleware", Level::TRACE, $($args)+),
+        }
+    };
+}
+
+/// Emits a `tracing` event for every request a `Client` sends, and for
+/// every response or error it gets back.
+///
+/// The events carry structured fields (`http.method`, `http.url`,
+/// `http.status`, `http.headers`, `http.body`, `elapsed_ms`, ...) under the
+/// `rquest::middleware` target. Redirects are followed before the response
+/// is logged, so each `send()` produces one request and one response event.
+///
+/// # Example
+///
+/// ```rust
+/// use rquest::middleware::LoggingMiddleware;
+///
+/// # fn run() -> Result<(), rquest::Error> {
+/// let logging = LoggingMiddleware::builder()
+///     .log_level(tracing::Level::INFO)
+///     .log_request_body(true)
+///     .max_body_bytes(1024)
+///     .build();
+///
+/// let client = rquest::Client::builder().logging(logging).build()?;
+/// # drop(client);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct LoggingMiddleware {
+    level: Level,
+    log_request_body: bool,
+    log_response_body: bool,
+    redact_headers: Vec<HeaderName>,
+    #[cfg(feature = "json")]
+    redact_body_fields: Vec<String>,
+    max_body_bytes: usize,
+}
+
+/// A builder for [`LoggingMiddleware`].
+#[must_use]
+#[derive(Debug)]
+pub struct LoggingMiddlewareBuilder(LoggingMiddleware);
+
+impl LoggingMiddleware {
+    /// Constructs a [`LoggingMiddlewareBuilder`].
+    pub fn builder() -> LoggingMiddlewareBuilder {
+        LoggingMiddlewareBuilder(LoggingMiddleware::default())
+    }
+
+    pub(crate) fn log_request(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: &HeaderMap,
+        body: Option<Option<&Bytes>>,
+    ) {
+        let headers = self.redact(headers);
+        let body = match body {
+            _ if !self.log_request_body => None,
+            Some(Some(bytes)) => Some(self.format_body(bytes)),
+            Some(None) => Some("<stream>".to_owned()),
+            None => Some(String::new()),
+        };
+
+        event!(
+            self.level,
+            http.method = %method,
+            http.url = %url,
+            http.headers = ?headers,
+            http.body = body.as_deref(),
+            "sending request"
+        );
+    }
+
+    pub(crate) async fn log_response(
+        &self,
+        method: Method,
+        url: Url,
+        started: Instant,
+        res: crate::Result<Response>,
+    ) -> crate::Result<Response> {
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+
+        let res = match res {
+            Ok(res) => res,
+            Err(err) => {
+                event!(
+                    self.level,
+                    http.method = %method,
+                    http.url = %url,
+                    elapsed_ms,
+                    error = %err,
+                    "request failed"
+                );
+                return Err(err);
+            }
+        };
+
+        let (res, body) = if self.log_response_body {
+            let (res, bytes) = res.buffered().await?;
+            (res, Some(self.format_body(&bytes)))
+        } else {
+            (res, None)
+        };
+
+        let headers = self.redact(res.headers());
+        event!(
+            self.level,
+            http.method = %method,
+            http.url = %res.url(),
+            http.status = res.status().as_u16(),
+            http.version = ?res.version(),
+            http.headers = ?headers,
+            http.body = body.as_deref(),
+            elapsed_ms,
+            "received response"
+        );
+
+        Ok(res)
+    }
+
+    fn redact(&self, headers: &HeaderMap) -> HeaderMap {
+        let mut headers = headers.clone();
+        for name in &self.redact_headers {
+            if let http::header::Entry::Occupied(mut entry) = headers.entry(name) {
+                for value in entry.iter_mut() {
+                    *value = HeaderValue::from_static(REDACTED);
+                }
+            }
+        }
+        headers
+    }
+
+    fn format_body(&self, bytes: &Bytes) -> String {
+        #[cfg(feature = "json")]
+        let redacted;
+        #[cfg(feature = "json")]
+        let bytes = match self.redact_json(bytes) {
+            Some(json) => {
+                redacted = Bytes::from(json);
+                &redacted
+            }
+            None => bytes,
+        };
+
+        let truncated = bytes.len() > self.max_body_bytes;
+        let head = &bytes[..bytes.len().min(self.max_body_bytes)];
+
+        let mut out = match std::str::from_utf8(head) {
+            Ok(text) => text.to_owned(),
+            // a cut multi-byte character is still text
+            Err(e) if truncated && e.error_len().is_none() => {
+                String::from_utf8_lossy(&head[..e.valid_up_to()]).into_owned()
+            }
+            Err(_) => {
+                let mut hex = String::with_capacity(head.len() * 2 + 4);
+                hex.push_str("hex:");
+                for byte in head {
+                    let _ = write!(hex, "{:02x}", byte);
+                }
+                hex
+            }
+        };
+
+        if truncated {
+            let _ = write!(out, "... ({} bytes total)", bytes.len());
+        }
+        out
+    }
+
+    #[cfg(feature = "json")]
+    fn redact_json(&self, bytes: &[u8]) -> Option<Vec<u8>> {
+        fn walk(value: &mut serde_json::Value, fields: &[String]) {
+            match value {
+                serde_json::Value::Object(map) => {
+                    for (key, value) in map.iter_mut() {
+                        if fields.iter().any(|f| f == key) {
+                            *value = serde_json::Value::from(REDACTED);
+                        } else {
+                            walk(value, fields);
+                        }
+                    }
+                }
+                serde_json::Value::Array(values) => {
+                    for value in values {
+                        walk(value, fields);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if self.redact_body_fields.is_empty() {
+            return None;
+        }
+        let mut value = serde_json::from_slice(bytes).ok()?;
+        walk(&mut value, &self.redact_body_fields);
+        serde_json::to_vec(&value).ok()
+    }
+}
+
+impl Default for LoggingMiddleware {
+    fn default() -> Self {
+        LoggingMiddleware {
+            level: Level::DEBUG,
+            log_request_body: false,
+            log_response_body: false,
+            redact_headers: vec![AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE, SET_COOKIE],
+            #[cfg(feature = "json")]
+            redact_body_fields: Vec::new(),
+            max_body_bytes: 4096,
+        }
+    }
+}
+
+impl LoggingMiddlewareBuilder {
+    /// Sets the level of the emitted events.
+    ///
+    /// Default is `DEBUG`.
+    pub fn log_level(mut self, level: Level) -> Self {
+        self.0.level = level;
+        self
+    }
+
+    /// Include the request body in the request event.
+    ///
+    /// Streaming bodies are logged as `<stream>`, since reading them here
+    /// would consume them.
+    ///
+    /// Default is `false`.
+    pub fn log_request_body(mut self, enabled: bool) -> Self {
+        self.0.log_request_body = enabled;
+        self
+    }
+
+    /// Include the response body in the response event.
+    ///
+    /// The body is read in full before the response is returned, so the
+    /// response no longer streams.
+    ///
+    /// Default is `false`.
+    pub fn log_response_body(mut self, enabled: bool) -> Self {
+        self.0.log_response_body = enabled;
+        self
+    }
+
+    /// Sets the headers whose values are logged as `[REDACTED]`.
+    ///
+    /// This replaces the default list, which is `Authorization`,
+    /// `Proxy-Authorization`, `Cookie` and `Set-Cookie`.
+    pub fn redact_headers(mut self, headers: Vec<HeaderName>) -> Self {
+        self.0.redact_headers = headers;
+        self
+    }
+
+    /// Sets the JSON object fields whose values are logged as `[REDACTED]`,
+    /// at any depth of a JSON body.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature to be enabled.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn redact_body_fields(mut self, fields: Vec<String>) -> Self {
+        self.0.redact_body_fields = fields;
+        self
+    }
+
+    /// Sets how many bytes of a body are logged before it is truncated.
+    ///
+    /// Bodies that are not UTF-8 are logged as hex.
+    ///
+    /// Default is 4096.
+    pub fn max_body_bytes(mut self, max: usize) -> Self {
+        self.0.max_body_bytes = max;
+        self
+    }
+
+    /// Returns the configured [`LoggingMiddleware`].
+    pub fn build(self) -> LoggingMiddleware {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        headers.append(SET_COOKIE, HeaderValue::from_static("a=1"));
+        headers.append(SET_COOKIE, HeaderValue::from_static("b=2"));
+        headers.insert("x-visible", HeaderValue::from_static("yes"));
+
+        let redacted = LoggingMiddleware::default().redact(&headers);
+        assert_eq!(redacted[AUTHORIZATION], REDACTED);
+        assert!(redacted.get_all(SET_COOKIE).iter().all(|v| v == REDACTED));
+        assert_eq!(redacted.get_all(SET_COOKIE).iter().count(), 2);
+        assert_eq!(redacted["x-visible"], "yes");
+    }
+
+    #[test]
+    fn truncates_and_hex_encodes_bodies() {
+        let logging = LoggingMiddleware::builder().max_body_bytes(4).build();
+
+        assert_eq!(logging.format_body(&Bytes::from_static(b"abc")), "abc");
+        assert_eq!(
+            logging.format_body(&Bytes::from_static(b"abcdef")),
+            "abcd... (6 bytes total)"
+        );
+        assert_eq!(
+            logging.format_body(&Bytes::from_static(b"\xff\x00\x01")),
+            "hex:ff0001"
+        );
+        // "é" is cut in half by the limit
+        assert_eq!(
+            logging.format_body(&Bytes::from("abcé")),
+            "abc... (5 bytes total)"
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn redacts_json_fields() {
+        let logging = LoggingMiddleware::builder()
+            .redact_body_fields(vec!["password".into()])
+            .build();
+
+        let body = Bytes::from_static(br#"{"user":"a","nested":[{"password":"hunter2"}]}"#);
+        assert_eq!(
+            logging.format_body(&body),
+            r#"{"nested":[{"password":"[REDACTED]"}],"user":"a"}"#
+        );
+    }
+}
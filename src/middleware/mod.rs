@@ -0,0 +1,13 @@
+//! Behavior shared by every request of a `Client`.
+//!
+//! Any tower layer can wrap the requests of a `Client`, see
+//! [`ClientBuilder::layer`](crate::ClientBuilder::layer). This module has
+//! the service those layers wrap, and the middleware rquest provides.
+
+#[cfg(feature = "tracing")]
+mod logging;
+mod service;
+
+#[cfg(feature = "tracing")]
+pub use self::logging::{LoggingMiddleware, LoggingMiddlewareBuilder};
+pub use self::service::ClientService;
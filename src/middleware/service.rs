@@ -0,0 +1,131 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tower_service::Service;
+
+use crate::error::BoxError;
+use crate::{Client, Request, Response};
+
+type BoxFuture = Pin<Box<dyn Future<Output = crate::Result<Response>> + Send>>;
+
+/// The service the layers added with
+/// [`ClientBuilder::layer`](crate::ClientBuilder::layer) wrap.
+///
+/// The innermost one sends the request, following redirects and handling
+/// cookies. Every layer's service is erased back into a `ClientService`
+/// for the next layer to wrap.
+pub struct ClientService {
+    inner: Box<dyn CloneService>,
+}
+
+impl ClientService {
+    pub(crate) fn new<S>(service: S) -> ClientService
+    where
+        S: Service<Request, Response = Response> + Clone + Send + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        ClientService {
+            inner: Box::new(Erased(service)),
+        }
+    }
+
+    /// The service sending requests for `client`, which must not have
+    /// layers itself.
+    pub(crate) fn sender(client: Client) -> ClientService {
+        ClientService::new(Sender(client))
+    }
+}
+
+impl Service<Request> for ClientService {
+    type Response = Response;
+    type Error = crate::Error;
+    type Future = BoxFuture;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+impl Clone for ClientService {
+    fn clone(&self) -> Self {
+        ClientService {
+            inner: self.inner.clone_box(),
+        }
+    }
+}
+
+impl fmt::Debug for ClientService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientService").finish_non_exhaustive()
+    }
+}
+
+trait CloneService:
+    Service<Request, Response = Response, Error = crate::Error, Future = BoxFuture> + Send
+{
+    fn clone_box(&self) -> Box<dyn CloneService>;
+}
+
+#[derive(Clone)]
+struct Erased<S>(S);
+
+impl<S> Service<Request> for Erased<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = crate::Error;
+    type Future = BoxFuture;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx).map_err(into_error)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let fut = self.0.call(req);
+        Box::pin(async move { fut.await.map_err(into_error) })
+    }
+}
+
+impl<S> CloneService for Erased<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+{
+    fn clone_box(&self) -> Box<dyn CloneService> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone)]
+struct Sender(Client);
+
+impl Service<Request> for Sender {
+    type Response = Response;
+    type Error = crate::Error;
+    type Future = crate::client::http::Pending;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        self.0.send_request(req)
+    }
+}
+
+/// The client's own errors pass through, those of other layers become
+/// request errors.
+fn into_error(err: impl Into<BoxError>) -> crate::Error {
+    crate::error::request_boxed(err.into())
+}
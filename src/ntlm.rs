@@ -0,0 +1,399 @@
+//! NTLMv2 authentication, as of MS-NLMP.
+//!
+//! NTLM authenticates a connection rather than a request: the negotiate
+//! message, the server challenge and the answer to it are exchanged on the
+//! same connection, which is then authenticated for the requests following.
+
+use std::fmt;
+use std::future::Future;
+use std::os::raw::c_void;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use boring_sys as ffi;
+use http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use http::HeaderValue;
+use log::trace;
+
+use crate::{Request, Response, StatusCode};
+
+const SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+
+const NEGOTIATE_UNICODE: u32 = 0x0000_0001;
+const NEGOTIATE_OEM: u32 = 0x0000_0002;
+const REQUEST_TARGET: u32 = 0x0000_0004;
+const NEGOTIATE_NTLM: u32 = 0x0000_0200;
+const NEGOTIATE_ALWAYS_SIGN: u32 = 0x0000_8000;
+const NEGOTIATE_EXTENDED_SESSION_SECURITY: u32 = 0x0008_0000;
+const NEGOTIATE_128: u32 = 0x2000_0000;
+const NEGOTIATE_56: u32 = 0x8000_0000;
+
+const NEGOTIATE_FLAGS: u32 = NEGOTIATE_UNICODE
+    | NEGOTIATE_OEM
+    | REQUEST_TARGET
+    | NEGOTIATE_NTLM
+    | NEGOTIATE_ALWAYS_SIGN
+    | NEGOTIATE_EXTENDED_SESSION_SECURITY
+    | NEGOTIATE_128
+    | NEGOTIATE_56;
+
+const AV_EOL: u16 = 0;
+const AV_TIMESTAMP: u16 = 7;
+
+/// The difference between the Windows and the Unix epochs, in 100ns.
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+/// The credentials of an NTLM authentication.
+#[derive(Clone)]
+pub struct NtlmCredentials {
+    username: String,
+    password: String,
+    domain: String,
+}
+
+/// The `CHALLENGE_MESSAGE` of a server.
+struct Challenge {
+    flags: u32,
+    server_challenge: [u8; 8],
+    target_info: Vec<u8>,
+}
+
+impl NtlmCredentials {
+    pub(crate) fn new(username: String, password: String, domain: String) -> NtlmCredentials {
+        NtlmCredentials {
+            username,
+            password,
+            domain,
+        }
+    }
+
+    /// The `NTLM` header value starting the exchange.
+    pub(crate) fn negotiate(&self) -> HeaderValue {
+        let mut msg = Vec::with_capacity(32);
+        msg.extend_from_slice(SIGNATURE);
+        msg.extend_from_slice(&1u32.to_le_bytes());
+        msg.extend_from_slice(&NEGOTIATE_FLAGS.to_le_bytes());
+        // Empty domain and workstation names.
+        msg.extend_from_slice(&[0; 16]);
+        header_value(&msg)
+    }
+
+    /// The `NTLM` header value answering the challenge of one of `values`,
+    /// the `WWW-Authenticate` or `Proxy-Authenticate` values of a response.
+    pub(crate) fn authenticate<'a, I>(&self, values: I) -> Option<HeaderValue>
+    where
+        I: IntoIterator<Item = &'a HeaderValue>,
+    {
+        let challenge = values
+            .into_iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| {
+                let (scheme, token) = value.trim().split_once(' ')?;
+                scheme.eq_ignore_ascii_case("ntlm").then_some(token)
+            })
+            .filter_map(|token| BASE64_STANDARD.decode(token.trim()).ok())
+            .find_map(|msg| Challenge::parse(&msg))?;
+
+        let client_challenge = crate::util::fast_random().to_le_bytes();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64
+            / 100
+            + FILETIME_UNIX_EPOCH;
+        Some(header_value(&self.authenticate_message(
+            &challenge,
+            client_challenge,
+            now,
+        )))
+    }
+
+    /// The `AUTHENTICATE_MESSAGE` answering `challenge` with an NTLMv2 response.
+    fn authenticate_message(
+        &self,
+        challenge: &Challenge,
+        client_challenge: [u8; 8],
+        now: u64,
+    ) -> Vec<u8> {
+        let response_key = self.response_key();
+        let timestamp = challenge.timestamp();
+
+        let mut temp = vec![1, 1, 0, 0, 0, 0, 0, 0];
+        temp.extend_from_slice(&timestamp.unwrap_or(now).to_le_bytes());
+        temp.extend_from_slice(&client_challenge);
+        temp.extend_from_slice(&[0; 4]);
+        temp.extend_from_slice(&challenge.target_info);
+        temp.extend_from_slice(&[0; 4]);
+
+        let nt_proof = hmac_md5(&response_key, &[&challenge.server_challenge[..], &temp]);
+        let nt_response = [&nt_proof[..], &temp].concat();
+
+        // The LMv2 response is left out when the server sent its time.
+        let lm_response = match timestamp {
+            Some(_) => vec![0; 24],
+            None => {
+                let proof = hmac_md5(
+                    &response_key,
+                    &[&challenge.server_challenge[..], &client_challenge],
+                );
+                [&proof[..], &client_challenge].concat()
+            }
+        };
+
+        let unicode = challenge.flags & NEGOTIATE_UNICODE != 0;
+        let encode = |s: &str| match unicode {
+            true => utf16le(s),
+            false => s.as_bytes().to_vec(),
+        };
+        let mut flags = challenge.flags & NEGOTIATE_FLAGS;
+        if unicode {
+            flags &= !NEGOTIATE_OEM;
+        }
+
+        // LM and NT responses, domain, user, workstation and session key.
+        let fields = [
+            lm_response,
+            nt_response,
+            encode(&self.domain),
+            encode(&self.username),
+            Vec::new(),
+            Vec::new(),
+        ];
+
+        let header_len = 8 + 4 + 8 * fields.len() + 4;
+        let mut msg = Vec::with_capacity(header_len + fields.iter().map(Vec::len).sum::<usize>());
+        msg.extend_from_slice(SIGNATURE);
+        msg.extend_from_slice(&3u32.to_le_bytes());
+        let mut offset = header_len;
+        for field in &fields {
+            msg.extend_from_slice(&(field.len() as u16).to_le_bytes());
+            msg.extend_from_slice(&(field.len() as u16).to_le_bytes());
+            msg.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset += field.len();
+        }
+        msg.extend_from_slice(&flags.to_le_bytes());
+        for field in &fields {
+            msg.extend_from_slice(field);
+        }
+        msg
+    }
+
+    /// `NTOWFv2`, the key of the responses.
+    fn response_key(&self) -> [u8; 16] {
+        let password = utf16le(&self.password);
+        let mut nt_hash = [0; 16];
+        unsafe { ffi::MD4(password.as_ptr(), password.len(), nt_hash.as_mut_ptr()) };
+
+        let identity = utf16le(&format!("{}{}", self.username.to_uppercase(), self.domain));
+        hmac_md5(&nt_hash, &[&identity])
+    }
+}
+
+impl fmt::Debug for NtlmCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NtlmCredentials")
+            .field("username", &self.username)
+            .field("domain", &self.domain)
+            .finish()
+    }
+}
+
+impl Challenge {
+    fn parse(msg: &[u8]) -> Option<Challenge> {
+        if !msg.starts_with(SIGNATURE) || u32_le(msg, 8)? != 2 {
+            return None;
+        }
+
+        let flags = u32_le(msg, 20)?;
+        let server_challenge = msg.get(24..32)?.try_into().ok()?;
+        // Old servers end the message before the target information.
+        let target_info = match msg.len() >= 48 {
+            true => {
+                let len = u16_le(msg, 40)? as usize;
+                let offset = u32_le(msg, 44)? as usize;
+                msg.get(offset..offset.checked_add(len)?)?.to_vec()
+            }
+            false => Vec::new(),
+        };
+
+        Some(Challenge {
+            flags,
+            server_challenge,
+            target_info,
+        })
+    }
+
+    /// The `MsvAvTimestamp` of the target information.
+    fn timestamp(&self) -> Option<u64> {
+        let mut info = &self.target_info[..];
+        while info.len() >= 4 {
+            let id = u16_le(info, 0)?;
+            let len = u16_le(info, 2)? as usize;
+            let value = info.get(4..4 + len)?;
+            match id {
+                AV_EOL => return None,
+                AV_TIMESTAMP => return Some(u64::from_le_bytes(value.try_into().ok()?)),
+                _ => info = &info[4 + len..],
+            }
+        }
+        None
+    }
+}
+
+/// Send `req` with `send`, authenticating the connection with NTLM.
+///
+/// The request is sent with the negotiate message. A `401 Unauthorized`
+/// response with an NTLM challenge is read to the end, so its connection
+/// goes back to the pool to be picked up again, and the request sent once
+/// more with the answer. If the request body can not be cloned, it is sent
+/// once as is.
+pub(crate) async fn send<F, Fut>(
+    credentials: &NtlmCredentials,
+    mut req: Request,
+    send: F,
+) -> crate::Result<Response>
+where
+    F: Fn(Request) -> Fut,
+    Fut: Future<Output = crate::Result<Response>>,
+{
+    let Some(mut retry) = req.try_clone() else {
+        return send(req).await;
+    };
+
+    req.headers_mut()
+        .insert(AUTHORIZATION, credentials.negotiate());
+    let res = send(req).await?;
+    if res.status() != StatusCode::UNAUTHORIZED {
+        return Ok(res);
+    }
+
+    let Some(auth) = credentials.authenticate(res.headers().get_all(WWW_AUTHENTICATE)) else {
+        return Ok(res);
+    };
+    trace!("answering ntlm challenge for {}", retry.url());
+    res.bytes().await?;
+    retry.headers_mut().insert(AUTHORIZATION, auth);
+    send(retry).await
+}
+
+fn header_value(msg: &[u8]) -> HeaderValue {
+    let mut value = HeaderValue::from_str(&format!("NTLM {}", BASE64_STANDARD.encode(msg)))
+        .expect("base64 is always valid HeaderValue");
+    value.set_sensitive(true);
+    value
+}
+
+fn hmac_md5(key: &[u8], data: &[&[u8]]) -> [u8; 16] {
+    let data = data.concat();
+    let mut out = [0; 16];
+    let mut out_len = 0;
+    unsafe {
+        ffi::HMAC(
+            ffi::EVP_md5(),
+            key.as_ptr() as *const c_void,
+            key.len(),
+            data.as_ptr(),
+            data.len(),
+            out.as_mut_ptr(),
+            &mut out_len,
+        )
+    };
+    out
+}
+
+fn utf16le(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+fn u16_le(buf: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(buf.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_le(buf: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(buf.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn credentials() -> NtlmCredentials {
+        NtlmCredentials::new("User".into(), "Password".into(), "Domain".into())
+    }
+
+    // MS-NLMP 4.2.4, NTLMv2 authentication.
+    fn challenge() -> Challenge {
+        let mut target_info = Vec::new();
+        for (id, name) in [(2u16, "Domain"), (1, "Server")] {
+            let name = utf16le(name);
+            target_info.extend_from_slice(&id.to_le_bytes());
+            target_info.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            target_info.extend_from_slice(&name);
+        }
+        target_info.extend_from_slice(&[0; 4]);
+
+        Challenge {
+            flags: 0xe28a_8233,
+            server_challenge: [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef],
+            target_info,
+        }
+    }
+
+    #[test]
+    fn answer_ms_nlmp_example() {
+        let credentials = credentials();
+        assert_eq!(
+            hex(&credentials.response_key()),
+            "0c868a403bfd7a93a3001ef22ef02e3f"
+        );
+
+        let msg = credentials.authenticate_message(&challenge(), [0xaa; 8], 0);
+        assert!(msg.starts_with(SIGNATURE));
+        assert_eq!(u32_le(&msg, 8), Some(3));
+
+        let field = |at: usize| {
+            let len = u16_le(&msg, at).unwrap() as usize;
+            let offset = u32_le(&msg, at + 4).unwrap() as usize;
+            &msg[offset..offset + len]
+        };
+        assert_eq!(
+            hex(field(12)),
+            "86c35097ac9cec102554764a57cccc19aaaaaaaaaaaaaaaa"
+        );
+        assert_eq!(hex(&field(20)[..16]), "68cd0ab851e51c96aabc927bebef6a1c");
+        assert_eq!(field(28), &utf16le("Domain")[..]);
+        assert_eq!(field(36), &utf16le("User")[..]);
+    }
+
+    #[test]
+    fn parse_challenge_message() {
+        let mut msg = SIGNATURE.to_vec();
+        msg.extend_from_slice(&2u32.to_le_bytes());
+        // Empty target name.
+        msg.extend_from_slice(&[0, 0, 0, 0, 48, 0, 0, 0]);
+        msg.extend_from_slice(&NEGOTIATE_FLAGS.to_le_bytes());
+        msg.extend_from_slice(&[7; 8]);
+        msg.extend_from_slice(&[0; 8]);
+        // A timestamp and the end of the target information.
+        msg.extend_from_slice(&[16, 0, 16, 0, 48, 0, 0, 0]);
+        msg.extend_from_slice(&[7, 0, 8, 0]);
+        msg.extend_from_slice(&42u64.to_le_bytes());
+        msg.extend_from_slice(&[0; 4]);
+
+        let challenge = Challenge::parse(&msg).unwrap();
+        assert_eq!(challenge.server_challenge, [7; 8]);
+        assert_eq!(challenge.timestamp(), Some(42));
+
+        let value =
+            HeaderValue::from_str(&format!("NTLM {}", BASE64_STANDARD.encode(&msg))).unwrap();
+        let basic = HeaderValue::from_static("Basic realm=\"x\"");
+        let auth = credentials().authenticate([&basic, &value]).unwrap();
+        assert!(auth.to_str().unwrap().starts_with("NTLM TlRMTVNTUAADAAAA"));
+
+        assert!(Challenge::parse(&msg[..20]).is_none());
+    }
+}
@@ -4,6 +4,8 @@ use std::net::SocketAddr;
 use std::sync::{Arc, LazyLock};
 
 use crate::into_url::{IntoUrl, IntoUrlSealed};
+#[cfg(feature = "ntlm")]
+use crate::ntlm::NtlmCredentials;
 use crate::Url;
 
 use http::{header::HeaderValue, Uri};
@@ -100,10 +102,14 @@ pub enum ProxyScheme {
     Http {
         auth: Option<HeaderValue>,
         host: http::uri::Authority,
+        #[cfg(feature = "ntlm")]
+        ntlm: Option<NtlmCredentials>,
     },
     Https {
         auth: Option<HeaderValue>,
         host: http::uri::Authority,
+        #[cfg(feature = "ntlm")]
+        ntlm: Option<NtlmCredentials>,
     },
     #[cfg(feature = "socks")]
     Socks4 { addr: SocketAddr },
@@ -272,10 +278,26 @@ impl Proxy {
     {
         Proxy::new(Intercept::Custom(Custom {
             auth: None,
+            #[cfg(feature = "ntlm")]
+            ntlm: None,
             func: Arc::new(move |url| fun(url).map(IntoProxyScheme::into_proxy_scheme)),
         }))
     }
 
+    /// A proxy picking, for each connection, the `Proxy` that `selector`
+    /// returns for the target, if any.
+    pub(crate) fn selector<F>(selector: F) -> Proxy
+    where
+        F: Fn(&Url) -> Option<Proxy> + Send + Sync + 'static,
+    {
+        Proxy::new(Intercept::Custom(Custom {
+            auth: None,
+            #[cfg(feature = "ntlm")]
+            ntlm: None,
+            func: Arc::new(move |url| selector(url)?.intercept(url).map(Ok)),
+        }))
+    }
+
     pub(crate) fn system() -> Proxy {
         static SYS_PROXIES: LazyLock<Arc<SystemProxyMap>> =
             LazyLock::new(|| Arc::new(get_sys_proxies(get_from_platform())));
@@ -335,6 +357,42 @@ impl Proxy {
         self
     }
 
+    /// Authenticate the tunnels through this proxy with NTLM.
+    ///
+    /// The `CONNECT` request of a tunnel is sent with an NTLM negotiate
+    /// message, and if the proxy answers `407 Proxy Authentication Required`
+    /// with an NTLM challenge, sent again with the NTLMv2 answer on the same
+    /// connection.
+    ///
+    /// # Note
+    ///
+    /// Only the tunnels of HTTPS requests are authenticated this way, HTTP
+    /// requests forwarded by the proxy are not.
+    /// With the system proxies, the credentials apply to each HTTP proxy
+    /// among them, SOCKS proxies are left as they are.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # extern crate rquest;
+    /// # fn run() -> Result<(), Box<std::error::Error>> {
+    /// let proxy = rquest::Proxy::https("http://localhost:1234")?
+    ///     .ntlm_auth("Aladdin", "open sesame", "CORP");
+    /// # Ok(())
+    /// # }
+    /// # fn main() {}
+    /// ```
+    #[cfg(feature = "ntlm")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ntlm")))]
+    pub fn ntlm_auth(mut self, username: &str, password: &str, domain: &str) -> Proxy {
+        self.intercept.set_ntlm_auth(NtlmCredentials::new(
+            username.into(),
+            password.into(),
+            domain.into(),
+        ));
+        self
+    }
+
     /// Adds a `No Proxy` exclusion list to this Proxy
     ///
     /// # Example
@@ -459,12 +517,16 @@ impl NoProxy {
     /// The rules are as follows:
     /// * The environment variable `NO_PROXY` is checked, if it is not set, `no_proxy` is checked
     /// * If neither environment variable is set, `None` is returned
-    /// * Entries are expected to be comma-separated (whitespace between entries is ignored)
+    /// * Entries are expected to be comma or semicolon separated (whitespace between entries is
+    /// ignored, as are empty entries)
     /// * IP addresses (both IPv4 and IPv6) are allowed, as are optional subnet masks (by adding /size,
-    /// for example "`192.168.1.0/24`").
-    /// * An entry "`*`" matches all hostnames (this is the only wildcard allowed)
-    /// * Any other entry is considered a domain name (and may contain a leading dot, for example `google.com`
-    /// and `.google.com` are equivalent) and would match both that domain AND all subdomains.
+    /// for example "`192.168.1.0/24`"). IPv6 addresses may be written in brackets, as in URLs, for
+    /// example "`[::1]`" or "`[fd00::]/8`".
+    /// * An entry "`*`" matches all hostnames
+    /// * Any other entry is considered a domain name (and may contain a leading dot or `*.`, for
+    /// example `google.com`, `.google.com` and `*.google.com` are equivalent) and would match both
+    /// that domain AND all subdomains.
+    /// * Entries and hosts are compared case-insensitively.
     ///
     /// For example, if `"NO_PROXY=google.com, 192.168.1.0/24"` was set, all of the following would match
     /// (and therefore would bypass the proxy):
@@ -479,14 +541,27 @@ impl NoProxy {
         }
         let mut ips = Vec::new();
         let mut domains = Vec::new();
-        let parts = no_proxy_list.split(',').map(str::trim);
+        let parts = no_proxy_list
+            .split([',', ';'])
+            .map(str::trim)
+            .filter(|part| !part.is_empty());
         for part in parts {
-            match part.parse::<IpNet>() {
+            let part = part.to_ascii_lowercase();
+            // `[fd00::]/8` is read as `fd00::/8`
+            let ip = match part.strip_prefix('[').and_then(|part| part.split_once(']')) {
+                Some((addr, prefix)) => format!("{addr}{prefix}"),
+                None => part.clone(),
+            };
+            match ip.parse::<IpNet>() {
                 // If we can parse an IP net or address, then use it, otherwise, assume it is a domain
                 Ok(ip) => ips.push(Ip::Network(ip)),
-                Err(_) => match part.parse::<IpAddr>() {
+                Err(_) => match ip.parse::<IpAddr>() {
                     Ok(addr) => ips.push(Ip::Address(addr)),
-                    Err(_) => domains.push(part.to_owned()),
+                    // `*.google.com` is the same as `.google.com`
+                    Err(_) => match part.strip_prefix("*.") {
+                        Some(domain) => domains.push(format!(".{domain}")),
+                        None => domains.push(part),
+                    },
                 },
             }
         }
@@ -508,7 +583,7 @@ impl NoProxy {
         match host.parse::<IpAddr>() {
             // If we can parse an IP addr, then use it, otherwise, assume it is a domain
             Ok(ip) => self.ips.contains(ip),
-            Err(_) => self.domains.contains(host),
+            Err(_) => self.domains.contains(&host.to_ascii_lowercase()),
         }
     }
 }
@@ -568,6 +643,8 @@ impl ProxyScheme {
         Ok(ProxyScheme::Http {
             auth: None,
             host: host.parse().map_err(crate::error::builder)?,
+            #[cfg(feature = "ntlm")]
+            ntlm: None,
         })
     }
 
@@ -576,6 +653,8 @@ impl ProxyScheme {
         Ok(ProxyScheme::Https {
             auth: None,
             host: host.parse().map_err(crate::error::builder)?,
+            #[cfg(feature = "ntlm")]
+            ntlm: None,
         })
     }
 
@@ -669,6 +748,40 @@ impl ProxyScheme {
         }
     }
 
+    #[cfg(feature = "ntlm")]
+    fn set_ntlm_auth(&mut self, credentials: NtlmCredentials) {
+        match *self {
+            ProxyScheme::Http { ref mut ntlm, .. } | ProxyScheme::Https { ref mut ntlm, .. } => {
+                *ntlm = Some(credentials);
+            }
+            #[cfg(feature = "socks")]
+            ProxyScheme::Socks4 { .. } => {
+                panic!("Socks4 is not supported for this method")
+            }
+            #[cfg(feature = "socks")]
+            ProxyScheme::Socks5 { .. } => {
+                panic!("Socks5 is not supported for this method")
+            }
+        }
+    }
+
+    #[cfg(feature = "ntlm")]
+    fn if_no_ntlm(mut self, update: &Option<NtlmCredentials>) -> Self {
+        match self {
+            ProxyScheme::Http { ref mut ntlm, .. } | ProxyScheme::Https { ref mut ntlm, .. } => {
+                if ntlm.is_none() {
+                    *ntlm = update.clone();
+                }
+            }
+            #[cfg(feature = "socks")]
+            ProxyScheme::Socks4 { .. } => {}
+            #[cfg(feature = "socks")]
+            ProxyScheme::Socks5 { .. } => {}
+        }
+
+        self
+    }
+
     fn if_no_auth(mut self, update: &Option<HeaderValue>) -> Self {
         match self {
             ProxyScheme::Http { ref mut auth, .. } => {
@@ -737,8 +850,8 @@ impl ProxyScheme {
 impl fmt::Debug for ProxyScheme {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            ProxyScheme::Http { auth: _auth, host } => write!(f, "http://{}", host),
-            ProxyScheme::Https { auth: _auth, host } => write!(f, "https://{}", host),
+            ProxyScheme::Http { host, .. } => write!(f, "http://{}", host),
+            ProxyScheme::Https { host, .. } => write!(f, "https://{}", host),
             #[cfg(feature = "socks")]
             ProxyScheme::Socks4 { addr } => {
                 write!(f, "socks4://{addr}")
@@ -792,12 +905,34 @@ impl Intercept {
             }
         }
     }
+
+    #[cfg(feature = "ntlm")]
+    fn set_ntlm_auth(&mut self, credentials: NtlmCredentials) {
+        match self {
+            Intercept::All(ref mut s)
+            | Intercept::Http(ref mut s)
+            | Intercept::Https(ref mut s) => s.set_ntlm_auth(credentials),
+            Intercept::System(ref mut map) => {
+                // NTLM only applies to the HTTP proxies, not the SOCKS ones.
+                for scheme in Arc::make_mut(map).values_mut() {
+                    if let ProxyScheme::Http { .. } | ProxyScheme::Https { .. } = scheme {
+                        scheme.set_ntlm_auth(credentials.clone());
+                    }
+                }
+            }
+            Intercept::Custom(ref mut custom) => {
+                custom.ntlm = Some(credentials);
+            }
+        }
+    }
 }
 
 #[derive(Clone)]
 struct Custom {
     // This auth only applies if the returned ProxyScheme doesn't have an auth...
     auth: Option<HeaderValue>,
+    #[cfg(feature = "ntlm")]
+    ntlm: Option<NtlmCredentials>,
     func: Arc<dyn Fn(&Url) -> Option<crate::Result<ProxyScheme>> + Send + Sync + 'static>,
 }
 
@@ -813,9 +948,10 @@ impl Custom {
         .parse()
         .expect("should be valid Url");
 
-        (self.func)(&url)
-            .and_then(|result| result.ok())
-            .map(|scheme| scheme.if_no_auth(&self.auth))
+        let scheme = (self.func)(&url)?.ok()?.if_no_auth(&self.auth);
+        #[cfg(feature = "ntlm")]
+        let scheme = scheme.if_no_ntlm(&self.ntlm);
+        Some(scheme)
     }
 }
 
@@ -852,6 +988,20 @@ impl Dst for Uri {
     }
 }
 
+impl Dst for Url {
+    fn scheme(&self) -> &str {
+        Url::scheme(self)
+    }
+
+    fn host(&self) -> &str {
+        self.host_str().unwrap_or_default()
+    }
+
+    fn port(&self) -> Option<u16> {
+        Url::port(self)
+    }
+}
+
 /// Get system proxies information.
 ///
 /// All platforms will check for proxy settings via environment variables.
@@ -7,7 +7,9 @@
 use std::error::Error as StdError;
 use std::fmt;
 
-use crate::header::{HeaderMap, AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, WWW_AUTHENTICATE};
+use crate::header::{
+    HeaderMap, AUTHORIZATION, COOKIE, HOST, PROXY_AUTHORIZATION, WWW_AUTHENTICATE,
+};
 use hyper::StatusCode;
 
 use crate::Url;
@@ -241,6 +243,9 @@ pub(crate) fn remove_sensitive_headers(headers: &mut HeaderMap, next: &Url, prev
             headers.remove("cookie2");
             headers.remove(PROXY_AUTHORIZATION);
             headers.remove(WWW_AUTHENTICATE);
+            // A Host overridden for the previous host doesn't apply to
+            // the next one.
+            headers.remove(HOST);
         }
     }
 }
@@ -314,12 +319,13 @@ fn test_redirect_policy_custom() {
 
 #[test]
 fn test_remove_sensitive_headers() {
-    use hyper::header::{HeaderValue, ACCEPT, AUTHORIZATION, COOKIE};
+    use hyper::header::{HeaderValue, ACCEPT, AUTHORIZATION, COOKIE, HOST};
 
     let mut headers = HeaderMap::new();
     headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
     headers.insert(AUTHORIZATION, HeaderValue::from_static("let me in"));
     headers.insert(COOKIE, HeaderValue::from_static("foo=bar"));
+    headers.insert(HOST, HeaderValue::from_static("vhost.internal"));
 
     let next = Url::parse("http://initial-domain.com/path").unwrap();
     let mut prev = vec![Url::parse("http://initial-domain.com/new_path").unwrap()];
@@ -331,6 +337,7 @@ fn test_remove_sensitive_headers() {
     prev.push(Url::parse("http://new-domain.com/path").unwrap());
     filtered_headers.remove(AUTHORIZATION);
     filtered_headers.remove(COOKIE);
+    filtered_headers.remove(HOST);
 
     remove_sensitive_headers(&mut headers, &next, &prev);
     assert_eq!(headers, filtered_headers);
@@ -0,0 +1,502 @@
+//! Retry Handling
+//!
+//! By default, a `Client` only retries HTTP/2 requests refused by a server
+//! shutting the connection down gracefully. A `retry::Policy` set with
+//! [`ClientBuilder::retry`](crate::ClientBuilder::retry) also retries
+//! failed connects, timeouts and some server responses, waiting longer
+//! between each attempt.
+
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+use http::header::RETRY_AFTER;
+use http::HeaderMap;
+use log::debug;
+
+use crate::{Request, Response, StatusCode};
+
+/// A type that controls when and how often failed requests are retried.
+///
+/// The default policy makes up to 3 attempts, and retries:
+///
+/// - errors connecting to the server,
+/// - timeouts,
+/// - `429 Too Many Requests`, `502 Bad Gateway`, `503 Service Unavailable`
+///   and `504 Gateway Timeout` responses.
+///
+/// Attempts are spaced by an exponential backoff with jitter. For `429` and
+/// `503` responses, a `Retry-After` header is honored instead.
+///
+/// Only requests whose body can be replayed are retried. A request with a
+/// streaming body is sent once; if it fails with an error that would
+/// otherwise have been retried, the error says the retry was skipped.
+///
+/// The [`timeout`](crate::ClientBuilder::timeout) of the client or the
+/// request applies to each attempt.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use rquest::retry;
+/// #
+/// # fn run() -> Result<(), rquest::Error> {
+/// let policy = retry::Policy::new()
+///     .max_attempts(5)
+///     .backoff(Duration::from_millis(50), Duration::from_secs(5));
+///
+/// let client = rquest::Client::builder().retry(policy).build()?;
+/// # drop(client);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Policy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    connect: bool,
+    timeout: bool,
+    statuses: Vec<StatusCode>,
+}
+
+/// The number of attempts it took to get a `Response`.
+///
+/// Added to the extensions of every response of a client with a retry
+/// policy.
+///
+/// # Example
+///
+/// ```rust
+/// # async fn run(client: rquest::Client) -> Result<(), rquest::Error> {
+/// let res = client.get("https://example.com").send().await?;
+/// if let Some(attempts) = res.extensions().get::<rquest::retry::Attempts>() {
+///     println!("took {} attempts", attempts.count());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Attempts(u32);
+
+/// The number of `Retry-After` delays waited for before getting a
+/// `Response`.
+///
+/// Added to the extensions of every response of a client with
+/// [`respect_retry_after`](crate::ClientBuilder::respect_retry_after) set.
+///
+/// # Example
+///
+/// ```rust
+/// # async fn run(client: rquest::Client) -> Result<(), rquest::Error> {
+/// let res = client.get("https://example.com").send().await?;
+/// if let Some(waits) = res.extensions().get::<rquest::retry::RetryAfterWaits>() {
+///     println!("waited {} times", waits.count());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryAfterWaits(u32);
+
+impl Policy {
+    /// Create the default `Policy`.
+    pub fn new() -> Self {
+        Policy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+            connect: true,
+            timeout: true,
+            statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+        }
+    }
+
+    /// Sets the maximum number of attempts, including the first one.
+    ///
+    /// A value of 1 disables retries. Default is 3.
+    pub fn max_attempts(mut self, max: u32) -> Self {
+        self.max_attempts = max.max(1);
+        self
+    }
+
+    /// Sets the delay before the first retry, and the cap of the delays
+    /// after it, which double on each retry.
+    ///
+    /// A `Retry-After` longer than `max` is not waited for: the response
+    /// is returned as is.
+    ///
+    /// Default is 100 milliseconds, capped to 10 seconds.
+    pub fn backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.base_delay = base;
+        self.max_delay = max.max(base);
+        self
+    }
+
+    /// Randomize each backoff delay between half and all of its value, so
+    /// that clients failing together don't retry together.
+    ///
+    /// Default is `true`.
+    pub fn jitter(mut self, enabled: bool) -> Self {
+        self.jitter = enabled;
+        self
+    }
+
+    /// Retry errors connecting to the server.
+    ///
+    /// Default is `true`.
+    pub fn retry_connect(mut self, enabled: bool) -> Self {
+        self.connect = enabled;
+        self
+    }
+
+    /// Retry requests that timed out.
+    ///
+    /// Default is `true`.
+    pub fn retry_timeout(mut self, enabled: bool) -> Self {
+        self.timeout = enabled;
+        self
+    }
+
+    /// Sets the response statuses that are retried.
+    ///
+    /// Default is `429`, `502`, `503` and `504`.
+    pub fn retry_statuses(mut self, statuses: &[StatusCode]) -> Self {
+        self.statuses = statuses.to_vec();
+        self
+    }
+
+    /// Sends `req` with `send`, retrying as long as the policy, and
+    /// `budget` if any, allow.
+    pub(crate) async fn send<F, Fut>(
+        &self,
+        mut req: Request,
+        budget: Option<&RetryBudget>,
+        send: F,
+    ) -> crate::Result<Response>
+    where
+        F: Fn(Request) -> Fut,
+        Fut: Future<Output = crate::Result<Response>>,
+    {
+        let _request = budget.map(RetryBudget::start_request);
+        let mut retrying = None;
+        let mut attempt = 1;
+        loop {
+            let retry = if attempt < self.max_attempts {
+                req.try_clone()
+            } else {
+                None
+            };
+
+            let url = req.url().clone();
+            let res = send(req).await;
+            // The retry, if any, is no longer in flight.
+            drop(retrying.take());
+            let delay = match res {
+                Ok(ref res) => self.response_delay(res, attempt),
+                Err(ref err) => self.error_delay(err, attempt),
+            };
+
+            match (delay, retry) {
+                (Some(delay), Some(next)) => {
+                    if let Some(budget) = budget {
+                        match budget.start_retry() {
+                            Some(guard) => retrying = Some(guard),
+                            None => {
+                                debug!("retry budget exhausted");
+                                return Err(crate::error::retry_budget_exhausted(res.err(), url));
+                            }
+                        }
+                    }
+                    debug!("retrying attempt {} in {:?}", attempt, delay);
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(
+                        target: "rquest",
+                        attempt = attempt + 1,
+                        delay_ms = delay.as_millis() as u64,
+                        http.status = res.as_ref().ok().map(|res| res.status().as_u16()),
+                        error = res.as_ref().err().map(tracing::field::display),
+                        "retrying request"
+                    );
+                    tokio::time::sleep(delay).await;
+                    req = next;
+                    attempt += 1;
+                }
+                (Some(_), None) if attempt < self.max_attempts => {
+                    debug!("retry skipped, request body is not replayable");
+                    return res
+                        .map(|res| with_attempts(res, attempt))
+                        .map_err(crate::Error::retry_skipped);
+                }
+                _ => return res.map(|res| with_attempts(res, attempt)),
+            }
+        }
+    }
+
+    /// Whether responses with `status` are retried.
+    pub(crate) fn retries_status(&self, status: StatusCode) -> bool {
+        self.statuses.contains(&status)
+    }
+
+    fn error_delay(&self, err: &crate::Error, attempt: u32) -> Option<Duration> {
+        if (self.connect && err.is_connect()) || (self.timeout && err.is_timeout()) {
+            Some(self.backoff_delay(attempt))
+        } else {
+            None
+        }
+    }
+
+    fn response_delay(&self, res: &Response, attempt: u32) -> Option<Duration> {
+        let status = res.status();
+        if !self.statuses.contains(&status) {
+            return None;
+        }
+
+        if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE {
+            if let Some(delay) = retry_after(res.headers(), SystemTime::now()) {
+                return (delay <= self.max_delay).then_some(delay);
+            }
+        }
+        Some(self.backoff_delay(attempt))
+    }
+
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << (attempt - 1).min(31))
+            .min(self.max_delay);
+
+        if self.jitter {
+            let half = delay / 2;
+            let nanos = half.as_nanos().min(u64::MAX as u128) as u64;
+            half + Duration::from_nanos(crate::util::fast_random() % (nanos + 1))
+        } else {
+            delay
+        }
+    }
+}
+
+/// Limits the retries in flight to a share of the requests in flight, see
+/// [`ClientBuilder::retry_budget`](crate::ClientBuilder::retry_budget).
+#[derive(Debug)]
+pub(crate) struct RetryBudget {
+    ratio: f64,
+    min_retries: u32,
+    requests: AtomicUsize,
+    retries: AtomicUsize,
+}
+
+impl RetryBudget {
+    pub(crate) fn new(ratio: f64, min_retries: u32) -> RetryBudget {
+        RetryBudget {
+            ratio,
+            min_retries,
+            requests: AtomicUsize::new(0),
+            retries: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn settings(&self) -> (f64, u32) {
+        (self.ratio, self.min_retries)
+    }
+
+    fn start_request(&self) -> RequestGuard<'_> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        RequestGuard(self)
+    }
+
+    /// Counts a retry in flight, if the budget allows one more.
+    fn start_retry(&self) -> Option<RetryGuard<'_>> {
+        let retries = self.retries.fetch_add(1, Ordering::AcqRel);
+        let requests = self.requests.load(Ordering::Relaxed).max(1);
+        if retries < self.min_retries as usize || (retries as f64) / (requests as f64) < self.ratio
+        {
+            Some(RetryGuard(self))
+        } else {
+            self.retries.fetch_sub(1, Ordering::AcqRel);
+            None
+        }
+    }
+}
+
+struct RequestGuard<'a>(&'a RetryBudget);
+
+impl Drop for RequestGuard<'_> {
+    fn drop(&mut self) {
+        self.0.requests.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+struct RetryGuard<'a>(&'a RetryBudget);
+
+impl Drop for RetryGuard<'_> {
+    fn drop(&mut self) {
+        self.0.retries.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl Default for Policy {
+    fn default() -> Policy {
+        Policy::new()
+    }
+}
+
+impl Attempts {
+    /// Returns the number of attempts, 1 if the request wasn't retried.
+    pub fn count(&self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for Attempts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+fn with_attempts(mut res: Response, attempt: u32) -> Response {
+    res.extensions_mut().insert(Attempts(attempt));
+    res
+}
+
+impl RetryAfterWaits {
+    /// Returns the number of waits, 0 if the request wasn't retried.
+    pub fn count(&self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for RetryAfterWaits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// Sends `req` with `send`, and once more after the `Retry-After` delay of
+/// a `429` or `503` response, unless it is longer than `max_wait`, the
+/// body of `req` can't be replayed, or `skip` is true for the status.
+pub(crate) async fn send_after_retry_after<F, Fut>(
+    max_wait: Duration,
+    req: Request,
+    skip: impl Fn(StatusCode) -> bool,
+    send: F,
+) -> crate::Result<Response>
+where
+    F: Fn(Request) -> Fut,
+    Fut: Future<Output = crate::Result<Response>>,
+{
+    let retry = req.try_clone();
+    let res = send(req).await?;
+
+    let status = res.status();
+    if (status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::SERVICE_UNAVAILABLE)
+        || skip(status)
+    {
+        return Ok(with_waits(res, 0));
+    }
+    let (delay, next) = match (retry_after(res.headers(), SystemTime::now()), retry) {
+        (Some(delay), Some(next)) if delay <= max_wait => (delay, next),
+        _ => return Ok(with_waits(res, 0)),
+    };
+
+    debug!("waiting {:?} for Retry-After", delay);
+    drop(res);
+    tokio::time::sleep(delay).await;
+    send(next).await.map(|res| with_waits(res, 1))
+}
+
+fn with_waits(mut res: Response, waits: u32) -> Response {
+    res.extensions_mut().insert(RetryAfterWaits(waits));
+    res
+}
+
+/// Parses a `Retry-After` header, in either delay-seconds or HTTP-date
+/// form.
+pub(crate) fn retry_after(headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(now).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_retry_after() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(784_111_777);
+        let mut headers = HeaderMap::new();
+
+        headers.insert(RETRY_AFTER, "120".parse().unwrap());
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(120)));
+
+        headers.insert(
+            RETRY_AFTER,
+            "Sun, 06 Nov 1994 08:49:47 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(10)));
+
+        // dates in the past mean now
+        headers.insert(
+            RETRY_AFTER,
+            "Sun, 06 Nov 1994 08:49:27 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers, now), Some(Duration::ZERO));
+
+        headers.insert(RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(retry_after(&headers, now), None);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let policy = Policy::new()
+            .jitter(false)
+            .backoff(Duration::from_millis(100), Duration::from_millis(350));
+
+        assert_eq!(policy.backoff_delay(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff_delay(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff_delay(3), Duration::from_millis(350));
+        assert_eq!(policy.backoff_delay(40), Duration::from_millis(350));
+    }
+
+    #[test]
+    fn budget_limits_retries_in_flight() {
+        let budget = RetryBudget::new(0.5, 1);
+        let requests = (0..4).map(|_| budget.start_request()).collect::<Vec<_>>();
+
+        // 1 retry is allowed by `min_retries`, then up to half the requests.
+        let first = budget.start_retry().unwrap();
+        let second = budget.start_retry().unwrap();
+        assert!(budget.start_retry().is_none());
+
+        drop(second);
+        assert!(budget.start_retry().is_some());
+        drop((first, requests));
+        assert_eq!(budget.retries.load(Ordering::Relaxed), 0);
+        assert_eq!(budget.requests.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn jitter_stays_within_half() {
+        let policy = Policy::new().backoff(Duration::from_millis(100), Duration::from_secs(1));
+
+        for _ in 0..100 {
+            let delay = policy.backoff_delay(2);
+            assert!(delay >= Duration::from_millis(100));
+            assert!(delay <= Duration::from_millis(200));
+        }
+    }
+}
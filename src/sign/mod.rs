@@ -0,0 +1,153 @@
+//! Request signing
+//!
+//! A [`RequestSigner`] set with
+//! [`RequestBuilder::sign_with`](crate::RequestBuilder::sign_with) signs a
+//! request right before it is sent, once the client added its default
+//! headers, so the signature covers the headers going out.
+//!
+//! The `sigv4` feature provides [`sigv4::SigV4`], signing requests for AWS
+//! and S3-compatible services.
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::Arc;
+
+use http::header::{CONTENT_LENGTH, HOST};
+use http::{HeaderMap, HeaderValue};
+
+use crate::{Method, Url};
+
+#[cfg(feature = "sigv4")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sigv4")))]
+pub mod sigv4;
+
+/// A type that signs requests.
+///
+/// Closures taking a `&mut SignableRequest` implement it.
+pub trait RequestSigner: Send + Sync + 'static {
+    /// Sign `req`, inserting the headers carrying the signature.
+    ///
+    /// An error fails the request.
+    fn sign(&self, req: &mut SignableRequest<'_>) -> Result<(), Box<dyn StdError + Send + Sync>>;
+}
+
+impl<F> RequestSigner for F
+where
+    F: Fn(&mut SignableRequest<'_>) -> Result<(), Box<dyn StdError + Send + Sync>>
+        + Send
+        + Sync
+        + 'static,
+{
+    fn sign(&self, req: &mut SignableRequest<'_>) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        self(req)
+    }
+}
+
+/// A request about to be sent, as seen by a [`RequestSigner`].
+///
+/// Its headers include the `Host` header and, for a non-empty body, the
+/// `Content-Length` header the client sends.
+pub struct SignableRequest<'a> {
+    method: &'a Method,
+    url: &'a Url,
+    headers: &'a mut HeaderMap,
+    payload: Payload<'a>,
+}
+
+/// The body of a [`SignableRequest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Payload<'a> {
+    /// A buffered body, empty for a request without one.
+    Bytes(&'a [u8]),
+    /// A streaming body, which can not be read before it is sent.
+    Streaming,
+}
+
+impl SignableRequest<'_> {
+    /// Get the method.
+    pub fn method(&self) -> &Method {
+        self.method
+    }
+
+    /// Get the url.
+    pub fn url(&self) -> &Url {
+        self.url
+    }
+
+    /// Get the headers, in the order they are sent.
+    pub fn headers(&self) -> &HeaderMap {
+        self.headers
+    }
+
+    /// Get a mutable reference to the headers.
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        self.headers
+    }
+
+    /// Get the body.
+    pub fn payload(&self) -> Payload<'_> {
+        self.payload
+    }
+}
+
+impl fmt::Debug for SignableRequest<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SignableRequest")
+            .field("method", &self.method)
+            .field("url", &self.url.as_str())
+            .field("headers", &self.headers)
+            .field("payload", &self.payload)
+            .finish()
+    }
+}
+
+/// Sign the request parts with `signer`.
+///
+/// The `Host` and `Content-Length` headers are added for the signer to see,
+/// then taken out again so the connection writes them as it always does.
+pub(crate) fn sign(
+    signer: &Arc<dyn RequestSigner>,
+    method: &Method,
+    url: &Url,
+    headers: &mut HeaderMap,
+    payload: Payload<'_>,
+) -> crate::Result<()> {
+    let mut added = Vec::new();
+    if !headers.contains_key(HOST) {
+        if let Some(host) = host_header(url) {
+            headers.insert(HOST, host);
+            added.push(HOST);
+        }
+    }
+    if let Payload::Bytes(bytes) = payload {
+        if !bytes.is_empty() && !headers.contains_key(CONTENT_LENGTH) {
+            headers.insert(CONTENT_LENGTH, HeaderValue::from(bytes.len()));
+            added.push(CONTENT_LENGTH);
+        }
+    }
+
+    let mut req = SignableRequest {
+        method,
+        url,
+        headers,
+        payload,
+    };
+    signer
+        .sign(&mut req)
+        .map_err(|e| crate::error::request_boxed(e).with_url(url.clone()))?;
+
+    for name in added {
+        headers.remove(name);
+    }
+    Ok(())
+}
+
+fn host_header(url: &Url) -> Option<HeaderValue> {
+    let host = url.host_str()?;
+    let value = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_owned(),
+    };
+    HeaderValue::from_str(&value).ok()
+}
@@ -0,0 +1,395 @@
+//! AWS Signature Version 4
+//!
+//! ```rust,no_run
+//! use rquest::sign::sigv4::SigV4;
+//!
+//! # async fn run() -> Result<(), rquest::Error> {
+//! let signer = SigV4::new("AKIDEXAMPLE", "secret", "us-east-1", "s3");
+//! let res = rquest::Client::new()
+//!     .get("https://bucket.s3.us-east-1.amazonaws.com/key")
+//!     .sign_with(signer)
+//!     .send()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::os::raw::c_void;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use boring::sha::sha256;
+use boring_sys as ffi;
+use http::header::AUTHORIZATION;
+use http::{HeaderMap, HeaderValue};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use super::{Payload, RequestSigner, SignableRequest};
+use crate::Url;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+const X_AMZ_DATE: &str = "x-amz-date";
+const X_AMZ_CONTENT_SHA256: &str = "x-amz-content-sha256";
+const X_AMZ_SECURITY_TOKEN: &str = "x-amz-security-token";
+
+/// The headers left out of the signature, as proxies and the connection
+/// may change them.
+const UNSIGNED_HEADERS: &[&str] = &[
+    "authorization",
+    "connection",
+    "expect",
+    "proxy-authorization",
+    "transfer-encoding",
+    "user-agent",
+    "x-amzn-trace-id",
+];
+
+/// The characters AWS leaves unencoded: letters, digits, `-`, `.`, `_` and `~`.
+const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// A [`RequestSigner`] computing AWS Signature Version 4 signatures.
+///
+/// The signature covers every header but a few a proxy or the connection
+/// may change, like `User-Agent`. The time of the signature is taken from an
+/// `x-amz-date` header if the request has one, and the payload hash from an
+/// `x-amz-content-sha256` header, otherwise the body is hashed. A streaming
+/// body is signed as `UNSIGNED-PAYLOAD`.
+#[derive(Clone)]
+pub struct SigV4 {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+    region: String,
+    service: String,
+    content_sha256: bool,
+}
+
+impl SigV4 {
+    /// Create a signer for the `service` of `region` with the given keys.
+    ///
+    /// For the `s3` service, the payload hash is also sent in an
+    /// `x-amz-content-sha256` header, and paths are encoded once.
+    pub fn new(
+        access_key_id: impl Into<String>,
+        secret_access_key: impl Into<String>,
+        region: impl Into<String>,
+        service: impl Into<String>,
+    ) -> SigV4 {
+        let service = service.into();
+        SigV4 {
+            access_key_id: access_key_id.into(),
+            secret_access_key: secret_access_key.into(),
+            session_token: None,
+            region: region.into(),
+            content_sha256: service == "s3",
+            service,
+        }
+    }
+
+    /// Send a session token of temporary credentials in an
+    /// `x-amz-security-token` header.
+    pub fn session_token(mut self, token: impl Into<String>) -> SigV4 {
+        self.session_token = Some(token.into());
+        self
+    }
+
+    /// Whether to send the payload hash in an `x-amz-content-sha256` header.
+    ///
+    /// Default is `true` for the `s3` service only.
+    pub fn content_sha256_header(mut self, enabled: bool) -> SigV4 {
+        self.content_sha256 = enabled;
+        self
+    }
+
+    fn signing_key(&self, date: &str) -> [u8; 32] {
+        let key = format!("AWS4{}", self.secret_access_key);
+        [self.region.as_str(), &self.service, "aws4_request"]
+            .iter()
+            .fold(hmac_sha256(key.as_bytes(), date.as_bytes()), |key, part| {
+                hmac_sha256(&key, part.as_bytes())
+            })
+    }
+}
+
+impl RequestSigner for SigV4 {
+    fn sign(&self, req: &mut SignableRequest<'_>) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        let date_time = match req.headers().get(X_AMZ_DATE) {
+            Some(value) => value.to_str()?.to_owned(),
+            None => {
+                let now = amz_date(SystemTime::now());
+                req.headers_mut()
+                    .insert(X_AMZ_DATE, HeaderValue::from_str(&now)?);
+                now
+            }
+        };
+        let date = date_time
+            .get(..8)
+            .ok_or("invalid x-amz-date header for SigV4")?;
+
+        let payload_hash = match req.headers().get(X_AMZ_CONTENT_SHA256) {
+            Some(value) => value.to_str()?.to_owned(),
+            None => match req.payload() {
+                Payload::Bytes(bytes) => hex(&sha256(bytes)),
+                Payload::Streaming => UNSIGNED_PAYLOAD.to_owned(),
+            },
+        };
+        if self.content_sha256 && !req.headers().contains_key(X_AMZ_CONTENT_SHA256) {
+            req.headers_mut()
+                .insert(X_AMZ_CONTENT_SHA256, HeaderValue::from_str(&payload_hash)?);
+        }
+        if let Some(ref token) = self.session_token {
+            let mut value = HeaderValue::from_str(token)?;
+            value.set_sensitive(true);
+            req.headers_mut().insert(X_AMZ_SECURITY_TOKEN, value);
+        }
+
+        let (canonical_headers, signed_headers) = canonical_headers(req.headers());
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            req.method(),
+            canonical_uri(req.url(), self.service != "s3"),
+            canonical_query(req.url()),
+            canonical_headers,
+            signed_headers,
+            payload_hash
+        );
+
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "{}\n{}\n{}\n{}",
+            ALGORITHM,
+            date_time,
+            scope,
+            hex(&sha256(canonical_request.as_bytes()))
+        );
+        let signature = hmac_sha256(&self.signing_key(date), string_to_sign.as_bytes());
+
+        let mut value = HeaderValue::from_str(&format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            ALGORITHM,
+            self.access_key_id,
+            scope,
+            signed_headers,
+            hex(&signature)
+        ))?;
+        value.set_sensitive(true);
+        req.headers_mut().insert(AUTHORIZATION, value);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for SigV4 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SigV4")
+            .field("access_key_id", &self.access_key_id)
+            .field("region", &self.region)
+            .field("service", &self.service)
+            .finish()
+    }
+}
+
+/// The path with each segment encoded, twice for services other than S3.
+fn canonical_uri(url: &Url, double_encode: bool) -> String {
+    url.path()
+        .split('/')
+        .map(|segment| {
+            let decoded = percent_decode_str(segment).decode_utf8_lossy();
+            let encoded = utf8_percent_encode(&decoded, UNRESERVED).to_string();
+            match double_encode {
+                true => utf8_percent_encode(&encoded, UNRESERVED).to_string(),
+                false => encoded,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The query parameters encoded and sorted by name, then value.
+fn canonical_query(url: &Url) -> String {
+    let encode = |s: &str| {
+        let decoded = percent_decode_str(s).decode_utf8_lossy();
+        utf8_percent_encode(&decoded, UNRESERVED).to_string()
+    };
+
+    let mut params = url
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            (encode(name), encode(value))
+        })
+        .collect::<Vec<_>>();
+    params.sort();
+
+    params
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// The canonical headers block and the signed headers list.
+fn canonical_headers(headers: &HeaderMap) -> (String, String) {
+    let mut canonical = BTreeMap::<&str, Vec<String>>::new();
+    for (name, value) in headers {
+        if UNSIGNED_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        let value = String::from_utf8_lossy(value.as_bytes());
+        canonical
+            .entry(name.as_str())
+            .or_default()
+            .push(value.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+
+    let block = canonical
+        .iter()
+        .map(|(name, values)| format!("{}:{}\n", name, values.join(",")))
+        .collect();
+    let signed = canonical.keys().copied().collect::<Vec<_>>().join(";");
+    (block, signed)
+}
+
+/// The `x-amz-date` format of `time`, like `20150830T123600Z`.
+fn amz_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // Civil from days, as of Howard Hinnant's algorithm.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut out = [0; 32];
+    let mut out_len = 0;
+    unsafe {
+        ffi::HMAC(
+            ffi::EVP_sha256(),
+            key.as_ptr() as *const c_void,
+            key.len(),
+            data.as_ptr(),
+            data.len(),
+            out.as_mut_ptr(),
+            &mut out_len,
+        )
+    };
+    out
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::header::HOST;
+    use std::time::Duration;
+
+    // From the AWS SigV4 test suite.
+    fn sign(method: &str, url: &str) -> HeaderMap {
+        let signer = SigV4::new(
+            "AKIDEXAMPLE",
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "us-east-1",
+            "service",
+        );
+        let method = method.parse().unwrap();
+        let url = Url::parse(url).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(HOST, HeaderValue::from_static("example.amazonaws.com"));
+        headers.insert(X_AMZ_DATE, HeaderValue::from_static("20150830T123600Z"));
+
+        let mut req = SignableRequest {
+            method: &method,
+            url: &url,
+            headers: &mut headers,
+            payload: Payload::Bytes(b""),
+        };
+        signer.sign(&mut req).unwrap();
+        headers
+    }
+
+    fn signature(headers: &HeaderMap) -> &str {
+        let auth = headers[AUTHORIZATION].to_str().unwrap();
+        auth.rsplit_once("Signature=").unwrap().1
+    }
+
+    #[test]
+    fn get_vanilla() {
+        let headers = sign("GET", "https://example.amazonaws.com/");
+        assert_eq!(
+            headers[AUTHORIZATION],
+            "AWS4-HMAC-SHA256 \
+             Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+        assert!(!headers.contains_key(X_AMZ_CONTENT_SHA256));
+    }
+
+    #[test]
+    fn get_vanilla_query_order_key_case() {
+        let headers = sign(
+            "GET",
+            "https://example.amazonaws.com/?Param2=value2&Param1=value1",
+        );
+        assert_eq!(
+            signature(&headers),
+            "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"
+        );
+    }
+
+    #[test]
+    fn post_vanilla() {
+        let headers = sign("POST", "https://example.amazonaws.com/");
+        assert_eq!(
+            signature(&headers),
+            "5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b"
+        );
+    }
+
+    #[test]
+    fn canonical_uri_encoding() {
+        let url = Url::parse("https://example.com/a b/c~d").unwrap();
+        assert_eq!(canonical_uri(&url, false), "/a%20b/c~d");
+        assert_eq!(canonical_uri(&url, true), "/a%2520b/c~d");
+    }
+
+    #[test]
+    fn amz_date_format() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_440_938_160);
+        assert_eq!(amz_date(time), "20150830T123600Z");
+    }
+}
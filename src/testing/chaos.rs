@@ -0,0 +1,416 @@
+//! Network fault injection.
+//!
+//! A [`ChaosLayer`] wraps a connector so that every established connection
+//! goes through a [`ChaosStream`], which delays, drops or fails I/O at the
+//! configured rates. It is meant for exercising retry and circuit breaker
+//! logic without a real bad network.
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use rquest::testing::chaos::{ChaosLayer, Distribution};
+//!
+//! # fn run() -> Result<(), rquest::Error> {
+//! let chaos = ChaosLayer::new()
+//!     .latency(Duration::from_millis(80), Distribution::Normal(Duration::from_millis(20)))
+//!     .packet_loss_rate(0.05)
+//!     .error_rate(0.01);
+//!
+//! let client = rquest::Client::builder().chaos(chaos).build()?;
+//! # drop(client);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::io::{self, IoSlice};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use http::Uri;
+use hyper::client::connect::{Connected, Connection};
+use hyper::service::Service;
+use pin_project_lite::pin_project;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::Sleep;
+use tower_layer::Layer;
+
+use crate::error::BoxError;
+use crate::util::fast_random;
+
+/// How the injected latency is spread around its mean.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Distribution {
+    /// Always the mean.
+    Fixed,
+    /// Uniformly within the mean plus or minus the given jitter.
+    Uniform(Duration),
+    /// Normally distributed with the given standard deviation.
+    Normal(Duration),
+}
+
+/// A tower [`Layer`] injecting latency, packet loss and I/O errors into the
+/// connections of the wrapped connector.
+///
+/// The rates are probabilities between `0.0` and `1.0`, and all default to
+/// zero, so `ChaosLayer::new()` changes nothing until configured.
+#[derive(Clone, Debug, Default)]
+pub struct ChaosLayer {
+    latency: Option<(Duration, Distribution)>,
+    packet_loss_rate: f64,
+    error_rate: f64,
+}
+
+impl ChaosLayer {
+    /// Creates a `ChaosLayer` injecting no faults.
+    pub fn new() -> ChaosLayer {
+        ChaosLayer::default()
+    }
+
+    /// Delays connecting, and the first read after every write, by a
+    /// duration sampled around `mean`.
+    pub fn latency(mut self, mean: Duration, distribution: Distribution) -> ChaosLayer {
+        self.latency = Some((mean, distribution));
+        self
+    }
+
+    /// Sets the probability of a connection being dropped before its first
+    /// byte is read.
+    ///
+    /// Writes to a dropped connection still succeed, and reads see EOF, as
+    /// with a peer that went away.
+    pub fn packet_loss_rate(mut self, rate: f64) -> ChaosLayer {
+        self.packet_loss_rate = clamp_rate(rate);
+        self
+    }
+
+    /// Sets the probability of any read or write failing with an `io::Error`.
+    pub fn error_rate(mut self, rate: f64) -> ChaosLayer {
+        self.error_rate = clamp_rate(rate);
+        self
+    }
+
+    /// Wraps an established connection.
+    pub fn wrap<T>(&self, io: T) -> ChaosStream<T> {
+        ChaosStream {
+            inner: io,
+            dropped: chance(self.packet_loss_rate),
+            config: self.clone(),
+            awaiting_response: false,
+            delay: None,
+        }
+    }
+
+    pub(crate) fn sample_latency(&self) -> Option<Duration> {
+        let (mean, distribution) = self.latency?;
+        let mean = mean.as_secs_f64();
+        let secs = match distribution {
+            Distribution::Fixed => mean,
+            Distribution::Uniform(jitter) => {
+                let jitter = jitter.as_secs_f64();
+                mean - jitter + 2.0 * jitter * unit()
+            }
+            Distribution::Normal(std_dev) => {
+                // Box-Muller transform, `1.0 - unit()` keeps the log finite.
+                let z = (-2.0 * (1.0 - unit()).ln()).sqrt()
+                    * (2.0 * std::f64::consts::PI * unit()).cos();
+                mean + std_dev.as_secs_f64() * z
+            }
+        };
+        Some(Duration::from_secs_f64(secs.max(0.0)))
+    }
+
+    pub(crate) fn sample_error(&self) -> Option<io::Error> {
+        if !chance(self.error_rate) {
+            return None;
+        }
+
+        const KINDS: [io::ErrorKind; 4] = [
+            io::ErrorKind::ConnectionReset,
+            io::ErrorKind::ConnectionAborted,
+            io::ErrorKind::BrokenPipe,
+            io::ErrorKind::TimedOut,
+        ];
+        let kind = KINDS[fast_random() as usize % KINDS.len()];
+        Some(io::Error::new(kind, "chaos: injected error"))
+    }
+
+    /// Delays and wraps a connection that is being established.
+    pub(crate) async fn connect<F, T, E>(self, connecting: F) -> Result<ChaosStream<T>, E>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        if let Some(delay) = self.sample_latency() {
+            tokio::time::sleep(delay).await;
+        }
+        connecting.await.map(|io| self.wrap(io))
+    }
+}
+
+impl<S> Layer<S> for ChaosLayer {
+    type Service = ChaosConnector<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ChaosConnector {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+/// A connector whose connections are wrapped in a [`ChaosStream`].
+///
+/// This is created by [`ChaosLayer`].
+#[derive(Clone, Debug)]
+pub struct ChaosConnector<S> {
+    inner: S,
+    config: ChaosLayer,
+}
+
+impl<S> Service<Uri> for ChaosConnector<S>
+where
+    S: Service<Uri>,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+    S::Response: Send + 'static,
+{
+    type Response = ChaosStream<S::Response>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let connecting = self.inner.call(dst);
+        let config = self.config.clone();
+        Box::pin(async move { config.connect(connecting).await.map_err(Into::into) })
+    }
+}
+
+pin_project! {
+    /// A connection that delays, drops or fails I/O.
+    ///
+    /// This is created by [`ChaosLayer::wrap`].
+    pub struct ChaosStream<T> {
+        #[pin]
+        inner: T,
+        config: ChaosLayer,
+        dropped: bool,
+        awaiting_response: bool,
+        delay: Option<Pin<Box<Sleep>>>,
+    }
+}
+
+impl<T> ChaosStream<T> {
+    /// Returns a reference to the wrapped connection.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns whether the connection was chosen to be dropped.
+    pub fn is_dropped(&self) -> bool {
+        self.dropped
+    }
+}
+
+impl<T> fmt::Debug for ChaosStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChaosStream")
+            .field("config", &self.config)
+            .field("dropped", &self.dropped)
+            .finish()
+    }
+}
+
+impl<T: Connection> Connection for ChaosStream<T> {
+    fn connected(&self) -> Connected {
+        self.inner.connected()
+    }
+}
+
+impl<T: AsyncRead> AsyncRead for ChaosStream<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.project();
+
+        // A round trip: the first read after a write waits out the latency.
+        if *this.awaiting_response {
+            if this.delay.is_none() {
+                *this.delay = this
+                    .config
+                    .sample_latency()
+                    .map(|delay| Box::pin(tokio::time::sleep(delay)));
+            }
+            if let Some(delay) = this.delay.as_mut() {
+                futures_core::ready!(delay.as_mut().poll(cx));
+            }
+            *this.delay = None;
+            *this.awaiting_response = false;
+        }
+
+        if *this.dropped {
+            return Poll::Ready(Ok(()));
+        }
+        if let Some(err) = this.config.sample_error() {
+            return Poll::Ready(Err(err));
+        }
+        this.inner.poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite> AsyncWrite for ChaosStream<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        let this = self.project();
+        if *this.dropped {
+            return Poll::Ready(Ok(buf.len()));
+        }
+        if let Some(err) = this.config.sample_error() {
+            return Poll::Ready(Err(err));
+        }
+        let res = this.inner.poll_write(cx, buf);
+        if let Poll::Ready(Ok(_)) = res {
+            *this.awaiting_response = true;
+        }
+        res
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<Result<usize, io::Error>> {
+        let this = self.project();
+        if *this.dropped {
+            return Poll::Ready(Ok(bufs.iter().map(|b| b.len()).sum()));
+        }
+        if let Some(err) = this.config.sample_error() {
+            return Poll::Ready(Err(err));
+        }
+        let res = this.inner.poll_write_vectored(cx, bufs);
+        if let Poll::Ready(Ok(_)) = res {
+            *this.awaiting_response = true;
+        }
+        res
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.project();
+        if *this.dropped {
+            return Poll::Ready(Ok(()));
+        }
+        this.inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.project();
+        if *this.dropped {
+            return Poll::Ready(Ok(()));
+        }
+        this.inner.poll_shutdown(cx)
+    }
+}
+
+fn clamp_rate(rate: f64) -> f64 {
+    if rate.is_nan() {
+        0.0
+    } else {
+        rate.clamp(0.0, 1.0)
+    }
+}
+
+/// A uniform sample in `[0, 1)`.
+fn unit() -> f64 {
+    (fast_random() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn chance(rate: f64) -> bool {
+    rate > 0.0 && unit() < rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn uniform_latency_within_jitter() {
+        let chaos = ChaosLayer::new().latency(
+            Duration::from_millis(100),
+            Distribution::Uniform(Duration::from_millis(20)),
+        );
+        for _ in 0..100 {
+            let delay = chaos.sample_latency().unwrap();
+            assert!(delay >= Duration::from_millis(80), "{:?}", delay);
+            assert!(delay <= Duration::from_millis(120), "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn normal_latency_is_never_negative() {
+        let chaos = ChaosLayer::new().latency(
+            Duration::from_millis(1),
+            Distribution::Normal(Duration::from_millis(50)),
+        );
+        for _ in 0..100 {
+            chaos.sample_latency().unwrap();
+        }
+        assert_eq!(ChaosLayer::new().sample_latency(), None);
+    }
+
+    #[test]
+    fn rates_are_clamped() {
+        let chaos = ChaosLayer::new().packet_loss_rate(2.0).error_rate(f64::NAN);
+        assert_eq!(chaos.packet_loss_rate, 1.0);
+        assert_eq!(chaos.error_rate, 0.0);
+    }
+
+    #[tokio::test]
+    async fn dropped_connection_reads_eof() {
+        let (client, mut server) = tokio::io::duplex(64);
+        let mut stream = ChaosLayer::new().packet_loss_rate(1.0).wrap(client);
+        assert!(stream.is_dropped());
+
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+        server.write_all(b"HTTP/1.1 200 OK\r\n\r\n").await.unwrap();
+
+        let mut buf = Vec::new();
+        assert_eq!(stream.read_to_end(&mut buf).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn injected_errors() {
+        let (client, _server) = tokio::io::duplex(64);
+        let mut stream = ChaosLayer::new().error_rate(1.0).wrap(client);
+
+        stream.write_all(b"ping").await.unwrap_err();
+        let mut buf = [0; 4];
+        stream.read(&mut buf).await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn no_faults_by_default() {
+        let (client, mut server) = tokio::io::duplex(64);
+        let mut stream = ChaosLayer::new().wrap(client);
+
+        stream.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        server.read_exact(&mut buf).await.unwrap();
+        server.write_all(b"pong").await.unwrap();
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+    }
+}
@@ -0,0 +1,381 @@
+//! In-memory transport for testing.
+//!
+//! A client built with
+//! [`ClientBuilder::with_transport`](crate::ClientBuilder::with_transport)
+//! hands its requests to a [`MockTransport`] instead of the network.
+//! [`MockServer`] is a transport answering the requests matching a set of
+//! [`Mock`] expectations, and checking in the end that they were met.
+//!
+//! ```rust
+//! use rquest::testing::mock::{Mock, MockServer};
+//! use rquest::Method;
+//!
+//! # async fn run() -> Result<(), rquest::Error> {
+//! let server = MockServer::new();
+//! server.mock(
+//!     Mock::new(Method::GET, "/users/1")
+//!         .header("accept", "application/json")
+//!         .respond(200, r#"{"name":"ferris"}"#),
+//! );
+//!
+//! let client = rquest::Client::builder()
+//!     .with_transport(server.clone())
+//!     .build()?;
+//! let user = client
+//!     .get("http://api.test/users/1")
+//!     .header("accept", "application/json")
+//!     .send()
+//!     .await?
+//!     .text()
+//!     .await?;
+//!
+//! assert_eq!(user, r#"{"name":"ferris"}"#);
+//! server.verify();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use http::StatusCode;
+
+use crate::{Method, Request};
+
+/// Answers the requests of a client instead of the network.
+///
+/// The transport gets every request as it would be sent: once per redirect
+/// hop and per retry. An error fails the request like a connection error
+/// would.
+///
+/// Closures taking a `Request` are transports.
+pub trait MockTransport: Send + Sync + 'static {
+    /// Answers `req`.
+    fn handle(
+        &self,
+        req: Request,
+    ) -> Result<http::Response<Bytes>, Box<dyn StdError + Send + Sync>>;
+}
+
+impl<F> MockTransport for F
+where
+    F: Fn(Request) -> Result<http::Response<Bytes>, Box<dyn StdError + Send + Sync>>
+        + Send
+        + Sync
+        + 'static,
+{
+    fn handle(
+        &self,
+        req: Request,
+    ) -> Result<http::Response<Bytes>, Box<dyn StdError + Send + Sync>> {
+        self(req)
+    }
+}
+
+/// A request expected by a [`MockServer`], and the responses to it.
+///
+/// A request matches when it has the method and path of the mock, and the
+/// headers, query pairs and body it was given, if any. The responses are
+/// returned in order, the last one for every further match.
+#[derive(Clone, Debug)]
+pub struct Mock {
+    method: Method,
+    path: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    body: Option<Bytes>,
+    responses: VecDeque<Canned>,
+    times: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct Canned {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+/// A [`MockTransport`] answering requests from a list of [`Mock`]s.
+///
+/// Cloning a `MockServer` shares its mocks, so a clone can be given to the
+/// client and the original kept to [`verify`](MockServer::verify) it.
+#[derive(Clone, Default)]
+pub struct MockServer {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    mocks: Vec<(Mock, usize)>,
+    unmatched: Vec<String>,
+}
+
+impl Mock {
+    /// Expects requests with `method` to `path`, ignoring the query.
+    pub fn new(method: Method, path: impl Into<String>) -> Mock {
+        Mock {
+            method,
+            path: path.into(),
+            query: Vec::new(),
+            headers: Vec::new(),
+            body: None,
+            responses: VecDeque::new(),
+            times: None,
+        }
+    }
+
+    /// Only match requests with this query pair.
+    pub fn query(mut self, name: impl Into<String>, value: impl Into<String>) -> Mock {
+        self.query.push((name.into(), value.into()));
+        self
+    }
+
+    /// Only match requests with this header value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name or the value are invalid.
+    pub fn header(mut self, name: &str, value: &str) -> Mock {
+        let name = HeaderName::from_bytes(name.as_bytes()).expect("invalid header name");
+        let value = HeaderValue::from_str(value).expect("invalid header value");
+        self.headers.push((name, value));
+        self
+    }
+
+    /// Only match requests with this body.
+    ///
+    /// A request with a streaming body never matches.
+    pub fn body(mut self, body: impl Into<Bytes>) -> Mock {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Queues a response with `status` and `body`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `status` is not a valid status code.
+    pub fn respond(self, status: u16, body: impl Into<Bytes>) -> Mock {
+        let status = StatusCode::from_u16(status).expect("invalid status code");
+        self.respond_with(
+            http::Response::builder()
+                .status(status)
+                .body(body.into())
+                .expect("valid response"),
+        )
+    }
+
+    /// Queues `res` as a response.
+    pub fn respond_with(mut self, res: http::Response<Bytes>) -> Mock {
+        let (parts, body) = res.into_parts();
+        self.responses.push_back(Canned {
+            status: parts.status,
+            headers: parts.headers,
+            body,
+        });
+        self
+    }
+
+    /// Expects exactly `n` matching requests, instead of at least one.
+    pub fn times(mut self, n: usize) -> Mock {
+        self.times = Some(n);
+        self
+    }
+
+    fn matches(&self, req: &Request) -> bool {
+        if *req.method() != self.method || req.url().path() != self.path {
+            return false;
+        }
+
+        let query_matches = self.query.iter().all(|(name, value)| {
+            req.url()
+                .query_pairs()
+                .any(|(n, v)| n == name.as_str() && v == value.as_str())
+        });
+        let headers_match = self
+            .headers
+            .iter()
+            .all(|(name, value)| req.headers().get_all(name).iter().any(|sent| sent == value));
+        let body_matches = match self.body {
+            Some(ref body) => {
+                let sent = req.body().map_or(Some(&b""[..]), |b| b.as_bytes());
+                sent == Some(&body[..])
+            }
+            None => true,
+        };
+
+        query_matches && headers_match && body_matches
+    }
+
+    fn next_response(&mut self) -> http::Response<Bytes> {
+        let canned = if self.responses.len() > 1 {
+            self.responses.pop_front()
+        } else {
+            self.responses.front().cloned()
+        };
+        let Some(canned) = canned else {
+            return http::Response::default();
+        };
+
+        let mut res = http::Response::new(canned.body);
+        *res.status_mut() = canned.status;
+        *res.headers_mut() = canned.headers;
+        res
+    }
+
+    fn describe(&self) -> String {
+        format!("{} {}", self.method, self.path)
+    }
+}
+
+impl MockServer {
+    /// Creates a `MockServer` without any mock.
+    pub fn new() -> MockServer {
+        MockServer::default()
+    }
+
+    /// Adds a mock.
+    ///
+    /// When several mocks match a request, the first one added answers.
+    pub fn mock(&self, mock: Mock) -> &MockServer {
+        self.lock().mocks.push((mock, 0));
+        self
+    }
+
+    /// Panics if a mock was not matched as expected, or if a request
+    /// matched no mock.
+    pub fn verify(&self) {
+        let state = self.lock();
+        let mut failures = Vec::new();
+
+        for (mock, hits) in &state.mocks {
+            match mock.times {
+                Some(times) if *hits != times => failures.push(format!(
+                    "{} expected {} times, matched {}",
+                    mock.describe(),
+                    times,
+                    hits
+                )),
+                None if *hits == 0 => {
+                    failures.push(format!("{} expected, never matched", mock.describe()))
+                }
+                _ => (),
+            }
+        }
+        for req in &state.unmatched {
+            failures.push(format!("{} matched no mock", req));
+        }
+
+        if !failures.is_empty() {
+            panic!("mock expectations not met:\n  {}", failures.join("\n  "));
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl MockTransport for MockServer {
+    fn handle(
+        &self,
+        req: Request,
+    ) -> Result<http::Response<Bytes>, Box<dyn StdError + Send + Sync>> {
+        let mut state = self.lock();
+        match state.mocks.iter_mut().find(|(mock, _)| mock.matches(&req)) {
+            Some((mock, hits)) => {
+                *hits += 1;
+                Ok(mock.next_response())
+            }
+            None => {
+                let described = format!("{} {}", req.method(), req.url());
+                state.unmatched.push(described.clone());
+                Err(format!("no mock matches {}", described).into())
+            }
+        }
+    }
+}
+
+impl fmt::Debug for MockServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("MockServer")
+            .field("mocks", &state.mocks.len())
+            .field("unmatched", &state.unmatched.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method, url: &str) -> Request {
+        Request::new(method, url.parse().unwrap())
+    }
+
+    #[test]
+    fn responses_are_queued_and_the_last_repeats() {
+        let server = MockServer::new();
+        server.mock(
+            Mock::new(Method::GET, "/flaky")
+                .respond(503, "")
+                .respond(200, "ok"),
+        );
+
+        let statuses = (0..3)
+            .map(|_| {
+                server
+                    .handle(request(Method::GET, "http://a.test/flaky"))
+                    .unwrap()
+                    .status()
+                    .as_u16()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, [503, 200, 200]);
+        server.verify();
+    }
+
+    #[test]
+    fn matches_query_headers_and_body() {
+        let mock = Mock::new(Method::POST, "/search")
+            .query("q", "rust")
+            .header("content-type", "text/plain")
+            .body("body");
+
+        let mut req = request(Method::POST, "http://a.test/search?page=2&q=rust");
+        req.headers_mut()
+            .insert("content-type", HeaderValue::from_static("text/plain"));
+        *req.body_mut() = Some("body".into());
+        assert!(mock.matches(&req));
+
+        *req.body_mut() = Some("other".into());
+        assert!(!mock.matches(&req));
+
+        let req = request(Method::POST, "http://a.test/search?q=go");
+        assert!(!mock.matches(&req));
+    }
+
+    #[test]
+    #[should_panic(expected = "GET /missing expected, never matched")]
+    fn verify_panics_on_unmet_expectations() {
+        let server = MockServer::new();
+        server.mock(Mock::new(Method::GET, "/missing"));
+        server.verify();
+    }
+
+    #[test]
+    fn unmatched_requests_fail() {
+        let server = MockServer::new();
+        let err = server
+            .handle(request(Method::DELETE, "http://a.test/"))
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("no mock matches DELETE http://a.test/"));
+    }
+}
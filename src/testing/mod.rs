@@ -0,0 +1,12 @@
+//! Utilities for testing code built on top of rquest.
+//!
+//! # Optional
+//!
+//! This requires the optional `chaos`, `mock` or `vcr` features to be enabled.
+
+#[cfg(feature = "chaos")]
+pub mod chaos;
+#[cfg(feature = "mock")]
+pub mod mock;
+#[cfg(feature = "vcr")]
+pub mod vcr;
@@ -0,0 +1,540 @@
+//! Recording and replaying of HTTP interactions.
+//!
+//! A [`Vcr`] records the requests of a client and their responses to a
+//! cassette file, and replays them from it later without any network access,
+//! so tests against third-party APIs are deterministic.
+//!
+//! ```rust,no_run
+//! use rquest::testing::vcr::VcrMode;
+//!
+//! # async fn run() -> Result<(), rquest::Error> {
+//! let client = rquest::Client::builder()
+//!     .vcr(VcrMode::Auto, "tests/cassettes/users.json")
+//!     .build()?;
+//! let user = client
+//!     .get("https://api.example.com/users/1")
+//!     .bearer_auth("secret")
+//!     .send()
+//!     .await?
+//!     .text()
+//!     .await?;
+//! # drop(user);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The cassette is a JSON file. The values of the `Authorization`,
+//! `Proxy-Authorization`, `Cookie` and `Set-Cookie` headers are always
+//! redacted from it, so it can be committed along with the tests.
+
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http::header::{
+    HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, SET_COOKIE,
+};
+use http::StatusCode;
+use serde_json::{json, Value};
+use tower_layer::Layer;
+use tower_service::Service;
+use url::Url;
+
+use crate::error;
+use crate::middleware::ClientService;
+use crate::{Method, Request, Response, ResponseBuilderExt};
+
+type BoxFuture = Pin<Box<dyn Future<Output = crate::Result<Response>> + Send>>;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Whether a [`Vcr`] records or replays its cassette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VcrMode {
+    /// Send requests to the network, and record them to a new cassette.
+    Record,
+    /// Answer requests from the cassette, failing those it has no match for.
+    Replay,
+    /// Replay the cassette if it exists, else record it.
+    Auto,
+}
+
+/// A tower layer recording or replaying the requests of a client.
+///
+/// Added with [`ClientBuilder::vcr`](crate::ClientBuilder::vcr), or with
+/// [`ClientBuilder::layer`](crate::ClientBuilder::layer) to configure it.
+///
+/// A request matches a recorded one with the same method and URL, and the
+/// headers and body it is also matched on, if any. Recorded requests are
+/// replayed in order, the last one matching for every further request.
+#[derive(Clone)]
+pub struct Vcr {
+    config: Arc<Config>,
+    cassette: Arc<Mutex<Option<Cassette>>>,
+}
+
+struct Config {
+    mode: VcrMode,
+    path: PathBuf,
+    match_headers: Vec<HeaderName>,
+    match_body: bool,
+    redact_headers: Vec<HeaderName>,
+    body_limit: usize,
+}
+
+/// The service of a [`Vcr`] layer.
+#[derive(Clone)]
+pub struct VcrService {
+    vcr: Vcr,
+    inner: ClientService,
+}
+
+struct Cassette {
+    recording: bool,
+    interactions: Vec<(Interaction, bool)>,
+}
+
+struct Interaction {
+    method: Method,
+    url: Url,
+    headers: HeaderMap,
+    body: Option<Bytes>,
+    status: StatusCode,
+    response_url: Url,
+    response_headers: HeaderMap,
+    response_body: Bytes,
+}
+
+impl Vcr {
+    /// Creates a `Vcr` using the cassette at `path`.
+    pub fn new(mode: VcrMode, path: impl Into<PathBuf>) -> Vcr {
+        Vcr {
+            config: Arc::new(Config {
+                mode,
+                path: path.into(),
+                match_headers: Vec::new(),
+                match_body: false,
+                redact_headers: vec![AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE, SET_COOKIE],
+                body_limit: 1024 * 1024,
+            }),
+            cassette: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Also match requests on the values of the header `name`.
+    ///
+    /// A redacted header matches on its name only.
+    pub fn match_header(mut self, name: HeaderName) -> Vcr {
+        self.config_mut().match_headers.push(name);
+        self
+    }
+
+    /// Also match requests on their body.
+    ///
+    /// Default is `false`.
+    pub fn match_body(mut self, enabled: bool) -> Vcr {
+        self.config_mut().match_body = enabled;
+        self
+    }
+
+    /// Redact the values of the header `name`, of requests and responses,
+    /// in the cassette.
+    pub fn redact_header(mut self, name: HeaderName) -> Vcr {
+        self.config_mut().redact_headers.push(name);
+        self
+    }
+
+    /// Record at most `limit` bytes of each body.
+    ///
+    /// A longer body is cut, and replayed cut. Default is 1 MiB.
+    pub fn body_limit(mut self, limit: usize) -> Vcr {
+        self.config_mut().body_limit = limit;
+        self
+    }
+
+    fn config_mut(&mut self) -> &mut Config {
+        Arc::get_mut(&mut self.config).expect("Vcr is configured before use")
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Cassette>> {
+        self.cassette.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Loads the cassette on first use, to report a missing one as a
+    /// request error.
+    fn recording(&self) -> crate::Result<bool> {
+        let mut cassette = self.lock();
+        if let Some(ref cassette) = *cassette {
+            return Ok(cassette.recording);
+        }
+
+        let path = &self.config.path;
+        let recording = match self.config.mode {
+            VcrMode::Record => true,
+            VcrMode::Replay => false,
+            VcrMode::Auto => !path.exists(),
+        };
+        let interactions = if recording {
+            Vec::new()
+        } else {
+            load(path).map_err(|e| {
+                error::request(format!("cannot load cassette {}: {}", path.display(), e))
+            })?
+        };
+        *cassette = Some(Cassette {
+            recording,
+            interactions: interactions.into_iter().map(|i| (i, false)).collect(),
+        });
+        Ok(recording)
+    }
+
+    fn redact(&self, headers: &HeaderMap) -> HeaderMap {
+        let mut headers = headers.clone();
+        for name in &self.config.redact_headers {
+            if let http::header::Entry::Occupied(mut entry) = headers.entry(name) {
+                entry.insert(HeaderValue::from_static(REDACTED));
+            }
+        }
+        headers
+    }
+
+    fn limit(&self, body: &[u8]) -> Bytes {
+        Bytes::copy_from_slice(&body[..body.len().min(self.config.body_limit)])
+    }
+
+    fn matches(&self, interaction: &Interaction, req: &Request, headers: &HeaderMap) -> bool {
+        if interaction.method != *req.method() || interaction.url != *req.url() {
+            return false;
+        }
+
+        let headers_match = self.config.match_headers.iter().all(|name| {
+            interaction
+                .headers
+                .get_all(name)
+                .iter()
+                .eq(headers.get_all(name).iter())
+        });
+        let body_matches = !self.config.match_body || {
+            let body = req.body().and_then(|b| b.as_bytes()).map(|b| self.limit(b));
+            interaction.body == body
+        };
+
+        headers_match && body_matches
+    }
+
+    fn replay(&self, req: &Request) -> crate::Result<Response> {
+        let headers = self.redact(req.headers());
+        let mut cassette = self.lock();
+        let cassette = cassette.as_mut().expect("cassette loaded");
+
+        let matching = cassette
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, (interaction, _))| self.matches(interaction, req, &headers))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        let next = matching
+            .iter()
+            .find(|&&index| !cassette.interactions[index].1)
+            .or(matching.last());
+        let Some(&index) = next else {
+            return Err(error::request(format!(
+                "no interaction in cassette {} matches {} {}",
+                self.config.path.display(),
+                req.method(),
+                req.url()
+            ))
+            .with_url(req.url().clone()));
+        };
+        let (interaction, replayed) = &mut cassette.interactions[index];
+        *replayed = true;
+
+        let mut res = http::Response::builder()
+            .status(interaction.status)
+            .url(interaction.response_url.clone())
+            .body(interaction.response_body.clone())
+            .expect("valid response parts");
+        *res.headers_mut() = interaction.response_headers.clone();
+        Ok(Response::from(res))
+    }
+
+    fn record(&self, interaction: Interaction) -> crate::Result<()> {
+        let mut cassette = self.lock();
+        let cassette = cassette.as_mut().expect("cassette loaded");
+        cassette.interactions.push((interaction, true));
+        save(&self.config.path, &cassette.interactions).map_err(|e| {
+            error::request(format!(
+                "cannot save cassette {}: {}",
+                self.config.path.display(),
+                e
+            ))
+        })
+    }
+}
+
+impl Layer<ClientService> for Vcr {
+    type Service = VcrService;
+
+    fn layer(&self, inner: ClientService) -> VcrService {
+        VcrService {
+            vcr: self.clone(),
+            inner,
+        }
+    }
+}
+
+impl fmt::Debug for Vcr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Vcr")
+            .field("mode", &self.config.mode)
+            .field("path", &self.config.path)
+            .field("match_headers", &self.config.match_headers)
+            .field("match_body", &self.config.match_body)
+            .field("redact_headers", &self.config.redact_headers)
+            .field("body_limit", &self.config.body_limit)
+            .finish()
+    }
+}
+
+impl Service<Request> for VcrService {
+    type Response = Response;
+    type Error = crate::Error;
+    type Future = BoxFuture;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let vcr = self.vcr.clone();
+        match vcr.recording() {
+            Ok(false) => return Box::pin(async move { vcr.replay(&req) }),
+            Ok(true) => (),
+            Err(err) => return Box::pin(async move { Err(err) }),
+        }
+
+        let method = req.method().clone();
+        let url = req.url().clone();
+        let headers = vcr.redact(req.headers());
+        let body = req.body().and_then(|b| b.as_bytes()).map(|b| vcr.limit(b));
+
+        // Drive the service that was readied, keeping a clone for next time.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let res = inner.call(req).await?;
+            let status = res.status();
+            let version = res.version();
+            let response_url = res.url().clone();
+            let response_headers = res.headers().clone();
+            let response_body = res.bytes().await?;
+
+            vcr.record(Interaction {
+                method,
+                url,
+                headers,
+                body,
+                status,
+                response_url: response_url.clone(),
+                response_headers: vcr.redact(&response_headers),
+                response_body: vcr.limit(&response_body),
+            })?;
+
+            let mut res = http::Response::builder()
+                .status(status)
+                .version(version)
+                .url(response_url)
+                .body(response_body)
+                .expect("valid response parts");
+            *res.headers_mut() = response_headers;
+            Ok(Response::from(res))
+        })
+    }
+}
+
+impl fmt::Debug for VcrService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VcrService")
+            .field("vcr", &self.vcr)
+            .finish()
+    }
+}
+
+// ===== cassette files =====
+
+fn load(path: &Path) -> io::Result<Vec<Interaction>> {
+    let json: Value = serde_json::from_slice(&std::fs::read(path)?)?;
+    json["interactions"]
+        .as_array()
+        .ok_or_else(|| invalid("missing interactions"))?
+        .iter()
+        .map(interaction_from_json)
+        .collect()
+}
+
+fn save(path: &Path, interactions: &[(Interaction, bool)]) -> io::Result<()> {
+    let interactions = interactions
+        .iter()
+        .map(|(interaction, _)| interaction_to_json(interaction))
+        .collect::<Vec<_>>();
+    let json = serde_json::to_vec_pretty(&json!({ "interactions": interactions }))?;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, json)
+}
+
+fn interaction_to_json(interaction: &Interaction) -> Value {
+    json!({
+        "request": {
+            "method": interaction.method.as_str(),
+            "url": interaction.url.as_str(),
+            "headers": headers_to_json(&interaction.headers),
+            "body": interaction.body.as_deref().map(body_to_json),
+        },
+        "response": {
+            "status": interaction.status.as_u16(),
+            "url": interaction.response_url.as_str(),
+            "headers": headers_to_json(&interaction.response_headers),
+            "body": body_to_json(&interaction.response_body),
+        },
+    })
+}
+
+fn interaction_from_json(json: &Value) -> io::Result<Interaction> {
+    let (req, res) = (&json["request"], &json["response"]);
+    let url = |json: &Value| {
+        json.as_str()
+            .and_then(|url| Url::parse(url).ok())
+            .ok_or_else(|| invalid("invalid url"))
+    };
+
+    Ok(Interaction {
+        method: req["method"]
+            .as_str()
+            .and_then(|method| method.parse().ok())
+            .ok_or_else(|| invalid("invalid method"))?,
+        url: url(&req["url"])?,
+        headers: headers_from_json(&req["headers"])?,
+        body: match req["body"] {
+            Value::Null => None,
+            ref body => Some(body_from_json(body)?),
+        },
+        status: res["status"]
+            .as_u64()
+            .and_then(|status| StatusCode::from_u16(status as u16).ok())
+            .ok_or_else(|| invalid("invalid status"))?,
+        response_url: url(&res["url"])?,
+        response_headers: headers_from_json(&res["headers"])?,
+        response_body: body_from_json(&res["body"])?,
+    })
+}
+
+fn headers_to_json(headers: &HeaderMap) -> Value {
+    headers
+        .iter()
+        .map(|(name, value)| json!([name.as_str(), String::from_utf8_lossy(value.as_bytes())]))
+        .collect()
+}
+
+fn headers_from_json(json: &Value) -> io::Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for header in json.as_array().ok_or_else(|| invalid("invalid headers"))? {
+        let (Some(name), Some(value)) = (header[0].as_str(), header[1].as_str()) else {
+            return Err(invalid("invalid header"));
+        };
+        headers.append(
+            HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid("invalid header name"))?,
+            HeaderValue::from_str(value).map_err(|_| invalid("invalid header value"))?,
+        );
+    }
+    Ok(headers)
+}
+
+/// Text bodies are kept readable, others are stored as arrays of bytes.
+fn body_to_json(body: &[u8]) -> Value {
+    match std::str::from_utf8(body) {
+        Ok(text) => Value::from(text),
+        Err(_) => Value::from(body),
+    }
+}
+
+fn body_from_json(json: &Value) -> io::Result<Bytes> {
+    match json {
+        Value::String(text) => Ok(Bytes::from(text.clone())),
+        Value::Array(bytes) => bytes
+            .iter()
+            .map(|byte| {
+                byte.as_u64()
+                    .and_then(|byte| u8::try_from(byte).ok())
+                    .ok_or_else(|| invalid("invalid body"))
+            })
+            .collect::<io::Result<Vec<u8>>>()
+            .map(Bytes::from),
+        _ => Err(invalid("invalid body")),
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interaction() -> Interaction {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        headers.insert("accept", HeaderValue::from_static("*/*"));
+        Interaction {
+            method: Method::POST,
+            url: "http://a.test/users".parse().unwrap(),
+            headers,
+            body: Some(Bytes::from_static(b"name=ferris")),
+            status: StatusCode::CREATED,
+            response_url: "http://a.test/users/1".parse().unwrap(),
+            response_headers: HeaderMap::new(),
+            response_body: Bytes::from_static(b"\x89PNG\xff"),
+        }
+    }
+
+    #[test]
+    fn interactions_round_trip_through_json() {
+        let json = interaction_to_json(&interaction());
+        let parsed = interaction_from_json(&json).unwrap();
+
+        assert_eq!(parsed.method, Method::POST);
+        assert_eq!(parsed.url.as_str(), "http://a.test/users");
+        assert_eq!(parsed.headers["accept"], "*/*");
+        assert_eq!(parsed.body.as_deref(), Some(&b"name=ferris"[..]));
+        assert_eq!(parsed.status, StatusCode::CREATED);
+        assert_eq!(parsed.response_url.as_str(), "http://a.test/users/1");
+        assert_eq!(parsed.response_body, &b"\x89PNG\xff"[..]);
+        assert!(json["response"]["body"].is_array());
+    }
+
+    #[test]
+    fn credentials_are_redacted() {
+        let vcr = Vcr::new(VcrMode::Record, "unused.json")
+            .redact_header(HeaderName::from_static("accept"));
+        let headers = vcr.redact(&interaction().headers);
+
+        assert_eq!(headers[AUTHORIZATION], REDACTED);
+        assert_eq!(headers["accept"], REDACTED);
+    }
+
+    #[test]
+    fn bodies_are_cut_to_the_limit() {
+        let vcr = Vcr::new(VcrMode::Record, "unused.json").body_limit(4);
+        assert_eq!(vcr.limit(b"0123456789"), &b"0123"[..]);
+        assert_eq!(vcr.limit(b"01"), &b"01"[..]);
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use antidote::Mutex;
+use boring::ssl::SslRef;
+use http::Uri;
+
+/// Connections to an origin that must have ended up on HTTP/1.1 before it
+/// is connected to without offering `h2`.
+const MIN_HISTORY: u8 = 3;
+
+/// How long an origin is connected to without offering `h2`, before `h2` is
+/// offered again in case it has been enabled since.
+const REPROBE_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// Counters of the adaptive HTTP version cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HttpVersionCacheStats {
+    /// Origins with a recorded history.
+    pub origins: usize,
+    /// Origins currently connected to without offering `h2`.
+    pub http1_preferred: usize,
+    /// Connections made without offering `h2`, because of the cache.
+    pub http1_connects: u64,
+}
+
+/// Remembers, per origin, which protocol the last connections negotiated
+/// through ALPN, so that origins that never pick `h2` are connected to with
+/// an HTTP/1.1-only ALPN.
+#[derive(Default)]
+pub(crate) struct AdaptiveVersionCache {
+    origins: Mutex<HashMap<Origin, History>>,
+    http1_connects: AtomicU64,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Origin {
+    host: String,
+    port: u16,
+}
+
+struct History {
+    /// The last 8 negotiations, most recent in the low bit, 1 for `h2`.
+    recent: u8,
+    len: u8,
+    updated: Instant,
+}
+
+impl History {
+    fn prefers_http1(&self, now: Instant) -> bool {
+        self.len >= MIN_HISTORY
+            && self.recent == 0
+            && now.duration_since(self.updated) < REPROBE_AFTER
+    }
+}
+
+impl AdaptiveVersionCache {
+    /// Whether `dst` should be connected to without offering `h2`.
+    pub(crate) fn prefers_http1(&self, dst: &Uri) -> bool {
+        let Some(origin) = origin(dst) else {
+            return false;
+        };
+
+        let prefers = self
+            .origins
+            .lock()
+            .get(&origin)
+            .map_or(false, |history| history.prefers_http1(Instant::now()));
+        if prefers {
+            self.http1_connects.fetch_add(1, Ordering::Relaxed);
+        }
+        prefers
+    }
+
+    /// Records the protocol a connection to `dst` offering `h2` negotiated.
+    pub(crate) fn record(&self, dst: &Uri, ssl: &SslRef) {
+        if let Some(origin) = origin(dst) {
+            self.record_h2(origin, ssl.selected_alpn_protocol() == Some(b"h2"));
+        }
+    }
+
+    fn record_h2(&self, origin: Origin, h2: bool) {
+        let mut origins = self.origins.lock();
+        let history = origins.entry(origin).or_insert(History {
+            recent: 0,
+            len: 0,
+            updated: Instant::now(),
+        });
+        history.recent = (history.recent << 1) | h2 as u8;
+        history.len = history.len.saturating_add(1).min(8);
+        history.updated = Instant::now();
+    }
+
+    pub(crate) fn stats(&self) -> HttpVersionCacheStats {
+        let origins = self.origins.lock();
+        let now = Instant::now();
+        HttpVersionCacheStats {
+            origins: origins.len(),
+            http1_preferred: origins
+                .values()
+                .filter(|history| history.prefers_http1(now))
+                .count(),
+            http1_connects: self.http1_connects.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn origin(dst: &Uri) -> Option<Origin> {
+    Some(Origin {
+        host: dst.host()?.to_owned(),
+        port: dst.port_u16().unwrap_or(443),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_http1_after_repeated_http1() {
+        let cache = AdaptiveVersionCache::default();
+        let dst: Uri = "https://h1.test/".parse().unwrap();
+        let origin = origin(&dst).unwrap();
+
+        for _ in 0..MIN_HISTORY - 1 {
+            cache.record_h2(origin.clone(), false);
+            assert!(!cache.prefers_http1(&dst));
+        }
+        cache.record_h2(origin, false);
+        assert!(cache.prefers_http1(&dst));
+
+        let stats = cache.stats();
+        assert_eq!(stats.origins, 1);
+        assert_eq!(stats.http1_preferred, 1);
+        assert_eq!(stats.http1_connects, 1);
+    }
+
+    #[test]
+    fn any_recent_h2_keeps_offering_h2() {
+        let cache = AdaptiveVersionCache::default();
+        let dst: Uri = "https://mixed.test:8443/".parse().unwrap();
+        let origin = origin(&dst).unwrap();
+
+        cache.record_h2(origin.clone(), true);
+        for _ in 0..7 {
+            cache.record_h2(origin.clone(), false);
+        }
+        assert!(!cache.prefers_http1(&dst));
+
+        // the h2 negotiation falls out of the window
+        cache.record_h2(origin, false);
+        assert!(cache.prefers_http1(&dst));
+    }
+}
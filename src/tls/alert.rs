@@ -0,0 +1,196 @@
+use super::TlsResult;
+use boring::ex_data::Index;
+use boring::ssl::{SslConnectorBuilder, SslContext, SslRef};
+use boring_sys as ffi;
+use foreign_types::ForeignTypeRef;
+use std::os::raw::c_int;
+use std::sync::{Arc, LazyLock};
+
+/// A callback told about the TLS alerts sent and received.
+pub type TlsAlertCallback = Arc<dyn Fn(TlsAlert) + Send + Sync>;
+
+/// A TLS alert sent or received on a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TlsAlert {
+    /// Whether the alert was sent or received.
+    pub direction: AlertDirection,
+    /// The level of the alert.
+    pub level: AlertLevel,
+    /// The description of the alert.
+    pub description: AlertDescription,
+}
+
+/// Whether a [`TlsAlert`] was sent or received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertDirection {
+    /// The alert was sent by the server.
+    Received,
+    /// The alert was sent to the server.
+    Sent,
+}
+
+/// The level of a [`TlsAlert`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertLevel {
+    /// A warning, the connection may go on.
+    Warning,
+    /// A fatal alert, the connection is closed.
+    Fatal,
+    /// Any other level, with its code.
+    Other(u8),
+}
+
+impl From<u8> for AlertLevel {
+    fn from(code: u8) -> AlertLevel {
+        match code {
+            1 => AlertLevel::Warning,
+            2 => AlertLevel::Fatal,
+            code => AlertLevel::Other(code),
+        }
+    }
+}
+
+macro_rules! alert_descriptions {
+    ($($(#[$doc:meta])* $name:ident = $code:literal,)+) => {
+        /// The description of a [`TlsAlert`], from the TLS alert registry.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum AlertDescription {
+            $($(#[$doc])* $name,)+
+            /// Any other description, with its code.
+            Other(u8),
+        }
+
+        impl AlertDescription {
+            /// The code of the description.
+            pub fn code(&self) -> u8 {
+                match self {
+                    $(AlertDescription::$name => $code,)+
+                    AlertDescription::Other(code) => *code,
+                }
+            }
+        }
+
+        impl From<u8> for AlertDescription {
+            fn from(code: u8) -> AlertDescription {
+                match code {
+                    $($code => AlertDescription::$name,)+
+                    code => AlertDescription::Other(code),
+                }
+            }
+        }
+    };
+}
+
+alert_descriptions! {
+    /// `close_notify`
+    CloseNotify = 0,
+    /// `unexpected_message`
+    UnexpectedMessage = 10,
+    /// `bad_record_mac`
+    BadRecordMac = 20,
+    /// `record_overflow`
+    RecordOverflow = 22,
+    /// `handshake_failure`
+    HandshakeFailure = 40,
+    /// `bad_certificate`
+    BadCertificate = 42,
+    /// `unsupported_certificate`
+    UnsupportedCertificate = 43,
+    /// `certificate_revoked`
+    CertificateRevoked = 44,
+    /// `certificate_expired`
+    CertificateExpired = 45,
+    /// `certificate_unknown`
+    CertificateUnknown = 46,
+    /// `illegal_parameter`
+    IllegalParameter = 47,
+    /// `unknown_ca`
+    UnknownCa = 48,
+    /// `access_denied`
+    AccessDenied = 49,
+    /// `decode_error`
+    DecodeError = 50,
+    /// `decrypt_error`
+    DecryptError = 51,
+    /// `protocol_version`
+    ProtocolVersion = 70,
+    /// `insufficient_security`
+    InsufficientSecurity = 71,
+    /// `internal_error`
+    InternalError = 80,
+    /// `inappropriate_fallback`
+    InappropriateFallback = 86,
+    /// `user_canceled`
+    UserCanceled = 90,
+    /// `missing_extension`
+    MissingExtension = 109,
+    /// `unsupported_extension`
+    UnsupportedExtension = 110,
+    /// `unrecognized_name`
+    UnrecognizedName = 112,
+    /// `bad_certificate_status_response`
+    BadCertificateStatusResponse = 113,
+    /// `unknown_psk_identity`
+    UnknownPskIdentity = 115,
+    /// `certificate_required`
+    CertificateRequired = 116,
+    /// `no_application_protocol`
+    NoApplicationProtocol = 120,
+    /// `ech_required`
+    EchRequired = 121,
+}
+
+fn callback_index() -> TlsResult<Index<SslContext, TlsAlertCallback>> {
+    static IDX: LazyLock<TlsResult<Index<SslContext, TlsAlertCallback>>> =
+        LazyLock::new(SslContext::new_ex_index);
+    IDX.clone()
+}
+
+/// Calls `callback` with the alerts of the connections of `builder`.
+pub(super) fn set_callback(
+    builder: &mut SslConnectorBuilder,
+    callback: TlsAlertCallback,
+) -> TlsResult<()> {
+    builder.set_ex_data(callback_index()?, callback);
+    unsafe { ffi::SSL_CTX_set_info_callback(builder.as_ptr(), Some(info_callback)) };
+    Ok(())
+}
+
+unsafe extern "C" fn info_callback(ssl: *const ffi::SSL, type_: c_int, value: c_int) {
+    let is = |flag: u32| type_ as u32 & flag == flag;
+    let direction = if is(ffi::SSL_CB_READ_ALERT as u32) {
+        AlertDirection::Received
+    } else if is(ffi::SSL_CB_WRITE_ALERT as u32) {
+        AlertDirection::Sent
+    } else {
+        return;
+    };
+
+    // BoringSSL calls this before closing the connection on a fatal alert.
+    let ssl = SslRef::from_ptr(ssl as *mut _);
+    if let Some(callback) = callback_index()
+        .ok()
+        .and_then(|idx| ssl.ssl_context().ex_data(idx))
+    {
+        callback(TlsAlert {
+            direction,
+            level: ((value >> 8) as u8).into(),
+            description: (value as u8).into(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alert_description_codes_roundtrip() {
+        let description = AlertDescription::from(40);
+        assert_eq!(description, AlertDescription::HandshakeFailure);
+        assert_eq!(description.code(), 40);
+
+        assert_eq!(AlertDescription::from(200), AlertDescription::Other(200));
+        assert_eq!(AlertLevel::from(2), AlertLevel::Fatal);
+    }
+}
@@ -0,0 +1,185 @@
+use super::Version;
+use boring_sys as ffi;
+use std::{ffi::CStr, os::raw::c_char, ptr, sync::LazyLock};
+
+/// A cipher suite BoringSSL supports, as returned by
+/// [`TlsSettings::supported_ciphers`](super::TlsSettings::supported_ciphers).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CipherInfo {
+    /// The OpenSSL name, such as `ECDHE-RSA-AES128-GCM-SHA256`.
+    pub name: String,
+    /// The IANA name, such as `TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256`.
+    pub standard_name: String,
+    /// The lowest TLS version the cipher can be used with.
+    pub protocol: Version,
+    /// The authentication: `RSA`, `ECDSA`, `PSK`, or `ANY` for TLS 1.3
+    /// ciphers.
+    pub auth: String,
+    /// The key exchange: `RSA`, `ECDHE`, `PSK`, or `ANY` for TLS 1.3
+    /// ciphers.
+    pub kex: String,
+    /// The strength of the cipher, in bits.
+    pub bits: u32,
+}
+
+/// The ciphers BoringSSL knows, in the order of their values.
+pub(crate) fn supported_ciphers() -> &'static [CipherInfo] {
+    static CIPHERS: LazyLock<Vec<CipherInfo>> = LazyLock::new(|| {
+        (0..=u16::MAX)
+            .filter_map(|value| {
+                // SAFETY: the cipher table is static, the returned pointer
+                // is null or lives for the whole program.
+                let cipher = unsafe { ffi::SSL_get_cipher_by_value(value) };
+                if cipher.is_null() {
+                    None
+                } else {
+                    Some(unsafe { cipher_info(cipher) })
+                }
+            })
+            .collect()
+    });
+    &CIPHERS
+}
+
+unsafe fn cipher_info(cipher: *const ffi::SSL_CIPHER) -> CipherInfo {
+    let protocol = match ffi::SSL_CIPHER_get_min_version(cipher) as u32 {
+        ffi::TLS1_3_VERSION => Version::TLS_1_3,
+        ffi::TLS1_2_VERSION => Version::TLS_1_2,
+        ffi::TLS1_1_VERSION => Version::TLS_1_1,
+        _ => Version::TLS_1_0,
+    };
+    let auth = match ffi::SSL_CIPHER_get_auth_nid(cipher) as u32 {
+        ffi::NID_auth_rsa => "RSA",
+        ffi::NID_auth_ecdsa => "ECDSA",
+        ffi::NID_auth_psk => "PSK",
+        _ => "ANY",
+    };
+    let kex = match ffi::SSL_CIPHER_get_kx_nid(cipher) as u32 {
+        ffi::NID_kx_rsa => "RSA",
+        ffi::NID_kx_ecdhe => "ECDHE",
+        ffi::NID_kx_psk => "PSK",
+        _ => "ANY",
+    };
+    CipherInfo {
+        name: c_string(ffi::SSL_CIPHER_get_name(cipher)),
+        standard_name: c_string(ffi::SSL_CIPHER_standard_name(cipher)),
+        protocol,
+        auth: auth.to_owned(),
+        kex: kex.to_owned(),
+        bits: ffi::SSL_CIPHER_get_bits(cipher, ptr::null_mut()) as u32,
+    }
+}
+
+unsafe fn c_string(s: *const c_char) -> String {
+    if s.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(s).to_string_lossy().into_owned()
+    }
+}
+
+/// The cipher named `name`, by its OpenSSL or IANA name.
+pub(crate) fn find_cipher(name: &str) -> Option<&'static CipherInfo> {
+    supported_ciphers()
+        .iter()
+        .find(|cipher| cipher.name == name || cipher.standard_name == name)
+}
+
+/// The rule keywords of BoringSSL's cipher list syntax, from
+/// `kCipherAliases` in `ssl_cipher.cc`.
+const CIPHER_ALIASES: &[&str] = &[
+    "ALL",
+    "kRSA",
+    "RSA",
+    "kECDHE",
+    "kEECDH",
+    "ECDHE",
+    "EECDH",
+    "kPSK",
+    "aRSA",
+    "aECDSA",
+    "ECDSA",
+    "aPSK",
+    "3DES",
+    "AES128",
+    "AES256",
+    "AES",
+    "AESGCM",
+    "CHACHA20",
+    "SHA1",
+    "SHA",
+    "SHA256",
+    "SHA384",
+    "SSLv3",
+    "TLSv1",
+    "TLSv1.2",
+    "HIGH",
+    "FIPS",
+    "DEFAULT",
+    "@STRENGTH",
+];
+
+/// Returns the first name of the `cipher_list` rules that is neither a
+/// cipher nor a rule keyword.
+///
+/// The rules are separated by `:`, and may be prefixed with `!`, `-` or
+/// `+`, join keywords with `+`, or group equally preferred ciphers as
+/// `[A|B]`.
+pub(crate) fn find_unknown_cipher(cipher_list: &str) -> Option<&str> {
+    let known = |name: &str| CIPHER_ALIASES.contains(&name) || find_cipher(name).is_some();
+
+    cipher_list
+        .split(':')
+        .map(|rule| rule.trim_start_matches(['!', '-', '+']))
+        .map(|rule| {
+            rule.strip_prefix('[')
+                .and_then(|group| group.strip_suffix(']'))
+                .unwrap_or(rule)
+        })
+        .flat_map(|rule| rule.split(['|', '+']))
+        .find(|name| !known(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supported_ciphers_are_listed() {
+        let ciphers = supported_ciphers();
+        let cipher = ciphers
+            .iter()
+            .find(|cipher| cipher.standard_name == "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256")
+            .unwrap();
+        assert_eq!(cipher.name, "ECDHE-RSA-AES128-GCM-SHA256");
+        assert_eq!(cipher.protocol, Version::TLS_1_2);
+        assert_eq!(cipher.auth, "RSA");
+        assert_eq!(cipher.kex, "ECDHE");
+        assert_eq!(cipher.bits, 128);
+
+        let cipher = ciphers
+            .iter()
+            .find(|cipher| cipher.standard_name == "TLS_AES_256_GCM_SHA384")
+            .unwrap();
+        assert_eq!(cipher.protocol, Version::TLS_1_3);
+        assert_eq!(cipher.kex, "ANY");
+        assert_eq!(cipher.bits, 256);
+    }
+
+    #[test]
+    fn cipher_list_rules_are_checked() {
+        for list in [
+            "TLS_AES_128_GCM_SHA256:TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+            "ECDHE-RSA-AES128-GCM-SHA256:ECDHE+AESGCM:!aPSK:@STRENGTH",
+            "[ECDHE-ECDSA-AES128-GCM-SHA256|ECDHE-ECDSA-CHACHA20-POLY1305]:ALL",
+        ] {
+            assert_eq!(find_unknown_cipher(list), None, "{list}");
+        }
+        assert_eq!(
+            find_unknown_cipher("TLS_AES_128_GCM_SHA256:TLS_FAKE_CIPHER"),
+            Some("TLS_FAKE_CIPHER")
+        );
+        assert_eq!(find_unknown_cipher("ALL:"), Some(""));
+    }
+}
@@ -1,10 +1,13 @@
 /// referrer: https://github.com/cloudflare/boring/blob/master/hyper-boring/src/cache.rs
 use boring::ssl::SslVersion;
 use boring::ssl::{SslSession, SslSessionRef};
+use boring_sys as ffi;
+use foreign_types::ForeignTypeRef;
 use linked_hash_set::LinkedHashSet;
 use std::borrow::Borrow;
 use std::collections::hash_map::{Entry, HashMap};
 use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
 
 #[derive(Hash, PartialEq, Eq, Clone)]
 pub struct SessionKey {
@@ -38,44 +41,127 @@ impl Borrow<[u8]> for HashSession {
     }
 }
 
+/// Counters of the TLS session cache, for monitoring session resumption.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SessionCacheStats {
+    /// Handshakes offered a cached session.
+    pub hits: u64,
+    /// Handshakes without a cached session for their host.
+    pub misses: u64,
+    /// Sessions dropped because the cache was full or they expired.
+    pub evictions: u64,
+}
+
+impl SessionCacheStats {
+    pub(crate) fn merge(self, other: SessionCacheStats) -> SessionCacheStats {
+        SessionCacheStats {
+            hits: self.hits + other.hits,
+            misses: self.misses + other.misses,
+            evictions: self.evictions + other.evictions,
+        }
+    }
+}
+
+/// When a cached session was stored, and how long it may be resumed.
+struct Stored {
+    inserted: Instant,
+    lifetime: Option<Duration>,
+}
+
 pub struct SessionCache {
     sessions: HashMap<SessionKey, LinkedHashSet<HashSession>>,
-    reverse: HashMap<HashSession, SessionKey>,
-    /// Maximum capacity of LinkedHashSet per SessionKey
-    per_key_session_capacity: usize,
+    reverse: HashMap<HashSession, (SessionKey, Stored)>,
+    /// All sessions, least recently used first.
+    lru: LinkedHashSet<HashSession>,
+    /// Maximum number of sessions across all keys
+    capacity: usize,
+    ttl: Option<Duration>,
+    /// The most the ticket lifetime a server advertises is trusted for.
+    max_ticket_lifetime: Option<Duration>,
+    stats: SessionCacheStats,
 }
 
 impl SessionCache {
-    pub fn with_capacity(per_key_session_capacity: usize) -> SessionCache {
+    pub fn new(
+        capacity: usize,
+        ttl: Option<Duration>,
+        max_ticket_lifetime: Option<Duration>,
+    ) -> SessionCache {
         SessionCache {
             sessions: HashMap::new(),
             reverse: HashMap::new(),
-            per_key_session_capacity,
+            lru: LinkedHashSet::new(),
+            capacity,
+            ttl,
+            max_ticket_lifetime,
+            stats: SessionCacheStats::default(),
         }
     }
 
     pub fn insert(&mut self, key: SessionKey, session: SslSession) {
-        let session = HashSession(session);
+        if self.capacity == 0 {
+            return;
+        }
 
-        let sessions = self.sessions.entry(key.clone()).or_default();
+        let hint = unsafe { ffi::SSL_SESSION_get_ticket_lifetime_hint(session.as_ptr()) };
+        let tls13 = session.protocol_version() == SslVersion::TLS1_3;
+        let Some(lifetime) = ticket_lifetime(hint, tls13, self.max_ticket_lifetime) else {
+            return;
+        };
 
-        // if sessions exceed capacity, discard oldest
-        if sessions.len() >= self.per_key_session_capacity {
-            if let Some(hash) = sessions.pop_front() {
-                self.reverse.remove(&hash);
+        // if the cache is full, discard the least recently used session
+        while self.lru.len() >= self.capacity {
+            match self.lru.front().cloned() {
+                Some(hash) => {
+                    self.remove(&hash.0);
+                    self.stats.evictions += 1;
+                }
+                None => break,
             }
         }
 
-        sessions.insert(session.clone());
-        self.reverse.insert(session, key);
+        let session = HashSession(session);
+
+        self.sessions
+            .entry(key.clone())
+            .or_default()
+            .insert(session.clone());
+        self.lru.insert(session.clone());
+        self.reverse.insert(
+            session,
+            (
+                key,
+                Stored {
+                    inserted: Instant::now(),
+                    lifetime,
+                },
+            ),
+        );
     }
 
     pub fn get(&mut self, key: &SessionKey) -> Option<SslSession> {
-        let session = {
-            let sessions = self.sessions.get_mut(key)?;
-            sessions.front().cloned()?.0
+        let session = loop {
+            let Some(session) = self.sessions.get(key).and_then(|s| s.front()).cloned() else {
+                self.stats.misses += 1;
+                return None;
+            };
+
+            if !self.is_expired(&session) {
+                break session;
+            }
+
+            self.remove(&session.0);
+            self.stats.evictions += 1;
         };
 
+        self.stats.hits += 1;
+
+        // mark the session as recently used
+        self.lru.remove(&session);
+        self.lru.insert(session.clone());
+
+        let session = session.0;
+
         // https://tools.ietf.org/html/rfc8446#appendix-C.4
         // OpenSSL will remove the session from its cache after the handshake completes anyway, but this ensures
         // that concurrent handshakes don't end up with the same session.
@@ -88,10 +174,12 @@ impl SessionCache {
 
     pub fn remove(&mut self, session: &SslSessionRef) {
         let key = match self.reverse.remove(session.id()) {
-            Some(key) => key,
+            Some((key, _)) => key,
             None => return,
         };
 
+        self.lru.remove(session.id());
+
         if let Entry::Occupied(mut sessions) = self.sessions.entry(key) {
             sessions.get_mut().remove(session.id());
             if sessions.get().is_empty() {
@@ -99,4 +187,164 @@ impl SessionCache {
             }
         }
     }
+
+    pub fn stats(&self) -> SessionCacheStats {
+        self.stats
+    }
+
+    fn is_expired(&self, session: &HashSession) -> bool {
+        let Some((_, stored)) = self.reverse.get(session) else {
+            return false;
+        };
+        let lifetime = match (self.ttl, stored.lifetime) {
+            (Some(ttl), Some(lifetime)) => Some(ttl.min(lifetime)),
+            (ttl, lifetime) => ttl.or(lifetime),
+        };
+        lifetime.is_some_and(|lifetime| stored.inserted.elapsed() >= lifetime)
+    }
+}
+
+/// How long a session with a ticket lifetime `hint` may be resumed, at
+/// most `max`, or `None` if it must not be stored.
+///
+/// A zero lifetime means the ticket is not to be resumed in TLS 1.3, while
+/// it only leaves it unspecified before.
+fn ticket_lifetime(hint: u32, tls13: bool, max: Option<Duration>) -> Option<Option<Duration>> {
+    let lifetime = match hint {
+        0 if tls13 => return None,
+        0 => max,
+        hint => {
+            let hint = Duration::from_secs(hint.into());
+            Some(max.map_or(hint, |max| max.min(hint)))
+        }
+    };
+    Some(lifetime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A session with ID `id`, for TLS 1.3 or 1.2, in the DER encoding
+    /// BoringSSL parses. A zero `lifetime_hint` leaves the hint out.
+    fn session(id: u8, tls13: bool, lifetime_hint: u8) -> SslSession {
+        assert!(lifetime_hint < 0x80);
+        let (version, cipher) = if tls13 {
+            (0x04, [0x13, 0x01])
+        } else {
+            (0x03, [0xc0, 0x2f])
+        };
+        let mut fields = vec![
+            0x02, 0x01, 0x01, // encoding version
+            0x02, 0x02, 0x03, version, // protocol version
+            0x04, 0x02, cipher[0], cipher[1], // cipher suite
+            0x04, 0x01, id, // session ID
+            0x04, 0x00, // secret
+            0xa1, 0x03, 0x02, 0x01, 0x01, // time
+            0xa2, 0x03, 0x02, 0x01, 0x7f, // timeout
+        ];
+        if lifetime_hint > 0 {
+            fields.extend([0xa9, 0x03, 0x02, 0x01, lifetime_hint]);
+        }
+        let mut der = vec![0x30, fields.len() as u8];
+        der.extend(fields);
+        SslSession::from_der(&der).unwrap()
+    }
+
+    fn key(host: &str) -> SessionKey {
+        SessionKey {
+            host: host.to_owned(),
+            port: 443,
+        }
+    }
+
+    fn get_id(cache: &mut SessionCache, host: &str) -> Option<u8> {
+        cache.get(&key(host)).map(|session| session.id()[0])
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = SessionCache::new(2, None, None);
+        cache.insert(key("a.test"), session(1, false, 60));
+        cache.insert(key("b.test"), session(2, false, 60));
+
+        // Getting a session moves it to the back.
+        assert_eq!(get_id(&mut cache, "a.test"), Some(1));
+        cache.insert(key("c.test"), session(3, false, 60));
+
+        assert_eq!(get_id(&mut cache, "b.test"), None);
+        assert_eq!(get_id(&mut cache, "a.test"), Some(1));
+        assert_eq!(get_id(&mut cache, "c.test"), Some(3));
+        assert_eq!(
+            cache.stats(),
+            SessionCacheStats {
+                hits: 3,
+                misses: 1,
+                evictions: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn zero_capacity_stores_nothing() {
+        let mut cache = SessionCache::new(0, None, None);
+        cache.insert(key("a.test"), session(1, false, 60));
+
+        assert_eq!(get_id(&mut cache, "a.test"), None);
+        assert_eq!(
+            cache.stats(),
+            SessionCacheStats {
+                hits: 0,
+                misses: 1,
+                evictions: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn expires_sessions_past_ttl() {
+        let mut cache = SessionCache::new(4, Some(Duration::from_millis(20)), None);
+        cache.insert(key("a.test"), session(1, false, 60));
+        assert_eq!(get_id(&mut cache, "a.test"), Some(1));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(get_id(&mut cache, "a.test"), None);
+        assert_eq!(
+            cache.stats(),
+            SessionCacheStats {
+                hits: 1,
+                misses: 1,
+                evictions: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn tls13_sessions_are_used_once() {
+        let mut cache = SessionCache::new(4, None, None);
+        cache.insert(key("a.test"), session(1, true, 60));
+        // Not to be resumed, so not stored.
+        cache.insert(key("b.test"), session(2, true, 0));
+
+        assert_eq!(get_id(&mut cache, "a.test"), Some(1));
+        assert_eq!(get_id(&mut cache, "a.test"), None);
+        assert_eq!(get_id(&mut cache, "b.test"), None);
+    }
+
+    #[test]
+    fn ticket_lifetime_is_clamped() {
+        let day = Duration::from_secs(24 * 60 * 60);
+        assert_eq!(
+            ticket_lifetime(7200, true, Some(day)),
+            Some(Some(Duration::from_secs(7200)))
+        );
+        assert_eq!(ticket_lifetime(u32::MAX, true, Some(day)), Some(Some(day)));
+        assert_eq!(
+            ticket_lifetime(u32::MAX, true, None),
+            Some(Some(Duration::from_secs(u32::MAX.into())))
+        );
+        // Not to be resumed in TLS 1.3, unspecified before.
+        assert_eq!(ticket_lifetime(0, true, Some(day)), None);
+        assert_eq!(ticket_lifetime(0, false, Some(day)), Some(Some(day)));
+    }
 }
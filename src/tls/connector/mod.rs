@@ -10,6 +10,8 @@ use boring::ssl::{
 };
 
 use super::TlsResult;
+use crate::metrics::{self, Phase};
+pub use cache::SessionCacheStats;
 ///! Hyper SSL support via OpenSSL.
 use cache::{SessionCache, SessionKey};
 use http::uri::Scheme;
@@ -23,6 +25,7 @@ use std::net;
 use std::pin::Pin;
 use std::sync::{Arc, LazyLock};
 use std::task::{Context, Poll};
+use std::time::Duration;
 use std::{error::Error, fmt};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio_boring::SslStream;
@@ -87,6 +90,8 @@ pub struct HttpsLayer {
 /// Settings for [`HttpsLayer`]
 pub struct HttpsLayerSettings {
     session_cache_capacity: usize,
+    session_cache_ttl: Option<Duration>,
+    session_ticket_max_lifetime: Option<Duration>,
     session_cache: bool,
 }
 
@@ -100,7 +105,9 @@ impl HttpsLayerSettings {
 impl Default for HttpsLayerSettings {
     fn default() -> Self {
         Self {
-            session_cache_capacity: 8,
+            session_cache_capacity: 64,
+            session_cache_ttl: Some(Duration::from_secs(60 * 60)),
+            session_ticket_max_lifetime: Some(Duration::from_secs(24 * 60 * 60)),
             session_cache: true,
         }
     }
@@ -110,13 +117,26 @@ impl Default for HttpsLayerSettings {
 pub struct HttpsLayerSettingsBuilder(HttpsLayerSettings);
 
 impl HttpsLayerSettingsBuilder {
-    /// Sets maximum number of sessions to cache. Session capacity is per session key (domain).
-    /// Defaults to 8.
+    /// Sets maximum number of sessions to cache across all session keys (domains).
+    /// The least recently used session is evicted first. Defaults to 64.
     pub fn session_cache_capacity(mut self, capacity: usize) -> Self {
         self.0.session_cache_capacity = capacity;
         self
     }
 
+    /// Sets how long a cached session may be resumed. Defaults to 1 hour.
+    pub fn session_cache_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.0.session_cache_ttl = ttl;
+        self
+    }
+
+    /// Sets the most a session may be resumed for, whatever ticket
+    /// lifetime the server advertises. Defaults to 24 hours.
+    pub fn session_ticket_max_lifetime(mut self, lifetime: Option<Duration>) -> Self {
+        self.0.session_ticket_max_lifetime = lifetime;
+        self
+    }
+
     /// Sets whether to enable session caching. Defaults to `true`.
     pub fn session_cache(mut self, enable: bool) -> Self {
         self.0.session_cache = enable;
@@ -137,8 +157,10 @@ impl HttpsLayer {
     ) -> TlsResult<HttpsLayer> {
         // If the session cache is disabled, we don't need to set up any callbacks.
         let cache = if settings.session_cache {
-            let cache = Arc::new(Mutex::new(SessionCache::with_capacity(
+            let cache = Arc::new(Mutex::new(SessionCache::new(
                 settings.session_cache_capacity,
+                settings.session_cache_ttl,
+                settings.session_ticket_max_lifetime,
             )));
 
             ssl.set_session_cache_mode(SslSessionCacheMode::CLIENT);
@@ -166,6 +188,15 @@ impl HttpsLayer {
             },
         })
     }
+
+    /// Returns the counters of the session cache, all zero if it is disabled.
+    pub fn session_cache_stats(&self) -> SessionCacheStats {
+        self.inner
+            .cache
+            .as_ref()
+            .map(|cache| cache.lock().stats())
+            .unwrap_or_default()
+    }
 }
 
 impl<S> Layer<S> for HttpsLayer {
@@ -251,6 +282,7 @@ where
 
         let f = async {
             let conn = connect.await.map_err(Into::into)?;
+            metrics::mark(Phase::Connected);
 
             let (inner, uri) = match tls_setup {
                 Some((inner, uri)) => (inner, uri),
@@ -277,6 +309,7 @@ where
             let stream = tokio_boring::SslStreamBuilder::new(ssl, conn)
                 .connect()
                 .await?;
+            metrics::mark(Phase::Handshaken);
 
             Ok(MaybeHttpsStream::Https(stream))
         };
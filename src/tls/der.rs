@@ -0,0 +1,52 @@
+//! A minimal reader of the DER encoding, for the few fields of
+//! certificates and OCSP responses BoringSSL doesn't parse for us.
+
+pub(super) const INTEGER: u8 = 0x02;
+pub(super) const BIT_STRING: u8 = 0x03;
+pub(super) const OCTET_STRING: u8 = 0x04;
+pub(super) const OID: u8 = 0x06;
+pub(super) const ENUMERATED: u8 = 0x0a;
+pub(super) const SEQUENCE: u8 = 0x30;
+
+/// A reader of DER elements.
+pub(super) struct Der<'a>(pub(super) &'a [u8]);
+
+impl<'a> Der<'a> {
+    /// The tag, contents and whole encoding of the next element.
+    pub(super) fn next(&mut self) -> Option<(u8, &'a [u8], &'a [u8])> {
+        let input = self.0;
+        let (&tag, rest) = input.split_first()?;
+        let (&first, rest) = rest.split_first()?;
+        let (len, rest) = match first {
+            0..=0x7f => (first as usize, rest),
+            0x81..=0x84 => {
+                let n = (first & 0x7f) as usize;
+                let len = rest
+                    .get(..n)?
+                    .iter()
+                    .fold(0usize, |len, &b| len << 8 | b as usize);
+                (len, &rest[n..])
+            }
+            _ => return None,
+        };
+        let contents = rest.get(..len)?;
+        let header_len = input.len() - rest.len();
+        self.0 = &rest[len..];
+        Some((tag, contents, &input[..header_len + len]))
+    }
+
+    /// The contents of the next element, which must have `tag`.
+    pub(super) fn read(&mut self, tag: u8) -> Option<&'a [u8]> {
+        match self.next()? {
+            (t, contents, _) if t == tag => Some(contents),
+            _ => None,
+        }
+    }
+
+    /// Skips the next element if it has `tag`.
+    pub(super) fn skip(&mut self, tag: u8) {
+        if self.0.first() == Some(&tag) {
+            self.next();
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use super::TlsResult;
+use boring::ex_data::Index;
+use boring::ssl::{SslContext, SslRef};
+use boring_sys as ffi;
+use foreign_types::ForeignTypeRef;
+use std::os::raw::{c_int, c_uint, c_void};
+use std::sync::LazyLock;
+
+/// A TLS extension sent as is in the ClientHello.
+///
+/// This is an escape hatch for extensions BoringSSL does not support, its
+/// type must not be one BoringSSL already sends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawTlsExtension {
+    /// The extension type code.
+    pub extension_type: u16,
+    /// The extension data, without the type and length prefix.
+    pub data: Vec<u8>,
+}
+
+impl RawTlsExtension {
+    /// Create a new `RawTlsExtension` with the given type and data.
+    pub fn new(extension_type: u16, data: impl Into<Vec<u8>>) -> RawTlsExtension {
+        RawTlsExtension {
+            extension_type,
+            data: data.into(),
+        }
+    }
+}
+
+/// Checks the extensions can be added to a connector.
+pub(crate) fn validate(extensions: &[RawTlsExtension]) -> crate::Result<()> {
+    for (i, ext) in extensions.iter().enumerate() {
+        if unsafe { ffi::SSL_extension_supported(ext.extension_type as _) } == 1 {
+            return Err(crate::error::builder(format!(
+                "custom TLS extension {} conflicts with a built-in extension",
+                ext.extension_type
+            )));
+        }
+        if extensions[..i]
+            .iter()
+            .any(|prev| prev.extension_type == ext.extension_type)
+        {
+            return Err(crate::error::builder(format!(
+                "custom TLS extension {} is set more than once",
+                ext.extension_type
+            )));
+        }
+        if ext.data.len() > u16::MAX as usize {
+            return Err(crate::error::builder(format!(
+                "custom TLS extension {} data is too long",
+                ext.extension_type
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// The extensions of a context, read back by `add_callback` for each handshake.
+pub(super) fn extensions_index() -> TlsResult<Index<SslContext, Vec<RawTlsExtension>>> {
+    static IDX: LazyLock<TlsResult<Index<SslContext, Vec<RawTlsExtension>>>> =
+        LazyLock::new(SslContext::new_ex_index);
+    IDX.clone()
+}
+
+pub(super) extern "C" fn add_callback(
+    ssl: *mut ffi::SSL,
+    extension_value: c_uint,
+    out: *mut *const u8,
+    out_len: *mut usize,
+    _out_alert_value: *mut c_int,
+    _add_arg: *mut c_void,
+) -> c_int {
+    let ssl = unsafe { SslRef::from_ptr(ssl) };
+    let ext = extensions_index()
+        .ok()
+        .and_then(|idx| ssl.ssl_context().ex_data(idx))
+        .and_then(|exts| {
+            exts.iter()
+                .find(|ext| ext.extension_type as c_uint == extension_value)
+        });
+
+    match ext {
+        Some(ext) => {
+            unsafe {
+                *out = ext.data.as_ptr();
+                *out_len = ext.data.len();
+            }
+            1
+        }
+        // Skip the extension.
+        None => 0,
+    }
+}
+
+pub(super) extern "C" fn parse_callback(
+    _ssl: *mut ffi::SSL,
+    _extension_value: c_uint,
+    _contents: *const u8,
+    _contents_len: usize,
+    _out_alert_value: *mut c_int,
+    _parse_arg: *mut c_void,
+) -> c_int {
+    // The server echoing the extension is not an error.
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_rejects_built_in_and_duplicate_types() {
+        // server_name
+        let err = validate(&[RawTlsExtension::new(0, Vec::new())]).unwrap_err();
+        assert!(err.is_builder());
+        assert!(err.to_string().contains("built-in"), "{}", err);
+
+        let ext = RawTlsExtension::new(0xfe42, vec![1, 2, 3]);
+        validate(&[ext.clone()]).unwrap();
+
+        let err = validate(&[ext.clone(), ext]).unwrap_err();
+        assert!(err.to_string().contains("more than once"), "{}", err);
+    }
+}
@@ -1,6 +1,7 @@
 #![allow(missing_debug_implementations)]
 
 pub mod cert_compression;
+pub mod custom_ext;
 
 use super::{TlsResult, Version};
 use crate::client::http::HttpVersionPref;
@@ -14,6 +15,7 @@ use boring::x509::store::X509Store;
 ))]
 use boring::x509::{store::X509StoreBuilder, X509};
 use cert_compression::CertCompressionAlgorithm;
+use custom_ext::RawTlsExtension;
 use foreign_types::ForeignTypeRef;
 #[cfg(any(
     feature = "boring-tls-webpki-roots",
@@ -60,6 +62,12 @@ pub trait TlsExtension {
         cert_compression_alg: CertCompressionAlgorithm,
     ) -> TlsResult<SslConnectorBuilder>;
 
+    /// Configure the custom extensions for the given `SslConnectorBuilder`.
+    fn configure_custom_extensions(
+        self,
+        extensions: &[RawTlsExtension],
+    ) -> TlsResult<SslConnectorBuilder>;
+
     /// Configure the ca certificate store for the given `SslConnectorBuilder`.
     fn configure_ca_cert_store(
         self,
@@ -162,6 +170,30 @@ impl TlsExtension for SslConnectorBuilder {
         }
     }
 
+    fn configure_custom_extensions(
+        mut self,
+        extensions: &[RawTlsExtension],
+    ) -> TlsResult<SslConnectorBuilder> {
+        for ext in extensions {
+            unsafe {
+                sv_handler(boring_sys::SSL_CTX_add_client_custom_ext(
+                    self.as_ptr(),
+                    ext.extension_type as _,
+                    Some(custom_ext::add_callback),
+                    None,
+                    std::ptr::null_mut(),
+                    Some(custom_ext::parse_callback),
+                    std::ptr::null_mut(),
+                ))?;
+            }
+        }
+
+        // The callbacks read the data from the context, so it lives as long
+        // as the connections using it.
+        self.set_ex_data(custom_ext::extensions_index()?, extensions.to_vec());
+        Ok(self)
+    }
+
     fn configure_ca_cert_store(
         mut self,
         ca_cert_stroe: Option<&(dyn Fn() -> TlsResult<X509Store> + Send + Sync)>,
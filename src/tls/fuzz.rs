@@ -0,0 +1,287 @@
+//! Best-effort decoders used by the fuzz targets in `fuzz/`.
+//!
+//! None of this is a stable API. The input formats only exist so that a
+//! fuzzer can reach every `configure_*` path of the connector with
+//! arbitrary settings, and the decoders never reject input they can make
+//! sense of.
+
+use std::time::Duration;
+
+use boring::ssl::SslCurve;
+
+use super::cert_compression::CertCompressionAlgorithm;
+use super::{create_connect_layer, TlsResult, TlsSettings, Version};
+use crate::HttpVersionPref;
+
+const CURVES: [SslCurve; 4] = [
+    SslCurve::X25519,
+    SslCurve::SECP256R1,
+    SslCurve::SECP384R1,
+    SslCurve::SECP521R1,
+];
+
+impl TlsSettings {
+    /// Decodes settings from arbitrary bytes, then builds a connector from
+    /// them.
+    ///
+    /// Missing input decodes as zeroes, so every byte string is accepted.
+    #[doc(hidden)]
+    pub fn from_bytes(data: &[u8]) -> TlsResult<TlsSettings> {
+        let mut input = Input(data);
+        let flags = input.u8();
+
+        let settings = TlsSettings::builder()
+            .certs_verification(flags & 0x01 != 0)
+            .tls_sni(flags & 0x02 != 0)
+            .application_settings(flags & 0x04 != 0)
+            .pre_shared_key(flags & 0x08 != 0)
+            .enable_ech_grease(flags & 0x10 != 0)
+            .enable_ocsp_stapling(flags & 0x20 != 0)
+            .enable_signed_cert_timestamps(flags & 0x40 != 0)
+            .http_version_pref(match input.u8() % 3 {
+                0 => HttpVersionPref::Http1,
+                1 => HttpVersionPref::Http2,
+                _ => HttpVersionPref::All,
+            })
+            .session_ticket(input.option_bool())
+            .min_tls_version(input.version())
+            .max_tls_version(input.version())
+            .session_cache_capacity(input.option(|input| input.u8() as usize))
+            .session_cache_ttl(input.option(|input| Duration::from_secs(input.u8() as u64)))
+            .permute_extensions(input.option_bool())
+            .grease_enabled(input.option_bool())
+            .cert_compression_algorithm(input.option(|input| {
+                if input.u8() % 2 == 0 {
+                    CertCompressionAlgorithm::Brotli
+                } else {
+                    CertCompressionAlgorithm::Zlib
+                }
+            }))
+            .curves(input.option(|input| {
+                let mask = input.u8();
+                CURVES
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| mask & (1 << i) != 0)
+                    .map(|(_, curve)| *curve)
+                    .collect::<Vec<_>>()
+            }))
+            .sigalgs_list(input.option_string())
+            .cipher_list(input.option_string())
+            .session_ticket_max_lifetime(
+                input.option(|input| Duration::from_secs(input.u8() as u64)),
+            )
+            .build();
+
+        create_connect_layer(&settings, settings.http_version_pref)?;
+        Ok(settings)
+    }
+}
+
+/// A cursor over fuzzer input.
+struct Input<'a>(&'a [u8]);
+
+impl Input<'_> {
+    fn u8(&mut self) -> u8 {
+        match self.0.split_first() {
+            Some((byte, rest)) => {
+                self.0 = rest;
+                *byte
+            }
+            None => 0,
+        }
+    }
+
+    fn option<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> Option<T> {
+        if self.u8() % 2 == 0 {
+            None
+        } else {
+            Some(f(self))
+        }
+    }
+
+    fn option_bool(&mut self) -> Option<bool> {
+        self.option(|input| input.u8() % 2 == 1)
+    }
+
+    fn option_string(&mut self) -> Option<String> {
+        self.option(|input| {
+            let len = (input.u8() as usize).min(input.0.len());
+            let (s, rest) = input.0.split_at(len);
+            input.0 = rest;
+            String::from_utf8_lossy(s).into_owned()
+        })
+    }
+
+    fn version(&mut self) -> Option<Version> {
+        version(self.u8() % 5)
+    }
+}
+
+fn version(n: u8) -> Option<Version> {
+    match n {
+        1 => Some(Version::TLS_1_0),
+        2 => Some(Version::TLS_1_1),
+        3 => Some(Version::TLS_1_2),
+        4 => Some(Version::TLS_1_3),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "json")]
+mod json {
+    use http::{HeaderMap, HeaderName, HeaderValue};
+    use serde_json::{Map, Value};
+
+    use super::{version, CURVES};
+    use crate::tls::cert_compression::CertCompressionAlgorithm;
+    use crate::tls::{create_connect_layer, Http2Settings, ImpersonateSettings, TlsSettings};
+    use crate::HttpVersionPref;
+
+    impl ImpersonateSettings {
+        /// Decodes settings from a JSON object with optional `tls`, `http2`
+        /// and `headers` members, then builds a connector from them.
+        ///
+        /// Members are named after the settings fields. Unknown members and
+        /// values of the wrong type are ignored.
+        #[doc(hidden)]
+        pub fn from_json(json: &str) -> crate::Result<ImpersonateSettings> {
+            let value: Value = serde_json::from_str(json).map_err(crate::error::decode)?;
+            let empty = Map::new();
+            let object = |key: &str| value.get(key).and_then(Value::as_object);
+
+            let tls = tls_settings(object("tls").unwrap_or(&empty));
+            create_connect_layer(&tls, tls.http_version_pref).map_err(crate::error::builder)?;
+
+            let http2 = http2_settings(object("http2").unwrap_or(&empty));
+
+            let mut headers = HeaderMap::new();
+            for (name, value) in object("headers").unwrap_or(&empty) {
+                let name = HeaderName::from_bytes(name.as_bytes());
+                let value = value.as_str().map(HeaderValue::from_str);
+                if let (Ok(name), Some(Ok(value))) = (name, value) {
+                    headers.insert(name, value);
+                }
+            }
+
+            Ok(ImpersonateSettings::builder()
+                .tls(tls)
+                .http2(http2)
+                .headers(Box::new(move |map: &mut HeaderMap| map.extend(headers)))
+                .build())
+        }
+    }
+
+    fn tls_settings(o: &Map<String, Value>) -> TlsSettings {
+        let flag = |key: &str| o.get(key).and_then(Value::as_bool);
+        let string = |key: &str| o.get(key).and_then(Value::as_str).map(str::to_owned);
+        let tls_version = |key: &str| match o.get(key).and_then(Value::as_str) {
+            Some("1.0") => version(1),
+            Some("1.1") => version(2),
+            Some("1.2") => version(3),
+            Some("1.3") => version(4),
+            _ => None,
+        };
+
+        TlsSettings::builder()
+            .certs_verification(flag("certs_verification").unwrap_or(true))
+            .tls_sni(flag("tls_sni").unwrap_or(true))
+            .http_version_pref(match o.get("http_version_pref").and_then(Value::as_str) {
+                Some("http1") => HttpVersionPref::Http1,
+                Some("http2") => HttpVersionPref::Http2,
+                _ => HttpVersionPref::All,
+            })
+            .session_ticket(flag("session_ticket"))
+            .min_tls_version(tls_version("min_tls_version"))
+            .max_tls_version(tls_version("max_tls_version"))
+            .application_settings(flag("application_settings").unwrap_or_default())
+            .pre_shared_key(flag("pre_shared_key").unwrap_or_default())
+            .session_cache_capacity(
+                o.get("session_cache_capacity")
+                    .and_then(Value::as_u64)
+                    .map(|n| n as usize),
+            )
+            .enable_ech_grease(flag("enable_ech_grease").unwrap_or_default())
+            .permute_extensions(flag("permute_extensions"))
+            .grease_enabled(flag("grease_enabled"))
+            .enable_ocsp_stapling(flag("enable_ocsp_stapling").unwrap_or_default())
+            .curves(o.get("curves").and_then(Value::as_array).map(|curves| {
+                curves
+                    .iter()
+                    .filter_map(|curve| match curve.as_str()? {
+                        "X25519" => Some(CURVES[0]),
+                        "P-256" => Some(CURVES[1]),
+                        "P-384" => Some(CURVES[2]),
+                        "P-521" => Some(CURVES[3]),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            }))
+            .sigalgs_list(string("sigalgs_list"))
+            .cipher_list(string("cipher_list"))
+            .enable_signed_cert_timestamps(
+                flag("enable_signed_cert_timestamps").unwrap_or_default(),
+            )
+            .cert_compression_algorithm(
+                match o.get("cert_compression_algorithm").and_then(Value::as_str) {
+                    Some("brotli") => Some(CertCompressionAlgorithm::Brotli),
+                    Some("zlib") => Some(CertCompressionAlgorithm::Zlib),
+                    _ => None,
+                },
+            )
+            .build()
+    }
+
+    fn http2_settings(o: &Map<String, Value>) -> Http2Settings {
+        let number = |key: &str| {
+            o.get(key)
+                .and_then(Value::as_u64)
+                .and_then(|n| u32::try_from(n).ok())
+        };
+        let flag = |key: &str| o.get(key).and_then(Value::as_bool);
+
+        let headers_priority = o
+            .get("headers_priority")
+            .and_then(Value::as_array)
+            .and_then(|p| match p.as_slice() {
+                [dep, weight, exclusive] => Some((
+                    u32::try_from(dep.as_u64()?).ok()?,
+                    u8::try_from(weight.as_u64()?).ok()?,
+                    exclusive.as_bool()?,
+                )),
+                _ => None,
+            });
+
+        Http2Settings::builder()
+            .initial_connection_window_size(number("initial_connection_window_size"))
+            .header_table_size(number("header_table_size"))
+            .enable_push(flag("enable_push"))
+            .max_concurrent_streams(number("max_concurrent_streams"))
+            .initial_stream_window_size(number("initial_stream_window_size"))
+            .max_frame_size(number("max_frame_size"))
+            .max_header_list_size(number("max_header_list_size"))
+            .unknown_setting8(flag("unknown_setting8"))
+            .unknown_setting9(flag("unknown_setting9"))
+            .headers_priority(headers_priority)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TlsSettings;
+
+    #[test]
+    fn from_bytes_accepts_any_length() {
+        for data in [&b""[..], b"\xff", b"\xff\x02\x01\x01\x03\x04"] {
+            TlsSettings::from_bytes(data).unwrap();
+        }
+    }
+
+    #[test]
+    fn nul_in_cipher_list_does_not_panic() {
+        // flags, pref, then every option absent but the cipher list
+        let data = b"\x00\x02\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01\x06AES\x00XY";
+        let _ = TlsSettings::from_bytes(data);
+    }
+}
@@ -34,4 +34,5 @@ fn header_initializer(headers: &mut HeaderMap) {
         HeaderValue::from_static("gzip, deflate, br, zstd"),
     );
     headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("en-US;q=1.0"));
+    headers.insert("priority", HeaderValue::from_static("u=0, i"));
 }
@@ -0,0 +1,23 @@
+use crate::tls::impersonate::ImpersonateSettings;
+use crate::tls::TlsResult;
+use http::{
+    header::{ACCEPT, ACCEPT_ENCODING, USER_AGENT},
+    HeaderMap, HeaderValue,
+};
+
+pub(crate) fn get_settings() -> TlsResult<ImpersonateSettings> {
+    Ok(ImpersonateSettings::builder()
+        .tls(super::tls::tls_template_1()?)
+        .http2(super::http2_template_1())
+        .headers(Box::new(header_initializer))
+        .build())
+}
+
+fn header_initializer(headers: &mut HeaderMap) {
+    headers.insert(USER_AGENT, HeaderValue::from_static("curl/8.0.1"));
+    headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
+    headers.insert(
+        ACCEPT_ENCODING,
+        HeaderValue::from_static("deflate, gzip, br, zstd"),
+    );
+}
@@ -0,0 +1,106 @@
+pub mod curl7_88;
+pub mod curl8_0;
+
+use crate::tls::Http2Settings;
+use http2::{HEADERS_PSEUDO_ORDER, SETTINGS_ORDER};
+
+// ============== HTTP template ==============
+pub fn http2_template_1() -> Http2Settings {
+    Http2Settings::builder()
+        .initial_stream_window_size(33554432)
+        .initial_connection_window_size(33554432)
+        .max_concurrent_streams(100)
+        .enable_push(false)
+        .headers_pseudo_order(HEADERS_PSEUDO_ORDER)
+        .settings_order(SETTINGS_ORDER)
+        .build()
+}
+
+// curl as built against OpenSSL 3, as shipped by most Linux distributions.
+// BoringSSL lacks the DHE cipher suites and the X448 curve, which are left out.
+mod tls {
+    use crate::tls::TlsSettings;
+    use boring::{
+        error::ErrorStack,
+        ssl::{SslConnector, SslCurve, SslMethod, SslVersion},
+    };
+
+    pub const CURVES: &[SslCurve] = &[
+        SslCurve::X25519,
+        SslCurve::SECP256R1,
+        SslCurve::SECP521R1,
+        SslCurve::SECP384R1,
+    ];
+
+    pub const SIGALGS_LIST: [&str; 12] = [
+        "ecdsa_secp256r1_sha256",
+        "ecdsa_secp384r1_sha384",
+        "ecdsa_secp521r1_sha512",
+        "ed25519",
+        "rsa_pss_rsae_sha256",
+        "rsa_pss_rsae_sha384",
+        "rsa_pss_rsae_sha512",
+        "rsa_pkcs1_sha256",
+        "rsa_pkcs1_sha384",
+        "rsa_pkcs1_sha512",
+        "ecdsa_sha1",
+        "rsa_pkcs1_sha1",
+    ];
+
+    pub const CIPHER_LIST: [&str; 17] = [
+        "TLS_AES_256_GCM_SHA384",
+        "TLS_CHACHA20_POLY1305_SHA256",
+        "TLS_AES_128_GCM_SHA256",
+        "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+        "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+        "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+        "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+        "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+        "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+        "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA",
+        "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA",
+        "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA",
+        "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA",
+        "TLS_RSA_WITH_AES_256_GCM_SHA384",
+        "TLS_RSA_WITH_AES_128_GCM_SHA256",
+        "TLS_RSA_WITH_AES_256_CBC_SHA",
+        "TLS_RSA_WITH_AES_128_CBC_SHA",
+    ];
+
+    pub fn tls_template_1() -> Result<TlsSettings, ErrorStack> {
+        let connector = Box::new(move || {
+            let mut builder = SslConnector::builder(SslMethod::tls_client())?;
+            builder.set_curves(CURVES)?;
+            builder.set_sigalgs_list(&SIGALGS_LIST.join(":"))?;
+            builder.set_cipher_list(&CIPHER_LIST.join(":"))?;
+            builder.set_min_proto_version(Some(SslVersion::TLS1_2))?;
+            builder.set_max_proto_version(Some(SslVersion::TLS1_3))?;
+            Ok(builder)
+        });
+
+        Ok(TlsSettings::builder()
+            .connector(connector)
+            .http_version_pref(crate::HttpVersionPref::All)
+            .build())
+    }
+}
+
+mod http2 {
+    use hyper::PseudoOrder::{self, *};
+    use hyper::SettingsOrder::{self, *};
+
+    /// ============== http2 headers pseudo order ==============
+    pub const HEADERS_PSEUDO_ORDER: [PseudoOrder; 4] = [Method, Path, Scheme, Authority];
+
+    /// ============== http2 settings frame order ==============
+    pub const SETTINGS_ORDER: [SettingsOrder; 8] = [
+        MaxConcurrentStreams,
+        InitialWindowSize,
+        EnablePush,
+        HeaderTableSize,
+        MaxFrameSize,
+        MaxHeaderListSize,
+        UnknownSetting8,
+        UnknownSetting9,
+    ];
+}
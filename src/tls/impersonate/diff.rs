@@ -0,0 +1,164 @@
+use super::ImpersonateSettings;
+use std::fmt::{self, Debug};
+
+/// The differences between two [`ImpersonateSettings`], from
+/// [`ImpersonateSettings::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImpersonateDiff {
+    /// The `TlsSettings` fields that differ.
+    pub tls: Vec<FieldDiff>,
+    /// The `Http2Settings` fields that differ.
+    pub http2: Vec<FieldDiff>,
+}
+
+/// A settings field that differs, with its two values in their `Debug`
+/// format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDiff {
+    /// The name of the field.
+    pub name: &'static str,
+    /// The value in the settings `diff` was called on.
+    pub old: String,
+    /// The value in the other settings.
+    pub new: String,
+}
+
+macro_rules! diff_fields {
+    ($diffs:expr, $old:expr, $new:expr, $($field:ident),+) => {
+        $(
+            push_diff(&mut $diffs, stringify!($field), &$old.$field, &$new.$field);
+        )+
+    };
+}
+
+impl ImpersonateSettings {
+    /// Compares these settings with `other`, field by field.
+    ///
+    /// The connector, certificate store and callbacks are not compared, nor
+    /// are the headers, which can only be read by applying them. So the
+    /// ciphers, curves and signature algorithms a profile sets up in its
+    /// connector only show up when they are set as fields.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use rquest::tls::{tls_settings, Impersonate};
+    ///
+    /// let chrome = tls_settings(Impersonate::Chrome130).unwrap();
+    /// let edge = tls_settings(Impersonate::Edge127).unwrap();
+    /// print!("{}", chrome.diff(&edge));
+    /// ```
+    pub fn diff(&self, other: &ImpersonateSettings) -> ImpersonateDiff {
+        let mut diff = ImpersonateDiff::default();
+        diff_fields!(
+            diff.tls,
+            self.tls,
+            other.tls,
+            certs_verification,
+            tls_sni,
+            http_version_pref,
+            session_ticket,
+            min_tls_version,
+            max_tls_version,
+            application_settings,
+            pre_shared_key,
+            session_cache_capacity,
+            session_cache_ttl,
+            session_ticket_max_lifetime,
+            enable_ech_grease,
+            permute_extensions,
+            grease_enabled,
+            enable_ocsp_stapling,
+            curves,
+            sigalgs_list,
+            cipher_list,
+            enable_signed_cert_timestamps,
+            cert_compression_algorithm,
+            custom_extensions
+        );
+        diff_fields!(
+            diff.http2,
+            self.http2,
+            other.http2,
+            initial_connection_window_size,
+            header_table_size,
+            enable_push,
+            max_concurrent_streams,
+            initial_stream_window_size,
+            max_frame_size,
+            max_header_list_size,
+            unknown_setting8,
+            unknown_setting9,
+            settings_order,
+            headers_priority,
+            headers_pseudo_order
+        );
+        diff
+    }
+}
+
+fn push_diff<T: Debug>(diffs: &mut Vec<FieldDiff>, name: &'static str, old: &T, new: &T) {
+    let (old, new) = (value(old), value(new));
+    if old != new {
+        diffs.push(FieldDiff { name, old, new });
+    }
+}
+
+/// Formats `value` with `Debug`, without the `Some` around set options.
+fn value<T: Debug>(value: &T) -> String {
+    let value = format!("{:?}", value);
+    match value.strip_prefix("Some(") {
+        Some(inner) => inner[..inner.len() - 1].to_owned(),
+        None => value,
+    }
+}
+
+impl ImpersonateDiff {
+    /// Whether the settings compared are the same.
+    pub fn is_empty(&self) -> bool {
+        self.tls.is_empty() && self.http2.is_empty()
+    }
+}
+
+impl fmt::Display for ImpersonateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for field in self.tls.iter().chain(&self.http2) {
+            writeln!(f, "- {}: {}", field.name, field.old)?;
+            writeln!(f, "+ {}: {}", field.name, field.new)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tls::{tls_settings, Impersonate};
+
+    #[test]
+    fn diff_lists_changed_fields() {
+        let chrome = tls_settings(Impersonate::Chrome130).unwrap();
+        assert!(chrome.diff(&chrome).is_empty());
+
+        let mut other = tls_settings(Impersonate::Chrome130).unwrap();
+        other.tls.cipher_list = Some("TLS_AES_128_GCM_SHA256".into());
+        other.http2.max_frame_size = Some(16384);
+
+        let diff = chrome.diff(&other);
+        assert_eq!(diff.tls.len(), 1);
+        assert_eq!(diff.tls[0].name, "cipher_list");
+        assert_eq!(diff.tls[0].new, "\"TLS_AES_128_GCM_SHA256\"");
+        assert_eq!(
+            diff.http2,
+            vec![FieldDiff {
+                name: "max_frame_size",
+                old: "None".into(),
+                new: "16384".into(),
+            }]
+        );
+
+        let display = diff.to_string();
+        assert!(display.contains("+ cipher_list: \"TLS_AES_128_GCM_SHA256\"\n"));
+        assert!(display.ends_with("- max_frame_size: None\n+ max_frame_size: 16384\n"));
+    }
+}
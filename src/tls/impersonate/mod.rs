@@ -1,20 +1,32 @@
 #![allow(missing_docs, missing_debug_implementations)]
 
 pub mod chrome;
+pub mod curl;
+mod diff;
 pub mod edge;
 pub mod okhttp;
+pub mod python_requests;
+#[cfg(feature = "impersonate-random")]
+mod random;
 pub mod safari;
 
 use super::{Http2Settings, TlsResult, TlsSettings};
 use chrome::*;
+use curl::*;
 use edge::*;
 use http::HeaderMap;
 use okhttp::*;
+use python_requests::*;
 use safari::*;
-use std::{fmt::Debug, str::FromStr};
+use std::{
+    fmt::{self, Debug},
+    str::FromStr,
+};
 use typed_builder::TypedBuilder;
 use Impersonate::*;
 
+pub use diff::{FieldDiff, ImpersonateDiff};
+
 /// Impersonate Settings.
 #[derive(TypedBuilder)]
 pub struct ImpersonateSettings {
@@ -29,6 +41,103 @@ pub struct ImpersonateSettings {
     pub(crate) headers: Option<Box<dyn FnOnce(&mut HeaderMap)>>,
 }
 
+impl ImpersonateSettings {
+    /// Starts building settings from those of the `base` profile.
+    ///
+    /// The parts that are not overridden are the ones of `base`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use rquest::tls::{Impersonate, ImpersonateSettings};
+    ///
+    /// let settings = ImpersonateSettings::builder_from(Impersonate::Chrome130)
+    ///     .http2_with(|http2| http2.max_concurrent_streams = Some(100))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn builder_from(base: Impersonate) -> ImpersonateSettingsFrom {
+        ImpersonateSettingsFrom {
+            base,
+            tls: None,
+            http2: None,
+            headers: None,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+/// A builder of [`ImpersonateSettings`] overriding those of a profile, from
+/// [`ImpersonateSettings::builder_from`].
+#[must_use]
+pub struct ImpersonateSettingsFrom {
+    base: Impersonate,
+    tls: Option<TlsSettings>,
+    http2: Option<Http2Settings>,
+    headers: Option<Box<dyn FnOnce(&mut HeaderMap)>>,
+    overrides: Vec<Box<dyn FnOnce(&mut ImpersonateSettings)>>,
+}
+
+impl ImpersonateSettingsFrom {
+    /// Replaces the TLS settings of the profile.
+    pub fn tls(mut self, tls: TlsSettings) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Replaces the HTTP/2 settings of the profile.
+    pub fn http2(mut self, http2: Http2Settings) -> Self {
+        self.http2 = Some(http2);
+        self
+    }
+
+    /// Replaces the headers of the profile.
+    pub fn headers(mut self, headers: Box<dyn FnOnce(&mut HeaderMap)>) -> Self {
+        self.headers = Some(headers);
+        self
+    }
+
+    /// Changes some of the TLS settings, those of the profile or the ones
+    /// set with [`tls`](ImpersonateSettingsFrom::tls).
+    pub fn tls_with<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut TlsSettings) + 'static,
+    {
+        self.overrides
+            .push(Box::new(move |settings| f(&mut settings.tls)));
+        self
+    }
+
+    /// Changes some of the HTTP/2 settings, those of the profile or the ones
+    /// set with [`http2`](ImpersonateSettingsFrom::http2).
+    pub fn http2_with<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut Http2Settings) + 'static,
+    {
+        self.overrides
+            .push(Box::new(move |settings| f(&mut settings.http2)));
+        self
+    }
+
+    /// Builds the settings, failing if those of the profile fail to build.
+    pub fn build(self) -> TlsResult<ImpersonateSettings> {
+        let mut settings = tls_settings(self.base)?;
+        if let Some(tls) = self.tls {
+            settings.tls = tls;
+        }
+        if let Some(http2) = self.http2 {
+            settings.http2 = http2;
+        }
+        if let Some(headers) = self.headers {
+            settings.headers = Some(headers);
+        }
+        for apply in self.overrides {
+            apply(&mut settings);
+        }
+        Ok(settings)
+    }
+}
+
 macro_rules! impersonate_match {
     ($ver:expr, $($variant:pat => $path:path),+) => {
         match $ver {
@@ -96,10 +205,31 @@ pub fn tls_settings(ver: Impersonate) -> TlsResult<ImpersonateSettings> {
         // Edge
         Edge101 => edge101::get_settings,
         Edge122 => edge122::get_settings,
-        Edge127 => edge127::get_settings
+        Edge127 => edge127::get_settings,
+
+        // Curl
+        Curl7_88 => curl7_88::get_settings,
+        Curl8_0 => curl8_0::get_settings,
+
+        // Python requests
+        PythonRequests2_31 => requests2_31::get_settings,
+        PythonRequests2_32 => requests2_32::get_settings
     )
 }
 
+/// A browser or HTTP client profile to impersonate.
+///
+/// Each profile has a name, such as `chrome_130`, that `Display` writes and
+/// `FromStr` parses, and [`Impersonate::variants`] lists them all, which is
+/// enough to take a profile from the command line:
+///
+/// ```rust
+/// use rquest::tls::Impersonate;
+///
+/// let impersonate: Impersonate = "chrome_130".parse().unwrap();
+/// let names: Vec<String> = Impersonate::variants().iter().map(|i| i.to_string()).collect();
+/// assert!(names.contains(&impersonate.to_string()));
+/// ```
 #[derive(Clone, Copy, Debug, Default)]
 pub enum Impersonate {
     // Chrome
@@ -155,74 +285,194 @@ pub enum Impersonate {
     Edge101,
     Edge122,
     Edge127,
+
+    // Curl
+    Curl7_88,
+    Curl8_0,
+
+    // Python requests
+    PythonRequests2_31,
+    PythonRequests2_32,
 }
 
-macro_rules! impl_from_str {
-    ($(($variant:ident, $string:expr)),* $(,)?) => {
+macro_rules! impl_names {
+    ($($(#[$meta:meta])* $group:ident: [$(($variant:ident, $string:expr)),* $(,)?],)*) => {
+        impl Impersonate {
+            /// Every built-in profile, grouped by client.
+            pub const fn variants() -> &'static [Impersonate] {
+                &[$($(Impersonate::$variant,)*)*]
+            }
+
+            $(
+                $(#[$meta])*
+                pub const fn $group() -> &'static [Impersonate] {
+                    &[$(Impersonate::$variant),*]
+                }
+            )*
+
+            /// The name of the profile, such as `chrome_130`, which is also
+            /// its `Display` output and is parsed back by `FromStr`.
+            pub const fn as_str(&self) -> &'static str {
+                match self {
+                    $($(Impersonate::$variant => $string,)*)*
+                }
+            }
+        }
+
         impl FromStr for Impersonate {
             type Err = String;
 
+            /// Parses the name of a profile, such as `chrome_130`, or of its
+            /// variant, such as `Chrome130`, in any case.
             fn from_str(s: &str) -> Result<Self, Self::Err> {
-                match s {
-                    $( $string => Ok(Impersonate::$variant), )*
-                    _ => Err(format!("Unknown impersonate version: {}", s)),
-                }
+                $($(
+                    if s.eq_ignore_ascii_case($string) || s.eq_ignore_ascii_case(stringify!($variant)) {
+                        return Ok(Impersonate::$variant);
+                    }
+                )*)*
+                Err(format!("Unknown impersonate version: {}", s))
             }
         }
     };
 }
 
-impl_from_str! {
-    // Chrome
-    (Chrome100, "chrome_100"),
-    (Chrome101, "chrome_101"),
-    (Chrome104, "chrome_104"),
-    (Chrome105, "chrome_105"),
-    (Chrome106, "chrome_106"),
-    (Chrome107, "chrome_107"),
-    (Chrome108, "chrome_108"),
-    (Chrome109, "chrome_109"),
-    (Chrome114, "chrome_114"),
-    (Chrome116, "chrome_116"),
-    (Chrome117, "chrome_117"),
-    (Chrome118, "chrome_118"),
-    (Chrome119, "chrome_119"),
-    (Chrome120, "chrome_120"),
-    (Chrome123, "chrome_123"),
-    (Chrome124, "chrome_124"),
-    (Chrome126, "chrome_126"),
-    (Chrome127, "chrome_127"),
-    (Chrome128, "chrome_128"),
-    (Chrome129, "chrome_129"),
-    (Chrome130, "chrome_130"),
+impl fmt::Display for Impersonate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
 
-    // Safari
-    (SafariIos17_2, "safari_ios_17.2"),
-    (SafariIos17_4_1, "safari_ios_17.4.1"),
-    (SafariIos16_5, "safari_ios_16.5"),
-    (Safari15_3, "safari_15.3"),
-    (Safari15_5, "safari_15.5"),
-    (Safari15_6_1, "safari_15.6.1"),
-    (Safari16, "safari_16"),
-    (Safari16_5, "safari_16.5"),
-    (Safari17_0, "safari_17.0"),
-    (Safari17_2_1, "safari_17.2.1"),
-    (Safari17_4_1, "safari_17.4.1"),
-    (Safari17_5, "safari_17.5"),
-    (Safari18, "safari_18"),
-    (SafariIPad18, "safari_ipad_18"),
+impl_names! {
+    /// The Chrome profiles.
+    chrome_variants: [
+        (Chrome100, "chrome_100"),
+        (Chrome101, "chrome_101"),
+        (Chrome104, "chrome_104"),
+        (Chrome105, "chrome_105"),
+        (Chrome106, "chrome_106"),
+        (Chrome107, "chrome_107"),
+        (Chrome108, "chrome_108"),
+        (Chrome109, "chrome_109"),
+        (Chrome114, "chrome_114"),
+        (Chrome116, "chrome_116"),
+        (Chrome117, "chrome_117"),
+        (Chrome118, "chrome_118"),
+        (Chrome119, "chrome_119"),
+        (Chrome120, "chrome_120"),
+        (Chrome123, "chrome_123"),
+        (Chrome124, "chrome_124"),
+        (Chrome126, "chrome_126"),
+        (Chrome127, "chrome_127"),
+        (Chrome128, "chrome_128"),
+        (Chrome129, "chrome_129"),
+        (Chrome130, "chrome_130"),
+    ],
 
-    // OkHttp
-    (OkHttp3_9, "okhttp_3.9"),
-    (OkHttp3_11, "okhttp_3.11"),
-    (OkHttp3_13, "okhttp_3.13"),
-    (OkHttp3_14, "okhttp_3.14"),
-    (OkHttp4_9, "okhttp_4.9"),
-    (OkHttp4_10, "okhttp_4.10"),
-    (OkHttp5, "okhttp_5"),
+    /// The Safari profiles.
+    safari_variants: [
+        (SafariIos17_2, "safari_ios_17.2"),
+        (SafariIos17_4_1, "safari_ios_17.4.1"),
+        (SafariIos16_5, "safari_ios_16.5"),
+        (Safari15_3, "safari_15.3"),
+        (Safari15_5, "safari_15.5"),
+        (Safari15_6_1, "safari_15.6.1"),
+        (Safari16, "safari_16"),
+        (Safari16_5, "safari_16.5"),
+        (Safari17_0, "safari_17.0"),
+        (Safari17_2_1, "safari_17.2.1"),
+        (Safari17_4_1, "safari_17.4.1"),
+        (Safari17_5, "safari_17.5"),
+        (Safari18, "safari_18"),
+        (SafariIPad18, "safari_ipad_18"),
+    ],
 
-    // Edge
-    (Edge101, "edge_101"),
-    (Edge122, "edge_122"),
-    (Edge127, "edge_127"),
+    /// The OkHttp profiles.
+    okhttp_variants: [
+        (OkHttp3_9, "okhttp_3.9"),
+        (OkHttp3_11, "okhttp_3.11"),
+        (OkHttp3_13, "okhttp_3.13"),
+        (OkHttp3_14, "okhttp_3.14"),
+        (OkHttp4_9, "okhttp_4.9"),
+        (OkHttp4_10, "okhttp_4.10"),
+        (OkHttp5, "okhttp_5"),
+    ],
+
+    /// The Edge profiles.
+    edge_variants: [
+        (Edge101, "edge_101"),
+        (Edge122, "edge_122"),
+        (Edge127, "edge_127"),
+    ],
+
+    /// The Curl profiles.
+    curl_variants: [
+        (Curl7_88, "curl_7.88"),
+        (Curl8_0, "curl_8.0"),
+    ],
+
+    /// The Python requests profiles.
+    python_requests_variants: [
+        (PythonRequests2_31, "python_requests_2.31"),
+        (PythonRequests2_32, "python_requests_2.32"),
+    ],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_from_keeps_what_is_not_overridden() {
+        let settings = ImpersonateSettings::builder_from(Chrome130)
+            .http2_with(|http2| http2.max_concurrent_streams = Some(100))
+            .build()
+            .unwrap();
+        let base = tls_settings(Chrome130).unwrap();
+
+        assert_eq!(settings.http2.max_concurrent_streams, Some(100));
+        assert_eq!(settings.http2.settings_order, base.http2.settings_order);
+        assert!(settings.headers.is_some());
+
+        let diff = base.diff(&settings);
+        assert!(diff.tls.is_empty());
+        assert_eq!(diff.http2.len(), 1);
+        assert_eq!(diff.http2[0].name, "max_concurrent_streams");
+    }
+
+    #[test]
+    fn profiles_have_valid_tls_settings() {
+        for &impersonate in Impersonate::variants() {
+            let settings = tls_settings(impersonate).unwrap();
+            assert_eq!(settings.tls.validate(), Ok(()), "{impersonate}");
+        }
+    }
+
+    #[test]
+    fn from_str_accepts_variant_names() {
+        assert!(matches!("chrome_130".parse(), Ok(Chrome130)));
+        assert!(matches!("Chrome130".parse(), Ok(Chrome130)));
+        assert!(matches!("SAFARI_IOS_17.2".parse(), Ok(SafariIos17_2)));
+        assert!(matches!("safariios17_2".parse(), Ok(SafariIos17_2)));
+        assert!("chrome".parse::<Impersonate>().is_err());
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for impersonate in Impersonate::variants() {
+            let parsed = impersonate.to_string().parse::<Impersonate>().unwrap();
+            assert_eq!(parsed.as_str(), impersonate.as_str());
+        }
+
+        let groups = [
+            Impersonate::chrome_variants(),
+            Impersonate::safari_variants(),
+            Impersonate::okhttp_variants(),
+            Impersonate::edge_variants(),
+            Impersonate::curl_variants(),
+            Impersonate::python_requests_variants(),
+        ];
+        let grouped = groups.iter().map(|group| group.len()).sum::<usize>();
+        assert_eq!(grouped, Impersonate::variants().len());
+        assert_eq!(Chrome130.to_string(), "chrome_130");
+    }
 }
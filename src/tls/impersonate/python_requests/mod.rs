@@ -0,0 +1,76 @@
+pub mod requests2_31;
+pub mod requests2_32;
+
+use crate::tls::Http2Settings;
+
+// ============== HTTP template ==============
+// urllib3 only speaks HTTP/1.1, these settings are never sent.
+pub fn http2_template_1() -> Http2Settings {
+    Http2Settings::builder().build()
+}
+
+// Python's `ssl` module built against OpenSSL 3, with the default cipher
+// string of `ssl.create_default_context`. BoringSSL lacks the X448 curve and
+// the DHE cipher suites, which are left out.
+mod tls {
+    use crate::tls::TlsSettings;
+    use boring::{
+        error::ErrorStack,
+        ssl::{SslConnector, SslCurve, SslMethod, SslVersion},
+    };
+
+    pub const CURVES: &[SslCurve] = &[
+        SslCurve::X25519,
+        SslCurve::SECP256R1,
+        SslCurve::SECP521R1,
+        SslCurve::SECP384R1,
+    ];
+
+    pub const SIGALGS_LIST: [&str; 12] = [
+        "ecdsa_secp256r1_sha256",
+        "ecdsa_secp384r1_sha384",
+        "ecdsa_secp521r1_sha512",
+        "ed25519",
+        "rsa_pss_rsae_sha256",
+        "rsa_pss_rsae_sha384",
+        "rsa_pss_rsae_sha512",
+        "rsa_pkcs1_sha256",
+        "rsa_pkcs1_sha384",
+        "rsa_pkcs1_sha512",
+        "ecdsa_sha1",
+        "rsa_pkcs1_sha1",
+    ];
+
+    pub const CIPHER_LIST: [&str; 13] = [
+        "TLS_AES_256_GCM_SHA384",
+        "TLS_CHACHA20_POLY1305_SHA256",
+        "TLS_AES_128_GCM_SHA256",
+        "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+        "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+        "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+        "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+        "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+        "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+        "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA384",
+        "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA384",
+        "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA256",
+        "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA256",
+    ];
+
+    pub fn tls_template_1() -> Result<TlsSettings, ErrorStack> {
+        let connector = Box::new(move || {
+            let mut builder = SslConnector::builder(SslMethod::tls_client())?;
+            builder.set_curves(CURVES)?;
+            builder.set_sigalgs_list(&SIGALGS_LIST.join(":"))?;
+            builder.set_cipher_list(&CIPHER_LIST.join(":"))?;
+            builder.set_min_proto_version(Some(SslVersion::TLS1_2))?;
+            builder.set_max_proto_version(Some(SslVersion::TLS1_3))?;
+            Ok(builder)
+        });
+
+        Ok(TlsSettings::builder()
+            .connector(connector)
+            .http_version_pref(crate::HttpVersionPref::Http1)
+            .build())
+    }
+}
@@ -0,0 +1,24 @@
+use crate::tls::impersonate::ImpersonateSettings;
+use crate::tls::TlsResult;
+use http::{
+    header::{ACCEPT, ACCEPT_ENCODING, CONNECTION, USER_AGENT},
+    HeaderMap, HeaderValue,
+};
+
+pub(crate) fn get_settings() -> TlsResult<ImpersonateSettings> {
+    Ok(ImpersonateSettings::builder()
+        .tls(super::tls::tls_template_1()?)
+        .http2(super::http2_template_1())
+        .headers(Box::new(header_initializer))
+        .build())
+}
+
+fn header_initializer(headers: &mut HeaderMap) {
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_static("python-requests/2.31.0"),
+    );
+    headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip, deflate"));
+    headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
+    headers.insert(CONNECTION, HeaderValue::from_static("keep-alive"));
+}
@@ -0,0 +1,71 @@
+use super::Impersonate::{self, *};
+use rand::distributions::{Distribution, WeightedIndex};
+
+/// The recent desktop and mobile browsers, weighted by their approximate
+/// share of web traffic.
+const COMMON_WEIGHTS: [(Impersonate, f64); 10] = [
+    (Chrome130, 0.30),
+    (Chrome129, 0.18),
+    (Chrome128, 0.08),
+    (Chrome127, 0.04),
+    (SafariIos17_4_1, 0.14),
+    (Safari18, 0.06),
+    (Safari17_5, 0.04),
+    (SafariIPad18, 0.03),
+    (Edge127, 0.10),
+    (Edge122, 0.03),
+];
+
+#[cfg_attr(docsrs, doc(cfg(feature = "impersonate-random")))]
+impl Impersonate {
+    /// Picks a profile at random, each with the probability of its weight.
+    ///
+    /// The weights are relative, they need not add up to 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weights` is empty, has a negative or non finite weight, or
+    /// only zero weights.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rquest::tls::Impersonate;
+    ///
+    /// let impersonate = Impersonate::random_weighted(&[
+    ///     (Impersonate::Chrome130, 0.8),
+    ///     (Impersonate::Safari18, 0.15),
+    ///     (Impersonate::Edge127, 0.05),
+    /// ]);
+    /// ```
+    pub fn random_weighted(weights: &[(Impersonate, f64)]) -> Impersonate {
+        let index = WeightedIndex::new(weights.iter().map(|(_, weight)| *weight))
+            .expect("invalid impersonate weights");
+        weights[index.sample(&mut rand::thread_rng())].0
+    }
+
+    /// Picks one of the recent browser profiles at random, weighted by the
+    /// approximate share of web traffic of these browsers.
+    pub fn random_common() -> Impersonate {
+        Impersonate::random_weighted(&COMMON_WEIGHTS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_weighted_skips_zero_weights() {
+        for _ in 0..100 {
+            let impersonate = Impersonate::random_weighted(&[(Chrome120, 0.0), (Safari18, 2.0)]);
+            assert!(matches!(impersonate, Safari18));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid impersonate weights")]
+    fn random_weighted_rejects_empty_weights() {
+        Impersonate::random_weighted(&[]);
+    }
+}
@@ -6,36 +6,61 @@
 //!   `ClientBuilder`.
 
 #![allow(missing_docs)]
+mod adaptive;
+mod alert;
+mod cipher;
 mod connector;
+mod der;
 mod extension;
+#[cfg(feature = "__fuzz")]
+mod fuzz;
 mod impersonate;
+mod ocsp;
+mod post_handshake;
+mod sct;
 mod settings;
 
 use crate::{connect::HttpConnector, HttpVersionPref};
+pub(crate) use adaptive::AdaptiveVersionCache;
+pub use adaptive::HttpVersionCacheStats;
+pub use alert::{AlertDescription, AlertDirection, AlertLevel, TlsAlert, TlsAlertCallback};
 use boring::{
     error::ErrorStack,
-    ssl::{SslConnector, SslMethod, SslOptions, SslVersion},
+    ssl::{SslConnector, SslMethod, SslOptions, SslRef, SslVersion},
 };
-pub use connector::MaybeHttpsStream;
+pub(crate) use cipher::find_cipher;
+pub use cipher::CipherInfo;
 use connector::{HttpsConnector, HttpsLayer, HttpsLayerSettings};
+pub use connector::{MaybeHttpsStream, SessionCacheStats};
 pub use extension::cert_compression;
+pub(crate) use extension::custom_ext;
+pub use extension::custom_ext::RawTlsExtension;
 use extension::{TlsConnectExtension, TlsExtension};
 pub use impersonate::{
-    chrome, edge, okhttp, safari, tls_settings, Impersonate, ImpersonateSettings,
+    chrome, edge, okhttp, safari, tls_settings, FieldDiff, Impersonate, ImpersonateDiff,
+    ImpersonateSettings, ImpersonateSettingsFrom,
 };
-pub use settings::{Http2Settings, TlsSettings};
+pub use ocsp::OcspStatus;
+pub use post_handshake::{PostHandshakeCallback, PostHandshakeKind, PostHandshakeMessage};
+pub use sct::{Sct, SctSource};
+pub(crate) use settings::check_pseudo_order;
+pub use settings::{Http2Settings, Http2SettingsError, TlsSettings, TlsSettingsError};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
 
 type TlsResult<T> = std::result::Result<T, ErrorStack>;
 type ConnectLayer = HttpsLayer;
 
 /// A wrapper around a `SslConnectorBuilder` that allows for additional settings.
 #[derive(Clone)]
-#[allow(missing_debug_implementations)]
 pub struct BoringTlsConnector {
     tls_sni: bool,
     enable_ech_grease: bool,
     application_settings: bool,
     http_version_pref: HttpVersionPref,
+    // Summary of the fingerprint, for `Debug`.
+    cipher_list_hash: Option<u64>,
+    curves: Vec<String>,
     connect_layer: ConnectLayer,
     ws_connect_layer: Option<ConnectLayer>,
 }
@@ -43,17 +68,21 @@ pub struct BoringTlsConnector {
 impl BoringTlsConnector {
     /// Create a new `BoringTlsConnector` with the given function.
     pub fn new(settings: TlsSettings) -> TlsResult<BoringTlsConnector> {
+        Self::with_settings(&settings)
+    }
+
+    fn with_settings(settings: &TlsSettings) -> TlsResult<BoringTlsConnector> {
         // If the HTTP version preference is HTTP/1, we only need to create the
         // connect layer once.
         let (connect_layer, ws_connect_layer) = match settings.http_version_pref {
             HttpVersionPref::Http1 => {
-                let connect_layer = create_connect_layer(&settings, settings.http_version_pref)?;
+                let connect_layer = create_connect_layer(settings, settings.http_version_pref)?;
                 (connect_layer, None)
             }
             HttpVersionPref::Http2 | HttpVersionPref::All => {
-                let connect_layer = create_connect_layer(&settings, settings.http_version_pref)?;
+                let connect_layer = create_connect_layer(settings, settings.http_version_pref)?;
                 // Set websocket use http1 alpn proto
-                let ws_connect_layer = create_connect_layer(&settings, HttpVersionPref::Http1)?;
+                let ws_connect_layer = create_connect_layer(settings, HttpVersionPref::Http1)?;
                 (connect_layer, Some(ws_connect_layer))
             }
         };
@@ -63,17 +92,35 @@ impl BoringTlsConnector {
             enable_ech_grease: settings.enable_ech_grease,
             application_settings: settings.application_settings,
             http_version_pref: settings.http_version_pref,
+            cipher_list_hash: settings.cipher_list.as_deref().map(fnv1a),
+            curves: settings
+                .curves
+                .iter()
+                .flatten()
+                .map(|curve| format!("{:?}", curve))
+                .collect(),
             connect_layer,
             ws_connect_layer,
         })
     }
 
-    /// Create a new `HttpsConnector` with the settings from the `HttpConnector`.
+    /// Returns the session cache counters of all connect layers.
+    pub(crate) fn session_cache_stats(&self) -> SessionCacheStats {
+        self.ws_connect_layer
+            .iter()
+            .fold(self.connect_layer.session_cache_stats(), |stats, layer| {
+                stats.merge(layer.session_cache_stats())
+            })
+    }
+
+    /// Create a new `HttpsConnector` with the settings from the `HttpConnector`,
+    /// overriding the minimum TLS version with `min_tls_version`.
     #[inline]
     pub(crate) async fn create_connector(
         &self,
         http: HttpConnector,
         ws: bool,
+        min_tls_version: Option<Version>,
     ) -> HttpsConnector<HttpConnector> {
         // Create the `HttpsConnector` with the given `HttpConnector` and `ConnectLayer`.
         let mut http = HttpsConnector::with_connector_layer(
@@ -98,6 +145,11 @@ impl BoringTlsConnector {
             conf.configure_enable_ech_grease(enable_ech_grease)?
                 .set_verify_hostname(tls_sni);
 
+            // Override the minimum TLS version if it is set.
+            if let Some(version) = min_tls_version {
+                conf.set_min_proto_version(Some(version.0))?;
+            }
+
             // Add application settings if it is set.
             if application_settings {
                 conf.configure_add_application_settings(http_version_pref)?;
@@ -109,6 +161,106 @@ impl BoringTlsConnector {
     }
 }
 
+impl std::fmt::Debug for BoringTlsConnector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoringTlsConnector")
+            .field("tls_sni", &self.tls_sni)
+            .field("enable_ech_grease", &self.enable_ech_grease)
+            .field("application_settings", &self.application_settings)
+            .field("http_version_pref", &self.http_version_pref)
+            .field("cipher_list_hash", &self.cipher_list_hash)
+            .field("curves", &self.curves)
+            .finish()
+    }
+}
+
+/// The 64-bit FNV-1a hash of `s`, to tell cipher lists apart in `Debug`
+/// output without printing them.
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// A `BoringTlsConnector` built either upfront or on first use.
+#[derive(Clone)]
+pub(crate) enum MaybeLazyTlsConnector {
+    Ready(BoringTlsConnector),
+    Lazy {
+        settings: Arc<TlsSettings>,
+        connector: Arc<OnceCell<BoringTlsConnector>>,
+    },
+}
+
+impl MaybeLazyTlsConnector {
+    /// Create a new `MaybeLazyTlsConnector`, building the connector now unless `lazy` is set.
+    pub(crate) fn new(settings: TlsSettings, lazy: bool) -> TlsResult<MaybeLazyTlsConnector> {
+        if lazy {
+            Ok(Self::Lazy {
+                settings: Arc::new(settings),
+                connector: Arc::new(OnceCell::new()),
+            })
+        } else {
+            BoringTlsConnector::new(settings).map(Self::Ready)
+        }
+    }
+
+    /// Returns the connector, building it on first use.
+    async fn get(&self) -> TlsResult<&BoringTlsConnector> {
+        match self {
+            Self::Ready(connector) => Ok(connector),
+            Self::Lazy {
+                settings,
+                connector,
+            } => {
+                connector
+                    .get_or_try_init(|| async { BoringTlsConnector::with_settings(settings) })
+                    .await
+            }
+        }
+    }
+
+    /// Create a new `HttpsConnector`, building the TLS connector first if needed.
+    pub(crate) async fn create_connector(
+        &self,
+        http: HttpConnector,
+        ws: bool,
+        min_tls_version: Option<Version>,
+    ) -> TlsResult<HttpsConnector<HttpConnector>> {
+        Ok(self
+            .get()
+            .await?
+            .create_connector(http, ws, min_tls_version)
+            .await)
+    }
+
+    /// The protocols offered through ALPN, only `http/1.1` for `http1_alpn`
+    /// connections, as with `create_connector`.
+    pub(crate) fn alpn_protos(&self, http1_alpn: bool) -> &'static [&'static str] {
+        let http_version_pref = match self {
+            Self::Ready(connector) => connector.http_version_pref,
+            Self::Lazy { settings, .. } => settings.http_version_pref,
+        };
+        match http_version_pref {
+            _ if http1_alpn => &["http/1.1"],
+            HttpVersionPref::Http1 => &["http/1.1"],
+            HttpVersionPref::Http2 => &["h2"],
+            HttpVersionPref::All => &["h2", "http/1.1"],
+        }
+    }
+
+    /// Returns the session cache counters, all zero if not built yet.
+    pub(crate) fn session_cache_stats(&self) -> SessionCacheStats {
+        match self {
+            Self::Ready(connector) => connector.session_cache_stats(),
+            Self::Lazy { connector, .. } => connector
+                .get()
+                .map(BoringTlsConnector::session_cache_stats)
+                .unwrap_or_default(),
+        }
+    }
+}
+
 /// Create a new `ConnectLayer` with the given `Tls` settings.
 #[inline]
 fn create_connect_layer(
@@ -162,12 +314,12 @@ fn create_connect_layer(
 
     // Set the signature algorithms list if it is set.
     if let Some(sigalgs_list) = tls.sigalgs_list.as_deref() {
-        connector.set_sigalgs_list(sigalgs_list)?;
+        connector.set_sigalgs_list(c_str_prefix(sigalgs_list))?;
     }
 
     // Set the cipher list if it is set.
     if let Some(cipher_list) = tls.cipher_list.as_deref() {
-        connector.set_cipher_list(cipher_list)?;
+        connector.set_cipher_list(c_str_prefix(cipher_list))?;
     }
 
     // Set the certificate compression algorithm if it is set.
@@ -175,6 +327,21 @@ fn create_connect_layer(
         connector = connector.configure_add_cert_compression_alg(cert_compression_algorithm)?;
     }
 
+    // Set the TLS alert callback if it is set.
+    if let Some(callback) = tls.on_tls_alert.clone() {
+        alert::set_callback(&mut connector, callback)?;
+    }
+
+    // Set the post-handshake message callback if it is set.
+    if let Some(callback) = tls.on_post_handshake_message.clone() {
+        post_handshake::set_callback(&mut connector, callback)?;
+    }
+
+    // Add the custom extensions if they are set.
+    if let Some(extensions) = tls.custom_extensions.as_deref() {
+        connector = connector.configure_custom_extensions(extensions)?;
+    }
+
     // Conditionally configure the TLS builder based on the "boring-tls-native-roots" feature.
     // If no custom CA cert store, use the system's native certificate store if the feature is enabled.
     let connector = if settings.ca_cert_store.is_none() {
@@ -206,15 +373,30 @@ fn create_connect_layer(
         connector.configure_ca_cert_store(settings.ca_cert_store.as_deref())?
     };
 
-    // Create the `HttpsLayerSettings` with the default session cache capacity.
-    let settings = HttpsLayerSettings::builder()
-        .session_cache_capacity(8)
-        .session_cache(tls.pre_shared_key)
-        .build();
+    // Create the `HttpsLayerSettings` with the configured session cache.
+    let mut builder = HttpsLayerSettings::builder().session_cache(tls.pre_shared_key);
+    if let Some(capacity) = tls.session_cache_capacity {
+        builder = builder.session_cache_capacity(capacity);
+    }
+    if let Some(ttl) = tls.session_cache_ttl {
+        builder = builder.session_cache_ttl(Some(ttl));
+    }
+    if let Some(lifetime) = tls.session_ticket_max_lifetime {
+        builder = builder.session_ticket_max_lifetime(Some(lifetime));
+    }
+    let settings = builder.build();
 
     HttpsLayer::with_connector_and_settings(connector, settings)
 }
 
+/// The part of `s` BoringSSL would see as a C string.
+///
+/// The list setters panic on an interior NUL, so cut the string there as C
+/// would instead.
+fn c_str_prefix(s: &str) -> &str {
+    s.split('\0').next().unwrap_or_default()
+}
+
 /// A TLS protocol version.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Version(SslVersion);
@@ -230,6 +412,11 @@ impl Version {
     pub const TLS_1_2: Version = Version(SslVersion::TLS1_2);
     /// Version 1.3 of the TLS protocol.
     pub const TLS_1_3: Version = Version(SslVersion::TLS1_3);
+
+    /// The version negotiated on `ssl`, once handshaken.
+    pub(crate) fn of(ssl: &SslRef) -> Option<Version> {
+        ssl.version2().map(Version)
+    }
 }
 
 /// Hyper extension carrying extra TLS layer information.
@@ -237,11 +424,126 @@ impl Version {
 #[derive(Debug, Clone)]
 pub struct TlsInfo {
     pub(crate) peer_certificate: Option<Vec<u8>>,
+    ocsp_response: Option<Vec<u8>>,
+    ocsp_status: Option<OcspStatus>,
+    scts: Vec<Sct>,
 }
 
 impl TlsInfo {
+    pub(crate) fn new(ssl: &SslRef) -> TlsInfo {
+        let peer_certificate = ssl.peer_certificate().and_then(|c| c.to_der().ok());
+        let ocsp_response = ssl.ocsp_status().map(<[u8]>::to_vec);
+        let ocsp_status = match (&ocsp_response, &peer_certificate) {
+            (None, _) => Some(OcspStatus::Absent),
+            (Some(response), Some(leaf)) => {
+                let chain = ssl
+                    .peer_cert_chain()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|cert| cert.to_der().ok())
+                    .collect::<Vec<_>>();
+                ocsp::status(response, leaf, &chain)
+            }
+            (Some(_), None) => None,
+        };
+        let scts = sct::collect(ssl, peer_certificate.as_deref());
+        TlsInfo {
+            peer_certificate,
+            ocsp_response,
+            ocsp_status,
+            scts,
+        }
+    }
+
     /// Get the DER encoded leaf certificate of the peer.
     pub fn peer_certificate(&self) -> Option<&[u8]> {
         self.peer_certificate.as_ref().map(|der| &der[..])
     }
+
+    /// Get the DER encoded OCSP response stapled by the peer, if it stapled
+    /// one.
+    ///
+    /// Servers only staple responses to clients that ask for them, with
+    /// `TlsSettings::enable_ocsp_stapling`, as browsers do.
+    pub fn ocsp_response(&self) -> Option<&[u8]> {
+        self.ocsp_response.as_deref()
+    }
+
+    /// Get the status of the leaf certificate of the peer in its stapled
+    /// OCSP response.
+    ///
+    /// This is what the response says, whether or not it is signed by the
+    /// issuer of the certificate, and does not fail the handshake. `None`
+    /// if the response is not a successful one about the leaf certificate.
+    pub fn ocsp_status(&self) -> Option<OcspStatus> {
+        self.ocsp_status
+    }
+
+    /// Get the signed certificate timestamps of the leaf certificate of the
+    /// peer, from the TLS extension first, then from the certificate.
+    ///
+    /// Servers only send the TLS extension to clients that ask for it, with
+    /// `TlsSettings::enable_signed_cert_timestamps`. Their signatures are
+    /// not verified.
+    pub fn signed_certificate_timestamps(&self) -> &[Sct] {
+        &self.scts
+    }
+}
+
+/// Hyper extension carrying the protocols negotiated through ALPN.
+/// Made available to clients on responses received over TLS when
+/// `protocol_info` or `tls_info` is set.
+#[derive(Debug, Clone)]
+pub struct ProtocolInfo {
+    pub(crate) alpn_offered: &'static [&'static str],
+    pub(crate) alpn_selected: Option<String>,
+    pub(crate) via_proxy_tunnel: bool,
+}
+
+impl ProtocolInfo {
+    /// The protocols offered through ALPN, in order of preference.
+    pub fn alpn_offered(&self) -> &[&'static str] {
+        self.alpn_offered
+    }
+
+    /// The protocol the server selected through ALPN, if it selected one.
+    pub fn alpn_selected(&self) -> Option<&str> {
+        self.alpn_selected.as_deref()
+    }
+
+    /// Whether ALPN selected HTTP/2.
+    pub fn is_h2(&self) -> bool {
+        self.alpn_selected() == Some("h2")
+    }
+
+    /// Whether the connection was tunneled through a proxy.
+    pub fn via_proxy_tunnel(&self) -> bool {
+        self.via_proxy_tunnel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use boring::ssl::SslCurve;
+
+    #[test]
+    fn connector_debug_summarizes_fingerprint() {
+        assert_eq!(fnv1a(""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a("a"), 0xaf63dc4c8601ec8c);
+
+        let cipher_list = "TLS_AES_128_GCM_SHA256:ECDHE-RSA-AES128-GCM-SHA256";
+        let connector = BoringTlsConnector::new(
+            TlsSettings::builder()
+                .cipher_list(cipher_list.to_owned())
+                .curves(vec![SslCurve::X25519])
+                .build(),
+        )
+        .unwrap();
+
+        let debug = format!("{:?}", connector);
+        assert!(debug.contains(&format!("cipher_list_hash: Some({})", fnv1a(cipher_list))));
+        assert!(debug.contains("curves: [\""), "{debug}");
+        assert!(!debug.contains(cipher_list), "{debug}");
+    }
 }
@@ -0,0 +1,235 @@
+//! The status of stapled OCSP responses.
+//!
+//! BoringSSL hands over the staple as is, without parsing it, so the few
+//! fields needed to find the response about the leaf certificate are read
+//! from the DER here.
+
+use boring::hash::{hash, MessageDigest};
+
+use super::der::{Der, BIT_STRING, ENUMERATED, INTEGER, OCTET_STRING, OID, SEQUENCE};
+
+/// The revocation status of the leaf certificate of a server, as told by
+/// the OCSP response it stapled, see
+/// [`TlsInfo::ocsp_status`](super::TlsInfo::ocsp_status).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OcspStatus {
+    /// The certificate is not revoked.
+    Good,
+    /// The certificate is revoked.
+    Revoked,
+    /// The responder doesn't know about the certificate.
+    Unknown,
+    /// The server stapled no OCSP response.
+    Absent,
+}
+
+const OCSP_BASIC: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01];
+const SHA1: &[u8] = &[0x2b, 0x0e, 0x03, 0x02, 0x1a];
+const SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+const SHA384: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02];
+const SHA512: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03];
+
+/// The status of `leaf` in the OCSP `response`, its issuer being one of
+/// `chain`.
+///
+/// `None` if the response is not a successful basic one, or has nothing
+/// about `leaf`. The signature of the response is not verified.
+pub(crate) fn status(response: &[u8], leaf: &[u8], chain: &[Vec<u8>]) -> Option<OcspStatus> {
+    let leaf = Certificate::parse(leaf)?;
+    let issuer = chain
+        .iter()
+        .filter_map(|der| Certificate::parse(der))
+        .find(|cert| cert.subject == leaf.issuer)?;
+
+    let mut response = Der(Der(response).read(SEQUENCE)?);
+    if response.read(ENUMERATED)? != [0] {
+        return None;
+    }
+    let mut bytes = Der(Der(response.read(0xa0)?).read(SEQUENCE)?);
+    if bytes.read(OID)? != OCSP_BASIC {
+        return None;
+    }
+    let mut basic = Der(Der(bytes.read(OCTET_STRING)?).read(SEQUENCE)?);
+    let mut data = Der(basic.read(SEQUENCE)?);
+    data.skip(0xa0);
+    // The responder id, by name or by key.
+    data.next()?;
+    // producedAt
+    data.next()?;
+
+    let mut responses = Der(data.read(SEQUENCE)?);
+    while !responses.0.is_empty() {
+        let mut single = Der(responses.read(SEQUENCE)?);
+        let mut cert_id = Der(single.read(SEQUENCE)?);
+        let digest = match Der(cert_id.read(SEQUENCE)?).read(OID)? {
+            SHA1 => MessageDigest::sha1(),
+            SHA256 => MessageDigest::sha256(),
+            SHA384 => MessageDigest::sha384(),
+            SHA512 => MessageDigest::sha512(),
+            _ => continue,
+        };
+        let name_hash = cert_id.read(OCTET_STRING)?;
+        let key_hash = cert_id.read(OCTET_STRING)?;
+        let serial = cert_id.read(INTEGER)?;
+        if serial != leaf.serial
+            || hash(digest, issuer.subject).ok()?[..] != *name_hash
+            || hash(digest, issuer.public_key).ok()?[..] != *key_hash
+        {
+            continue;
+        }
+
+        return match single.next()?.0 {
+            0x80 => Some(OcspStatus::Good),
+            0xa1 => Some(OcspStatus::Revoked),
+            0x82 => Some(OcspStatus::Unknown),
+            _ => None,
+        };
+    }
+    None
+}
+
+/// The fields of a certificate a response is matched against.
+struct Certificate<'a> {
+    serial: &'a [u8],
+    /// The DER of the issuer name.
+    issuer: &'a [u8],
+    /// The DER of the subject name.
+    subject: &'a [u8],
+    /// The bits of the public key.
+    public_key: &'a [u8],
+}
+
+impl<'a> Certificate<'a> {
+    fn parse(der: &'a [u8]) -> Option<Certificate<'a>> {
+        let mut cert = Der(Der(der).read(SEQUENCE)?);
+        let mut tbs = Der(cert.read(SEQUENCE)?);
+        tbs.skip(0xa0);
+        let serial = tbs.read(INTEGER)?;
+        // The signature algorithm.
+        tbs.read(SEQUENCE)?;
+        let issuer = tbs.next()?.2;
+        // The validity.
+        tbs.read(SEQUENCE)?;
+        let subject = tbs.next()?.2;
+        let mut key_info = Der(tbs.read(SEQUENCE)?);
+        key_info.read(SEQUENCE)?;
+        // Past the count of unused bits.
+        let public_key = key_info.read(BIT_STRING)?.get(1..)?;
+        Some(Certificate {
+            serial,
+            issuer,
+            subject,
+            public_key,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tlv(tag: u8, parts: &[&[u8]]) -> Vec<u8> {
+        let contents = parts.concat();
+        let mut der = vec![tag];
+        match contents.len() {
+            len @ 0..=0x7f => der.push(len as u8),
+            len @ 0x80..=0xff => der.extend([0x81, len as u8]),
+            len => der.extend([0x82, (len >> 8) as u8, len as u8]),
+        }
+        der.extend(contents);
+        der
+    }
+
+    fn name(cn: &str) -> Vec<u8> {
+        let attr = tlv(
+            SEQUENCE,
+            &[
+                &tlv(OID, &[&[0x55, 0x04, 0x03]]),
+                &tlv(0x0c, &[cn.as_bytes()]),
+            ],
+        );
+        tlv(SEQUENCE, &[&tlv(0x31, &[&attr])])
+    }
+
+    fn certificate(serial: u8, issuer: &str, subject: &str, key: &[u8]) -> Vec<u8> {
+        let algorithm = tlv(SEQUENCE, &[&tlv(OID, &[&[0x2a, 0x03]])]);
+        let tbs = tlv(
+            SEQUENCE,
+            &[
+                &tlv(0xa0, &[&tlv(INTEGER, &[&[2]])]),
+                &tlv(INTEGER, &[&[serial]]),
+                &algorithm,
+                &name(issuer),
+                &tlv(SEQUENCE, &[]),
+                &name(subject),
+                &tlv(SEQUENCE, &[&algorithm, &tlv(BIT_STRING, &[&[0], key])]),
+            ],
+        );
+        tlv(SEQUENCE, &[&tbs, &algorithm, &tlv(BIT_STRING, &[&[0]])])
+    }
+
+    fn response(serial: u8, issuer: &str, key: &[u8], cert_status: &[u8]) -> Vec<u8> {
+        let sha1 = MessageDigest::sha1();
+        let cert_id = tlv(
+            SEQUENCE,
+            &[
+                &tlv(SEQUENCE, &[&tlv(OID, &[SHA1]), &tlv(0x05, &[])]),
+                &tlv(OCTET_STRING, &[&hash(sha1, &name(issuer)).unwrap()]),
+                &tlv(OCTET_STRING, &[&hash(sha1, key).unwrap()]),
+                &tlv(INTEGER, &[&[serial]]),
+            ],
+        );
+        let single = tlv(
+            SEQUENCE,
+            &[&cert_id, cert_status, &tlv(0x18, &[b"20260101000000Z"])],
+        );
+        let data = tlv(
+            SEQUENCE,
+            &[
+                &tlv(0xa2, &[&tlv(OCTET_STRING, &[&[0; 20]])]),
+                &tlv(0x18, &[b"20260101000000Z"]),
+                &tlv(SEQUENCE, &[&single]),
+            ],
+        );
+        let basic = tlv(
+            SEQUENCE,
+            &[&data, &tlv(SEQUENCE, &[]), &tlv(BIT_STRING, &[&[0]])],
+        );
+        let bytes = tlv(
+            SEQUENCE,
+            &[&tlv(OID, &[OCSP_BASIC]), &tlv(OCTET_STRING, &[&basic])],
+        );
+        tlv(
+            SEQUENCE,
+            &[&tlv(ENUMERATED, &[&[0]]), &tlv(0xa0, &[&bytes])],
+        )
+    }
+
+    #[test]
+    fn status_of_the_leaf() {
+        let leaf = certificate(7, "CA", "example.com", b"leaf key");
+        let chain = [leaf.clone(), certificate(1, "Root", "CA", b"ca key")];
+        let good = tlv(0x80, &[]);
+        let revoked = tlv(0xa1, &[&tlv(0x18, &[b"20260101000000Z"])]);
+
+        let check = |response: Vec<u8>| status(&response, &leaf, &chain);
+        assert_eq!(
+            check(response(7, "CA", b"ca key", &good)),
+            Some(OcspStatus::Good)
+        );
+        assert_eq!(
+            check(response(7, "CA", b"ca key", &revoked)),
+            Some(OcspStatus::Revoked)
+        );
+        assert_eq!(
+            check(response(7, "CA", b"ca key", &tlv(0x82, &[]))),
+            Some(OcspStatus::Unknown)
+        );
+        // About another certificate.
+        assert_eq!(check(response(8, "CA", b"ca key", &revoked)), None);
+        assert_eq!(check(response(7, "CA", b"other key", &revoked)), None);
+        // Unsuccessful.
+        assert_eq!(check(tlv(SEQUENCE, &[&tlv(ENUMERATED, &[&[1]])])), None);
+        assert_eq!(check(b"garbage".to_vec()), None);
+    }
+}
@@ -0,0 +1,99 @@
+use super::TlsResult;
+use boring::ex_data::Index;
+use boring::ssl::{SslConnectorBuilder, SslContext, SslRef};
+use boring_sys as ffi;
+use foreign_types::ForeignTypeRef;
+use std::os::raw::{c_int, c_void};
+use std::sync::{Arc, LazyLock};
+
+/// A callback told about the TLS handshake messages received after the handshake.
+pub type PostHandshakeCallback = Arc<dyn Fn(PostHandshakeMessage) + Send + Sync>;
+
+/// The type of a [`PostHandshakeMessage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostHandshakeKind {
+    /// A `NewSessionTicket` message.
+    NewSessionTicket,
+    /// A `KeyUpdate` message.
+    KeyUpdate,
+    /// A `CertificateRequest` message, for post-handshake authentication.
+    CertificateRequest,
+    /// Any other message, with its type code.
+    Other(u8),
+}
+
+/// A TLS handshake message received after the handshake completed.
+#[derive(Debug, Clone)]
+pub struct PostHandshakeMessage {
+    kind: PostHandshakeKind,
+    bytes: Vec<u8>,
+}
+
+impl PostHandshakeMessage {
+    /// The type of the message.
+    pub fn kind(&self) -> PostHandshakeKind {
+        self.kind
+    }
+
+    /// The raw message, including its type and length header.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl From<u8> for PostHandshakeKind {
+    fn from(code: u8) -> PostHandshakeKind {
+        match code {
+            4 => PostHandshakeKind::NewSessionTicket,
+            13 => PostHandshakeKind::CertificateRequest,
+            24 => PostHandshakeKind::KeyUpdate,
+            code => PostHandshakeKind::Other(code),
+        }
+    }
+}
+
+fn callback_index() -> TlsResult<Index<SslContext, PostHandshakeCallback>> {
+    static IDX: LazyLock<TlsResult<Index<SslContext, PostHandshakeCallback>>> =
+        LazyLock::new(SslContext::new_ex_index);
+    IDX.clone()
+}
+
+/// Calls `callback` with the post-handshake messages of the connections of `builder`.
+pub(super) fn set_callback(
+    builder: &mut SslConnectorBuilder,
+    callback: PostHandshakeCallback,
+) -> TlsResult<()> {
+    builder.set_ex_data(callback_index()?, callback);
+    unsafe { ffi::SSL_CTX_set_msg_callback(builder.as_ptr(), Some(msg_callback)) };
+    Ok(())
+}
+
+unsafe extern "C" fn msg_callback(
+    is_write: c_int,
+    _version: c_int,
+    content_type: c_int,
+    buf: *const c_void,
+    len: usize,
+    ssl: *mut ffi::SSL,
+    _arg: *mut c_void,
+) {
+    if is_write != 0
+        || content_type != ffi::SSL3_RT_HANDSHAKE as c_int
+        || len == 0
+        || ffi::SSL_in_init(ssl) != 0
+    {
+        return;
+    }
+
+    let ssl = SslRef::from_ptr(ssl);
+    if let Some(callback) = callback_index()
+        .ok()
+        .and_then(|idx| ssl.ssl_context().ex_data(idx))
+    {
+        let bytes = std::slice::from_raw_parts(buf as *const u8, len).to_vec();
+        callback(PostHandshakeMessage {
+            kind: bytes[0].into(),
+            bytes,
+        });
+    }
+}
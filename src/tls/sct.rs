@@ -0,0 +1,216 @@
+//! Signed certificate timestamps (RFC 6962), from the TLS extension and
+//! from the leaf certificate.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use boring::ssl::SslRef;
+use boring_sys as ffi;
+use foreign_types::ForeignTypeRef;
+
+use super::der::{Der, OCTET_STRING, OID, SEQUENCE};
+
+/// The OID of the SCT list extension of certificates.
+const SCT_LIST: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xd6, 0x79, 0x02, 0x04, 0x02];
+
+/// A signed certificate timestamp: the promise of a Certificate
+/// Transparency log to publish the leaf certificate of the server.
+///
+/// See [`TlsInfo::signed_certificate_timestamps`](super::TlsInfo::signed_certificate_timestamps).
+/// The signatures are not verified, checking them needs the keys of the
+/// logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sct {
+    log_id: [u8; 32],
+    timestamp: u64,
+    signature: Vec<u8>,
+    source: SctSource,
+}
+
+/// Where a server gave a [`Sct`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SctSource {
+    /// The `signed_certificate_timestamp` TLS extension, sent to clients
+    /// that ask for it with `TlsSettings::enable_signed_cert_timestamps`.
+    TlsExtension,
+    /// The SCT list extension of the leaf certificate.
+    Certificate,
+}
+
+impl Sct {
+    /// The id of the log, the SHA-256 hash of its public key.
+    pub fn log_id(&self) -> &[u8; 32] {
+        &self.log_id
+    }
+
+    /// When the log added the certificate.
+    pub fn timestamp(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.timestamp)
+    }
+
+    /// The signature of the log over the timestamp and the certificate.
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// Where the server gave this timestamp.
+    pub fn source(&self) -> SctSource {
+        self.source
+    }
+}
+
+/// The timestamps of the TLS extension of `ssl`, then of the `leaf`
+/// certificate.
+pub(crate) fn collect(ssl: &SslRef, leaf: Option<&[u8]>) -> Vec<Sct> {
+    let mut scts = Vec::new();
+    parse_list(tls_extension(ssl), SctSource::TlsExtension, &mut scts);
+    if let Some(list) = leaf.and_then(certificate_extension) {
+        parse_list(list, SctSource::Certificate, &mut scts);
+    }
+    scts
+}
+
+fn tls_extension(ssl: &SslRef) -> &[u8] {
+    let mut data = std::ptr::null();
+    let mut len = 0;
+    unsafe {
+        ffi::SSL_get0_signed_cert_timestamp_list(ssl.as_ptr(), &mut data, &mut len);
+        if data.is_null() {
+            &[]
+        } else {
+            std::slice::from_raw_parts(data, len)
+        }
+    }
+}
+
+/// The SCT list in the extensions of a DER certificate.
+fn certificate_extension(cert: &[u8]) -> Option<&[u8]> {
+    let mut cert = Der(Der(cert).read(SEQUENCE)?);
+    let mut tbs = Der(cert.read(SEQUENCE)?);
+    let extensions = loop {
+        match tbs.next()? {
+            (0xa3, contents, _) => break contents,
+            _ => continue,
+        }
+    };
+
+    let mut extensions = Der(Der(extensions).read(SEQUENCE)?);
+    while !extensions.0.is_empty() {
+        let mut extension = Der(extensions.read(SEQUENCE)?);
+        if extension.read(OID)? != SCT_LIST {
+            continue;
+        }
+        // Past the critical flag, if any.
+        let value = loop {
+            match extension.next()? {
+                (OCTET_STRING, contents, _) => break contents,
+                _ => continue,
+            }
+        };
+        return Der(value).read(OCTET_STRING);
+    }
+    None
+}
+
+/// Parses a TLS encoded SCT list, leaving out the timestamps of unknown
+/// versions.
+fn parse_list(list: &[u8], source: SctSource, scts: &mut Vec<Sct>) {
+    let mut list = Reader(list);
+    let Some(mut list) = list.vec16() else {
+        return;
+    };
+    while let Some(sct) = list.vec16() {
+        if let Some(sct) = parse(sct.0, source) {
+            scts.push(sct);
+        }
+    }
+}
+
+fn parse(sct: &[u8], source: SctSource) -> Option<Sct> {
+    let mut sct = Reader(sct);
+    // Version 1, the only one.
+    if sct.take(1)? != [0] {
+        return None;
+    }
+    let log_id = sct.take(32)?.try_into().ok()?;
+    let timestamp = u64::from_be_bytes(sct.take(8)?.try_into().ok()?);
+    // The extensions, then the hash and signature algorithms.
+    sct.vec16()?;
+    sct.take(2)?;
+    let signature = sct.vec16()?.0.to_vec();
+    Some(Sct {
+        log_id,
+        timestamp,
+        signature,
+        source,
+    })
+}
+
+/// A reader of TLS encoded structures.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.0.get(..n)?;
+        self.0 = &self.0[n..];
+        Some(bytes)
+    }
+
+    /// A vector with a 16 bit length.
+    fn vec16(&mut self) -> Option<Reader<'a>> {
+        let len = self.take(2)?;
+        let len = u16::from_be_bytes([len[0], len[1]]) as usize;
+        self.take(len).map(Reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vec16(bytes: &[u8]) -> Vec<u8> {
+        let mut vec = (bytes.len() as u16).to_be_bytes().to_vec();
+        vec.extend_from_slice(bytes);
+        vec
+    }
+
+    fn sct(log: u8, timestamp: u64) -> Vec<u8> {
+        let mut sct = vec![0];
+        sct.extend_from_slice(&[log; 32]);
+        sct.extend_from_slice(&timestamp.to_be_bytes());
+        sct.extend(vec16(&[]));
+        sct.extend_from_slice(&[4, 3]);
+        sct.extend(vec16(b"signature"));
+        sct
+    }
+
+    #[test]
+    fn parses_sct_lists() {
+        let mut unknown_version = sct(3, 0);
+        unknown_version[0] = 1;
+        let list = vec16(
+            &[
+                vec16(&sct(1, 1_700_000_000_000)),
+                vec16(&unknown_version),
+                vec16(&sct(2, 1_700_000_000_001)),
+            ]
+            .concat(),
+        );
+
+        let mut scts = Vec::new();
+        parse_list(&list, SctSource::Certificate, &mut scts);
+        assert_eq!(scts.len(), 2);
+        assert_eq!(scts[0].log_id(), &[1; 32]);
+        assert_eq!(
+            scts[0].timestamp(),
+            UNIX_EPOCH + Duration::from_millis(1_700_000_000_000)
+        );
+        assert_eq!(scts[0].signature(), b"signature");
+        assert_eq!(scts[1].log_id(), &[2; 32]);
+        assert_eq!(scts[1].source(), SctSource::Certificate);
+
+        // Truncated.
+        scts.clear();
+        parse_list(&list[..list.len() - 1], SctSource::TlsExtension, &mut scts);
+        assert!(scts.is_empty());
+    }
+}
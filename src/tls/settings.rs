@@ -1,13 +1,18 @@
 #![allow(missing_debug_implementations)]
 use crate::{
-    tls::{cert_compression::CertCompressionAlgorithm, TlsResult, Version},
+    tls::{
+        cert_compression::CertCompressionAlgorithm, cipher, CipherInfo, RawTlsExtension, TlsResult,
+        Version,
+    },
     HttpVersionPref,
 };
 use boring::{
     ssl::{SslConnectorBuilder, SslCurve},
     x509::store::X509Store,
 };
+use bytes::{BufMut, Bytes, BytesMut};
 use hyper::{PseudoOrder, SettingsOrder};
+use std::{error::Error as StdError, fmt, time::Duration};
 use typed_builder::TypedBuilder;
 
 // ============== TLS ==============
@@ -53,6 +58,20 @@ pub struct TlsSettings {
     #[builder(default = false)]
     pub pre_shared_key: bool,
 
+    /// The maximum number of cached sessions, defaults to 64.
+    #[builder(default, setter(into))]
+    pub session_cache_capacity: Option<usize>,
+
+    /// How long a cached session may be resumed, defaults to 1 hour.
+    #[builder(default, setter(into))]
+    pub session_cache_ttl: Option<Duration>,
+
+    /// The most the ticket lifetime a server advertises is trusted for when
+    /// caching a session, defaults to 24 hours. Sessions whose TLS 1.3
+    /// ticket has a lifetime of zero are not cached.
+    #[builder(default, setter(into))]
+    pub session_ticket_max_lifetime: Option<Duration>,
+
     /// Enable ECH grease.
     #[builder(default = false)]
     pub enable_ech_grease: bool,
@@ -73,11 +92,13 @@ pub struct TlsSettings {
     #[builder(default, setter(into))]
     pub curves: Option<Vec<SslCurve>>,
 
-    /// The signature algorithms list to use.
+    /// The signature algorithms list to use, colon-separated, of names from
+    /// [`TlsSettings::supported_sigalgs`].
     #[builder(default, setter(into))]
     pub sigalgs_list: Option<String>,
 
-    /// The cipher list to use.
+    /// The cipher list to use, colon-separated, of names from
+    /// [`TlsSettings::supported_ciphers`] or BoringSSL cipher rules.
     #[builder(default, setter(into))]
     pub cipher_list: Option<String>,
 
@@ -88,6 +109,20 @@ pub struct TlsSettings {
     /// The certificate compression algorithm to use.
     #[builder(default, setter(into))]
     pub cert_compression_algorithm: Option<CertCompressionAlgorithm>,
+
+    /// Extensions added to the ClientHello as raw bytes.
+    #[builder(default, setter(into))]
+    pub custom_extensions: Option<Vec<RawTlsExtension>>,
+
+    /// Called with the handshake messages received after the handshake, such
+    /// as `NewSessionTicket` and `KeyUpdate`, for debugging.
+    #[builder(default, setter(strip_option))]
+    pub on_post_handshake_message: Option<PostHandshakeCallback>,
+
+    /// Called with the TLS alerts sent and received, before a fatal alert
+    /// closes the connection.
+    #[builder(default, setter(strip_option))]
+    pub on_tls_alert: Option<TlsAlertCallback>,
 }
 
 impl std::fmt::Debug for TlsSettings {
@@ -101,6 +136,12 @@ impl std::fmt::Debug for TlsSettings {
             .field("max_tls_version", &self.max_tls_version)
             .field("application_settings", &self.application_settings)
             .field("pre_shared_key", &self.pre_shared_key)
+            .field("session_cache_capacity", &self.session_cache_capacity)
+            .field("session_cache_ttl", &self.session_cache_ttl)
+            .field(
+                "session_ticket_max_lifetime",
+                &self.session_ticket_max_lifetime,
+            )
             .field("enable_ech_grease", &self.enable_ech_grease)
             .field("permute_extensions", &self.permute_extensions)
             .field("grease_enabled", &self.grease_enabled)
@@ -116,10 +157,144 @@ impl std::fmt::Debug for TlsSettings {
                 "cert_compression_algorithm",
                 &self.cert_compression_algorithm,
             )
+            .field("custom_extensions", &self.custom_extensions)
             .finish()
     }
 }
 
+impl TlsSettings {
+    /// The signature algorithm names BoringSSL accepts in
+    /// [`sigalgs_list`](TlsSettings::sigalgs_list).
+    pub fn supported_sigalgs() -> &'static [&'static str] {
+        SIGALGS
+    }
+
+    /// Set the signature algorithms list, failing on a name BoringSSL does
+    /// not know instead of when connecting.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rquest::tls::{TlsSettings, TlsSettingsError};
+    ///
+    /// let err = TlsSettings::default()
+    ///     .with_sigalgs_list("ecdsa_secp256r1_sha256:UNKNOWN_ALG")
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err,
+    ///     TlsSettingsError::UnknownSignatureAlgorithm("UNKNOWN_ALG".into())
+    /// );
+    /// ```
+    pub fn with_sigalgs_list(
+        mut self,
+        sigalgs_list: impl Into<String>,
+    ) -> Result<TlsSettings, TlsSettingsError> {
+        let sigalgs_list = sigalgs_list.into();
+        check_sigalgs_list(&sigalgs_list)?;
+        self.sigalgs_list = Some(sigalgs_list);
+        Ok(self)
+    }
+
+    /// The ciphers BoringSSL supports, which can be named in
+    /// [`cipher_list`](TlsSettings::cipher_list) by their `name` or
+    /// `standard_name`.
+    pub fn supported_ciphers() -> Vec<CipherInfo> {
+        cipher::supported_ciphers().to_vec()
+    }
+
+    /// Set the cipher list, failing on a name BoringSSL does not know
+    /// instead of silently leaving it out.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rquest::tls::{TlsSettings, TlsSettingsError};
+    ///
+    /// let err = TlsSettings::default()
+    ///     .with_cipher_list("TLS_AES_128_GCM_SHA256:TLS_FAKE_CIPHER")
+    ///     .unwrap_err();
+    /// assert_eq!(err, TlsSettingsError::UnknownCipher("TLS_FAKE_CIPHER".into()));
+    /// ```
+    pub fn with_cipher_list(
+        mut self,
+        cipher_list: impl Into<String>,
+    ) -> Result<TlsSettings, TlsSettingsError> {
+        let cipher_list = cipher_list.into();
+        check_cipher_list(&cipher_list)?;
+        self.cipher_list = Some(cipher_list);
+        Ok(self)
+    }
+
+    /// Check the settings are consistent.
+    pub fn validate(&self) -> Result<(), TlsSettingsError> {
+        if let Some(ref sigalgs_list) = self.sigalgs_list {
+            check_sigalgs_list(sigalgs_list)?;
+        }
+        if let Some(ref cipher_list) = self.cipher_list {
+            check_cipher_list(cipher_list)?;
+        }
+        Ok(())
+    }
+}
+
+/// The names of `kSignatureAlgorithmNames` in BoringSSL's `ssl_privkey.cc`.
+const SIGALGS: &[&str] = &[
+    "rsa_pkcs1_md5_sha1",
+    "rsa_pkcs1_sha1",
+    "rsa_pkcs1_sha256",
+    "rsa_pkcs1_sha384",
+    "rsa_pkcs1_sha512",
+    "ecdsa_sha1",
+    "ecdsa_secp256r1_sha256",
+    "ecdsa_secp384r1_sha384",
+    "ecdsa_secp521r1_sha512",
+    "rsa_pss_rsae_sha256",
+    "rsa_pss_rsae_sha384",
+    "rsa_pss_rsae_sha512",
+    "ed25519",
+];
+
+/// Checks every name of the colon-separated `sigalgs_list` is known.
+fn check_sigalgs_list(sigalgs_list: &str) -> Result<(), TlsSettingsError> {
+    match sigalgs_list.split(':').find(|name| !SIGALGS.contains(name)) {
+        Some(name) => Err(TlsSettingsError::UnknownSignatureAlgorithm(name.to_owned())),
+        None => Ok(()),
+    }
+}
+
+/// Checks every name of the colon-separated `cipher_list` is a known
+/// cipher or rule keyword.
+fn check_cipher_list(cipher_list: &str) -> Result<(), TlsSettingsError> {
+    match cipher::find_unknown_cipher(cipher_list) {
+        Some(name) => Err(TlsSettingsError::UnknownCipher(name.to_owned())),
+        None => Ok(()),
+    }
+}
+
+/// An error in [`TlsSettings`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TlsSettingsError {
+    /// The signature algorithms list has this name, which BoringSSL does
+    /// not know.
+    UnknownSignatureAlgorithm(String),
+    /// The cipher list has this name, which BoringSSL does not know.
+    UnknownCipher(String),
+}
+
+impl fmt::Display for TlsSettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TlsSettingsError::UnknownSignatureAlgorithm(name) => {
+                write!(f, "unknown TLS signature algorithm: {:?}", name)
+            }
+            TlsSettingsError::UnknownCipher(name) => write!(f, "unknown TLS cipher: {:?}", name),
+        }
+    }
+}
+
+impl StdError for TlsSettingsError {}
+
 // ============== http2 ==============
 #[derive(TypedBuilder, Debug)]
 pub struct Http2Settings {
@@ -173,4 +348,389 @@ pub struct Http2Settings {
     /// The pseudo header order.
     #[builder(default, setter(into))]
     pub headers_pseudo_order: Option<[PseudoOrder; 4]>,
+
+    // ============== connection ==============
+    /// How long to wait for the server to acknowledge the settings, 10
+    /// seconds if unset.
+    #[builder(default, setter(into))]
+    pub settings_ack_timeout: Option<Duration>,
+}
+
+impl Http2Settings {
+    /// Set a pseudo header order naming only some of the pseudo headers.
+    ///
+    /// HTTP/2 requests always carry the four pseudo headers, the ones left
+    /// out of `order` are sent after it, in the order `:method`, `:scheme`,
+    /// `:authority` and `:path`.
+    pub fn pseudo_headers_only(
+        mut self,
+        order: Vec<PseudoOrder>,
+    ) -> Result<Http2Settings, Http2SettingsError> {
+        check_pseudo_order(&order)?;
+
+        let mut full = order;
+        for pseudo in DEFAULT_PSEUDO_ORDER {
+            if !full.contains(&pseudo) {
+                full.push(pseudo);
+            }
+        }
+        self.headers_pseudo_order = Some([full[0], full[1], full[2], full[3]]);
+        Ok(self)
+    }
+
+    /// Check the settings are consistent.
+    pub fn validate(&self) -> Result<(), Http2SettingsError> {
+        match self.headers_pseudo_order {
+            Some(ref order) => check_pseudo_order(order),
+            None => Ok(()),
+        }
+    }
+
+    /// Serialize the settings frame fields into the SETTINGS frame sent
+    /// after the connection preface, frame header included.
+    ///
+    /// The settings that are set are written in `settings_order`, or in the
+    /// order of their identifiers if there is none, so the bytes can be
+    /// compared with a captured frame.
+    pub fn to_settings_frame_bytes(&self) -> Bytes {
+        let settings: Vec<(u16, u32)> = self
+            .settings_order
+            .unwrap_or(DEFAULT_SETTINGS_ORDER)
+            .iter()
+            .filter_map(|setting| self.setting(*setting))
+            .collect();
+
+        let len = settings.len() * SETTING_LEN;
+        let mut frame = BytesMut::with_capacity(FRAME_HEADER_LEN + len);
+        frame.put_uint(len as u64, 3);
+        frame.put_u8(SETTINGS_FRAME_TYPE);
+        frame.put_u8(0);
+        frame.put_u32(0);
+        for (id, value) in settings {
+            frame.put_u16(id);
+            frame.put_u32(value);
+        }
+        frame.freeze()
+    }
+
+    /// Parse a SETTINGS frame, frame header included, as serialized by
+    /// [`to_settings_frame_bytes`](Http2Settings::to_settings_frame_bytes).
+    ///
+    /// The settings are set in the order of the frame, and the fields the
+    /// frame doesn't carry, such as the pseudo header order, are left unset.
+    pub fn from_settings_frame_bytes(bytes: &[u8]) -> Result<Http2Settings, Http2SettingsError> {
+        let invalid = Http2SettingsError::InvalidSettingsFrame;
+        if bytes.len() < FRAME_HEADER_LEN {
+            return Err(invalid("truncated frame header"));
+        }
+        let (header, payload) = bytes.split_at(FRAME_HEADER_LEN);
+        let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+        if header[3] != SETTINGS_FRAME_TYPE {
+            return Err(invalid("not a SETTINGS frame"));
+        }
+        if header[4] & SETTINGS_ACK_FLAG != 0 {
+            return Err(invalid("SETTINGS acknowledgment"));
+        }
+        if header[5..] != [0; 4] {
+            return Err(invalid("SETTINGS frame on a stream"));
+        }
+        if len != payload.len() || len % SETTING_LEN != 0 {
+            return Err(invalid("invalid frame length"));
+        }
+
+        let mut settings = Http2Settings::builder().build();
+        let mut order = Vec::with_capacity(DEFAULT_SETTINGS_ORDER.len());
+        for chunk in payload.chunks_exact(SETTING_LEN) {
+            let id = u16::from_be_bytes([chunk[0], chunk[1]]);
+            let value = u32::from_be_bytes([chunk[2], chunk[3], chunk[4], chunk[5]]);
+            let setting = settings.set_setting(id, value)?;
+            if order.contains(&setting) {
+                return Err(invalid("duplicate setting"));
+            }
+            order.push(setting);
+        }
+
+        for setting in DEFAULT_SETTINGS_ORDER {
+            if !order.contains(&setting) {
+                order.push(setting);
+            }
+        }
+        if order != DEFAULT_SETTINGS_ORDER {
+            settings.settings_order = Some([
+                order[0], order[1], order[2], order[3], order[4], order[5], order[6], order[7],
+            ]);
+        }
+        Ok(settings)
+    }
+
+    /// The identifier and value of `setting`, if it is set.
+    fn setting(&self, setting: SettingsOrder) -> Option<(u16, u32)> {
+        let value = match setting {
+            SettingsOrder::HeaderTableSize => self.header_table_size?,
+            SettingsOrder::EnablePush => self.enable_push? as u32,
+            SettingsOrder::MaxConcurrentStreams => self.max_concurrent_streams?,
+            SettingsOrder::InitialWindowSize => self.initial_stream_window_size?,
+            SettingsOrder::MaxFrameSize => self.max_frame_size?,
+            SettingsOrder::MaxHeaderListSize => self.max_header_list_size?,
+            SettingsOrder::UnknownSetting8 => self.unknown_setting8? as u32,
+            SettingsOrder::UnknownSetting9 => self.unknown_setting9? as u32,
+        };
+        Some((setting_id(setting), value))
+    }
+
+    /// Set the setting identified by `id` to `value`.
+    fn set_setting(&mut self, id: u16, value: u32) -> Result<SettingsOrder, Http2SettingsError> {
+        let flag = |value| match value {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Http2SettingsError::InvalidSettingsFrame(
+                "invalid boolean setting",
+            )),
+        };
+        let setting = DEFAULT_SETTINGS_ORDER
+            .into_iter()
+            .find(|setting| setting_id(*setting) == id)
+            .ok_or(Http2SettingsError::InvalidSettingsFrame("unknown setting"))?;
+        match setting {
+            SettingsOrder::HeaderTableSize => self.header_table_size = Some(value),
+            SettingsOrder::EnablePush => self.enable_push = Some(flag(value)?),
+            SettingsOrder::MaxConcurrentStreams => self.max_concurrent_streams = Some(value),
+            SettingsOrder::InitialWindowSize => self.initial_stream_window_size = Some(value),
+            SettingsOrder::MaxFrameSize => self.max_frame_size = Some(value),
+            SettingsOrder::MaxHeaderListSize => self.max_header_list_size = Some(value),
+            SettingsOrder::UnknownSetting8 => self.unknown_setting8 = Some(flag(value)?),
+            SettingsOrder::UnknownSetting9 => self.unknown_setting9 = Some(flag(value)?),
+        }
+        Ok(setting)
+    }
+}
+
+const FRAME_HEADER_LEN: usize = 9;
+const SETTING_LEN: usize = 6;
+const SETTINGS_FRAME_TYPE: u8 = 0x4;
+const SETTINGS_ACK_FLAG: u8 = 0x1;
+
+/// The settings in the order of their identifiers.
+const DEFAULT_SETTINGS_ORDER: [SettingsOrder; 8] = [
+    SettingsOrder::HeaderTableSize,
+    SettingsOrder::EnablePush,
+    SettingsOrder::MaxConcurrentStreams,
+    SettingsOrder::InitialWindowSize,
+    SettingsOrder::MaxFrameSize,
+    SettingsOrder::MaxHeaderListSize,
+    SettingsOrder::UnknownSetting8,
+    SettingsOrder::UnknownSetting9,
+];
+
+fn setting_id(setting: SettingsOrder) -> u16 {
+    match setting {
+        SettingsOrder::HeaderTableSize => 0x1,
+        SettingsOrder::EnablePush => 0x2,
+        SettingsOrder::MaxConcurrentStreams => 0x3,
+        SettingsOrder::InitialWindowSize => 0x4,
+        SettingsOrder::MaxFrameSize => 0x5,
+        SettingsOrder::MaxHeaderListSize => 0x6,
+        SettingsOrder::UnknownSetting8 => 0x8,
+        SettingsOrder::UnknownSetting9 => 0x9,
+    }
+}
+
+const DEFAULT_PSEUDO_ORDER: [PseudoOrder; 4] = [
+    PseudoOrder::Method,
+    PseudoOrder::Scheme,
+    PseudoOrder::Authority,
+    PseudoOrder::Path,
+];
+
+/// Checks no pseudo header appears twice in `order`, so a full order has
+/// all four of them.
+pub(crate) fn check_pseudo_order(order: &[PseudoOrder]) -> Result<(), Http2SettingsError> {
+    for (i, pseudo) in order.iter().enumerate() {
+        if order[..i].contains(pseudo) {
+            return Err(Http2SettingsError::InvalidPseudoOrder(*pseudo));
+        }
+    }
+    Ok(())
+}
+
+/// An error in [`Http2Settings`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Http2SettingsError {
+    /// The pseudo header order has this pseudo header more than once, and so
+    /// misses another one.
+    InvalidPseudoOrder(PseudoOrder),
+    /// The bytes are not a valid SETTINGS frame, for this reason.
+    InvalidSettingsFrame(&'static str),
+}
+
+impl fmt::Display for Http2SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Http2SettingsError::InvalidPseudoOrder(pseudo) => write!(
+                f,
+                "invalid HTTP/2 pseudo header order: {:?} appears more than once",
+                pseudo
+            ),
+            Http2SettingsError::InvalidSettingsFrame(reason) => {
+                write!(f, "invalid HTTP/2 SETTINGS frame: {}", reason)
+            }
+        }
+    }
+}
+
+impl StdError for Http2SettingsError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::PseudoOrder::*;
+
+    #[test]
+    fn sigalgs_list_rejects_unknown_names() {
+        let settings = TlsSettings::default()
+            .with_sigalgs_list("ecdsa_secp256r1_sha256:rsa_pss_rsae_sha256:ed25519")
+            .unwrap();
+        assert_eq!(settings.validate(), Ok(()));
+
+        let err = TlsSettings::default()
+            .with_sigalgs_list("ecdsa_secp256r1_sha256:UNKNOWN_ALG")
+            .unwrap_err();
+        assert_eq!(
+            err,
+            TlsSettingsError::UnknownSignatureAlgorithm("UNKNOWN_ALG".into())
+        );
+
+        let settings = TlsSettings::builder()
+            .sigalgs_list("ecdsa_secp256r1_sha256:".to_owned())
+            .build();
+        assert_eq!(
+            settings.validate(),
+            Err(TlsSettingsError::UnknownSignatureAlgorithm(String::new()))
+        );
+    }
+
+    #[test]
+    fn cipher_list_rejects_unknown_names() {
+        let settings = TlsSettings::default()
+            .with_cipher_list("TLS_AES_128_GCM_SHA256:ECDHE-RSA-AES128-GCM-SHA256")
+            .unwrap();
+        assert_eq!(settings.validate(), Ok(()));
+
+        let settings = TlsSettings::builder()
+            .cipher_list("TLS_AES_128_GCM_SHA256:TLS_FAKE_CIPHER".to_owned())
+            .build();
+        assert_eq!(
+            settings.validate(),
+            Err(TlsSettingsError::UnknownCipher("TLS_FAKE_CIPHER".into()))
+        );
+    }
+
+    #[test]
+    fn pseudo_order_rejects_duplicates() {
+        let settings = Http2Settings::builder()
+            .headers_pseudo_order([Method, Path, Method, Scheme])
+            .build();
+        assert_eq!(
+            settings.validate(),
+            Err(Http2SettingsError::InvalidPseudoOrder(Method))
+        );
+
+        let settings = Http2Settings::builder()
+            .headers_pseudo_order([Method, Path, Authority, Scheme])
+            .build();
+        assert_eq!(settings.validate(), Ok(()));
+    }
+
+    #[test]
+    fn pseudo_headers_only_fills_the_rest() {
+        let settings = Http2Settings::builder()
+            .build()
+            .pseudo_headers_only(vec![Path, Authority])
+            .unwrap();
+        assert_eq!(
+            settings.headers_pseudo_order,
+            Some([Path, Authority, Method, Scheme])
+        );
+
+        let err = Http2Settings::builder()
+            .build()
+            .pseudo_headers_only(vec![Path, Path])
+            .unwrap_err();
+        assert_eq!(err, Http2SettingsError::InvalidPseudoOrder(Path));
+    }
+
+    #[test]
+    fn settings_frame_bytes_roundtrip() {
+        use hyper::SettingsOrder::*;
+
+        let settings = Http2Settings::builder()
+            .header_table_size(65536)
+            .enable_push(false)
+            .initial_stream_window_size(6291456)
+            .max_header_list_size(262144)
+            .settings_order([
+                HeaderTableSize,
+                EnablePush,
+                InitialWindowSize,
+                MaxHeaderListSize,
+                MaxConcurrentStreams,
+                MaxFrameSize,
+                UnknownSetting8,
+                UnknownSetting9,
+            ])
+            .build();
+
+        // Chrome's SETTINGS frame.
+        let frame = settings.to_settings_frame_bytes();
+        assert_eq!(
+            &frame[..],
+            &[
+                0x00, 0x00, 0x18, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, //
+                0x00, 0x01, 0x00, 0x01, 0x00, 0x00, //
+                0x00, 0x02, 0x00, 0x00, 0x00, 0x00, //
+                0x00, 0x04, 0x00, 0x60, 0x00, 0x00, //
+                0x00, 0x06, 0x00, 0x04, 0x00, 0x00, //
+            ][..]
+        );
+
+        let parsed = Http2Settings::from_settings_frame_bytes(&frame).unwrap();
+        assert_eq!(parsed.header_table_size, Some(65536));
+        assert_eq!(parsed.enable_push, Some(false));
+        assert_eq!(parsed.initial_stream_window_size, Some(6291456));
+        assert_eq!(parsed.max_header_list_size, Some(262144));
+        assert_eq!(parsed.max_concurrent_streams, None);
+        assert_eq!(parsed.settings_order, settings.settings_order);
+        assert_eq!(parsed.to_settings_frame_bytes(), frame);
+    }
+
+    #[test]
+    fn settings_frame_bytes_rejects_invalid_frames() {
+        let frame = Http2Settings::builder()
+            .enable_push(true)
+            .build()
+            .to_settings_frame_bytes();
+        assert!(Http2Settings::from_settings_frame_bytes(&frame).is_ok());
+
+        let mut ack = frame.to_vec();
+        ack[4] = 0x1;
+        let mut truncated = frame.to_vec();
+        truncated.pop();
+        let mut unknown = frame.to_vec();
+        unknown[10] = 0x7;
+        let mut not_bool = frame.to_vec();
+        not_bool[14] = 0x2;
+        for (bytes, reason) in [
+            (&ack[..], "SETTINGS acknowledgment"),
+            (&truncated[..], "invalid frame length"),
+            (&unknown[..], "unknown setting"),
+            (&not_bool[..], "invalid boolean setting"),
+            (&frame[..4], "truncated frame header"),
+        ] {
+            assert_eq!(
+                Http2Settings::from_settings_frame_bytes(bytes).unwrap_err(),
+                Http2SettingsError::InvalidSettingsFrame(reason)
+            );
+        }
+    }
 }
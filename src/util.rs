@@ -1,4 +1,5 @@
 use crate::header::{Entry, HeaderMap, HeaderValue, OccupiedEntry};
+use crate::Url;
 
 pub fn basic_auth<U, P>(username: U, password: Option<P>) -> HeaderValue
 where
@@ -22,6 +23,33 @@ where
     header
 }
 
+/// Takes the credentials out of `url`, percent-decoded.
+pub(crate) fn extract_authority(url: &mut Url) -> Option<(String, Option<String>)> {
+    use percent_encoding::percent_decode;
+
+    if url.has_authority() {
+        let username: String = percent_decode(url.username().as_bytes())
+            .decode_utf8()
+            .ok()?
+            .into();
+        let password = url.password().and_then(|pass| {
+            percent_decode(pass.as_bytes())
+                .decode_utf8()
+                .ok()
+                .map(String::from)
+        });
+        if !username.is_empty() || password.is_some() {
+            url.set_username("")
+                .expect("has_authority means set_username shouldn't fail");
+            url.set_password(None)
+                .expect("has_authority means set_password shouldn't fail");
+            return Some((username, password));
+        }
+    }
+
+    None
+}
+
 // xor-shift
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) fn fast_random() -> u64 {
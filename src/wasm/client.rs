@@ -0,0 +1,280 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use http::header::USER_AGENT;
+use http::{HeaderMap, HeaderValue, Method};
+
+use super::{AbortGuard, Request, RequestBuilder, Response};
+use crate::error::{TimedOut, TimeoutPhase};
+use crate::IntoUrl;
+
+/// An asynchronous `Client` to make Requests with, through `fetch`.
+///
+/// The browser handles the connections, so there is no pool to share:
+/// cloning a `Client` only shares its settings.
+#[derive(Clone)]
+pub struct Client {
+    config: Arc<Config>,
+}
+
+/// A `ClientBuilder` can be used to create a `Client` with custom
+/// configuration.
+#[must_use]
+pub struct ClientBuilder {
+    config: Config,
+    error: Option<crate::Error>,
+}
+
+#[derive(Debug)]
+struct Config {
+    headers: HeaderMap,
+    timeout: Option<Duration>,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClientBuilder {
+    /// Constructs a new `ClientBuilder`.
+    ///
+    /// This is the same as `Client::builder()`.
+    pub fn new() -> ClientBuilder {
+        ClientBuilder {
+            config: Config {
+                headers: HeaderMap::new(),
+                timeout: None,
+            },
+            error: None,
+        }
+    }
+
+    /// Returns a `Client` that uses this `ClientBuilder` configuration.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if one of the settings was invalid.
+    pub fn build(self) -> crate::Result<Client> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        Ok(Client {
+            config: Arc::new(self.config),
+        })
+    }
+
+    /// Sets the `User-Agent` header to be used by this client.
+    ///
+    /// Browsers may not let the page replace their own.
+    pub fn user_agent<V>(mut self, value: V) -> ClientBuilder
+    where
+        V: TryInto<HeaderValue>,
+        V::Error: Into<http::Error>,
+    {
+        match value.try_into() {
+            Ok(value) => {
+                self.config.headers.insert(USER_AGENT, value);
+            }
+            Err(e) => {
+                self.error = Some(crate::error::builder(e.into()));
+            }
+        }
+        self
+    }
+
+    /// Sets the default headers for every request.
+    pub fn default_headers(mut self, headers: HeaderMap) -> ClientBuilder {
+        for (key, value) in headers.iter() {
+            self.config.headers.insert(key, value.clone());
+        }
+        self
+    }
+
+    /// Enables a request timeout.
+    ///
+    /// The timeout is applied from when the request is sent until the
+    /// response body has finished.
+    ///
+    /// Default is no timeout.
+    pub fn timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.config.timeout = Some(timeout);
+        self
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Client {
+    /// Constructs a new `Client`.
+    pub fn new() -> Client {
+        Client::builder().build().expect("Client::new()")
+    }
+
+    /// Creates a `ClientBuilder` to configure a `Client`.
+    ///
+    /// This is the same as `ClientBuilder::new()`.
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    /// Convenience method to make a `GET` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    /// Convenience method to make a `POST` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    /// Convenience method to make a `PUT` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn put<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    /// Convenience method to make a `PATCH` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn patch<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::PATCH, url)
+    }
+
+    /// Convenience method to make a `DELETE` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn delete<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+
+    /// Convenience method to make a `HEAD` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn head<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.request(Method::HEAD, url)
+    }
+
+    /// Start building a `Request` with the `Method` and `Url`.
+    ///
+    /// Returns a `RequestBuilder`, which will allow setting headers and
+    /// the request body before sending.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever the supplied `Url` cannot be parsed.
+    pub fn request<U: IntoUrl>(&self, method: Method, url: U) -> RequestBuilder {
+        let req = url.into_url().map(move |url| Request::new(method, url));
+        RequestBuilder::new(self.clone(), req)
+    }
+
+    /// Executes a `Request`.
+    ///
+    /// A `Request` can be built manually with `Request::new()` or obtained
+    /// from a RequestBuilder with `RequestBuilder::build()`.
+    ///
+    /// # Errors
+    ///
+    /// This method fails if there was an error while sending request.
+    pub async fn execute(&self, request: Request) -> crate::Result<Response> {
+        self.execute_request(request).await
+    }
+
+    pub(super) async fn execute_request(&self, mut req: Request) -> crate::Result<Response> {
+        let headers = req.headers_mut();
+        for name in self.config.headers.keys() {
+            if !headers.contains_key(name) {
+                for value in self.config.headers.get_all(name) {
+                    headers.append(name, value.clone());
+                }
+            }
+        }
+
+        let timeout = req.timeout().copied().or(self.config.timeout);
+        fetch(req, timeout).await
+    }
+}
+
+async fn fetch(req: Request, timeout: Option<Duration>) -> crate::Result<Response> {
+    let url = req.url().clone();
+
+    let headers = web_sys::Headers::new()
+        .map_err(crate::error::wasm)
+        .map_err(crate::error::builder)?;
+    for (name, value) in req.headers() {
+        let value = value.to_str().map_err(crate::error::builder)?;
+        headers
+            .append(name.as_str(), value)
+            .map_err(crate::error::wasm)
+            .map_err(crate::error::builder)?;
+    }
+
+    let init = web_sys::RequestInit::new();
+    init.set_method(req.method().as_str());
+    init.set_headers(headers.as_ref());
+    if req.cors_disabled() {
+        init.set_mode(web_sys::RequestMode::NoCors);
+    }
+    if let Some(body) = req.body().filter(|body| !body.is_empty()) {
+        init.set_body(&body.to_js_value());
+    }
+
+    let mut abort = AbortGuard::new()?;
+    if let Some(timeout) = timeout {
+        abort.timeout(timeout);
+    }
+    init.set_signal(Some(&abort.signal()));
+
+    let js_req = web_sys::Request::new_with_str_and_init(url.as_str(), &init)
+        .map_err(crate::error::wasm)
+        .map_err(crate::error::builder)?;
+
+    match super::promise::<web_sys::Response>(super::fetch_with_request(&js_req)).await {
+        Ok(js_res) => Response::new(js_res, url, abort),
+        Err(_) if abort.timed_out() => {
+            let timed_out = TimedOut::new(TimeoutPhase::Total, abort.elapsed());
+            Err(crate::error::request(timed_out).with_url(url))
+        }
+        Err(err) => Err(crate::error::request(err).with_url(url)),
+    }
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("default_headers", &self.config.headers)
+            .field("timeout", &self.config.timeout)
+            .finish()
+    }
+}
+
+impl fmt::Debug for ClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClientBuilder")
+            .field("config", &self.config)
+            .finish()
+    }
+}
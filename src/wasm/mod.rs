@@ -0,0 +1,97 @@
+//! The backend of `wasm32` targets, over the `fetch` API of the browser.
+//!
+//! The browser owns the connections, TLS, proxies and cookies, so none of
+//! the settings about them exist on this target, impersonation included.
+//! What is left is the core of `Client`, `RequestBuilder` and `Response`.
+
+use std::time::Duration;
+
+use js_sys::{Function, Promise};
+use wasm_bindgen::prelude::{wasm_bindgen, Closure};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{AbortController, AbortSignal};
+
+mod body;
+mod client;
+mod request;
+mod response;
+
+pub use self::body::Body;
+pub use self::client::{Client, ClientBuilder};
+pub use self::request::{Request, RequestBuilder};
+pub use self::response::Response;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = "fetch")]
+    fn fetch_with_request(input: &web_sys::Request) -> Promise;
+
+    #[wasm_bindgen(js_name = "setTimeout")]
+    fn set_timeout(handler: &Function, timeout: i32) -> JsValue;
+
+    #[wasm_bindgen(js_name = "clearTimeout")]
+    fn clear_timeout(handle: &JsValue);
+}
+
+async fn promise<T>(promise: Promise) -> Result<T, crate::error::BoxError>
+where
+    T: JsCast,
+{
+    let js_val = wasm_bindgen_futures::JsFuture::from(promise)
+        .await
+        .map_err(crate::error::wasm)?;
+
+    js_val
+        .dyn_into::<T>()
+        .map_err(|_js_val| "promise resolved to unexpected type".into())
+}
+
+/// Aborts a fetch when dropped, or once its timeout expires.
+struct AbortGuard {
+    ctrl: AbortController,
+    /// When the fetch started, in milliseconds since the epoch.
+    started: f64,
+    timeout: Option<(JsValue, Closure<dyn FnMut()>)>,
+}
+
+impl AbortGuard {
+    fn new() -> crate::Result<AbortGuard> {
+        Ok(AbortGuard {
+            ctrl: AbortController::new()
+                .map_err(crate::error::wasm)
+                .map_err(crate::error::builder)?,
+            started: js_sys::Date::now(),
+            timeout: None,
+        })
+    }
+
+    fn signal(&self) -> AbortSignal {
+        self.ctrl.signal()
+    }
+
+    fn timeout(&mut self, timeout: Duration) {
+        let ctrl = self.ctrl.clone();
+        let abort = Closure::once(move || ctrl.abort());
+        let millis = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+        let handle = set_timeout(abort.as_ref().unchecked_ref(), millis);
+        self.timeout = Some((handle, abort));
+    }
+
+    /// Whether the fetch was aborted by its timeout.
+    fn timed_out(&self) -> bool {
+        self.timeout.is_some() && self.ctrl.signal().aborted()
+    }
+
+    fn elapsed(&self) -> Duration {
+        Duration::from_secs_f64((js_sys::Date::now() - self.started).max(0.0) / 1000.0)
+    }
+}
+
+impl Drop for AbortGuard {
+    fn drop(&mut self) {
+        if let Some((handle, _abort)) = self.timeout.take() {
+            clear_timeout(&handle);
+        }
+        self.ctrl.abort();
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+
+use bytes::Bytes;
+use http::header::CONTENT_LENGTH;
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode, Version};
+use js_sys::{Array, ArrayBuffer, Uint8Array};
+#[cfg(feature = "json")]
+use serde::de::DeserializeOwned;
+use url::Url;
+use wasm_bindgen::{JsCast, JsValue};
+
+use super::AbortGuard;
+use crate::error::{TimedOut, TimeoutPhase};
+
+/// A Response to a submitted `Request`.
+pub struct Response {
+    status: StatusCode,
+    headers: HeaderMap,
+    url: Box<Url>,
+    body: web_sys::Response,
+    // Aborts the fetch, reading of the body included, when the response is
+    // dropped.
+    abort: AbortGuard,
+}
+
+impl Response {
+    pub(super) fn new(
+        res: web_sys::Response,
+        url: Url,
+        abort: AbortGuard,
+    ) -> crate::Result<Response> {
+        let status = StatusCode::from_u16(res.status())
+            .map_err(|e| crate::error::request(e).with_url(url.clone()))?;
+
+        let mut headers = HeaderMap::new();
+        if let Ok(Some(entries)) = js_sys::try_iter(res.headers().as_ref()) {
+            for entry in entries.flatten() {
+                let entry = entry.unchecked_into::<Array>();
+                let name = entry.get(0).as_string().unwrap_or_default();
+                let value = entry.get(1).as_string().unwrap_or_default();
+                if let (Ok(name), Ok(value)) = (
+                    HeaderName::from_bytes(name.as_bytes()),
+                    HeaderValue::from_str(&value),
+                ) {
+                    headers.append(name, value);
+                }
+            }
+        }
+
+        // A redirected fetch ends at another URL.
+        let url = match Url::parse(&res.url()) {
+            Ok(final_url) => final_url,
+            Err(_) => url,
+        };
+
+        Ok(Response {
+            status,
+            headers,
+            url: Box::new(url),
+            body: res,
+            abort,
+        })
+    }
+
+    /// Get the `StatusCode` of this `Response`.
+    #[inline]
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Get the HTTP `Version` of this `Response`.
+    ///
+    /// `fetch` does not tell the version it used, so this is always
+    /// `HTTP/1.1`.
+    #[inline]
+    pub fn version(&self) -> Version {
+        Version::HTTP_11
+    }
+
+    /// Get the `Headers` of this `Response`.
+    #[inline]
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Get a mutable reference to the `Headers` of this `Response`.
+    #[inline]
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
+    /// Get the content-length of this response, if known.
+    ///
+    /// Browsers only expose the `Content-Length` header of cross-origin
+    /// responses when the server allows it.
+    pub fn content_length(&self) -> Option<u64> {
+        self.headers
+            .get(CONTENT_LENGTH)?
+            .to_str()
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    /// Get the final `Url` of this `Response`.
+    #[inline]
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Get the full response text.
+    ///
+    /// The body is decoded as UTF-8 by the browser.
+    pub async fn text(self) -> crate::Result<String> {
+        let js_val = self.read(self.body.text()).await?;
+        js_val
+            .as_string()
+            .ok_or_else(|| crate::error::decode("response.text isn't string"))
+    }
+
+    /// Try to deserialize the response body as JSON.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature enabled.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever the response body is not in JSON format
+    /// or it cannot be properly deserialized to target type `T`.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub async fn json<T: DeserializeOwned>(self) -> crate::Result<T> {
+        let full = self.bytes().await?;
+        serde_json::from_slice(&full).map_err(crate::error::decode)
+    }
+
+    /// Get the full response body as `Bytes`.
+    pub async fn bytes(self) -> crate::Result<Bytes> {
+        let js_val = self.read(self.body.array_buffer()).await?;
+        let buffer = js_val
+            .dyn_into::<ArrayBuffer>()
+            .map_err(|_| crate::error::decode("response.arrayBuffer isn't an ArrayBuffer"))?;
+        Ok(Uint8Array::new(&buffer).to_vec().into())
+    }
+
+    /// Turn a response into an error if the server returned an error.
+    pub fn error_for_status(self) -> crate::Result<Self> {
+        let status = self.status();
+        if status.is_client_error() || status.is_server_error() {
+            Err(crate::error::status_code(*self.url, status))
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Turn a reference to a response into an error if the server returned an error.
+    pub fn error_for_status_ref(&self) -> crate::Result<&Self> {
+        let status = self.status();
+        if status.is_client_error() || status.is_server_error() {
+            Err(crate::error::status_code(*self.url.clone(), status))
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Waits for the body read started by `promise`.
+    async fn read(&self, promise: Result<js_sys::Promise, JsValue>) -> crate::Result<JsValue> {
+        let promise = promise
+            .map_err(crate::error::wasm)
+            .map_err(crate::error::body)?;
+        match super::promise::<JsValue>(promise).await {
+            Ok(js_val) => Ok(js_val),
+            Err(_) if self.abort.timed_out() => {
+                let timed_out = TimedOut::new(TimeoutPhase::ResponseBody, self.abort.elapsed());
+                Err(crate::error::body(timed_out))
+            }
+            Err(err) => Err(crate::error::body(err)),
+        }
+    }
+}
+
+impl fmt::Debug for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Response")
+            .field("url", &self.url().as_str())
+            .field("status", &self.status())
+            .field("headers", self.headers())
+            .finish()
+    }
+}
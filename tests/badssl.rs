@@ -35,3 +35,20 @@ async fn test_badssl_self_signed() {
 
     assert!(text.contains("<title>self-signed.badssl.com</title>"));
 }
+
+#[cfg(feature = "boring-tls")]
+#[tokio::test]
+async fn test_badssl_expired_is_tls_error() {
+    let err = rquest::Client::builder()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get("https://expired.badssl.com/")
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(err.is_connect() && err.is_tls());
+    assert!(err.tls_code().is_some());
+    assert!(!err.is_dns() && !err.is_retryable());
+}
@@ -0,0 +1,108 @@
+mod support;
+
+use std::time::Duration;
+
+use support::*;
+
+#[test]
+fn test_response_text() {
+    let server = server::http(move |_req| async { http::Response::new("Hello".into()) });
+
+    let url = format!("http://{}/text", server.addr());
+    let res = rquest::blocking::get(&url).unwrap();
+    assert_eq!(res.url().as_str(), &url);
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    assert_eq!(res.content_length(), Some(5));
+
+    let body = res.text().unwrap();
+    assert_eq!(b"Hello", body.as_bytes());
+}
+
+#[test]
+fn test_response_copy_to() {
+    let server = server::http(move |_req| async { http::Response::new("Hello".into()) });
+
+    let url = format!("http://{}/1", server.addr());
+    let mut res = rquest::blocking::get(&url).unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+
+    let mut dst = Vec::new();
+    let n = res.copy_to(&mut dst).unwrap();
+    assert_eq!(n, 5);
+    assert_eq!(dst, b"Hello");
+}
+
+#[test]
+fn test_post_reader_body() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.method(), "POST");
+        assert_eq!(req.headers()["content-length"], "11");
+
+        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"hello world");
+
+        http::Response::default()
+    });
+
+    let url = format!("http://{}/2", server.addr());
+    let body = rquest::blocking::Body::sized(&b"hello world"[..], 11);
+    let res = rquest::blocking::Client::new()
+        .post(&url)
+        .body(body)
+        .send()
+        .unwrap();
+
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+}
+
+#[test]
+fn test_read_timeout() {
+    let server = server::http(move |_req| async {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        http::Response::default()
+    });
+
+    let url = format!("http://{}/slow", server.addr());
+    let err = rquest::blocking::Client::builder()
+        .timeout(Duration::from_millis(50))
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .unwrap_err();
+
+    assert!(err.is_timeout());
+    assert_eq!(err.url().map(|u| u.as_str()), Some(url.as_str()));
+}
+
+#[test]
+fn test_error_for_status() {
+    let server = server::http(move |_req| async {
+        http::Response::builder()
+            .status(400)
+            .body(Default::default())
+            .unwrap()
+    });
+
+    let url = format!("http://{}/1", server.addr());
+    let err = rquest::blocking::get(&url)
+        .unwrap()
+        .error_for_status()
+        .unwrap_err();
+
+    assert!(err.is_status());
+    assert_eq!(err.status(), Some(rquest::StatusCode::BAD_REQUEST));
+}
+
+#[tokio::test]
+async fn test_blocking_inside_a_runtime() {
+    let server = server::http(move |_req| async { http::Response::new("Hello".into()) });
+
+    let url = format!("http://{}/text", server.addr());
+
+    // Building, using and dropping the client must not touch this runtime.
+    let client = rquest::blocking::Client::new();
+    let body = client.get(&url).send().unwrap().text().unwrap();
+    assert_eq!(body, "Hello");
+    drop(client);
+}
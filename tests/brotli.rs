@@ -85,6 +85,109 @@ async fn test_accept_encoding_header_is_not_changed_if_set() {
     assert_eq!(res.status(), rquest::StatusCode::OK);
 }
 
+#[tokio::test]
+async fn brotli_body_is_decoded_as_it_arrives() {
+    let content: String = (0..200_000).map(|i| format!("line {}\n", i)).collect();
+    let mut brotlied = Vec::new();
+    brotli_crate::CompressorReader::new(content.as_bytes(), 4096, 5, 22)
+        .read_to_end(&mut brotlied)
+        .unwrap();
+
+    // The second half of the body is only sent once a chunk was decoded.
+    let first_read = std::sync::Arc::new(tokio::sync::Notify::new());
+    let server = server::http({
+        let first_read = first_read.clone();
+        move |_req| {
+            let brotlied = brotlied.clone();
+            let first_read = first_read.clone();
+            async move {
+                let (mut tx, body) = hyper::Body::channel();
+                tokio::spawn(async move {
+                    let (first, rest) = brotlied.split_at(brotlied.len() / 2);
+                    tx.send_data(first.to_vec().into()).await.unwrap();
+                    first_read.notified().await;
+                    tx.send_data(rest.to_vec().into()).await.unwrap();
+                });
+                http::Response::builder()
+                    .header("content-encoding", "br")
+                    .body(body)
+                    .unwrap()
+            }
+        }
+    });
+
+    let client = rquest::Client::builder()
+        .brotli_buffer_size(1024)
+        .build()
+        .unwrap();
+    let mut res = client
+        .get(&format!("http://{}/brotli", server.addr()))
+        .send()
+        .await
+        .unwrap();
+
+    let mut decoded = Vec::new();
+    while let Some(chunk) = res.chunk().await.unwrap() {
+        assert!(chunk.len() <= 1024, "chunk of {} bytes", chunk.len());
+        decoded.extend_from_slice(&chunk);
+        first_read.notify_one();
+    }
+    assert_eq!(decoded, content.as_bytes());
+}
+
+// Run with `cargo test --release --test brotli -- --ignored`.
+#[tokio::test]
+#[ignore = "decompresses 500MB"]
+async fn brotli_500mb_body_to_sink() {
+    use futures_util::stream::StreamExt;
+
+    const SIZE: u64 = 500 * 1024 * 1024;
+    const BUFFER_SIZE: usize = 64 * 1024;
+
+    let server = server::http(move |_req| async move {
+        // Compressed as it is sent, so neither side holds the whole body.
+        let mut encoder = brotli_crate::CompressorReader::new(
+            std::io::repeat(b'x').take(SIZE),
+            BUFFER_SIZE,
+            1,
+            24,
+        );
+        let stream = futures_util::stream::poll_fn(move |_cx| {
+            let mut chunk = vec![0; BUFFER_SIZE];
+            let n = encoder.read(&mut chunk).unwrap();
+            chunk.truncate(n);
+            std::task::Poll::Ready((n > 0).then_some(chunk))
+        });
+        http::Response::builder()
+            .header("content-encoding", "br")
+            .body(hyper::Body::wrap_stream(
+                stream.map(Ok::<_, std::convert::Infallible>),
+            ))
+            .unwrap()
+    });
+
+    let client = rquest::Client::builder()
+        .brotli_buffer_size(BUFFER_SIZE)
+        .build()
+        .unwrap();
+    let mut res = client
+        .get(&format!("http://{}/brotli", server.addr()))
+        .send()
+        .await
+        .unwrap();
+
+    let mut sink = tokio::io::sink();
+    let mut len = 0;
+    while let Some(chunk) = res.chunk().await.unwrap() {
+        assert!(chunk.len() <= BUFFER_SIZE);
+        len += chunk.len() as u64;
+        tokio::io::AsyncWriteExt::write_all(&mut sink, &chunk)
+            .await
+            .unwrap();
+    }
+    assert_eq!(len, SIZE);
+}
+
 async fn brotli_case(response_size: usize, chunk_size: usize) {
     use futures_util::stream::StreamExt;
 
@@ -0,0 +1,142 @@
+#![cfg(not(target_arch = "wasm32"))]
+mod support;
+use support::server;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use rquest::cache::{CacheConfig, CacheStatus};
+use rquest::Client;
+
+fn cached_client() -> Client {
+    Client::builder()
+        .http_cache(CacheConfig::default())
+        .build()
+        .unwrap()
+}
+
+fn cache_status(res: &rquest::Response) -> Option<CacheStatus> {
+    res.extensions().get::<CacheStatus>().copied()
+}
+
+#[tokio::test]
+async fn fresh_response_is_served_from_cache() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |_req| {
+        let n = counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            http::Response::builder()
+                .header("cache-control", "max-age=60")
+                .body(format!("response {}", n).into())
+                .unwrap()
+        }
+    });
+
+    let client = cached_client();
+    let url = format!("http://{}/meta", server.addr());
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(cache_status(&res), Some(CacheStatus::Miss));
+    assert_eq!(res.text().await.unwrap(), "response 0");
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(cache_status(&res), Some(CacheStatus::Hit));
+    assert!(res.headers().contains_key("age"));
+    assert_eq!(res.text().await.unwrap(), "response 0");
+
+    // A request asking for a fresh response skips the stored one.
+    let res = client
+        .get(&url)
+        .header("cache-control", "no-cache")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(cache_status(&res), Some(CacheStatus::Miss));
+    assert_eq!(res.text().await.unwrap(), "response 1");
+
+    // Changing the resource invalidates it.
+    client.post(&url).send().await.unwrap();
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(cache_status(&res), Some(CacheStatus::Miss));
+    assert_eq!(hits.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn stale_response_is_revalidated() {
+    let server = server::http(move |req| async move {
+        if req
+            .headers()
+            .get("if-none-match")
+            .is_some_and(|v| v == "\"v1\"")
+        {
+            return http::Response::builder()
+                .status(304)
+                .header("etag", "\"v1\"")
+                .header("x-revalidated", "yes")
+                .body(Default::default())
+                .unwrap();
+        }
+        http::Response::builder()
+            .header("cache-control", "no-cache")
+            .header("etag", "\"v1\"")
+            .body("metadata".into())
+            .unwrap()
+    });
+
+    let client = cached_client();
+    let url = format!("http://{}/meta", server.addr());
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(cache_status(&res), Some(CacheStatus::Miss));
+    assert_eq!(res.text().await.unwrap(), "metadata");
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    assert_eq!(cache_status(&res), Some(CacheStatus::Revalidated));
+    assert_eq!(res.headers()["x-revalidated"], "yes");
+    assert_eq!(res.text().await.unwrap(), "metadata");
+}
+
+#[tokio::test]
+async fn no_store_and_vary_are_respected() {
+    let server = server::http(move |req| async move {
+        let cache_control = match req.uri().path() {
+            "/no-store" => "no-store",
+            _ => "max-age=60",
+        };
+        let lang = req
+            .headers()
+            .get("x-lang")
+            .map(|v| v.to_str().unwrap().to_owned())
+            .unwrap_or_default();
+        http::Response::builder()
+            .header("cache-control", cache_control)
+            .header("vary", "x-lang")
+            .body(lang.into())
+            .unwrap()
+    });
+
+    let client = cached_client();
+
+    let url = format!("http://{}/no-store", server.addr());
+    client.get(&url).send().await.unwrap();
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(cache_status(&res), Some(CacheStatus::Miss));
+
+    let url = format!("http://{}/vary", server.addr());
+    for (lang, status) in [
+        ("en", CacheStatus::Miss),
+        ("en", CacheStatus::Hit),
+        ("fr", CacheStatus::Miss),
+    ] {
+        let res = client
+            .get(&url)
+            .header("x-lang", lang)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(cache_status(&res), Some(status));
+        assert_eq!(res.text().await.unwrap(), lang);
+    }
+}
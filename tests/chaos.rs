@@ -0,0 +1,55 @@
+#![cfg(not(target_arch = "wasm32"))]
+mod support;
+use support::*;
+
+use std::time::{Duration, Instant};
+
+use rquest::testing::chaos::{ChaosLayer, Distribution};
+
+#[tokio::test]
+async fn chaos_latency() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let client = rquest::Client::builder()
+        .chaos(ChaosLayer::new().latency(Duration::from_millis(100), Distribution::Fixed))
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/latency", server.addr());
+    let start = Instant::now();
+    let res = client.get(&url).send().await.unwrap();
+
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    // once to connect, once for the response
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
+
+#[tokio::test]
+async fn chaos_packet_loss() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let client = rquest::Client::builder()
+        .chaos(ChaosLayer::new().packet_loss_rate(1.0))
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/lost", server.addr());
+    let err = client.get(&url).send().await.unwrap_err();
+
+    assert!(err.is_request());
+}
+
+#[tokio::test]
+async fn chaos_error_rate() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let client = rquest::Client::builder()
+        .chaos(ChaosLayer::new().error_rate(1.0))
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/error", server.addr());
+    let err = client.get(&url).send().await.unwrap_err();
+
+    assert!(err.is_request());
+}
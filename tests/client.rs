@@ -346,3 +346,1794 @@ async fn test_tls_info() {
     let tls_info = resp.extensions().get::<rquest::tls::TlsInfo>();
     assert!(tls_info.is_none());
 }
+
+#[cfg(feature = "boring-tls")]
+#[tokio::test]
+async fn test_protocol_info() {
+    let resp = rquest::Client::builder()
+        .protocol_info(true)
+        .http1_only()
+        .build()
+        .expect("client builder")
+        .get("https://google.com")
+        .send()
+        .await
+        .expect("response");
+    let protocol_info = resp
+        .extensions()
+        .get::<rquest::tls::ProtocolInfo>()
+        .expect("protocol info");
+    assert_eq!(protocol_info.alpn_offered(), ["http/1.1"]);
+    assert_eq!(protocol_info.alpn_selected(), Some("http/1.1"));
+    assert!(!protocol_info.is_h2() && !protocol_info.via_proxy_tunnel());
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn logging_replays_response_body() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["authorization"], "Bearer secret");
+        http::Response::new("Hello".into())
+    });
+
+    let logging = rquest::middleware::LoggingMiddleware::builder()
+        .log_request_body(true)
+        .log_response_body(true)
+        .build();
+    let client = Client::builder().logging(logging).build().unwrap();
+
+    let url = format!("http://{}/logged", server.addr());
+    let res = client
+        .post(&url)
+        .bearer_auth("secret")
+        .body("ping")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.url().as_str(), &url);
+    assert_eq!(res.text().await.unwrap(), "Hello");
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn request_span_is_current_in_hooks() {
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata};
+    use tracing_core::span::Current;
+
+    #[derive(Clone, Default)]
+    struct Spans {
+        // The metadata and fields of each span, by id.
+        spans: Arc<Mutex<Vec<(&'static Metadata<'static>, Vec<(String, String)>)>>>,
+        entered: Arc<Mutex<Vec<Id>>>,
+    }
+
+    impl Spans {
+        fn fields(&self, name: &str) -> Vec<(String, String)> {
+            let spans = self.spans.lock().unwrap();
+            let (_, fields) = spans.iter().find(|(m, _)| m.name() == name).unwrap();
+            fields.clone()
+        }
+    }
+
+    struct Fields<'a>(&'a mut Vec<(String, String)>);
+
+    impl Visit for Fields<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0
+                .push((field.name().to_owned(), format!("{:?}", value)));
+        }
+    }
+
+    impl tracing::Subscriber for Spans {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+            let mut spans = self.spans.lock().unwrap();
+            let mut fields = Vec::new();
+            attrs.record(&mut Fields(&mut fields));
+            spans.push((attrs.metadata(), fields));
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, id: &Id, values: &Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            values.record(&mut Fields(&mut spans[id.into_u64() as usize - 1].1));
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, id: &Id) {
+            self.entered.lock().unwrap().push(id.clone());
+        }
+
+        fn exit(&self, id: &Id) {
+            let mut entered = self.entered.lock().unwrap();
+            if let Some(i) = entered.iter().rposition(|entered| entered == id) {
+                entered.remove(i);
+            }
+        }
+
+        fn current_span(&self) -> Current {
+            let entered = self.entered.lock().unwrap();
+            match entered.last() {
+                Some(id) => {
+                    let metadata = self.spans.lock().unwrap()[id.into_u64() as usize - 1].0;
+                    Current::new(id.clone(), metadata)
+                }
+                None => Current::none(),
+            }
+        }
+    }
+
+    let server = server::http(move |_req| async move { http::Response::new("Hello".into()) });
+
+    let spans = Spans::default();
+    let _guard = tracing::subscriber::set_default(spans.clone());
+
+    let client = Client::builder()
+        .trace_path(|url| match url.path().strip_prefix("/users/") {
+            Some(_) => "/users/{id}".to_owned(),
+            None => url.path().to_owned(),
+        })
+        .with_request_hook(|_req| match tracing::Span::current().metadata() {
+            Some(span) if span.name() == "rquest.request" => Ok(()),
+            _ => Err("not in the request span"),
+        })
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/users/42", server.addr());
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "Hello");
+
+    let fields = spans.fields("rquest.request");
+    for (name, value) in [
+        ("http.method", "GET"),
+        ("http.host", "\"127.0.0.1\""),
+        ("http.path", "/users/{id}"),
+        ("http.status", "200"),
+        ("http.response_bytes", "5"),
+    ] {
+        assert!(
+            fields.iter().any(|(n, v)| n == name && v == value),
+            "{name} = {value} not in {fields:?}"
+        );
+    }
+    assert!(!fields.iter().any(|(n, _)| n == "error.kind"));
+    spans.fields("rquest.connect");
+}
+
+#[tokio::test]
+async fn layers_wrap_redirects_in_order() {
+    use std::sync::{Arc, Mutex};
+
+    let server = server::http(move |req| async move {
+        if req.uri() == "/redirect" {
+            return http::Response::builder()
+                .status(302)
+                .header("location", "/dst")
+                .body(Default::default())
+                .unwrap();
+        }
+
+        assert_eq!(req.headers()["x-layer"], "outer, inner");
+        http::Response::default()
+    });
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let record = |name: &'static str| {
+        let seen = seen.clone();
+        tower::util::MapRequestLayer::new(move |mut req: rquest::Request| {
+            seen.lock().unwrap().push(req.url().path().to_owned());
+            let layers = match req.headers().get("x-layer") {
+                Some(v) => format!("{}, {}", v.to_str().unwrap(), name),
+                None => name.to_owned(),
+            };
+            req.headers_mut().insert("x-layer", layers.parse().unwrap());
+            req
+        })
+    };
+
+    let client = Client::builder()
+        .redirect(rquest::redirect::Policy::default())
+        .layer(record("outer"))
+        .layer(record("inner"))
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/redirect", server.addr());
+    let res = client.get(&url).send().await.unwrap();
+
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    assert_eq!(res.url().path(), "/dst");
+    // each layer saw the request once, before the redirect
+    assert_eq!(*seen.lock().unwrap(), ["/redirect", "/redirect"]);
+}
+
+#[tokio::test]
+async fn deduplicate_concurrent_gets() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |_req| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            http::Response::new("shared".into())
+        }
+    });
+
+    let client = Client::builder()
+        .deduplicate_requests(true)
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/dedup", server.addr());
+    let get = || async { client.get(&url).send().await.unwrap().text().await.unwrap() };
+    let (a, b, c) = tokio::join!(get(), get(), get());
+
+    assert_eq!(
+        (a.as_str(), b.as_str(), c.as_str()),
+        ("shared", "shared", "shared")
+    );
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+    // a POST is always sent
+    let post = || async { client.post(&url).send().await.unwrap() };
+    tokio::join!(post(), post());
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn hooks_run_on_every_redirect_hop() {
+    use std::sync::{Arc, Mutex};
+
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["x-signed"], req.uri().path());
+        assert_eq!(req.headers()["x-async"], "yes");
+
+        if req.uri() == "/redirect" {
+            return http::Response::builder()
+                .status(302)
+                .header("location", "/dst")
+                .body(Default::default())
+                .unwrap();
+        }
+        http::Response::default()
+    });
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let record = seen.clone();
+    let client = Client::builder()
+        .redirect(rquest::redirect::Policy::default())
+        .with_request_hook(|req| {
+            let path = req.url().path().parse()?;
+            req.headers_mut().insert("x-signed", path);
+            Ok::<_, rquest::header::InvalidHeaderValue>(())
+        })
+        .with_async_request_hook(|mut req| async move {
+            tokio::task::yield_now().await;
+            req.headers_mut()
+                .insert("x-async", rquest::header::HeaderValue::from_static("yes"));
+            Ok::<_, rquest::Error>(req)
+        })
+        .with_response_hook(move |req, res| {
+            record
+                .lock()
+                .unwrap()
+                .push((req.url().path().to_owned(), res.status().as_u16()));
+        })
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/redirect", server.addr());
+    let res = client.get(&url).send().await.unwrap();
+
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    assert_eq!(
+        *seen.lock().unwrap(),
+        [("/redirect".to_owned(), 302), ("/dst".to_owned(), 200)]
+    );
+}
+
+#[tokio::test]
+async fn request_hook_error_fails_request() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let client = Client::builder()
+        .with_request_hook(|_req| Err("unsigned"))
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/unsigned", server.addr());
+    let err = client.get(&url).send().await.unwrap_err();
+
+    assert!(err.is_request());
+    assert!(err.to_string().contains("unsigned"));
+}
+
+#[tokio::test]
+async fn metrics_observer_is_called_once_per_request() {
+    use rquest::metrics::{ErrorKind, MetricsObserver, RequestMetrics};
+    use std::sync::{Arc, Mutex};
+
+    struct Record(Mutex<Vec<RequestMetrics>>);
+
+    impl MetricsObserver for Record {
+        fn on_request_complete(&self, metrics: RequestMetrics) {
+            self.0.lock().unwrap().push(metrics);
+        }
+    }
+
+    let server = server::http(move |_req| async { http::Response::new("metrics".into()) });
+
+    let record = Arc::new(Record(Mutex::new(Vec::new())));
+    let client = Client::builder()
+        .metrics_observer(record.clone())
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/metrics", server.addr());
+    for _ in 0..2 {
+        let res = client.get(&url).send().await.unwrap();
+        let at_headers = res.extensions().get::<RequestMetrics>().unwrap();
+        assert_eq!(at_headers.body_read(), None);
+        assert_eq!(res.text().await.unwrap(), "metrics");
+    }
+
+    // a dropped response completes the request too
+    drop(client.get(&url).send().await.unwrap());
+
+    // bind and drop, so the connect is refused
+    let refused = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    };
+    client
+        .get(format!("http://{}/refused", refused))
+        .send()
+        .await
+        .unwrap_err();
+
+    let seen = record.0.lock().unwrap();
+    assert_eq!(seen.len(), 4);
+
+    assert_eq!(seen[0].connection_reused(), Some(false));
+    assert!(seen[0].connect().is_some());
+    assert_eq!(seen[0].version(), Some(http::Version::HTTP_11));
+    assert!(seen[0].body_read().is_some());
+    assert!(seen[0].total() >= seen[0].ttfb().unwrap());
+    assert_eq!(seen[0].error(), None);
+
+    assert_eq!(seen[1].connection_reused(), Some(true));
+    assert_eq!(seen[1].connect(), None);
+    assert_eq!(seen[1].error(), None);
+
+    assert_eq!(seen[2].error(), Some(ErrorKind::Canceled));
+
+    assert_eq!(seen[3].error(), Some(ErrorKind::Connect));
+    assert_eq!(seen[3].ttfb(), None);
+}
+
+#[tokio::test]
+async fn upload_progress_reaches_body_length() {
+    use std::sync::{Arc, Mutex};
+
+    let server = server::http(move |req| async move {
+        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+        assert_eq!(body.len(), 64 * 1024);
+        http::Response::default()
+    });
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let record = calls.clone();
+    let url = format!("http://{}/upload", server.addr());
+    let res = Client::new()
+        .post(&url)
+        .body(vec![b'x'; 64 * 1024])
+        .on_upload_progress(move |sent, total| record.lock().unwrap().push((sent, total)))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    let calls = calls.lock().unwrap();
+    assert_eq!(calls.last(), Some(&(64 * 1024, Some(64 * 1024))));
+    assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+}
+
+#[tokio::test]
+async fn expect_continue_skips_body_on_early_response() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["expect"], "100-continue");
+        http::Response::builder()
+            .status(401)
+            .body(hyper::Body::empty())
+            .unwrap()
+    });
+
+    let uploaded = Arc::new(AtomicBool::new(false));
+    let record = uploaded.clone();
+    let res = Client::builder()
+        .expect_continue_threshold(1024)
+        .expect_continue_timeout(std::time::Duration::from_secs(30))
+        .build()
+        .unwrap()
+        .put(format!("http://{}/upload", server.addr()))
+        .body(vec![b'x'; 64 * 1024])
+        .on_upload_progress(move |_, _| record.store(true, Ordering::SeqCst))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), rquest::StatusCode::UNAUTHORIZED);
+    assert!(!uploaded.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn expect_continue_sends_body_after_timeout() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["expect"], "100-continue");
+        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+        http::Response::new(body.into())
+    });
+
+    let client = Client::builder()
+        .expect_continue_timeout(std::time::Duration::from_millis(50))
+        .build()
+        .unwrap();
+    let url = format!("http://{}/upload", server.addr());
+    let res = client
+        .put(&url)
+        .body("small")
+        .expect_continue(true)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "small");
+
+    // Below the threshold, or without one, the header is not sent.
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers().get("expect"), None);
+        http::Response::default()
+    });
+    let res = client
+        .put(format!("http://{}/upload", server.addr()))
+        .body("small")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn upload_progress_of_stream_has_no_total() {
+    use std::sync::{Arc, Mutex};
+
+    let server = server::http(move |req| async move {
+        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+        assert_eq!(body, "streamed body");
+        http::Response::default()
+    });
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let record = calls.clone();
+    let chunks: Vec<Result<_, std::io::Error>> = vec![Ok("streamed"), Ok(" body")];
+    let url = format!("http://{}/upload", server.addr());
+    Client::new()
+        .post(&url)
+        .body(rquest::Body::wrap_stream(futures_util::stream::iter(
+            chunks,
+        )))
+        .on_upload_progress(move |sent, total| record.lock().unwrap().push((sent, total)))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(*calls.lock().unwrap(), [(8, None), (13, None)]);
+}
+
+#[tokio::test]
+async fn download_progress_reaches_content_length() {
+    use std::sync::{Arc, Mutex};
+
+    let server =
+        server::http(move |_req| async move { http::Response::new(vec![b'x'; 64 * 1024].into()) });
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let record = calls.clone();
+    let url = format!("http://{}/download", server.addr());
+    let body = Client::new()
+        .get(&url)
+        .on_download_progress(move |read, total| record.lock().unwrap().push((read, total)))
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+
+    assert_eq!(body.len(), 64 * 1024);
+    let calls = calls.lock().unwrap();
+    assert_eq!(calls.last(), Some(&(64 * 1024, Some(64 * 1024))));
+    assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+}
+
+#[tokio::test]
+async fn request_download_progress_overrides_client() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let server = server::http(move |_req| async move { http::Response::new("hello".into()) });
+
+    let client_calls = Arc::new(AtomicUsize::new(0));
+    let request_calls = Arc::new(AtomicUsize::new(0));
+    let (client_count, request_count) = (client_calls.clone(), request_calls.clone());
+    let client = Client::builder()
+        .on_download_progress(move |_, _| {
+            client_count.fetch_add(1, Ordering::SeqCst);
+        })
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/download", server.addr());
+    let text = client
+        .get(&url)
+        .on_download_progress(move |read, total| {
+            assert_eq!((read, total), (5, Some(5)));
+            request_count.fetch_add(1, Ordering::SeqCst);
+        })
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    assert_eq!(text, "hello");
+    assert_eq!(request_calls.load(Ordering::SeqCst), 1);
+    assert_eq!(client_calls.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn max_response_body_size_rejects_content_length() {
+    let server =
+        server::http(move |_req| async move { http::Response::new(vec![b'x'; 1024].into()) });
+
+    let client = Client::builder()
+        .max_response_body_size(100)
+        .build()
+        .unwrap();
+    let url = format!("http://{}/large", server.addr());
+
+    let mut res = client.get(&url).send().await.unwrap();
+    let err = res.chunk().await.unwrap_err();
+    assert!(err.is_body() && err.is_body_too_large());
+    assert!(
+        err.to_string()
+            .contains("limit of 100 bytes (0 bytes read)"),
+        "{}",
+        err
+    );
+    assert!(res.chunk().await.unwrap().is_none());
+
+    // The request limit overrides the client one.
+    let body = client
+        .get(&url)
+        .max_response_body_size(1024)
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    assert_eq!(body.len(), 1024);
+}
+
+#[tokio::test]
+async fn max_response_body_size_aborts_stream() {
+    let server = server::http(move |_req| async move {
+        let chunks = futures_util::stream::iter(vec![vec![b'x'; 64]; 4]);
+        http::Response::new(hyper::Body::wrap_stream(
+            chunks.map(Ok::<_, std::convert::Infallible>),
+        ))
+    });
+
+    let url = format!("http://{}/chunked", server.addr());
+    let err = Client::new()
+        .get(&url)
+        .max_response_body_size(100)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap_err();
+    assert!(err.is_body_too_large());
+    assert!(err.to_string().contains("limit of 100 bytes"), "{}", err);
+}
+
+#[tokio::test]
+async fn max_response_headers_fails_request() {
+    let server = server::http(move |_req| async move {
+        let mut res = http::Response::builder();
+        for i in 0..20 {
+            res = res.header(format!("x-header-{}", i), "value");
+        }
+        res.body(hyper::Body::empty()).unwrap()
+    });
+
+    let url = format!("http://{}/headers", server.addr());
+    let err = Client::builder()
+        .max_response_headers(10)
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_headers_too_large(), "{:?}", err);
+    assert!(err.to_string().contains("over the limit of 10"), "{}", err);
+
+    let res = Client::new().get(&url).send().await.unwrap();
+    assert_eq!(res.headers()["x-header-19"], "value");
+}
+
+#[tokio::test]
+async fn max_response_headers_allows_exactly_the_limit() {
+    let server = server::http(move |_req| async move {
+        let mut res = http::Response::builder();
+        for i in 0..5 {
+            res = res.header(format!("x-header-{}", i), "value");
+        }
+        res.body(hyper::Body::empty()).unwrap()
+    });
+
+    let url = format!("http://{}/headers", server.addr());
+    let count = Client::new()
+        .get(&url)
+        .send()
+        .await
+        .unwrap()
+        .headers()
+        .len();
+
+    let client = Client::builder()
+        .max_response_headers(count)
+        .build()
+        .unwrap();
+    client.get(&url).send().await.unwrap();
+
+    let client = Client::builder()
+        .max_response_headers(count - 1)
+        .build()
+        .unwrap();
+    let err = client.get(&url).send().await.unwrap_err();
+    assert!(err.is_headers_too_large(), "{:?}", err);
+}
+
+#[tokio::test]
+async fn http1_response_heads_over_the_size_are_not_buffered() {
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .header("set-cookie", "x".repeat(64 * 1024))
+            .body(hyper::Body::empty())
+            .unwrap()
+    });
+
+    let url = format!("http://{}/headers", server.addr());
+    let err = Client::builder()
+        .max_response_header_size(16 * 1024)
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap_err();
+    // hyper stops reading the head at the limit, before the headers are
+    // counted.
+    assert!(err.is_headers_too_large(), "{:?}", err);
+    assert!(!err.to_string().contains("over the limit"), "{}", err);
+}
+
+#[tokio::test]
+async fn http1_responses_are_limited_to_100_headers() {
+    let server = server::http(move |_req| async move {
+        let mut res = http::Response::builder();
+        for i in 0..150 {
+            res = res.header(format!("x-header-{}", i), "value");
+        }
+        res.body(hyper::Body::empty()).unwrap()
+    });
+
+    let url = format!("http://{}/headers", server.addr());
+    let err = Client::builder()
+        .max_response_headers(500)
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_headers_too_large(), "{:?}", err);
+}
+
+#[tokio::test]
+async fn max_response_header_size_fails_request() {
+    let server = server::http(move |_req| async move {
+        http::Response::builder()
+            .header("set-cookie", "x".repeat(2048))
+            .body(hyper::Body::empty())
+            .unwrap()
+    });
+
+    let url = format!("http://{}/headers", server.addr());
+    let err = Client::builder()
+        .max_response_header_size(1024)
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_headers_too_large(), "{:?}", err);
+    assert!(
+        err.to_string().contains("over the limit of 1024"),
+        "{}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn base_url_and_default_query() {
+    let server = server::http(move |req| async move {
+        let uri = req.uri().to_string();
+        http::Response::new(uri.into())
+    });
+
+    let base = format!("http://{}/api/v2/", server.addr());
+    let client = Client::builder()
+        .base_url(base.parse().unwrap())
+        .default_query(&[("key", "secret"), ("lang", "en")])
+        .build()
+        .unwrap();
+
+    let text = |res: rquest::Response| async move { res.text().await.unwrap() };
+
+    let res = client.get("/users").send().await.unwrap();
+    assert_eq!(text(res).await, "/api/v2/users?key=secret&lang=en");
+
+    let res = client
+        .get("users/a%2Fb")
+        .query(&[("lang", "fr")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(text(res).await, "/api/v2/users/a%2Fb?lang=fr&key=secret");
+
+    let absolute = format!("http://{}/other", server.addr());
+    let res = client.get(&absolute).send().await.unwrap();
+    assert_eq!(text(res).await, "/other?key=secret&lang=en");
+}
+
+#[tokio::test]
+async fn sign_with_sees_final_headers() {
+    use rquest::sign::{Payload, SignableRequest};
+    use std::error::Error;
+
+    fn sign(req: &mut SignableRequest<'_>) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let host = req.headers()["host"] == req.headers()["x-expected-host"];
+        let len = req.headers()["content-length"].to_str()?.to_owned();
+        let body = match req.payload() {
+            Payload::Bytes(bytes) => String::from_utf8(bytes.to_vec())?,
+            _ => return Err("streaming body".into()),
+        };
+        let value = format!("host={} len={} body={}", host, len, body);
+        req.headers_mut().insert("x-signature", value.parse()?);
+        Ok(())
+    }
+
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["x-signature"], "host=true len=5 body=hello");
+        http::Response::default()
+    });
+
+    let mut headers = rquest::header::HeaderMap::new();
+    headers.insert(
+        "x-expected-host",
+        server.addr().to_string().parse().unwrap(),
+    );
+    let client = Client::builder().default_headers(headers).build().unwrap();
+
+    let res = client
+        .post(format!("http://{}/sign", server.addr()))
+        .body("hello")
+        .sign_with(sign)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+}
+
+#[cfg(feature = "boring-tls")]
+#[tokio::test]
+async fn digest_auth_answers_challenge_and_caches_it() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let challenges = Arc::new(AtomicUsize::new(0));
+    let counter = challenges.clone();
+    let server = server::http(move |req| {
+        let counter = counter.clone();
+        async move {
+            match req.headers().get("authorization") {
+                Some(auth) => {
+                    let auth = auth.to_str().unwrap();
+                    assert!(auth.starts_with("Digest username=\"user\""), "{}", auth);
+                    assert!(auth.contains("nonce=\"abc\""), "{}", auth);
+                    assert!(auth.contains("algorithm=SHA-256"), "{}", auth);
+                    assert!(auth.contains("qop=auth"), "{}", auth);
+                    http::Response::new(req.uri().path().to_owned().into())
+                }
+                None => {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    http::Response::builder()
+                        .status(401)
+                        .header(
+                            "www-authenticate",
+                            r#"Digest realm="cam", qop="auth", algorithm=MD5, nonce="abc""#,
+                        )
+                        .header(
+                            "www-authenticate",
+                            r#"Digest realm="cam", qop="auth", algorithm=SHA-256, nonce="abc""#,
+                        )
+                        .body(hyper::Body::empty())
+                        .unwrap()
+                }
+            }
+        }
+    });
+
+    let client = Client::new();
+    for path in ["/first", "/second"] {
+        let url = format!("http://{}{}", server.addr(), path);
+        let res = client
+            .post(&url)
+            .body("snapshot")
+            .digest_auth("user", "pass")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), rquest::StatusCode::OK);
+        assert_eq!(res.text().await.unwrap(), path);
+    }
+
+    // The second request was answered up front.
+    assert_eq!(challenges.load(Ordering::SeqCst), 1);
+
+    // Requests without `digest_auth` do not answer the challenge.
+    let res = client
+        .get(format!("http://{}/other", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn bearer_auth_provider_refreshes_rejected_token() {
+    use rquest::auth::{Token, TokenFuture, TokenProvider};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct Provider {
+        tokens: AtomicUsize,
+        refreshes: AtomicUsize,
+    }
+
+    impl TokenProvider for Provider {
+        fn token(&self) -> TokenFuture<'_> {
+            Box::pin(async {
+                self.tokens.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                Ok(Token::new("stale"))
+            })
+        }
+
+        fn refresh(&self) -> TokenFuture<'_> {
+            Box::pin(async {
+                self.refreshes.fetch_add(1, Ordering::SeqCst);
+                Ok(Token::new("fresh"))
+            })
+        }
+    }
+
+    let server = server::http(move |req| async move {
+        let status = match req.headers().get("authorization") {
+            Some(auth) if auth == "Bearer fresh" => 200,
+            _ => 401,
+        };
+        http::Response::builder()
+            .status(status)
+            .body(hyper::Body::empty())
+            .unwrap()
+    });
+
+    let provider = Arc::new(Provider::default());
+    let client = Client::builder()
+        .bearer_auth_provider(provider.clone())
+        .build()
+        .unwrap();
+    let url = format!("http://{}/", server.addr());
+
+    let (a, b) = tokio::join!(client.get(&url).send(), client.get(&url).send());
+    assert_eq!(a.unwrap().status(), rquest::StatusCode::OK);
+    assert_eq!(b.unwrap().status(), rquest::StatusCode::OK);
+
+    // Both requests waited for one token, and one refresh.
+    assert_eq!(provider.tokens.load(Ordering::SeqCst), 1);
+    assert_eq!(provider.refreshes.load(Ordering::SeqCst), 1);
+
+    // The refreshed token is used up front.
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    assert_eq!(provider.refreshes.load(Ordering::SeqCst), 1);
+
+    // A request with its own `Authorization` header is left alone.
+    let res = client.get(&url).bearer_auth("mine").send().await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn validator_store_sends_conditional_headers() {
+    let server = server::http(move |req| async move {
+        if req
+            .headers()
+            .get("if-none-match")
+            .is_some_and(|v| v == "\"v1\"")
+        {
+            assert_eq!(
+                req.headers()["if-modified-since"],
+                "Wed, 21 Oct 2015 07:28:00 GMT"
+            );
+            return http::Response::builder()
+                .status(304)
+                .header("etag", "\"v1\"")
+                .body(Default::default())
+                .unwrap();
+        }
+        http::Response::builder()
+            .header("etag", "\"v1\"")
+            .header("last-modified", "Wed, 21 Oct 2015 07:28:00 GMT")
+            .header("content-type", "text/plain")
+            .body("feed".into())
+            .unwrap()
+    });
+
+    let store = rquest::ValidatorStore::new();
+    let client = Client::new();
+    let url = format!("http://{}/feed", server.addr());
+
+    let res = client
+        .get(&url)
+        .if_none_match_from(&store)
+        .send()
+        .await
+        .unwrap();
+    assert!(!res.not_modified());
+    assert_eq!(res.etag(), Some("\"v1\""));
+    assert_eq!(
+        res.last_modified(),
+        Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1445412480))
+    );
+    assert_eq!(store.len(), 1);
+
+    let res = client
+        .get(&url)
+        .if_none_match_from(&store)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.not_modified());
+    assert_eq!(res.headers()["content-type"], "text/plain");
+    assert_eq!(res.etag(), Some("\"v1\""));
+}
+
+#[tokio::test]
+async fn raw_path_and_query_is_sent_verbatim() {
+    const RAW: &str = "/a%2Fb/%2e%2E/c+d%20e/%C3%A9?q=a+b%2f%20&sig=%7e";
+
+    let server = server::http(move |req| async move {
+        assert_eq!(req.uri().path_and_query().unwrap().as_str(), RAW);
+        http::Response::default()
+    });
+
+    let url = format!("http://{}/ignored?x=1", server.addr());
+    let res = Client::new()
+        .get(&url)
+        .raw_path_and_query(RAW)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    assert_eq!(res.url().as_str(), url);
+
+    let err = Client::new()
+        .get(&url)
+        .raw_path_and_query("no-slash")
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_builder());
+}
+
+#[tokio::test]
+async fn connect_to_keeps_url_host() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["host"], "split-horizon.test:8080");
+        assert_eq!(req.uri(), "/health");
+        http::Response::default()
+    });
+
+    let client = Client::builder().no_proxy().build().unwrap();
+    let res = client
+        .get("http://split-horizon.test:8080/health")
+        .connect_to(server.addr())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    assert_eq!(res.url().as_str(), "http://split-horizon.test:8080/health");
+    assert_eq!(res.remote_addr(), Some(server.addr()));
+}
+
+#[tokio::test]
+async fn host_override_is_sent_first() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["host"], "vhost.test");
+        assert_eq!(req.headers().get_all("host").iter().count(), 1);
+        assert_eq!(req.headers().keys().next().unwrap(), "host");
+        http::Response::default()
+    });
+
+    let client = Client::builder()
+        .no_proxy()
+        .default_headers(
+            [(http::header::USER_AGENT, "test".parse().unwrap())]
+                .into_iter()
+                .collect(),
+        )
+        .build()
+        .unwrap();
+    let res = client
+        .get(format!("http://{}/", server.addr()))
+        .header("x-first", "1")
+        .header("host", "ignored.test")
+        .host("vhost.test")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+
+    let err = client
+        .get(format!("http://{}/", server.addr()))
+        .host("bad\nhost")
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder());
+}
+
+#[tokio::test]
+async fn priority_overrides_client_header() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers().get_all("priority").iter().count(), 1);
+        assert_eq!(req.headers()["priority"], "u=4, i");
+        http::Response::default()
+    });
+
+    let client = Client::builder()
+        .no_proxy()
+        .default_headers(
+            [(
+                http::HeaderName::from_static("priority"),
+                "u=0, i".parse().unwrap(),
+            )]
+            .into_iter()
+            .collect(),
+        )
+        .build()
+        .unwrap();
+    let url = format!("http://{}/", server.addr());
+    let res = client.get(&url).priority(4, true).send().await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+
+    let err = client.get(&url).priority(8, false).build().unwrap_err();
+    assert!(err.is_builder());
+}
+
+#[tokio::test]
+async fn request_target_forms() {
+    use rquest::RequestTarget;
+
+    let server = server::http(move |req| async move {
+        let target = req.uri().to_string();
+        http::Response::new(target.into())
+    });
+    let client = Client::builder().no_proxy().build().unwrap();
+    let url = format!("http://{}/path?q=1", server.addr());
+
+    let send = |method, target| client.request(method, &url).request_target(target).send();
+    let res = send(rquest::Method::GET, RequestTarget::Absolute)
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), url);
+    let res = send(rquest::Method::GET, RequestTarget::Origin)
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "/path?q=1");
+    let res = send(rquest::Method::OPTIONS, RequestTarget::Asterisk)
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "*");
+
+    let err = send(rquest::Method::GET, RequestTarget::Authority)
+        .await
+        .unwrap_err();
+    assert!(err.is_builder());
+}
+
+#[tokio::test]
+async fn http_10_request_keeps_alive() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.version(), http::Version::HTTP_10);
+        assert_eq!(req.headers()["connection"], "keep-alive");
+        http::Response::default()
+    });
+
+    let client = Client::builder().no_proxy().build().unwrap();
+    let res = client
+        .get(format!("http://{}/", server.addr()))
+        .version(http::Version::HTTP_10)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn refused_connect_is_classified() {
+    use std::error::Error as _;
+
+    let addr = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    };
+
+    let err = Client::builder()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get(format!("http://{}/", addr))
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(err.is_connect());
+    assert!(!err.is_dns() && !err.is_tls() && !err.is_proxy());
+    assert!(err.is_retryable());
+
+    let mut source = err.source();
+    let mut refused = false;
+    while let Some(cause) = source {
+        if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+            refused |= io.kind() == std::io::ErrorKind::ConnectionRefused;
+        }
+        source = cause.source();
+    }
+    assert!(refused, "{:?}", err);
+}
+
+#[tokio::test]
+async fn dns_failure_is_classified() {
+    use rquest::dns::{Name, Resolve, Resolving};
+
+    struct NoSuchHost;
+
+    impl Resolve for NoSuchHost {
+        fn resolve(&self, name: Name) -> Resolving {
+            let err = format!("no such host: {}", name.as_str());
+            Box::pin(async move { Err(err.into()) })
+        }
+    }
+
+    let err = Client::builder()
+        .no_proxy()
+        .dns_resolver(std::sync::Arc::new(NoSuchHost))
+        .build()
+        .unwrap()
+        .get("http://missing.test/")
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(err.is_connect() && err.is_dns());
+    assert!(!err.is_tls() && !err.is_timeout());
+    assert!(!err.is_retryable());
+}
+
+#[tokio::test]
+async fn from_env_applies_settings() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["user-agent"], "from-env");
+        http::Response::default()
+    });
+
+    // A prefix of its own, as the environment is shared by all tests.
+    std::env::set_var("RQUEST_TEST_ENV_USER_AGENT", "from-env");
+    std::env::set_var("RQUEST_TEST_ENV_TIMEOUT_MS", "5000");
+    let client = Client::builder()
+        .no_proxy()
+        .from_env("RQUEST_TEST_ENV")
+        .build()
+        .unwrap();
+    let url = format!("http://{}/env", server.addr());
+    client.get(&url).send().await.unwrap();
+
+    std::env::set_var("RQUEST_TEST_ENV_TIMEOUT_MS", "soon");
+    let err = Client::builder()
+        .from_env("RQUEST_TEST_ENV")
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder());
+    assert!(
+        err.to_string().contains("RQUEST_TEST_ENV_TIMEOUT_MS"),
+        "{err}"
+    );
+}
+
+#[tokio::test]
+async fn hedged_request_uses_first_response() {
+    let hits = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |_req| {
+        let hit = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        async move {
+            match hit {
+                0 => tokio::time::sleep(std::time::Duration::from_secs(5)).await,
+                2 => tokio::time::sleep(std::time::Duration::from_millis(200)).await,
+                _ => {}
+            }
+            http::Response::new(format!("hit {hit}").into())
+        }
+    });
+
+    let client = Client::builder()
+        .no_proxy()
+        .hedge_requests(std::time::Duration::from_millis(50))
+        .build()
+        .unwrap();
+    let url = format!("http://{}/slow", server.addr());
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(
+        res.extensions().get::<rquest::HedgedResponse>(),
+        Some(&rquest::HedgedResponse::Backup)
+    );
+    assert_eq!(res.text().await.unwrap(), "hit 1");
+
+    // POST requests are not hedged unless opted in.
+    let res = client.post(&url).send().await.unwrap();
+    assert!(res.extensions().get::<rquest::HedgedResponse>().is_none());
+    assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn strict_content_length_fails_truncated_bodies() {
+    use std::io::{Read, Write};
+
+    // Answers each connection with `head`, a 5 byte body, and closes it.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for head in [
+            "Content-Length: 10\r\n\r\nhello",
+            "Transfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n",
+        ] {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 1024]).unwrap();
+            let res = format!("HTTP/1.1 200 OK\r\nConnection: close\r\n{head}");
+            stream.write_all(res.as_bytes()).unwrap();
+        }
+    });
+
+    let client = Client::builder()
+        .no_proxy()
+        .strict_content_length(true)
+        .build()
+        .unwrap();
+    let url = format!("http://{}/", addr);
+
+    let err = client
+        .get(&url)
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap_err();
+    assert!(err.is_body());
+    assert!(err.is_incomplete_body(), "{:?}", err);
+    assert_eq!(err.content_length_mismatch(), Some((10, 5)));
+
+    let err = client
+        .get(&url)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap_err();
+    assert!(err.is_incomplete_body(), "{:?}", err);
+    assert_eq!(err.content_length_mismatch(), None);
+}
+
+#[tokio::test]
+async fn rate_limit_fails_past_max_wait() {
+    let server = server::http(move |_req| async { http::Response::new("Hello".into()) });
+
+    let client = Client::builder()
+        .no_proxy()
+        .rate_limit(
+            rquest::RateLimit::per_host(1, std::time::Duration::from_secs(60))
+                .max_wait(std::time::Duration::from_millis(100)),
+        )
+        .build()
+        .unwrap();
+    let url = format!("http://{}/", server.addr());
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+
+    let err = client.get(&url).send().await.unwrap_err();
+    assert!(err.is_rate_limited(), "{:?}", err);
+    assert_eq!(err.url().map(|u| u.as_str()), Some(url.as_str()));
+}
+
+#[cfg(feature = "boring-tls")]
+#[test]
+fn build_fails_on_validation_errors() {
+    use rquest::tls::Version;
+    use rquest::ValidationError;
+
+    let builder = Client::builder()
+        .min_tls_version(Version::TLS_1_3)
+        .max_tls_version(Version::TLS_1_2);
+    assert_eq!(
+        builder.validate().unwrap_err(),
+        vec![ValidationError::TlsVersionRange {
+            min: Version::TLS_1_3,
+            max: Version::TLS_1_2,
+        }]
+    );
+
+    let err = builder.build().unwrap_err();
+    assert!(err.is_builder());
+    assert_eq!(err.validation_errors().map(<[_]>::len), Some(1));
+    assert!(err.to_string().contains("minimum TLS version"), "{}", err);
+}
+
+#[tokio::test]
+async fn clones_share_the_connection_pool() {
+    let server = server::http(move |_req| async move { http::Response::default() });
+
+    let client = Client::builder().no_proxy().build().unwrap();
+    let clone = client.clone();
+    let shorter = client
+        .to_builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .build()
+        .unwrap();
+    let url = format!("http://{}/", server.addr());
+
+    for client in [&client, &clone, &shorter] {
+        let res = client.get(&url).send().await.unwrap();
+        res.bytes().await.unwrap();
+    }
+    assert_eq!(client.pool_stats().connections, 1);
+    assert_eq!(clone.pool_stats().connections, 1);
+}
+
+#[tokio::test]
+async fn connection_info_of_pooled_connection() {
+    let server = server::http(move |_req| async move { http::Response::default() });
+
+    let client = Client::builder().no_proxy().build().unwrap();
+    let url = format!("http://{}/", server.addr());
+    assert!(client.connection_info(&url).is_none());
+
+    let res = client.get(&url).send().await.unwrap();
+    res.bytes().await.unwrap();
+
+    let info = client.connection_info(&url).unwrap();
+    assert_eq!(info.remote_addr, Some(server.addr()));
+    assert_eq!(info.http_version, http::Version::HTTP_11);
+    assert_eq!(info.stream_count, 0);
+    assert!(info.idle_for <= info.connection_age);
+    #[cfg(feature = "boring-tls")]
+    assert_eq!(info.tls_version, None);
+
+    // Only looked at, the connection is still reused.
+    client.get(&url).send().await.unwrap();
+    assert_eq!(client.pool_stats().connections, 1);
+}
+
+#[tokio::test]
+async fn request_id_is_kept_across_redirects() {
+    use std::sync::{Arc, Mutex};
+
+    let ids = Arc::new(Mutex::new(Vec::new()));
+    let seen = ids.clone();
+    let server = server::http(move |req| {
+        let id = req.headers()["x-request-id"].to_str().unwrap().to_owned();
+        seen.lock().unwrap().push(id);
+        async move {
+            if req.uri() == "/redirect" {
+                return http::Response::builder()
+                    .status(302)
+                    .header("location", "/dst")
+                    .body(Default::default())
+                    .unwrap();
+            }
+            http::Response::default()
+        }
+    });
+
+    let client = Client::builder()
+        .no_proxy()
+        .redirect(rquest::redirect::Policy::default())
+        .request_id(rquest::RequestIdPolicy::new())
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/redirect", server.addr());
+    let res = client.get(&url).send().await.unwrap();
+    let id = res.extensions().get::<rquest::RequestId>().unwrap().clone();
+    assert_eq!(*ids.lock().unwrap(), [id.as_str(), id.as_str()]);
+
+    let res = client
+        .get(&url)
+        .header("x-request-id", "mine")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        res.extensions()
+            .get::<rquest::RequestId>()
+            .unwrap()
+            .as_str(),
+        "mine"
+    );
+
+    // Nothing listens on the port of a closed listener.
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", closed.local_addr().unwrap());
+    drop(closed);
+    let client = Client::builder()
+        .no_proxy()
+        .request_id(rquest::RequestIdPolicy::new().generator(|| "generated".to_owned()))
+        .build()
+        .unwrap();
+    let err = client.get(&url).send().await.unwrap_err();
+    assert_eq!(err.request_id(), Some("generated"));
+}
+
+#[tokio::test]
+async fn http2_settings_ack_timeout_closes_connection() {
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+
+    // Reads the frames of the client without ever answering.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (mut conn, _) = listener.accept().await.unwrap();
+        let mut buf = [0; 1024];
+        while conn.read(&mut buf).await.unwrap_or(0) > 0 {}
+    });
+
+    let client = Client::builder()
+        .no_proxy()
+        .http2_only()
+        .http2_settings_ack_timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+
+    let err = client
+        .get(format!("http://{}/", addr))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_settings_ack_timeout(), "{:?}", err);
+
+    server.abort();
+}
+
+/// Serves HTTP/2 on connections that send an `ORIGIN` frame after the
+/// server `SETTINGS`, declaring another origin, and the origin of the
+/// server too if `own_origin`. The connections are counted.
+async fn origin_frame_server(
+    own_origin: bool,
+) -> (
+    std::net::SocketAddr,
+    std::sync::Arc<std::sync::atomic::AtomicUsize>,
+) {
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{ready, Context, Poll};
+    use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+    struct OriginFrame {
+        inner: tokio::net::TcpStream,
+        settings_sent: bool,
+        frame: Option<Vec<u8>>,
+    }
+
+    impl AsyncRead for OriginFrame {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.inner).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for OriginFrame {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            if self.settings_sent {
+                if let Some(frame) = self.frame.clone() {
+                    let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &frame))?;
+                    assert_eq!(n, frame.len());
+                    self.frame = None;
+                }
+            }
+            self.settings_sent = true;
+            Pin::new(&mut self.inner).poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.inner).poll_flush(cx)
+        }
+
+        fn poll_shutdown(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.inner).poll_shutdown(cx)
+        }
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut origins = vec!["https://other.example".to_owned()];
+    if own_origin {
+        origins.push(format!("http://{}", addr));
+    }
+    let mut payload = Vec::new();
+    for origin in origins {
+        payload.extend_from_slice(&(origin.len() as u16).to_be_bytes());
+        payload.extend_from_slice(origin.as_bytes());
+    }
+    let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+    frame.extend_from_slice(&[0xc, 0, 0, 0, 0, 0]);
+    frame.extend_from_slice(&payload);
+
+    let accepted = Arc::new(AtomicUsize::new(0));
+    let counter = accepted.clone();
+    tokio::spawn(async move {
+        while let Ok((inner, _)) = listener.accept().await {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let io = OriginFrame {
+                inner,
+                settings_sent: false,
+                frame: Some(frame.clone()),
+            };
+            tokio::spawn(
+                hyper::server::conn::Http::new()
+                    .http2_only(true)
+                    .serve_connection(
+                        io,
+                        hyper::service::service_fn(|_| async {
+                            Ok::<_, std::convert::Infallible>(hyper::Response::new(
+                                hyper::Body::empty(),
+                            ))
+                        }),
+                    ),
+            );
+        }
+    });
+    (addr, accepted)
+}
+
+#[tokio::test]
+async fn http2_connection_is_not_reused_outside_its_origin_set() {
+    use std::sync::atomic::Ordering;
+
+    let (addr, accepted) = origin_frame_server(false).await;
+    let client = Client::builder().no_proxy().http2_only().build().unwrap();
+    let url = format!("http://{}/", addr);
+
+    client.get(&url).send().await.unwrap();
+    assert_eq!(
+        client.h2_origin_set(&url),
+        Some(vec!["https://other.example".to_owned()])
+    );
+    client.get(&url).send().await.unwrap();
+    assert_eq!(accepted.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn http2_connection_is_reused_inside_its_origin_set() {
+    use std::sync::atomic::Ordering;
+
+    let (addr, accepted) = origin_frame_server(true).await;
+    let client = Client::builder().no_proxy().http2_only().build().unwrap();
+    let url = format!("http://{}/", addr);
+
+    client.get(&url).send().await.unwrap();
+    client.get(&url).send().await.unwrap();
+    assert_eq!(accepted.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn max_connections_per_host_exhausts_pool() {
+    use std::time::Duration;
+
+    // Accepts connections without ever answering.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let mut conns = Vec::new();
+        while let Ok((conn, _)) = listener.accept().await {
+            conns.push(conn);
+        }
+    });
+
+    let client = Client::builder()
+        .no_proxy()
+        .max_connections_per_host(1)
+        .pool_checkout_timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+    let url = format!("http://{}/", addr);
+
+    let pending = tokio::spawn(client.get(&url).send());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(client.pool_stats().connections, 1);
+
+    let err = client.get(&url).send().await.unwrap_err();
+    assert!(err.is_pool_exhausted(), "{:?}", err);
+    assert_eq!(client.pool_stats().waiting, 0);
+
+    pending.abort();
+    server.abort();
+}
+
+#[cfg(feature = "boring-tls")]
+#[test]
+fn tls_settings_are_validated() {
+    use rquest::tls::{Http2Settings, TlsSettings};
+
+    Client::builder()
+        .tls_settings(TlsSettings::default())
+        .http2_settings(Http2Settings::builder().enable_push(false).build())
+        .build()
+        .unwrap();
+
+    let err = Client::builder()
+        .tls_settings(
+            TlsSettings::builder()
+                .sigalgs_list("UNKNOWN_ALG".to_owned())
+                .build(),
+        )
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder());
+}
+
+#[cfg(feature = "boring-tls")]
+#[tokio::test]
+async fn lazy_tls_init_fails_the_first_request_instead_of_build() {
+    use rquest::boring::error::ErrorStack;
+    use rquest::boring::ssl::SslConnectorBuilder;
+    use rquest::tls::TlsSettings;
+
+    let server = server::http(move |_req| async { http::Response::default() });
+    let failing = || {
+        TlsSettings::builder()
+            .connector(Box::new(|| -> Result<SslConnectorBuilder, ErrorStack> {
+                Err(ErrorStack::get())
+            }))
+            .build()
+    };
+
+    let err = Client::builder()
+        .tls_settings(failing())
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder());
+
+    let client = Client::builder()
+        .tls_settings(failing())
+        .lazy_tls_init(true)
+        .build()
+        .unwrap();
+    let url = format!("http://{}/", server.addr());
+    assert!(client.get(&url).send().await.is_err());
+}
+
+#[cfg(feature = "boring-tls")]
+#[tokio::test]
+async fn lazy_tls_init_builds_once_for_concurrent_requests() {
+    use rquest::boring::ssl::{SslConnector, SslMethod};
+    use rquest::tls::TlsSettings;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let server = server::http(move |_req| async { http::Response::default() });
+    let builds = Arc::new(AtomicUsize::new(0));
+    let settings = || {
+        let builds = builds.clone();
+        TlsSettings::builder()
+            .connector(Box::new(move || {
+                builds.fetch_add(1, Ordering::SeqCst);
+                SslConnector::builder(SslMethod::tls_client())
+            }))
+            .build()
+    };
+
+    // How many times building the TLS contexts of a client calls it.
+    Client::builder().tls_settings(settings()).build().unwrap();
+    let per_client = builds.swap(0, Ordering::SeqCst);
+    assert!(per_client > 0);
+
+    let client = Client::builder()
+        .tls_settings(settings())
+        .lazy_tls_init(true)
+        .build()
+        .unwrap();
+    assert_eq!(builds.load(Ordering::SeqCst), 0);
+
+    let url = format!("http://{}/", server.addr());
+    let requests = (0..8)
+        .map(|_| tokio::spawn(client.get(&url).send()))
+        .collect::<Vec<_>>();
+    for request in requests {
+        let res = request.await.unwrap().unwrap();
+        assert_eq!(res.status(), rquest::StatusCode::OK);
+    }
+    assert_eq!(builds.load(Ordering::SeqCst), per_client);
+}
@@ -96,6 +96,46 @@ async fn cookie_store_simple() {
     client.get(&url).send().await.unwrap();
 }
 
+#[tokio::test]
+async fn to_builder_shares_cookie_store() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["x-base"], "1");
+        if req.uri() == "/2" {
+            assert_eq!(req.headers()["x-derived"], "1");
+            assert_eq!(req.headers()["cookie"], "key=val");
+        } else {
+            assert!(req.headers().get("x-derived").is_none());
+        }
+        http::Response::builder()
+            .header("Set-Cookie", "key=val")
+            .body(Default::default())
+            .unwrap()
+    });
+
+    let mut headers = http::HeaderMap::new();
+    headers.insert("x-base", http::HeaderValue::from_static("1"));
+    let client = rquest::Client::builder()
+        .cookie_store(true)
+        .default_headers(headers)
+        .build()
+        .unwrap();
+
+    let mut headers = http::HeaderMap::new();
+    headers.insert("x-derived", http::HeaderValue::from_static("1"));
+    let derived = client
+        .to_builder()
+        .default_headers(headers)
+        .timeout(std::time::Duration::from_secs(5))
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/", server.addr());
+    client.get(&url).send().await.unwrap();
+
+    let url = format!("http://{}/2", server.addr());
+    derived.get(&url).send().await.unwrap();
+}
+
 #[tokio::test]
 async fn cookie_store_overwrite_existing() {
     let server = server::http(move |req| async move {
@@ -0,0 +1,560 @@
+//! Fingerprints of the `Impersonate` profiles, compared with the golden
+//! ones in `tests/golden_fingerprints`.
+//!
+//! Each profile connects to a local TLS server, which computes the JA3 and
+//! JA4 fingerprints of its `ClientHello`, and the Akamai fingerprint of the
+//! first HTTP/2 frames it sends, so the test runs offline:
+//!
+//! ```sh
+//! cargo test --features json --test fingerprint_regression
+//! ```
+//!
+//! A BoringSSL update or a profile change that changes a fingerprint fails
+//! the test. Once the change is intended, the golden files are rewritten by
+//! running it with `UPDATE_GOLDEN_FINGERPRINTS=1`.
+#![cfg(not(target_arch = "wasm32"))]
+#![cfg(feature = "boring-tls")]
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use boring::asn1::Asn1Time;
+use boring::bn::BigNum;
+use boring::ec::{EcGroup, EcKey};
+use boring::hash::MessageDigest;
+use boring::nid::Nid;
+use boring::pkey::PKey;
+use boring::ssl::{AlpnError, SslAcceptor, SslMethod};
+use boring::x509::{X509NameBuilder, X509};
+use rquest::tls::Impersonate;
+use serde_json::Value;
+use tokio::io::AsyncReadExt;
+use tokio::net::{TcpListener, TcpStream};
+
+/// The name the profiles connect to, sent in the SNI extension.
+const HOST: &str = "fingerprint.test";
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+const HEADERS: u8 = 0x1;
+const PRIORITY: u8 = 0x2;
+const SETTINGS: u8 = 0x4;
+const WINDOW_UPDATE: u8 = 0x8;
+
+fn acceptor() -> SslAcceptor {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_text("CN", HOST).unwrap();
+    let name = name.build();
+    let mut cert = X509::builder().unwrap();
+    cert.set_version(2).unwrap();
+    let serial = BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap();
+    cert.set_serial_number(&serial).unwrap();
+    cert.set_subject_name(&name).unwrap();
+    cert.set_issuer_name(&name).unwrap();
+    cert.set_pubkey(&key).unwrap();
+    cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    cert.sign(&key, MessageDigest::sha256()).unwrap();
+
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor.set_private_key(&key).unwrap();
+    acceptor.set_certificate(&cert.build()).unwrap();
+    acceptor.set_alpn_select_callback(|_, protos| {
+        boring::ssl::select_next_proto(b"\x02h2", protos).ok_or(AlpnError::NOACK)
+    });
+    acceptor.build()
+}
+
+async fn fingerprint(impersonate: Impersonate) -> Value {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        serve(stream).await
+    });
+
+    let client = rquest::Client::builder()
+        .impersonate(impersonate)
+        .danger_accept_invalid_certs(true)
+        .resolve(HOST, addr)
+        .no_proxy()
+        .build()
+        .unwrap();
+    // The server hangs up after the request headers.
+    let request = client
+        .get(format!("https://{HOST}:{}/", addr.port()))
+        .send();
+    let _ = tokio::time::timeout(Duration::from_secs(5), request).await;
+
+    server.await.unwrap()
+}
+
+/// Reads the fingerprints of the client on `stream`.
+async fn serve(stream: TcpStream) -> Value {
+    let hello = peek_client_hello(&stream).await;
+    let mut stream = tokio_boring::accept(&acceptor(), stream)
+        .await
+        .unwrap_or_else(|err| panic!("TLS handshake failed: {err:?}"));
+
+    let mut preface = [0; PREFACE.len()];
+    stream.read_exact(&mut preface).await.unwrap();
+    assert_eq!(preface, PREFACE, "not an HTTP/2 connection");
+
+    let mut settings = Vec::new();
+    let mut window_update = None;
+    let mut priorities = Vec::new();
+    let pseudo_headers = loop {
+        let mut header = [0; 9];
+        stream.read_exact(&mut header).await.unwrap();
+        let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+        let (kind, flags) = (header[3], header[4]);
+        let stream_id = u32::from_be_bytes([header[5], header[6], header[7], header[8]]);
+        let mut payload = vec![0; len];
+        stream.read_exact(&mut payload).await.unwrap();
+
+        match kind {
+            SETTINGS if flags & 0x1 == 0 => {
+                for setting in payload.chunks_exact(6) {
+                    let id = u16::from_be_bytes([setting[0], setting[1]]);
+                    let value =
+                        u32::from_be_bytes([setting[2], setting[3], setting[4], setting[5]]);
+                    settings.push(format!("{id}:{value}"));
+                }
+            }
+            WINDOW_UPDATE if stream_id == 0 => {
+                let increment = u32::from_be_bytes(payload[..4].try_into().unwrap());
+                window_update = Some(increment & 0x7fff_ffff);
+            }
+            PRIORITY => priorities.push(priority(stream_id, &payload)),
+            HEADERS => break pseudo_header_order(flags, &payload),
+            _ => {}
+        }
+    };
+
+    let akamai = format!(
+        "{}|{}|{}|{}",
+        settings.join(";"),
+        window_update.map_or("00".to_owned(), |increment| increment.to_string()),
+        if priorities.is_empty() {
+            "0".to_owned()
+        } else {
+            priorities.join(",")
+        },
+        pseudo_headers,
+    );
+
+    let mut fingerprint = serde_json::Map::new();
+    fingerprint.insert("ja3".to_owned(), Value::String(normalize_ja3(&hello.ja3())));
+    fingerprint.insert("ja4".to_owned(), Value::String(hello.ja4()));
+    fingerprint.insert("akamai".to_owned(), Value::String(akamai));
+    Value::Object(fingerprint)
+}
+
+/// Waits for the whole `ClientHello` on `stream`, and parses it, leaving
+/// it to be read by the TLS handshake.
+async fn peek_client_hello(stream: &TcpStream) -> ClientHello {
+    let mut buf = vec![0; 5 + 16 * 1024];
+    loop {
+        stream.readable().await.unwrap();
+        let n = stream.peek(&mut buf).await.unwrap();
+        assert!(n > 0, "connection closed before the ClientHello");
+        if n >= 5 {
+            assert_eq!(buf[0], 22, "not a TLS handshake record");
+            let len = u16::from_be_bytes([buf[3], buf[4]]) as usize;
+            if n >= 5 + len {
+                return ClientHello::parse(&buf[5..5 + len]);
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+/// The fields of a `ClientHello` the fingerprints are made of, GREASE
+/// values left out.
+#[derive(Default)]
+struct ClientHello {
+    version: u16,
+    ciphers: Vec<u16>,
+    extensions: Vec<u16>,
+    groups: Vec<u16>,
+    point_formats: Vec<u8>,
+    signature_algorithms: Vec<u16>,
+    supported_versions: Vec<u16>,
+    alpn: Option<Vec<u8>>,
+}
+
+fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+/// A cursor over the fields of a TLS message.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> &'a [u8] {
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        bytes
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.bytes(1)[0]
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_be_bytes(self.bytes(2).try_into().unwrap())
+    }
+
+    /// A vector of bytes prefixed with its length on `len_bytes` bytes.
+    fn vec(&mut self, len_bytes: usize) -> Reader<'a> {
+        let len = self
+            .bytes(len_bytes)
+            .iter()
+            .fold(0, |len, &b| len << 8 | b as usize);
+        Reader(self.bytes(len))
+    }
+
+    fn u16s(mut self) -> Vec<u16> {
+        let mut values = Vec::new();
+        while !self.0.is_empty() {
+            values.push(self.u16());
+        }
+        values.retain(|&value| !is_grease(value));
+        values
+    }
+}
+
+impl ClientHello {
+    /// Parses the `ClientHello` handshake message at the start of `record`.
+    fn parse(record: &[u8]) -> ClientHello {
+        let mut record = Reader(record);
+        assert_eq!(record.u8(), 1, "not a ClientHello");
+        let mut body = record.vec(3);
+
+        let mut hello = ClientHello {
+            version: body.u16(),
+            ..ClientHello::default()
+        };
+        body.bytes(32);
+        body.vec(1);
+        hello.ciphers = body.vec(2).u16s();
+        body.vec(1);
+
+        let mut extensions = body.vec(2);
+        while !extensions.0.is_empty() {
+            let kind = extensions.u16();
+            let mut data = extensions.vec(2);
+            if is_grease(kind) {
+                continue;
+            }
+            hello.extensions.push(kind);
+            match kind {
+                10 => hello.groups = data.vec(2).u16s(),
+                11 => hello.point_formats = data.vec(1).0.to_vec(),
+                13 => hello.signature_algorithms = data.vec(2).u16s(),
+                16 => hello.alpn = Some(data.vec(2).vec(1).0.to_vec()),
+                43 => hello.supported_versions = data.vec(1).u16s(),
+                _ => {}
+            }
+        }
+        hello
+    }
+
+    fn ja3(&self) -> String {
+        fn join<T: ToString>(values: &[T]) -> String {
+            values
+                .iter()
+                .map(T::to_string)
+                .collect::<Vec<_>>()
+                .join("-")
+        }
+        format!(
+            "{},{},{},{},{}",
+            self.version,
+            join(&self.ciphers),
+            join(&self.extensions),
+            join(&self.groups),
+            join(&self.point_formats),
+        )
+    }
+
+    fn ja4(&self) -> String {
+        fn hex(values: &[u16]) -> String {
+            values
+                .iter()
+                .map(|value| format!("{value:04x}"))
+                .collect::<Vec<_>>()
+                .join(",")
+        }
+
+        let version = match self.supported_versions.iter().max() {
+            Some(&version) => version,
+            None => self.version,
+        };
+        let version = match version {
+            0x0304 => "13",
+            0x0303 => "12",
+            0x0302 => "11",
+            0x0301 => "10",
+            _ => "00",
+        };
+        let sni = if self.extensions.contains(&0) {
+            'd'
+        } else {
+            'i'
+        };
+        let alpn = match self.alpn.as_deref() {
+            Some([first, .., last]) => format!("{}{}", *first as char, *last as char),
+            Some([only]) => format!("{0}{0}", *only as char),
+            _ => "00".to_owned(),
+        };
+
+        let mut ciphers = self.ciphers.clone();
+        ciphers.sort_unstable();
+        let mut extensions = self.extensions.clone();
+        extensions.retain(|&kind| kind != 0 && kind != 16);
+        extensions.sort_unstable();
+        let extensions = if self.signature_algorithms.is_empty() {
+            hex(&extensions)
+        } else {
+            format!("{}_{}", hex(&extensions), hex(&self.signature_algorithms))
+        };
+
+        format!(
+            "t{version}{sni}{:02}{:02}{alpn}_{}_{}",
+            self.ciphers.len().min(99),
+            self.extensions.len().min(99),
+            truncated_sha256(&hex(&ciphers)),
+            truncated_sha256(&extensions),
+        )
+    }
+}
+
+/// The first 12 hex digits of the SHA-256 of `s`.
+fn truncated_sha256(s: &str) -> String {
+    boring::sha::sha256(s.as_bytes())[..6]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// A `PRIORITY` frame, as `stream:exclusive:dependency:weight`.
+fn priority(stream_id: u32, payload: &[u8]) -> String {
+    let dependency = u32::from_be_bytes(payload[..4].try_into().unwrap());
+    format!(
+        "{}:{}:{}:{}",
+        stream_id & 0x7fff_ffff,
+        dependency >> 31,
+        dependency & 0x7fff_ffff,
+        payload[4] as u16 + 1,
+    )
+}
+
+/// The order of the pseudo-headers of a `HEADERS` frame, such as
+/// `m,a,s,p`, read from the names of its HPACK representations.
+fn pseudo_header_order(flags: u8, payload: &[u8]) -> String {
+    // The static table entries of the pseudo-headers.
+    const STATIC: [&str; 8] = [
+        "",
+        ":authority",
+        ":method",
+        ":method",
+        ":path",
+        ":path",
+        ":scheme",
+        ":scheme",
+    ];
+
+    let mut block = Reader(payload);
+    let mut padding = 0;
+    if flags & 0x8 != 0 {
+        padding = block.u8() as usize;
+    }
+    if flags & 0x20 != 0 {
+        block.bytes(5);
+    }
+    block.0 = &block.0[..block.0.len() - padding];
+
+    /// An integer with an `n`-bit prefix in `first`.
+    fn int(block: &mut Reader, first: u8, n: u32) -> usize {
+        let max = (1 << n) - 1;
+        let mut value = (first & max) as usize;
+        if value == max as usize {
+            let mut shift = 0;
+            loop {
+                let b = block.u8();
+                value += ((b & 0x7f) as usize) << shift;
+                shift += 7;
+                if b & 0x80 == 0 {
+                    break;
+                }
+            }
+        }
+        value
+    }
+    /// A string literal, `None` if it is Huffman encoded.
+    fn string<'a>(block: &mut Reader<'a>) -> Option<&'a [u8]> {
+        let first = block.u8();
+        let len = int(block, first, 7);
+        let bytes = block.bytes(len);
+        (first & 0x80 == 0).then_some(bytes)
+    }
+
+    let mut order = Vec::new();
+    while !block.0.is_empty() {
+        let first = block.u8();
+        let name = if first & 0x80 != 0 {
+            let index = int(&mut block, first, 7);
+            STATIC.get(index).map(|name| name.as_bytes())
+        } else if first & 0xe0 == 0x20 {
+            // A dynamic table size update.
+            int(&mut block, first, 5);
+            continue;
+        } else {
+            let index = if first & 0x40 != 0 {
+                int(&mut block, first, 6)
+            } else {
+                int(&mut block, first, 4)
+            };
+            let name = match index {
+                0 => string(&mut block),
+                index => STATIC.get(index).map(|name| name.as_bytes()),
+            };
+            string(&mut block);
+            name
+        };
+        if let Some([b':', letter, ..]) = name {
+            order.push((*letter as char).to_string());
+        }
+    }
+    order.join(",")
+}
+
+/// Sorts the extensions of a JA3 fingerprint, which some browsers permute
+/// on every connection.
+fn normalize_ja3(ja3: &str) -> String {
+    let mut fields = ja3.split(',').map(str::to_owned).collect::<Vec<_>>();
+    if let Some(extensions) = fields.get_mut(2) {
+        let mut ids = extensions
+            .split('-')
+            .filter_map(|id| id.parse::<u16>().ok())
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        *extensions = ids.iter().map(u16::to_string).collect::<Vec<_>>().join("-");
+    }
+    fields.join(",")
+}
+
+fn golden_path(impersonate: Impersonate) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden_fingerprints")
+        .join(format!("{impersonate}.json"))
+}
+
+#[tokio::test]
+async fn impersonate_fingerprints_match_golden_files() {
+    let update = std::env::var_os("UPDATE_GOLDEN_FINGERPRINTS").is_some();
+
+    let mut mismatches = Vec::new();
+    for &impersonate in Impersonate::variants() {
+        let actual = fingerprint(impersonate).await;
+        let path = golden_path(impersonate);
+        if update {
+            let json = serde_json::to_string_pretty(&actual).unwrap();
+            std::fs::write(&path, json + "\n").unwrap();
+            continue;
+        }
+
+        let golden = match std::fs::read(&path) {
+            Ok(golden) => serde_json::from_slice::<Value>(&golden).unwrap(),
+            Err(err) => {
+                mismatches.push(format!("{impersonate}: {}: {err}", path.display()));
+                continue;
+            }
+        };
+        if golden != actual {
+            mismatches.push(format!(
+                "{impersonate}:\n  golden: {golden}\n  actual: {actual}"
+            ));
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "fingerprints changed, run with UPDATE_GOLDEN_FINGERPRINTS=1 once intended:\n{}",
+        mismatches.join("\n")
+    );
+}
+
+#[test]
+fn ja3_extensions_are_sorted() {
+    assert_eq!(
+        normalize_ja3("771,4865-4866,51-0-23-65281,29-23,0"),
+        "771,4865-4866,0-23-51-65281,29-23,0"
+    );
+}
+
+#[test]
+fn client_hello_fingerprints() {
+    let mut extensions = Vec::new();
+    // A GREASE extension, left out.
+    extensions.extend_from_slice(&[0x1a, 0x1a, 0, 0]);
+    // server_name
+    extensions.extend_from_slice(&[0, 0, 0, 5, 0, 3, 0, 0, 0]);
+    // supported_groups: GREASE, x25519, secp256r1
+    extensions.extend_from_slice(&[0, 10, 0, 8, 0, 6, 0x2a, 0x2a, 0, 29, 0, 23]);
+    // ec_point_formats: uncompressed
+    extensions.extend_from_slice(&[0, 11, 0, 2, 1, 0]);
+    // signature_algorithms: ecdsa_secp256r1_sha256, rsa_pss_rsae_sha256
+    extensions.extend_from_slice(&[0, 13, 0, 6, 0, 4, 4, 3, 8, 4]);
+    // application_layer_protocol_negotiation: h2
+    extensions.extend_from_slice(&[0, 16, 0, 5, 0, 3, 2, b'h', b'2']);
+    // supported_versions: TLS 1.3, TLS 1.2
+    extensions.extend_from_slice(&[0, 43, 0, 5, 4, 3, 4, 3, 3]);
+
+    let mut body = vec![3, 3];
+    body.extend_from_slice(&[0; 32]);
+    body.push(0);
+    // GREASE, TLS_AES_128_GCM_SHA256, TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256
+    body.extend_from_slice(&[0, 6, 0x3a, 0x3a, 0x13, 0x01, 0xc0, 0x2b]);
+    body.extend_from_slice(&[1, 0]);
+    body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+    body.extend_from_slice(&extensions);
+    let mut message = vec![1];
+    message.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+    message.extend_from_slice(&body);
+
+    let hello = ClientHello::parse(&message);
+    assert_eq!(hello.ja3(), "771,4865-49195,0-10-11-13-16-43,29-23,0");
+    let ja4 = hello.ja4();
+    assert!(ja4.starts_with("t13d0206h2_"), "{ja4}");
+    let hashes = ja4.split('_').skip(1).collect::<Vec<_>>();
+    assert_eq!(
+        hashes,
+        [
+            truncated_sha256("1301,c02b"),
+            truncated_sha256("000a,000b,000d,002b_0403,0804"),
+        ]
+    );
+}
+
+#[test]
+fn reads_pseudo_header_order() {
+    // :method GET, :authority (incrementally indexed), :scheme https,
+    // :path /, then user-agent from the static table.
+    let block = [
+        0x82, 0x41, 0x03, b'a', b'.', b'b', 0x87, 0x84, 0x7a, 0x01, b'x',
+    ];
+    assert_eq!(pseudo_header_order(0x4, &block), "m,a,s,p");
+
+    // The same with the PRIORITY flag, and padding.
+    let mut padded = vec![2, 0x80, 0, 0, 0, 255];
+    padded.extend_from_slice(&block);
+    padded.extend_from_slice(&[0, 0]);
+    assert_eq!(pseudo_header_order(0x4 | 0x8 | 0x20, &padded), "m,a,s,p");
+}
@@ -86,6 +86,108 @@ async fn test_accept_encoding_header_is_not_changed_if_set() {
     assert_eq!(res.status(), rquest::StatusCode::OK);
 }
 
+#[tokio::test]
+async fn accept_encoding_restricts_decoders() {
+    let mut encoder = libflate::gzip::Encoder::new(Vec::new()).unwrap();
+    encoder.write_all(b"hello gzip").unwrap();
+    let gzipped = encoder.finish().into_result().unwrap();
+
+    let server = server::http({
+        let gzipped = gzipped.clone();
+        move |req| {
+            let gzipped = gzipped.clone();
+            async move {
+                http::Response::builder()
+                    .header("content-encoding", "gzip")
+                    .header(
+                        "x-accept-encoding",
+                        req.headers()["accept-encoding"].clone(),
+                    )
+                    .body(gzipped.into())
+                    .unwrap()
+            }
+        }
+    });
+    let url = format!("http://{}/gzip", server.addr());
+
+    // (client decodes gzip, `accept_encoding`, `header`, sent, decoded)
+    let cases = [
+        (true, None, None, "gzip", true),
+        (true, Some("identity"), None, "identity", false),
+        (true, Some("br, gzip;q=0.5"), None, "br, gzip;q=0.5", true),
+        (true, Some("gzip;q=0"), None, "gzip;q=0", false),
+        (true, None, Some("identity"), "identity", true),
+        (false, Some("gzip"), None, "gzip", false),
+        (false, None, Some("gzip"), "gzip", false),
+    ];
+    for (client_gzip, accept_encoding, header, sent, decoded) in cases {
+        let client = rquest::Client::builder().gzip(client_gzip).build().unwrap();
+        let mut req = client.get(&url);
+        if let Some(value) = header {
+            req = req.header(rquest::header::ACCEPT_ENCODING, value);
+        }
+        if let Some(value) = accept_encoding {
+            req = req.accept_encoding(value);
+        }
+        let res = req.send().await.unwrap();
+
+        let case = (client_gzip, accept_encoding, header);
+        let header = res.headers()["x-accept-encoding"].to_str().unwrap();
+        assert!(header.contains(sent), "{case:?}: {header}");
+        let body = res.bytes().await.unwrap();
+        if decoded {
+            assert_eq!(body, "hello gzip", "{case:?}");
+        } else {
+            assert_eq!(body, gzipped, "{case:?}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn compress_body_gzip() {
+    use std::io::Read;
+
+    let server = server::http(move |req| async move {
+        let compressed = req.headers().contains_key("content-length");
+        let encoding = req.headers()["content-encoding"].clone();
+        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+
+        let mut decoded = String::new();
+        if encoding == "gzip" && compressed {
+            libflate::gzip::Decoder::new(&body[..])
+                .unwrap()
+                .read_to_string(&mut decoded)
+                .unwrap();
+        } else {
+            decoded = String::from_utf8(body.to_vec()).unwrap();
+        }
+        http::Response::new(decoded.into())
+    });
+
+    let client = rquest::Client::new();
+    let url = format!("http://{}/upload", server.addr());
+    let content = "telemetry ".repeat(1000);
+    let res = client
+        .post(&url)
+        .body(content.clone())
+        .compress_body(rquest::Compression::Gzip, 6)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), content);
+
+    // A body already encoded is not compressed again.
+    let res = client
+        .post(&url)
+        .header("content-encoding", "identity")
+        .body("plain")
+        .compress_body(rquest::Compression::Gzip, 6)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "plain");
+}
+
 async fn gzip_case(response_size: usize, chunk_size: usize) {
     use futures_util::stream::StreamExt;
 
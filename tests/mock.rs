@@ -0,0 +1,86 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use rquest::testing::mock::{Mock, MockServer};
+use rquest::Method;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[tokio::test]
+async fn mock_server_answers_without_network() {
+    let server = MockServer::new();
+    server
+        .mock(
+            Mock::new(Method::POST, "/items")
+                .header("content-type", "application/json")
+                .body(r#"{"id":1}"#)
+                .respond(201, "created")
+                .times(1),
+        )
+        .mock(Mock::new(Method::GET, "/items/1").respond(200, "item"));
+
+    let client = rquest::Client::builder()
+        .with_transport(server.clone())
+        .build()
+        .unwrap();
+
+    let res = client
+        .post("http://api.test/items")
+        .header("content-type", "application/json")
+        .body(r#"{"id":1}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::CREATED);
+    assert_eq!(res.text().await.unwrap(), "created");
+
+    let res = client.get("http://api.test/items/1").send().await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "item");
+
+    server.verify();
+}
+
+#[tokio::test]
+async fn transport_sees_every_redirect_hop() {
+    let hops = Arc::new(Mutex::new(Vec::new()));
+    let seen = hops.clone();
+
+    let client = rquest::Client::builder()
+        .redirect(rquest::redirect::Policy::limited(5))
+        .with_transport(move |req: rquest::Request| -> Result<_, BoxError> {
+            seen.lock().unwrap().push(req.url().path().to_owned());
+            let res = match req.url().path() {
+                "/start" => http::Response::builder()
+                    .status(302)
+                    .header("location", "/end")
+                    .body(Bytes::new()),
+                _ => http::Response::builder().body(Bytes::from("done")),
+            };
+            Ok(res?)
+        })
+        .build()
+        .unwrap();
+
+    let res = client.get("http://api.test/start").send().await.unwrap();
+    assert_eq!(res.url().path(), "/end");
+    assert_eq!(res.text().await.unwrap(), "done");
+    assert_eq!(*hops.lock().unwrap(), ["/start", "/end"]);
+}
+
+#[tokio::test]
+async fn unmatched_request_fails_and_is_reported() {
+    let server = MockServer::new();
+    let client = rquest::Client::builder()
+        .with_transport(server.clone())
+        .build()
+        .unwrap();
+
+    let err = client.get("http://api.test/nope").send().await.unwrap_err();
+    assert!(err.is_request());
+    assert_eq!(err.url().map(|u| u.path()), Some("/nope"));
+
+    let verified = std::panic::catch_unwind(|| server.verify());
+    assert!(verified.is_err());
+}
@@ -162,6 +162,81 @@ async fn test_no_proxy() {
     assert_eq!(res.status(), rquest::StatusCode::OK);
 }
 
+#[tokio::test]
+async fn no_proxy_list_matches_cidr_ranges() {
+    let server = server::http(move |req| {
+        assert_eq!(req.uri(), "/cidr");
+
+        async { http::Response::default() }
+    });
+    let url = format!("http://{}/cidr", server.addr());
+
+    // Nothing listens on the proxy, the request must bypass it.
+    let proxy =
+        rquest::Proxy::http("http://127.0.0.1:1")
+            .unwrap()
+            .no_proxy(rquest::NoProxy::from_string(
+                "LocalHost; 127.0.0.0/8, *.internal, [::1]",
+            ));
+
+    let res = rquest::Client::builder()
+        .proxy(proxy)
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn proxy_selector_routes_by_target() {
+    let url = "http://hyper.rs/selected";
+    let server = server::http(move |req| {
+        assert_eq!(req.uri(), url);
+        assert_eq!(
+            req.headers()["proxy-authorization"],
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+
+        async { http::Response::default() }
+    });
+
+    let proxy = format!("http://{}", server.addr());
+    let client = rquest::Client::builder()
+        .proxy_selector(move |url| {
+            assert_eq!(url.path(), "/");
+            match url.host_str() {
+                Some("hyper.rs") => Some(
+                    rquest::Proxy::http(&proxy)
+                        .unwrap()
+                        .basic_auth("Aladdin", "open sesame"),
+                ),
+                _ => None,
+            }
+        })
+        .build()
+        .unwrap();
+
+    let res = client.get(url).send().await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+
+    // Not selected, so the request goes directly to the server.
+    let direct = server::http(move |req| {
+        assert_eq!(req.uri(), "/direct");
+
+        async { http::Response::default() }
+    });
+    let res = client
+        .get(format!("http://{}/direct", direct.addr()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+}
+
 #[tokio::test]
 async fn test_using_system_proxy() {
     let url = "http://not.a.real.sub.hyper.rs/prox";
@@ -0,0 +1,218 @@
+#![cfg(not(target_arch = "wasm32"))]
+mod support;
+use support::*;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hyper::Body;
+use rquest::retry::{Attempts, Policy, RetryAfterWaits};
+
+fn fast_policy() -> Policy {
+    Policy::new().backoff(Duration::from_millis(1), Duration::from_millis(10))
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |req| {
+        let hit = counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            assert_eq!(req.method(), "POST");
+            let status = if hit < 2 { 503 } else { 200 };
+            http::Response::builder()
+                .status(status)
+                .body(Body::default())
+                .unwrap()
+        }
+    });
+
+    let client = rquest::Client::builder()
+        .retry(fast_policy())
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/retry", server.addr());
+    let res = client.post(&url).body("replayable").send().await.unwrap();
+
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    assert_eq!(res.extensions().get::<Attempts>().unwrap().count(), 3);
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn returns_last_response_when_exhausted() {
+    let server = server::http(move |_req| async {
+        http::Response::builder()
+            .status(502)
+            .body(Body::default())
+            .unwrap()
+    });
+
+    let client = rquest::Client::builder()
+        .retry(fast_policy().max_attempts(2))
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/exhausted", server.addr());
+    let res = client.get(&url).send().await.unwrap();
+
+    assert_eq!(res.status(), rquest::StatusCode::BAD_GATEWAY);
+    assert_eq!(res.extensions().get::<Attempts>().unwrap().count(), 2);
+}
+
+#[tokio::test]
+async fn exhausted_retry_budget_fails_fast() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |_req| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async {
+            http::Response::builder()
+                .status(503)
+                .body(Body::default())
+                .unwrap()
+        }
+    });
+
+    let client = rquest::Client::builder()
+        .retry(fast_policy())
+        .retry_budget(0.0, 0)
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/budget", server.addr());
+    let err = client.get(&url).send().await.unwrap_err();
+
+    assert!(err.is_retry_budget_exhausted());
+    assert_eq!(err.url().unwrap().as_str(), url);
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn long_retry_after_is_not_waited_for() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |_req| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async {
+            http::Response::builder()
+                .status(429)
+                .header("retry-after", "3600")
+                .body(Body::default())
+                .unwrap()
+        }
+    });
+
+    let client = rquest::Client::builder()
+        .retry(fast_policy())
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/later", server.addr());
+    let res = client.get(&url).send().await.unwrap();
+
+    assert_eq!(res.status(), rquest::StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(res.extensions().get::<Attempts>().unwrap().count(), 1);
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn respect_retry_after_retries_once() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |_req| {
+        let hit = counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            let (status, retry_after) = match hit {
+                0 => (503, "0"),
+                1 => (200, "0"),
+                _ => (429, "3600"),
+            };
+            http::Response::builder()
+                .status(status)
+                .header("retry-after", retry_after)
+                .body(Body::default())
+                .unwrap()
+        }
+    });
+
+    let client = rquest::Client::builder()
+        .respect_retry_after(Duration::from_secs(1))
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/retry-after", server.addr());
+    let res = client.post(&url).body("replayable").send().await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    assert_eq!(
+        res.extensions().get::<RetryAfterWaits>().unwrap().count(),
+        1
+    );
+
+    // longer than `max_wait`, the response is returned as is
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(
+        res.extensions().get::<RetryAfterWaits>().unwrap().count(),
+        0
+    );
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |_req| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async {
+            http::Response::builder()
+                .status(404)
+                .body(Body::default())
+                .unwrap()
+        }
+    });
+
+    let client = rquest::Client::builder()
+        .retry(fast_policy())
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/missing", server.addr());
+    let res = client.get(&url).send().await.unwrap();
+
+    assert_eq!(res.status(), rquest::StatusCode::NOT_FOUND);
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn streaming_body_skips_retry() {
+    // bind and drop, so the connect is refused
+    let addr = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    };
+
+    let client = rquest::Client::builder()
+        .retry(fast_policy())
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let body = rquest::Body::wrap_stream(futures_util::stream::once(async {
+        Ok::<_, std::io::Error>("streamed")
+    }));
+    let err = client
+        .post(format!("http://{}/stream", addr))
+        .body(body)
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(err.is_connect());
+    assert!(err.to_string().contains("retry skipped"));
+}
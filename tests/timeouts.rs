@@ -28,6 +28,11 @@ async fn client_timeout() {
     let err = res.unwrap_err();
 
     assert!(err.is_timeout());
+    assert_eq!(
+        err.timeout_phase(),
+        Some(rquest::TimeoutPhase::ResponseHeader)
+    );
+    assert!(err.timeout_elapsed().unwrap() >= Duration::from_millis(500));
     assert_eq!(err.url().map(|u| u.as_str()), Some(url.as_str()));
 }
 
@@ -63,6 +68,39 @@ async fn request_timeout() {
     assert_eq!(err.url().map(|u| u.as_str()), Some(url.as_str()));
 }
 
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn request_body_timeout_is_in_request_write() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| async {
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        http::Response::default()
+    });
+
+    let client = rquest::Client::builder()
+        .timeout(Duration::from_millis(500))
+        .build()
+        .unwrap();
+
+    // The body never ends, so the request is still being written.
+    let body = rquest::Body::wrap_stream(futures_util::stream::pending::<
+        Result<&'static str, std::io::Error>,
+    >());
+    let err = client
+        .post(format!("http://{}/upload", server.addr()))
+        .body(body)
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(err.is_timeout());
+    assert_eq!(
+        err.timeout_phase(),
+        Some(rquest::TimeoutPhase::RequestWrite)
+    );
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 #[tokio::test]
 async fn connect_timeout() {
@@ -84,6 +122,8 @@ async fn connect_timeout() {
     let err = res.unwrap_err();
 
     assert!(err.is_connect() && err.is_timeout());
+    assert_eq!(err.timeout_phase(), Some(rquest::TimeoutPhase::TcpConnect));
+    assert_eq!(err.timeout_elapsed(), Some(Duration::from_millis(100)));
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -173,3 +213,35 @@ async fn response_timeout() {
 
     assert!(err.is_timeout());
 }
+
+#[tokio::test]
+async fn body_timeout_is_classified() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| async {
+        let body = hyper::Body::wrap_stream(futures_util::stream::once(async {
+            tokio::time::sleep(Duration::from_secs(2)).await;
+            Ok::<_, std::convert::Infallible>("Hello")
+        }));
+
+        http::Response::new(body)
+    });
+
+    let client = rquest::Client::builder()
+        .timeout(Duration::from_millis(500))
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/slow", server.addr());
+    let res = client.get(&url).send().await.expect("Failed to get");
+    let err = res.bytes().await.unwrap_err();
+
+    assert!(err.is_body() && err.is_timeout());
+    assert_eq!(
+        err.timeout_phase(),
+        Some(rquest::TimeoutPhase::ResponseBody)
+    );
+    assert!(!err.is_connect() && !err.is_dns());
+    assert!(err.is_retryable());
+}
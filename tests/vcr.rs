@@ -0,0 +1,113 @@
+#![cfg(not(target_arch = "wasm32"))]
+mod support;
+use support::server;
+
+use std::path::PathBuf;
+
+use rquest::header::HeaderName;
+use rquest::testing::vcr::{Vcr, VcrMode};
+
+fn cassette(name: &str) -> PathBuf {
+    let path = std::env::temp_dir()
+        .join(format!("rquest-vcr-{}", std::process::id()))
+        .join(name);
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[tokio::test]
+async fn recorded_requests_replay_without_network() {
+    let path = cassette("replay.json");
+    let server = server::http(move |req| async move {
+        let body = format!("hello {}", req.uri().path());
+        http::Response::builder()
+            .header("set-cookie", "session=secret")
+            .header("x-served", "live")
+            .body(body.into())
+            .unwrap()
+    });
+    let url = format!("http://{}/world", server.addr());
+
+    let recorder = rquest::Client::builder()
+        .vcr(VcrMode::Record, &path)
+        .build()
+        .unwrap();
+    let res = recorder
+        .get(&url)
+        .bearer_auth("token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.headers()["set-cookie"], "session=secret");
+    assert_eq!(res.text().await.unwrap(), "hello /world");
+
+    let recorded = std::fs::read_to_string(&path).unwrap();
+    assert!(!recorded.contains("token"), "{}", recorded);
+    assert!(!recorded.contains("session=secret"), "{}", recorded);
+    drop(server);
+
+    let replayer = rquest::Client::builder()
+        .vcr(VcrMode::Replay, &path)
+        .build()
+        .unwrap();
+    let res = replayer.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    assert_eq!(res.url().as_str(), url);
+    assert_eq!(res.headers()["x-served"], "live");
+    assert_eq!(res.text().await.unwrap(), "hello /world");
+}
+
+#[tokio::test]
+async fn unmatched_replay_fails() {
+    let path = cassette("unmatched.json");
+    let server = server::http(move |_req| async move { http::Response::default() });
+    let url = format!("http://{}/recorded", server.addr());
+
+    let client = rquest::Client::builder()
+        .layer(Vcr::new(VcrMode::Auto, &path).match_header(HeaderName::from_static("x-tenant")))
+        .build()
+        .unwrap();
+    client
+        .get(&url)
+        .header("x-tenant", "a")
+        .send()
+        .await
+        .unwrap();
+    drop(server);
+
+    let client = rquest::Client::builder()
+        .layer(Vcr::new(VcrMode::Auto, &path).match_header(HeaderName::from_static("x-tenant")))
+        .build()
+        .unwrap();
+    let res = client
+        .get(&url)
+        .header("x-tenant", "a")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+
+    let err = client
+        .get(&url)
+        .header("x-tenant", "b")
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_request());
+    assert!(
+        err.to_string().contains("no interaction in cassette"),
+        "{}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn replay_of_missing_cassette_fails() {
+    let client = rquest::Client::builder()
+        .vcr(VcrMode::Replay, cassette("missing.json"))
+        .build()
+        .unwrap();
+
+    let err = client.get("http://api.test/").send().await.unwrap_err();
+    assert!(err.to_string().contains("cannot load cassette"), "{}", err);
+}
@@ -0,0 +1,569 @@
+#![cfg(not(target_arch = "wasm32"))]
+mod support;
+use support::*;
+
+use std::error::Error as _;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use async_tungstenite::tungstenite::{self, handshake::derive_accept_key};
+use bytes::BytesMut;
+use futures_util::{SinkExt, StreamExt};
+use ratchet_rs::deflate::{Deflate, DeflateConfig, DeflateExtProvider, WindowBits};
+use ratchet_rs::{ExtensionProvider, PayloadType};
+use rquest::{
+    CloseCode, CloseFrame, Message, WebSocket, WebSocketRequestBuilder, WsCompression, WsError,
+};
+use tokio::sync::mpsc;
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+
+type ServerSocket = async_tungstenite::WebSocketStream<Compat<hyper::upgrade::Upgraded>>;
+
+/// Answers the upgrade request, handing the connection to `session`.
+///
+/// `extensions` is the `Sec-WebSocket-Extensions` response header, if any.
+fn upgrade<F, Fut>(
+    req: http::Request<hyper::Body>,
+    extensions: Option<String>,
+    session: F,
+) -> http::Response<hyper::Body>
+where
+    F: FnOnce(hyper::upgrade::Upgraded) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    let accept = derive_accept_key(req.headers()["sec-websocket-key"].as_bytes());
+
+    tokio::spawn(async move {
+        session(hyper::upgrade::on(req).await.unwrap()).await;
+    });
+
+    let mut res = http::Response::builder()
+        .status(http::StatusCode::SWITCHING_PROTOCOLS)
+        .header(http::header::CONNECTION, "upgrade")
+        .header(http::header::UPGRADE, "websocket")
+        .header(http::header::SEC_WEBSOCKET_ACCEPT, accept);
+    if let Some(extensions) = extensions {
+        res = res.header(http::header::SEC_WEBSOCKET_EXTENSIONS, extensions);
+    }
+    res.body(hyper::Body::empty()).unwrap()
+}
+
+/// Echoes every message with ratchet, a `permessage-deflate`
+/// implementation independent of the client's.
+///
+/// The server negotiates the client's offer with `config`, or accepts
+/// `agreed` if given, as if the client had offered it.
+fn deflate_echo_server(config: DeflateConfig, agreed: Option<&'static str>) -> server::Server {
+    server::http(move |req| {
+        let offer = match agreed {
+            Some(agreed) => agreed,
+            None => req.headers()[http::header::SEC_WEBSOCKET_EXTENSIONS]
+                .to_str()
+                .unwrap(),
+        };
+        let mut headers = ratchet_rs::HeaderMap::new();
+        headers.insert(
+            "sec-websocket-extensions",
+            ratchet_rs::HeaderValue::from_str(offer).unwrap(),
+        );
+        let (deflate, extensions) = DeflateExtProvider::with_config(config)
+            .negotiate_server(&headers)
+            .unwrap()
+            .expect("permessage-deflate negotiated");
+        let extensions = extensions.to_str().unwrap().to_owned();
+
+        let res = upgrade(req, Some(extensions), |io| echo(io, deflate));
+        async { res }
+    })
+}
+
+async fn echo(io: hyper::upgrade::Upgraded, deflate: Deflate) {
+    let mut ws = ratchet_rs::WebSocket::from_upgraded(
+        ratchet_rs::WebSocketConfig::default(),
+        io,
+        Some(deflate),
+        BytesMut::new(),
+        ratchet_rs::Role::Server,
+    );
+    let mut buf = BytesMut::new();
+    loop {
+        let kind = match ws.read(&mut buf).await {
+            Ok(ratchet_rs::Message::Text) => PayloadType::Text,
+            Ok(ratchet_rs::Message::Binary) => PayloadType::Binary,
+            Ok(ratchet_rs::Message::Ping(_) | ratchet_rs::Message::Pong(_)) => continue,
+            Ok(ratchet_rs::Message::Close(_)) | Err(_) => break,
+        };
+        ws.write(&mut buf, kind).await.unwrap();
+        buf.clear();
+    }
+}
+
+/// Text that barely compresses on its own, repeated further apart than a
+/// 512 byte window reaches.
+fn repeated_text() -> String {
+    let mut seed = 0x2545_f491_u32;
+    let chunk = (0..1500)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            char::from(b'a' + (seed % 26) as u8)
+        })
+        .collect::<String>();
+    chunk.repeat(2)
+}
+
+/// Serves every connection with tungstenite, handing it to `session`.
+fn ws_server<F, Fut>(session: F) -> server::Server
+where
+    F: Fn(ServerSocket) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    server::http(move |req| {
+        let session = session.clone();
+        let res = upgrade(req, None, |io| async move {
+            let role = tungstenite::protocol::Role::Server;
+            let ws = ServerSocket::from_raw_socket(io.compat(), role, None).await;
+            session(ws).await;
+        });
+        async { res }
+    })
+}
+
+fn websocket(server: &server::Server) -> WebSocketRequestBuilder {
+    rquest::Client::new().websocket(format!("ws://{}/", server.addr()))
+}
+
+/// Echoes text and binary messages.
+fn echo_server() -> server::Server {
+    ws_server(|mut ws| async move {
+        while let Some(Ok(message)) = ws.next().await {
+            if message.is_text() || message.is_binary() {
+                ws.send(message).await.unwrap();
+            }
+        }
+    })
+}
+
+async fn open(builder: WebSocketRequestBuilder) -> WebSocket {
+    builder
+        .send()
+        .await
+        .unwrap()
+        .into_websocket()
+        .await
+        .unwrap()
+}
+
+async fn connect(server: &server::Server, compression: WsCompression) -> WebSocket {
+    open(websocket(server).compression(compression)).await
+}
+
+/// Sends the same messages several times, so that they refer to the
+/// previous ones unless the context is reset.
+async fn assert_echoes(ws: &mut WebSocket) {
+    let text = repeated_text();
+    for _ in 0..3 {
+        ws.send(Message::Text(text.clone())).await.unwrap();
+        match ws.next().await {
+            Some(Ok(Message::Text(echo))) => assert_eq!(echo, text),
+            other => panic!("unexpected echo: {other:?}"),
+        }
+
+        ws.send(Message::Binary(text.clone().into_bytes()))
+            .await
+            .unwrap();
+        match ws.next().await {
+            Some(Ok(Message::Binary(echo))) => assert_eq!(echo, text.as_bytes()),
+            other => panic!("unexpected echo: {other:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn deflate_interop_context_takeover() {
+    for (client_no_context_takeover, server_no_context_takeover) in
+        [(false, false), (true, false), (false, true), (true, true)]
+    {
+        let server = deflate_echo_server(
+            DeflateConfig {
+                accept_no_context_takeover: server_no_context_takeover,
+                ..DeflateConfig::default()
+            },
+            None,
+        );
+
+        let mut ws = connect(
+            &server,
+            WsCompression {
+                client_no_context_takeover,
+                server_no_context_takeover,
+                ..WsCompression::default()
+            },
+        )
+        .await;
+
+        let negotiated = *ws.compression().expect("compression negotiated");
+        assert_eq!(
+            negotiated.client_no_context_takeover,
+            client_no_context_takeover
+        );
+        assert_eq!(
+            negotiated.server_no_context_takeover,
+            server_no_context_takeover
+        );
+
+        assert_echoes(&mut ws).await;
+    }
+}
+
+#[tokio::test]
+async fn deflate_interop_server_reduces_client_window() {
+    // ratchet only ever echoes the window the client asked for, so it is
+    // handed the smaller one as the offer, while the client offered the
+    // default `client_max_window_bits` without a value.
+    let server = deflate_echo_server(
+        DeflateConfig {
+            client_max_window_bits: WindowBits::nine(),
+            ..DeflateConfig::default()
+        },
+        Some("permessage-deflate; client_max_window_bits=9"),
+    );
+
+    let mut ws = connect(&server, WsCompression::default()).await;
+
+    let negotiated = ws.compression().expect("compression negotiated");
+    assert_eq!(negotiated.client_max_window_bits, Some(9));
+
+    assert_echoes(&mut ws).await;
+}
+
+#[tokio::test]
+async fn keepalive_pings_only_when_idle() {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let server = ws_server(move |mut ws| {
+        let tx = tx.clone();
+        async move {
+            while let Some(Ok(message)) = ws.next().await {
+                let _ = tx.send(message);
+            }
+        }
+    });
+
+    let mut ws = websocket(&server)
+        .ping_interval(Duration::from_millis(200))
+        .send()
+        .await
+        .unwrap()
+        .into_websocket()
+        .await
+        .unwrap();
+
+    // A message every 20ms leaves no interval idle.
+    let mut ticks = tokio::time::interval(Duration::from_millis(20));
+    for _ in 0..30 {
+        tokio::select! {
+            _ = ticks.tick() => ws.send(Message::Text("busy".into())).await.unwrap(),
+            message = ws.next() => panic!("unexpected message: {message:?}"),
+        }
+    }
+
+    // Reading while idle sends pings, and swallows their pongs.
+    let idle = tokio::time::timeout(Duration::from_millis(900), ws.next()).await;
+    assert!(idle.is_err(), "unexpected message: {idle:?}");
+    ws.send(Message::Text("done".into())).await.unwrap();
+
+    let mut received = Vec::new();
+    while let Some(message) = rx.recv().await {
+        if message == tungstenite::Message::Text("done".into()) {
+            break;
+        }
+        received.push(message);
+    }
+    let busy = received
+        .iter()
+        .take_while(|m| **m == tungstenite::Message::Text("busy".into()))
+        .count();
+    assert_eq!(busy, 30);
+    let pings = &received[busy..];
+    assert!(!pings.is_empty());
+    assert!(pings
+        .iter()
+        .all(|m| matches!(m, tungstenite::Message::Ping(data) if data.len() == 8)));
+}
+
+#[tokio::test]
+async fn keepalive_pong_timeout_closes_the_socket() {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let server = ws_server(move |mut ws| {
+        let tx = tx.clone();
+        async move {
+            // Nothing is read, so no pong is sent, until the client gave up.
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            while let Some(Ok(message)) = ws.next().await {
+                let _ = tx.send(message);
+            }
+        }
+    });
+
+    let mut ws = websocket(&server)
+        .ping_interval(Duration::from_millis(100))
+        .pong_timeout(Duration::from_millis(100))
+        .send()
+        .await
+        .unwrap()
+        .into_websocket()
+        .await
+        .unwrap();
+
+    let err = ws.next().await.unwrap().unwrap_err();
+    assert!(err.is_timeout());
+    assert_eq!(
+        err.source().and_then(|e| e.downcast_ref::<WsError>()),
+        Some(&WsError::PongTimeout)
+    );
+    assert!(ws.next().await.is_none());
+
+    assert!(matches!(
+        rx.recv().await,
+        Some(tungstenite::Message::Ping(_))
+    ));
+    assert!(matches!(
+        rx.recv().await,
+        Some(tungstenite::Message::Close(_))
+    ));
+}
+
+#[tokio::test]
+async fn keepalive_passes_other_pings_and_pongs_through() {
+    let server = ws_server(|mut ws| async move {
+        // Nothing is read, so the keepalive ping is in flight meanwhile.
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        ws.send(tungstenite::Message::Pong(b"unsolicited".to_vec()))
+            .await
+            .unwrap();
+        ws.send(tungstenite::Message::Ping(b"application".to_vec()))
+            .await
+            .unwrap();
+        ws.send(tungstenite::Message::Text("done".into()))
+            .await
+            .unwrap();
+        while let Some(Ok(_)) = ws.next().await {}
+    });
+
+    let mut ws = websocket(&server)
+        .ping_interval(Duration::from_millis(100))
+        .pong_timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .unwrap()
+        .into_websocket()
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        ws.next().await,
+        Some(Ok(Message::Pong(data))) if data == b"unsolicited"
+    ));
+    assert!(matches!(
+        ws.next().await,
+        Some(Ok(Message::Ping(data))) if data == b"application"
+    ));
+    assert!(matches!(
+        ws.next().await,
+        Some(Ok(Message::Text(text))) if text == "done"
+    ));
+}
+
+#[tokio::test]
+async fn split_halves_are_used_concurrently() {
+    let server = echo_server();
+    let (mut sink, mut stream) = open(websocket(&server)).await.split();
+
+    // The read is pending while the messages are sent.
+    let reader = tokio::spawn(async move {
+        let mut echoes = Vec::new();
+        while echoes.len() < 3 {
+            match stream.next().await {
+                Some(Ok(Message::Text(text))) => echoes.push(text),
+                other => panic!("unexpected message: {other:?}"),
+            }
+        }
+        echoes
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let send = async {
+        for i in 0..3 {
+            sink.send(Message::Text(format!("message {i}")))
+                .await
+                .unwrap();
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), send)
+        .await
+        .expect("sending waits for the pending read");
+
+    let echoes = tokio::time::timeout(Duration::from_secs(5), reader)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(echoes, ["message 0", "message 1", "message 2"]);
+}
+
+#[tokio::test]
+async fn reunite_rejects_halves_of_different_sockets() {
+    let server = echo_server();
+    let (sink_a, stream_a) = open(websocket(&server)).await.split();
+    let (sink_b, stream_b) = open(websocket(&server)).await.split();
+
+    let (sink_a, stream_b) = WebSocket::reunite(sink_a, stream_b).unwrap_err();
+    let (sink_b, stream_a) = WebSocket::reunite(sink_b, stream_a).unwrap_err();
+
+    let mut a = WebSocket::reunite(sink_a, stream_a).unwrap();
+    let mut b = WebSocket::reunite(sink_b, stream_b).unwrap();
+    for (ws, text) in [(&mut a, "a"), (&mut b, "b")] {
+        ws.send(Message::Text(text.into())).await.unwrap();
+        assert!(matches!(
+            ws.next().await,
+            Some(Ok(Message::Text(echo))) if echo == text
+        ));
+    }
+}
+
+#[tokio::test]
+async fn closing_the_sink_ends_the_stream_after_the_close_frame() {
+    let server = echo_server();
+    let (mut sink, mut stream) = open(websocket(&server)).await.split();
+
+    sink.send(Message::Close {
+        code: CloseCode::Normal,
+        reason: Some("bye".into()),
+    })
+    .await
+    .unwrap();
+    sink.close().await.unwrap();
+
+    let next = tokio::time::timeout(Duration::from_secs(5), stream.next());
+    match next.await.unwrap() {
+        Some(Ok(Message::Close { code, reason })) => {
+            assert_eq!(code, CloseCode::Normal);
+            assert_eq!(reason.as_deref(), Some("bye"));
+        }
+        other => panic!("unexpected message: {other:?}"),
+    }
+    let next = tokio::time::timeout(Duration::from_secs(5), stream.next());
+    assert!(next.await.unwrap().is_none());
+}
+
+/// Sends what the server first receives to `tx`, `None` if the
+/// connection ended or failed before a message.
+fn first_message_server(tx: mpsc::UnboundedSender<Option<tungstenite::Message>>) -> server::Server {
+    ws_server(move |mut ws| {
+        let tx = tx.clone();
+        async move {
+            let _ = tx.send(ws.next().await.and_then(Result::ok));
+        }
+    })
+}
+
+#[tokio::test]
+async fn close_returns_the_server_close_frame() {
+    let server = ws_server(|mut ws| async move {
+        // Discarded by the client, which is closing.
+        ws.send(tungstenite::Message::Text("unread".into()))
+            .await
+            .unwrap();
+        while let Some(Ok(_)) = ws.next().await {}
+    });
+
+    let ws = open(websocket(&server)).await;
+    let frame = ws.close(CloseCode::Normal, "bye").await.unwrap();
+    assert_eq!(
+        frame,
+        Some(CloseFrame {
+            code: CloseCode::Normal,
+            reason: "bye".into(),
+        })
+    );
+}
+
+#[tokio::test]
+async fn close_waits_for_the_server_close_frame_up_to_close_timeout() {
+    let server = ws_server(|ws| async move {
+        // Nothing is read, so the close frame is not answered.
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        drop(ws);
+    });
+
+    let ws = open(websocket(&server).close_timeout(Duration::from_millis(200))).await;
+    let start = Instant::now();
+    let frame = ws.close(CloseCode::Normal, "bye").await.unwrap();
+    assert_eq!(frame, None);
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn drop_sends_going_away() {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let server = first_message_server(tx);
+
+    drop(open(websocket(&server)).await);
+
+    match rx.recv().await.unwrap() {
+        Some(tungstenite::Message::Close(Some(frame))) => {
+            assert_eq!(u16::from(frame.code), 1001);
+        }
+        other => panic!("unexpected message: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn drop_without_close_on_drop_sends_nothing() {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let server = first_message_server(tx);
+
+    drop(open(websocket(&server).close_on_drop(false)).await);
+
+    assert_eq!(rx.recv().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn websocket_exposes_the_upgrade_response() {
+    let server = server::http(|req| {
+        let mut res = upgrade(req, None, |io| async move { drop(io) });
+        res.headers_mut()
+            .insert("x-session", http::HeaderValue::from_static("abc"));
+        async { res }
+    });
+
+    let ws = open(websocket(&server)).await;
+    let res = ws.response();
+    assert_eq!(res.status(), rquest::StatusCode::SWITCHING_PROTOCOLS);
+    assert_eq!(res.headers()["x-session"], "abc");
+}
+
+#[tokio::test]
+async fn rejected_upgrade_keeps_the_response() {
+    let server = server::http(|_req| async {
+        http::Response::builder()
+            .status(http::StatusCode::FORBIDDEN)
+            .header("x-reason", "banned")
+            .body("not allowed".into())
+            .unwrap()
+    });
+
+    let err = websocket(&server)
+        .send()
+        .await
+        .unwrap()
+        .into_websocket()
+        .await
+        .unwrap_err();
+    let res = err.into_response().expect("response kept in the error");
+    assert_eq!(res.status(), rquest::StatusCode::FORBIDDEN);
+    assert_eq!(res.headers()["x-reason"], "banned");
+    assert_eq!(res.text().await.unwrap(), "not allowed");
+}
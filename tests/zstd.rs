@@ -0,0 +1,170 @@
+mod support;
+use support::server;
+
+#[tokio::test]
+async fn zstd_response() {
+    zstd_case(10_000, 4096).await;
+}
+
+#[tokio::test]
+async fn zstd_single_byte_chunks() {
+    zstd_case(10, 1).await;
+}
+
+#[tokio::test]
+async fn test_zstd_empty_body() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.method(), "HEAD");
+
+        http::Response::builder()
+            .header("content-encoding", "zstd")
+            .header("content-length", 100)
+            .body(Default::default())
+            .unwrap()
+    });
+
+    let client = rquest::Client::new();
+    let res = client
+        .head(&format!("http://{}/zstd", server.addr()))
+        .send()
+        .await
+        .unwrap();
+
+    let body = res.text().await.unwrap();
+
+    assert_eq!(body, "");
+}
+
+#[tokio::test]
+async fn accept_encoding_lists_codings_by_preference() {
+    let content = "preferred coding";
+    let compressed = zstd_crate::encode_all(content.as_bytes(), 3).unwrap();
+
+    let server = server::http(move |req| {
+        let accept_encoding = req.headers()["accept-encoding"].to_str().unwrap();
+        assert!(accept_encoding.starts_with("zstd"), "{}", accept_encoding);
+        if cfg!(feature = "gzip") {
+            assert!(
+                accept_encoding.contains("gzip;q=0.8"),
+                "{}",
+                accept_encoding
+            );
+        }
+        assert!(!accept_encoding.contains("deflate"), "{}", accept_encoding);
+
+        let compressed = compressed.clone();
+        async move {
+            http::Response::builder()
+                .header("content-encoding", "zstd")
+                .body(compressed.into())
+                .unwrap()
+        }
+    });
+
+    let client = rquest::Client::builder()
+        .accept_encoding(rquest::AcceptEncoding::new().deflate(false))
+        .build()
+        .unwrap();
+    let res = client
+        .get(&format!("http://{}/zstd", server.addr()))
+        .send()
+        .await
+        .unwrap();
+
+    assert!(res.headers().get("content-encoding").is_none());
+    assert_eq!(res.text().await.unwrap(), content);
+}
+
+#[tokio::test]
+async fn accept_encoding_none_leaves_body_compressed() {
+    let compressed = zstd_crate::encode_all(&b"left alone"[..], 3).unwrap();
+    let body = compressed.clone();
+
+    let server = server::http(move |req| {
+        assert!(req.headers().get("accept-encoding").is_none());
+
+        let compressed = compressed.clone();
+        async move {
+            http::Response::builder()
+                .header("content-encoding", "zstd")
+                .body(compressed.into())
+                .unwrap()
+        }
+    });
+
+    let client = rquest::Client::builder()
+        .accept_encoding(rquest::AcceptEncoding::none())
+        .build()
+        .unwrap();
+    let res = client
+        .get(&format!("http://{}/zstd", server.addr()))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.headers()["content-encoding"], "zstd");
+    assert_eq!(res.bytes().await.unwrap(), body);
+}
+
+#[tokio::test]
+async fn compress_body_zstd() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["content-encoding"], "zstd");
+        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+        http::Response::new(zstd_crate::decode_all(&body[..]).unwrap().into())
+    });
+
+    let content = "telemetry ".repeat(1000);
+    let res = rquest::Client::new()
+        .post(format!("http://{}/upload", server.addr()))
+        .body(content.clone())
+        .compress_body(rquest::Compression::Zstd, 3)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), content);
+}
+
+async fn zstd_case(response_size: usize, chunk_size: usize) {
+    use futures_util::stream::StreamExt;
+
+    let content: String = (0..response_size).map(|i| format!("test {}", i)).collect();
+    let zstded_content = zstd_crate::encode_all(content.as_bytes(), 3).unwrap();
+
+    let server = server::http(move |req| {
+        assert!(req.headers()["accept-encoding"]
+            .to_str()
+            .unwrap()
+            .contains("zstd"));
+
+        let zstded = zstded_content.clone();
+        async move {
+            let len = zstded.len();
+            let stream =
+                futures_util::stream::unfold((zstded, 0), move |(zstded, pos)| async move {
+                    let chunk = zstded.chunks(chunk_size).nth(pos)?.to_vec();
+
+                    Some((chunk, (zstded, pos + 1)))
+                });
+
+            let body = hyper::Body::wrap_stream(stream.map(Ok::<_, std::convert::Infallible>));
+
+            http::Response::builder()
+                .header("content-encoding", "zstd")
+                .header("content-length", len)
+                .body(body)
+                .unwrap()
+        }
+    });
+
+    let client = rquest::Client::new();
+
+    let res = client
+        .get(&format!("http://{}/zstd", server.addr()))
+        .send()
+        .await
+        .expect("response");
+
+    let body = res.text().await.expect("text");
+    assert_eq!(body, content);
+}